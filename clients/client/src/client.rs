@@ -1,7 +1,23 @@
 use crate::Embedded;
+use crate::gamepad::Gamepads;
 use crate::prelude::*;
-use rusterix::{EntityAction, Rusterix, Value};
-use shared::{project::Project, rusterix_utils::*};
+use rusterix::Rusterix;
+use shared::{
+    audio::AudioOutput,
+    combat::CombatRunner,
+    dialogue::DialogueRunner,
+    generator::GeneratorRunner,
+    input::*,
+    inventory::InventoryUi,
+    navgrid::Navigation,
+    project::Project,
+    prop::add_props_to_map,
+    quest::QuestTracker,
+    replay::*,
+    rusterix_utils::*,
+    screen::{ScreenLayout, ScreenScaling, ScreenViewport},
+    shop::ShopRunner,
+};
 use std::path::PathBuf;
 use std::str::FromStr;
 use std::sync::mpsc::Receiver;
@@ -15,6 +31,46 @@ pub struct Client {
 
     rusterix: Rusterix,
     cmd_line_path: Option<PathBuf>,
+
+    /// The fixed step clock of a deterministic run (--seed / --replay).
+    simulation_clock: Option<SimulationClock>,
+    /// The replay being recorded (--record) and its output path.
+    replay_recording: Option<(PathBuf, Replay)>,
+    /// The number of recorded inputs in the written replay file.
+    replay_written: usize,
+    /// The replay being played back (--replay).
+    replay_player: Option<ReplayPlayer>,
+    /// Moves the characters walking along the navigation grids.
+    navigation: Navigation,
+    /// Plays the sounds and music of the game.
+    audio: AudioOutput,
+    /// Shows the dialogues started by scripts.
+    dialogues: DialogueRunner,
+    /// Tracks the quests of the player.
+    quests: QuestTracker,
+    /// Shows the trade screens opened by vendors.
+    shops: ShopRunner,
+    /// Resolves the attacks and tracks the status effects and turns.
+    combat: CombatRunner,
+    /// Draws the inventory widgets of the screen and moves the items.
+    inventory: InventoryUi,
+    /// Collects the regions the scripts generate again.
+    generator: GeneratorRunner,
+
+    /// How the screen is scaled and letterboxed in the window.
+    viewport: ScreenViewport,
+    /// The placement of the screen in the window of the last frame.
+    layout: Option<ScreenLayout>,
+    /// The game and its overlays at the size of the screen.
+    screen_buffer: TheRGBABuffer,
+
+    /// The input actions of the game and the bindings of the player.
+    input_map: InputMap,
+    input_state: InputState,
+    bindings_menu: BindingsMenu,
+    /// Where the bindings changed by the player are stored.
+    bindings_path: Option<PathBuf>,
+    gamepads: Gamepads,
 }
 
 impl TheTrait for Client {
@@ -35,6 +91,29 @@ impl TheTrait for Client {
 
             rusterix,
             cmd_line_path: None,
+
+            simulation_clock: None,
+            replay_recording: None,
+            replay_written: 0,
+            replay_player: None,
+            navigation: Navigation::default(),
+            audio: AudioOutput::new(),
+            dialogues: DialogueRunner::default(),
+            quests: QuestTracker::default(),
+            shops: ShopRunner::default(),
+            combat: CombatRunner::default(),
+            inventory: InventoryUi::default(),
+            generator: GeneratorRunner::default(),
+
+            viewport: ScreenViewport::default(),
+            layout: None,
+            screen_buffer: TheRGBABuffer::default(),
+
+            input_map: InputMap::default(),
+            input_state: InputState::new(),
+            bindings_menu: BindingsMenu::new(),
+            bindings_path: None,
+            gamepads: Gamepads::new(),
         }
     }
 
@@ -48,17 +127,116 @@ impl TheTrait for Client {
             }
         }
 
+        // Optional deterministic run / replay arguments
+        let mut seed: Option<u64> = None;
+        let mut record_path: Option<PathBuf> = None;
+        let mut replay: Option<Replay> = None;
+        let mut locale: Option<String> = None;
+
+        let mut i = 2;
+        while i < args.len() {
+            match args[i].as_str() {
+                "--seed" => {
+                    seed = args.get(i + 1).and_then(|s| s.parse::<u64>().ok());
+                    i += 1;
+                }
+                "--record" => {
+                    record_path = args.get(i + 1).map(PathBuf::from);
+                    i += 1;
+                }
+                "--replay" => {
+                    replay = args
+                        .get(i + 1)
+                        .and_then(|p| std::fs::read_to_string(p).ok())
+                        .and_then(|contents| Replay::from_json(&contents));
+                    if replay.is_none() {
+                        eprintln!("Unable to load replay.");
+                    }
+                    i += 1;
+                }
+                "--locale" => {
+                    locale = args.get(i + 1).cloned();
+                    i += 1;
+                }
+                _ => {}
+            }
+            i += 1;
+        }
+
         // Load the game data path
         if let Some(path) = self.get_data_path() {
+            // The input actions with the bindings the player changed
+            self.bindings_path = Some(path.with_extension("bindings.toml"));
             let mut project = self.load_project(path);
+            self.input_map = InputMap::from_config(&project.config);
+            if let Some(overrides) = self
+                .bindings_path
+                .as_ref()
+                .and_then(|p| std::fs::read_to_string(p).ok())
+            {
+                self.input_map.apply_overrides(&overrides);
+            }
             self.rusterix.set_tiles(project.tiles.clone(), false);
 
+            // Run the game in the requested language
+            if let Some(locale) = locale {
+                if project.localization.locales.contains(&locale) {
+                    project.localization.active = locale;
+                } else {
+                    eprintln!("Unknown locale \"{}\".", locale);
+                }
+            }
+
+            // A replay carries its own settings, otherwise a seed or a recording
+            // request starts a deterministic run.
+            let deterministic = if let Some(replay) = &replay {
+                Some(replay.settings)
+            } else if seed.is_some() || record_path.is_some() {
+                Some(DeterministicSettings::new(
+                    seed.unwrap_or(0),
+                    project.target_fps,
+                    project.tick_ms,
+                ))
+            } else {
+                None
+            };
+
             // Init server / client
 
-            start_server(&mut self.rusterix, &mut project, false);
+            self.audio.load(&project);
+            self.quests.start();
+            self.shops.start(&project.config);
+            self.combat.start(&project.config, deterministic);
+            self.inventory.start(&project);
+            self.generator.start(deterministic);
+            self.viewport = ScreenViewport::from_config(&project.config);
+            start_server(
+                &mut self.rusterix,
+                &mut project,
+                false,
+                false,
+                deterministic,
+            );
             let commands = setup_client(&mut self.rusterix, &mut project);
             self.rusterix.server.process_client_commands(commands);
+            // The project is not saved by the client, the props are drawn with the maps
+            for region in &mut project.regions {
+                add_props_to_map(&mut region.map, &region.props, &project.assets, false);
+            }
             self.rusterix.client.server_time = project.time;
+            self.navigation.start(
+                deterministic.map_or(self.rusterix.client.game_tick_ms as u32, |d| d.tick_ms),
+            );
+
+            if let Some(settings) = deterministic {
+                self.simulation_clock = Some(SimulationClock::new(settings));
+                if let Some(record_path) = record_path {
+                    self.replay_recording =
+                        Some((record_path, Replay::new(project.name.clone(), settings)));
+                }
+                self.replay_player = replay.map(ReplayPlayer::new);
+            }
+
             self.project = project;
 
             println!("Project loaded successfully");
@@ -127,99 +305,195 @@ impl TheTrait for Client {
     }
 
     /// Handle UI events and UI state
-    fn update_ui(&mut self, ui: &mut TheUI, _ctx: &mut TheContext) -> bool {
+    fn update_ui(&mut self, ui: &mut TheUI, ctx: &mut TheContext) -> bool {
         let mut redraw = false;
 
-        let (redraw_update, tick_update) = self.update_tracker.update(
+        let (redraw_update, mut tick_update) = self.update_tracker.update(
             (1000 / self.rusterix.client.target_fps) as u64,
             self.rusterix.client.game_tick_ms as u64,
         );
 
+        // In deterministic runs the game ticks are derived from the simulated frames
+        let mut write_replay = false;
+        if let Some(clock) = &mut self.simulation_clock {
+            tick_update = false;
+            if redraw_update {
+                // Feed the replay inputs of this frame before advancing
+                if let Some(player) = &mut self.replay_player {
+                    let inputs = player.inputs_for_frame(clock.frame);
+                    if let Some(region) = self
+                        .project
+                        .regions
+                        .iter()
+                        .find(|r| r.map.name == self.rusterix.client.current_map)
+                    {
+                        for input in &inputs {
+                            apply_replay_input(&mut self.rusterix, input, &region.map);
+                        }
+                    }
+                    if player.is_finished() {
+                        self.replay_player = None;
+                    }
+                }
+                tick_update = clock.advance();
+
+                // The recording is written once per simulated second
+                write_replay = clock.frame % clock.settings.frames_per_second() == 0;
+            }
+        }
+        if write_replay {
+            self.write_replay();
+        }
+
         if tick_update {
             self.rusterix.client.inc_animation_frame();
             self.rusterix.server.system_tick();
+            self.combat.tick();
+            self.navigation.tick();
         }
 
         if redraw_update {
             redraw = true;
 
             self.rusterix.server.redraw_tick();
+            self.layout_screen(&ui.canvas.buffer);
 
+            let dt = 1.0 / self.rusterix.client.target_fps.max(1) as f32;
+            self.audio.advance(dt);
+            let mut generate_requests = vec![];
             for r in &mut self.project.regions {
                 self.rusterix.server.apply_entities_items(&mut r.map);
+                self.navigation.update(
+                    &mut self.rusterix,
+                    r,
+                    self.project.generated_maps.get(&r.id),
+                    &self.project.tiles,
+                );
+                self.dialogues
+                    .update(&mut self.rusterix, r, &self.project.dialogues);
+                self.quests
+                    .update(&mut self.rusterix, r, &self.project.quests);
+                self.shops.update(r);
+                self.combat.update(&mut self.rusterix, r);
+                self.inventory.update(r);
+                generate_requests.extend(self.generator.requests(r));
+                self.audio.update(
+                    &r.map,
+                    r.map.name == self.rusterix.client.current_map,
+                    &self.rusterix.player_camera,
+                );
+            }
 
-                if r.map.name == self.rusterix.client.current_map {
-                    if let Some(new_region_name) = self.rusterix.update_server() {
-                        self.rusterix.client.current_map = new_region_name;
-                    }
-                    if let Some(time) = self.rusterix.server.get_time(&r.map.id) {
-                        self.rusterix.client.server_time = time;
-                    }
+            // Regions generated again by scripts replace their instances
+            let deterministic = self.simulation_clock.as_ref().map(|clock| clock.settings);
+            for (name, seed) in generate_requests {
+                regenerate_region(
+                    &mut self.rusterix,
+                    &mut self.project,
+                    &name,
+                    seed,
+                    deterministic,
+                );
+            }
 
-                    rusterix::tile_builder(&mut r.map, &mut self.rusterix.assets);
-                    let messages = self.rusterix.server.get_messages(&r.map.id);
-                    let choices = self.rusterix.server.get_choices(&r.map.id);
-                    self.rusterix.draw_game(&r.map, messages, choices);
-                    self.rusterix
-                        .client
-                        .insert_game_buffer(&mut ui.canvas.buffer);
-                    break;
+            // Only the region of the player is drawn
+            if let Some(new_region_name) = self.rusterix.update_server() {
+                self.rusterix.client.current_map = new_region_name;
+            }
+            if let Some(r) = self
+                .project
+                .regions
+                .iter_mut()
+                .find(|r| r.map.name == self.rusterix.client.current_map)
+            {
+                if let Some(time) = self.rusterix.server.get_time(&r.map.id) {
+                    self.rusterix.client.server_time = time;
                 }
+
+                rusterix::tile_builder(&mut r.map, &mut self.rusterix.assets);
+                let messages = self.rusterix.server.get_messages(&r.map.id);
+                let choices = self.rusterix.server.get_choices(&r.map.id);
+                self.rusterix.draw_game(&r.map, messages, choices);
+                self.rusterix
+                    .client
+                    .insert_game_buffer(&mut self.screen_buffer);
+                self.dialogues
+                    .draw(&self.project.dialogues, &mut self.screen_buffer, ctx);
+                self.inventory
+                    .draw(&mut self.screen_buffer, ctx, &self.project.tiles);
+                self.shops.draw(&mut self.screen_buffer, ctx);
+            }
+            self.present_screen(&mut ui.canvas.buffer, ctx);
+
+            if self.bindings_menu.open {
+                self.draw_bindings_menu(&mut ui.canvas.buffer, ctx);
             }
         }
 
-        if let Some(receiver) = &mut self.event_receiver {
-            while let Ok(event) = receiver.try_recv() {
-                //println!("Event received {:?}", event);
-                match event {
-                    TheEvent::Resize => {}
-                    TheEvent::MouseDown(coord) => {
-                        for r in &mut self.project.regions {
-                            self.rusterix.server.apply_entities_items(&mut r.map);
-
-                            if r.map.name == self.rusterix.client.current_map {
-                                if let Some(action) = self.rusterix.client.touch_down(coord, &r.map)
-                                {
-                                    self.rusterix.server.local_player_action(action);
-                                }
-                            }
-                        }
+        for (source, pressed) in self.gamepads.poll(self.input_map.stick_threshold) {
+            self.source_input(source, pressed);
+            redraw = true;
+        }
+
+        // Drain the receiver first, the handlers need mutable access to self
+        let events: Vec<TheEvent> = match &self.event_receiver {
+            Some(receiver) => receiver.try_iter().collect(),
+            None => vec![],
+        };
+        for event in events {
+            //println!("Event received {:?}", event);
+            match event {
+                TheEvent::Resize => {}
+                TheEvent::MouseDown(coord) => {
+                    let Some(coord) = self.screen_coord(coord) else {
+                        continue;
+                    };
+                    let dim = *self.screen_buffer.dim();
+                    let (width, height) = (dim.width as usize, dim.height as usize);
+                    if !self.dialogues.click(coord, width, height)
+                        && !self.shops.click(coord, width, height)
+                        && !self.inventory.press_at(coord)
+                    {
+                        self.player_input(ReplayInput::TouchDown(coord));
                     }
-                    TheEvent::MouseUp(coord) => {
-                        for r in &mut self.project.regions {
-                            self.rusterix.server.apply_entities_items(&mut r.map);
-
-                            if r.map.name == self.rusterix.client.current_map {
-                                self.rusterix.client.touch_up(coord, &r.map);
-                                self.rusterix.server.local_player_action(EntityAction::Off);
-                            }
-                        }
+                }
+                TheEvent::MouseUp(coord) => {
+                    let Some(coord) = self.screen_coord(coord) else {
+                        continue;
+                    };
+                    if !self.inventory.release_at(coord) {
+                        self.player_input(ReplayInput::TouchUp(coord));
                     }
-                    TheEvent::KeyDown(v) => {
-                        if let Some(char) = v.to_char() {
-                            let action = self
-                                .rusterix
-                                .client
-                                .user_event("key_down".into(), Value::Str(char.to_string()));
-
-                            self.rusterix.server.local_player_action(action);
-                        }
+                }
+                TheEvent::KeyDown(v) => {
+                    if let Some(char) = v.to_char() {
+                        self.source_input(InputSource::from_char(char), true);
                     }
-                    TheEvent::KeyUp(v) => {
-                        if let Some(char) = v.to_char() {
-                            let action = self
-                                .rusterix
-                                .client
-                                .user_event("key_up".into(), Value::Str(char.to_string()));
-
-                            self.rusterix.server.local_player_action(action);
-                        }
+                }
+                TheEvent::KeyUp(v) => {
+                    if let Some(char) = v.to_char() {
+                        self.source_input(InputSource::from_char(char), false);
+                    }
+                }
+                TheEvent::KeyCodeDown(TheValue::KeyCode(code)) => {
+                    if let Some(source) = InputSource::from_key_code(&code) {
+                        self.source_input(source, true);
+                    }
+                }
+                TheEvent::KeyCodeUp(TheValue::KeyCode(code)) => {
+                    if let Some(source) = InputSource::from_key_code(&code) {
+                        self.source_input(source, false);
                     }
-                    _ => {}
                 }
+                _ => {}
             }
         }
 
+        // The answers, trades and item moves of the widgets are player input
+        for input in self.widget_inputs() {
+            self.player_input(input);
+        }
+
         redraw
     }
 
@@ -229,12 +503,272 @@ impl TheTrait for Client {
     }
 }
 
+impl Client {
+    /// Routes a pressed or released key or button to the rebinding screen or
+    /// sends the resulting action events to the server.
+    fn source_input(&mut self, source: InputSource, pressed: bool) {
+        if self.bindings_menu.open {
+            if pressed && self.bindings_menu.press(&mut self.input_map, &source) {
+                self.save_bindings();
+            }
+            return;
+        }
+
+        // A shown dialogue takes the keys to continue and pick choices
+        if pressed
+            && self
+                .dialogues
+                .press(&self.project.dialogues, &self.input_map, &source)
+        {
+            return;
+        }
+
+        // The trade screen takes the keys to pick and trade items
+        if pressed && self.shops.press(&self.input_map, &source) {
+            return;
+        }
+
+        // The hotbar actions use the items of the hotbar
+        if pressed && self.inventory.press(&self.input_map, &source) {
+            return;
+        }
+
+        let inputs = if !pressed {
+            self.input_state.release(&self.input_map, source)
+        } else if InputState::opens_bindings_menu(&self.input_map, &source) {
+            self.bindings_menu.open();
+            self.input_state.release_all()
+        } else {
+            self.input_state.press(&self.input_map, source)
+        };
+        for input in inputs {
+            self.player_input(input);
+        }
+    }
+
+    /// The user events of the dialogue, trade and inventory widgets.
+    fn widget_inputs(&mut self) -> Vec<ReplayInput> {
+        let mut inputs = self.dialogues.take_inputs();
+        inputs.extend(self.shops.take_inputs());
+        inputs.extend(self.inventory.take_inputs());
+        inputs
+    }
+
+    /// Writes the recorded replay if inputs were recorded since the last write.
+    fn write_replay(&mut self) {
+        let Some((path, recording)) = &self.replay_recording else {
+            return;
+        };
+        if recording.events.len() == self.replay_written {
+            return;
+        }
+        if std::fs::write(path, recording.to_json()).is_err() {
+            eprintln!("Unable to write replay.");
+        }
+        self.replay_written = recording.events.len();
+    }
+
+    /// Writes the bindings changed by the player.
+    fn save_bindings(&self) {
+        #[cfg(not(target_arch = "wasm32"))]
+        if let Some(path) = &self.bindings_path {
+            if std::fs::write(path, self.input_map.overrides()).is_err() {
+                eprintln!("Unable to save the input bindings.");
+            }
+        }
+    }
+
+    /// Places the screen in the window, resizing the screen buffer. With the
+    /// expand scaling the screen grows to the aspect ratio of the window and
+    /// the widgets are placed again by their anchors.
+    fn layout_screen(&mut self, window: &TheRGBABuffer) {
+        let dim = *window.dim();
+        let layout = self
+            .viewport
+            .layout((dim.width as usize, dim.height as usize));
+        let (width, height) = layout.screen_size();
+        let resized = self.screen_buffer.dim().width as usize != width
+            || self.screen_buffer.dim().height as usize != height;
+        if resized {
+            self.screen_buffer = TheRGBABuffer::new(TheDim::sized(width as i32, height as i32));
+            if self.viewport.scaling == ScreenScaling::Expand {
+                self.rusterix.client.viewport = Vec2::new(width as _, height as _);
+                insert_screens(&mut self.rusterix, &self.project, layout.size);
+            }
+        }
+        self.layout = Some(layout);
+    }
+
+    /// Scales the screen buffer into the window and fills the letterbox bars.
+    fn present_screen(&self, window: &mut TheRGBABuffer, ctx: &mut TheContext) {
+        let Some(layout) = &self.layout else {
+            return;
+        };
+        let dim = *window.dim();
+        let stride = window.stride();
+        let letterbox = TheColor::from_hex(&self.viewport.letterbox).to_u8_array();
+        ctx.draw.rect(
+            window.pixels_mut(),
+            &(0, 0, dim.width as usize, dim.height as usize),
+            stride,
+            &letterbox,
+        );
+        let (x, y, w, h) = layout.screen_rect();
+        let screen = self.screen_buffer.dim();
+        ctx.draw.blend_scale_chunk(
+            window.pixels_mut(),
+            &(
+                x.max(0.0) as usize,
+                y.max(0.0) as usize,
+                w as usize,
+                h as usize,
+            ),
+            stride,
+            self.screen_buffer.pixels(),
+            &(screen.width as usize, screen.height as usize),
+        );
+    }
+
+    /// Maps a window coordinate to the screen, None on the letterbox bars.
+    fn screen_coord(&self, coord: Vec2<i32>) -> Option<Vec2<i32>> {
+        match &self.layout {
+            Some(layout) => layout.to_screen(coord),
+            None => Some(coord),
+        }
+    }
+
+    /// Draws the rebinding screen on top of the game.
+    fn draw_bindings_menu(&self, buffer: &mut TheRGBABuffer, ctx: &mut TheContext) {
+        const ROW_HEIGHT: usize = 22;
+
+        let rows = self.bindings_menu.rows(&self.input_map);
+        let width = buffer.dim().width as usize;
+        let height = buffer.dim().height as usize;
+        let stride = buffer.stride();
+
+        let menu_width = if width < 300 { width } else { width * 2 / 3 };
+        let menu_height = ((rows.len() + 3) * ROW_HEIGHT + 20).min(height);
+        let x = (width - menu_width) / 2;
+        let y = (height - menu_height) / 2;
+
+        let bg_color = [20, 20, 24, 255];
+        let selected_color = [60, 70, 100, 255];
+        let text_color = [220, 220, 220, 255];
+        let dim_color = [150, 150, 150, 255];
+        let font = TheFontSettings {
+            size: 14.0,
+            ..Default::default()
+        };
+
+        ctx.draw.rect(
+            buffer.pixels_mut(),
+            &(x, y, menu_width, menu_height),
+            stride,
+            &bg_color,
+        );
+
+        let mut row_y = y + 10;
+        ctx.draw.text_rect(
+            buffer.pixels_mut(),
+            &(x + 10, row_y, menu_width - 20, ROW_HEIGHT),
+            stride,
+            "Controls",
+            font.clone(),
+            &text_color,
+            &bg_color,
+        );
+        row_y += ROW_HEIGHT + ROW_HEIGHT / 2;
+
+        let column = menu_width * 2 / 5;
+        for (index, (name, bindings)) in rows.iter().enumerate() {
+            if row_y + ROW_HEIGHT > y + menu_height {
+                break;
+            }
+            let row_color = if index == self.bindings_menu.selected {
+                ctx.draw.rect(
+                    buffer.pixels_mut(),
+                    &(x + 4, row_y, menu_width - 8, ROW_HEIGHT),
+                    stride,
+                    &selected_color,
+                );
+                selected_color
+            } else {
+                bg_color
+            };
+            let bindings = if index == self.bindings_menu.selected && self.bindings_menu.capturing {
+                "...".to_string()
+            } else {
+                bindings.clone()
+            };
+            ctx.draw.text_rect(
+                buffer.pixels_mut(),
+                &(x + 10, row_y, column - 10, ROW_HEIGHT),
+                stride,
+                name,
+                font.clone(),
+                &text_color,
+                &row_color,
+            );
+            ctx.draw.text_rect(
+                buffer.pixels_mut(),
+                &(x + column, row_y, menu_width - column - 10, ROW_HEIGHT),
+                stride,
+                &bindings,
+                font.clone(),
+                &text_color,
+                &row_color,
+            );
+            row_y += ROW_HEIGHT;
+        }
+
+        ctx.draw.text_rect(
+            buffer.pixels_mut(),
+            &(
+                x + 10,
+                (y + menu_height).saturating_sub(ROW_HEIGHT + 6),
+                menu_width - 20,
+                ROW_HEIGHT,
+            ),
+            stride,
+            self.bindings_menu.help(),
+            TheFontSettings {
+                size: 12.0,
+                ..Default::default()
+            },
+            &dim_color,
+            &bg_color,
+        );
+    }
+}
+
 pub trait ClientTrait {
     fn get_data_path(&self) -> Option<PathBuf>;
     fn load_project(&mut self, path: PathBuf) -> Project;
+    fn player_input(&mut self, input: ReplayInput);
 }
 
 impl ClientTrait for Client {
+    /// Sends a player input to the server and records it (if recording). Live
+    /// input is ignored while a replay is played back.
+    fn player_input(&mut self, input: ReplayInput) {
+        if self.replay_player.is_some() {
+            return;
+        }
+
+        for r in &mut self.project.regions {
+            self.rusterix.server.apply_entities_items(&mut r.map);
+
+            if r.map.name == self.rusterix.client.current_map {
+                apply_replay_input(&mut self.rusterix, &input, &r.map);
+            }
+        }
+
+        if let Some((_, recording)) = &mut self.replay_recording {
+            let frame = self.simulation_clock.as_ref().map(|c| c.frame).unwrap_or(0);
+            recording.record(frame, input);
+        }
+    }
+
     /// Returns the path to the game data
     fn get_data_path(&self) -> Option<PathBuf> {
         // On WASM just return an empty path.
@@ -289,3 +823,10 @@ impl ClientTrait for Client {
         }
     }
 }
+
+impl Drop for Client {
+    /// Writes the inputs recorded since the last write of the replay.
+    fn drop(&mut self) {
+        self.write_replay();
+    }
+}
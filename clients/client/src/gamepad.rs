@@ -0,0 +1,105 @@
+use shared::input::InputSource;
+
+/// Polls the connected gamepads and turns their buttons and stick directions
+/// into input sources. Without the `gamepad` feature no events are reported.
+pub struct Gamepads {
+    #[cfg(all(feature = "gamepad", not(target_arch = "wasm32")))]
+    gilrs: Option<gilrs::Gilrs>,
+
+    /// The stick directions which are currently pressed.
+    #[allow(dead_code)]
+    sticks: Vec<&'static str>,
+}
+
+impl Default for Gamepads {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Gamepads {
+    pub fn new() -> Self {
+        Self {
+            #[cfg(all(feature = "gamepad", not(target_arch = "wasm32")))]
+            gilrs: match gilrs::Gilrs::new() {
+                Ok(gilrs) => Some(gilrs),
+                Err(err) => {
+                    eprintln!("Gamepads unavailable: {}", err);
+                    None
+                }
+            },
+            sticks: vec![],
+        }
+    }
+
+    /// Returns the sources pressed (true) or released (false) since the last poll.
+    #[cfg(all(feature = "gamepad", not(target_arch = "wasm32")))]
+    pub fn poll(&mut self, stick_threshold: f32) -> Vec<(InputSource, bool)> {
+        use gilrs::{Axis, Button, EventType};
+
+        let mut events = vec![];
+        let Some(gilrs) = &mut self.gilrs else {
+            return events;
+        };
+
+        while let Some(event) = gilrs.next_event() {
+            match event.event {
+                EventType::ButtonPressed(button, _) | EventType::ButtonReleased(button, _) => {
+                    let name = match button {
+                        Button::South => "south",
+                        Button::East => "east",
+                        Button::West => "west",
+                        Button::North => "north",
+                        Button::DPadUp => "dpad_up",
+                        Button::DPadDown => "dpad_down",
+                        Button::DPadLeft => "dpad_left",
+                        Button::DPadRight => "dpad_right",
+                        Button::LeftTrigger => "left_shoulder",
+                        Button::RightTrigger => "right_shoulder",
+                        Button::LeftTrigger2 => "left_trigger",
+                        Button::RightTrigger2 => "right_trigger",
+                        Button::Select => "select",
+                        Button::Start => "start",
+                        Button::LeftThumb => "left_stick",
+                        Button::RightThumb => "right_stick",
+                        _ => continue,
+                    };
+                    let pressed = matches!(event.event, EventType::ButtonPressed(..));
+                    events.push((InputSource::Gamepad(name.into()), pressed));
+                }
+                EventType::AxisChanged(axis, value, _) => {
+                    // The negative and positive direction of the axis
+                    let (negative, positive) = match axis {
+                        Axis::LeftStickX => ("left_stick_left", "left_stick_right"),
+                        Axis::LeftStickY => ("left_stick_down", "left_stick_up"),
+                        Axis::RightStickX => ("right_stick_left", "right_stick_right"),
+                        Axis::RightStickY => ("right_stick_down", "right_stick_up"),
+                        _ => continue,
+                    };
+                    for (name, pressed) in [
+                        (negative, value < -stick_threshold),
+                        (positive, value > stick_threshold),
+                    ] {
+                        let held = self.sticks.contains(&name);
+                        if pressed && !held {
+                            self.sticks.push(name);
+                            events.push((InputSource::Gamepad(name.into()), true));
+                        } else if !pressed && held {
+                            self.sticks.retain(|s| *s != name);
+                            events.push((InputSource::Gamepad(name.into()), false));
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        events
+    }
+
+    /// Returns the sources pressed (true) or released (false) since the last poll.
+    #[cfg(not(all(feature = "gamepad", not(target_arch = "wasm32"))))]
+    pub fn poll(&mut self, _stick_threshold: f32) -> Vec<(InputSource, bool)> {
+        vec![]
+    }
+}
@@ -3,6 +3,7 @@
 use theframework::*;
 
 pub mod client;
+pub mod gamepad;
 pub mod misc;
 
 use rust_embed::RustEmbed;
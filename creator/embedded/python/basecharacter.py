@@ -7,14 +7,14 @@ class NewCharacter:
     def user_event(self, event, value):
         """User Event"""
 
-        if event == 'key_down':
-            if value == 'w':
+        if event == 'action_down':
+            if value == 'move_forward':
                 action("forward")
-            if value == 'a':
+            if value == 'turn_left':
                 action("left")
-            if value == 'd':
+            if value == 'turn_right':
                 action("right")
-            if value == 's':
+            if value == 'move_backward':
                 action("backward")
-        if event == 'key_up':
+        if event == 'action_up':
                 action("none")
@@ -0,0 +1,7 @@
+# Shared helpers for character, item and widget classes.
+# Import them in a class via: from new_library import *
+
+
+def clamp(value, low, high):
+    """Clamps the value to the given range"""
+    return max(low, min(value, high))
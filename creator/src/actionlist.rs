@@ -18,6 +18,7 @@ impl ActionList {
             Box::new(crate::tools::iso_camera::IsoCamera::new()),
             Box::new(crate::tools::orbit_camera::OrbitCamera::new()),
             Box::new(crate::tools::add_arch::AddArch::new()),
+            Box::new(crate::tools::array_selection::ArraySelection::new()),
             // Box::new(crate::tools::apply_shader::ApplyShader::new()),
             // Box::new(crate::tools::add_shader_library::AddShaderLibrary::new()),
             Box::new(crate::tools::apply_tile::ApplyTile::new()),
@@ -30,22 +31,35 @@ impl ActionList {
             Box::new(crate::tools::duplicate_tile::DuplicateTile::new()),
             Box::new(crate::tools::edit_maximize::EditMaximize::new()),
             Box::new(crate::tools::edit_linedef::EditLinedef::new()),
+            Box::new(crate::tools::edit_prop::EditProp::new()),
             Box::new(crate::tools::edit_sector::EditSector::new()),
             Box::new(crate::tools::edit_vertex::EditVertex::new()),
             Box::new(crate::tools::edit_tile_meta::EditTileMeta::new()),
             Box::new(crate::tools::extrude_linedef::ExtrudeLinedef::new()),
             Box::new(crate::tools::extrude_sector::ExtrudeSector::new()),
-            // Box::new(crate::tools::gen_stone_trim::GenerateStoneTrim::new()),
+            Box::new(crate::tools::gen_opening::GenerateOpening::new()),
+            Box::new(crate::tools::gen_pillar::GeneratePillar::new()),
+            Box::new(crate::tools::gen_ramp::GenerateRamp::new()),
+            Box::new(crate::tools::gen_region::GenerateRegion::new()),
+            Box::new(crate::tools::gen_stairs::GenerateStairs::new()),
+            Box::new(crate::tools::gen_stone_trim::GenerateStoneTrim::new()),
             // Box::new(crate::tools::load_shader::LoadShader::new()),
             // Box::new(crate::tools::new_shader::NewShader::new()),
             Box::new(crate::tools::new_tile::NewTile::new()),
             Box::new(crate::tools::minimize::Minimize::new()),
+            Box::new(crate::tools::place_prop::PlaceProp::new()),
             Box::new(crate::tools::recess::Recess::new()),
             Box::new(crate::tools::relief::Relief::new()),
+            Box::new(crate::tools::sector_intersect::SectorIntersect::new()),
+            Box::new(crate::tools::sector_subtract::SectorSubtract::new()),
+            Box::new(crate::tools::sector_union::SectorUnion::new()),
             Box::new(crate::tools::set_editing_surface::SetEditingSurface::new()),
+            Box::new(crate::tools::sound_emitter::SoundEmitter::new()),
             Box::new(crate::tools::split::Split::new()),
             Box::new(crate::tools::toggle_editing_geo::ToggleEditingGeo::new()),
+            Box::new(crate::tools::toggle_navgrid::ToggleNavGrid::new()),
             Box::new(crate::tools::toggle_rect_geo::ToggleRectGeo::new()),
+            Box::new(crate::tools::transform_selection::TransformSelection::new()),
         ];
         Self { actions }
     }
@@ -0,0 +1,195 @@
+use crate::actions::selection_transform::*;
+use crate::prelude::*;
+use vek::Vec2;
+
+pub struct ArraySelection {
+    id: TheId,
+    nodeui: TheNodeUI,
+}
+
+impl Action for ArraySelection {
+    fn new() -> Self
+    where
+        Self: Sized,
+    {
+        let mut nodeui: TheNodeUI = TheNodeUI::default();
+
+        nodeui.add_item(TheNodeUIItem::Selector(
+            "actionArrayMode".into(),
+            "Mode".into(),
+            "Place the copies along the offset or around the pivot.".into(),
+            vec!["Linear".to_string(), "Radial".to_string()],
+            0,
+        ));
+        nodeui.add_item(TheNodeUIItem::IntEditSlider(
+            "actionArrayCount".into(),
+            "Copies".into(),
+            "The number of copies.".into(),
+            3,
+            1..=64,
+            false,
+        ));
+        nodeui.add_item(TheNodeUIItem::FloatEditSlider(
+            "actionArrayOffsetX".into(),
+            "Offset X".into(),
+            "The horizontal offset between two copies (linear mode).".into(),
+            2.0,
+            -100.0..=100.0,
+            false,
+        ));
+        nodeui.add_item(TheNodeUIItem::FloatEditSlider(
+            "actionArrayOffsetY".into(),
+            "Offset Y".into(),
+            "The vertical offset between two copies (linear mode).".into(),
+            0.0,
+            -100.0..=100.0,
+            false,
+        ));
+        nodeui.add_item(TheNodeUIItem::FloatEditSlider(
+            "actionArrayAngle".into(),
+            "Angle".into(),
+            "The angle covered by the copies in degrees (radial mode). A full circle distributes the copies evenly.".into(),
+            360.0,
+            -360.0..=360.0,
+            false,
+        ));
+        add_pivot_items(&mut nodeui, "actionArray", 1);
+        nodeui.add_item(TheNodeUIItem::Checkbox(
+            "actionArraySnap".into(),
+            "Snap to Grid".into(),
+            "Snap the vertices of the copies to the grid subdivisions.".into(),
+            true,
+        ));
+
+        let item = TheNodeUIItem::Markdown(
+            "desc".into(),
+            "Duplicates the selected geometry several times, either along an offset or around the pivot. The copies keep the properties of the original linedefs and sectors.".into(),
+        );
+        nodeui.add_item(item);
+
+        Self {
+            id: TheId::named("Array Selection"),
+            nodeui,
+        }
+    }
+
+    fn id(&self) -> TheId {
+        self.id.clone()
+    }
+
+    fn info(&self) -> &'static str {
+        "Duplicate the selection along an offset or around a center."
+    }
+
+    fn role(&self) -> ActionRole {
+        ActionRole::Editor
+    }
+
+    fn accel(&self) -> Option<TheAccelerator> {
+        None
+    }
+
+    fn is_applicable(&self, map: &Map, _ctx: &mut TheContext, _server_ctx: &ServerContext) -> bool {
+        !map.selected_vertices.is_empty()
+            || !map.selected_linedefs.is_empty()
+            || !map.selected_sectors.is_empty()
+    }
+
+    fn apply(
+        &self,
+        map: &mut Map,
+        _ui: &mut TheUI,
+        _ctx: &mut TheContext,
+        _server_ctx: &mut ServerContext,
+    ) -> Option<RegionUndoAtom> {
+        let prev = map.clone();
+
+        let radial = self.nodeui.get_i32_value("actionArrayMode").unwrap_or(0) == 1;
+        let count = self
+            .nodeui
+            .get_i32_value("actionArrayCount")
+            .unwrap_or(3)
+            .max(1);
+        let offset = Vec2::new(
+            self.nodeui
+                .get_f32_value("actionArrayOffsetX")
+                .unwrap_or(2.0),
+            self.nodeui
+                .get_f32_value("actionArrayOffsetY")
+                .unwrap_or(0.0),
+        );
+        let angle = self
+            .nodeui
+            .get_f32_value("actionArrayAngle")
+            .unwrap_or(360.0);
+        let snap = self
+            .nodeui
+            .get_bool_value("actionArraySnap")
+            .unwrap_or(true);
+
+        let vertices = selection_vertices(map);
+        if vertices.is_empty() {
+            return None;
+        }
+        let pivot = pivot(&self.nodeui, "actionArray", map, &vertices);
+
+        // A full circle would put the last copy on top of the original
+        let step = if angle.abs() >= 360.0 {
+            angle / (count + 1) as f32
+        } else {
+            angle / count as f32
+        }
+        .to_radians();
+
+        let mut selection = (vec![], vec![], vec![]);
+        for index in 1..=count {
+            let transform = |p: Vec2<f32>| -> Vec2<f32> {
+                if radial {
+                    let (sin, cos) = (step * index as f32).sin_cos();
+                    let d = p - pivot;
+                    pivot + Vec2::new(d.x * cos - d.y * sin, d.x * sin + d.y * cos)
+                } else {
+                    p + offset * index as f32
+                }
+            };
+            let (vertices, linedefs, sectors) = duplicate_selection(map, snap, &transform);
+            selection.0.extend(vertices);
+            selection.1.extend(linedefs);
+            selection.2.extend(sectors);
+        }
+
+        if selection.0.is_empty() && selection.1.is_empty() {
+            return None;
+        }
+
+        // Select the copies together with the original
+        let (vertices, linedefs, sectors) = selection;
+        map.selected_sectors.extend(sectors);
+        if !map.selected_linedefs.is_empty() {
+            map.selected_linedefs.extend(linedefs);
+        }
+        if !map.selected_vertices.is_empty() {
+            map.selected_vertices.extend(vertices);
+        }
+
+        Some(RegionUndoAtom::MapEdit(
+            Box::new(prev),
+            Box::new(map.clone()),
+        ))
+    }
+
+    fn params(&self) -> TheNodeUI {
+        self.nodeui.clone()
+    }
+
+    fn handle_event(
+        &mut self,
+        event: &TheEvent,
+        _project: &mut Project,
+        _ui: &mut TheUI,
+        _ctx: &mut TheContext,
+        _server_ctx: &mut ServerContext,
+    ) -> bool {
+        self.nodeui.handle_event(event)
+    }
+}
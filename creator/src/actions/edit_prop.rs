@@ -0,0 +1,211 @@
+use crate::actions::place_prop::{prop_collision, prop_collision_item};
+use crate::editor::UNDOMANAGER;
+use crate::prelude::*;
+
+pub struct EditProp {
+    id: TheId,
+    nodeui: TheNodeUI,
+    /// The props of the region in the order of the selector.
+    props: Vec<Uuid>,
+}
+
+impl EditProp {
+    /// Shows the transform of the selected prop.
+    fn load_prop(&mut self, project: &Project, server_ctx: &ServerContext) {
+        let index = self.nodeui.get_i32_value("actionProp").unwrap_or(0);
+        let Some(prop) = self.props.get(index.max(0) as usize).and_then(|id| {
+            project
+                .get_region_ctx(server_ctx)
+                .and_then(|region| region.props.get(id))
+        }) else {
+            return;
+        };
+        let transform = prop.transform;
+
+        self.nodeui
+            .set_f32_value("actionPropRotation", transform.rotation);
+        self.nodeui
+            .set_f32_value("actionPropScale", transform.scale);
+        self.nodeui
+            .set_f32_value("actionPropElevation", transform.elevation);
+        if let Some(item) = self.nodeui.get_item_mut("actionPropCollision") {
+            *item = prop_collision_item(transform.collision);
+        }
+    }
+}
+
+impl Action for EditProp {
+    fn new() -> Self
+    where
+        Self: Sized,
+    {
+        let mut nodeui: TheNodeUI = TheNodeUI::default();
+
+        nodeui.add_item(TheNodeUIItem::Selector(
+            "actionProp".into(),
+            "Prop".into(),
+            "The prop of the region to edit.".into(),
+            vec![],
+            0,
+        ));
+        nodeui.add_item(TheNodeUIItem::FloatEditSlider(
+            "actionPropRotation".into(),
+            "Rotation".into(),
+            "The rotation around the vertical axis in degrees.".into(),
+            0.0,
+            0.0..=360.0,
+            false,
+        ));
+        nodeui.add_item(TheNodeUIItem::FloatEditSlider(
+            "actionPropScale".into(),
+            "Scale".into(),
+            "The uniform scale of the mesh.".into(),
+            1.0,
+            0.01..=100.0,
+            false,
+        ));
+        nodeui.add_item(TheNodeUIItem::FloatEditSlider(
+            "actionPropElevation".into(),
+            "Elevation".into(),
+            "The height of the base of the mesh.".into(),
+            0.0,
+            -20.0..=20.0,
+            false,
+        ));
+        nodeui.add_item(prop_collision_item(PropCollision::None));
+        nodeui.add_item(TheNodeUIItem::Checkbox(
+            "actionPropRemove".into(),
+            "Remove".into(),
+            "Removes the prop from the region.".into(),
+            false,
+        ));
+
+        let item = TheNodeUIItem::Markdown(
+            "desc".into(),
+            "Changes the transform and the collision shape of a prop of the region, or removes it."
+                .into(),
+        );
+        nodeui.add_item(item);
+
+        Self {
+            id: TheId::named("Edit Prop"),
+            nodeui,
+            props: vec![],
+        }
+    }
+
+    fn id(&self) -> TheId {
+        self.id.clone()
+    }
+
+    fn info(&self) -> &'static str {
+        "Changes the transform of a prop of the region."
+    }
+
+    fn role(&self) -> ActionRole {
+        ActionRole::Editor
+    }
+
+    fn is_applicable(&self, _map: &Map, _ctx: &mut TheContext, server_ctx: &ServerContext) -> bool {
+        server_ctx.get_map_context() == MapContext::Region && server_ctx.editing_surface.is_none()
+    }
+
+    fn load_params_project(&mut self, project: &Project, server_ctx: &mut ServerContext) {
+        let mut names = vec![];
+        self.props.clear();
+        if let Some(region) = project.get_region_ctx(server_ctx) {
+            for (index, prop) in region.props.values().enumerate() {
+                let asset = project
+                    .assets
+                    .get(&prop.asset)
+                    .map(|a| a.name.clone())
+                    .unwrap_or("Missing Mesh".into());
+                self.props.push(prop.id);
+                names.push(format!("{} #{}", asset, index + 1));
+            }
+        }
+
+        if let Some(TheNodeUIItem::Selector(_, _, _, options, value)) =
+            self.nodeui.get_item_mut("actionProp")
+        {
+            *value = (*value).min(names.len().saturating_sub(1) as i32).max(0);
+            *options = names;
+        }
+        self.load_prop(project, server_ctx);
+    }
+
+    fn apply_project(
+        &self,
+        project: &mut Project,
+        ui: &mut TheUI,
+        ctx: &mut TheContext,
+        server_ctx: &mut ServerContext,
+    ) {
+        let Some(region) = project.get_region_ctx(server_ctx) else {
+            return;
+        };
+        let index = self.nodeui.get_i32_value("actionProp").unwrap_or(0);
+        let Some(id) = self
+            .props
+            .get(index.max(0) as usize)
+            .filter(|id| region.props.contains_key(*id))
+        else {
+            ctx.ui.send(TheEvent::SetStatusText(
+                TheId::empty(),
+                "The region has no props.".to_string(),
+            ));
+            return;
+        };
+
+        let prev = region.props.clone();
+        let mut props = prev.clone();
+        if self
+            .nodeui
+            .get_bool_value("actionPropRemove")
+            .unwrap_or(false)
+        {
+            props.shift_remove(id);
+        } else if let Some(prop) = props.get_mut(id) {
+            prop.transform = PropTransform {
+                rotation: self
+                    .nodeui
+                    .get_f32_value("actionPropRotation")
+                    .unwrap_or(0.0),
+                scale: self.nodeui.get_f32_value("actionPropScale").unwrap_or(1.0),
+                elevation: self
+                    .nodeui
+                    .get_f32_value("actionPropElevation")
+                    .unwrap_or(0.0),
+                collision: prop_collision(
+                    self.nodeui
+                        .get_i32_value("actionPropCollision")
+                        .unwrap_or(0),
+                ),
+            };
+        }
+
+        let atom = ProjectUndoAtom::EditProps(region.id, Box::new(prev), Box::new(props));
+        atom.redo(project, ui, ctx, server_ctx);
+        UNDOMANAGER.write().unwrap().add_undo(atom, ctx);
+    }
+
+    fn params(&self) -> TheNodeUI {
+        self.nodeui.clone()
+    }
+
+    fn handle_event(
+        &mut self,
+        event: &TheEvent,
+        project: &mut Project,
+        _ui: &mut TheUI,
+        _ctx: &mut TheContext,
+        server_ctx: &mut ServerContext,
+    ) -> bool {
+        let prop = self.nodeui.get_i32_value("actionProp");
+        let changed = self.nodeui.handle_event(event);
+        if changed && self.nodeui.get_i32_value("actionProp") != prop {
+            self.load_prop(project, server_ctx);
+        }
+        changed
+    }
+}
@@ -1,4 +1,5 @@
 use crate::prelude::*;
+use rusterix::Value;
 
 pub struct EditSector {
     id: TheId,
@@ -22,9 +23,29 @@ impl Action for EditSector {
         );
         nodeui.add_item(item);
 
+        let item = TheNodeUIItem::Text(
+            "actionSectorMusic".into(),
+            "Music".into(),
+            "The audio asset played as music while the player is inside the sector.".into(),
+            "".into(),
+            None,
+            false,
+        );
+        nodeui.add_item(item);
+
+        let item = TheNodeUIItem::Text(
+            "actionSectorAmbient".into(),
+            "Ambient Sound".into(),
+            "The audio asset looped as ambient sound while the player is inside the sector.".into(),
+            "".into(),
+            None,
+            false,
+        );
+        nodeui.add_item(item);
+
         let item = TheNodeUIItem::Markdown(
             "desc".into(),
-            "Edit the attributes of the selected sector.".into(),
+            "Edit the attributes of the selected sector. Music keeps playing when the player leaves the sector, ambient sounds fade out.".into(),
         );
         nodeui.add_item(item);
 
@@ -59,6 +80,18 @@ impl Action for EditSector {
             if let Some(sector) = map.find_sector(*sector_id) {
                 self.nodeui
                     .set_text_value("actionSectorName", sector.name.clone());
+                self.nodeui.set_text_value(
+                    "actionSectorMusic",
+                    sector
+                        .properties
+                        .get_str_default(SECTOR_MUSIC, String::new()),
+                );
+                self.nodeui.set_text_value(
+                    "actionSectorAmbient",
+                    sector
+                        .properties
+                        .get_str_default(SECTOR_AMBIENT, String::new()),
+                );
             }
         }
     }
@@ -84,6 +117,25 @@ impl Action for EditSector {
                     sector.name = name;
                     changed = true;
                 }
+                for (key, param) in [
+                    (SECTOR_MUSIC, "actionSectorMusic"),
+                    (SECTOR_AMBIENT, "actionSectorAmbient"),
+                ] {
+                    let value = self
+                        .nodeui
+                        .get_text_value(param)
+                        .unwrap_or_default()
+                        .trim()
+                        .to_string();
+                    if value != sector.properties.get_str_default(key, String::new()) {
+                        if value.is_empty() {
+                            sector.properties.remove(key);
+                        } else {
+                            sector.properties.set(key, Value::Str(value));
+                        }
+                        changed = true;
+                    }
+                }
             }
         }
 
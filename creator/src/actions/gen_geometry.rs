@@ -0,0 +1,151 @@
+use crate::prelude::*;
+use rusterix::{Surface, Value};
+use vek::Vec2;
+
+/// Sector property holding the extrusion depth a generator requested. The
+/// property is turned into a surface extrusion when the geometry is placed.
+pub const GEN_EXTRUSION: &str = "gen_extrusion";
+
+/// A new map for generated geometry. Like the shape picker, the geometry is
+/// created around the origin and placed by the paste operation of the editor,
+/// which previews it under the cursor.
+pub fn preview_map() -> Map {
+    Map {
+        subdivisions: 1000.0,
+        ..Default::default()
+    }
+}
+
+/// Activates the generated geometry for pasting.
+pub fn activate_preview(map: Map, server_ctx: &mut ServerContext) {
+    server_ctx.paste_prefab = None;
+    server_ctx.paste_prop = None;
+    server_ctx.paste_clipboard = Some(map);
+}
+
+/// Regenerates the preview after a parameter change, if a preview is active.
+pub fn refresh_preview(map: Map, server_ctx: &mut ServerContext) {
+    if server_ctx.paste_clipboard.is_some() && server_ctx.paste_prefab.is_none() {
+        server_ctx.paste_clipboard = Some(map);
+    }
+}
+
+/// Adds a closed polygon as a new sector. The vertices are placed at the
+/// floor height and the floor and ceiling heights are stored in the sector.
+pub fn add_polygon_sector(
+    map: &mut Map,
+    points: &[Vec2<f32>],
+    floor: f32,
+    ceiling: f32,
+) -> Option<u32> {
+    add_sloped_sector(
+        map,
+        &points.iter().map(|p| (*p, floor)).collect::<Vec<_>>(),
+        ceiling,
+    )
+}
+
+/// Adds a closed polygon with individual vertex heights as a new sector. The
+/// lowest vertex defines the floor height of the sector.
+pub fn add_sloped_sector(map: &mut Map, points: &[(Vec2<f32>, f32)], ceiling: f32) -> Option<u32> {
+    let sector_id = add_loop(map, points)?;
+    let floor = points.iter().map(|(_, z)| *z).fold(f32::MAX, f32::min);
+    let sector = map.find_sector_mut(sector_id)?;
+    sector.properties.set("floor_height", Value::Float(floor));
+    sector
+        .properties
+        .set("ceiling_height", Value::Float(ceiling));
+    Some(sector_id)
+}
+
+/// Adds a closed polygon as a new sector of a surface profile.
+pub fn add_profile_sector(map: &mut Map, points: &[Vec2<f32>]) -> Option<u32> {
+    add_loop(map, &points.iter().map(|p| (*p, 0.0)).collect::<Vec<_>>())
+}
+
+fn add_loop(map: &mut Map, points: &[(Vec2<f32>, f32)]) -> Option<u32> {
+    if points.len() < 3 {
+        return None;
+    }
+
+    let vertices: Vec<u32> = points
+        .iter()
+        .map(|(p, z)| map.add_vertex_at_3d(p.x, p.y, *z, false))
+        .collect();
+
+    map.possible_polygon = vec![];
+    let mut sector_id = None;
+    for i in 0..vertices.len() {
+        let (_, sector) = map.create_linedef(vertices[i], vertices[(i + 1) % vertices.len()]);
+        if sector.is_some() {
+            sector_id = sector;
+        }
+    }
+    map.possible_polygon = vec![];
+    sector_id
+}
+
+/// Requests a solid extrusion of the sector when the geometry is placed.
+pub fn set_extrusion(map: &mut Map, sector_id: u32, depth: f32) {
+    if let Some(sector) = map.find_sector_mut(sector_id) {
+        sector.properties.set(GEN_EXTRUSION, Value::Float(depth));
+    }
+}
+
+/// Creates the surfaces of placed generated sectors and applies the
+/// requested extrusions.
+pub fn apply_generated_extrusions(map: &mut Map) {
+    let sectors: Vec<(u32, f32)> = map
+        .sectors
+        .iter()
+        .filter_map(|s| match s.properties.get(GEN_EXTRUSION) {
+            Some(Value::Float(depth)) => Some((s.id, *depth)),
+            _ => None,
+        })
+        .collect();
+
+    for (sector_id, depth) in sectors {
+        let existing = map
+            .surfaces
+            .iter()
+            .find(|(_, s)| s.sector_id == sector_id)
+            .map(|(id, _)| *id);
+        let surface_id = match existing {
+            Some(id) => id,
+            None => {
+                let mut surface = Surface::new(sector_id);
+                surface.calculate_geometry(map);
+                let id = surface.id;
+                map.surfaces.insert(id, surface);
+                id
+            }
+        };
+        if let Some(surface) = map.surfaces.get_mut(&surface_id) {
+            surface.extrusion.enabled = true;
+            surface.extrusion.depth = depth;
+            surface.extrusion.cap_front = true;
+            surface.extrusion.cap_back = true;
+            surface.extrusion.flip_normal = false;
+        }
+        if let Some(sector) = map.find_sector_mut(sector_id) {
+            sector.properties.remove(GEN_EXTRUSION);
+        }
+    }
+}
+
+/// The points of a regular polygon or circle segment around the center.
+pub fn arc_points(
+    center: Vec2<f32>,
+    radius: f32,
+    start_angle: f32,
+    end_angle: f32,
+    segments: usize,
+) -> Vec<Vec2<f32>> {
+    let segments = segments.max(1);
+    (0..=segments)
+        .map(|i| {
+            let a = start_angle + (end_angle - start_angle) * i as f32 / segments as f32;
+            center + Vec2::new(a.cos(), a.sin()) * radius
+        })
+        .collect()
+}
@@ -0,0 +1,262 @@
+use crate::actions::gen_geometry::*;
+use crate::actions::gen_stone_trim::StoneTrim;
+use crate::prelude::*;
+use rusterix::Value;
+use vek::Vec2;
+
+pub struct GenerateOpening {
+    id: TheId,
+    nodeui: TheNodeUI,
+}
+
+impl GenerateOpening {
+    /// Creates the opening in profile coordinates, the bottom center of a door
+    /// or the wall below a window at the origin.
+    fn generate(&self) -> Map {
+        let mut map = preview_map();
+
+        let door = self.nodeui.get_i32_value("actionOpeningType").unwrap_or(0) == 0;
+        let width = self
+            .nodeui
+            .get_f32_value("actionOpeningWidth")
+            .unwrap_or(1.0)
+            .max(0.05);
+        let height = self
+            .nodeui
+            .get_f32_value("actionOpeningHeight")
+            .unwrap_or(2.0)
+            .max(0.05);
+        let sill = if door {
+            0.0
+        } else {
+            self.nodeui
+                .get_f32_value("actionOpeningSill")
+                .unwrap_or(1.0)
+        };
+        let arch = self.nodeui.get_i32_value("actionOpeningArch").unwrap_or(0);
+        let arch_height = self
+            .nodeui
+            .get_f32_value("actionOpeningArchHeight")
+            .unwrap_or(0.5)
+            .clamp(0.0, height);
+        let segments = self
+            .nodeui
+            .get_i32_value("actionOpeningSegments")
+            .unwrap_or(8)
+            .max(2) as usize;
+        let depth = self
+            .nodeui
+            .get_f32_value("actionOpeningDepth")
+            .unwrap_or(0.0);
+        let trim = self.nodeui.get_i32_value("actionOpeningTrim").unwrap_or(0);
+
+        let r = width * 0.5;
+        let spring = sill + height - if arch == 0 { 0.0 } else { arch_height };
+
+        // Counter-clockwise, starting at the bottom left corner
+        let mut points = vec![
+            Vec2::new(-r, sill),
+            Vec2::new(r, sill),
+            Vec2::new(r, spring),
+        ];
+        if arch != 0 && arch_height > 0.0 {
+            for i in 1..segments {
+                let x = r - width * i as f32 / segments as f32;
+                let u = (x / r).abs().min(1.0);
+                let rise = if arch == 1 {
+                    (1.0 - u * u).sqrt()
+                } else {
+                    (1.0 - u).sqrt()
+                };
+                points.push(Vec2::new(x, spring + arch_height * rise));
+            }
+        }
+        points.push(Vec2::new(-r, spring));
+
+        // The opening cuts through the wall unless it has a depth
+        if let Some(sector_id) = add_profile_sector(&mut map, &points)
+            && depth > 0.0
+        {
+            if let Some(sector) = map.find_sector_mut(sector_id) {
+                sector.properties.set("profile_op", Value::Int(2));
+                sector.properties.set("profile_amount", Value::Float(depth));
+                sector.properties.set("profile_target", Value::Int(1));
+            }
+        }
+
+        if trim == 1 {
+            let stone = StoneTrim {
+                course_width: self
+                    .nodeui
+                    .get_f32_value("actionOpeningCourseWidth")
+                    .unwrap_or(0.25)
+                    .max(0.01),
+                depth: self
+                    .nodeui
+                    .get_f32_value("actionOpeningTrimDepth")
+                    .unwrap_or(0.03),
+                open_first_edge: door,
+                ..Default::default()
+            };
+            stone.emit(&mut map, &points);
+        }
+
+        map
+    }
+}
+
+impl Action for GenerateOpening {
+    fn new() -> Self
+    where
+        Self: Sized,
+    {
+        let mut nodeui: TheNodeUI = TheNodeUI::default();
+
+        nodeui.add_item(TheNodeUIItem::Selector(
+            "actionOpeningType".into(),
+            "Type".into(),
+            "Doors start at the bottom of the wall, windows at the sill height.".into(),
+            vec!["Door".to_string(), "Window".to_string()],
+            0,
+        ));
+        nodeui.add_item(TheNodeUIItem::FloatEditSlider(
+            "actionOpeningWidth".into(),
+            "Width".into(),
+            "The width of the opening.".into(),
+            1.0,
+            0.1..=10.0,
+            false,
+        ));
+        nodeui.add_item(TheNodeUIItem::FloatEditSlider(
+            "actionOpeningHeight".into(),
+            "Height".into(),
+            "The height of the opening including the arch.".into(),
+            2.0,
+            0.1..=10.0,
+            false,
+        ));
+        nodeui.add_item(TheNodeUIItem::FloatEditSlider(
+            "actionOpeningSill".into(),
+            "Sill".into(),
+            "The height of the bottom of a window.".into(),
+            1.0,
+            0.0..=10.0,
+            false,
+        ));
+        nodeui.add_item(TheNodeUIItem::Selector(
+            "actionOpeningArch".into(),
+            "Arch".into(),
+            "The shape of the top of the opening.".into(),
+            vec![
+                "Flat".to_string(),
+                "Round".to_string(),
+                "Pointed".to_string(),
+            ],
+            1,
+        ));
+        nodeui.add_item(TheNodeUIItem::FloatEditSlider(
+            "actionOpeningArchHeight".into(),
+            "Arch Height".into(),
+            "The height of the arch.".into(),
+            0.5,
+            0.0..=5.0,
+            false,
+        ));
+        nodeui.add_item(TheNodeUIItem::IntEditSlider(
+            "actionOpeningSegments".into(),
+            "Segments".into(),
+            "The number of segments of the arch.".into(),
+            8,
+            2..=32,
+            false,
+        ));
+        nodeui.add_item(TheNodeUIItem::FloatEditSlider(
+            "actionOpeningDepth".into(),
+            "Depth".into(),
+            "Zero cuts through the wall, other values create a recess of this depth.".into(),
+            0.0,
+            0.0..=1.0,
+            false,
+        ));
+        nodeui.add_item(TheNodeUIItem::Selector(
+            "actionOpeningTrim".into(),
+            "Trim".into(),
+            "The trim around the opening.".into(),
+            vec!["None".to_string(), "Stone".to_string()],
+            1,
+        ));
+        nodeui.add_item(TheNodeUIItem::FloatEditSlider(
+            "actionOpeningCourseWidth".into(),
+            "Course Width".into(),
+            "The thickness of the stone trim.".into(),
+            0.25,
+            0.01..=2.0,
+            false,
+        ));
+        nodeui.add_item(TheNodeUIItem::FloatEditSlider(
+            "actionOpeningTrimDepth".into(),
+            "Trim Depth".into(),
+            "Positive values raise the trim stones, negative values recess them.".into(),
+            0.03,
+            -0.2..=0.2,
+            false,
+        ));
+
+        let item = TheNodeUIItem::Markdown(
+            "desc".into(),
+            "Generates a door or window opening with an optional stone trim in the **active surface profile**. Apply to preview the opening under the cursor and click into the profile to place it. Changing the parameters updates the preview.".into(),
+        );
+        nodeui.add_item(item);
+
+        Self {
+            id: TheId::named("Generate Opening"),
+            nodeui,
+        }
+    }
+
+    fn id(&self) -> TheId {
+        self.id.clone()
+    }
+
+    fn info(&self) -> &'static str {
+        "Generates a door or window opening in the current surface profile."
+    }
+
+    fn role(&self) -> ActionRole {
+        ActionRole::Editor
+    }
+
+    fn is_applicable(&self, _map: &Map, _ctx: &mut TheContext, server_ctx: &ServerContext) -> bool {
+        server_ctx.editor_view_mode == EditorViewMode::D2 && server_ctx.editing_surface.is_some()
+    }
+
+    fn apply(
+        &self,
+        _map: &mut Map,
+        _ui: &mut TheUI,
+        _ctx: &mut TheContext,
+        server_ctx: &mut ServerContext,
+    ) -> Option<RegionUndoAtom> {
+        activate_preview(self.generate(), server_ctx);
+        None
+    }
+
+    fn params(&self) -> TheNodeUI {
+        self.nodeui.clone()
+    }
+
+    fn handle_event(
+        &mut self,
+        event: &TheEvent,
+        _project: &mut Project,
+        _ui: &mut TheUI,
+        _ctx: &mut TheContext,
+        server_ctx: &mut ServerContext,
+    ) -> bool {
+        let changed = self.nodeui.handle_event(event);
+        if changed {
+            refresh_preview(self.generate(), server_ctx);
+        }
+        changed
+    }
+}
@@ -0,0 +1,160 @@
+use crate::actions::gen_geometry::*;
+use crate::prelude::*;
+use vek::Vec2;
+
+pub struct GeneratePillar {
+    id: TheId,
+    nodeui: TheNodeUI,
+}
+
+impl GeneratePillar {
+    /// Creates the pillar as a regular polygon around the origin, extruded
+    /// from its base to its top.
+    fn generate(&self) -> Map {
+        let mut map = preview_map();
+
+        let sides = self
+            .nodeui
+            .get_i32_value("actionPillarSides")
+            .unwrap_or(8)
+            .max(3) as usize;
+        let radius = self
+            .nodeui
+            .get_f32_value("actionPillarRadius")
+            .unwrap_or(0.25)
+            .max(0.01);
+        let base = self.nodeui.get_f32_value("actionPillarBase").unwrap_or(0.0);
+        let height = self
+            .nodeui
+            .get_f32_value("actionPillarHeight")
+            .unwrap_or(3.0)
+            .max(0.01);
+        let rotation = self
+            .nodeui
+            .get_f32_value("actionPillarRotation")
+            .unwrap_or(0.0)
+            .to_radians();
+
+        let mut points = arc_points(
+            Vec2::zero(),
+            radius,
+            rotation,
+            rotation + std::f32::consts::TAU,
+            sides,
+        );
+        points.pop();
+
+        if let Some(sector_id) = add_polygon_sector(&mut map, &points, base, base + height) {
+            set_extrusion(&mut map, sector_id, height);
+        }
+
+        map
+    }
+}
+
+impl Action for GeneratePillar {
+    fn new() -> Self
+    where
+        Self: Sized,
+    {
+        let mut nodeui: TheNodeUI = TheNodeUI::default();
+
+        nodeui.add_item(TheNodeUIItem::IntEditSlider(
+            "actionPillarSides".into(),
+            "Sides".into(),
+            "The number of sides. Use 4 for square pillars and more for round ones.".into(),
+            8,
+            3..=32,
+            false,
+        ));
+        nodeui.add_item(TheNodeUIItem::FloatEditSlider(
+            "actionPillarRadius".into(),
+            "Radius".into(),
+            "The distance from the center to the corners.".into(),
+            0.25,
+            0.05..=10.0,
+            false,
+        ));
+        nodeui.add_item(TheNodeUIItem::FloatEditSlider(
+            "actionPillarBase".into(),
+            "Base Height".into(),
+            "The floor height the pillar stands on.".into(),
+            0.0,
+            -20.0..=20.0,
+            false,
+        ));
+        nodeui.add_item(TheNodeUIItem::FloatEditSlider(
+            "actionPillarHeight".into(),
+            "Height".into(),
+            "The height of the pillar.".into(),
+            3.0,
+            0.1..=50.0,
+            false,
+        ));
+        nodeui.add_item(TheNodeUIItem::FloatEditSlider(
+            "actionPillarRotation".into(),
+            "Rotation".into(),
+            "The rotation of the corners in degrees.".into(),
+            0.0,
+            0.0..=360.0,
+            false,
+        ));
+
+        let item = TheNodeUIItem::Markdown(
+            "desc".into(),
+            "Generates a solid pillar. Apply to preview the pillar under the cursor and click into the map to place it. Changing the parameters updates the preview.".into(),
+        );
+        nodeui.add_item(item);
+
+        Self {
+            id: TheId::named("Generate Pillar"),
+            nodeui,
+        }
+    }
+
+    fn id(&self) -> TheId {
+        self.id.clone()
+    }
+
+    fn info(&self) -> &'static str {
+        "Generates a solid pillar."
+    }
+
+    fn role(&self) -> ActionRole {
+        ActionRole::Editor
+    }
+
+    fn is_applicable(&self, _map: &Map, _ctx: &mut TheContext, server_ctx: &ServerContext) -> bool {
+        server_ctx.get_map_context() == MapContext::Region && server_ctx.editing_surface.is_none()
+    }
+
+    fn apply(
+        &self,
+        _map: &mut Map,
+        _ui: &mut TheUI,
+        _ctx: &mut TheContext,
+        server_ctx: &mut ServerContext,
+    ) -> Option<RegionUndoAtom> {
+        activate_preview(self.generate(), server_ctx);
+        None
+    }
+
+    fn params(&self) -> TheNodeUI {
+        self.nodeui.clone()
+    }
+
+    fn handle_event(
+        &mut self,
+        event: &TheEvent,
+        _project: &mut Project,
+        _ui: &mut TheUI,
+        _ctx: &mut TheContext,
+        server_ctx: &mut ServerContext,
+    ) -> bool {
+        let changed = self.nodeui.handle_event(event);
+        if changed {
+            refresh_preview(self.generate(), server_ctx);
+        }
+        changed
+    }
+}
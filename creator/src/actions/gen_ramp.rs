@@ -0,0 +1,186 @@
+use crate::actions::gen_geometry::*;
+use crate::prelude::*;
+use vek::Vec2;
+
+pub struct GenerateRamp {
+    id: TheId,
+    nodeui: TheNodeUI,
+}
+
+impl GenerateRamp {
+    /// Creates the ramp around the origin, rising along the y axis. Optional
+    /// landings at both ends are flat sectors sharing the edge of the ramp.
+    fn generate(&self) -> Map {
+        let mut map = preview_map();
+
+        let length = self
+            .nodeui
+            .get_f32_value("actionRampLength")
+            .unwrap_or(4.0)
+            .max(0.01);
+        let width = self
+            .nodeui
+            .get_f32_value("actionRampWidth")
+            .unwrap_or(1.0)
+            .max(0.01);
+        let from = self.nodeui.get_f32_value("actionRampFrom").unwrap_or(0.0);
+        let to = self.nodeui.get_f32_value("actionRampTo").unwrap_or(1.0);
+        let landing = self
+            .nodeui
+            .get_f32_value("actionRampLanding")
+            .unwrap_or(0.0)
+            .max(0.0);
+        let headroom = self
+            .nodeui
+            .get_f32_value("actionRampHeadroom")
+            .unwrap_or(2.0);
+
+        let (x0, x1) = (-width * 0.5, width * 0.5);
+        let (y0, y1) = (-length * 0.5, length * 0.5);
+
+        if landing > 0.0 {
+            let points = [
+                Vec2::new(x0, y0 - landing),
+                Vec2::new(x1, y0 - landing),
+                Vec2::new(x1, y0),
+                Vec2::new(x0, y0),
+            ];
+            add_polygon_sector(&mut map, &points, from, from + headroom);
+        }
+
+        let points = [
+            (Vec2::new(x0, y0), from),
+            (Vec2::new(x1, y0), from),
+            (Vec2::new(x1, y1), to),
+            (Vec2::new(x0, y1), to),
+        ];
+        add_sloped_sector(&mut map, &points, from.max(to) + headroom);
+
+        if landing > 0.0 {
+            let points = [
+                Vec2::new(x0, y1),
+                Vec2::new(x1, y1),
+                Vec2::new(x1, y1 + landing),
+                Vec2::new(x0, y1 + landing),
+            ];
+            add_polygon_sector(&mut map, &points, to, to + headroom);
+        }
+
+        map
+    }
+}
+
+impl Action for GenerateRamp {
+    fn new() -> Self
+    where
+        Self: Sized,
+    {
+        let mut nodeui: TheNodeUI = TheNodeUI::default();
+
+        nodeui.add_item(TheNodeUIItem::FloatEditSlider(
+            "actionRampLength".into(),
+            "Length".into(),
+            "The length of the slope.".into(),
+            4.0,
+            0.1..=50.0,
+            false,
+        ));
+        nodeui.add_item(TheNodeUIItem::FloatEditSlider(
+            "actionRampWidth".into(),
+            "Width".into(),
+            "The width of the ramp.".into(),
+            1.0,
+            0.1..=20.0,
+            false,
+        ));
+        nodeui.add_item(TheNodeUIItem::FloatEditSlider(
+            "actionRampFrom".into(),
+            "From Height".into(),
+            "The floor height at the bottom of the ramp.".into(),
+            0.0,
+            -20.0..=20.0,
+            false,
+        ));
+        nodeui.add_item(TheNodeUIItem::FloatEditSlider(
+            "actionRampTo".into(),
+            "To Height".into(),
+            "The floor height at the top of the ramp.".into(),
+            1.0,
+            -20.0..=20.0,
+            false,
+        ));
+        nodeui.add_item(TheNodeUIItem::FloatEditSlider(
+            "actionRampLanding".into(),
+            "Landing".into(),
+            "The depth of the flat landings at both ends. Zero creates no landings.".into(),
+            0.0,
+            0.0..=10.0,
+            false,
+        ));
+        nodeui.add_item(TheNodeUIItem::FloatEditSlider(
+            "actionRampHeadroom".into(),
+            "Headroom".into(),
+            "The ceiling height above the highest point of each sector.".into(),
+            2.0,
+            0.0..=20.0,
+            false,
+        ));
+
+        let item = TheNodeUIItem::Markdown(
+            "desc".into(),
+            "Generates a ramp between two floor heights. Apply to preview the ramp under the cursor and click into the map to place it. Changing the parameters updates the preview.".into(),
+        );
+        nodeui.add_item(item);
+
+        Self {
+            id: TheId::named("Generate Ramp"),
+            nodeui,
+        }
+    }
+
+    fn id(&self) -> TheId {
+        self.id.clone()
+    }
+
+    fn info(&self) -> &'static str {
+        "Generates a ramp between two floor heights."
+    }
+
+    fn role(&self) -> ActionRole {
+        ActionRole::Editor
+    }
+
+    fn is_applicable(&self, _map: &Map, _ctx: &mut TheContext, server_ctx: &ServerContext) -> bool {
+        server_ctx.get_map_context() == MapContext::Region && server_ctx.editing_surface.is_none()
+    }
+
+    fn apply(
+        &self,
+        _map: &mut Map,
+        _ui: &mut TheUI,
+        _ctx: &mut TheContext,
+        server_ctx: &mut ServerContext,
+    ) -> Option<RegionUndoAtom> {
+        activate_preview(self.generate(), server_ctx);
+        None
+    }
+
+    fn params(&self) -> TheNodeUI {
+        self.nodeui.clone()
+    }
+
+    fn handle_event(
+        &mut self,
+        event: &TheEvent,
+        _project: &mut Project,
+        _ui: &mut TheUI,
+        _ctx: &mut TheContext,
+        server_ctx: &mut ServerContext,
+    ) -> bool {
+        let changed = self.nodeui.handle_event(event);
+        if changed {
+            refresh_preview(self.generate(), server_ctx);
+        }
+        changed
+    }
+}
@@ -0,0 +1,390 @@
+use crate::editor::UNDOMANAGER;
+use crate::prelude::*;
+
+pub struct GenerateRegion {
+    id: TheId,
+    nodeui: TheNodeUI,
+}
+
+impl GenerateRegion {
+    /// The generator described by the parameters.
+    fn generator(&self) -> RegionGenerator {
+        let defaults = RegionGenerator::default();
+        let int = |id: &str, default: i32| self.nodeui.get_i32_value(id).unwrap_or(default);
+        let float = |id: &str, default: f32| self.nodeui.get_f32_value(id).unwrap_or(default);
+        let text = |id: &str, default: &str| {
+            self.nodeui
+                .get_text_value(id)
+                .unwrap_or(default.to_string())
+        };
+
+        RegionGenerator {
+            mode: if int("actionGenMode", 0) == 1 {
+                GeneratorMode::Town
+            } else {
+                GeneratorMode::Dungeon
+            },
+            layout: if int("actionGenLayout", 0) == 1 {
+                DungeonLayout::Rooms
+            } else {
+                DungeonLayout::Bsp
+            },
+            seed: int("actionGenSeed", 0).max(0) as u64,
+            on_start: false,
+            width: int("actionGenWidth", defaults.width),
+            height: int("actionGenHeight", defaults.height),
+            cell_size: float("actionGenCellSize", defaults.cell_size).max(0.1),
+            room_min: int("actionGenRoomMin", defaults.room_min),
+            room_max: int("actionGenRoomMax", defaults.room_max),
+            room_count: int("actionGenRoomCount", defaults.room_count),
+            corridor_width: int("actionGenCorridorWidth", defaults.corridor_width),
+            street_width: int("actionGenStreetWidth", defaults.street_width),
+            block_size: int("actionGenBlockSize", defaults.block_size),
+            lot_min: int("actionGenLotMin", defaults.lot_min),
+            floor_height: float("actionGenFloorHeight", defaults.floor_height),
+            ceiling_height: float("actionGenCeilingHeight", defaults.ceiling_height),
+            floor_tags: text("actionGenFloorTags", &defaults.floor_tags),
+            wall_tags: text("actionGenWallTags", &defaults.wall_tags),
+            door_tags: text("actionGenDoorTags", &defaults.door_tags),
+            ceiling_tags: text("actionGenCeilingTags", &defaults.ceiling_tags),
+            street_tags: text("actionGenStreetTags", &defaults.street_tags),
+            characters: RegionGenerator::parse_spawn_list(&text("actionGenCharacters", "")),
+            character_count: int("actionGenCharacterCount", 0),
+            items: RegionGenerator::parse_spawn_list(&text("actionGenItems", "")),
+            item_count: int("actionGenItemCount", 0),
+        }
+    }
+
+    fn set_text(&mut self, id: &str, text: String) {
+        if let Some(TheNodeUIItem::Text(_, _, _, value, _, _)) = self.nodeui.get_item_mut(id) {
+            *value = text;
+        }
+    }
+}
+
+impl Action for GenerateRegion {
+    fn new() -> Self
+    where
+        Self: Sized,
+    {
+        let mut nodeui: TheNodeUI = TheNodeUI::default();
+
+        nodeui.add_item(TheNodeUIItem::Selector(
+            "actionGenMode".into(),
+            "Mode".into(),
+            "Generate a dungeon of rooms and corridors or a town of streets and houses.".into(),
+            vec!["Dungeon".to_string(), "Town".to_string()],
+            0,
+        ));
+        nodeui.add_item(TheNodeUIItem::Selector(
+            "actionGenLayout".into(),
+            "Layout".into(),
+            "Dungeon rooms by binary space partitioning or randomly placed rooms.".into(),
+            vec!["BSP".to_string(), "Rooms".to_string()],
+            0,
+        ));
+        nodeui.add_item(TheNodeUIItem::IntEditSlider(
+            "actionGenSeed".into(),
+            "Seed".into(),
+            "The same seed and parameters always generate the same region. 0 picks a random seed."
+                .into(),
+            0,
+            0..=99999,
+            false,
+        ));
+        nodeui.add_item(TheNodeUIItem::IntEditSlider(
+            "actionGenWidth".into(),
+            "Width".into(),
+            "The width of the region in cells.".into(),
+            48,
+            8..=256,
+            false,
+        ));
+        nodeui.add_item(TheNodeUIItem::IntEditSlider(
+            "actionGenHeight".into(),
+            "Height".into(),
+            "The height of the region in cells.".into(),
+            48,
+            8..=256,
+            false,
+        ));
+        nodeui.add_item(TheNodeUIItem::FloatEditSlider(
+            "actionGenCellSize".into(),
+            "Cell Size".into(),
+            "The size of a cell in map units.".into(),
+            1.0,
+            0.25..=8.0,
+            false,
+        ));
+        nodeui.add_item(TheNodeUIItem::IntEditSlider(
+            "actionGenRoomMin".into(),
+            "Room Min".into(),
+            "The minimum size of a dungeon room in cells.".into(),
+            4,
+            2..=32,
+            false,
+        ));
+        nodeui.add_item(TheNodeUIItem::IntEditSlider(
+            "actionGenRoomMax".into(),
+            "Room Max".into(),
+            "The maximum size of a dungeon room in cells.".into(),
+            9,
+            2..=64,
+            false,
+        ));
+        nodeui.add_item(TheNodeUIItem::IntEditSlider(
+            "actionGenRoomCount".into(),
+            "Rooms".into(),
+            "The number of rooms of the rooms layout.".into(),
+            10,
+            1..=100,
+            false,
+        ));
+        nodeui.add_item(TheNodeUIItem::IntEditSlider(
+            "actionGenCorridorWidth".into(),
+            "Corridor Width".into(),
+            "The width of dungeon corridors in cells.".into(),
+            1,
+            1..=8,
+            false,
+        ));
+        nodeui.add_item(TheNodeUIItem::IntEditSlider(
+            "actionGenStreetWidth".into(),
+            "Street Width".into(),
+            "The width of town streets in cells.".into(),
+            2,
+            1..=8,
+            false,
+        ));
+        nodeui.add_item(TheNodeUIItem::IntEditSlider(
+            "actionGenBlockSize".into(),
+            "Block Size".into(),
+            "The size of a town block between streets in cells.".into(),
+            10,
+            4..=64,
+            false,
+        ));
+        nodeui.add_item(TheNodeUIItem::IntEditSlider(
+            "actionGenLotMin".into(),
+            "Lot Min".into(),
+            "The minimum size of a house lot in cells.".into(),
+            4,
+            2..=32,
+            false,
+        ));
+        nodeui.add_item(TheNodeUIItem::FloatEditSlider(
+            "actionGenFloorHeight".into(),
+            "Floor Height".into(),
+            "The floor height of the generated sectors.".into(),
+            0.0,
+            -20.0..=20.0,
+            false,
+        ));
+        nodeui.add_item(TheNodeUIItem::FloatEditSlider(
+            "actionGenCeilingHeight".into(),
+            "Ceiling Height".into(),
+            "The ceiling and wall height of the generated sectors.".into(),
+            3.0,
+            0.5..=20.0,
+            false,
+        ));
+        nodeui.add_item(TheNodeUIItem::Text(
+            "actionGenFloorTags".into(),
+            "Floor Tags".into(),
+            "Floor tiles are picked from the tiles with any of these comma separated tags.".into(),
+            "floor".into(),
+            None,
+            false,
+        ));
+        nodeui.add_item(TheNodeUIItem::Text(
+            "actionGenWallTags".into(),
+            "Wall Tags".into(),
+            "The tags of the wall tiles.".into(),
+            "wall".into(),
+            None,
+            false,
+        ));
+        nodeui.add_item(TheNodeUIItem::Text(
+            "actionGenDoorTags".into(),
+            "Door Tags".into(),
+            "The tags of the door tiles.".into(),
+            "door".into(),
+            None,
+            false,
+        ));
+        nodeui.add_item(TheNodeUIItem::Text(
+            "actionGenCeilingTags".into(),
+            "Ceiling Tags".into(),
+            "The tags of the ceiling tiles. Leave empty for no ceilings.".into(),
+            "".into(),
+            None,
+            false,
+        ));
+        nodeui.add_item(TheNodeUIItem::Text(
+            "actionGenStreetTags".into(),
+            "Street Tags".into(),
+            "The tags of the town street tiles.".into(),
+            "street".into(),
+            None,
+            false,
+        ));
+        nodeui.add_item(TheNodeUIItem::Text(
+            "actionGenCharacters".into(),
+            "Characters".into(),
+            "Character templates and their weights, for example 'Orc:3, Rat:5'.".into(),
+            "".into(),
+            None,
+            false,
+        ));
+        nodeui.add_item(TheNodeUIItem::IntEditSlider(
+            "actionGenCharacterCount".into(),
+            "Character Count".into(),
+            "The number of characters to spawn. 0 keeps the existing character instances.".into(),
+            0,
+            0..=100,
+            false,
+        ));
+        nodeui.add_item(TheNodeUIItem::Text(
+            "actionGenItems".into(),
+            "Items".into(),
+            "Item templates and their weights, for example 'Chest:1, Torch:4'.".into(),
+            "".into(),
+            None,
+            false,
+        ));
+        nodeui.add_item(TheNodeUIItem::IntEditSlider(
+            "actionGenItemCount".into(),
+            "Item Count".into(),
+            "The number of items to spawn. 0 keeps the existing item instances.".into(),
+            0,
+            0..=100,
+            false,
+        ));
+
+        let item = TheNodeUIItem::Markdown(
+            "desc".into(),
+            "Replaces the geometry of the current region with a generated dungeon or town and optionally spawns character and item instances. Tiles are picked by their tags.\n\nThe parameters are read from the `[generator]` section of the region config if present. Set `on_start = true` in this section to generate a new region every time the game starts. Scripts generate the region again while the game runs with `generate_region(\"<region>\")` of the `generator` library.".into(),
+        );
+        nodeui.add_item(item);
+
+        Self {
+            id: TheId::named("Generate Region"),
+            nodeui,
+        }
+    }
+
+    fn id(&self) -> TheId {
+        self.id.clone()
+    }
+
+    fn info(&self) -> &'static str {
+        "Generates a dungeon or town for the current region."
+    }
+
+    fn role(&self) -> ActionRole {
+        ActionRole::Editor
+    }
+
+    fn is_applicable(&self, _map: &Map, _ctx: &mut TheContext, server_ctx: &ServerContext) -> bool {
+        server_ctx.get_map_context() == MapContext::Region && server_ctx.editing_surface.is_none()
+    }
+
+    fn load_params_project(&mut self, project: &Project, server_ctx: &mut ServerContext) {
+        let Some(generator) = project
+            .get_region_ctx(server_ctx)
+            .and_then(|region| RegionGenerator::from_config(&region.config))
+        else {
+            return;
+        };
+
+        self.nodeui.set_i32_value(
+            "actionGenMode",
+            (generator.mode == GeneratorMode::Town) as i32,
+        );
+        self.nodeui.set_i32_value(
+            "actionGenLayout",
+            (generator.layout == DungeonLayout::Rooms) as i32,
+        );
+        self.nodeui
+            .set_i32_value("actionGenSeed", generator.seed.min(99999) as i32);
+        self.nodeui.set_i32_value("actionGenWidth", generator.width);
+        self.nodeui
+            .set_i32_value("actionGenHeight", generator.height);
+        self.nodeui
+            .set_f32_value("actionGenCellSize", generator.cell_size);
+        self.nodeui
+            .set_i32_value("actionGenRoomMin", generator.room_min);
+        self.nodeui
+            .set_i32_value("actionGenRoomMax", generator.room_max);
+        self.nodeui
+            .set_i32_value("actionGenRoomCount", generator.room_count);
+        self.nodeui
+            .set_i32_value("actionGenCorridorWidth", generator.corridor_width);
+        self.nodeui
+            .set_i32_value("actionGenStreetWidth", generator.street_width);
+        self.nodeui
+            .set_i32_value("actionGenBlockSize", generator.block_size);
+        self.nodeui
+            .set_i32_value("actionGenLotMin", generator.lot_min);
+        self.nodeui
+            .set_f32_value("actionGenFloorHeight", generator.floor_height);
+        self.nodeui
+            .set_f32_value("actionGenCeilingHeight", generator.ceiling_height);
+        self.set_text("actionGenFloorTags", generator.floor_tags.clone());
+        self.set_text("actionGenWallTags", generator.wall_tags.clone());
+        self.set_text("actionGenDoorTags", generator.door_tags.clone());
+        self.set_text("actionGenCeilingTags", generator.ceiling_tags.clone());
+        self.set_text("actionGenStreetTags", generator.street_tags.clone());
+        self.set_text(
+            "actionGenCharacters",
+            RegionGenerator::spawn_list_to_string(&generator.characters),
+        );
+        self.nodeui
+            .set_i32_value("actionGenCharacterCount", generator.character_count);
+        self.set_text(
+            "actionGenItems",
+            RegionGenerator::spawn_list_to_string(&generator.items),
+        );
+        self.nodeui
+            .set_i32_value("actionGenItemCount", generator.item_count);
+    }
+
+    fn apply_project(
+        &self,
+        project: &mut Project,
+        ui: &mut TheUI,
+        ctx: &mut TheContext,
+        server_ctx: &mut ServerContext,
+    ) {
+        let Some(prev) = project.get_region_ctx(server_ctx).cloned() else {
+            return;
+        };
+
+        let mut region = prev.clone();
+        self.generator().generate(
+            &mut region,
+            project,
+            &crate::utils::embedded_instance_source("python/instcharacter.py"),
+            &crate::utils::embedded_instance_source("python/institem.py"),
+        );
+
+        let atom = ProjectUndoAtom::GenerateRegion(Box::new(prev), Box::new(region));
+        atom.redo(project, ui, ctx, server_ctx);
+        UNDOMANAGER.write().unwrap().add_undo(atom, ctx);
+    }
+
+    fn params(&self) -> TheNodeUI {
+        self.nodeui.clone()
+    }
+
+    fn handle_event(
+        &mut self,
+        event: &TheEvent,
+        _project: &mut Project,
+        _ui: &mut TheUI,
+        _ctx: &mut TheContext,
+        _server_ctx: &mut ServerContext,
+    ) -> bool {
+        self.nodeui.handle_event(event)
+    }
+}
@@ -0,0 +1,234 @@
+use crate::actions::gen_geometry::*;
+use crate::prelude::*;
+use vek::Vec2;
+
+pub struct GenerateStairs {
+    id: TheId,
+    nodeui: TheNodeUI,
+}
+
+impl GenerateStairs {
+    /// Creates the stairs around the origin, one sector per step.
+    fn generate(&self) -> Map {
+        let mut map = preview_map();
+
+        let spiral = self.nodeui.get_i32_value("actionStairsMode").unwrap_or(0) == 1;
+        let steps = self
+            .nodeui
+            .get_i32_value("actionStairsSteps")
+            .unwrap_or(8)
+            .max(1) as usize;
+        let width = self
+            .nodeui
+            .get_f32_value("actionStairsWidth")
+            .unwrap_or(1.0)
+            .max(0.01);
+        let run = self
+            .nodeui
+            .get_f32_value("actionStairsRun")
+            .unwrap_or(0.5)
+            .max(0.01);
+        let from = self.nodeui.get_f32_value("actionStairsFrom").unwrap_or(0.0);
+        let to = self.nodeui.get_f32_value("actionStairsTo").unwrap_or(2.0);
+        let radius = self
+            .nodeui
+            .get_f32_value("actionStairsRadius")
+            .unwrap_or(0.5)
+            .max(0.05);
+        let turn = self
+            .nodeui
+            .get_f32_value("actionStairsTurn")
+            .unwrap_or(270.0)
+            .to_radians();
+        let headroom = self
+            .nodeui
+            .get_f32_value("actionStairsHeadroom")
+            .unwrap_or(2.0);
+        let solid = self
+            .nodeui
+            .get_bool_value("actionStairsSolid")
+            .unwrap_or(true);
+
+        for i in 0..steps {
+            let height = from + (to - from) * (i + 1) as f32 / steps as f32;
+
+            let points = if spiral {
+                let a0 = turn * i as f32 / steps as f32;
+                let a1 = turn * (i + 1) as f32 / steps as f32;
+                let segments = ((a1 - a0).abs() / (std::f32::consts::PI / 16.0)).ceil() as usize;
+
+                let mut points = arc_points(Vec2::zero(), radius + width, a0, a1, segments);
+                let mut inner = arc_points(Vec2::zero(), radius, a0, a1, segments);
+                inner.reverse();
+                points.extend(inner);
+                if turn < 0.0 {
+                    points.reverse();
+                }
+                points
+            } else {
+                let y0 = -run * steps as f32 * 0.5 + run * i as f32;
+                let y1 = y0 + run;
+                vec![
+                    Vec2::new(-width * 0.5, y0),
+                    Vec2::new(width * 0.5, y0),
+                    Vec2::new(width * 0.5, y1),
+                    Vec2::new(-width * 0.5, y1),
+                ]
+            };
+
+            if let Some(sector_id) =
+                add_polygon_sector(&mut map, &points, height, height + headroom)
+            {
+                if solid && height != from {
+                    set_extrusion(&mut map, sector_id, from - height);
+                }
+            }
+        }
+
+        map
+    }
+}
+
+impl Action for GenerateStairs {
+    fn new() -> Self
+    where
+        Self: Sized,
+    {
+        let mut nodeui: TheNodeUI = TheNodeUI::default();
+
+        nodeui.add_item(TheNodeUIItem::Selector(
+            "actionStairsMode".into(),
+            "Mode".into(),
+            "Straight stairs or stairs winding around a center.".into(),
+            vec!["Straight".to_string(), "Spiral".to_string()],
+            0,
+        ));
+        nodeui.add_item(TheNodeUIItem::IntEditSlider(
+            "actionStairsSteps".into(),
+            "Steps".into(),
+            "The number of steps.".into(),
+            8,
+            1..=64,
+            false,
+        ));
+        nodeui.add_item(TheNodeUIItem::FloatEditSlider(
+            "actionStairsWidth".into(),
+            "Width".into(),
+            "The width of the steps.".into(),
+            1.0,
+            0.1..=10.0,
+            false,
+        ));
+        nodeui.add_item(TheNodeUIItem::FloatEditSlider(
+            "actionStairsRun".into(),
+            "Step Depth".into(),
+            "The depth of a single step of straight stairs.".into(),
+            0.5,
+            0.05..=4.0,
+            false,
+        ));
+        nodeui.add_item(TheNodeUIItem::FloatEditSlider(
+            "actionStairsFrom".into(),
+            "From Height".into(),
+            "The floor height at the bottom of the stairs.".into(),
+            0.0,
+            -20.0..=20.0,
+            false,
+        ));
+        nodeui.add_item(TheNodeUIItem::FloatEditSlider(
+            "actionStairsTo".into(),
+            "To Height".into(),
+            "The floor height of the last step.".into(),
+            2.0,
+            -20.0..=20.0,
+            false,
+        ));
+        nodeui.add_item(TheNodeUIItem::FloatEditSlider(
+            "actionStairsRadius".into(),
+            "Inner Radius".into(),
+            "The radius of the center of spiral stairs.".into(),
+            0.5,
+            0.05..=10.0,
+            false,
+        ));
+        nodeui.add_item(TheNodeUIItem::FloatEditSlider(
+            "actionStairsTurn".into(),
+            "Turn".into(),
+            "The angle in degrees spiral stairs turn around their center. Negative values turn clockwise.".into(),
+            270.0,
+            -360.0..=360.0,
+            false,
+        ));
+        nodeui.add_item(TheNodeUIItem::FloatEditSlider(
+            "actionStairsHeadroom".into(),
+            "Headroom".into(),
+            "The ceiling height above each step.".into(),
+            2.0,
+            0.0..=20.0,
+            false,
+        ));
+        nodeui.add_item(TheNodeUIItem::Checkbox(
+            "actionStairsSolid".into(),
+            "Solid".into(),
+            "Extrude the steps down to the bottom height.".into(),
+            true,
+        ));
+
+        let item = TheNodeUIItem::Markdown(
+            "desc".into(),
+            "Generates straight or spiral stairs between two floor heights, one sector per step. Apply to preview the stairs under the cursor and click into the map to place them. Changing the parameters updates the preview.".into(),
+        );
+        nodeui.add_item(item);
+
+        Self {
+            id: TheId::named("Generate Stairs"),
+            nodeui,
+        }
+    }
+
+    fn id(&self) -> TheId {
+        self.id.clone()
+    }
+
+    fn info(&self) -> &'static str {
+        "Generates straight or spiral stairs between two floor heights."
+    }
+
+    fn role(&self) -> ActionRole {
+        ActionRole::Editor
+    }
+
+    fn is_applicable(&self, _map: &Map, _ctx: &mut TheContext, server_ctx: &ServerContext) -> bool {
+        server_ctx.get_map_context() == MapContext::Region && server_ctx.editing_surface.is_none()
+    }
+
+    fn apply(
+        &self,
+        _map: &mut Map,
+        _ui: &mut TheUI,
+        _ctx: &mut TheContext,
+        server_ctx: &mut ServerContext,
+    ) -> Option<RegionUndoAtom> {
+        activate_preview(self.generate(), server_ctx);
+        None
+    }
+
+    fn params(&self) -> TheNodeUI {
+        self.nodeui.clone()
+    }
+
+    fn handle_event(
+        &mut self,
+        event: &TheEvent,
+        _project: &mut Project,
+        _ui: &mut TheUI,
+        _ctx: &mut TheContext,
+        server_ctx: &mut ServerContext,
+    ) -> bool {
+        let changed = self.nodeui.handle_event(event);
+        if changed {
+            refresh_preview(self.generate(), server_ctx);
+        }
+        changed
+    }
+}
@@ -1,11 +1,12 @@
 use crate::prelude::*;
+use rusterix::Value;
 
 /// Generate a ring of “stone” sectors around a door opening (profile UV).
 /// - Operates in the active surface's profile map (editing_surface must be Some).
 /// - Expects exactly one selected sector **in the profile** (the inner door loop).
 pub struct GenerateStoneTrim {
     id: TheId,
-    ui: TheNodeUI,
+    nodeui: TheNodeUI,
 }
 
 impl Action for GenerateStoneTrim {
@@ -13,10 +14,10 @@ impl Action for GenerateStoneTrim {
     where
         Self: Sized,
     {
-        let mut ui = TheNodeUI::default();
+        let mut nodeui = TheNodeUI::default();
 
         // Ring / band
-        ui.add_item(TheNodeUIItem::FloatEditSlider(
+        nodeui.add_item(TheNodeUIItem::FloatEditSlider(
             "actionTrimCourseWidth".into(),
             "Course width".into(),
             "Thickness of the stone band (UV units).".into(),
@@ -24,7 +25,7 @@ impl Action for GenerateStoneTrim {
             0.01..=2.0,
             false,
         ));
-        ui.add_item(TheNodeUIItem::FloatEditSlider(
+        nodeui.add_item(TheNodeUIItem::FloatEditSlider(
             "actionTrimJointWidth".into(),
             "Joint width".into(),
             "Gap between stones along the path (visual or geometric).".into(),
@@ -32,7 +33,7 @@ impl Action for GenerateStoneTrim {
             0.0..=0.1,
             false,
         ));
-        ui.add_item(TheNodeUIItem::FloatEditSlider(
+        nodeui.add_item(TheNodeUIItem::FloatEditSlider(
             "actionTrimClearance".into(),
             "Clearance".into(),
             "Gap from opening to stones (UV units).".into(),
@@ -42,7 +43,7 @@ impl Action for GenerateStoneTrim {
         ));
 
         // Stone sizing
-        ui.add_item(TheNodeUIItem::FloatEditSlider(
+        nodeui.add_item(TheNodeUIItem::FloatEditSlider(
             "actionTrimBlockLenMin".into(),
             "Block length min".into(),
             "Minimum block length for straight jamb segments.".into(),
@@ -50,7 +51,7 @@ impl Action for GenerateStoneTrim {
             0.10..=2.0,
             false,
         ));
-        ui.add_item(TheNodeUIItem::FloatEditSlider(
+        nodeui.add_item(TheNodeUIItem::FloatEditSlider(
             "actionTrimBlockLenMax".into(),
             "Block length max".into(),
             "Maximum block length for straight jamb segments.".into(),
@@ -58,17 +59,9 @@ impl Action for GenerateStoneTrim {
             0.10..=3.0,
             false,
         ));
-        ui.add_item(TheNodeUIItem::FloatEditSlider(
-            "actionTrimKeystoneWidth".into(),
-            "Keystone width".into(),
-            "Width of the central arch keystone (UV).".into(),
-            0.50,
-            0.10..=2.0,
-            false,
-        ));
 
         // Finish / detailing
-        ui.add_item(TheNodeUIItem::FloatEditSlider(
+        nodeui.add_item(TheNodeUIItem::FloatEditSlider(
             "actionTrimBevel".into(),
             "Bevel".into(),
             "Bevel size to apply per stone sector (profile op).".into(),
@@ -76,7 +69,7 @@ impl Action for GenerateStoneTrim {
             0.0..=0.2,
             false,
         ));
-        ui.add_item(TheNodeUIItem::FloatEditSlider(
+        nodeui.add_item(TheNodeUIItem::FloatEditSlider(
             "actionTrimDepth".into(),
             "Depth".into(),
             "Positive=relief (out), Negative=recess (in).".into(),
@@ -86,7 +79,7 @@ impl Action for GenerateStoneTrim {
         ));
 
         // Distribution randomness
-        ui.add_item(TheNodeUIItem::FloatEditSlider(
+        nodeui.add_item(TheNodeUIItem::FloatEditSlider(
             "actionTrimJitterPct".into(),
             "Jitter %".into(),
             "Random variation percentage for block lengths.".into(),
@@ -94,7 +87,7 @@ impl Action for GenerateStoneTrim {
             0.0..=0.25,
             false,
         ));
-        ui.add_item(TheNodeUIItem::IntEditSlider(
+        nodeui.add_item(TheNodeUIItem::IntEditSlider(
             "actionTrimSeed".into(),
             "Seed".into(),
             "Random seed for reproducible results.".into(),
@@ -103,29 +96,15 @@ impl Action for GenerateStoneTrim {
             false,
         ));
 
-        // Behavior toggles
-        ui.add_item(TheNodeUIItem::Checkbox(
-            "actionTrimFollowArch".into(),
-            "Follow arch curve".into(),
-            "Orient arch voussoirs orthogonal to the arch tangent.".into(),
-            true,
-        ));
-        ui.add_item(TheNodeUIItem::Checkbox(
-            "actionTrimEmitGaps".into(),
-            "Emit geometric joints".into(),
-            "If off, keep stones tight and let the material draw joints.".into(),
-            false,
-        ));
-
-        ui.add_item(TheNodeUIItem::Markdown(
+        nodeui.add_item(TheNodeUIItem::Markdown(
             "desc".into(),
-            "Generate a ring of stone blocks (jamb + arch voussoirs) around the selected **door opening** in the **active surface profile**.\n\
+            "Generate a ring of stone blocks around the selected **door opening** in the **active surface profile**.\n\
              The tool writes sectors into the profile map; your existing triangulation + relief/bevel ops handle the mesh.".into(),
         ));
 
         Self {
             id: TheId::named("Generate Stone Trim"),
-            ui,
+            nodeui,
         }
     }
 
@@ -136,14 +115,16 @@ impl Action for GenerateStoneTrim {
         "Create stone courses around a door opening in the current surface profile."
     }
     fn role(&self) -> ActionRole {
-        ActionRole::Geometry
+        ActionRole::Editor
     }
     fn accel(&self) -> Option<TheAccelerator> {
         None
     }
 
     fn is_applicable(&self, map: &Map, _ctx: &mut TheContext, server_ctx: &ServerContext) -> bool {
-        map.selected_sectors.len() == 1 && server_ctx.editor_view_mode == EditorViewMode::D2
+        map.selected_sectors.len() == 1
+            && server_ctx.editor_view_mode == EditorViewMode::D2
+            && server_ctx.editing_surface.is_some()
     }
 
     fn apply(
@@ -161,118 +142,150 @@ impl Action for GenerateStoneTrim {
         // Snapshot for undo
         let prev = map.clone();
 
-        // Exactly one selected sector (either a main-map sector or a profile sector)
-        let selected_sector = {
-            if map.selected_sectors.len() != 1 {
-                return None;
-            }
-            map.selected_sectors[0]
-        };
+        // Exactly one selected sector
+        if map.selected_sectors.len() != 1 {
+            return None;
+        }
 
         // The current `map` **is** the profile map in D2 profile edit.
         // Use the single selected sector on this map as the inner door loop.
-        let inner_sector_id = selected_sector;
-        let profile: &mut Map = map;
-
-        // Read params
-        let course_w = self
-            .ui
-            .get_f32_value("actionTrimCourseWidth")
-            .unwrap_or(0.25);
-        let joint_w = self
-            .ui
-            .get_f32_value("actionTrimJointWidth")
-            .unwrap_or(0.0)
-            .max(0.0);
-        let len_min = self
-            .ui
+        let inner_loop = extract_sector_loop_xy(map, map.selected_sectors[0])?;
+        let trim = StoneTrim::from_nodeui(&self.nodeui);
+
+        if trim.emit(map, &inner_loop) > 0 {
+            Some(RegionUndoAtom::MapEdit(
+                Box::new(prev),
+                Box::new(map.clone()),
+            ))
+        } else {
+            None
+        }
+    }
+
+    fn params(&self) -> TheNodeUI {
+        self.nodeui.clone()
+    }
+
+    fn handle_event(
+        &mut self,
+        event: &TheEvent,
+        _project: &mut Project,
+        _ui: &mut TheUI,
+        _ctx: &mut TheContext,
+        _server_ctx: &mut ServerContext,
+    ) -> bool {
+        self.nodeui.handle_event(event)
+    }
+}
+
+/// The parameters of a ring of stone blocks around an opening. Used by the
+/// stone trim action and by the opening generator.
+#[derive(Clone, Debug)]
+pub struct StoneTrim {
+    pub course_width: f32,
+    pub joint_width: f32,
+    pub clearance: f32,
+    pub block_len_min: f32,
+    pub block_len_max: f32,
+    pub bevel: f32,
+    pub depth: f32,
+    pub jitter_pct: f32,
+    pub seed: i32,
+    /// Leaves the first edge of the loop without stones, e.g. a threshold.
+    pub open_first_edge: bool,
+}
+
+impl Default for StoneTrim {
+    fn default() -> Self {
+        Self {
+            course_width: 0.25,
+            joint_width: 0.0,
+            clearance: 0.05,
+            block_len_min: 0.40,
+            block_len_max: 0.80,
+            bevel: 0.02,
+            depth: 0.03,
+            jitter_pct: 0.0,
+            seed: 1337,
+            open_first_edge: false,
+        }
+    }
+}
+
+impl StoneTrim {
+    fn from_nodeui(nodeui: &TheNodeUI) -> Self {
+        let d = Self::default();
+        let block_len_min = nodeui
             .get_f32_value("actionTrimBlockLenMin")
-            .unwrap_or(0.40);
-        let len_max = self
-            .ui
-            .get_f32_value("actionTrimBlockLenMax")
-            .unwrap_or(0.80)
-            .max(len_min);
-        let keystone_w = self
-            .ui
-            .get_f32_value("actionTrimKeystoneWidth")
-            .unwrap_or(0.50);
-        let bevel = self
-            .ui
-            .get_f32_value("actionTrimBevel")
-            .unwrap_or(0.02)
-            .max(0.0);
-        let depth = self.ui.get_f32_value("actionTrimDepth").unwrap_or(0.03);
-        let jitter_pct = self
-            .ui
-            .get_f32_value("actionTrimJitterPct")
-            .unwrap_or(0.0)
-            .clamp(0.0, 0.25);
-        let seed = self.ui.get_i32_value("actionTrimSeed").unwrap_or(1337);
-        let follow_arch = self
-            .ui
-            .get_bool_value("actionTrimFollowArch")
-            .unwrap_or(true);
-        let emit_gaps = self
-            .ui
-            .get_bool_value("actionTrimEmitGaps")
-            .unwrap_or(false);
-
-        // 1) Pull inner loop (door) from the profile map (XY)
-        let inner_loop = extract_sector_loop_xy(profile, inner_sector_id)?;
+            .unwrap_or(d.block_len_min);
+        Self {
+            course_width: nodeui
+                .get_f32_value("actionTrimCourseWidth")
+                .unwrap_or(d.course_width),
+            joint_width: nodeui
+                .get_f32_value("actionTrimJointWidth")
+                .unwrap_or(d.joint_width)
+                .max(0.0),
+            clearance: nodeui
+                .get_f32_value("actionTrimClearance")
+                .unwrap_or(d.clearance)
+                .max(0.0),
+            block_len_min,
+            block_len_max: nodeui
+                .get_f32_value("actionTrimBlockLenMax")
+                .unwrap_or(d.block_len_max)
+                .max(block_len_min),
+            bevel: nodeui
+                .get_f32_value("actionTrimBevel")
+                .unwrap_or(d.bevel)
+                .max(0.0),
+            depth: nodeui.get_f32_value("actionTrimDepth").unwrap_or(d.depth),
+            jitter_pct: nodeui
+                .get_f32_value("actionTrimJitterPct")
+                .unwrap_or(d.jitter_pct)
+                .clamp(0.0, 0.25),
+            seed: nodeui.get_i32_value("actionTrimSeed").unwrap_or(d.seed),
+            open_first_edge: false,
+        }
+    }
+
+    /// Emits the stone blocks around the inner loop as profile sectors.
+    /// Returns the number of created blocks.
+    pub fn emit(&self, profile: &mut Map, inner_loop: &[V2<f32>]) -> usize {
         if inner_loop.len() < 3 {
-            return None;
+            return 0;
         }
 
-        // 2) Build a **centerline** offset outward by (clearance + half thickness)
+        // Build a **centerline** offset outward by (clearance + half thickness)
         // Stones are standalone rectangles centered on this curve; they do NOT touch the inner loop.
-        let clearance = self
-            .ui
-            .get_f32_value("actionTrimClearance")
-            .unwrap_or(0.05)
-            .max(0.0);
-        let half_thick = 0.5 * course_w;
-        let centerline = offset_polyline_miter(&inner_loop, clearance + half_thick);
+        let half_thick = 0.5 * self.course_width;
+        let centerline = offset_polyline_miter(inner_loop, self.clearance + half_thick);
         if centerline.len() != inner_loop.len() {
-            return None;
+            return 0;
         }
 
-        // 3) Plan stone rectangles along the centerline
+        // Plan stone rectangles along the centerline
         let rects = plan_stone_rects(
-            &inner_loop,
+            inner_loop,
             &centerline,
-            len_min,
-            len_max,
-            joint_w,
-            jitter_pct,
-            seed,
+            self.block_len_min,
+            self.block_len_max,
+            self.joint_width,
+            self.jitter_pct,
+            self.seed,
             half_thick,
+            self.open_first_edge,
         );
 
-        // 4) Emit each rectangle as an independent sector (with a small outward epsilon)
-        let mut made_any = false;
+        // Emit each rectangle as an independent sector
+        let mut count = 0;
         for rect in rects.iter() {
             if let Some(sector_id) = emit_rect_sector(profile, rect) {
-                set_block_ops(profile, sector_id, depth, bevel);
-                made_any = true;
+                set_block_ops(profile, sector_id, self.depth, self.bevel);
+                count += 1;
             }
         }
-
-        if made_any {
-            Some(RegionUndoAtom::MapEdit(
-                Box::new(prev),
-                Box::new(map.clone()),
-            ))
-        } else {
-            None
-        }
-    }
-
-    fn params(&self) -> TheNodeUI {
-        self.ui.clone()
-    }
-    fn handle_event(&mut self, ev: &TheEvent) -> bool {
-        self.ui.handle_event(ev)
+        count
     }
 }
 
@@ -379,6 +392,7 @@ fn lerp(a: f32, b: f32, t: f32) -> f32 {
 }
 
 /// Plan rectangles centered on the outward offset curve. Each rect has length along tangent and thickness across normal.
+#[allow(clippy::too_many_arguments)]
 fn plan_stone_rects(
     inner: &[V2<f32>],
     center: &[V2<f32>],
@@ -388,6 +402,7 @@ fn plan_stone_rects(
     jitter_pct: f32,
     seed: i32,
     half_thick: f32,
+    open_first_edge: bool,
 ) -> Vec<StoneRect> {
     use rand::{Rng, SeedableRng, rngs::StdRng};
     let mut rng = StdRng::seed_from_u64(seed as u64);
@@ -402,6 +417,9 @@ fn plan_stone_rects(
     let ccw = polygon_signed_area(inner) > 0.0;
 
     for i in 0..n {
+        if open_first_edge && i == 0 {
+            continue;
+        }
         let ia = i;
         let ib = (i + 1) % n;
         let ca = center[ia];
@@ -418,12 +436,12 @@ fn plan_stone_rects(
 
         // Per-segment target with jitter
         let jitter = if jitter_pct > 0.0 {
-            1.0 + rng.gen_range(-jitter_pct..=jitter_pct)
+            1.0 + rng.random_range(-jitter_pct..=jitter_pct)
         } else {
             1.0
         };
         let mut block_len = (target * jitter).clamp(len_min, len_max);
-        let mut count = (seg_len / block_len).round().max(1.0) as usize;
+        let count = (seg_len / block_len).round().max(1.0) as usize;
         block_len = seg_len / (count as f32);
 
         let half_len = 0.5 * (block_len - joint_w).max(0.0);
@@ -475,18 +493,15 @@ fn emit_rect_sector(profile: &mut Map, r: &StoneRect) -> Option<u32> {
 /// Attach per-block relief/bevel properties (profile ops) to the sector.
 fn set_block_ops(profile: &mut Map, sector_id: u32, depth: f32, bevel: f32) {
     if let Some(sec) = profile.find_sector_mut(sector_id) {
-        // Generic properties — adapt names to your builder’s expectations if needed
-        if depth > 0.0 {
-            sec.properties
-                .set("profile_op", Value::Str("relief".into()));
-            sec.properties.set("relief_depth", Value::Float(depth));
-        } else if depth < 0.0 {
-            sec.properties
-                .set("profile_op", Value::Str("recess".into()));
-            sec.properties.set("recess_depth", Value::Float(-depth));
+        // Same properties as the Relief (1) and Recess (2) actions
+        if depth >= 0.0 {
+            sec.properties.set("profile_op", Value::Int(1));
+            sec.properties.set("profile_amount", Value::Float(depth));
         } else {
-            sec.properties.set("profile_op", Value::Str("inset".into()));
+            sec.properties.set("profile_op", Value::Int(2));
+            sec.properties.set("profile_amount", Value::Float(-depth));
         }
+        sec.properties.set("profile_target", Value::Int(1));
         if bevel > 0.0 {
             sec.properties.set("bevel", Value::Float(bevel));
         }
@@ -1,6 +1,7 @@
 pub use crate::prelude::*;
 
 pub mod add_arch;
+pub mod array_selection;
 // pub mod add_shader_library;
 // pub mod apply_shader;
 pub mod apply_tile;
@@ -11,13 +12,20 @@ pub mod create_linedef;
 pub mod create_sector;
 pub mod edit_linedef;
 pub mod edit_maximize;
+pub mod edit_prop;
 pub mod edit_sector;
+pub mod edit_tile_meta;
 pub mod edit_vertex;
 pub mod extrude_linedef;
 pub mod extrude_sector;
-// pub mod gen_stone_trim;
-pub mod edit_tile_meta;
 pub mod firstp_camera;
+pub mod gen_geometry;
+pub mod gen_opening;
+pub mod gen_pillar;
+pub mod gen_ramp;
+pub mod gen_region;
+pub mod gen_stairs;
+pub mod gen_stone_trim;
 pub mod iso_camera;
 pub mod new_tile;
 // pub mod load_shader;
@@ -27,12 +35,21 @@ pub mod duplicate_tile;
 pub mod editing_camera;
 pub mod minimize;
 pub mod orbit_camera;
+pub mod place_prop;
 pub mod recess;
 pub mod relief;
+pub mod sector_boolean;
+pub mod sector_intersect;
+pub mod sector_subtract;
+pub mod sector_union;
+pub mod selection_transform;
 pub mod set_editing_surface;
+pub mod sound_emitter;
 pub mod split;
 pub mod toggle_editing_geo;
+pub mod toggle_navgrid;
 pub mod toggle_rect_geo;
+pub mod transform_selection;
 
 #[derive(PartialEq)]
 pub enum ActionRole {
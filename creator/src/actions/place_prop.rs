@@ -0,0 +1,208 @@
+use crate::actions::gen_geometry::*;
+use crate::prelude::*;
+use vek::Vec2;
+
+pub struct PlaceProp {
+    id: TheId,
+    nodeui: TheNodeUI,
+    /// The mesh assets in the order of the selector.
+    meshes: Vec<Uuid>,
+}
+
+impl PlaceProp {
+    fn transform(&self) -> PropTransform {
+        PropTransform {
+            rotation: self
+                .nodeui
+                .get_f32_value("actionPropRotation")
+                .unwrap_or(0.0),
+            scale: self.nodeui.get_f32_value("actionPropScale").unwrap_or(1.0),
+            elevation: self
+                .nodeui
+                .get_f32_value("actionPropElevation")
+                .unwrap_or(0.0),
+            collision: prop_collision(
+                self.nodeui
+                    .get_i32_value("actionPropCollision")
+                    .unwrap_or(0),
+            ),
+        }
+    }
+
+    /// The prop to place and the preview of its mesh around the origin.
+    fn generate(&self, project: &Project) -> Option<(PropInstance, Map)> {
+        let index = self.nodeui.get_i32_value("actionPropMesh").unwrap_or(0);
+        let asset_id = *self.meshes.get(index.max(0) as usize)?;
+        let AssetBuffer::Mesh(mesh) = &project.assets.get(&asset_id)?.buffer else {
+            return None;
+        };
+
+        let prop = PropInstance::new(asset_id, Vec2::zero(), self.transform());
+        let mut map = preview_map();
+        mesh.add_render_geometry(&mut map, &prop);
+        Some((prop, map))
+    }
+}
+
+/// The collision shape of the index of the collision selector.
+pub fn prop_collision(index: i32) -> PropCollision {
+    match index {
+        1 => PropCollision::Box,
+        2 => PropCollision::Cylinder,
+        _ => PropCollision::None,
+    }
+}
+
+/// The collision selector, shared with the Edit Prop action.
+pub fn prop_collision_item(collision: PropCollision) -> TheNodeUIItem {
+    TheNodeUIItem::Selector(
+        "actionPropCollision".into(),
+        "Collision".into(),
+        "The shape which blocks movement, independent of the triangles of the mesh.".into(),
+        vec!["None".into(), "Box".into(), "Cylinder".into()],
+        match collision {
+            PropCollision::None => 0,
+            PropCollision::Box => 1,
+            PropCollision::Cylinder => 2,
+        },
+    )
+}
+
+impl Action for PlaceProp {
+    fn new() -> Self
+    where
+        Self: Sized,
+    {
+        let mut nodeui: TheNodeUI = TheNodeUI::default();
+
+        nodeui.add_item(TheNodeUIItem::Selector(
+            "actionPropMesh".into(),
+            "Mesh".into(),
+            "The mesh asset to place.".into(),
+            vec![],
+            0,
+        ));
+        nodeui.add_item(TheNodeUIItem::FloatEditSlider(
+            "actionPropRotation".into(),
+            "Rotation".into(),
+            "The rotation around the vertical axis in degrees.".into(),
+            0.0,
+            0.0..=360.0,
+            false,
+        ));
+        nodeui.add_item(TheNodeUIItem::FloatEditSlider(
+            "actionPropScale".into(),
+            "Scale".into(),
+            "The uniform scale of the mesh.".into(),
+            1.0,
+            0.01..=100.0,
+            false,
+        ));
+        nodeui.add_item(TheNodeUIItem::FloatEditSlider(
+            "actionPropElevation".into(),
+            "Elevation".into(),
+            "The height of the base of the mesh.".into(),
+            0.0,
+            -20.0..=20.0,
+            false,
+        ));
+        nodeui.add_item(prop_collision_item(PropCollision::None));
+
+        let item = TheNodeUIItem::Markdown(
+            "desc".into(),
+            "Places a static mesh asset (imported from glTF or OBJ) as a prop. Apply to preview the prop under the cursor and click into the map to place it. Props are instances of the region like characters and items, they do not change the geometry of the map. Textures are baked into the colors of the triangles.".into(),
+        );
+        nodeui.add_item(item);
+
+        Self {
+            id: TheId::named("Place Prop"),
+            nodeui,
+            meshes: vec![],
+        }
+    }
+
+    fn id(&self) -> TheId {
+        self.id.clone()
+    }
+
+    fn info(&self) -> &'static str {
+        "Places a static mesh asset as a prop."
+    }
+
+    fn role(&self) -> ActionRole {
+        ActionRole::Editor
+    }
+
+    fn is_applicable(&self, _map: &Map, _ctx: &mut TheContext, server_ctx: &ServerContext) -> bool {
+        server_ctx.get_map_context() == MapContext::Region && server_ctx.editing_surface.is_none()
+    }
+
+    fn load_params_project(&mut self, project: &Project, _server_ctx: &mut ServerContext) {
+        let mut names = vec![];
+        self.meshes.clear();
+        for (id, name) in project.sorted_assets_list() {
+            if let Some(Asset {
+                buffer: AssetBuffer::Mesh(_),
+                ..
+            }) = project.assets.get(&id)
+            {
+                self.meshes.push(id);
+                names.push(name);
+            }
+        }
+
+        if let Some(TheNodeUIItem::Selector(_, _, _, options, value)) =
+            self.nodeui.get_item_mut("actionPropMesh")
+        {
+            *value = (*value).min(names.len().saturating_sub(1) as i32).max(0);
+            *options = names;
+        }
+    }
+
+    fn apply_project(
+        &self,
+        project: &mut Project,
+        _ui: &mut TheUI,
+        ctx: &mut TheContext,
+        server_ctx: &mut ServerContext,
+    ) {
+        match self.generate(project) {
+            Some((prop, map)) => {
+                activate_preview(map, server_ctx);
+                server_ctx.paste_prop = Some(prop);
+                ctx.ui.send(TheEvent::SetStatusText(
+                    TheId::empty(),
+                    "Click to place the prop, Escape to cancel.".to_string(),
+                ));
+            }
+            None => {
+                ctx.ui.send(TheEvent::SetStatusText(
+                    TheId::empty(),
+                    "Add a mesh asset to the project to place props.".to_string(),
+                ));
+            }
+        }
+    }
+
+    fn params(&self) -> TheNodeUI {
+        self.nodeui.clone()
+    }
+
+    fn handle_event(
+        &mut self,
+        event: &TheEvent,
+        project: &mut Project,
+        _ui: &mut TheUI,
+        _ctx: &mut TheContext,
+        server_ctx: &mut ServerContext,
+    ) -> bool {
+        let changed = self.nodeui.handle_event(event);
+        if changed && server_ctx.paste_prop.is_some() {
+            if let Some((prop, map)) = self.generate(project) {
+                refresh_preview(map, server_ctx);
+                server_ctx.paste_prop = Some(prop);
+            }
+        }
+        changed
+    }
+}
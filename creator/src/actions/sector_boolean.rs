@@ -0,0 +1,761 @@
+use crate::prelude::*;
+use rusterix::Surface;
+use std::collections::{HashMap, HashSet};
+use vek::Vec2;
+
+/// Distance below which two points or a point and a segment are considered equal.
+const EPS: f32 = 1e-4;
+
+/// The boolean operations on the selected sectors. The first selected sector is
+/// the primary sector, its properties are transferred to the result.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum SectorBooleanOp {
+    /// Merge the selected sectors. Sectors which do not overlap or touch the
+    /// others stay apart, the result is one sector per separate area.
+    Union,
+    /// Cut all other selected sectors out of the primary sector.
+    Subtract,
+    /// Keep the area shared by all selected sectors.
+    Intersect,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum EdgeClass {
+    Inside,
+    Outside,
+    /// On the boundary of the other region, pointing in the same direction.
+    Same,
+    /// On the boundary of the other region, pointing in the opposite direction.
+    Opposite,
+}
+
+/// Apply the boolean operation to the selected sectors of the map. Returns false
+/// if the selection is not applicable, the result would be empty or the outline
+/// of the result could not be closed.
+pub fn apply_sector_boolean(map: &mut Map, op: SectorBooleanOp) -> bool {
+    let selected = map.selected_sectors.clone();
+    if selected.len() < 2 {
+        return false;
+    }
+
+    let mut polygons = vec![];
+    for sector_id in &selected {
+        let Some(polygon) = sector_polygon(map, *sector_id) else {
+            return false;
+        };
+        polygons.push(polygon);
+    }
+
+    let Some(loops) = boolean_loops(&polygons, op) else {
+        return false;
+    };
+
+    if loops.is_empty() && op != SectorBooleanOp::Subtract {
+        return false;
+    }
+
+    // Subtraction keeps the cutting sectors, the other operations replace all
+    let removed: Vec<u32> = if op == SectorBooleanOp::Subtract {
+        vec![selected[0]]
+    } else {
+        selected.clone()
+    };
+
+    // Vertices of the remaining sectors are kept in the result even if collinear
+    let mut remaining_linedefs = HashSet::new();
+    for sector in map.sectors.iter() {
+        if !removed.contains(&sector.id) {
+            remaining_linedefs.extend(sector.linedefs.iter().copied());
+        }
+    }
+    let mut keep = HashSet::new();
+    for linedef in map.linedefs.iter() {
+        if remaining_linedefs.contains(&linedef.id) {
+            for vertex_id in [linedef.start_vertex, linedef.end_vertex] {
+                if let Some(v) = map.get_vertex(vertex_id) {
+                    keep.insert(point_key(v));
+                }
+            }
+        }
+    }
+    let loops: Vec<Vec<Vec2<f32>>> = loops
+        .into_iter()
+        .map(|l| remove_collinear(l, &keep))
+        .filter(|l| l.len() >= 3 && signed_area(l).abs() > EPS)
+        .collect();
+
+    let Some(primary) = map.find_sector(selected[0]).cloned() else {
+        return false;
+    };
+
+    // Remove the replaced sectors with their unshared linedefs
+    let mut removed_linedefs = vec![];
+    let mut removed_vertices = HashSet::new();
+    for sector_id in &removed {
+        if let Some(sector) = map.find_sector(*sector_id) {
+            for linedef_id in &sector.linedefs {
+                if !remaining_linedefs.contains(linedef_id) {
+                    removed_linedefs.push(*linedef_id);
+                }
+                if let Some(linedef) = map.find_linedef(*linedef_id) {
+                    removed_vertices.insert(linedef.start_vertex);
+                    removed_vertices.insert(linedef.end_vertex);
+                }
+            }
+        }
+    }
+    map.delete_elements(&[], &removed_linedefs, &removed);
+    map.surfaces.retain(|_, s| !removed.contains(&s.sector_id));
+
+    // Build the resulting sectors
+    let mut new_sectors = vec![];
+    for l in &loops {
+        let vertices: Vec<u32> = l.iter().map(|p| map.add_vertex_at(p.x, p.y)).collect();
+
+        map.possible_polygon = vec![];
+        let mut sector_id = None;
+        for i in 0..vertices.len() {
+            let ids = map.create_linedef(vertices[i], vertices[(i + 1) % vertices.len()]);
+            if let Some(id) = ids.1 {
+                sector_id = Some(id);
+                break;
+            }
+        }
+
+        if let Some(sector_id) = sector_id {
+            if let Some(sector) = map.find_sector_mut(sector_id) {
+                sector.properties = primary.properties.clone();
+                sector.name = primary.name.clone();
+                sector.layer = primary.layer;
+            }
+
+            let mut surface = Surface::new(sector_id);
+            surface.calculate_geometry(map);
+            map.surfaces.insert(surface.id, surface);
+
+            new_sectors.push(sector_id);
+        }
+    }
+    map.possible_polygon.clear();
+
+    // Remove vertices which are no longer used by any linedef
+    let used: HashSet<u32> = map
+        .linedefs
+        .iter()
+        .flat_map(|l| [l.start_vertex, l.end_vertex])
+        .collect();
+    let orphans: Vec<u32> = removed_vertices
+        .into_iter()
+        .filter(|id| !used.contains(id))
+        .collect();
+    if !orphans.is_empty() {
+        map.delete_elements(&orphans, &[], &[]);
+    }
+
+    map.selected_vertices.clear();
+    map.selected_linedefs.clear();
+    map.selected_sectors = new_sectors;
+
+    true
+}
+
+/// The ordered outline of a sector, counter-clockwise.
+fn sector_polygon(map: &Map, sector_id: u32) -> Option<Vec<Vec2<f32>>> {
+    let mut polygon = vec![];
+    for id in sector_vertex_loop(map, sector_id)? {
+        polygon.push(map.get_vertex(id)?);
+    }
+    if signed_area(&polygon) < 0.0 {
+        polygon.reverse();
+    }
+    Some(polygon)
+}
+
+/// The vertex ids of a sector in the order of its linedefs. Linedefs which are
+/// stored reversed are walked backwards.
+pub fn sector_vertex_loop(map: &Map, sector_id: u32) -> Option<Vec<u32>> {
+    let sector = map.find_sector(sector_id)?;
+    if sector.linedefs.len() < 3 {
+        return None;
+    }
+
+    let mut ids = vec![];
+    for (index, linedef_id) in sector.linedefs.iter().enumerate() {
+        let linedef = map.find_linedef(*linedef_id)?;
+        let (start, end) = (linedef.start_vertex, linedef.end_vertex);
+        if index == 0 {
+            let next = map.find_linedef(sector.linedefs[1])?;
+            if start == next.start_vertex || start == next.end_vertex {
+                ids.push(end);
+                ids.push(start);
+            } else {
+                ids.push(start);
+                ids.push(end);
+            }
+        } else if *ids.last()? == start {
+            ids.push(end);
+        } else if *ids.last()? == end {
+            ids.push(start);
+        } else {
+            return None;
+        }
+    }
+    if ids.first() == ids.last() {
+        ids.pop();
+    }
+    Some(ids)
+}
+
+/// Combine the counter-clockwise polygons from left to right and resolve the holes
+/// of the result. Returns None if the outline of the result could not be closed
+/// or a hole could not be resolved.
+fn boolean_loops(polygons: &[Vec<Vec2<f32>>], op: SectorBooleanOp) -> Option<Vec<Vec<Vec2<f32>>>> {
+    let mut region = vec![polygons.first()?.clone()];
+    for polygon in polygons.iter().skip(1) {
+        region = boolean(&region, std::slice::from_ref(polygon), op)?;
+    }
+    split_holes(region, 0)
+}
+
+/// Compute the boolean operation of two regions. A region is a list of loops,
+/// outlines are counter-clockwise and holes clockwise. Returns None if the
+/// resulting edges do not form closed loops.
+fn boolean(
+    a: &[Vec<Vec2<f32>>],
+    b: &[Vec<Vec2<f32>>],
+    op: SectorBooleanOp,
+) -> Option<Vec<Vec<Vec2<f32>>>> {
+    let edges_a = region_edges(a);
+    let edges_b = region_edges(b);
+    let (split_a, split_b) = split_edges(&edges_a, &edges_b);
+
+    let mut result = vec![];
+    for (p, q) in split_a {
+        let keep = match (op, classify(p, q, b, &edges_b)) {
+            (SectorBooleanOp::Union, EdgeClass::Outside | EdgeClass::Same) => true,
+            (SectorBooleanOp::Intersect, EdgeClass::Inside | EdgeClass::Same) => true,
+            (SectorBooleanOp::Subtract, EdgeClass::Outside | EdgeClass::Opposite) => true,
+            _ => false,
+        };
+        if keep {
+            result.push((p, q));
+        }
+    }
+    for (p, q) in split_b {
+        match (op, classify(p, q, a, &edges_a)) {
+            (SectorBooleanOp::Union, EdgeClass::Outside) => result.push((p, q)),
+            (SectorBooleanOp::Intersect, EdgeClass::Inside) => result.push((p, q)),
+            (SectorBooleanOp::Subtract, EdgeClass::Inside) => result.push((q, p)),
+            _ => {}
+        }
+    }
+
+    chain_edges(&result)
+}
+
+/// Sectors cannot have holes. Outlines containing holes are cut into strips
+/// along the vertical lines through the left- and rightmost point of the hole.
+/// Returns None if the holes cannot be resolved, dropping them would fill the
+/// area the user cut out.
+fn split_holes(region: Vec<Vec<Vec2<f32>>>, depth: usize) -> Option<Vec<Vec<Vec2<f32>>>> {
+    let Some(hole) = region.iter().find(|l| signed_area(l) < 0.0) else {
+        return Some(region);
+    };
+    if depth > 8 {
+        return None;
+    }
+
+    let (hole_min, hole_max) = bounds(std::slice::from_ref(hole));
+    let (min, max) = bounds(&region);
+    let cuts = [min.x - 1.0, hole_min.x, hole_max.x, max.x + 1.0];
+
+    let mut result = vec![];
+    for window in cuts.windows(2) {
+        if window[1] - window[0] < EPS {
+            continue;
+        }
+        let strip = vec![
+            Vec2::new(window[0], min.y - 1.0),
+            Vec2::new(window[1], min.y - 1.0),
+            Vec2::new(window[1], max.y + 1.0),
+            Vec2::new(window[0], max.y + 1.0),
+        ];
+        let part = boolean(&region, &[strip], SectorBooleanOp::Intersect)?;
+        result.extend(split_holes(part, depth + 1)?);
+    }
+    Some(result)
+}
+
+/// All directed edges of a region.
+fn region_edges(region: &[Vec<Vec2<f32>>]) -> Vec<(Vec2<f32>, Vec2<f32>)> {
+    let mut edges = vec![];
+    for l in region {
+        for i in 0..l.len() {
+            edges.push((l[i], l[(i + 1) % l.len()]));
+        }
+    }
+    edges
+}
+
+/// Split the edges of both regions at their intersections and at the vertices
+/// of the other region which touch them. Intersection points are computed once
+/// and shared so that the split edges meet exactly.
+#[allow(clippy::type_complexity)]
+fn split_edges(
+    edges_a: &[(Vec2<f32>, Vec2<f32>)],
+    edges_b: &[(Vec2<f32>, Vec2<f32>)],
+) -> (Vec<(Vec2<f32>, Vec2<f32>)>, Vec<(Vec2<f32>, Vec2<f32>)>) {
+    let mut splits_a: Vec<Vec<(f32, Vec2<f32>)>> = vec![vec![]; edges_a.len()];
+    let mut splits_b: Vec<Vec<(f32, Vec2<f32>)>> = vec![vec![]; edges_b.len()];
+
+    for (i, (p, q)) in edges_a.iter().enumerate() {
+        for (j, (r, s)) in edges_b.iter().enumerate() {
+            // Vertices touching the other edge
+            for v in [*r, *s] {
+                if let Some(t) = interior_param(v, *p, *q) {
+                    splits_a[i].push((t, v));
+                }
+            }
+            for v in [*p, *q] {
+                if let Some(u) = interior_param(v, *r, *s) {
+                    splits_b[j].push((u, v));
+                }
+            }
+
+            // Proper crossings
+            let d1 = *q - *p;
+            let d2 = *s - *r;
+            let denom = cross(d1, d2);
+            if denom.abs() < 1e-12 {
+                continue;
+            }
+            let t = cross(*r - *p, d2) / denom;
+            let u = cross(*r - *p, d1) / denom;
+            if t <= 0.0 || t >= 1.0 || u <= 0.0 || u >= 1.0 {
+                continue;
+            }
+            let x = *p + d1 * t;
+            if [*p, *q, *r, *s].iter().any(|v| v.distance(x) < EPS) {
+                continue;
+            }
+            splits_a[i].push((t, x));
+            splits_b[j].push((u, x));
+        }
+    }
+
+    (
+        apply_splits(edges_a, splits_a),
+        apply_splits(edges_b, splits_b),
+    )
+}
+
+fn apply_splits(
+    edges: &[(Vec2<f32>, Vec2<f32>)],
+    splits: Vec<Vec<(f32, Vec2<f32>)>>,
+) -> Vec<(Vec2<f32>, Vec2<f32>)> {
+    let mut result = vec![];
+    for ((p, q), mut points) in edges.iter().zip(splits) {
+        points.sort_by(|a, b| a.0.total_cmp(&b.0));
+        let mut start = *p;
+        for (_, x) in points {
+            if start.distance(x) > EPS {
+                result.push((start, x));
+                start = x;
+            }
+        }
+        if start.distance(*q) > EPS {
+            result.push((start, *q));
+        }
+    }
+    result
+}
+
+/// The parameter of `v` on the segment if it lies on the segment, excluding its end points.
+fn interior_param(v: Vec2<f32>, a: Vec2<f32>, b: Vec2<f32>) -> Option<f32> {
+    if v.distance(a) < EPS || v.distance(b) < EPS {
+        return None;
+    }
+    let d = b - a;
+    let len2 = d.magnitude_squared();
+    if len2 < EPS * EPS {
+        return None;
+    }
+    let t = (v - a).dot(d) / len2;
+    if t <= 0.0 || t >= 1.0 {
+        return None;
+    }
+    if (a + d * t).distance(v) < EPS {
+        Some(t)
+    } else {
+        None
+    }
+}
+
+fn classify(
+    p: Vec2<f32>,
+    q: Vec2<f32>,
+    region: &[Vec<Vec2<f32>>],
+    edges: &[(Vec2<f32>, Vec2<f32>)],
+) -> EdgeClass {
+    let mid = (p + q) * 0.5;
+    for (r, s) in edges {
+        if distance_to_segment(mid, *r, *s) < EPS {
+            return if (q - p).dot(*s - *r) > 0.0 {
+                EdgeClass::Same
+            } else {
+                EdgeClass::Opposite
+            };
+        }
+    }
+    if point_in_region(mid, region) {
+        EdgeClass::Inside
+    } else {
+        EdgeClass::Outside
+    }
+}
+
+/// Link the directed edges into closed loops. Where several edges leave a point
+/// the one turning most to the left is taken, which keeps touching loops apart.
+/// Returns None if a chain of edges does not close, a partial outline would
+/// silently drop a part of the result.
+fn chain_edges(edges: &[(Vec2<f32>, Vec2<f32>)]) -> Option<Vec<Vec<Vec2<f32>>>> {
+    let mut outgoing: HashMap<(i64, i64), Vec<usize>> = HashMap::new();
+    for (index, (p, _)) in edges.iter().enumerate() {
+        outgoing.entry(point_key(*p)).or_default().push(index);
+    }
+
+    let mut used = vec![false; edges.len()];
+    let mut loops = vec![];
+
+    for start in 0..edges.len() {
+        if used[start] {
+            continue;
+        }
+        used[start] = true;
+
+        let start_key = point_key(edges[start].0);
+        let mut l = vec![edges[start].0];
+        let mut current = start;
+
+        loop {
+            let (p, q) = edges[current];
+            if point_key(q) == start_key {
+                loops.push(l);
+                break;
+            }
+            l.push(q);
+
+            let back = p - q;
+            let next = outgoing.get(&point_key(q)).and_then(|candidates| {
+                candidates
+                    .iter()
+                    .filter(|index| !used[**index])
+                    .min_by(|a, b| {
+                        let da = edges[**a].1 - edges[**a].0;
+                        let db = edges[**b].1 - edges[**b].0;
+                        clockwise_angle(back, da).total_cmp(&clockwise_angle(back, db))
+                    })
+                    .copied()
+            });
+
+            match next {
+                Some(index) => {
+                    used[index] = true;
+                    current = index;
+                }
+                None => return None,
+            }
+        }
+    }
+
+    Some(loops)
+}
+
+/// Remove duplicate and collinear points which are not in the keep set.
+fn remove_collinear(l: Vec<Vec2<f32>>, keep: &HashSet<(i64, i64)>) -> Vec<Vec2<f32>> {
+    let mut points = l;
+    let mut changed = true;
+    while changed && points.len() > 3 {
+        changed = false;
+        for i in 0..points.len() {
+            let prev = points[(i + points.len() - 1) % points.len()];
+            let curr = points[i];
+            let next = points[(i + 1) % points.len()];
+            let duplicate = prev.distance(curr) < EPS;
+            let collinear = cross(curr - prev, next - curr).abs() < EPS
+                && (curr - prev).dot(next - curr) > 0.0
+                && !keep.contains(&point_key(curr));
+            if duplicate || collinear {
+                points.remove(i);
+                changed = true;
+                break;
+            }
+        }
+    }
+    points
+}
+
+/// Angle from `from` to `to`, measured clockwise in (0, 2π].
+fn clockwise_angle(from: Vec2<f32>, to: Vec2<f32>) -> f32 {
+    let angle = from.y.atan2(from.x) - to.y.atan2(to.x);
+    let angle = angle.rem_euclid(std::f32::consts::TAU);
+    if angle < 1e-6 {
+        std::f32::consts::TAU
+    } else {
+        angle
+    }
+}
+
+/// Quantized position used to match points.
+fn point_key(p: Vec2<f32>) -> (i64, i64) {
+    let scale = 1.0 / (EPS * 10.0);
+    ((p.x * scale).round() as i64, (p.y * scale).round() as i64)
+}
+
+fn cross(a: Vec2<f32>, b: Vec2<f32>) -> f32 {
+    a.x * b.y - a.y * b.x
+}
+
+/// Signed area (shoelace). >0 => CCW, <0 => CW.
+fn signed_area(polygon: &[Vec2<f32>]) -> f32 {
+    let mut area = 0.0;
+    for i in 0..polygon.len() {
+        area += cross(polygon[i], polygon[(i + 1) % polygon.len()]);
+    }
+    area * 0.5
+}
+
+fn distance_to_segment(p: Vec2<f32>, a: Vec2<f32>, b: Vec2<f32>) -> f32 {
+    let d = b - a;
+    let len2 = d.magnitude_squared();
+    if len2 < EPS * EPS {
+        return p.distance(a);
+    }
+    let t = ((p - a).dot(d) / len2).clamp(0.0, 1.0);
+    p.distance(a + d * t)
+}
+
+/// Even-odd test against all loops of the region.
+fn point_in_region(p: Vec2<f32>, region: &[Vec<Vec2<f32>>]) -> bool {
+    let mut inside = false;
+    for l in region {
+        let mut j = l.len() - 1;
+        for i in 0..l.len() {
+            let (a, b) = (l[i], l[j]);
+            if (a.y > p.y) != (b.y > p.y) && p.x < (b.x - a.x) * (p.y - a.y) / (b.y - a.y) + a.x {
+                inside = !inside;
+            }
+            j = i;
+        }
+    }
+    inside
+}
+
+fn bounds(region: &[Vec<Vec2<f32>>]) -> (Vec2<f32>, Vec2<f32>) {
+    let mut min = Vec2::broadcast(f32::MAX);
+    let mut max = Vec2::broadcast(f32::MIN);
+    for p in region.iter().flatten() {
+        min = Vec2::partial_min(min, *p);
+        max = Vec2::partial_max(max, *p);
+    }
+    (min, max)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rect(x0: f32, y0: f32, x1: f32, y1: f32) -> Vec<Vec2<f32>> {
+        vec![
+            Vec2::new(x0, y0),
+            Vec2::new(x1, y0),
+            Vec2::new(x1, y1),
+            Vec2::new(x0, y1),
+        ]
+    }
+
+    /// Applies the operation and returns the number of sectors and their area.
+    fn apply(polygons: &[Vec<Vec2<f32>>], op: SectorBooleanOp) -> (usize, f32) {
+        let loops = boolean_loops(polygons, op).expect("closed result");
+        for l in &loops {
+            assert!(signed_area(l) > 0.0, "sectors are counter-clockwise");
+        }
+        (loops.len(), loops.iter().map(|l| signed_area(l)).sum())
+    }
+
+    fn assert_result(result: (usize, f32), sectors: usize, area: f32) {
+        assert_eq!(result.0, sectors);
+        assert!(
+            (result.1 - area).abs() < 1e-3,
+            "area {} != {}",
+            result.1,
+            area
+        );
+    }
+
+    #[test]
+    fn union() {
+        let a = rect(0.0, 0.0, 2.0, 2.0);
+        // Overlapping
+        assert_result(
+            apply(
+                &[a.clone(), rect(1.0, 1.0, 3.0, 3.0)],
+                SectorBooleanOp::Union,
+            ),
+            1,
+            7.0,
+        );
+        // Sharing an edge
+        assert_result(
+            apply(
+                &[a.clone(), rect(2.0, 0.0, 4.0, 2.0)],
+                SectorBooleanOp::Union,
+            ),
+            1,
+            8.0,
+        );
+        // Disjoint sectors stay apart
+        assert_result(
+            apply(
+                &[a.clone(), rect(5.0, 5.0, 6.0, 6.0)],
+                SectorBooleanOp::Union,
+            ),
+            2,
+            5.0,
+        );
+        // Contained and identical
+        assert_result(
+            apply(
+                &[a.clone(), rect(0.5, 0.5, 1.5, 1.5)],
+                SectorBooleanOp::Union,
+            ),
+            1,
+            4.0,
+        );
+        assert_result(apply(&[a.clone(), a], SectorBooleanOp::Union), 1, 4.0);
+    }
+
+    #[test]
+    fn difference() {
+        let a = rect(0.0, 0.0, 2.0, 2.0);
+        // Overlapping
+        assert_result(
+            apply(
+                &[a.clone(), rect(1.0, 1.0, 3.0, 3.0)],
+                SectorBooleanOp::Subtract,
+            ),
+            1,
+            3.0,
+        );
+        // Cutting through splits the sector
+        assert_result(
+            apply(
+                &[a.clone(), rect(0.5, -1.0, 1.5, 3.0)],
+                SectorBooleanOp::Subtract,
+            ),
+            2,
+            2.0,
+        );
+        // A contained hole is cut into the strips left, right, below and above it
+        assert_result(
+            apply(
+                &[a.clone(), rect(0.5, 0.5, 1.5, 1.5)],
+                SectorBooleanOp::Subtract,
+            ),
+            4,
+            3.0,
+        );
+        // Disjoint and touching sectors do not change the primary sector
+        assert_result(
+            apply(
+                &[a.clone(), rect(5.0, 5.0, 6.0, 6.0)],
+                SectorBooleanOp::Subtract,
+            ),
+            1,
+            4.0,
+        );
+        assert_result(
+            apply(
+                &[a.clone(), rect(2.0, 0.0, 4.0, 2.0)],
+                SectorBooleanOp::Subtract,
+            ),
+            1,
+            4.0,
+        );
+        // Identical and covering sectors leave nothing
+        assert_result(
+            apply(&[a.clone(), a.clone()], SectorBooleanOp::Subtract),
+            0,
+            0.0,
+        );
+        assert_result(
+            apply(&[a, rect(-1.0, -1.0, 3.0, 3.0)], SectorBooleanOp::Subtract),
+            0,
+            0.0,
+        );
+    }
+
+    #[test]
+    fn intersection() {
+        let a = rect(0.0, 0.0, 2.0, 2.0);
+        // Overlapping
+        assert_result(
+            apply(
+                &[a.clone(), rect(1.0, 1.0, 3.0, 3.0)],
+                SectorBooleanOp::Intersect,
+            ),
+            1,
+            1.0,
+        );
+        // Shared by all three
+        assert_result(
+            apply(
+                &[
+                    a.clone(),
+                    rect(1.0, 0.0, 3.0, 2.0),
+                    rect(0.0, 1.0, 2.0, 3.0),
+                ],
+                SectorBooleanOp::Intersect,
+            ),
+            1,
+            1.0,
+        );
+        // Contained and identical
+        assert_result(
+            apply(
+                &[a.clone(), rect(0.5, 0.5, 1.5, 1.5)],
+                SectorBooleanOp::Intersect,
+            ),
+            1,
+            1.0,
+        );
+        assert_result(
+            apply(&[a.clone(), a.clone()], SectorBooleanOp::Intersect),
+            1,
+            4.0,
+        );
+        // Disjoint and touching sectors share no area
+        assert_result(
+            apply(
+                &[a.clone(), rect(5.0, 5.0, 6.0, 6.0)],
+                SectorBooleanOp::Intersect,
+            ),
+            0,
+            0.0,
+        );
+        assert_result(
+            apply(&[a, rect(2.0, 0.0, 4.0, 2.0)], SectorBooleanOp::Intersect),
+            0,
+            0.0,
+        );
+    }
+
+    #[test]
+    fn open_chain() {
+        let edges = [
+            (Vec2::new(0.0, 0.0), Vec2::new(1.0, 0.0)),
+            (Vec2::new(1.0, 0.0), Vec2::new(1.0, 1.0)),
+        ];
+        assert!(chain_edges(&edges).is_none());
+    }
+}
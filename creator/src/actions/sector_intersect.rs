@@ -0,0 +1,79 @@
+use crate::actions::sector_boolean::{SectorBooleanOp, apply_sector_boolean};
+use crate::prelude::*;
+
+pub struct SectorIntersect {
+    id: TheId,
+    nodeui: TheNodeUI,
+}
+
+impl Action for SectorIntersect {
+    fn new() -> Self
+    where
+        Self: Sized,
+    {
+        let mut nodeui: TheNodeUI = TheNodeUI::default();
+
+        let item = TheNodeUIItem::Markdown("desc".into(), "Replaces the selected sectors with the area they all share. The properties of the first selected sector are kept.".into());
+        nodeui.add_item(item);
+
+        Self {
+            id: TheId::named("Sector Intersect"),
+            nodeui,
+        }
+    }
+
+    fn id(&self) -> TheId {
+        self.id.clone()
+    }
+
+    fn info(&self) -> &'static str {
+        "Keep the overlap of the selected sectors."
+    }
+
+    fn role(&self) -> ActionRole {
+        ActionRole::Editor
+    }
+
+    fn accel(&self) -> Option<TheAccelerator> {
+        None
+    }
+
+    fn is_applicable(&self, map: &Map, _ctx: &mut TheContext, _server_ctx: &ServerContext) -> bool {
+        map.selected_sectors.len() >= 2
+    }
+
+    fn apply(
+        &self,
+        map: &mut Map,
+        _ui: &mut TheUI,
+        _ctx: &mut TheContext,
+        _server_ctx: &mut ServerContext,
+    ) -> Option<RegionUndoAtom> {
+        let prev = map.clone();
+
+        if apply_sector_boolean(map, SectorBooleanOp::Intersect) {
+            Some(RegionUndoAtom::MapEdit(
+                Box::new(prev),
+                Box::new(map.clone()),
+            ))
+        } else {
+            *map = prev;
+            None
+        }
+    }
+
+    fn params(&self) -> TheNodeUI {
+        self.nodeui.clone()
+    }
+
+    fn handle_event(
+        &mut self,
+        event: &TheEvent,
+        _project: &mut Project,
+        _ui: &mut TheUI,
+        _ctx: &mut TheContext,
+        _server_ctx: &mut ServerContext,
+    ) -> bool {
+        self.nodeui.handle_event(event)
+    }
+}
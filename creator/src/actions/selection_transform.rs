@@ -0,0 +1,267 @@
+use crate::actions::sector_boolean::sector_vertex_loop;
+use crate::prelude::*;
+use std::collections::HashSet;
+use vek::Vec2;
+
+/// Adds the pivot parameters shared by the transform actions.
+pub fn add_pivot_items(nodeui: &mut TheNodeUI, prefix: &str, default: i32) {
+    nodeui.add_item(TheNodeUIItem::Selector(
+        format!("{prefix}Pivot"),
+        "Pivot".into(),
+        "Transform around the center of the selection or around the custom position.".into(),
+        vec!["Selection Center".to_string(), "Custom".to_string()],
+        default,
+    ));
+    nodeui.add_item(TheNodeUIItem::FloatEditSlider(
+        format!("{prefix}PivotX"),
+        "Pivot X".into(),
+        "The x position of the custom pivot.".into(),
+        0.0,
+        -100.0..=100.0,
+        false,
+    ));
+    nodeui.add_item(TheNodeUIItem::FloatEditSlider(
+        format!("{prefix}PivotY"),
+        "Pivot Y".into(),
+        "The y position of the custom pivot.".into(),
+        0.0,
+        -100.0..=100.0,
+        false,
+    ));
+}
+
+/// Returns the pivot selected in the parameters.
+pub fn pivot(nodeui: &TheNodeUI, prefix: &str, map: &Map, vertices: &[u32]) -> Vec2<f32> {
+    if nodeui.get_i32_value(&format!("{prefix}Pivot")).unwrap_or(0) == 1 {
+        Vec2::new(
+            nodeui
+                .get_f32_value(&format!("{prefix}PivotX"))
+                .unwrap_or(0.0),
+            nodeui
+                .get_f32_value(&format!("{prefix}PivotY"))
+                .unwrap_or(0.0),
+        )
+    } else {
+        selection_center(map, vertices)
+    }
+}
+
+/// All vertices of the selected vertices, linedefs and sectors.
+pub fn selection_vertices(map: &Map) -> Vec<u32> {
+    let mut linedefs = map.selected_linedefs.clone();
+    for sector_id in &map.selected_sectors {
+        if let Some(sector) = map.find_sector(*sector_id) {
+            linedefs.extend(sector.linedefs.iter().copied());
+        }
+    }
+
+    let mut vertices = map.selected_vertices.clone();
+    for linedef_id in linedefs {
+        if let Some(linedef) = map.find_linedef(linedef_id) {
+            vertices.push(linedef.start_vertex);
+            vertices.push(linedef.end_vertex);
+        }
+    }
+
+    let mut seen = HashSet::new();
+    vertices.retain(|id| seen.insert(*id));
+    vertices
+}
+
+/// The center of the bounding box of the given vertices.
+pub fn selection_center(map: &Map, vertices: &[u32]) -> Vec2<f32> {
+    let mut min = Vec2::broadcast(f32::MAX);
+    let mut max = Vec2::broadcast(f32::MIN);
+    for id in vertices {
+        if let Some(v) = map.get_vertex(*id) {
+            min = Vec2::partial_min(min, v);
+            max = Vec2::partial_max(max, v);
+        }
+    }
+    if min.x > max.x {
+        Vec2::zero()
+    } else {
+        (min + max) * 0.5
+    }
+}
+
+/// Snaps the position to the grid, honoring the subdivisions of the map.
+pub fn snap_to_grid(p: Vec2<f32>, subdivisions: f32) -> Vec2<f32> {
+    let subdivisions = subdivisions.max(1.0);
+    p.map(|x| (x * subdivisions).round() / subdivisions)
+}
+
+/// Moves all given vertices to their transformed position and updates the
+/// surfaces of the affected sectors.
+pub fn transform_vertices(
+    map: &mut Map,
+    vertices: &[u32],
+    snap: bool,
+    transform: &dyn Fn(Vec2<f32>) -> Vec2<f32>,
+) {
+    for id in vertices {
+        if let Some(v) = map.get_vertex(*id) {
+            let mut p = transform(v);
+            if snap {
+                p = snap_to_grid(p, map.subdivisions);
+            }
+            map.update_vertex(*id, p);
+        }
+    }
+
+    let moved: HashSet<u32> = vertices.iter().copied().collect();
+    let mut sectors = vec![];
+    for sector in &map.sectors {
+        let touched = sector.linedefs.iter().any(|linedef_id| {
+            map.find_linedef(*linedef_id)
+                .map(|l| moved.contains(&l.start_vertex) || moved.contains(&l.end_vertex))
+                .unwrap_or(false)
+        });
+        if touched {
+            sectors.push(sector.id);
+        }
+    }
+    update_sector_surfaces(map, &sectors);
+}
+
+/// Duplicates the selected geometry with all positions transformed. The
+/// properties of the linedefs and sectors and the sector surfaces are copied.
+/// Returns the new vertices, linedefs and sectors.
+pub fn duplicate_selection(
+    map: &mut Map,
+    snap: bool,
+    transform: &dyn Fn(Vec2<f32>) -> Vec2<f32>,
+) -> (Vec<u32>, Vec<u32>, Vec<u32>) {
+    let position = |map: &Map, id: u32| -> Option<Vec2<f32>> {
+        let mut p = transform(map.get_vertex(id)?);
+        if snap {
+            p = snap_to_grid(p, map.subdivisions);
+        }
+        Some(p)
+    };
+
+    let mut new_vertices = vec![];
+    let mut new_linedefs = vec![];
+    let mut new_sectors = vec![];
+    let mut covered_linedefs = HashSet::new();
+    let mut covered_vertices = HashSet::new();
+
+    for sector_id in map.selected_sectors.clone() {
+        let Some(sector) = map.find_sector(sector_id).cloned() else {
+            continue;
+        };
+        let Some(ids) = sector_vertex_loop(map, sector_id) else {
+            continue;
+        };
+
+        let mut vertices = vec![];
+        for id in &ids {
+            if let Some(p) = position(map, *id) {
+                vertices.push(map.add_vertex_at(p.x, p.y));
+            }
+        }
+        if vertices.len() != ids.len() {
+            continue;
+        }
+        covered_vertices.extend(ids.iter().copied());
+        covered_linedefs.extend(sector.linedefs.iter().copied());
+
+        map.possible_polygon = vec![];
+        let mut new_sector = None;
+        for (i, linedef_id) in sector.linedefs.iter().enumerate() {
+            let properties = map.find_linedef(*linedef_id).map(|l| l.properties.clone());
+            let (new_linedef, sector) =
+                map.create_linedef(vertices[i], vertices[(i + 1) % vertices.len()]);
+            if let (Some(linedef), Some(properties)) =
+                (map.find_linedef_mut(new_linedef), properties)
+            {
+                linedef.properties = properties;
+            }
+            new_linedefs.push(new_linedef);
+            if sector.is_some() {
+                new_sector = sector;
+            }
+        }
+        new_vertices.extend(vertices);
+
+        if let Some(new_sector) = new_sector {
+            if let Some(s) = map.find_sector_mut(new_sector) {
+                s.properties = sector.properties.clone();
+                s.name = sector.name.clone();
+                s.layer = sector.layer;
+            }
+
+            let surface = map
+                .surfaces
+                .values()
+                .find(|s| s.sector_id == sector_id)
+                .cloned();
+            if let Some(mut surface) = surface {
+                surface.id = Uuid::new_v4();
+                surface.sector_id = new_sector;
+                map.surfaces.insert(surface.id, surface);
+            }
+            new_sectors.push(new_sector);
+        }
+    }
+    map.possible_polygon = vec![];
+
+    for linedef_id in map.selected_linedefs.clone() {
+        if covered_linedefs.contains(&linedef_id) {
+            continue;
+        }
+        let Some(linedef) = map.find_linedef(linedef_id).cloned() else {
+            continue;
+        };
+        let (Some(start), Some(end)) = (
+            position(map, linedef.start_vertex),
+            position(map, linedef.end_vertex),
+        ) else {
+            continue;
+        };
+        let start = map.add_vertex_at(start.x, start.y);
+        let end = map.add_vertex_at(end.x, end.y);
+        covered_vertices.insert(linedef.start_vertex);
+        covered_vertices.insert(linedef.end_vertex);
+
+        map.possible_polygon = vec![];
+        let (new_linedef, _) = map.create_linedef(start, end);
+        if let Some(l) = map.find_linedef_mut(new_linedef) {
+            l.properties = linedef.properties.clone();
+        }
+        new_vertices.extend([start, end]);
+        new_linedefs.push(new_linedef);
+    }
+    map.possible_polygon = vec![];
+
+    for vertex_id in map.selected_vertices.clone() {
+        if covered_vertices.contains(&vertex_id) {
+            continue;
+        }
+        if let Some(p) = position(map, vertex_id) {
+            new_vertices.push(map.add_vertex_at(p.x, p.y));
+        }
+    }
+
+    update_sector_surfaces(map, &new_sectors);
+
+    let mut seen = HashSet::new();
+    new_vertices.retain(|id| seen.insert(*id));
+    (new_vertices, new_linedefs, new_sectors)
+}
+
+/// Recalculates the surface geometry of the given sectors.
+fn update_sector_surfaces(map: &mut Map, sectors: &[u32]) {
+    let ids: Vec<Uuid> = map
+        .surfaces
+        .iter()
+        .filter(|(_, s)| sectors.contains(&s.sector_id))
+        .map(|(id, _)| *id)
+        .collect();
+    for id in ids {
+        if let Some(mut surface) = map.surfaces.get(&id).cloned() {
+            surface.calculate_geometry(map);
+            map.surfaces.insert(id, surface);
+        }
+    }
+}
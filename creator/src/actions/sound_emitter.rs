@@ -0,0 +1,184 @@
+use crate::prelude::*;
+use rusterix::{Value, ValueContainer};
+
+pub struct SoundEmitter {
+    id: TheId,
+    nodeui: TheNodeUI,
+}
+
+impl Action for SoundEmitter {
+    fn new() -> Self
+    where
+        Self: Sized,
+    {
+        let mut nodeui: TheNodeUI = TheNodeUI::default();
+
+        let item = TheNodeUIItem::Text(
+            "actionEmitterSound".into(),
+            "Sound".into(),
+            "The audio asset looped at the selection. Leave empty to remove the emitter.".into(),
+            "".into(),
+            None,
+            false,
+        );
+        nodeui.add_item(item);
+
+        let item = TheNodeUIItem::FloatEditSlider(
+            "actionEmitterVolume".into(),
+            "Volume".into(),
+            "The volume of the sound.".into(),
+            1.0,
+            0.0..=1.0,
+            false,
+        );
+        nodeui.add_item(item);
+
+        let item = TheNodeUIItem::FloatEditSlider(
+            "actionEmitterRadius".into(),
+            "Radius".into(),
+            "The distance at which the sound fades out.".into(),
+            10.0,
+            1.0..=100.0,
+            false,
+        );
+        nodeui.add_item(item);
+
+        let item = TheNodeUIItem::Markdown(
+            "desc".into(),
+            "Attaches a looping positional sound to the selected vertices and sectors, like a waterfall or a fireplace. The sound fades with the distance to the player and is muffled by walls. Sectors sound from their closest point.".into(),
+        );
+        nodeui.add_item(item);
+
+        Self {
+            id: TheId::named("Sound Emitter"),
+            nodeui,
+        }
+    }
+
+    fn id(&self) -> TheId {
+        self.id.clone()
+    }
+
+    fn info(&self) -> &'static str {
+        "Attaches a positional sound to the selected vertices and sectors."
+    }
+
+    fn role(&self) -> ActionRole {
+        ActionRole::Editor
+    }
+
+    fn accel(&self) -> Option<TheAccelerator> {
+        None
+    }
+
+    fn is_applicable(&self, map: &Map, _ctx: &mut TheContext, server_ctx: &ServerContext) -> bool {
+        server_ctx.get_map_context() == MapContext::Region
+            && (!map.selected_vertices.is_empty() || !map.selected_sectors.is_empty())
+    }
+
+    fn load_params(&mut self, map: &Map) {
+        let properties = if let Some(vertex) = map
+            .selected_vertices
+            .first()
+            .and_then(|id| map.find_vertex(*id))
+        {
+            &vertex.properties
+        } else if let Some(sector) = map
+            .selected_sectors
+            .first()
+            .and_then(|id| map.find_sector(*id))
+        {
+            &sector.properties
+        } else {
+            return;
+        };
+
+        self.nodeui.set_text_value(
+            "actionEmitterSound",
+            properties.get_str_default(SOUND_EMITTER, String::new()),
+        );
+        self.nodeui.set_f32_value(
+            "actionEmitterVolume",
+            properties.get_float_default(SOUND_VOLUME, 1.0),
+        );
+        self.nodeui.set_f32_value(
+            "actionEmitterRadius",
+            properties.get_float_default(SOUND_RADIUS, 10.0),
+        );
+    }
+
+    fn apply(
+        &self,
+        map: &mut Map,
+        _ui: &mut TheUI,
+        _ctx: &mut TheContext,
+        _server_ctx: &mut ServerContext,
+    ) -> Option<RegionUndoAtom> {
+        let mut changed = false;
+        let prev = map.clone();
+
+        let sound = self
+            .nodeui
+            .get_text_value("actionEmitterSound")
+            .unwrap_or_default()
+            .trim()
+            .to_string();
+        let volume = self
+            .nodeui
+            .get_f32_value("actionEmitterVolume")
+            .unwrap_or(1.0);
+        let radius = self
+            .nodeui
+            .get_f32_value("actionEmitterRadius")
+            .unwrap_or(10.0);
+
+        let apply = |properties: &mut ValueContainer| {
+            if sound.is_empty() {
+                properties.remove(SOUND_EMITTER);
+                properties.remove(SOUND_VOLUME);
+                properties.remove(SOUND_RADIUS);
+            } else {
+                properties.set(SOUND_EMITTER, Value::Str(sound.clone()));
+                properties.set(SOUND_VOLUME, Value::Float(volume));
+                properties.set(SOUND_RADIUS, Value::Float(radius));
+            }
+        };
+
+        for vertex_id in map.selected_vertices.clone() {
+            if let Some(vertex) = map.find_vertex_mut(vertex_id) {
+                apply(&mut vertex.properties);
+                changed = true;
+            }
+        }
+        for sector_id in map.selected_sectors.clone() {
+            if let Some(sector) = map.find_sector_mut(sector_id) {
+                apply(&mut sector.properties);
+                changed = true;
+            }
+        }
+
+        if changed {
+            Some(RegionUndoAtom::MapEdit(
+                Box::new(prev),
+                Box::new(map.clone()),
+            ))
+        } else {
+            None
+        }
+    }
+
+    fn params(&self) -> TheNodeUI {
+        self.nodeui.clone()
+    }
+
+    fn handle_event(
+        &mut self,
+        event: &TheEvent,
+        _project: &mut Project,
+        _ui: &mut TheUI,
+        _ctx: &mut TheContext,
+        _server_ctx: &mut ServerContext,
+    ) -> bool {
+        self.nodeui.handle_event(event)
+    }
+}
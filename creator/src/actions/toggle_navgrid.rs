@@ -0,0 +1,69 @@
+use crate::prelude::*;
+
+pub struct ToggleNavGrid {
+    id: TheId,
+    nodeui: TheNodeUI,
+}
+
+impl Action for ToggleNavGrid {
+    fn new() -> Self
+    where
+        Self: Sized,
+    {
+        let mut nodeui: TheNodeUI = TheNodeUI::default();
+        let item = TheNodeUIItem::Markdown(
+            "desc".into(),
+            "Toggles the navigation grid overlay of the 2D region map. Walkable cells are drawn in green, walls which block the step between two cells in red. The grid is baked with the settings of the `[navigation]` section of the region config (`cell_size`, `agent_radius`, `max_slope`, `max_step`).".into(),
+        );
+        nodeui.add_item(item);
+
+        Self {
+            id: TheId::named("Toggle Nav Grid"),
+            nodeui,
+        }
+    }
+
+    fn id(&self) -> TheId {
+        self.id.clone()
+    }
+
+    fn info(&self) -> &'static str {
+        "Toggle the navigation grid overlay in the 2D editor."
+    }
+
+    fn role(&self) -> ActionRole {
+        ActionRole::Editor
+    }
+
+    fn is_applicable(&self, _map: &Map, _ctx: &mut TheContext, server_ctx: &ServerContext) -> bool {
+        server_ctx.editor_view_mode == EditorViewMode::D2
+            && server_ctx.get_map_context() == MapContext::Region
+    }
+
+    fn apply(
+        &self,
+        _map: &mut Map,
+        _ui: &mut TheUI,
+        _ctx: &mut TheContext,
+        server_ctx: &mut ServerContext,
+    ) -> Option<RegionUndoAtom> {
+        server_ctx.show_navgrid = !server_ctx.show_navgrid;
+
+        None
+    }
+
+    fn params(&self) -> TheNodeUI {
+        self.nodeui.clone()
+    }
+
+    fn handle_event(
+        &mut self,
+        event: &TheEvent,
+        _project: &mut Project,
+        _ui: &mut TheUI,
+        _ctx: &mut TheContext,
+        _server_ctx: &mut ServerContext,
+    ) -> bool {
+        self.nodeui.handle_event(event)
+    }
+}
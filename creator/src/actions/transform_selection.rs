@@ -0,0 +1,171 @@
+use crate::actions::selection_transform::*;
+use crate::prelude::*;
+use vek::Vec2;
+
+pub struct TransformSelection {
+    id: TheId,
+    nodeui: TheNodeUI,
+}
+
+impl Action for TransformSelection {
+    fn new() -> Self
+    where
+        Self: Sized,
+    {
+        let mut nodeui: TheNodeUI = TheNodeUI::default();
+
+        nodeui.add_item(TheNodeUIItem::FloatEditSlider(
+            "actionTransformRotate".into(),
+            "Rotate".into(),
+            "The rotation in degrees, counter-clockwise.".into(),
+            0.0,
+            -360.0..=360.0,
+            false,
+        ));
+        nodeui.add_item(TheNodeUIItem::FloatEditSlider(
+            "actionTransformScaleX".into(),
+            "Scale X".into(),
+            "The horizontal scale factor.".into(),
+            1.0,
+            0.01..=10.0,
+            false,
+        ));
+        nodeui.add_item(TheNodeUIItem::FloatEditSlider(
+            "actionTransformScaleY".into(),
+            "Scale Y".into(),
+            "The vertical scale factor.".into(),
+            1.0,
+            0.01..=10.0,
+            false,
+        ));
+        nodeui.add_item(TheNodeUIItem::Selector(
+            "actionTransformMirror".into(),
+            "Mirror".into(),
+            "Mirror the selection along the horizontal or vertical axis through the pivot.".into(),
+            vec![
+                "None".to_string(),
+                "Horizontal".to_string(),
+                "Vertical".to_string(),
+            ],
+            0,
+        ));
+        add_pivot_items(&mut nodeui, "actionTransform", 0);
+        nodeui.add_item(TheNodeUIItem::Checkbox(
+            "actionTransformSnap".into(),
+            "Snap to Grid".into(),
+            "Snap the transformed vertices to the grid subdivisions.".into(),
+            true,
+        ));
+
+        let item = TheNodeUIItem::Markdown(
+            "desc".into(),
+            "Rotates, scales and mirrors the selected geometry around the pivot. Mirroring is applied after scaling and rotation.".into(),
+        );
+        nodeui.add_item(item);
+
+        Self {
+            id: TheId::named("Transform Selection"),
+            nodeui,
+        }
+    }
+
+    fn id(&self) -> TheId {
+        self.id.clone()
+    }
+
+    fn info(&self) -> &'static str {
+        "Rotate, scale or mirror the selection."
+    }
+
+    fn role(&self) -> ActionRole {
+        ActionRole::Editor
+    }
+
+    fn accel(&self) -> Option<TheAccelerator> {
+        Some(TheAccelerator::new(TheAcceleratorKey::ALT, 't'))
+    }
+
+    fn is_applicable(&self, map: &Map, _ctx: &mut TheContext, _server_ctx: &ServerContext) -> bool {
+        !map.selected_vertices.is_empty()
+            || !map.selected_linedefs.is_empty()
+            || !map.selected_sectors.is_empty()
+    }
+
+    fn apply(
+        &self,
+        map: &mut Map,
+        _ui: &mut TheUI,
+        _ctx: &mut TheContext,
+        _server_ctx: &mut ServerContext,
+    ) -> Option<RegionUndoAtom> {
+        let prev = map.clone();
+
+        let angle = self
+            .nodeui
+            .get_f32_value("actionTransformRotate")
+            .unwrap_or(0.0)
+            .to_radians();
+        let scale = Vec2::new(
+            self.nodeui
+                .get_f32_value("actionTransformScaleX")
+                .unwrap_or(1.0),
+            self.nodeui
+                .get_f32_value("actionTransformScaleY")
+                .unwrap_or(1.0),
+        );
+        let mirror = self
+            .nodeui
+            .get_i32_value("actionTransformMirror")
+            .unwrap_or(0);
+        let snap = self
+            .nodeui
+            .get_bool_value("actionTransformSnap")
+            .unwrap_or(true);
+
+        let vertices = selection_vertices(map);
+        if vertices.is_empty() {
+            return None;
+        }
+        let pivot = pivot(&self.nodeui, "actionTransform", map, &vertices);
+
+        let (sin, cos) = angle.sin_cos();
+        let transform = |p: Vec2<f32>| -> Vec2<f32> {
+            let d = (p - pivot) * scale;
+            let mut d = Vec2::new(d.x * cos - d.y * sin, d.x * sin + d.y * cos);
+            match mirror {
+                1 => d.x = -d.x,
+                2 => d.y = -d.y,
+                _ => {}
+            }
+            pivot + d
+        };
+        transform_vertices(map, &vertices, snap, &transform);
+
+        let changed = vertices
+            .iter()
+            .any(|id| map.get_vertex(*id) != prev.get_vertex(*id));
+        if changed {
+            Some(RegionUndoAtom::MapEdit(
+                Box::new(prev),
+                Box::new(map.clone()),
+            ))
+        } else {
+            None
+        }
+    }
+
+    fn params(&self) -> TheNodeUI {
+        self.nodeui.clone()
+    }
+
+    fn handle_event(
+        &mut self,
+        event: &TheEvent,
+        _project: &mut Project,
+        _ui: &mut TheUI,
+        _ctx: &mut TheContext,
+        _server_ctx: &mut ServerContext,
+    ) -> bool {
+        self.nodeui.handle_event(event)
+    }
+}
@@ -0,0 +1,433 @@
+use crate::editor::UNDOMANAGER;
+use crate::prelude::*;
+use theframework::prelude::*;
+
+pub struct DialogueEditor {
+    pub dialogue: Dialogue,
+
+    pub categories: FxHashMap<String, TheColor>,
+}
+
+#[allow(clippy::new_without_default)]
+impl DialogueEditor {
+    pub fn new() -> Self {
+        let mut categories: FxHashMap<String, TheColor> = FxHashMap::default();
+        categories.insert("Flow".into(), TheColor::from("#9e9e9e")); // Neutral grey — where the dialogue starts and ends
+        categories.insert("Text".into(), TheColor::from("#4285F4")); // Vivid blue — what is said and answered
+        categories.insert("Condition".into(), TheColor::from("#c49a00")); // Warm gold — branches on attributes
+        categories.insert("Effect".into(), TheColor::from("#00bfa5")); // Teal green — changes the game state
+
+        Self {
+            dialogue: Dialogue::default(),
+            categories,
+        }
+    }
+
+    /// Activates the given dialogue in the editor.
+    pub fn apply_dialogue(&mut self, dialogue: &Dialogue, ui: &mut TheUI, ctx: &mut TheContext) {
+        self.dialogue = dialogue.clone();
+        self.dialogue.selected_node = None;
+        let canvas = self.to_canvas();
+        ui.set_node_canvas("Dialogue NodeCanvas", canvas);
+        ui.set_widget_value(
+            "Dialogue Name Text",
+            ctx,
+            TheValue::Text(self.dialogue.name.clone()),
+        );
+        self.set_selected_node_ui(ui, ctx);
+    }
+
+    pub fn build(&mut self) -> TheCanvas {
+        let mut center = TheCanvas::new();
+
+        // Toolbar
+        let mut top_toolbar = TheCanvas::new();
+        top_toolbar.set_widget(TheTraybar::new(TheId::empty()));
+
+        let mut toolbar_hlayout = TheHLayout::new(TheId::empty());
+        toolbar_hlayout.set_background_color(None);
+        toolbar_hlayout.set_margin(Vec4::new(10, 4, 5, 4));
+
+        let mut name_text = TheText::new(TheId::named("Dialogue Name Text"));
+        name_text.set_status_text("The name scripts start the dialogue with.");
+        name_text.set_text("".to_string());
+        toolbar_hlayout.add_widget(Box::new(name_text));
+
+        for (name, status) in [
+            (
+                "Text",
+                "Lines spoken by the character and the choices of the player.",
+            ),
+            (
+                "Condition",
+                "Branches on an attribute of the player, the speaker or a global flag.",
+            ),
+            (
+                "Effect",
+                "Gives items, sets flags, starts quests or opens the shop of the speaker.",
+            ),
+            ("Flow", "Ends the dialogue."),
+        ] {
+            let mut button =
+                TheTraybarButton::new(TheId::named(&format!("Dialogue {} Nodes", name)));
+            button.set_custom_color(self.categories.get(name).cloned());
+            button.set_text(name.to_string());
+            button.set_status_text(status);
+            let items = match name {
+                "Text" => vec!["Line", "Choice"],
+                "Condition" => vec!["Condition"],
+                "Effect" => vec!["Effect"],
+                _ => vec!["End"],
+            };
+            button.set_context_menu(Some(TheContextMenu {
+                items: items
+                    .into_iter()
+                    .map(|item| TheContextMenuItem::new(item.to_string(), TheId::named(item)))
+                    .collect(),
+                ..Default::default()
+            }));
+            toolbar_hlayout.add_widget(Box::new(button));
+        }
+
+        toolbar_hlayout.set_reverse_index(Some(4));
+        top_toolbar.set_layout(toolbar_hlayout);
+        center.set_top(top_toolbar);
+
+        let mut shared_layout = TheSharedHLayout::new(TheId::named("Dialogue Shared Layout"));
+        shared_layout.set_shared_ratio(0.7);
+        shared_layout.set_mode(TheSharedHLayoutMode::Shared);
+
+        let mut node_canvas = TheCanvas::new();
+        let node_view = TheNodeCanvasView::new(TheId::named("Dialogue NodeCanvas"));
+        node_canvas.set_widget(node_view);
+        shared_layout.add_canvas(node_canvas);
+
+        let mut settings_canvas = TheCanvas::new();
+        let mut text_layout = TheTextLayout::new(TheId::named("Dialogue Node Settings"));
+        text_layout.limiter_mut().set_max_width(300);
+        settings_canvas.set_layout(text_layout);
+        shared_layout.add_canvas(settings_canvas);
+
+        center.set_layout(shared_layout);
+
+        center
+    }
+
+    pub fn to_canvas(&mut self) -> TheNodeCanvas {
+        let mut canvas = TheNodeCanvas {
+            node_width: 136,
+            selected_node: self.dialogue.selected_node,
+            offset: self.dialogue.scroll_offset,
+            connections: self.dialogue.connections.clone(),
+            categories: self.categories.clone(),
+            ..Default::default()
+        };
+
+        for node in self.dialogue.nodes.iter() {
+            let terminal = |name: String| TheNodeTerminal {
+                name,
+                category_name: node.category().into(),
+            };
+            let n = TheNode {
+                name: node.name(),
+                position: node.position,
+                inputs: node.inputs().into_iter().map(terminal).collect(),
+                outputs: node.outputs().into_iter().map(terminal).collect(),
+                preview: TheRGBABuffer::default(),
+                supports_preview: false,
+                preview_is_open: false,
+                can_be_deleted: node.kind != DialogueNodeKind::Start,
+            };
+            canvas.nodes.push(n);
+        }
+
+        canvas
+    }
+
+    pub fn handle_event(
+        &mut self,
+        event: &TheEvent,
+        ui: &mut TheUI,
+        ctx: &mut TheContext,
+        project: &mut Project,
+    ) -> bool {
+        let mut redraw = false;
+        let prev = self.dialogue.clone();
+        let mut changed = false;
+
+        match event {
+            TheEvent::ContextMenuSelected(id, item) => {
+                if id.name.starts_with("Dialogue ")
+                    && id.name.ends_with(" Nodes")
+                    && let Some(mut node) = DialogueNode::from_name(&item.name)
+                {
+                    node.position = Vec2::new(
+                        self.dialogue.scroll_offset.x + 220,
+                        self.dialogue.scroll_offset.y + 10,
+                    );
+                    self.dialogue.nodes.push(node);
+                    self.dialogue.selected_node = Some(self.dialogue.nodes.len() - 1);
+
+                    let canvas = self.to_canvas();
+                    ui.set_node_canvas("Dialogue NodeCanvas", canvas);
+                    self.set_selected_node_ui(ui, ctx);
+                    changed = true;
+                }
+            }
+            TheEvent::NodeSelectedIndexChanged(id, index) => {
+                if id.name == "Dialogue NodeCanvas" {
+                    self.dialogue.selected_node = *index;
+                    self.set_selected_node_ui(ui, ctx);
+                    self.store(project);
+                }
+            }
+            TheEvent::NodeDragged(id, index, position) => {
+                if id.name == "Dialogue NodeCanvas" {
+                    if let Some(node) = self.dialogue.nodes.get_mut(*index) {
+                        node.position = *position;
+                    }
+                    self.store(project);
+                }
+            }
+            TheEvent::NodeConnectionAdded(id, connections)
+            | TheEvent::NodeConnectionRemoved(id, connections) => {
+                if id.name == "Dialogue NodeCanvas" {
+                    self.dialogue.connections.clone_from(connections);
+                    changed = true;
+                }
+            }
+            TheEvent::NodeDeleted(id, deleted_node_index, connections) => {
+                if id.name == "Dialogue NodeCanvas" {
+                    self.dialogue.nodes.remove(*deleted_node_index);
+                    self.dialogue.connections.clone_from(connections);
+                    self.dialogue.selected_node = None;
+                    self.set_selected_node_ui(ui, ctx);
+                    changed = true;
+                }
+            }
+            TheEvent::NodeViewScrolled(id, offset) => {
+                if id.name == "Dialogue NodeCanvas" {
+                    self.dialogue.scroll_offset = *offset;
+                    self.store(project);
+                }
+            }
+            TheEvent::ValueChanged(id, value) => {
+                if id.name.starts_with("dialogue")
+                    && let Some(index) = self.dialogue.selected_node
+                    && let Some(node) = self.dialogue.nodes.get_mut(index)
+                {
+                    let text = value.to_string().unwrap_or_default();
+                    let selected = value.to_i32().unwrap_or(0);
+                    let mut relayout = false;
+                    match (&mut node.kind, id.name.as_str()) {
+                        (DialogueNodeKind::Line { speaker, .. }, "dialogueSpeaker") => {
+                            *speaker = text
+                        }
+                        (DialogueNodeKind::Line { text: line, .. }, "dialogueText") => *line = text,
+                        (DialogueNodeKind::Choice { choices }, name)
+                            if name.starts_with("dialogueChoice") =>
+                        {
+                            if let Ok(number) = name["dialogueChoice".len()..].parse::<usize>()
+                                && let Some(choice) = choices.get_mut(number.wrapping_sub(1))
+                            {
+                                *choice = text;
+                            }
+                        }
+                        (
+                            DialogueNodeKind::Condition { scope, .. }
+                            | DialogueNodeKind::Effect { scope, .. },
+                            "dialogueScope",
+                        ) => *scope = DialogueScope::from_index(selected),
+                        (DialogueNodeKind::Condition { attribute, .. }, "dialogueAttribute") => {
+                            *attribute = text
+                        }
+                        (DialogueNodeKind::Condition { op, .. }, "dialogueOp") => {
+                            *op = DialogueOp::from_index(selected)
+                        }
+                        (
+                            DialogueNodeKind::Condition { value, .. }
+                            | DialogueNodeKind::Effect { value, .. },
+                            "dialogueValue",
+                        ) => *value = text,
+                        (DialogueNodeKind::Effect { effect, .. }, "dialogueEffect") => {
+                            *effect = DialogueEffect::from_index(selected);
+                            relayout = true;
+                        }
+                        (DialogueNodeKind::Effect { argument, .. }, "dialogueArgument") => {
+                            *argument = text
+                        }
+                        _ => {}
+                    }
+                    if relayout {
+                        // The effect is the node name and decides the fields
+                        let canvas = self.to_canvas();
+                        ui.set_node_canvas("Dialogue NodeCanvas", canvas);
+                        self.set_selected_node_ui(ui, ctx);
+                    }
+                    changed = true;
+                }
+            }
+            _ => {}
+        }
+
+        if changed && prev != self.dialogue {
+            self.store(project);
+            let atom =
+                ProjectUndoAtom::EditDialogue(Box::new(prev), Box::new(self.dialogue.clone()));
+            UNDOMANAGER.write().unwrap().add_undo(atom, ctx);
+            redraw = true;
+        }
+
+        redraw
+    }
+
+    /// Writes the edited dialogue back into the project.
+    fn store(&self, project: &mut Project) {
+        if let Some(dialogue) = project.dialogues.get_mut(&self.dialogue.id) {
+            let name = dialogue.name.clone();
+            *dialogue = self.dialogue.clone();
+            // Renamed from the project tree
+            dialogue.name = name;
+        }
+    }
+
+    /// Create the UI for the selected node.
+    pub fn set_selected_node_ui(&mut self, ui: &mut TheUI, ctx: &mut TheContext) {
+        let mut nodeui = TheNodeUI::default();
+
+        let text = |id: &str, name: &str, status: &str, value: &String| {
+            TheNodeUIItem::Text(
+                id.into(),
+                name.into(),
+                status.into(),
+                value.clone(),
+                None,
+                false,
+            )
+        };
+        let scope_selector = |status: &str, scope: &DialogueScope| {
+            TheNodeUIItem::Selector(
+                "dialogueScope".into(),
+                "Scope".into(),
+                status.into(),
+                vec!["Player".into(), "Speaker".into(), "Global".into()],
+                scope.to_index(),
+            )
+        };
+
+        if let Some(index) = self.dialogue.selected_node
+            && let Some(node) = self.dialogue.nodes.get(index)
+        {
+            match &node.kind {
+                DialogueNodeKind::Line {
+                    speaker,
+                    text: line,
+                } => {
+                    nodeui.add_item(text(
+                        "dialogueSpeaker",
+                        "Speaker",
+                        "The name shown above the line. Empty uses the name of the character.",
+                        speaker,
+                    ));
+                    nodeui.add_item(text(
+                        "dialogueText",
+                        "Text",
+                        "The spoken line. The player continues with Return.",
+                        line,
+                    ));
+                }
+                DialogueNodeKind::Choice { choices } => {
+                    for (index, choice) in choices.iter().enumerate() {
+                        nodeui.add_item(text(
+                            &format!("dialogueChoice{}", index + 1),
+                            &format!("Choice {}", index + 1),
+                            "The answer of the player, continues at the output with its number. Empty choices are hidden.",
+                            choice,
+                        ));
+                    }
+                }
+                DialogueNodeKind::Condition {
+                    scope,
+                    attribute,
+                    op,
+                    value,
+                } => {
+                    nodeui.add_item(scope_selector(
+                        "Read the attribute of the player, the speaker or a global flag.",
+                        scope,
+                    ));
+                    nodeui.add_item(text(
+                        "dialogueAttribute",
+                        "Attribute",
+                        "The name of the attribute or flag.",
+                        attribute,
+                    ));
+                    nodeui.add_item(TheNodeUIItem::Selector(
+                        "dialogueOp".into(),
+                        "Compare".into(),
+                        "How the attribute is compared with the value. Numbers are compared numerically.".into(),
+                        DialogueOp::ALL.iter().map(|o| o.symbol().to_string()).collect(),
+                        op.to_index(),
+                    ));
+                    nodeui.add_item(text(
+                        "dialogueValue",
+                        "Value",
+                        "The value to compare with. Continues at Yes or No.",
+                        value,
+                    ));
+                }
+                DialogueNodeKind::Effect {
+                    effect,
+                    scope,
+                    argument,
+                    value,
+                } => {
+                    nodeui.add_item(TheNodeUIItem::Selector(
+                        "dialogueEffect".into(),
+                        "Effect".into(),
+                        "What happens when the dialogue passes the node.".into(),
+                        DialogueEffect::ALL
+                            .iter()
+                            .map(|e| e.name().to_string())
+                            .collect(),
+                        effect.to_index(),
+                    ));
+                    let (name, status) = match effect {
+                        DialogueEffect::GiveItem => {
+                            ("Item", "The name of the item given to the player.")
+                        }
+                        DialogueEffect::SetFlag => ("Flag", "The name of the attribute or flag."),
+                        DialogueEffect::StartQuest => ("Quest", "The name of the quest."),
+                        DialogueEffect::OpenShop => (
+                            "Filter",
+                            "Filters the items the speaker offers, empty offers all.",
+                        ),
+                        DialogueEffect::Event => (
+                            "Event",
+                            "Sent to user_event() of the player as (\"dialogue_event\", value).",
+                        ),
+                    };
+                    nodeui.add_item(text("dialogueArgument", name, status, argument));
+                    if *effect == DialogueEffect::SetFlag {
+                        nodeui.add_item(scope_selector(
+                            "Set the attribute of the player, the speaker or a global flag.",
+                            scope,
+                        ));
+                        nodeui.add_item(text(
+                            "dialogueValue",
+                            "Value",
+                            "The new value: true, false, a number or text.",
+                            value,
+                        ));
+                    }
+                }
+                DialogueNodeKind::Start | DialogueNodeKind::End => {}
+            }
+        }
+
+        if let Some(layout) = ui.get_text_layout("Dialogue Node Settings") {
+            nodeui.apply_to_text_layout(layout);
+            ctx.ui.relayout = true;
+        }
+    }
+}
@@ -36,6 +36,9 @@ impl DockManager {
         let dock: Box<dyn Dock> = Box::new(crate::docks::tiles::TilesDock::new());
         docks.insert("Tiles".into(), dock);
 
+        let dock: Box<dyn Dock> = Box::new(crate::docks::prefabs::PrefabsDock::new());
+        docks.insert("Prefabs".into(), dock);
+
         let dock: Box<dyn Dock> = Box::new(crate::docks::visual_code::VisualCodeDock::new());
         docks.insert("Visual Code".into(), dock);
 
@@ -48,6 +51,27 @@ impl DockManager {
         let dock: Box<dyn Dock> = Box::new(crate::docks::tilemap::TilemapDock::new());
         docks.insert("Tilemap".into(), dock);
 
+        let dock: Box<dyn Dock> = Box::new(crate::docks::tests::TestsDock::new());
+        docks.insert("Tests".into(), dock);
+
+        let dock: Box<dyn Dock> = Box::new(crate::docks::profiler::ProfilerDock::new());
+        docks.insert("Profiler".into(), dock);
+
+        let dock: Box<dyn Dock> = Box::new(crate::docks::inspector::InspectorDock::new());
+        docks.insert("Inspector".into(), dock);
+
+        let dock: Box<dyn Dock> = Box::new(crate::docks::localization::LocalizationDock::new());
+        docks.insert("Localization".into(), dock);
+
+        let dock: Box<dyn Dock> = Box::new(crate::docks::dialogue::DialogueDock::new());
+        docks.insert("Dialogue".into(), dock);
+
+        let dock: Box<dyn Dock> = Box::new(crate::docks::quests::QuestsDock::new());
+        docks.insert("Quests".into(), dock);
+
+        let dock: Box<dyn Dock> = Box::new(crate::docks::screen_preview::ScreenPreviewDock::new());
+        docks.insert("Screen Preview".into(), dock);
+
         Self {
             state: DockManagerState::Minimized,
             docks,
@@ -179,6 +203,14 @@ impl DockManager {
     ) -> bool {
         let mut redraw = false;
 
+        // Docks sharing a project context switch between each other
+        if let TheEvent::Custom(id, TheValue::Text(dock)) = event {
+            if id.name == "Set Dock" {
+                self.set_dock(dock.clone(), ui, ctx, project, server_ctx);
+                return true;
+            }
+        }
+
         if let Some((_, dock)) = self.docks.get_index_mut(self.index) {
             redraw = dock.handle_event(event, ui, ctx, project, server_ctx);
 
@@ -8,6 +8,7 @@ use theframework::theui::thewidget::thetextedit::TheTextEditState;
 pub enum EntityKey {
     Character(Uuid),
     Item(Uuid),
+    Library(Uuid),
 }
 
 pub struct CodeDock {
@@ -79,6 +80,16 @@ impl Dock for CodeDock {
                     // Switch to this entity's undo stack
                     self.switch_to_entity(EntityKey::Item(id), ctx);
                 }
+            } else if server_ctx.pc.is_library() {
+                if let Some(library) = project.libraries.get(&id) {
+                    ui.set_widget_value(
+                        "DockCodeEditor",
+                        ctx,
+                        TheValue::Text(library.source.clone()),
+                    );
+                    // Switch to this library's undo stack
+                    self.switch_to_entity(EntityKey::Library(id), ctx);
+                }
             }
         }
 
@@ -128,6 +139,13 @@ impl Dock for CodeDock {
                                     redraw = true;
                                 }
                             }
+                        } else if server_ctx.pc.is_library() {
+                            if let Some(code) = value.to_string() {
+                                if let Some(library) = project.libraries.get_mut(&id) {
+                                    library.source = code;
+                                    redraw = true;
+                                }
+                            }
                         }
                     }
                 }
@@ -254,6 +272,10 @@ impl CodeDock {
                         item.source = text.clone();
                         item.source_debug = text;
                     }
+                } else if server_ctx.pc.is_library() {
+                    if let Some(library) = project.libraries.get_mut(&id) {
+                        library.source = text;
+                    }
                 }
             }
         }
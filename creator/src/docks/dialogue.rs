@@ -0,0 +1,53 @@
+use crate::editor::DIALOGUEEDITOR;
+use crate::prelude::*;
+use theframework::prelude::*;
+
+pub struct DialogueDock {}
+
+impl Dock for DialogueDock {
+    fn new() -> Self
+    where
+        Self: Sized,
+    {
+        Self {}
+    }
+
+    fn setup(&mut self, _ctx: &mut TheContext) -> TheCanvas {
+        DIALOGUEEDITOR.write().unwrap().build()
+    }
+
+    fn activate(
+        &mut self,
+        ui: &mut TheUI,
+        ctx: &mut TheContext,
+        project: &Project,
+        server_ctx: &mut ServerContext,
+    ) {
+        if let ProjectContext::Dialogue(id) = server_ctx.pc
+            && let Some(dialogue) = project.dialogues.get(&id)
+        {
+            DIALOGUEEDITOR
+                .write()
+                .unwrap()
+                .apply_dialogue(dialogue, ui, ctx);
+        }
+    }
+
+    fn supports_actions(&self) -> bool {
+        false
+    }
+
+    fn handle_event(
+        &mut self,
+        event: &TheEvent,
+        ui: &mut TheUI,
+        ctx: &mut TheContext,
+        project: &mut Project,
+        _server_ctx: &mut ServerContext,
+    ) -> bool {
+        DIALOGUEEDITOR
+            .write()
+            .unwrap()
+            .handle_event(event, ui, ctx, project)
+    }
+}
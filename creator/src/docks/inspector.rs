@@ -0,0 +1,178 @@
+use crate::editor::{INSPECTOR, UNDOMANAGER};
+use crate::prelude::*;
+use theframework::prelude::*;
+
+pub struct InspectorDock {}
+
+impl Dock for InspectorDock {
+    fn new() -> Self
+    where
+        Self: Sized,
+    {
+        Self {}
+    }
+
+    fn setup(&mut self, _ctx: &mut TheContext) -> TheCanvas {
+        let mut canvas = TheCanvas::new();
+
+        let mut shared_layout = TheSharedHLayout::new(TheId::named("Inspector Shared Layout"));
+        shared_layout.set_shared_ratio(0.25);
+        shared_layout.set_mode(TheSharedHLayoutMode::Shared);
+
+        // Instances of the current region
+        let mut list_canvas = TheCanvas::new();
+        let list_layout = TheListLayout::new(TheId::named("Inspector List"));
+        list_canvas.set_layout(list_layout);
+
+        // Runtime state of the selected instance
+        let mut edit_canvas = TheCanvas::new();
+
+        let mut toolbar_canvas = TheCanvas::default();
+        toolbar_canvas.set_widget(TheTraybar::new(TheId::empty()));
+        let mut toolbar_hlayout = TheHLayout::new(TheId::empty());
+        toolbar_hlayout.set_background_color(None);
+        toolbar_hlayout.set_margin(Vec4::new(10, 1, 5, 1));
+        toolbar_hlayout.set_padding(3);
+
+        let mut mode_switch = TheGroupButton::new(TheId::named("Inspector Mode"));
+        mode_switch.add_text_status("Attributes".to_string(), "Show the attributes.".to_string());
+        mode_switch.add_text_status("Inventory".to_string(), "Show the inventory.".to_string());
+        mode_switch.add_text_status(
+            "Equipped".to_string(),
+            "Show the gear and weapon slots.".to_string(),
+        );
+        mode_switch.set_item_width(80);
+        toolbar_hlayout.add_widget(Box::new(mode_switch));
+
+        let mut apply_button = TheTraybarButton::new(TheId::named("Inspector Apply"));
+        apply_button.set_text("Apply".to_string());
+        apply_button.set_status_text("Apply the edited values to the running server.");
+        toolbar_hlayout.add_widget(Box::new(apply_button));
+
+        let mut revert_button = TheTraybarButton::new(TheId::named("Inspector Revert"));
+        revert_button.set_text("Revert".to_string());
+        revert_button.set_status_text("Discard the edits and show the live values again.");
+        toolbar_hlayout.add_widget(Box::new(revert_button));
+
+        let mut copy_button = TheTraybarButton::new(TheId::named("Inspector Copy Data"));
+        copy_button.set_text("Copy to Data".to_string());
+        copy_button.set_status_text(
+            "Copy the attributes changed in the inspector into the [attributes] of the instance's data.",
+        );
+        toolbar_hlayout.add_widget(Box::new(copy_button));
+        toolbar_hlayout.set_reverse_index(Some(3));
+
+        toolbar_canvas.set_layout(toolbar_hlayout);
+        edit_canvas.set_top(toolbar_canvas);
+
+        let mut textedit = TheTextAreaEdit::new(TheId::named("Inspector Edit"));
+        textedit.auto_scroll_to_cursor(false);
+        if let Some(bytes) = crate::Embedded::get("parser/TOML.sublime-syntax") {
+            if let Ok(source) = std::str::from_utf8(bytes.data.as_ref()) {
+                textedit.add_syntax_from_string(source);
+                textedit.set_code_type("TOML");
+            }
+        }
+        textedit.set_continuous(true);
+        textedit.display_line_number(true);
+        textedit.set_code_theme("base16-eighties.dark");
+        textedit.use_global_statusbar(true);
+        textedit.set_font_size(14.0);
+        edit_canvas.set_widget(textedit);
+
+        shared_layout.add_canvas(list_canvas);
+        shared_layout.add_canvas(edit_canvas);
+        canvas.set_layout(shared_layout);
+
+        canvas
+    }
+
+    fn activate(
+        &mut self,
+        ui: &mut TheUI,
+        ctx: &mut TheContext,
+        project: &Project,
+        server_ctx: &mut ServerContext,
+    ) {
+        INSPECTOR
+            .write()
+            .unwrap()
+            .update(project, ui, ctx, server_ctx);
+    }
+
+    fn supports_actions(&self) -> bool {
+        false
+    }
+
+    fn handle_event(
+        &mut self,
+        event: &TheEvent,
+        ui: &mut TheUI,
+        ctx: &mut TheContext,
+        project: &mut Project,
+        server_ctx: &mut ServerContext,
+    ) -> bool {
+        let mut redraw = false;
+
+        match event {
+            TheEvent::ValueChanged(id, value) => {
+                if id.name == "Inspector Edit" {
+                    if let Some(text) = value.to_string() {
+                        INSPECTOR.write().unwrap().edited = Some(text);
+                    }
+                }
+            }
+            TheEvent::IndexChanged(id, index) => {
+                if id.name == "Inspector Mode" {
+                    let mut inspector = INSPECTOR.write().unwrap();
+                    inspector.mode = InspectorMode::from_index(*index);
+                    inspector.edited = None;
+                    inspector.update(project, ui, ctx, server_ctx);
+                    redraw = true;
+                }
+            }
+            TheEvent::StateChanged(id, TheWidgetState::Selected) => {
+                if id.name == "Inspector Item" {
+                    let mut inspector = INSPECTOR.write().unwrap();
+                    inspector.select(&id.uuid);
+                    inspector.update(project, ui, ctx, server_ctx);
+                    redraw = true;
+                }
+            }
+            TheEvent::StateChanged(id, TheWidgetState::Clicked) => {
+                let status = if id.name == "Inspector Apply" {
+                    match INSPECTOR.write().unwrap().apply(project, server_ctx) {
+                        Ok(count) => Some(format!("Applied {} changed values.", count)),
+                        Err(err) => Some(err),
+                    }
+                } else if id.name == "Inspector Revert" {
+                    let mut inspector = INSPECTOR.write().unwrap();
+                    inspector.edited = None;
+                    inspector.update(project, ui, ctx, server_ctx);
+                    None
+                } else if id.name == "Inspector Copy Data" {
+                    match INSPECTOR.read().unwrap().copy_to_data(project, server_ctx) {
+                        Ok((name, atom)) => {
+                            UNDOMANAGER.write().unwrap().add_undo(atom, ctx);
+                            Some(format!(
+                                "Copied the changed attributes into the data of {}.",
+                                name
+                            ))
+                        }
+                        Err(err) => Some(err),
+                    }
+                } else {
+                    None
+                };
+
+                if let Some(status) = status {
+                    ctx.ui.send(TheEvent::SetStatusText(TheId::empty(), status));
+                    redraw = true;
+                }
+            }
+            _ => {}
+        }
+
+        redraw
+    }
+}
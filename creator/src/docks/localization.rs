@@ -0,0 +1,322 @@
+use crate::editor::{LOCALIZATIONEDITOR, RUSTERIX, UNDOMANAGER};
+use crate::prelude::*;
+use theframework::prelude::*;
+
+pub struct LocalizationDock {
+    /// The locale code typed into the toolbar.
+    new_locale: String,
+}
+
+impl Dock for LocalizationDock {
+    fn new() -> Self
+    where
+        Self: Sized,
+    {
+        Self {
+            new_locale: String::new(),
+        }
+    }
+
+    fn setup(&mut self, _ctx: &mut TheContext) -> TheCanvas {
+        let mut canvas = TheCanvas::new();
+
+        let mut shared_layout = TheSharedHLayout::new(TheId::named("Localization Shared Layout"));
+        shared_layout.set_shared_ratio(0.2);
+        shared_layout.set_mode(TheSharedHLayoutMode::Shared);
+
+        // The locales with their missing translations
+        let mut list_canvas = TheCanvas::new();
+        let list_layout = TheListLayout::new(TheId::named("Localization List"));
+        list_canvas.set_layout(list_layout);
+
+        let mut list_toolbar_canvas = TheCanvas::default();
+        list_toolbar_canvas.set_widget(TheTraybar::new(TheId::empty()));
+        let mut list_toolbar_hlayout = TheHLayout::new(TheId::empty());
+        list_toolbar_hlayout.set_background_color(None);
+        list_toolbar_hlayout.set_margin(Vec4::new(10, 1, 5, 1));
+        list_toolbar_hlayout.set_padding(3);
+
+        let mut locale_edit = TheTextLineEdit::new(TheId::named("Localization Locale Edit"));
+        locale_edit.set_text("".to_string());
+        locale_edit.limiter_mut().set_max_size(Vec2::new(50, 18));
+        locale_edit.set_font_size(12.5);
+        locale_edit.set_embedded(true);
+        locale_edit.set_status_text("The code of the locale to add, e.g. \"de\" or \"pt_BR\".");
+        locale_edit.set_continuous(true);
+        list_toolbar_hlayout.add_widget(Box::new(locale_edit));
+
+        let mut add_button = TheTraybarButton::new(TheId::named("Localization Add Locale"));
+        add_button.set_text("Add".to_string());
+        add_button.set_status_text("Add the locale.");
+        list_toolbar_hlayout.add_widget(Box::new(add_button));
+
+        let mut remove_button = TheTraybarButton::new(TheId::named("Localization Remove Locale"));
+        remove_button.set_text("Remove".to_string());
+        remove_button.set_status_text("Remove the selected locale and its translations.");
+        list_toolbar_hlayout.add_widget(Box::new(remove_button));
+
+        list_toolbar_canvas.set_layout(list_toolbar_hlayout);
+        list_canvas.set_bottom(list_toolbar_canvas);
+
+        // The strings of the selected locale
+        let mut edit_canvas = TheCanvas::new();
+
+        let mut toolbar_canvas = TheCanvas::default();
+        toolbar_canvas.set_widget(TheTraybar::new(TheId::empty()));
+        let mut toolbar_hlayout = TheHLayout::new(TheId::empty());
+        toolbar_hlayout.set_background_color(None);
+        toolbar_hlayout.set_margin(Vec4::new(10, 1, 5, 1));
+        toolbar_hlayout.set_padding(3);
+
+        let mut extract_button = TheTraybarButton::new(TheId::named("Localization Extract"));
+        extract_button.set_text("Extract".to_string());
+        extract_button.set_status_text(
+            "Collect the translatable strings of screens, widgets, script messages and names.",
+        );
+        toolbar_hlayout.add_widget(Box::new(extract_button));
+
+        let mut apply_button = TheTraybarButton::new(TheId::named("Localization Apply"));
+        apply_button.set_text("Apply".to_string());
+        apply_button.set_status_text("Apply the edited strings to the locale.");
+        toolbar_hlayout.add_widget(Box::new(apply_button));
+
+        let mut preview_button = TheTraybarButton::new(TheId::named("Localization Preview"));
+        preview_button.set_text("Play in Locale".to_string());
+        preview_button
+            .set_status_text("Run the game preview in the selected locale (marked with *).");
+        toolbar_hlayout.add_widget(Box::new(preview_button));
+
+        let mut import_button = TheTraybarButton::new(TheId::named("Localization Import"));
+        import_button.set_text("Import".to_string());
+        import_button.set_status_text("Import translations from CSV, XLIFF or gettext PO.");
+        toolbar_hlayout.add_widget(Box::new(import_button));
+
+        let mut format_drop_down = TheDropdownMenu::new(TheId::named("Localization Format"));
+        format_drop_down.add_option("CSV".to_string());
+        format_drop_down.add_option("XLIFF".to_string());
+        format_drop_down.add_option("PO".to_string());
+        format_drop_down.set_status_text(
+            "The export format. CSV holds all locales, XLIFF and PO the selected one.",
+        );
+        toolbar_hlayout.add_widget(Box::new(format_drop_down));
+
+        let mut export_button = TheTraybarButton::new(TheId::named("Localization Export"));
+        export_button.set_text("Export".to_string());
+        export_button.set_status_text("Export the strings for translation.");
+        toolbar_hlayout.add_widget(Box::new(export_button));
+        toolbar_hlayout.set_reverse_index(Some(3));
+
+        toolbar_canvas.set_layout(toolbar_hlayout);
+        edit_canvas.set_top(toolbar_canvas);
+
+        let mut textedit = TheTextAreaEdit::new(TheId::named("Localization Edit"));
+        textedit.auto_scroll_to_cursor(false);
+        if let Some(bytes) = crate::Embedded::get("parser/TOML.sublime-syntax") {
+            if let Ok(source) = std::str::from_utf8(bytes.data.as_ref()) {
+                textedit.add_syntax_from_string(source);
+                textedit.set_code_type("TOML");
+            }
+        }
+        textedit.set_continuous(true);
+        textedit.display_line_number(true);
+        textedit.set_code_theme("base16-eighties.dark");
+        textedit.use_global_statusbar(true);
+        textedit.set_font_size(14.0);
+        edit_canvas.set_widget(textedit);
+
+        shared_layout.add_canvas(list_canvas);
+        shared_layout.add_canvas(edit_canvas);
+        canvas.set_layout(shared_layout);
+
+        canvas
+    }
+
+    fn activate(
+        &mut self,
+        ui: &mut TheUI,
+        ctx: &mut TheContext,
+        project: &Project,
+        _server_ctx: &mut ServerContext,
+    ) {
+        LOCALIZATIONEDITOR
+            .write()
+            .unwrap()
+            .refresh(project, ui, ctx);
+    }
+
+    fn supports_actions(&self) -> bool {
+        false
+    }
+
+    fn handle_event(
+        &mut self,
+        event: &TheEvent,
+        ui: &mut TheUI,
+        ctx: &mut TheContext,
+        project: &mut Project,
+        _server_ctx: &mut ServerContext,
+    ) -> bool {
+        let mut redraw = false;
+        let prev = matches!(
+            event,
+            TheEvent::StateChanged(_, TheWidgetState::Clicked)
+                | TheEvent::FileRequesterResult(_, _)
+        )
+        .then(|| project.localization.clone());
+        let mut status = None;
+
+        match event {
+            TheEvent::ValueChanged(id, value) => {
+                if id.name == "Localization Edit" {
+                    if let Some(text) = value.to_string() {
+                        LOCALIZATIONEDITOR.write().unwrap().edited = Some(text);
+                    }
+                } else if id.name == "Localization Locale Edit" {
+                    if let Some(text) = value.to_string() {
+                        self.new_locale = text;
+                    }
+                }
+            }
+            TheEvent::IndexChanged(id, index) => {
+                if id.name == "Localization Format" {
+                    LOCALIZATIONEDITOR.write().unwrap().format =
+                        LocalizationFormat::from_index(*index);
+                }
+            }
+            TheEvent::StateChanged(id, TheWidgetState::Selected) => {
+                if id.name == "Localization Item" {
+                    let mut editor = LOCALIZATIONEDITOR.write().unwrap();
+                    editor.select(project, &id.uuid);
+                    editor.update(project, ui, ctx);
+                    redraw = true;
+                }
+            }
+            TheEvent::StateChanged(id, TheWidgetState::Clicked) => {
+                if id.name == "Localization Extract" {
+                    let mut localization = project.localization.clone();
+                    let added = localization.extract(project);
+                    project.localization = localization;
+                    status = Some(format!("Extracted {} new strings.", added));
+                } else if id.name == "Localization Apply" {
+                    status = Some(
+                        match LOCALIZATIONEDITOR
+                            .write()
+                            .unwrap()
+                            .apply(&mut project.localization)
+                        {
+                            Ok(count) => format!("Applied {} changed strings.", count),
+                            Err(err) => err,
+                        },
+                    );
+                } else if id.name == "Localization Add Locale" {
+                    status = Some(if project.localization.add_locale(&self.new_locale) {
+                        LOCALIZATIONEDITOR.write().unwrap().locale =
+                            self.new_locale.trim().to_string();
+                        format!("Added locale \"{}\".", self.new_locale.trim())
+                    } else {
+                        "Invalid or existing locale code.".to_string()
+                    });
+                } else if id.name == "Localization Remove Locale" {
+                    let locale = LOCALIZATIONEDITOR.read().unwrap().locale.clone();
+                    status = Some(if project.localization.remove_locale(&locale) {
+                        format!("Removed locale \"{}\".", locale)
+                    } else {
+                        "The source locale cannot be removed.".to_string()
+                    });
+                } else if id.name == "Localization Preview" {
+                    let locale = LOCALIZATIONEDITOR.read().unwrap().locale.clone();
+                    project.localization.active = locale.clone();
+
+                    // Swap the string tables of a running preview, scripts and
+                    // widgets pick up the locale on the next start.
+                    let mut rusterix = RUSTERIX.write().unwrap();
+                    if rusterix.server.state == rusterix::ServerState::Running {
+                        rusterix.assets.config =
+                            project.localization.runtime_config(&project.config);
+                        rusterix.assets.read_locales();
+                    }
+                    status = Some(format!("The game preview runs in \"{}\".", locale));
+                } else if id.name == "Localization Import" {
+                    ctx.ui.open_file_requester(
+                        TheId::named_with_id("Localization Import File", Uuid::new_v4()),
+                        "Import Translations".into(),
+                        TheFileExtension::new(
+                            "Translations".into(),
+                            vec![
+                                "csv".to_string(),
+                                "xlf".to_string(),
+                                "xliff".to_string(),
+                                "po".to_string(),
+                            ],
+                        ),
+                    );
+                } else if id.name == "Localization Export" {
+                    let editor = LOCALIZATIONEDITOR.read().unwrap();
+                    let extension = editor.format.extension();
+                    ctx.ui.save_file_requester(
+                        TheId::named_with_id("Localization Export File", Uuid::new_v4()),
+                        "Export Translations".into(),
+                        TheFileExtension::new(
+                            format!("{} ({})", editor.locale, extension),
+                            vec![extension.to_string()],
+                        ),
+                    );
+                }
+            }
+            TheEvent::FileRequesterResult(id, paths) => {
+                if id.name == "Localization Import File" {
+                    for p in paths {
+                        status = Some(
+                            match LOCALIZATIONEDITOR
+                                .write()
+                                .unwrap()
+                                .import(&mut project.localization, p)
+                            {
+                                Ok(status) => status,
+                                Err(err) => format!("Unable to import translations: {}", err),
+                            },
+                        );
+                    }
+                } else if id.name == "Localization Export File" {
+                    let editor = LOCALIZATIONEDITOR.read().unwrap();
+                    let contents = editor.export(project);
+                    for p in paths {
+                        status = Some(
+                            if std::fs::write(
+                                p.with_extension(editor.format.extension()),
+                                &contents,
+                            )
+                            .is_ok()
+                            {
+                                "Translations exported successfully.".to_string()
+                            } else {
+                                "Unable to export translations!".to_string()
+                            },
+                        );
+                    }
+                }
+            }
+            _ => {}
+        }
+
+        if let Some(prev) = prev.filter(|prev| *prev != project.localization) {
+            let atom = ProjectUndoAtom::EditLocalization(
+                Box::new(prev),
+                Box::new(project.localization.clone()),
+            );
+            UNDOMANAGER.write().unwrap().add_undo(atom, ctx);
+            LOCALIZATIONEDITOR
+                .write()
+                .unwrap()
+                .refresh(project, ui, ctx);
+            redraw = true;
+        }
+
+        if let Some(status) = status {
+            ctx.ui.send(TheEvent::SetStatusText(TheId::empty(), status));
+            redraw = true;
+        }
+
+        redraw
+    }
+}
@@ -2,6 +2,14 @@ pub mod code;
 pub mod code_undo;
 pub mod data;
 pub mod data_undo;
+pub mod dialogue;
+pub mod inspector;
+pub mod localization;
+pub mod prefabs;
+pub mod profiler;
+pub mod quests;
+pub mod screen_preview;
+pub mod tests;
 pub mod tilemap;
 pub mod tiles;
 pub mod tiles_editor;
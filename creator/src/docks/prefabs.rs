@@ -0,0 +1,441 @@
+use crate::editor::RUSTERIX;
+use crate::prelude::*;
+
+pub struct PrefabsDock {
+    pub prefab_ids: FxHashMap<(i32, i32), Uuid>,
+
+    pub filter: String,
+    pub zoom: f32,
+    pub linked: bool,
+
+    pub curr_prefab: Option<Uuid>,
+}
+
+impl Dock for PrefabsDock {
+    fn new() -> Self
+    where
+        Self: Sized,
+    {
+        Self {
+            prefab_ids: FxHashMap::default(),
+            filter: "".to_string(),
+            zoom: 1.5,
+            linked: false,
+            curr_prefab: None,
+        }
+    }
+
+    fn setup(&mut self, _ctx: &mut TheContext) -> TheCanvas {
+        let mut canvas = TheCanvas::new();
+
+        // Toolbar
+        let mut toolbar_canvas = TheCanvas::default();
+        let traybar_widget = TheTraybar::new(TheId::empty());
+        toolbar_canvas.set_widget(traybar_widget);
+        let mut toolbar_hlayout = TheHLayout::new(TheId::empty());
+        toolbar_hlayout.set_background_color(None);
+        toolbar_hlayout.set_margin(Vec4::new(10, 1, 5, 1));
+        toolbar_hlayout.set_padding(3);
+
+        let mut tiles_button = TheTraybarButton::new(TheId::named("Prefabs Dock Tiles"));
+        tiles_button.set_text("Tiles".to_string());
+        tiles_button.set_status_text("Switch back to the tile picker.");
+        toolbar_hlayout.add_widget(Box::new(tiles_button));
+
+        let mut filter_text = TheText::new(TheId::empty());
+        filter_text.set_text("Filter".to_string());
+        toolbar_hlayout.add_widget(Box::new(filter_text));
+        let mut filter_edit = TheTextLineEdit::new(TheId::named("Prefabs Dock Filter Edit"));
+        filter_edit.set_text("".to_string());
+        filter_edit.limiter_mut().set_max_size(Vec2::new(120, 18));
+        filter_edit.set_font_size(12.5);
+        filter_edit.set_status_text("Show prefabs containing the given text.");
+        filter_edit.set_continuous(true);
+        toolbar_hlayout.add_widget(Box::new(filter_edit));
+
+        let mut spacer = TheSpacer::new(TheId::empty());
+        spacer.limiter_mut().set_max_width(10);
+        toolbar_hlayout.add_widget(Box::new(spacer));
+
+        let mut add_button = TheTraybarButton::new(TheId::named("Prefabs Dock Add"));
+        add_button.set_text("Add".to_string());
+        add_button.set_status_text("Save the selected geometry as a new prefab.");
+        toolbar_hlayout.add_widget(Box::new(add_button));
+
+        let mut update_button = TheTraybarButton::new(TheId::named("Prefabs Dock Update"));
+        update_button.set_text("Update".to_string());
+        update_button.set_status_text(
+            "Replace the geometry of the current prefab with the selection and update its linked instances.",
+        );
+        toolbar_hlayout.add_widget(Box::new(update_button));
+
+        let mut remove_button = TheTraybarButton::new(TheId::named("Prefabs Dock Remove"));
+        remove_button.set_text("Remove".to_string());
+        remove_button.set_status_text("Remove the current prefab from the library.");
+        toolbar_hlayout.add_widget(Box::new(remove_button));
+
+        let mut linked_button = TheTraybarButton::new(TheId::named("Prefabs Dock Linked"));
+        linked_button.set_text("Unlinked".to_string());
+        linked_button.set_status_text(
+            "Toggle if placed instances are linked to the prefab and update when it changes.",
+        );
+        toolbar_hlayout.add_widget(Box::new(linked_button));
+
+        let mut import_button = TheTraybarButton::new(TheId::named("Prefabs Dock Import"));
+        import_button.set_text("Import".to_string());
+        import_button.set_status_text("Import a prefab file.");
+        toolbar_hlayout.add_widget(Box::new(import_button));
+
+        let mut export_button = TheTraybarButton::new(TheId::named("Prefabs Dock Export"));
+        export_button.set_text("Export".to_string());
+        export_button.set_status_text("Export the current prefab to a file.");
+        toolbar_hlayout.add_widget(Box::new(export_button));
+
+        let mut zoom = TheSlider::new(TheId::named("Prefabs Dock Zoom"));
+        zoom.set_value(TheValue::Float(self.zoom));
+        zoom.set_default_value(TheValue::Float(1.5));
+        zoom.set_range(TheValue::RangeF32(1.0..=3.0));
+        zoom.set_continuous(true);
+        zoom.limiter_mut().set_max_width(120);
+        toolbar_hlayout.add_widget(Box::new(zoom));
+        toolbar_hlayout.set_reverse_index(Some(1));
+
+        toolbar_canvas.set_layout(toolbar_hlayout);
+
+        let mut rgba_layout = TheRGBALayout::new(TheId::named("Prefabs Dock RGBA Layout"));
+        if let Some(rgba_view) = rgba_layout.rgba_view_mut().as_rgba_view() {
+            rgba_view.set_supports_external_zoom(true);
+            rgba_view.set_background([116, 116, 116, 255]);
+            rgba_view.set_grid(Some(48));
+            rgba_view.set_mode(TheRGBAViewMode::TilePicker);
+            let mut c = WHITE;
+            c[3] = 128;
+            rgba_view.set_hover_color(Some(c));
+        }
+
+        canvas.set_top(toolbar_canvas);
+        canvas.set_layout(rgba_layout);
+
+        canvas
+    }
+
+    fn activate(
+        &mut self,
+        ui: &mut TheUI,
+        ctx: &mut TheContext,
+        project: &Project,
+        _server_ctx: &mut ServerContext,
+    ) {
+        self.set_prefabs(project, ui, ctx);
+    }
+
+    fn handle_event(
+        &mut self,
+        event: &TheEvent,
+        ui: &mut TheUI,
+        ctx: &mut TheContext,
+        project: &mut Project,
+        server_ctx: &mut ServerContext,
+    ) -> bool {
+        let mut redraw = false;
+
+        match event {
+            TheEvent::StateChanged(id, TheWidgetState::Clicked) => {
+                if id.name == "Prefabs Dock Tiles" {
+                    ctx.ui.send(TheEvent::Custom(
+                        TheId::named("Set Dock"),
+                        TheValue::Text("Tiles".into()),
+                    ));
+                } else if id.name == "Prefabs Dock Add" {
+                    if project
+                        .get_map(server_ctx)
+                        .map(|m| m.has_selection())
+                        .unwrap_or(false)
+                    {
+                        open_text_dialog(
+                            "Add Prefab",
+                            "Prefab Name",
+                            "Prefab",
+                            Uuid::new_v4(),
+                            ui,
+                            ctx,
+                        );
+                    } else {
+                        self.status("No geometry selected!", ctx);
+                    }
+                } else if id.name == "Prefabs Dock Update" {
+                    self.update_prefab(ui, ctx, project, server_ctx);
+                    redraw = true;
+                } else if id.name == "Prefabs Dock Remove" {
+                    if let Some(prefab_id) = self.curr_prefab.take() {
+                        project.prefabs.shift_remove(&prefab_id);
+                        if server_ctx.paste_prefab.map(|(id, _)| id) == Some(prefab_id) {
+                            server_ctx.paste_prefab = None;
+                            server_ctx.paste_clipboard = None;
+                        }
+                        self.set_prefabs(project, ui, ctx);
+                        redraw = true;
+                    }
+                } else if id.name == "Prefabs Dock Linked" {
+                    self.linked = !self.linked;
+                    if let Some(widget) = ui.get_widget("Prefabs Dock Linked") {
+                        if let Some(button) = widget.as_any().downcast_mut::<TheTraybarButton>() {
+                            button.set_text(
+                                if self.linked { "Linked" } else { "Unlinked" }.to_string(),
+                            );
+                        }
+                    }
+                    if let Some((id, _)) = server_ctx.paste_prefab {
+                        server_ctx.paste_prefab = Some((id, self.linked));
+                    }
+                    redraw = true;
+                } else if id.name == "Prefabs Dock Import" {
+                    ctx.ui.open_file_requester(
+                        TheId::named_with_id("Prefabs Dock Import File", Uuid::new_v4()),
+                        "Import Prefab".into(),
+                        TheFileExtension::new("Prefab".into(), vec!["prefab".to_string()]),
+                    );
+                } else if id.name == "Prefabs Dock Export" {
+                    if self.curr_prefab.is_some() {
+                        ctx.ui.save_file_requester(
+                            TheId::named_with_id("Prefabs Dock Export File", Uuid::new_v4()),
+                            "Export Prefab".into(),
+                            TheFileExtension::new("Prefab".into(), vec!["prefab".to_string()]),
+                        );
+                    } else {
+                        self.status("No prefab selected!", ctx);
+                    }
+                }
+            }
+            TheEvent::DialogValueOnClose(role, name, _uuid, value) => {
+                if name == "Add Prefab" && *role == TheDialogButtonRole::Accept {
+                    let prefab = project
+                        .get_map(server_ctx)
+                        .and_then(|map| Prefab::from_selection(value.describe(), map, project));
+                    if let Some(prefab) = prefab {
+                        self.curr_prefab = Some(prefab.id);
+                        project.prefabs.insert(prefab.id, prefab);
+                        self.set_prefabs(project, ui, ctx);
+                        redraw = true;
+                    }
+                }
+            }
+            TheEvent::FileRequesterResult(id, paths) => {
+                if id.name == "Prefabs Dock Import File" {
+                    for p in paths {
+                        let prefab = std::fs::read_to_string(p)
+                            .ok()
+                            .and_then(|json| Prefab::from_json(&json));
+                        if let Some(mut prefab) = prefab {
+                            // Importing the same file twice creates two prefabs
+                            if project.prefabs.contains_key(&prefab.id) {
+                                prefab.id = Uuid::new_v4();
+                            }
+                            let tiles = prefab.import_tiles(project);
+                            if tiles > 0 {
+                                ctx.ui.send(TheEvent::Custom(
+                                    TheId::named("Update Tilepicker"),
+                                    TheValue::Empty,
+                                ));
+                            }
+                            self.status(
+                                &format!(
+                                    "Imported prefab \"{}\" ({} new tiles).",
+                                    prefab.name, tiles
+                                ),
+                                ctx,
+                            );
+                            self.curr_prefab = Some(prefab.id);
+                            project.prefabs.insert(prefab.id, prefab);
+                        } else {
+                            self.status("Unable to import prefab!", ctx);
+                        }
+                    }
+                    self.set_prefabs(project, ui, ctx);
+                    redraw = true;
+                } else if id.name == "Prefabs Dock Export File" {
+                    if let Some(prefab) = self.curr_prefab.and_then(|id| project.prefabs.get(&id)) {
+                        for p in paths {
+                            if std::fs::write(p.with_extension("prefab"), prefab.to_json()).is_ok()
+                            {
+                                self.status("Prefab exported successfully.", ctx);
+                            } else {
+                                self.status("Unable to export prefab!", ctx);
+                            }
+                        }
+                    }
+                }
+            }
+            TheEvent::Resize => {
+                self.set_prefabs(project, ui, ctx);
+            }
+            TheEvent::TilePicked(id, pos) => {
+                if id.name == "Prefabs Dock RGBA Layout View" {
+                    if let Some(prefab_id) = self.prefab_ids.get(&(pos.x, pos.y)) {
+                        if let Some(prefab) = project.prefabs.get(prefab_id) {
+                            self.curr_prefab = Some(*prefab_id);
+                            server_ctx.paste_clipboard = Some(prefab.map.clone());
+                            server_ctx.paste_prefab = Some((*prefab_id, self.linked));
+                            server_ctx.paste_prop = None;
+                            self.status(
+                                &format!(
+                                    "Placing \"{}\". Click to insert, Escape to cancel.",
+                                    prefab.name
+                                ),
+                                ctx,
+                            );
+                            RUSTERIX.write().unwrap().set_dirty();
+                            redraw = true;
+                        }
+                    }
+                }
+            }
+            TheEvent::TileEditorHoverChanged(id, pos) => {
+                if id.name == "Prefabs Dock RGBA Layout View" {
+                    if let Some(prefab) = self
+                        .prefab_ids
+                        .get(&(pos.x, pos.y))
+                        .and_then(|id| project.prefabs.get(id))
+                    {
+                        ctx.ui
+                            .send(TheEvent::SetStatusText(id.clone(), prefab.name.clone()));
+                    }
+                }
+            }
+            TheEvent::TileEditorDelete(id, selected) => {
+                if id.name == "Prefabs Dock RGBA Layout View" {
+                    for pos in selected {
+                        if let Some(prefab_id) = self.prefab_ids.get(pos) {
+                            project.prefabs.shift_remove(prefab_id);
+                        }
+                    }
+                    self.curr_prefab = None;
+                    self.set_prefabs(project, ui, ctx);
+                }
+            }
+            TheEvent::TileZoomBy(id, delta) => {
+                if id.name == "Prefabs Dock RGBA Layout View" {
+                    self.zoom += *delta * 0.05;
+                    self.zoom = self.zoom.clamp(1.0, 3.0);
+                    self.set_prefabs(project, ui, ctx);
+                    ui.set_widget_value("Prefabs Dock Zoom", ctx, TheValue::Float(self.zoom));
+                }
+            }
+            TheEvent::ValueChanged(id, value) => {
+                if id.name == "Prefabs Dock Filter Edit" {
+                    if let TheValue::Text(filter) = value {
+                        self.filter = filter.to_lowercase();
+                        self.set_prefabs(project, ui, ctx);
+                    }
+                } else if id.name == "Prefabs Dock Zoom" {
+                    if let TheValue::Float(zoom) = value {
+                        self.zoom = *zoom;
+                        self.set_prefabs(project, ui, ctx);
+                    }
+                }
+            }
+            _ => {}
+        }
+        redraw
+    }
+}
+
+impl PrefabsDock {
+    /// Set the prefabs for the picker.
+    pub fn set_prefabs(&mut self, project: &Project, ui: &mut TheUI, ctx: &mut TheContext) {
+        self.prefab_ids.clear();
+        if let Some(editor) = ui.get_rgba_layout("Prefabs Dock RGBA Layout") {
+            let width = editor.dim().width - 16;
+            let height = editor.dim().height - 16;
+
+            if width <= 0 {
+                return;
+            }
+
+            if let Some(rgba_view) = editor.rgba_view_mut().as_rgba_view() {
+                let grid = (32_f32 * self.zoom) as i32;
+                rgba_view.set_grid(Some(grid));
+
+                let filtered: Vec<&Prefab> = project
+                    .prefabs
+                    .values()
+                    .filter(|p| p.name.to_lowercase().contains(&self.filter))
+                    .collect();
+
+                let per_row = (width / grid).max(1);
+                let lines = filtered.len() as i32 / per_row + 1;
+
+                let mut buffer =
+                    TheRGBABuffer::new(TheDim::sized(width, (lines * grid).max(height)));
+
+                for (i, prefab) in filtered.iter().enumerate() {
+                    let x = i as i32 % per_row;
+                    let y = i as i32 / per_row;
+
+                    self.prefab_ids.insert((x, y), prefab.id);
+                    if prefab.thumbnail.len() == PREFAB_THUMBNAIL_SIZE * PREFAB_THUMBNAIL_SIZE * 4 {
+                        let thumbnail = TheRGBABuffer::from(
+                            prefab.thumbnail.clone(),
+                            PREFAB_THUMBNAIL_SIZE as u32,
+                            PREFAB_THUMBNAIL_SIZE as u32,
+                        );
+                        buffer.copy_into(x * grid, y * grid, &thumbnail.scaled(grid, grid));
+                    }
+                }
+
+                rgba_view.set_buffer(buffer);
+            }
+            editor.relayout(ctx);
+        }
+    }
+
+    /// Replaces the geometry of the current prefab with the selection and
+    /// updates the linked instances in all regions.
+    fn update_prefab(
+        &mut self,
+        ui: &mut TheUI,
+        ctx: &mut TheContext,
+        project: &mut Project,
+        server_ctx: &mut ServerContext,
+    ) {
+        let Some(prefab_id) = self.curr_prefab else {
+            self.status("No prefab selected!", ctx);
+            return;
+        };
+        let Some(mut prefab) = project.prefabs.get(&prefab_id).cloned() else {
+            return;
+        };
+        match project.get_map(server_ctx) {
+            Some(map) if map.has_selection() => prefab.set_geometry(map, project),
+            _ => {
+                self.status("No geometry selected!", ctx);
+                return;
+            }
+        }
+
+        let mut instances = 0;
+        for region in project.regions.iter_mut() {
+            instances += prefab.update_instances(&mut region.map);
+        }
+        if server_ctx.paste_prefab.map(|(id, _)| id) == Some(prefab_id) {
+            server_ctx.paste_clipboard = Some(prefab.map.clone());
+        }
+        project.prefabs.insert(prefab_id, prefab);
+
+        self.set_prefabs(project, ui, ctx);
+        RUSTERIX.write().unwrap().set_dirty();
+        ctx.ui.send(TheEvent::Custom(
+            TheId::named("Update Minimaps"),
+            TheValue::Empty,
+        ));
+        self.status(
+            &format!("Prefab updated, {} linked instances replaced.", instances),
+            ctx,
+        );
+    }
+
+    fn status(&self, text: &str, ctx: &mut TheContext) {
+        ctx.ui
+            .send(TheEvent::SetStatusText(TheId::empty(), text.to_string()));
+    }
+}
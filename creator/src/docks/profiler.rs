@@ -0,0 +1,281 @@
+use crate::editor::PROFILER;
+use crate::prelude::*;
+use theframework::prelude::*;
+
+const ROW_HEIGHT: usize = 18;
+const TIMELINE_HEIGHT: usize = 40;
+
+pub struct ProfilerDock {}
+
+impl Dock for ProfilerDock {
+    fn new() -> Self
+    where
+        Self: Sized,
+    {
+        Self {}
+    }
+
+    fn setup(&mut self, _ctx: &mut TheContext) -> TheCanvas {
+        let mut canvas = TheCanvas::new();
+
+        let mut toolbar_canvas = TheCanvas::default();
+        toolbar_canvas.set_widget(TheTraybar::new(TheId::empty()));
+        let mut toolbar_hlayout = TheHLayout::new(TheId::empty());
+        toolbar_hlayout.set_background_color(None);
+        toolbar_hlayout.set_margin(Vec4::new(10, 1, 5, 1));
+        toolbar_hlayout.set_padding(3);
+
+        let mut record_button = TheTraybarButton::new(TheId::named("Profiler Record"));
+        record_button.set_text("Record".to_string());
+        record_button
+            .set_status_text("Start or stop recording the game loop of the running server.");
+        toolbar_hlayout.add_widget(Box::new(record_button));
+
+        let mut clear_button = TheTraybarButton::new(TheId::named("Profiler Clear"));
+        clear_button.set_text("Clear".to_string());
+        clear_button.set_status_text("Clear the recorded frames.");
+        toolbar_hlayout.add_widget(Box::new(clear_button));
+
+        let mut export_button = TheTraybarButton::new(TheId::named("Profiler Export"));
+        export_button.set_text("Export Trace".to_string());
+        export_button
+            .set_status_text("Export the recorded frames as Chrome trace JSON (chrome://tracing).");
+        toolbar_hlayout.add_widget(Box::new(export_button));
+
+        toolbar_canvas.set_layout(toolbar_hlayout);
+        canvas.set_top(toolbar_canvas);
+
+        let render_view = TheRenderView::new(TheId::named("Profiler View"));
+        canvas.set_widget(render_view);
+
+        canvas
+    }
+
+    fn activate(
+        &mut self,
+        ui: &mut TheUI,
+        ctx: &mut TheContext,
+        _project: &Project,
+        _server_ctx: &mut ServerContext,
+    ) {
+        draw_profiler(ui, ctx);
+    }
+
+    fn supports_actions(&self) -> bool {
+        false
+    }
+
+    fn handle_event(
+        &mut self,
+        event: &TheEvent,
+        ui: &mut TheUI,
+        ctx: &mut TheContext,
+        _project: &mut Project,
+        _server_ctx: &mut ServerContext,
+    ) -> bool {
+        let mut redraw = false;
+
+        match event {
+            TheEvent::StateChanged(id, TheWidgetState::Clicked) => {
+                if id.name == "Profiler Record" {
+                    let enabled = !PROFILER.read().unwrap().enabled;
+                    PROFILER.write().unwrap().set_enabled(enabled);
+                    if let Some(widget) = ui.get_widget("Profiler Record") {
+                        if let Some(button) = widget.as_any().downcast_mut::<TheTraybarButton>() {
+                            button.set_text(if enabled { "Stop" } else { "Record" }.to_string());
+                        }
+                    }
+                    draw_profiler(ui, ctx);
+                    redraw = true;
+                } else if id.name == "Profiler Clear" {
+                    PROFILER.write().unwrap().clear();
+                    draw_profiler(ui, ctx);
+                    redraw = true;
+                } else if id.name == "Profiler Export" {
+                    ctx.ui.save_file_requester(
+                        TheId::named_with_id("Profiler Export Trace", Uuid::new_v4()),
+                        "Export Trace".into(),
+                        TheFileExtension::new("Chrome Trace".into(), vec!["json".to_string()]),
+                    );
+                }
+            }
+            TheEvent::FileRequesterResult(id, paths) => {
+                if id.name == "Profiler Export Trace" {
+                    let trace = PROFILER.read().unwrap().to_chrome_trace();
+                    for p in paths {
+                        let status = if std::fs::write(p.with_extension("json"), &trace).is_ok() {
+                            "Trace exported successfully.".to_string()
+                        } else {
+                            "Unable to export trace!".to_string()
+                        };
+                        ctx.ui.send(TheEvent::SetStatusText(TheId::empty(), status));
+                    }
+                }
+            }
+            _ => {}
+        }
+
+        redraw
+    }
+}
+
+/// Returns the color of a span category.
+fn category_color(category: &str) -> RGBA {
+    match category {
+        "server" => [196, 120, 80, 255],
+        "region" => [90, 150, 200, 255],
+        "client" => [120, 180, 100, 255],
+        _ => [150, 150, 150, 255],
+    }
+}
+
+/// Draws the frame timeline, the flame chart of the latest frame and the
+/// per span averages into the profiler view.
+pub fn draw_profiler(ui: &mut TheUI, ctx: &mut TheContext) {
+    let Some(render_view) = ui.get_render_view("Profiler View") else {
+        return;
+    };
+    let dim = *render_view.dim();
+    if dim.width <= 0 || dim.height <= 0 {
+        return;
+    }
+
+    let buffer = render_view.render_buffer_mut();
+    buffer.resize(dim.width, dim.height);
+
+    let width = dim.width as usize;
+    let height = dim.height as usize;
+    let stride = buffer.stride();
+
+    let bg_color = [30, 30, 30, 255];
+    let dark_bg_color = [45, 45, 45, 255];
+    let text_color = [200, 200, 200, 255];
+    let font = TheFontSettings {
+        size: 11.5,
+        ..Default::default()
+    };
+
+    ctx.draw.rect(
+        buffer.pixels_mut(),
+        &(0, 0, width, height),
+        stride,
+        &bg_color,
+    );
+
+    let profiler = PROFILER.read().unwrap();
+
+    if profiler.frames.is_empty() {
+        let text = if profiler.enabled {
+            "Recording, start the server to profile the game loop."
+        } else {
+            "Press Record to profile the game loop, record before starting the server to include the script callbacks."
+        };
+        ctx.draw.text_rect(
+            buffer.pixels_mut(),
+            &(10, 10, width.saturating_sub(20), ROW_HEIGHT),
+            stride,
+            text,
+            font,
+            &text_color,
+            &bg_color,
+        );
+        return;
+    }
+
+    // Timeline of the frame durations, the latest frame on the right
+    let max_frame_us = profiler
+        .frames
+        .iter()
+        .map(|f| f.dur_us)
+        .max()
+        .unwrap_or(1)
+        .max(1);
+    let bar_width = (width / profiler.max_frames).max(1);
+    let mut x = width.saturating_sub(profiler.frames.len() * bar_width);
+    for frame in &profiler.frames {
+        let h = ((frame.dur_us as f32 / max_frame_us as f32) * TIMELINE_HEIGHT as f32) as usize;
+        let h = h.clamp(1, TIMELINE_HEIGHT);
+        ctx.draw.rect(
+            buffer.pixels_mut(),
+            &(x, TIMELINE_HEIGHT - h, bar_width, h),
+            stride,
+            &category_color("server"),
+        );
+        x += bar_width;
+    }
+
+    // Flame chart of the latest frame
+    let mut y = TIMELINE_HEIGHT + 6;
+    if let Some(frame) = profiler.latest() {
+        ctx.draw.text_rect(
+            buffer.pixels_mut(),
+            &(10, y, width.saturating_sub(20), ROW_HEIGHT),
+            stride,
+            &format!(
+                "Frame {}: {:.2} ms (max {:.2} ms)",
+                frame.index,
+                frame.dur_us as f32 / 1000.0,
+                max_frame_us as f32 / 1000.0
+            ),
+            font.clone(),
+            &text_color,
+            &bg_color,
+        );
+        y += ROW_HEIGHT + 2;
+
+        let scale = width as f32 / frame.dur_us.max(1) as f32;
+        let mut max_depth = 0;
+        for span in &frame.spans {
+            let sx = ((span.start_us.saturating_sub(frame.start_us)) as f32 * scale) as usize;
+            let sw = ((span.dur_us as f32 * scale) as usize).max(1);
+            let sy = y + span.depth as usize * ROW_HEIGHT;
+            if sx >= width || sy + ROW_HEIGHT > height {
+                continue;
+            }
+            let sw = sw.min(width - sx);
+            let color = category_color(span.category);
+            ctx.draw.rect(
+                buffer.pixels_mut(),
+                &(sx, sy, sw, ROW_HEIGHT - 1),
+                stride,
+                &color,
+            );
+            if sw > 40 {
+                ctx.draw.text_rect(
+                    buffer.pixels_mut(),
+                    &(sx + 3, sy, sw - 6, ROW_HEIGHT - 1),
+                    stride,
+                    &format!("{} {:.2}ms", span.name, span.dur_us as f32 / 1000.0),
+                    font.clone(),
+                    &[20, 20, 20, 255],
+                    &color,
+                );
+            }
+            max_depth = max_depth.max(span.depth as usize);
+        }
+        y += (max_depth + 1) * ROW_HEIGHT + 8;
+    }
+
+    // Averages over all recorded frames
+    for (name, category, avg, max) in profiler.summary() {
+        if y + ROW_HEIGHT > height {
+            break;
+        }
+        ctx.draw.rect(
+            buffer.pixels_mut(),
+            &(10, y + 4, 8, 8),
+            stride,
+            &category_color(category),
+        );
+        ctx.draw.text_rect(
+            buffer.pixels_mut(),
+            &(24, y, width.saturating_sub(34), ROW_HEIGHT),
+            stride,
+            &format!("{name} ({category})  avg {avg:.2} ms  max {max:.2} ms"),
+            font.clone(),
+            &text_color,
+            &dark_bg_color,
+        );
+        y += ROW_HEIGHT;
+    }
+}
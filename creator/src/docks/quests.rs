@@ -0,0 +1,124 @@
+use crate::prelude::*;
+use theframework::prelude::*;
+
+pub struct QuestsDock {}
+
+impl Dock for QuestsDock {
+    fn new() -> Self
+    where
+        Self: Sized,
+    {
+        Self {}
+    }
+
+    fn setup(&mut self, _ctx: &mut TheContext) -> TheCanvas {
+        let mut canvas = TheCanvas::new();
+
+        let mut shared_layout = TheSharedHLayout::new(TheId::named("Dock Quest Layout"));
+        shared_layout.set_shared_ratio(0.6);
+        shared_layout.set_mode(TheSharedHLayoutMode::Shared);
+
+        // Description, stages and rewards
+        let mut spec_canvas = TheCanvas::new();
+        let mut textedit = TheTextAreaEdit::new(TheId::named("DockQuestEditor"));
+        if let Some(bytes) = crate::Embedded::get("parser/TOML.sublime-syntax") {
+            if let Ok(source) = std::str::from_utf8(bytes.data.as_ref()) {
+                textedit.add_syntax_from_string(source);
+                textedit.set_code_type("TOML");
+            }
+        }
+        if let Some(bytes) = crate::Embedded::get("parser/gruvbox-dark.tmTheme") {
+            if let Ok(source) = std::str::from_utf8(bytes.data.as_ref()) {
+                textedit.add_theme_from_string(source);
+                textedit.set_code_theme("Gruvbox Dark");
+            }
+        }
+        textedit.set_continuous(true);
+        textedit.display_line_number(true);
+        textedit.use_global_statusbar(true);
+        textedit.set_font_size(14.0);
+        spec_canvas.set_widget(textedit);
+
+        // Outline
+        let mut outline_canvas = TheCanvas::new();
+
+        let mut toolbar_canvas = TheCanvas::default();
+        toolbar_canvas.set_widget(TheTraybar::new(TheId::empty()));
+        let mut toolbar_hlayout = TheHLayout::new(TheId::empty());
+        toolbar_hlayout.set_background_color(None);
+        toolbar_hlayout.set_margin(Vec4::new(10, 1, 5, 1));
+        toolbar_hlayout.set_padding(3);
+
+        let mut text = TheText::new(TheId::empty());
+        text.set_text("Outline".to_string());
+        text.set_text_size(12.0);
+        toolbar_hlayout.add_widget(Box::new(text));
+
+        toolbar_canvas.set_layout(toolbar_hlayout);
+        outline_canvas.set_top(toolbar_canvas);
+
+        let mut outline = TheTextAreaEdit::new(TheId::named("DockQuestOutline"));
+        outline.set_continuous(true);
+        outline.set_code_theme("base16-eighties.dark");
+        outline.use_global_statusbar(true);
+        outline.set_font_size(14.0);
+        outline.readonly(true);
+        outline_canvas.set_widget(outline);
+
+        shared_layout.add_canvas(spec_canvas);
+        shared_layout.add_canvas(outline_canvas);
+        canvas.set_layout(shared_layout);
+
+        canvas
+    }
+
+    fn activate(
+        &mut self,
+        ui: &mut TheUI,
+        ctx: &mut TheContext,
+        project: &Project,
+        server_ctx: &mut ServerContext,
+    ) {
+        if let ProjectContext::Quest(id) = server_ctx.pc {
+            if let Some(quest) = project.quests.get(&id) {
+                ui.set_widget_value("DockQuestEditor", ctx, TheValue::Text(quest.to_toml()));
+                ui.set_widget_value("DockQuestOutline", ctx, TheValue::Text(quest.outline()));
+            }
+        }
+    }
+
+    fn supports_actions(&self) -> bool {
+        false
+    }
+
+    fn handle_event(
+        &mut self,
+        event: &TheEvent,
+        ui: &mut TheUI,
+        ctx: &mut TheContext,
+        project: &mut Project,
+        server_ctx: &mut ServerContext,
+    ) -> bool {
+        let mut redraw = false;
+
+        if let TheEvent::ValueChanged(id, value) = event {
+            if id.name == "DockQuestEditor" {
+                if let ProjectContext::Quest(quest_id) = server_ctx.pc {
+                    if let (Some(source), Some(quest)) =
+                        (value.to_string(), project.quests.get_mut(&quest_id))
+                    {
+                        // Invalid TOML keeps the last valid quest
+                        let outline = match quest.apply_toml(&source) {
+                            Ok(()) => quest.outline(),
+                            Err(err) => format!("Error: {}", err.trim()),
+                        };
+                        ui.set_widget_value("DockQuestOutline", ctx, TheValue::Text(outline));
+                        redraw = true;
+                    }
+                }
+            }
+        }
+
+        redraw
+    }
+}
@@ -0,0 +1,273 @@
+use crate::prelude::*;
+use theframework::prelude::*;
+
+/// The window heights offered for each aspect ratio.
+const HEIGHTS: [i32; 5] = [540, 720, 1080, 1440, 2160];
+
+/// Shows the widgets of the current screen laid out for a window of another
+/// resolution and aspect ratio, with the letterbox bars and the safe area.
+pub struct ScreenPreviewDock {
+    aspect: ScreenAspectRatio,
+    height: i32,
+    scaling: Option<ScreenScaling>,
+}
+
+impl Dock for ScreenPreviewDock {
+    fn new() -> Self
+    where
+        Self: Sized,
+    {
+        Self {
+            aspect: ScreenAspectRatio::_16_9,
+            height: HEIGHTS[0],
+            scaling: None,
+        }
+    }
+
+    fn setup(&mut self, _ctx: &mut TheContext) -> TheCanvas {
+        let mut canvas = TheCanvas::new();
+
+        let mut toolbar_canvas = TheCanvas::default();
+        toolbar_canvas.set_widget(TheTraybar::new(TheId::empty()));
+        let mut toolbar_hlayout = TheHLayout::new(TheId::empty());
+        toolbar_hlayout.set_background_color(None);
+        toolbar_hlayout.set_margin(Vec4::new(10, 1, 5, 1));
+        toolbar_hlayout.set_padding(3);
+
+        let mut tiles_button = TheTraybarButton::new(TheId::named("Screen Preview Tiles"));
+        tiles_button.set_text("Tiles".to_string());
+        tiles_button.set_status_text("Switch back to the tile library.");
+        toolbar_hlayout.add_widget(Box::new(tiles_button));
+
+        let mut aspect_drop_down = TheDropdownMenu::new(TheId::named("Screen Preview Aspect"));
+        for aspect in ScreenAspectRatio::iterator() {
+            aspect_drop_down.add_option(aspect.to_string().to_string());
+        }
+        aspect_drop_down.set_status_text("The aspect ratio of the previewed window.");
+        toolbar_hlayout.add_widget(Box::new(aspect_drop_down));
+
+        let mut height_drop_down = TheDropdownMenu::new(TheId::named("Screen Preview Height"));
+        for height in HEIGHTS {
+            height_drop_down.add_option(format!("{}p", height));
+        }
+        height_drop_down.set_status_text("The height of the previewed window.");
+        toolbar_hlayout.add_widget(Box::new(height_drop_down));
+
+        let mut scaling_drop_down = TheDropdownMenu::new(TheId::named("Screen Preview Scaling"));
+        scaling_drop_down.add_option("Config".to_string());
+        for scaling in ScreenScaling::iterator() {
+            scaling_drop_down.add_option(scaling.to_string().to_string());
+        }
+        scaling_drop_down
+            .set_status_text("Preview another scaling than the one of the [viewport] config.");
+        toolbar_hlayout.add_widget(Box::new(scaling_drop_down));
+
+        toolbar_canvas.set_layout(toolbar_hlayout);
+        canvas.set_top(toolbar_canvas);
+
+        let render_view = TheRenderView::new(TheId::named("Screen Preview View"));
+        canvas.set_widget(render_view);
+
+        canvas
+    }
+
+    fn activate(
+        &mut self,
+        ui: &mut TheUI,
+        ctx: &mut TheContext,
+        project: &Project,
+        server_ctx: &mut ServerContext,
+    ) {
+        self.draw(ui, ctx, project, server_ctx);
+    }
+
+    fn supports_actions(&self) -> bool {
+        false
+    }
+
+    fn handle_event(
+        &mut self,
+        event: &TheEvent,
+        ui: &mut TheUI,
+        ctx: &mut TheContext,
+        project: &mut Project,
+        server_ctx: &mut ServerContext,
+    ) -> bool {
+        let mut redraw = false;
+
+        match event {
+            TheEvent::StateChanged(id, TheWidgetState::Clicked) => {
+                if id.name == "Screen Preview Tiles" {
+                    ctx.ui.send(TheEvent::Custom(
+                        TheId::named("Set Dock"),
+                        TheValue::Text("Tiles".into()),
+                    ));
+                }
+            }
+            TheEvent::IndexChanged(id, index) => {
+                if id.name == "Screen Preview Aspect" {
+                    if let Some(aspect) = ScreenAspectRatio::from_index(*index as u8) {
+                        self.aspect = aspect;
+                    }
+                } else if id.name == "Screen Preview Height" {
+                    if let Some(height) = HEIGHTS.get(*index) {
+                        self.height = *height;
+                    }
+                } else if id.name == "Screen Preview Scaling" {
+                    self.scaling = index
+                        .checked_sub(1)
+                        .and_then(|index| ScreenScaling::from_index(index as u8));
+                } else {
+                    return false;
+                }
+                self.draw(ui, ctx, project, server_ctx);
+                redraw = true;
+            }
+            TheEvent::Resize => {
+                self.draw(ui, ctx, project, server_ctx);
+            }
+            _ => {}
+        }
+
+        redraw
+    }
+}
+
+impl ScreenPreviewDock {
+    /// The width and height of the previewed window.
+    fn resolution(&self) -> (i32, i32) {
+        (self.aspect.width(self.height), self.height)
+    }
+
+    /// Draws the window scaled into the view, the screen inside of it and the
+    /// widgets of the current screen placed by their anchors.
+    fn draw(
+        &self,
+        ui: &mut TheUI,
+        ctx: &mut TheContext,
+        project: &Project,
+        server_ctx: &ServerContext,
+    ) {
+        let Some(render_view) = ui.get_render_view("Screen Preview View") else {
+            return;
+        };
+        let dim = *render_view.dim();
+        if dim.width <= 0 || dim.height <= 0 {
+            return;
+        }
+
+        let buffer = render_view.render_buffer_mut();
+        buffer.resize(dim.width, dim.height);
+
+        let width = dim.width as usize;
+        let height = dim.height as usize;
+        let stride = buffer.stride();
+
+        let bg_color = [30, 30, 30, 255];
+        let screen_color = [60, 60, 66, 255];
+        let safe_color = [200, 160, 80, 255];
+        let widget_color = [90, 150, 200, 255];
+        let text_color = [200, 200, 200, 255];
+        let font = TheFontSettings {
+            size: 11.5,
+            ..Default::default()
+        };
+
+        ctx.draw.rect(
+            buffer.pixels_mut(),
+            &(0, 0, width, height),
+            stride,
+            &bg_color,
+        );
+
+        let mut viewport = ScreenViewport::from_config(&project.config);
+        if let Some(scaling) = self.scaling {
+            viewport.scaling = scaling;
+        }
+        let (window_w, window_h) = self.resolution();
+        let layout = viewport.layout((window_w as usize, window_h as usize));
+
+        ctx.draw.text_rect(
+            buffer.pixels_mut(),
+            &(10, 4, width.saturating_sub(20), 18),
+            stride,
+            &format!(
+                "{}x{} ({}), {}, screen {}x{} at {:.2}x",
+                window_w,
+                window_h,
+                self.aspect.to_string(),
+                viewport.scaling.to_string(),
+                layout.size.x.round(),
+                layout.size.y.round(),
+                layout.scale.x
+            ),
+            font.clone(),
+            &text_color,
+            &bg_color,
+        );
+
+        // The window, scaled to fit below the info line
+        let (area_y, area_w, area_h) = (26.0, width as f32 - 20.0, height as f32 - 36.0);
+        if area_w <= 0.0 || area_h <= 0.0 {
+            return;
+        }
+        let preview = (area_w / window_w as f32).min(area_h / window_h as f32);
+        let origin = Vec2::new(
+            10.0 + (area_w - window_w as f32 * preview) / 2.0,
+            area_y + (area_h - window_h as f32 * preview) / 2.0,
+        );
+        let to_view = |(x, y, w, h): (f32, f32, f32, f32)| {
+            (
+                (origin.x + x * preview).max(0.0) as usize,
+                (origin.y + y * preview).max(0.0) as usize,
+                (w * preview).max(1.0) as usize,
+                (h * preview).max(1.0) as usize,
+            )
+        };
+
+        let letterbox = TheColor::from_hex(&viewport.letterbox).to_u8_array();
+        ctx.draw.rect(
+            buffer.pixels_mut(),
+            &to_view((0.0, 0.0, window_w as f32, window_h as f32)),
+            stride,
+            &letterbox,
+        );
+        ctx.draw.rect(
+            buffer.pixels_mut(),
+            &to_view(layout.screen_rect()),
+            stride,
+            &screen_color,
+        );
+        ctx.draw.rect_outline(
+            buffer.pixels_mut(),
+            &to_view(layout.to_output(viewport.safe_rect(layout.size))),
+            stride,
+            &safe_color,
+        );
+
+        if server_ctx.get_map_context() != MapContext::Screen {
+            return;
+        }
+        let Some(map) = project.get_map(server_ctx) else {
+            return;
+        };
+        for sector in &map.sectors {
+            let rect = viewport.widget_rect(map, sector);
+            let placed = viewport.place(rect, &WidgetAnchor::from_sector(sector), layout.size);
+            let rect = to_view(layout.to_output(placed));
+            ctx.draw
+                .rect_outline(buffer.pixels_mut(), &rect, stride, &widget_color);
+            if rect.2 > 8 && rect.3 > 14 {
+                ctx.draw.text_rect(
+                    buffer.pixels_mut(),
+                    &(rect.0 + 3, rect.1 + 2, rect.2 - 6, 14),
+                    stride,
+                    &sector.name,
+                    font.clone(),
+                    &text_color,
+                    &screen_color,
+                );
+            }
+        }
+    }
+}
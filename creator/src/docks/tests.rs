@@ -0,0 +1,166 @@
+use crate::prelude::*;
+use theframework::prelude::*;
+
+pub struct TestsDock {}
+
+impl Dock for TestsDock {
+    fn new() -> Self
+    where
+        Self: Sized,
+    {
+        Self {}
+    }
+
+    fn setup(&mut self, _ctx: &mut TheContext) -> TheCanvas {
+        let mut canvas = TheCanvas::new();
+
+        let mut shared_layout = TheSharedHLayout::new(TheId::named("Dock Test Layout"));
+        shared_layout.set_shared_ratio(0.5);
+        shared_layout.set_mode(TheSharedHLayoutMode::Shared);
+
+        // Test specification
+        let mut spec_canvas = TheCanvas::new();
+        let mut textedit = TheTextAreaEdit::new(TheId::named("DockTestEditor"));
+        if let Some(bytes) = crate::Embedded::get("parser/TOML.sublime-syntax") {
+            if let Ok(source) = std::str::from_utf8(bytes.data.as_ref()) {
+                textedit.add_syntax_from_string(source);
+                textedit.set_code_type("TOML");
+            }
+        }
+        if let Some(bytes) = crate::Embedded::get("parser/gruvbox-dark.tmTheme") {
+            if let Ok(source) = std::str::from_utf8(bytes.data.as_ref()) {
+                textedit.add_theme_from_string(source);
+                textedit.set_code_theme("Gruvbox Dark");
+            }
+        }
+        textedit.set_continuous(true);
+        textedit.display_line_number(true);
+        textedit.use_global_statusbar(true);
+        textedit.set_font_size(14.0);
+        spec_canvas.set_widget(textedit);
+
+        // Results
+        let mut results_canvas = TheCanvas::new();
+
+        let mut toolbar_canvas = TheCanvas::default();
+        toolbar_canvas.set_widget(TheTraybar::new(TheId::empty()));
+        let mut toolbar_hlayout = TheHLayout::new(TheId::empty());
+        toolbar_hlayout.set_background_color(None);
+        toolbar_hlayout.set_margin(Vec4::new(10, 1, 5, 1));
+        toolbar_hlayout.set_padding(3);
+
+        let mut text = TheText::new(TheId::empty());
+        text.set_text("Results".to_string());
+        text.set_text_size(12.0);
+        toolbar_hlayout.add_widget(Box::new(text));
+
+        let mut run_button = TheTraybarButton::new(TheId::named("Dock Test Run"));
+        run_button.set_text("Run".to_string());
+        run_button.set_status_text("Run the current test.");
+        toolbar_hlayout.add_widget(Box::new(run_button));
+
+        let mut run_all_button = TheTraybarButton::new(TheId::named("Dock Test Run All"));
+        run_all_button.set_text("Run All".to_string());
+        run_all_button.set_status_text("Run all tests of the project.");
+        toolbar_hlayout.add_widget(Box::new(run_all_button));
+        toolbar_hlayout.set_reverse_index(Some(2));
+
+        toolbar_canvas.set_layout(toolbar_hlayout);
+        results_canvas.set_top(toolbar_canvas);
+
+        let mut results = TheTextAreaEdit::new(TheId::named("DockTestResults"));
+        results.set_continuous(true);
+        results.set_code_theme("base16-eighties.dark");
+        results.use_global_statusbar(true);
+        results.set_font_size(14.0);
+        results.readonly(true);
+        results_canvas.set_widget(results);
+
+        shared_layout.add_canvas(spec_canvas);
+        shared_layout.add_canvas(results_canvas);
+        canvas.set_layout(shared_layout);
+
+        canvas
+    }
+
+    fn activate(
+        &mut self,
+        ui: &mut TheUI,
+        ctx: &mut TheContext,
+        project: &Project,
+        server_ctx: &mut ServerContext,
+    ) {
+        if let ProjectContext::ScriptTest(id) = server_ctx.pc {
+            if let Some(test) = project.tests.get(&id) {
+                ui.set_widget_value("DockTestEditor", ctx, TheValue::Text(test.source.clone()));
+            }
+        }
+    }
+
+    fn supports_actions(&self) -> bool {
+        false
+    }
+
+    fn handle_event(
+        &mut self,
+        event: &TheEvent,
+        ui: &mut TheUI,
+        ctx: &mut TheContext,
+        project: &mut Project,
+        server_ctx: &mut ServerContext,
+    ) -> bool {
+        let mut redraw = false;
+
+        match event {
+            TheEvent::ValueChanged(id, value) => {
+                if id.name == "DockTestEditor" {
+                    if let ProjectContext::ScriptTest(test_id) = server_ctx.pc {
+                        if let Some(source) = value.to_string() {
+                            if let Some(test) = project.tests.get_mut(&test_id) {
+                                test.source = source;
+                                redraw = true;
+                            }
+                        }
+                    }
+                }
+            }
+            TheEvent::StateChanged(id, TheWidgetState::Clicked) => {
+                if id.name == "Dock Test Run" {
+                    if let ProjectContext::ScriptTest(test_id) = server_ctx.pc {
+                        if let Some(test) = project.tests.get(&test_id) {
+                            let results = vec![test.run(project)];
+                            self.show_results(&results, ui, ctx);
+                            redraw = true;
+                        }
+                    }
+                } else if id.name == "Dock Test Run All" {
+                    let results = run_script_tests(project);
+                    self.show_results(&results, ui, ctx);
+                    redraw = true;
+                }
+            }
+            _ => {}
+        }
+
+        redraw
+    }
+}
+
+impl TestsDock {
+    /// Show the results in the results panel and the status bar.
+    fn show_results(&self, results: &[ScriptTestResult], ui: &mut TheUI, ctx: &mut TheContext) {
+        ui.set_widget_value(
+            "DockTestResults",
+            ctx,
+            TheValue::Text(script_test_report(results)),
+        );
+
+        let failed = results.iter().filter(|r| !r.passed).count();
+        let status = if failed == 0 {
+            format!("All {} tests passed.", results.len())
+        } else {
+            format!("{} of {} tests failed.", failed, results.len())
+        };
+        ctx.ui.send(TheEvent::SetStatusText(TheId::empty(), status));
+    }
+}
@@ -46,6 +46,18 @@ impl Dock for TilesDock {
 
         toolbar_hlayout.set_margin(Vec4::new(10, 1, 5, 1));
         toolbar_hlayout.set_padding(3);
+
+        let mut prefabs_button = TheTraybarButton::new(TheId::named("Tiles Dock Prefabs"));
+        prefabs_button.set_text("Prefabs".to_string());
+        prefabs_button.set_status_text("Switch to the prefab library.");
+        toolbar_hlayout.add_widget(Box::new(prefabs_button));
+
+        let mut preview_button = TheTraybarButton::new(TheId::named("Tiles Dock Screen Preview"));
+        preview_button.set_text("Preview".to_string());
+        preview_button
+            .set_status_text("Preview the current screen at other resolutions and aspect ratios.");
+        toolbar_hlayout.add_widget(Box::new(preview_button));
+
         toolbar_hlayout.add_widget(Box::new(filter_text));
         let mut filter_edit = TheTextLineEdit::new(TheId::named("Tiles Dock Filter Edit"));
         filter_edit.set_text("".to_string());
@@ -126,7 +138,17 @@ impl Dock for TilesDock {
 
         match event {
             TheEvent::StateChanged(id, TheWidgetState::Clicked) => {
-                if id.name == "Tiles Dock Tile Copy" {
+                if id.name == "Tiles Dock Prefabs" {
+                    ctx.ui.send(TheEvent::Custom(
+                        TheId::named("Set Dock"),
+                        TheValue::Text("Prefabs".into()),
+                    ));
+                } else if id.name == "Tiles Dock Screen Preview" {
+                    ctx.ui.send(TheEvent::Custom(
+                        TheId::named("Set Dock"),
+                        TheValue::Text("Screen Preview".into()),
+                    ));
+                } else if id.name == "Tiles Dock Tile Copy" {
                     if let Some(tile_id) = self.curr_tile {
                         let txt = format!("\"{tile_id}\"");
                         ctx.ui.clipboard = Some(TheValue::Text(txt.clone()));
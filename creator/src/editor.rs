@@ -45,6 +45,28 @@ pub static CONFIGEDITOR: LazyLock<RwLock<ConfigEditor>> =
     LazyLock::new(|| RwLock::new(ConfigEditor::new()));
 pub static INFOVIEWER: LazyLock<RwLock<InfoViewer>> =
     LazyLock::new(|| RwLock::new(InfoViewer::new()));
+pub static PROFILER: LazyLock<RwLock<Profiler>> = LazyLock::new(|| RwLock::new(Profiler::new()));
+pub static INSPECTOR: LazyLock<RwLock<Inspector>> = LazyLock::new(|| RwLock::new(Inspector::new()));
+pub static LOCALIZATIONEDITOR: LazyLock<RwLock<LocalizationEditor>> =
+    LazyLock::new(|| RwLock::new(LocalizationEditor::new()));
+pub static NAVIGATION: LazyLock<RwLock<Navigation>> =
+    LazyLock::new(|| RwLock::new(Navigation::default()));
+pub static DIALOGUEEDITOR: LazyLock<RwLock<DialogueEditor>> =
+    LazyLock::new(|| RwLock::new(DialogueEditor::new()));
+pub static DIALOGUES: LazyLock<RwLock<DialogueRunner>> =
+    LazyLock::new(|| RwLock::new(DialogueRunner::default()));
+pub static QUESTS: LazyLock<RwLock<QuestTracker>> =
+    LazyLock::new(|| RwLock::new(QuestTracker::default()));
+pub static SHOPS: LazyLock<RwLock<ShopRunner>> =
+    LazyLock::new(|| RwLock::new(ShopRunner::default()));
+pub static COMBAT: LazyLock<RwLock<CombatRunner>> =
+    LazyLock::new(|| RwLock::new(CombatRunner::default()));
+pub static INVENTORY: LazyLock<RwLock<InventoryUi>> =
+    LazyLock::new(|| RwLock::new(InventoryUi::default()));
+pub static GENERATOR: LazyLock<RwLock<GeneratorRunner>> =
+    LazyLock::new(|| RwLock::new(GeneratorRunner::default()));
+pub static INPUTMAP: LazyLock<RwLock<InputMap>> =
+    LazyLock::new(|| RwLock::new(InputMap::default()));
 pub static CONFIG: LazyLock<RwLock<toml::Table>> =
     LazyLock::new(|| RwLock::new(toml::Table::default()));
 pub static NODEEDITOR: LazyLock<RwLock<NodeEditor>> =
@@ -89,6 +111,9 @@ pub struct Editor {
     update_counter: usize,
 
     build_values: ValueContainer,
+
+    /// The audio output of the game view.
+    audio: AudioOutput,
 }
 
 impl TheTrait for Editor {
@@ -132,6 +157,8 @@ impl TheTrait for Editor {
             update_counter: 0,
 
             build_values: ValueContainer::default(),
+
+            audio: AudioOutput::new(),
         }
     }
 
@@ -274,6 +301,20 @@ impl TheTrait for Editor {
             TheId::named("Action Apply"),
             TheAccelerator::new(TheAcceleratorKey::CTRLCMD, 'p'),
         ));
+        let mut game_menu = TheContextMenu::named(str!("Game"));
+        game_menu.add(TheContextMenuItem::new(
+            str!("Play Deterministic"),
+            TheId::named("Play Deterministic"),
+        ));
+        game_menu.add_separator();
+        game_menu.add(TheContextMenuItem::new(
+            str!("Save Replay..."),
+            TheId::named("Save Replay"),
+        ));
+        game_menu.add(TheContextMenuItem::new(
+            str!("Play Replay..."),
+            TheId::named("Play Replay"),
+        ));
         // let mut view_menu = TheContextMenu::named(str!("View"));
         // view_menu.add(TheContextMenuItem::new_with_accel(
         //     str!("2D Map"),
@@ -304,6 +345,7 @@ impl TheTrait for Editor {
 
         menu.add_context_menu(file_menu);
         menu.add_context_menu(edit_menu);
+        menu.add_context_menu(game_menu);
         menu_canvas.set_widget(menu);
 
         // Menubar
@@ -619,11 +661,44 @@ impl TheTrait for Editor {
         }
 
         // Check for redraw (30fps) and tick updates
-        let (redraw_update, tick_update) = self.update_tracker.update(
+        let (redraw_update, mut tick_update) = self.update_tracker.update(
             (1000 / CONFIGEDITOR.read().unwrap().target_fps.clamp(1, 60)) as u64,
             CONFIGEDITOR.read().unwrap().game_tick_ms as u64,
         );
 
+        // In deterministic runs the game ticks are derived from the simulated frames
+        if let Some(clock) = &mut self.server_ctx.simulation_clock {
+            let rusterix = &mut RUSTERIX.write().unwrap();
+            if rusterix.server.state == rusterix::ServerState::Running {
+                tick_update = false;
+                if redraw_update {
+                    // Feed the replay inputs of this frame before advancing
+                    if let Some(player) = &mut self.server_ctx.replay_player {
+                        let inputs = player.inputs_for_frame(clock.frame);
+                        let current_map = rusterix.client.current_map.clone();
+                        if let Some(region) = self
+                            .project
+                            .regions
+                            .iter()
+                            .find(|r| r.map.name == current_map)
+                        {
+                            for input in &inputs {
+                                apply_replay_input(rusterix, input, &region.map);
+                            }
+                        }
+                        if player.is_finished() {
+                            self.server_ctx.replay_player = None;
+                            ctx.ui.send(TheEvent::SetStatusText(
+                                TheId::empty(),
+                                "Replay has finished.".to_string(),
+                            ));
+                        }
+                    }
+                    tick_update = clock.advance();
+                }
+            }
+        }
+
         if tick_update {
             RUSTERIX.write().unwrap().client.inc_animation_frame();
 
@@ -638,6 +713,12 @@ impl TheTrait for Editor {
                     .write()
                     .unwrap()
                     .update(&self.project, ui, ctx, &self.server_ctx);
+                if DOCKMANAGER.read().unwrap().dock == "Inspector" {
+                    INSPECTOR
+                        .write()
+                        .unwrap()
+                        .update(&self.project, ui, ctx, &self.server_ctx);
+                }
             }
         }
 
@@ -658,17 +739,23 @@ impl TheTrait for Editor {
             {
                 let rusterix = &mut RUSTERIX.write().unwrap();
                 if rusterix.server.state == rusterix::ServerState::Running {
+                    PROFILER.write().unwrap().begin_frame();
+
                     // Send a game tick to all servers
                     if tick_update {
-                        rusterix.server.system_tick();
+                        profile("system_tick", "server", || rusterix.server.system_tick());
+                        COMBAT.write().unwrap().tick();
+                        NAVIGATION.write().unwrap().tick();
                     }
 
                     // Send a redraw tick to all servers
                     if redraw_update {
-                        rusterix.server.redraw_tick();
+                        profile("redraw_tick", "server", || rusterix.server.redraw_tick());
                     }
 
-                    if let Some(new_region_name) = rusterix.update_server() {
+                    if let Some(new_region_name) =
+                        profile("update_server", "server", || rusterix.update_server())
+                    {
                         rusterix.client.current_map = new_region_name;
                     }
                     if rusterix.server.log_changed {
@@ -678,8 +765,48 @@ impl TheTrait for Editor {
                             TheValue::Text(rusterix.server.get_log()),
                         );
                     }
+                    let dt = 1.0 / CONFIGEDITOR.read().unwrap().target_fps.clamp(1, 60) as f32;
+                    self.audio.advance(dt);
+                    let mut generate_requests = vec![];
                     for r in &mut self.project.regions {
-                        rusterix.server.apply_entities_items(&mut r.map);
+                        let region_span = PROFILER.write().unwrap().begin(&r.name, "region");
+                        profile("apply_entities_items", "region", || {
+                            rusterix.server.apply_entities_items(&mut r.map)
+                        });
+                        PROFILER.write().unwrap().record_scripts(r);
+                        profile("navigation", "region", || {
+                            NAVIGATION.write().unwrap().update(
+                                rusterix,
+                                r,
+                                self.project.generated_maps.get(&r.id),
+                                &self.project.tiles,
+                            )
+                        });
+                        profile("dialogues", "region", || {
+                            DIALOGUES
+                                .write()
+                                .unwrap()
+                                .update(rusterix, r, &self.project.dialogues)
+                        });
+                        profile("quests", "region", || {
+                            QUESTS
+                                .write()
+                                .unwrap()
+                                .update(rusterix, r, &self.project.quests)
+                        });
+                        profile("shops", "region", || SHOPS.write().unwrap().update(r));
+                        profile("combat", "region", || {
+                            COMBAT.write().unwrap().update(rusterix, r)
+                        });
+                        profile("inventory", "region", || {
+                            INVENTORY.write().unwrap().update(r)
+                        });
+                        generate_requests.extend(GENERATOR.write().unwrap().requests(r));
+                        self.audio.update(
+                            &r.map,
+                            r.map.name == rusterix.client.current_map,
+                            &rusterix.player_camera,
+                        );
 
                         if r.id == self.server_ctx.curr_region {
                             if let Some(time) = rusterix.server.get_time(&r.map.id) {
@@ -689,7 +816,9 @@ impl TheTrait for Editor {
                                 }
                             }
 
-                            rusterix::tile_builder(&mut r.map, &mut rusterix.assets);
+                            profile("tile_builder", "region", || {
+                                rusterix::tile_builder(&mut r.map, &mut rusterix.assets)
+                            });
                             messages = rusterix.server.get_messages(&r.map.id);
                             choices = rusterix.server.get_choices(&r.map.id);
 
@@ -710,6 +839,25 @@ impl TheTrait for Editor {
                                 _ => {}
                             }
                         }
+                        PROFILER.write().unwrap().end(region_span);
+                    }
+
+                    // Regions generated again by scripts replace their instances
+                    let deterministic = self
+                        .server_ctx
+                        .simulation_clock
+                        .as_ref()
+                        .map(|clock| clock.settings);
+                    for (name, seed) in generate_requests {
+                        profile("generate", "server", || {
+                            regenerate_region(
+                                rusterix,
+                                &mut self.project,
+                                &name,
+                                seed,
+                                deterministic,
+                            )
+                        });
                     }
                 }
             }
@@ -748,6 +896,8 @@ impl TheTrait for Editor {
                         let b = &mut rusterix.client.builder_d2;
 
                         if is_running && self.server_ctx.game_mode {
+                            let client_span =
+                                PROFILER.write().unwrap().begin("draw_game", "client");
                             for r in &mut self.project.regions {
                                 if r.map.name == rusterix.client.current_map {
                                     rusterix.draw_game(&r.map, messages, choices);
@@ -758,6 +908,21 @@ impl TheTrait for Editor {
                             rusterix
                                 .client
                                 .insert_game_buffer(render_view.render_buffer_mut());
+                            DIALOGUES.read().unwrap().draw(
+                                &self.project.dialogues,
+                                render_view.render_buffer_mut(),
+                                ctx,
+                            );
+                            INVENTORY.write().unwrap().draw(
+                                render_view.render_buffer_mut(),
+                                ctx,
+                                &self.project.tiles,
+                            );
+                            SHOPS
+                                .read()
+                                .unwrap()
+                                .draw(render_view.render_buffer_mut(), ctx);
+                            PROFILER.write().unwrap().end(client_span);
                         } else {
                             if self.server_ctx.editor_view_mode != EditorViewMode::D2
                                 && self.server_ctx.get_map_context() == MapContext::Region
@@ -975,6 +1140,28 @@ impl TheTrait for Editor {
                             }
                         }
                     }
+                    if self.server_ctx.show_navgrid
+                        && !self.server_ctx.game_mode
+                        && self.server_ctx.editor_view_mode == EditorViewMode::D2
+                        && self.server_ctx.get_map_context() == MapContext::Region
+                        && self.server_ctx.editing_surface.is_none()
+                    {
+                        if let Some(region) = self.project.get_region(&self.server_ctx.curr_region)
+                        {
+                            // While the game runs the navigation grid of the running map is shown
+                            let is_running = RUSTERIX.read().unwrap().server.state
+                                == rusterix::ServerState::Running;
+                            let geometry = self
+                                .project
+                                .generated_maps
+                                .get(&region.id)
+                                .filter(|_| is_running)
+                                .unwrap_or(&region.map);
+                            let mut navigation = NAVIGATION.write().unwrap();
+                            let grid = navigation.grid(region, geometry, &self.project.tiles);
+                            draw_navgrid(render_view.render_buffer_mut(), &region.map, grid);
+                        }
+                    }
                     if !self.server_ctx.game_mode {
                         if let Some(map) = self.project.get_map_mut(&self.server_ctx) {
                             TOOLLIST.write().unwrap().draw_hud(
@@ -1001,6 +1188,12 @@ impl TheTrait for Editor {
             //     }
             // }
 
+            // Finish the profiled frame and update the profiler view
+            PROFILER.write().unwrap().end_frame();
+            if PROFILER.read().unwrap().enabled && DOCKMANAGER.read().unwrap().dock == "Profiler" {
+                crate::docks::profiler::draw_profiler(ui, ctx);
+            }
+
             redraw = true;
         }
 
@@ -1539,6 +1732,47 @@ impl TheTrait for Editor {
                         }
                     }*/
                     TheEvent::FileRequesterResult(id, paths) => {
+                        // Save the recorded replay
+                        if id.name == "Replay Save" {
+                            if let Some(recording) = &self.server_ctx.replay_recording {
+                                for p in paths {
+                                    let path = p.with_extension("eldiron_replay");
+                                    let status =
+                                        if std::fs::write(&path, recording.to_json()).is_ok() {
+                                            "Replay saved.".to_string()
+                                        } else {
+                                            "Unable to save replay.".to_string()
+                                        };
+                                    ctx.ui.send(TheEvent::SetStatusText(TheId::empty(), status));
+                                }
+                            }
+                        } else
+                        // Play back a replay
+                        if id.name == "Replay Open" {
+                            for p in paths {
+                                let replay = std::fs::read_to_string(p)
+                                    .ok()
+                                    .and_then(|contents| Replay::from_json(&contents));
+                                if let Some(replay) = replay {
+                                    if RUSTERIX.read().unwrap().server.state
+                                        != rusterix::ServerState::Off
+                                    {
+                                        RUSTERIX.write().unwrap().server.stop();
+                                        insert_content_into_maps(&mut self.project);
+                                    }
+                                    self.start_game(Some(replay.settings), ctx);
+                                    self.server_ctx.replay_recording = None;
+                                    self.server_ctx.replay_player = Some(ReplayPlayer::new(replay));
+                                    self.update_server_state_icons(ui);
+                                    redraw = true;
+                                } else {
+                                    ctx.ui.send(TheEvent::SetStatusText(
+                                        TheId::empty(),
+                                        "Unable to load replay.".to_string(),
+                                    ));
+                                }
+                            }
+                        } else
                         // Load a palette from a file
                         if id.name == "Palette Import" {
                             for p in paths {
@@ -1931,23 +2165,7 @@ impl TheTrait for Editor {
                         else if id.name == "Play" {
                             let state = RUSTERIX.read().unwrap().server.state;
                             if state == rusterix::ServerState::Off {
-                                start_server(
-                                    &mut RUSTERIX.write().unwrap(),
-                                    &mut self.project,
-                                    true,
-                                );
-                                let commands =
-                                    setup_client(&mut RUSTERIX.write().unwrap(), &mut self.project);
-                                RUSTERIX
-                                    .write()
-                                    .unwrap()
-                                    .server
-                                    .process_client_commands(commands);
-                                ctx.ui.send(TheEvent::SetStatusText(
-                                    TheId::empty(),
-                                    "Server has been started.".to_string(),
-                                ));
-                                RUSTERIX.write().unwrap().player_camera = PlayerCamera::D2;
+                                self.start_game(None, ctx);
                             }
                             /*
                             self.server.start();
@@ -1981,8 +2199,51 @@ impl TheTrait for Editor {
                                 let interactions = self.server.get_interactions();
                                 self.server_ctx.add_interactions(interactions);
                             }*/
+                        } else if id.name == "Play Deterministic" {
+                            let state = RUSTERIX.read().unwrap().server.state;
+                            if state == rusterix::ServerState::Off {
+                                let config = CONFIGEDITOR.read().unwrap();
+                                let settings = DeterministicSettings::new(
+                                    config.get_i32_default("game", "seed", 0) as u64,
+                                    config.target_fps as u32,
+                                    config.game_tick_ms as u32,
+                                );
+                                drop(config);
+                                self.start_game(Some(settings), ctx);
+                                self.server_ctx.replay_recording =
+                                    Some(Replay::new(self.project.name.clone(), settings));
+                                update_server_icons = true;
+                            }
+                        } else if id.name == "Save Replay" {
+                            if self.server_ctx.replay_recording.is_some() {
+                                ctx.ui.save_file_requester(
+                                    TheId::named_with_id("Replay Save", Uuid::new_v4()),
+                                    "Save Replay".into(),
+                                    TheFileExtension::new(
+                                        "Eldiron Replay".into(),
+                                        vec!["eldiron_replay".to_string()],
+                                    ),
+                                );
+                            } else {
+                                ctx.ui.send(TheEvent::SetStatusText(
+                                    TheId::empty(),
+                                    "No replay recorded. Use Play Deterministic first.".to_string(),
+                                ));
+                            }
+                        } else if id.name == "Play Replay" {
+                            ctx.ui.open_file_requester(
+                                TheId::named_with_id("Replay Open", Uuid::new_v4()),
+                                "Play Replay".into(),
+                                TheFileExtension::new(
+                                    "Eldiron Replay".into(),
+                                    vec!["eldiron_replay".to_string()],
+                                ),
+                            );
                         } else if id.name == "Stop" {
                             RUSTERIX.write().unwrap().server.stop();
+                            self.audio.stop();
+                            self.server_ctx.simulation_clock = None;
+                            self.server_ctx.replay_player = None;
                             RUSTERIX.write().unwrap().player_camera = PlayerCamera::D2;
 
                             ui.set_widget_value("InfoView", ctx, TheValue::Text("".into()));
@@ -2181,6 +2442,7 @@ impl TheTrait for Editor {
 
 pub trait EldironEditor {
     fn update_server_state_icons(&mut self, ui: &mut TheUI);
+    fn start_game(&mut self, deterministic: Option<DeterministicSettings>, ctx: &mut TheContext);
 }
 
 impl EldironEditor for Editor {
@@ -2236,4 +2498,50 @@ impl EldironEditor for Editor {
             }
         }
     }
+
+    fn start_game(&mut self, deterministic: Option<DeterministicSettings>, ctx: &mut TheContext) {
+        NAVIGATION.write().unwrap().start(deterministic.map_or(
+            CONFIGEDITOR.read().unwrap().game_tick_ms.max(1) as u32,
+            |d| d.tick_ms,
+        ));
+        DIALOGUES.write().unwrap().clear();
+        QUESTS.write().unwrap().start();
+        SHOPS.write().unwrap().start(&self.project.config);
+        COMBAT
+            .write()
+            .unwrap()
+            .start(&self.project.config, deterministic);
+        INVENTORY.write().unwrap().start(&self.project);
+        GENERATOR.write().unwrap().start(deterministic);
+        *INPUTMAP.write().unwrap() = InputMap::from_config(&self.project.config);
+        self.audio.load(&self.project);
+        start_server(
+            &mut RUSTERIX.write().unwrap(),
+            &mut self.project,
+            true,
+            PROFILER.read().unwrap().enabled,
+            deterministic,
+        );
+        let commands = setup_client(&mut RUSTERIX.write().unwrap(), &mut self.project);
+        RUSTERIX
+            .write()
+            .unwrap()
+            .server
+            .process_client_commands(commands);
+        RUSTERIX.write().unwrap().player_camera = PlayerCamera::D2;
+
+        self.server_ctx.simulation_clock = deterministic.map(SimulationClock::new);
+        self.server_ctx.replay_recording = None;
+        self.server_ctx.replay_player = None;
+
+        let status = if let Some(settings) = deterministic {
+            format!(
+                "Server has been started (deterministic, seed {}).",
+                settings.seed
+            )
+        } else {
+            "Server has been started.".to_string()
+        };
+        ctx.ui.send(TheEvent::SetStatusText(TheId::empty(), status));
+    }
 }
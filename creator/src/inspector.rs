@@ -0,0 +1,500 @@
+use crate::editor::RUSTERIX;
+use crate::prelude::*;
+use rusterix::{Value, ValueContainer};
+use shared::rusterix_utils::*;
+use theframework::prelude::*;
+
+/// Attributes which are set by the creator and not shown in the inspector.
+const HIDDEN_KEYS: [&str; 3] = ["source", "setup", "name"];
+
+/// Whether the attribute is hidden in the inspector.
+fn is_hidden(key: &str) -> bool {
+    HIDDEN_KEYS.contains(&key)
+}
+
+/// A runtime entity or item of the current region.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum InspectorSelection {
+    Entity(u32),
+    Item(u32),
+}
+
+impl InspectorSelection {
+    /// A stable id for the list widgets.
+    pub fn to_uuid(self) -> Uuid {
+        match self {
+            InspectorSelection::Entity(id) => Uuid::from_u128(id as u128),
+            InspectorSelection::Item(id) => Uuid::from_u128((1 << 32) | id as u128),
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum InspectorMode {
+    Attributes,
+    Inventory,
+    Equipped,
+}
+
+impl InspectorMode {
+    pub fn from_index(index: usize) -> Self {
+        match index {
+            1 => InspectorMode::Inventory,
+            2 => InspectorMode::Equipped,
+            _ => InspectorMode::Attributes,
+        }
+    }
+}
+
+/// Shows the runtime state of an entity or item of the running game as TOML and
+/// applies edits of it to the server.
+pub struct Inspector {
+    /// The instance picked in the list. If None, the instance selected in the
+    /// map is shown.
+    pub selection: Option<InspectorSelection>,
+    pub mode: InspectorMode,
+
+    /// The edited text. While the user edits, the view is not refreshed.
+    pub edited: Option<String>,
+
+    /// The instances of the current region, as shown in the list.
+    instances: Vec<(InspectorSelection, String)>,
+
+    /// The attributes the user changed per instance, only these are copied
+    /// into the instance data.
+    applied: FxHashMap<InspectorSelection, Vec<String>>,
+}
+
+impl Default for Inspector {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Inspector {
+    pub fn new() -> Self {
+        Self {
+            selection: None,
+            mode: InspectorMode::Attributes,
+            edited: None,
+            instances: vec![],
+            applied: FxHashMap::default(),
+        }
+    }
+
+    /// Refresh the instance list and, if not edited, the inspector text.
+    pub fn update(
+        &mut self,
+        project: &Project,
+        ui: &mut TheUI,
+        ctx: &mut TheContext,
+        server_ctx: &ServerContext,
+    ) {
+        let Some(map) = project.get_map(server_ctx) else {
+            return;
+        };
+
+        let mut instances = vec![];
+        for entity in map.entities.iter() {
+            let name = entity.attributes.get_str_default("name", "Entity".into());
+            instances.push((
+                InspectorSelection::Entity(entity.id),
+                format!("{} ({})", name, entity.id),
+            ));
+        }
+        for item in map.items.iter() {
+            let name = item.attributes.get_str_default("name", "Item".into());
+            instances.push((
+                InspectorSelection::Item(item.id),
+                format!("{} ({})", name, item.id),
+            ));
+        }
+
+        if instances != self.instances {
+            if let Some(list_layout) = ui.get_list_layout("Inspector List") {
+                list_layout.clear();
+                for (selection, name) in &instances {
+                    let mut item = TheListItem::new(TheId::named_with_id(
+                        "Inspector Item",
+                        selection.to_uuid(),
+                    ));
+                    item.set_text(name.clone());
+                    if Some(*selection) == self.selection {
+                        item.set_state(TheWidgetState::Selected);
+                    }
+                    list_layout.add_item(item, ctx);
+                }
+            }
+            self.instances = instances;
+        }
+
+        if self.edited.is_none() {
+            ui.set_widget_value(
+                "Inspector Edit",
+                ctx,
+                TheValue::Text(self.to_toml(project, map, server_ctx)),
+            );
+        }
+    }
+
+    /// Select the instance with the given list id.
+    pub fn select(&mut self, uuid: &Uuid) {
+        self.selection = self
+            .instances
+            .iter()
+            .find(|(selection, _)| selection.to_uuid() == *uuid)
+            .map(|(selection, _)| *selection);
+        self.edited = None;
+    }
+
+    /// The picked instance, or the instance selected in the map.
+    fn resolve(&self, map: &Map, server_ctx: &ServerContext) -> Option<InspectorSelection> {
+        if let Some(selection) = self.selection {
+            return Some(selection);
+        }
+        match server_ctx.cc {
+            ContentContext::CharacterInstance(uuid) => map
+                .entities
+                .iter()
+                .find(|e| e.creator_id == uuid)
+                .map(|e| InspectorSelection::Entity(e.id)),
+            ContentContext::ItemInstance(uuid) => map
+                .items
+                .iter()
+                .find(|i| i.creator_id == uuid)
+                .map(|i| InspectorSelection::Item(i.id)),
+            _ => None,
+        }
+    }
+
+    /// Generate the TOML for the selected instance in the current mode.
+    fn to_toml(&self, project: &Project, map: &Map, server_ctx: &ServerContext) -> String {
+        let mut output = vec![];
+
+        match self.resolve(map, server_ctx) {
+            Some(InspectorSelection::Entity(id)) => {
+                let Some(entity) = map.entities.iter().find(|e| e.id == id) else {
+                    return String::new();
+                };
+                let name = entity.attributes.get_str_default("name", "Entity".into());
+
+                match self.mode {
+                    InspectorMode::Attributes => {
+                        output.push(format!("# {} ({}), action: {:?}", name, id, entity.action));
+                        output.push(format!(
+                            "position = [{:?}, {:?}, {:?}]",
+                            entity.position.x, entity.position.y, entity.position.z
+                        ));
+                        output.push(String::new());
+                        output.push("[attributes]".into());
+                        Self::push_attributes(&mut output, &entity.attributes);
+                    }
+                    InspectorMode::Inventory => {
+                        output.push(format!("# Inventory of {} ({})", name, id));
+                        for (slot, item) in entity.iter_inventory() {
+                            let item_name = item.attributes.get_str_default("name", "Item".into());
+                            output.push(String::new());
+                            output.push(format!("# Slot {}: {} ({})", slot, item_name, item.id));
+                            output.push(format!("[inventory.{}]", slot));
+                            Self::push_attributes(&mut output, &item.attributes);
+                        }
+                    }
+                    InspectorMode::Equipped => {
+                        output.push(format!("# Equipment of {} ({})", name, id));
+                        for (table, key) in [("gear", "gear_slots"), ("weapons", "weapon_slots")] {
+                            for slot in Self::config_slots(project, key) {
+                                output.push(String::new());
+                                match entity.equipped.iter().find(|(s, _)| s.as_str() == slot) {
+                                    Some((_, item)) => {
+                                        let item_name =
+                                            item.attributes.get_str_default("name", "Item".into());
+                                        output.push(format!(
+                                            "# {}: {} ({})",
+                                            slot, item_name, item.id
+                                        ));
+                                        output.push(format!("[{}.{}]", table, slot));
+                                        Self::push_attributes(&mut output, &item.attributes);
+                                    }
+                                    None => output.push(format!("# {}: empty", slot)),
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+            Some(InspectorSelection::Item(id)) => {
+                let Some(item) = map.items.iter().find(|i| i.id == id) else {
+                    return String::new();
+                };
+                let name = item.attributes.get_str_default("name", "Item".into());
+                output.push(format!("# {} ({})", name, id));
+                output.push(String::new());
+                output.push("[attributes]".into());
+                Self::push_attributes(&mut output, &item.attributes);
+            }
+            None => {
+                output.push("# Select an entity or item in the list or on the map.".into());
+            }
+        }
+
+        output.join("\n")
+    }
+
+    /// Apply the edited text to the running server. Returns the number of
+    /// changed values.
+    pub fn apply(
+        &mut self,
+        project: &mut Project,
+        server_ctx: &ServerContext,
+    ) -> Result<usize, String> {
+        let Some(text) = self.edited.clone() else {
+            return Ok(0);
+        };
+        let table = text
+            .parse::<toml::Table>()
+            .map_err(|err| format!("Invalid TOML: {}", err))?;
+
+        let Some(map) = project.get_map_mut(server_ctx) else {
+            return Err("No region.".into());
+        };
+        let Some(selection) = self.resolve(map, server_ctx) else {
+            return Err("No instance selected.".into());
+        };
+
+        let mut edits = vec![];
+        match selection {
+            InspectorSelection::Entity(id) => {
+                let Some(entity) = map.entities.iter().find(|e| e.id == id) else {
+                    return Err("The instance no longer exists.".into());
+                };
+                match self.mode {
+                    InspectorMode::Attributes => {
+                        if let Some(toml::Value::Array(position)) = table.get("position") {
+                            let coords: Vec<f32> = position
+                                .iter()
+                                .filter_map(|v| v.as_float().or(v.as_integer().map(|i| i as f64)))
+                                .map(|v| v as f32)
+                                .collect();
+                            if coords.len() == 3 {
+                                let position = Vec3::new(coords[0], coords[1], coords[2]);
+                                if position != entity.position {
+                                    edits.push(InstanceEdit::EntityPosition(id, position));
+                                }
+                            }
+                        }
+                        for (key, value) in Self::changed_attributes(&entity.attributes, &table) {
+                            edits.push(InstanceEdit::EntityAttribute(id, key, value));
+                        }
+                    }
+                    InspectorMode::Inventory => {
+                        if let Some(toml::Value::Table(slots)) = table.get("inventory") {
+                            for (slot, item) in entity.iter_inventory() {
+                                if let Some(toml::Value::Table(attrs)) =
+                                    slots.get(&slot.to_string())
+                                {
+                                    for (key, value) in
+                                        Self::changed_values(&item.attributes, attrs)
+                                    {
+                                        edits
+                                            .push(InstanceEdit::ItemAttribute(item.id, key, value));
+                                    }
+                                }
+                            }
+                        }
+                    }
+                    InspectorMode::Equipped => {
+                        for table_name in ["gear", "weapons"] {
+                            if let Some(toml::Value::Table(slots)) = table.get(table_name) {
+                                for (slot, item) in entity.equipped.iter() {
+                                    if let Some(toml::Value::Table(attrs)) =
+                                        slots.get(slot.as_str())
+                                    {
+                                        for (key, value) in
+                                            Self::changed_values(&item.attributes, attrs)
+                                        {
+                                            edits.push(InstanceEdit::ItemAttribute(
+                                                item.id, key, value,
+                                            ));
+                                        }
+                                    }
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+            InspectorSelection::Item(id) => {
+                let Some(item) = map.items.iter().find(|i| i.id == id) else {
+                    return Err("The instance no longer exists.".into());
+                };
+                for (key, value) in Self::changed_attributes(&item.attributes, &table) {
+                    edits.push(InstanceEdit::ItemAttribute(id, key, value));
+                }
+            }
+        }
+
+        let mut rusterix = RUSTERIX.write().unwrap();
+        if rusterix.server.state != rusterix::ServerState::Running {
+            return Err("The server is not running.".into());
+        }
+        for edit in &edits {
+            apply_instance_edit(&mut rusterix, map, edit);
+            let key = match (self.mode, edit) {
+                (InspectorMode::Attributes, InstanceEdit::EntityAttribute(_, key, _))
+                | (InspectorMode::Attributes, InstanceEdit::ItemAttribute(_, key, _)) => key,
+                _ => continue,
+            };
+            let applied = self.applied.entry(selection).or_default();
+            if !applied.contains(key) {
+                applied.push(key.clone());
+            }
+        }
+
+        self.edited = None;
+        Ok(edits.len())
+    }
+
+    /// Copy the attributes changed in the inspector from the selected instance
+    /// into the `[attributes]` table of its TOML data, keeping the comments and
+    /// formatting of the data. Returns the instance name and the undo atom.
+    pub fn copy_to_data(
+        &self,
+        project: &mut Project,
+        server_ctx: &ServerContext,
+    ) -> Result<(String, ProjectUndoAtom), String> {
+        let Some(map) = project.get_map(server_ctx) else {
+            return Err("No region.".into());
+        };
+        let Some(selection) = self.resolve(map, server_ctx) else {
+            return Err("No instance selected.".into());
+        };
+        let keys = match self.applied.get(&selection) {
+            Some(keys) if !keys.is_empty() => keys,
+            _ => return Err("No attributes of the instance were changed.".into()),
+        };
+
+        let (creator_id, attributes) = match selection {
+            InspectorSelection::Entity(id) => map
+                .entities
+                .iter()
+                .find(|e| e.id == id)
+                .map(|e| (e.creator_id, e.attributes.clone())),
+            InspectorSelection::Item(id) => map
+                .items
+                .iter()
+                .find(|i| i.id == id)
+                .map(|i| (i.creator_id, i.attributes.clone())),
+        }
+        .ok_or("The instance no longer exists.")?;
+
+        let region_id = server_ctx.curr_region;
+        let Some(region) = project.get_region_mut(&region_id) else {
+            return Err("No region.".into());
+        };
+
+        let (name, data, pc) = match selection {
+            InspectorSelection::Entity(_) => region.characters.get_mut(&creator_id).map(|c| {
+                (
+                    c.name.clone(),
+                    &mut c.data,
+                    ProjectContext::RegionCharacterInstance(region_id, creator_id),
+                )
+            }),
+            InspectorSelection::Item(_) => region.items.get_mut(&creator_id).map(|i| {
+                (
+                    i.name.clone(),
+                    &mut i.data,
+                    ProjectContext::RegionItemInstance(region_id, creator_id),
+                )
+            }),
+        }
+        .ok_or("The instance was created at runtime and has no data.")?;
+
+        let mut doc = data
+            .parse::<toml_edit::DocumentMut>()
+            .map_err(|err| format!("Invalid instance data: {}", err))?;
+        if !doc.contains_key("attributes") {
+            doc["attributes"] = toml_edit::table();
+        }
+        let Some(attrs) = doc["attributes"].as_table_like_mut() else {
+            return Err("The attributes of the instance data are not a table.".into());
+        };
+        for key in keys {
+            let Some(value) = attributes
+                .get(key)
+                .and_then(value_to_toml)
+                .and_then(|value| value.to_string().parse::<toml_edit::Value>().ok())
+            else {
+                continue;
+            };
+            match attrs.get_mut(key).and_then(|item| item.as_value_mut()) {
+                Some(existing) => {
+                    let decor = existing.decor().clone();
+                    *existing = value;
+                    *existing.decor_mut() = decor;
+                }
+                None => {
+                    attrs.insert(key, toml_edit::value(value));
+                }
+            }
+        }
+
+        let prev = data.clone();
+        *data = doc.to_string();
+
+        Ok((
+            name,
+            ProjectUndoAtom::EditInstanceData(pc, prev, data.clone()),
+        ))
+    }
+
+    /// Push the visible attributes as TOML key / value pairs.
+    fn push_attributes(output: &mut Vec<String>, attributes: &ValueContainer) {
+        for key in attributes.keys_sorted() {
+            if is_hidden(key) {
+                continue;
+            }
+            if let Some(value) = attributes.get(key).and_then(value_to_toml) {
+                output.push(format!("{} = {}", key, value));
+            }
+        }
+    }
+
+    /// The values of the `[attributes]` table which differ from the runtime attributes.
+    fn changed_attributes(
+        attributes: &ValueContainer,
+        table: &toml::Table,
+    ) -> Vec<(String, Value)> {
+        match table.get("attributes") {
+            Some(toml::Value::Table(attrs)) => Self::changed_values(attributes, attrs),
+            _ => vec![],
+        }
+    }
+
+    /// The values of the table which differ from the runtime attributes.
+    fn changed_values(attributes: &ValueContainer, table: &toml::Table) -> Vec<(String, Value)> {
+        let mut changed = vec![];
+        for (key, value) in table {
+            let current = attributes.get(key).and_then(value_to_toml);
+            if current.as_ref() != Some(value) {
+                changed.push((key.clone(), toml_to_value(value)));
+            }
+        }
+        changed
+    }
+
+    /// The slot names of the given key in the `[game]` config.
+    fn config_slots(project: &Project, key: &str) -> Vec<String> {
+        if let Ok(config) = project.config.parse::<toml::Table>() {
+            if let Some(toml::Value::Array(slots)) =
+                config.get("game").and_then(|game| game.get(key))
+            {
+                return slots
+                    .iter()
+                    .filter_map(|slot| slot.as_str().map(|s| s.to_string()))
+                    .collect();
+            }
+        }
+        vec![]
+    }
+}
@@ -0,0 +1,211 @@
+use crate::prelude::*;
+use theframework::prelude::*;
+
+/// The exchange formats of the string tables.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum LocalizationFormat {
+    Csv,
+    Xliff,
+    Po,
+}
+
+impl LocalizationFormat {
+    pub fn from_index(index: usize) -> Self {
+        match index {
+            1 => LocalizationFormat::Xliff,
+            2 => LocalizationFormat::Po,
+            _ => LocalizationFormat::Csv,
+        }
+    }
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            LocalizationFormat::Csv => "csv",
+            LocalizationFormat::Xliff => "xlf",
+            LocalizationFormat::Po => "po",
+        }
+    }
+
+    pub fn from_extension(extension: &str) -> Option<Self> {
+        match extension.to_lowercase().as_str() {
+            "csv" => Some(LocalizationFormat::Csv),
+            "xlf" | "xliff" => Some(LocalizationFormat::Xliff),
+            "po" => Some(LocalizationFormat::Po),
+            _ => None,
+        }
+    }
+}
+
+pub struct LocalizationEditor {
+    /// The locale shown in the editor.
+    pub locale: String,
+    /// The format used for export.
+    pub format: LocalizationFormat,
+
+    /// The edited text. While the user edits, the view is not refreshed.
+    pub edited: Option<String>,
+
+    /// The locales with their missing and stale counts, as shown in the list.
+    locales: Vec<(String, usize, usize)>,
+}
+
+impl Default for LocalizationEditor {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl LocalizationEditor {
+    pub fn new() -> Self {
+        Self {
+            locale: "en".into(),
+            format: LocalizationFormat::Csv,
+            edited: None,
+            locales: vec![],
+        }
+    }
+
+    /// The list id of a locale.
+    fn locale_id(locale: &str) -> Uuid {
+        let mut bytes = [0u8; 16];
+        for (index, byte) in locale.bytes().take(16).enumerate() {
+            bytes[index] = byte;
+        }
+        Uuid::from_bytes(bytes)
+    }
+
+    /// Refresh the locale list and, if not edited, the strings of the locale.
+    pub fn update(&mut self, project: &Project, ui: &mut TheUI, ctx: &mut TheContext) {
+        let localization = &project.localization;
+        if !localization.locales.contains(&self.locale) {
+            self.locale = localization.source_locale().to_string();
+            self.edited = None;
+        }
+
+        let locales: Vec<(String, usize, usize)> = localization
+            .locales
+            .iter()
+            .map(|locale| {
+                (
+                    locale.clone(),
+                    localization.missing(locale).len(),
+                    localization.stale(locale).len(),
+                )
+            })
+            .collect();
+
+        if locales != self.locales {
+            if let Some(list_layout) = ui.get_list_layout("Localization List") {
+                list_layout.clear();
+                for (index, (locale, missing, stale)) in locales.iter().enumerate() {
+                    let mut item = TheListItem::new(TheId::named_with_id(
+                        "Localization Item",
+                        Self::locale_id(locale),
+                    ));
+                    let mut text = locale.clone();
+                    if index == 0 {
+                        text += " (source)";
+                    }
+                    if *locale == localization.active {
+                        text += " *";
+                    }
+                    if *missing > 0 {
+                        text += &format!(" - {} missing", missing);
+                    }
+                    if *stale > 0 {
+                        text += &format!(" - {} stale", stale);
+                    }
+                    item.set_text(text);
+                    if *locale == self.locale {
+                        item.set_state(TheWidgetState::Selected);
+                    }
+                    list_layout.add_item(item, ctx);
+                }
+            }
+            self.locales = locales;
+        }
+
+        if self.edited.is_none() {
+            ui.set_widget_value(
+                "Localization Edit",
+                ctx,
+                TheValue::Text(localization.to_toml(&self.locale)),
+            );
+        }
+    }
+
+    /// Forces a rebuild of the list and the text.
+    pub fn refresh(&mut self, project: &Project, ui: &mut TheUI, ctx: &mut TheContext) {
+        self.locales.clear();
+        self.edited = None;
+        self.update(project, ui, ctx);
+    }
+
+    /// Select the locale with the given list id.
+    pub fn select(&mut self, project: &Project, uuid: &Uuid) {
+        if let Some(locale) = project
+            .localization
+            .locales
+            .iter()
+            .find(|locale| Self::locale_id(locale) == *uuid)
+        {
+            self.locale = locale.clone();
+            self.locales.clear();
+            self.edited = None;
+        }
+    }
+
+    /// Applies the edited strings to the selected locale.
+    pub fn apply(&mut self, localization: &mut Localization) -> Result<usize, String> {
+        let Some(edited) = &self.edited else {
+            return Ok(0);
+        };
+        let changed = localization.apply_toml(&self.locale, edited)?;
+        self.edited = None;
+        self.locales.clear();
+        Ok(changed)
+    }
+
+    /// The strings of the selected locale in the export format.
+    pub fn export(&self, project: &Project) -> String {
+        let localization = &project.localization;
+        match self.format {
+            LocalizationFormat::Csv => localization.to_csv(),
+            LocalizationFormat::Xliff => localization.to_xliff(&self.locale, &project.name),
+            LocalizationFormat::Po => localization.to_po(&self.locale),
+        }
+    }
+
+    /// Imports a CSV, XLIFF or PO file by its extension. Returns the status text.
+    pub fn import(
+        &mut self,
+        localization: &mut Localization,
+        path: &std::path::Path,
+    ) -> Result<String, String> {
+        let format = path
+            .extension()
+            .and_then(|e| e.to_str())
+            .and_then(LocalizationFormat::from_extension)
+            .ok_or("Unsupported file type.")?;
+        let contents = std::fs::read_to_string(path).map_err(|e| e.to_string())?;
+        let status = match format {
+            LocalizationFormat::Csv => {
+                let count = localization.import_csv(&contents)?;
+                format!("Imported {} texts.", count)
+            }
+            LocalizationFormat::Xliff => {
+                let (locale, count) = localization.import_xliff(&contents)?;
+                self.locale = locale.clone();
+                format!("Imported {} texts into \"{}\".", count, locale)
+            }
+            LocalizationFormat::Po => {
+                let (locale, count) = localization.import_po(&contents, &self.locale)?;
+                self.locale = locale.clone();
+                format!("Imported {} texts into \"{}\".", count, locale)
+            }
+        };
+        self.edited = None;
+        self.locales.clear();
+        Ok(status)
+    }
+}
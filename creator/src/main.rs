@@ -6,6 +6,7 @@ pub mod actionlist;
 pub mod actions;
 pub mod codeeditor;
 pub mod configeditor;
+pub mod dialogueeditor;
 pub mod dockmanager;
 pub mod docks;
 pub mod editcamera;
@@ -14,11 +15,14 @@ pub mod editor_tools;
 pub mod effectpicker;
 pub mod hud;
 pub mod infoviewer;
+pub mod inspector;
+pub mod localizationeditor;
 pub mod mapeditor;
 pub mod minimap;
 pub mod misc;
 pub mod nodeeditor;
 pub mod panels;
+pub mod profiler;
 pub mod rendereditor;
 pub mod self_update;
 pub mod shapepicker;
@@ -84,9 +88,13 @@ pub mod prelude {
     pub use crate::tools::*;
 
     pub use crate::configeditor::ConfigEditor;
+    pub use crate::dialogueeditor::DialogueEditor;
     pub use crate::editcamera::{CustomMoveAction, EditCamera};
     pub use crate::infoviewer::InfoViewer;
+    pub use crate::inspector::{Inspector, InspectorMode, InspectorSelection};
+    pub use crate::localizationeditor::{LocalizationEditor, LocalizationFormat};
     pub use crate::nodeeditor::{NodeContext, NodeEditor};
+    pub use crate::profiler::{Profiler, profile};
     pub use crate::rendereditor::{RenderEditor, RenderMoveAction};
     pub use crate::worldeditor::WorldEditor;
 
@@ -104,9 +112,38 @@ fn main() {
         std::env::set_var("RUST_BACKTRACE", "1");
     }
 
+    // Run the script tests of a project without opening the editor
+    if args.len() > 2 && args[1] == "--test" {
+        std::process::exit(run_tests(&args[2]));
+    }
+
     let editor = Editor::new();
     let mut app = TheApp::new();
     app.set_cmd_line_args(args);
 
     let () = app.run(Box::new(editor));
 }
+
+/// Runs the script tests of the given project file and prints the report.
+/// Returns the process exit code.
+fn run_tests(path: &str) -> i32 {
+    use shared::prelude::*;
+
+    let Ok(contents) = std::fs::read_to_string(path) else {
+        eprintln!("Unable to read '{}'.", path);
+        return 2;
+    };
+    let Ok(project) = serde_json::from_str::<Project>(&contents) else {
+        eprintln!("Unable to load project '{}'.", path);
+        return 2;
+    };
+
+    let results = run_script_tests(&project);
+    print!("{}", script_test_report(&results));
+
+    if results.iter().all(|r| r.passed) {
+        0
+    } else {
+        1
+    }
+}
@@ -240,46 +240,65 @@ impl MapEditor {
             }
 
             TheEvent::Copy => {
-                if let Some(map) = project.get_map_mut(server_ctx) {
-                    if map.has_selection() {
-                        server_ctx.clipboard = map.copy_selected(false);
-                        ctx.ui.send(TheEvent::SetStatusText(
-                            TheId::empty(),
-                            "Geometry copied into clipboard.".to_string(),
-                        ));
-                    } else {
-                        ctx.ui.send(TheEvent::SetStatusText(
-                            TheId::empty(),
-                            "No geometry selected!".to_string(),
-                        ));
+                if server_ctx.polyview_has_focus(ctx) {
+                    self.copy_to_system_clipboard(project, server_ctx);
+                    if let Some(map) = project.get_map_mut(server_ctx) {
+                        if map.has_selection() {
+                            server_ctx.clipboard = map.copy_selected(false);
+                            ctx.ui.send(TheEvent::SetStatusText(
+                                TheId::empty(),
+                                "Geometry copied into clipboard.".to_string(),
+                            ));
+                        } else {
+                            ctx.ui.send(TheEvent::SetStatusText(
+                                TheId::empty(),
+                                "No geometry selected!".to_string(),
+                            ));
+                        }
                     }
                 }
             }
             TheEvent::Cut => {
-                if let Some(map) = project.get_map_mut(server_ctx) {
-                    if map.has_selection() {
-                        let prev = map.clone();
-                        server_ctx.clipboard = map.copy_selected(true);
-                        ctx.ui.send(TheEvent::SetStatusText(
-                            TheId::empty(),
-                            "Geometry copied into clipboard.".to_string(),
-                        ));
-                        let undo_atom =
-                            RegionUndoAtom::MapEdit(Box::new(prev), Box::new(map.clone()));
-                        UNDOMANAGER.write().unwrap().add_region_undo(
-                            &server_ctx.curr_region,
-                            undo_atom,
-                            ctx,
-                        );
-                    } else {
-                        ctx.ui.send(TheEvent::SetStatusText(
-                            TheId::empty(),
-                            "No geometry selected!".to_string(),
-                        ));
+                if server_ctx.polyview_has_focus(ctx) {
+                    self.copy_to_system_clipboard(project, server_ctx);
+                    if let Some(map) = project.get_map_mut(server_ctx) {
+                        if map.has_selection() {
+                            let prev = map.clone();
+                            server_ctx.clipboard = map.copy_selected(true);
+                            ctx.ui.send(TheEvent::SetStatusText(
+                                TheId::empty(),
+                                "Geometry copied into clipboard.".to_string(),
+                            ));
+                            let undo_atom =
+                                RegionUndoAtom::MapEdit(Box::new(prev), Box::new(map.clone()));
+                            UNDOMANAGER.write().unwrap().add_region_undo(
+                                &server_ctx.curr_region,
+                                undo_atom,
+                                ctx,
+                            );
+                        } else {
+                            ctx.ui.send(TheEvent::SetStatusText(
+                                TheId::empty(),
+                                "No geometry selected!".to_string(),
+                            ));
+                        }
                     }
                 }
             }
             TheEvent::Paste(_, _) => {
+                // Geometry copied in this or another creator instance
+                if server_ctx.polyview_has_focus(ctx) {
+                    if let Some(fragment) = self.system_clipboard_geometry() {
+                        let tiles = fragment.missing_tiles(project);
+                        if !tiles.is_empty() {
+                            let atom = ProjectUndoAtom::ImportTiles(tiles);
+                            atom.redo(project, ui, ctx, server_ctx);
+                            UNDOMANAGER.write().unwrap().add_undo(atom, ctx);
+                        }
+                        server_ctx.clipboard = fragment.map;
+                    }
+                }
+
                 // TODO use focus_widget_supports_clipboard here
                 if !server_ctx.clipboard.is_empty() && server_ctx.polyview_has_focus(ctx) {
                     ctx.ui.send(TheEvent::SetStatusText(
@@ -287,6 +306,8 @@ impl MapEditor {
                         "Geometry pasted. Click to insert, Escape to cancel.".to_string(),
                     ));
                     server_ctx.paste_clipboard = Some(server_ctx.clipboard.clone());
+                    server_ctx.paste_prefab = None;
+                    server_ctx.paste_prop = None;
                 }
             }
             TheEvent::Custom(id, value) => {
@@ -1816,6 +1837,28 @@ impl MapEditor {
         }
     }
 
+    /// Writes the selected geometry, with its tiles and graphs, as tagged text
+    /// into the system clipboard so it can be pasted in other creator instances.
+    fn copy_to_system_clipboard(&self, project: &Project, server_ctx: &ServerContext) {
+        let Some(fragment) = project
+            .get_map(server_ctx)
+            .and_then(|map| Prefab::from_selection("Clipboard".into(), map, project))
+        else {
+            return;
+        };
+        if let Ok(mut clipboard) = arboard::Clipboard::new() {
+            if let Err(err) = clipboard.set_text(fragment.to_clipboard_text()) {
+                eprintln!("Unable to write geometry to the clipboard: {err}");
+            }
+        }
+    }
+
+    /// Returns the geometry in the system clipboard if it has the tagged format.
+    fn system_clipboard_geometry(&self) -> Option<Prefab> {
+        let text = arboard::Clipboard::new().ok()?.get_text().ok()?;
+        Prefab::from_clipboard_text(&text)
+    }
+
     fn transform_to_snake_case(&self, input: &str, strip_prefix: &str) -> String {
         // Strip the prefix if it exists
         let stripped = if let Some(remainder) = input.strip_prefix(strip_prefix) {
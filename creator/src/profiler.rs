@@ -0,0 +1,280 @@
+use crate::editor::PROFILER;
+use crate::prelude::*;
+use shared::rusterix_utils::SCRIPT_PROFILE;
+use std::collections::VecDeque;
+use std::time::Instant;
+
+/// A timed span inside a profiled frame.
+#[derive(Clone, Debug)]
+pub struct ProfileSpan {
+    pub name: String,
+    pub category: &'static str,
+    /// Start time in microseconds relative to the start of the profiler.
+    pub start_us: u64,
+    pub dur_us: u64,
+    /// The nesting depth of the span inside the frame.
+    pub depth: u32,
+}
+
+/// All spans recorded during one redraw of the running game.
+#[derive(Clone, Debug, Default)]
+pub struct ProfileFrame {
+    pub index: u64,
+    pub start_us: u64,
+    pub dur_us: u64,
+    pub spans: Vec<ProfileSpan>,
+}
+
+/// Records the time spent in the server, region and client stages of the game
+/// loop. Script callbacks run in the region threads, in games started while
+/// recording they report their run time, which is added as `script` spans to
+/// the region they run in.
+pub struct Profiler {
+    pub enabled: bool,
+
+    /// The recorded frames, oldest first.
+    pub frames: VecDeque<ProfileFrame>,
+    pub max_frames: usize,
+
+    origin: Instant,
+    frame_counter: u64,
+    current: Option<ProfileFrame>,
+    open: Vec<usize>,
+    /// The script run times last reported per region, entity or item and
+    /// callback.
+    script_totals: FxHashMap<(Uuid, bool, u32, String), u64>,
+}
+
+impl Default for Profiler {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Profiler {
+    pub fn new() -> Self {
+        Self {
+            enabled: false,
+
+            frames: VecDeque::new(),
+            max_frames: 300,
+
+            origin: Instant::now(),
+            frame_counter: 0,
+            current: None,
+            open: vec![],
+            script_totals: FxHashMap::default(),
+        }
+    }
+
+    /// Enable or disable recording. Enabling clears the previous recording.
+    pub fn set_enabled(&mut self, enabled: bool) {
+        if enabled && !self.enabled {
+            self.clear();
+        }
+        self.enabled = enabled;
+        self.current = None;
+        self.open.clear();
+    }
+
+    /// Clear all recorded frames.
+    pub fn clear(&mut self) {
+        self.frames.clear();
+        self.origin = Instant::now();
+        self.frame_counter = 0;
+    }
+
+    fn now_us(&self) -> u64 {
+        self.origin.elapsed().as_micros() as u64
+    }
+
+    /// Start a new frame.
+    pub fn begin_frame(&mut self) {
+        if !self.enabled {
+            return;
+        }
+        self.frame_counter += 1;
+        self.open.clear();
+        self.current = Some(ProfileFrame {
+            index: self.frame_counter,
+            start_us: self.now_us(),
+            dur_us: 0,
+            spans: vec![],
+        });
+    }
+
+    /// Finish the current frame and store it.
+    pub fn end_frame(&mut self) {
+        let now = self.now_us();
+        if let Some(mut frame) = self.current.take() {
+            frame.dur_us = now.saturating_sub(frame.start_us);
+            self.frames.push_back(frame);
+            while self.frames.len() > self.max_frames {
+                self.frames.pop_front();
+            }
+        }
+        self.open.clear();
+    }
+
+    /// Open a span in the current frame. Returns the span index used to close it.
+    pub fn begin(&mut self, name: &str, category: &'static str) -> Option<usize> {
+        let start_us = self.now_us();
+        let depth = self.open.len() as u32;
+        let frame = self.current.as_mut()?;
+        frame.spans.push(ProfileSpan {
+            name: name.to_string(),
+            category,
+            start_us,
+            dur_us: 0,
+            depth,
+        });
+        let index = frame.spans.len() - 1;
+        self.open.push(index);
+        Some(index)
+    }
+
+    /// Close the given span.
+    pub fn end(&mut self, span: Option<usize>) {
+        let Some(index) = span else {
+            return;
+        };
+        let now = self.now_us();
+        if let Some(frame) = self.current.as_mut() {
+            if let Some(span) = frame.spans.get_mut(index) {
+                span.dur_us = now.saturating_sub(span.start_us);
+            }
+        }
+        if let Some(pos) = self.open.iter().rposition(|i| *i == index) {
+            self.open.truncate(pos);
+        }
+    }
+
+    /// Adds a span for every script callback of the entities and items of the
+    /// region which ran since the last report. The spans start at the time of
+    /// the report and last as long as the callbacks ran in the region thread.
+    pub fn record_scripts(&mut self, region: &Region) {
+        if self.current.is_none() {
+            return;
+        }
+        let start_us = self.now_us();
+        let depth = self.open.len() as u32;
+
+        let instances = region
+            .map
+            .entities
+            .iter()
+            .map(|e| (false, e.id, &e.attributes))
+            .chain(region.map.items.iter().map(|i| (true, i.id, &i.attributes)));
+        let mut spans = vec![];
+        for (is_item, id, attributes) in instances {
+            let profile = attributes.get_str_default(SCRIPT_PROFILE, String::new());
+            if profile.is_empty() {
+                continue;
+            }
+            let name = attributes.get_str_default("name", String::new());
+            for line in profile.lines() {
+                let Some((callback, total)) = line.rsplit_once('=') else {
+                    continue;
+                };
+                let Ok(total) = total.trim().parse::<u64>() else {
+                    continue;
+                };
+                let last = self
+                    .script_totals
+                    .insert((region.id, is_item, id, callback.to_string()), total)
+                    .unwrap_or(0);
+                // A lower total belongs to a new run of the game
+                let dur_us = if total >= last { total - last } else { total };
+                if dur_us > 0 {
+                    spans.push(ProfileSpan {
+                        name: format!("{} {}", name, callback),
+                        category: "script",
+                        start_us,
+                        dur_us,
+                        depth,
+                    });
+                }
+            }
+        }
+        if let Some(frame) = self.current.as_mut() {
+            frame.spans.extend(spans);
+        }
+    }
+
+    /// Returns the most recent frame.
+    pub fn latest(&self) -> Option<&ProfileFrame> {
+        self.frames.back()
+    }
+
+    /// Returns (name, category, average ms, max ms) per span name over all
+    /// recorded frames, slowest first.
+    pub fn summary(&self) -> Vec<(String, &'static str, f32, f32)> {
+        let mut totals: IndexMap<(String, &'static str), (u64, u64, u64)> = IndexMap::default();
+        for frame in &self.frames {
+            for span in &frame.spans {
+                let entry = totals
+                    .entry((span.name.clone(), span.category))
+                    .or_insert((0, 0, 0));
+                entry.0 += span.dur_us;
+                entry.1 = entry.1.max(span.dur_us);
+                entry.2 += 1;
+            }
+        }
+
+        let mut summary: Vec<(String, &'static str, f32, f32)> = totals
+            .into_iter()
+            .map(|((name, category), (total, max, count))| {
+                (
+                    name,
+                    category,
+                    total as f32 / count.max(1) as f32 / 1000.0,
+                    max as f32 / 1000.0,
+                )
+            })
+            .collect();
+        summary.sort_by(|a, b| b.2.total_cmp(&a.2));
+        summary
+    }
+
+    /// Converts the recorded frames into the Chrome trace event format, which
+    /// can be loaded in chrome://tracing or Perfetto.
+    pub fn to_chrome_trace(&self) -> String {
+        let mut events = vec![];
+        for frame in &self.frames {
+            events.push(serde_json::json!({
+                "name": format!("Frame {}", frame.index),
+                "cat": "frame",
+                "ph": "X",
+                "ts": frame.start_us,
+                "dur": frame.dur_us,
+                "pid": 1,
+                "tid": 1,
+            }));
+            for span in &frame.spans {
+                events.push(serde_json::json!({
+                    "name": span.name,
+                    "cat": span.category,
+                    "ph": "X",
+                    "ts": span.start_us,
+                    "dur": span.dur_us,
+                    "pid": 1,
+                    "tid": 1,
+                }));
+            }
+        }
+
+        serde_json::to_string_pretty(&serde_json::json!({
+            "traceEvents": events,
+            "displayTimeUnit": "ms",
+        }))
+        .unwrap_or_default()
+    }
+}
+
+/// Runs the closure inside a profiler span.
+pub fn profile<R>(name: &str, category: &'static str, f: impl FnOnce() -> R) -> R {
+    let span = PROFILER.write().unwrap().begin(name, category);
+    let result = f();
+    PROFILER.write().unwrap().end(span);
+    result
+}
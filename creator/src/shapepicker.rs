@@ -274,6 +274,7 @@ impl ShapePicker {
                 };
                 shape.create(&mut map, None, None);
                 server_ctx.paste_clipboard = Some(map);
+                server_ctx.paste_prop = None;
             }
         }
     }
@@ -94,14 +94,50 @@ impl Sidebar {
         assets_node.add_child(fonts_node);
         root.add_child(assets_node);
 
+        let libraries_node: TheTreeNode = TheTreeNode::new(TheId::named_with_id(
+            "Libraries",
+            server_ctx.tree_libraries_id,
+        ));
+        root.add_child(libraries_node);
+
+        let tests_node: TheTreeNode =
+            TheTreeNode::new(TheId::named_with_id("Tests", server_ctx.tree_tests_id));
+        root.add_child(tests_node);
+
+        let dialogues_node: TheTreeNode = TheTreeNode::new(TheId::named_with_id(
+            "Dialogues",
+            server_ctx.tree_dialogues_id,
+        ));
+        root.add_child(dialogues_node);
+
+        let quests_node: TheTreeNode =
+            TheTreeNode::new(TheId::named_with_id("Quests", server_ctx.tree_quests_id));
+        root.add_child(quests_node);
+
         let mut config_node: TheTreeNode = TheTreeNode::new(TheId::named("Game"));
 
         let mut config_item = TheTreeItem::new(TheId::named("Project Settings"));
         config_item.set_text("Settings".to_string());
         config_node.add_widget(Box::new(config_item));
 
+        let mut localization_item = TheTreeItem::new(TheId::named("Project Localization"));
+        localization_item.set_text("Localization".to_string());
+        config_node.add_widget(Box::new(localization_item));
+
         root.add_child(config_node);
 
+        let mut debug_node: TheTreeNode = TheTreeNode::new(TheId::named("Debug"));
+
+        let mut profiler_item = TheTreeItem::new(TheId::named("Project Profiler"));
+        profiler_item.set_text("Profiler".to_string());
+        debug_node.add_widget(Box::new(profiler_item));
+
+        let mut inspector_item = TheTreeItem::new(TheId::named("Project Inspector"));
+        inspector_item.set_text("Inspector".to_string());
+        debug_node.add_widget(Box::new(inspector_item));
+
+        root.add_child(debug_node);
+
         // let palette_node: TheTreeNode =
         //     TheTreeNode::new(TheId::named_with_id("Palette", server_ctx.tree_palette_id));
         // root.add_child(palette_node);
@@ -124,6 +160,14 @@ impl Sidebar {
                     "Add Font Asset".to_string(),
                     TheId::named("Add Font Asset"),
                 ),
+                TheContextMenuItem::new(
+                    "Add Mesh Asset".to_string(),
+                    TheId::named("Add Mesh Asset"),
+                ),
+                TheContextMenuItem::new("Add Library".to_string(), TheId::named("Add Library")),
+                TheContextMenuItem::new("Add Test".to_string(), TheId::named("Add Test")),
+                TheContextMenuItem::new("Add Dialogue".to_string(), TheId::named("Add Dialogue")),
+                TheContextMenuItem::new("Add Quest".to_string(), TheId::named("Add Quest")),
             ],
             ..Default::default()
         }));
@@ -156,6 +200,18 @@ impl Sidebar {
                     "Import Font Asset".to_string(),
                     TheId::named("Import Font Asset"),
                 ),
+                TheContextMenuItem::new(
+                    "Import Audio Asset".to_string(),
+                    TheId::named("Import Audio Asset"),
+                ),
+                TheContextMenuItem::new(
+                    "Import Library".to_string(),
+                    TheId::named("Import Library"),
+                ),
+                TheContextMenuItem::new(
+                    "Import Tiled / LDtk".to_string(),
+                    TheId::named("Import Level"),
+                ),
             ],
             ..Default::default()
         }));
@@ -164,6 +220,28 @@ impl Sidebar {
             TheTraybarButton::new(TheId::named("Project Export"));
         export_button.set_icon_name("export".to_string());
         export_button.set_status_text("Export from the project.");
+        export_button.set_context_menu(Some(TheContextMenu {
+            items: vec![
+                TheContextMenuItem::new("Export".to_string(), TheId::named("Project Export")),
+                TheContextMenuItem::new(
+                    "Export Region as Tiled Map".to_string(),
+                    TheId::named("Export Tiled Map"),
+                ),
+                TheContextMenuItem::new(
+                    "Export Region as glTF".to_string(),
+                    TheId::named("Export Region glTF"),
+                ),
+                TheContextMenuItem::new(
+                    "Export Region as OBJ".to_string(),
+                    TheId::named("Export Region OBJ"),
+                ),
+                TheContextMenuItem::new(
+                    "Export Models as glTF".to_string(),
+                    TheId::named("Export Models glTF"),
+                ),
+            ],
+            ..Default::default()
+        }));
 
         let mut toolbar_hlayout = TheHLayout::new(TheId::empty());
         toolbar_hlayout.set_background_color(None);
@@ -331,6 +409,46 @@ impl Sidebar {
                             server_ctx,
                             ProjectContext::Asset(id.uuid),
                         );
+                    } else
+                    // Library
+                    if let Some(_item) = project.libraries.get(&id.uuid) {
+                        set_project_context(
+                            ctx,
+                            ui,
+                            project,
+                            server_ctx,
+                            ProjectContext::Library(id.uuid),
+                        );
+                    } else
+                    // Test
+                    if let Some(_item) = project.tests.get(&id.uuid) {
+                        set_project_context(
+                            ctx,
+                            ui,
+                            project,
+                            server_ctx,
+                            ProjectContext::ScriptTest(id.uuid),
+                        );
+                    } else
+                    // Dialogue
+                    if let Some(_item) = project.dialogues.get(&id.uuid) {
+                        set_project_context(
+                            ctx,
+                            ui,
+                            project,
+                            server_ctx,
+                            ProjectContext::Dialogue(id.uuid),
+                        );
+                    } else
+                    // Quest
+                    if let Some(_item) = project.quests.get(&id.uuid) {
+                        set_project_context(
+                            ctx,
+                            ui,
+                            project,
+                            server_ctx,
+                            ProjectContext::Quest(id.uuid),
+                        );
                     }
                 }
             }
@@ -1005,6 +1123,62 @@ impl Sidebar {
                         atom.redo(project, ui, ctx, server_ctx);
                         UNDOMANAGER.write().unwrap().add_undo(atom, ctx);
                     }
+                } else if id.name.starts_with("Library Item Name Edit") {
+                    // Rename a Library
+                    let mut old = String::new();
+                    if let Some(library) = project.libraries.get(&id.uuid) {
+                        old = library.name.clone();
+                    }
+
+                    if let Some(name) = value.to_string()
+                        && old != name
+                    {
+                        let atom = ProjectUndoAtom::RenameLibrary(id.uuid, old, name);
+                        atom.redo(project, ui, ctx, server_ctx);
+                        UNDOMANAGER.write().unwrap().add_undo(atom, ctx);
+                    }
+                } else if id.name.starts_with("Test Item Name Edit") {
+                    // Rename a Test
+                    let mut old = String::new();
+                    if let Some(test) = project.tests.get(&id.uuid) {
+                        old = test.name.clone();
+                    }
+
+                    if let Some(name) = value.to_string()
+                        && old != name
+                    {
+                        let atom = ProjectUndoAtom::RenameScriptTest(id.uuid, old, name);
+                        atom.redo(project, ui, ctx, server_ctx);
+                        UNDOMANAGER.write().unwrap().add_undo(atom, ctx);
+                    }
+                } else if id.name.starts_with("Dialogue Item Name Edit") {
+                    // Rename a Dialogue
+                    let mut old = String::new();
+                    if let Some(dialogue) = project.dialogues.get(&id.uuid) {
+                        old = dialogue.name.clone();
+                    }
+
+                    if let Some(name) = value.to_string()
+                        && old != name
+                    {
+                        let atom = ProjectUndoAtom::RenameDialogue(id.uuid, old, name);
+                        atom.redo(project, ui, ctx, server_ctx);
+                        UNDOMANAGER.write().unwrap().add_undo(atom, ctx);
+                    }
+                } else if id.name.starts_with("Quest Item Name Edit") {
+                    // Rename a Quest
+                    let mut old = String::new();
+                    if let Some(quest) = project.quests.get(&id.uuid) {
+                        old = quest.name.clone();
+                    }
+
+                    if let Some(name) = value.to_string()
+                        && old != name
+                    {
+                        let atom = ProjectUndoAtom::RenameQuest(id.uuid, old, name);
+                        atom.redo(project, ui, ctx, server_ctx);
+                        UNDOMANAGER.write().unwrap().add_undo(atom, ctx);
+                    }
                 } else if let Some(action_id) = server_ctx.curr_action_id
                     && id.name.starts_with("action")
                 {
@@ -1103,6 +1277,74 @@ impl Sidebar {
                             }
                         }
                     }
+                } else if id.name == "Add Mesh Asset" {
+                    for p in paths {
+                        match shared::prop::import_prop(p, crate::utils::decode_png_data) {
+                            Ok((mesh, warnings)) => {
+                                let mut status = format!(
+                                    "Mesh imported with {} triangles.",
+                                    mesh.triangles.len()
+                                );
+                                for warning in &warnings {
+                                    status += " ";
+                                    status += warning;
+                                }
+
+                                let asset = Asset {
+                                    name: p
+                                        .file_stem()
+                                        .unwrap_or_default()
+                                        .to_string_lossy()
+                                        .to_string(),
+                                    id: Uuid::new_v4(),
+                                    buffer: AssetBuffer::Mesh(mesh),
+                                };
+
+                                let atom = ProjectUndoAtom::AddAsset(asset);
+                                atom.redo(project, ui, ctx, server_ctx);
+                                UNDOMANAGER.write().unwrap().add_undo(atom, ctx);
+                                ctx.ui.send(TheEvent::SetStatusText(TheId::empty(), status));
+                            }
+                            Err(err) => {
+                                ctx.ui.send(TheEvent::SetStatusText(
+                                    TheId::empty(),
+                                    format!("Unable to import mesh: {}", err),
+                                ));
+                            }
+                        }
+                    }
+                } else if id.name == "Audio Asset Import" {
+                    for p in paths {
+                        match AudioData::load(p) {
+                            Ok(audio) => {
+                                let duration =
+                                    audio.decode().map(|c| c.duration()).unwrap_or_default();
+                                let asset = Asset {
+                                    name: p
+                                        .file_stem()
+                                        .unwrap_or_default()
+                                        .to_string_lossy()
+                                        .to_string(),
+                                    id: Uuid::new_v4(),
+                                    buffer: AssetBuffer::Audio(audio),
+                                };
+
+                                let atom = ProjectUndoAtom::AddAsset(asset);
+                                atom.redo(project, ui, ctx, server_ctx);
+                                UNDOMANAGER.write().unwrap().add_undo(atom, ctx);
+                                ctx.ui.send(TheEvent::SetStatusText(
+                                    TheId::empty(),
+                                    format!("Audio imported ({:.1}s).", duration),
+                                ));
+                            }
+                            Err(err) => {
+                                ctx.ui.send(TheEvent::SetStatusText(
+                                    TheId::empty(),
+                                    format!("Unable to import audio: {}", err),
+                                ));
+                            }
+                        }
+                    }
                 } else if id.name == "Add Font Old" {
                     for p in paths {
                         if let Ok(bytes) = std::fs::read(p) {
@@ -1231,6 +1473,153 @@ impl Sidebar {
                             }
                         }
                     }
+                } else if id.name == "Level Import" {
+                    for p in paths {
+                        let extension = p
+                            .extension()
+                            .map(|e| e.to_string_lossy().to_lowercase())
+                            .unwrap_or_default();
+                        let import = if extension == "ldtk" {
+                            shared::ldtk::import_ldtk(p)
+                        } else {
+                            shared::tiled::import_tiled(p)
+                        };
+
+                        let mut import = match import {
+                            Ok(import) => import,
+                            Err(err) => {
+                                ctx.ui.send(TheEvent::SetStatusText(
+                                    TheId::empty(),
+                                    format!("Unable to import {}: {}", p.display(), err),
+                                ));
+                                continue;
+                            }
+                        };
+
+                        let (tilemaps, regions) = import.build(
+                            project,
+                            &crate::utils::embedded_instance_source("python/instcharacter.py"),
+                            &crate::utils::embedded_instance_source("python/institem.py"),
+                            crate::utils::decode_png,
+                        );
+                        let (tilemap_count, region_count) = (tilemaps.len(), regions.len());
+
+                        for tilemap in tilemaps {
+                            let tilemap_id = tilemap.id;
+                            let atom = ProjectUndoAtom::AddTilemap(tilemap);
+                            atom.redo(project, ui, ctx, server_ctx);
+                            UNDOMANAGER.write().unwrap().add_undo(atom, ctx);
+                            project.add_tilemap_tiles(tilemap_id);
+                        }
+                        self.update_tiles(ui, ctx, project);
+
+                        for region in regions {
+                            let atom = ProjectUndoAtom::AddRegion(region);
+                            atom.redo(project, ui, ctx, server_ctx);
+                            UNDOMANAGER.write().unwrap().add_undo(atom, ctx);
+                        }
+                        shared::rusterix_utils::insert_content_into_maps(project);
+
+                        let mut status = format!(
+                            "Imported {} tileset(s) and {} region(s).",
+                            tilemap_count, region_count
+                        );
+                        for warning in &import.warnings {
+                            status += " ";
+                            status += warning;
+                        }
+                        ctx.ui.send(TheEvent::SetStatusText(TheId::empty(), status));
+                    }
+                } else if id.name == "Tiled Map Export" {
+                    if let Some(region) = project.get_region(&id.uuid) {
+                        for p in paths {
+                            let path = p.with_extension("tmx");
+                            let name = path
+                                .file_stem()
+                                .map(|s| s.to_string_lossy().to_string())
+                                .unwrap_or(region.name.clone());
+                            let export = shared::tiled::export_tiled(region, project, &name);
+
+                            let saved = std::fs::write(&path, &export.tmx).is_ok()
+                                && std::fs::write(path.with_extension("tsx"), &export.tsx).is_ok()
+                                && crate::utils::write_png(
+                                    &export.image,
+                                    &path.with_extension("png"),
+                                );
+
+                            let status = if !saved {
+                                "Unable to save Tiled Map!".to_string()
+                            } else if export.skipped > 0 {
+                                format!(
+                                    "Tiled Map saved, {} sector(s) which are not grid aligned rectangles were skipped.",
+                                    export.skipped
+                                )
+                            } else {
+                                "Tiled Map saved successfully.".to_string()
+                            };
+                            ctx.ui.send(TheEvent::SetStatusText(TheId::empty(), status));
+                        }
+                    }
+                } else if id.name == "Region glTF Export" || id.name == "Region OBJ Export" {
+                    if let Some(region) = project.get_region(&id.uuid) {
+                        let mesh = shared::meshexport::MapMesh::build_region(region, project);
+                        for p in paths {
+                            let saved = if id.name == "Region glTF Export" {
+                                let glb = mesh.to_glb(project, crate::utils::encode_png);
+                                std::fs::write(p.with_extension("glb"), glb).is_ok()
+                            } else {
+                                let path = p.with_extension("obj");
+                                let name = path
+                                    .file_stem()
+                                    .map(|s| s.to_string_lossy().to_string())
+                                    .unwrap_or(region.name.clone());
+                                let export = mesh.to_obj(project, &name);
+                                std::fs::write(&path, &export.obj).is_ok()
+                                    && std::fs::write(path.with_extension("mtl"), &export.mtl)
+                                        .is_ok()
+                                    && export.textures.iter().all(|(file, texture)| {
+                                        crate::utils::write_png(texture, &path.with_file_name(file))
+                                    })
+                            };
+
+                            let status = if !saved {
+                                "Unable to save the Region geometry!".to_string()
+                            } else if mesh.is_empty() {
+                                "The Region has no geometry, saved an empty mesh.".to_string()
+                            } else {
+                                "Region geometry saved successfully.".to_string()
+                            };
+                            ctx.ui.send(TheEvent::SetStatusText(TheId::empty(), status));
+                        }
+                    }
+                } else if id.name == "Models glTF Export" {
+                    for p in paths {
+                        let stem = p
+                            .file_stem()
+                            .map(|s| s.to_string_lossy().to_string())
+                            .unwrap_or("model".into());
+                        let mut saved = 0;
+                        for model in project.models.values() {
+                            let mesh = shared::meshexport::MapMesh::build(model);
+                            if mesh.is_empty() {
+                                continue;
+                            }
+                            let name: String = model
+                                .name
+                                .chars()
+                                .map(|c| if c.is_alphanumeric() { c } else { '_' })
+                                .collect();
+                            let path = p.with_file_name(format!("{}_{}.glb", stem, name));
+                            let glb = mesh.to_glb(project, crate::utils::encode_png);
+                            if std::fs::write(path, glb).is_ok() {
+                                saved += 1;
+                            }
+                        }
+                        ctx.ui.send(TheEvent::SetStatusText(
+                            TheId::empty(),
+                            format!("{} model(s) saved as glTF.", saved),
+                        ));
+                    }
                 } else if id.name == "Character Import" {
                     for p in paths {
                         let contents = std::fs::read_to_string(p).unwrap_or("".to_string());
@@ -1391,6 +1780,38 @@ impl Sidebar {
                             }
                         }
                     }
+                } else if id.name == "Library Import" {
+                    for p in paths {
+                        let contents = std::fs::read_to_string(p).unwrap_or("".to_string());
+                        let mut library: ScriptLibrary =
+                            serde_json::from_str(&contents).unwrap_or(ScriptLibrary::default());
+
+                        library.id = Uuid::new_v4();
+
+                        let atom = ProjectUndoAtom::AddLibrary(library);
+                        atom.redo(project, ui, ctx, server_ctx);
+                        UNDOMANAGER.write().unwrap().add_undo(atom, ctx);
+                    }
+                } else if id.name == "Library Export" {
+                    if let Some(library) = project.libraries.get(&id.uuid) {
+                        let mut library = library.clone();
+                        for p in paths {
+                            library.id = Uuid::new_v4();
+                            if let Ok(json) = serde_json::to_string(&library) {
+                                if std::fs::write(p, json).is_ok() {
+                                    ctx.ui.send(TheEvent::SetStatusText(
+                                        TheId::empty(),
+                                        "Library saved successfully.".to_string(),
+                                    ))
+                                } else {
+                                    ctx.ui.send(TheEvent::SetStatusText(
+                                        TheId::empty(),
+                                        "Unable to save Library!".to_string(),
+                                    ))
+                                }
+                            }
+                        }
+                    }
                 }
             }
             TheEvent::ImageDecodeResult(id, name, buffer) => {
@@ -1670,6 +2091,19 @@ impl Sidebar {
                         .set_widget_state("Add Font Asset".to_string(), TheWidgetState::None);
                     ctx.ui.clear_hover();
                     redraw = true;
+                } else if id.name == "Add Mesh Asset" {
+                    ctx.ui.open_file_requester(
+                        TheId::named_with_id("Add Mesh Asset", Uuid::new_v4()),
+                        "Open Mesh File".into(),
+                        TheFileExtension::new(
+                            "glTF or OBJ Mesh".into(),
+                            vec!["gltf".to_string(), "glb".to_string(), "obj".to_string()],
+                        ),
+                    );
+                    ctx.ui
+                        .set_widget_state("Add Mesh Asset".to_string(), TheWidgetState::None);
+                    ctx.ui.clear_hover();
+                    redraw = true;
                 } else if id.name == "Import Item" {
                     if let Some(id) = server_ctx.pc.id() {
                         ctx.ui.open_file_requester(
@@ -1708,6 +2142,84 @@ impl Sidebar {
                             vec!["eldiron_font_asset".to_string()],
                         ),
                     );
+                } else if id.name == "Import Audio Asset" {
+                    ctx.ui.open_file_requester(
+                        TheId::named_with_id("Audio Asset Import", Uuid::new_v4()),
+                        "Import Audio Asset".into(),
+                        TheFileExtension::new(
+                            "WAV or OGG Audio".into(),
+                            vec!["wav".to_string(), "ogg".to_string()],
+                        ),
+                    );
+                } else if id.name == "Add Library" {
+                    // Add Library
+                    let atom = ProjectUndoAtom::AddLibrary(ScriptLibrary::default());
+                    atom.redo(project, ui, ctx, server_ctx);
+                    UNDOMANAGER.write().unwrap().add_undo(atom, ctx);
+                } else if id.name == "Add Test" {
+                    // Add Test
+                    let atom = ProjectUndoAtom::AddScriptTest(ScriptTest::default());
+                    atom.redo(project, ui, ctx, server_ctx);
+                    UNDOMANAGER.write().unwrap().add_undo(atom, ctx);
+                } else if id.name == "Add Dialogue" {
+                    // Add Dialogue
+                    let atom = ProjectUndoAtom::AddDialogue(Dialogue::default());
+                    atom.redo(project, ui, ctx, server_ctx);
+                    UNDOMANAGER.write().unwrap().add_undo(atom, ctx);
+                } else if id.name == "Add Quest" {
+                    // Add Quest
+                    let atom = ProjectUndoAtom::AddQuest(Quest::default());
+                    atom.redo(project, ui, ctx, server_ctx);
+                    UNDOMANAGER.write().unwrap().add_undo(atom, ctx);
+                } else if id.name == "Import Library" {
+                    ctx.ui.open_file_requester(
+                        TheId::named_with_id("Library Import", Uuid::new_v4()),
+                        "Import Library".into(),
+                        TheFileExtension::new(
+                            "Eldiron Library".into(),
+                            vec!["eldiron_library".to_string()],
+                        ),
+                    );
+                } else if id.name == "Import Level" {
+                    ctx.ui.open_file_requester(
+                        TheId::named_with_id("Level Import", Uuid::new_v4()),
+                        "Import Tiled / LDtk".into(),
+                        TheFileExtension::new(
+                            "Tiled Map, Tiled Tileset or LDtk Project".into(),
+                            vec!["tmx".to_string(), "tsx".to_string(), "ldtk".to_string()],
+                        ),
+                    );
+                } else if id.name == "Export Tiled Map" {
+                    if server_ctx.pc.is_region()
+                        && let Some(id) = server_ctx.pc.id()
+                    {
+                        ctx.ui.save_file_requester(
+                            TheId::named_with_id("Tiled Map Export", id),
+                            "Export Tiled Map".into(),
+                            TheFileExtension::new("Tiled Map".into(), vec!["tmx".to_string()]),
+                        );
+                    }
+                } else if id.name == "Export Region glTF" || id.name == "Export Region OBJ" {
+                    if server_ctx.pc.is_region()
+                        && let Some(region_id) = server_ctx.pc.id()
+                    {
+                        let (name, extension) = if id.name == "Export Region glTF" {
+                            ("glTF", "glb")
+                        } else {
+                            ("OBJ", "obj")
+                        };
+                        ctx.ui.save_file_requester(
+                            TheId::named_with_id(&format!("Region {} Export", name), region_id),
+                            format!("Export Region as {}", name),
+                            TheFileExtension::new(name.into(), vec![extension.to_string()]),
+                        );
+                    }
+                } else if id.name == "Export Models glTF" {
+                    ctx.ui.save_file_requester(
+                        TheId::named_with_id("Models glTF Export", Uuid::new_v4()),
+                        "Export Models as glTF".into(),
+                        TheFileExtension::new("glTF".into(), vec!["glb".to_string()]),
+                    );
                 } else if id.name == "Project Remove" {
                     if server_ctx.pc.is_region() {
                         if let Some(instance_id) = server_ctx.pc.get_region_character_instance_id()
@@ -1844,6 +2356,62 @@ impl Sidebar {
                                 UNDOMANAGER.write().unwrap().add_undo(atom, ctx);
                             }
                         }
+                    } else if server_ctx.pc.is_library() {
+                        // Remove Library
+                        let mut library: ScriptLibrary = ScriptLibrary::default();
+                        if let Some(id) = server_ctx.pc.id() {
+                            if let Some(l) = project.libraries.get(&id) {
+                                library = l.clone();
+                            }
+
+                            if let Some(index) = project.libraries.get_index_of(&id) {
+                                let atom = ProjectUndoAtom::RemoveLibrary(index, library);
+                                atom.redo(project, ui, ctx, server_ctx);
+                                UNDOMANAGER.write().unwrap().add_undo(atom, ctx);
+                            }
+                        }
+                    } else if server_ctx.pc.is_script_test() {
+                        // Remove Test
+                        let mut test: ScriptTest = ScriptTest::default();
+                        if let Some(id) = server_ctx.pc.id() {
+                            if let Some(t) = project.tests.get(&id) {
+                                test = t.clone();
+                            }
+
+                            if let Some(index) = project.tests.get_index_of(&id) {
+                                let atom = ProjectUndoAtom::RemoveScriptTest(index, test);
+                                atom.redo(project, ui, ctx, server_ctx);
+                                UNDOMANAGER.write().unwrap().add_undo(atom, ctx);
+                            }
+                        }
+                    } else if server_ctx.pc.is_dialogue() {
+                        // Remove Dialogue
+                        let mut dialogue: Dialogue = Dialogue::default();
+                        if let Some(id) = server_ctx.pc.id() {
+                            if let Some(d) = project.dialogues.get(&id) {
+                                dialogue = d.clone();
+                            }
+
+                            if let Some(index) = project.dialogues.get_index_of(&id) {
+                                let atom = ProjectUndoAtom::RemoveDialogue(index, dialogue);
+                                atom.redo(project, ui, ctx, server_ctx);
+                                UNDOMANAGER.write().unwrap().add_undo(atom, ctx);
+                            }
+                        }
+                    } else if server_ctx.pc.is_quest() {
+                        // Remove Quest
+                        let mut quest: Quest = Quest::default();
+                        if let Some(id) = server_ctx.pc.id() {
+                            if let Some(q) = project.quests.get(&id) {
+                                quest = q.clone();
+                            }
+
+                            if let Some(index) = project.quests.get_index_of(&id) {
+                                let atom = ProjectUndoAtom::RemoveQuest(index, quest);
+                                atom.redo(project, ui, ctx, server_ctx);
+                                UNDOMANAGER.write().unwrap().add_undo(atom, ctx);
+                            }
+                        }
                     }
                 } else if id.name == "Project Export" {
                     if let Some(id) = server_ctx.pc.id() {
@@ -1901,6 +2469,15 @@ impl Sidebar {
                                     vec!["eldiron_font_asset".to_string()],
                                 ),
                             );
+                        } else if server_ctx.pc.is_library() {
+                            ctx.ui.save_file_requester(
+                                TheId::named_with_id("Library Export", id),
+                                "Export Library".into(),
+                                TheFileExtension::new(
+                                    "Eldiron Library".into(),
+                                    vec!["eldiron_library".to_string()],
+                                ),
+                            );
                         }
                     }
                 } else if id.name == "Region Item" {
@@ -2079,6 +2656,62 @@ impl Sidebar {
                         );
                         redraw = true;
                     }
+                } else if id.name == "Library Item"
+                    || id.name == "Library Item Name Edit"
+                    || id.name == "Library Item Code Edit"
+                {
+                    if let Some(_library) = project.libraries.get(&id.references) {
+                        set_project_context(
+                            ctx,
+                            ui,
+                            project,
+                            server_ctx,
+                            ProjectContext::Library(id.references),
+                        );
+                        redraw = true;
+                    }
+                } else if id.name == "Test Item"
+                    || id.name == "Test Item Name Edit"
+                    || id.name == "Test Item Spec Edit"
+                {
+                    if let Some(_test) = project.tests.get(&id.references) {
+                        set_project_context(
+                            ctx,
+                            ui,
+                            project,
+                            server_ctx,
+                            ProjectContext::ScriptTest(id.references),
+                        );
+                        redraw = true;
+                    }
+                } else if id.name == "Dialogue Item"
+                    || id.name == "Dialogue Item Name Edit"
+                    || id.name == "Dialogue Item Graph Edit"
+                {
+                    if let Some(_dialogue) = project.dialogues.get(&id.references) {
+                        set_project_context(
+                            ctx,
+                            ui,
+                            project,
+                            server_ctx,
+                            ProjectContext::Dialogue(id.references),
+                        );
+                        redraw = true;
+                    }
+                } else if id.name == "Quest Item"
+                    || id.name == "Quest Item Name Edit"
+                    || id.name == "Quest Item Stages Edit"
+                {
+                    if let Some(_quest) = project.quests.get(&id.references) {
+                        set_project_context(
+                            ctx,
+                            ui,
+                            project,
+                            server_ctx,
+                            ProjectContext::Quest(id.references),
+                        );
+                        redraw = true;
+                    }
                 } else if id.name == "Item Item Visual Code Edit" {
                     if let Some(_) = project.items.get(&id.references) {
                         server_ctx.curr_character = ContentContext::ItemTemplate(id.references);
@@ -2127,6 +2760,15 @@ impl Sidebar {
                         ProjectContext::ProjectSettings,
                     );
                     redraw = true;
+                } else if id.name == "Project Profiler" {
+                    set_project_context(ctx, ui, project, server_ctx, ProjectContext::Profiler);
+                    redraw = true;
+                } else if id.name == "Project Inspector" {
+                    set_project_context(ctx, ui, project, server_ctx, ProjectContext::Inspector);
+                    redraw = true;
+                } else if id.name == "Project Localization" {
+                    set_project_context(ctx, ui, project, server_ctx, ProjectContext::Localization);
+                    redraw = true;
                 } else if id.name == "Shader Add" {
                     let mut module: Module = Module::as_type(codegridfx::ModuleType::Shader);
                     module.update_routines();
@@ -2267,6 +2909,10 @@ impl Sidebar {
         self.apply_tilemaps(ui, ctx, server_ctx, project);
         self.apply_screens(ui, ctx, server_ctx, project);
         self.apply_assets(ui, ctx, server_ctx, project);
+        self.apply_libraries(ui, ctx, server_ctx, project);
+        self.apply_tests(ui, ctx, server_ctx, project);
+        self.apply_dialogues(ui, ctx, server_ctx, project);
+        self.apply_quests(ui, ctx, server_ctx, project);
         // self.apply_palette(ui, ctx, server_ctx, project);
 
         // if let Some(list_layout) = ui.get_list_layout("Region List") {
@@ -2664,6 +3310,94 @@ impl Sidebar {
         }
     }
 
+    /// Apply the current script libraries to the tree.
+    pub fn apply_libraries(
+        &mut self,
+        ui: &mut TheUI,
+        _ctx: &mut TheContext,
+        server_ctx: &mut ServerContext,
+        project: &mut Project,
+    ) {
+        if let Some(tree_layout) = ui.get_tree_layout("Project Tree") {
+            if let Some(library_node) =
+                tree_layout.get_node_by_id_mut(&server_ctx.tree_libraries_id)
+            {
+                library_node.widgets.clear();
+                library_node.childs.clear();
+
+                for (_, library) in project.libraries.iter() {
+                    let node = gen_library_tree_node(library);
+                    library_node.add_child(node);
+                }
+            }
+        }
+    }
+
+    /// Apply the current script tests to the tree.
+    pub fn apply_tests(
+        &mut self,
+        ui: &mut TheUI,
+        _ctx: &mut TheContext,
+        server_ctx: &mut ServerContext,
+        project: &mut Project,
+    ) {
+        if let Some(tree_layout) = ui.get_tree_layout("Project Tree") {
+            if let Some(test_node) = tree_layout.get_node_by_id_mut(&server_ctx.tree_tests_id) {
+                test_node.widgets.clear();
+                test_node.childs.clear();
+
+                for (_, test) in project.tests.iter() {
+                    let node = gen_test_tree_node(test);
+                    test_node.add_child(node);
+                }
+            }
+        }
+    }
+
+    /// Apply the current dialogues to the tree.
+    pub fn apply_dialogues(
+        &mut self,
+        ui: &mut TheUI,
+        _ctx: &mut TheContext,
+        server_ctx: &mut ServerContext,
+        project: &mut Project,
+    ) {
+        if let Some(tree_layout) = ui.get_tree_layout("Project Tree") {
+            if let Some(dialogue_node) =
+                tree_layout.get_node_by_id_mut(&server_ctx.tree_dialogues_id)
+            {
+                dialogue_node.widgets.clear();
+                dialogue_node.childs.clear();
+
+                for (_, dialogue) in project.dialogues.iter() {
+                    let node = gen_dialogue_tree_node(dialogue);
+                    dialogue_node.add_child(node);
+                }
+            }
+        }
+    }
+
+    /// Apply the current quests to the tree.
+    pub fn apply_quests(
+        &mut self,
+        ui: &mut TheUI,
+        _ctx: &mut TheContext,
+        server_ctx: &mut ServerContext,
+        project: &mut Project,
+    ) {
+        if let Some(tree_layout) = ui.get_tree_layout("Project Tree") {
+            if let Some(quest_node) = tree_layout.get_node_by_id_mut(&server_ctx.tree_quests_id) {
+                quest_node.widgets.clear();
+                quest_node.childs.clear();
+
+                for (_, quest) in project.quests.iter() {
+                    let node = gen_quest_tree_node(quest);
+                    quest_node.add_child(node);
+                }
+            }
+        }
+    }
+
     /// Apply the current palette to the tree.
     pub fn apply_palette(
         &mut self,
@@ -3334,11 +4068,12 @@ impl Sidebar {
     ) -> bool {
         if let Some(undo_atom) = action.apply(map, ui, ctx, server_ctx) {
             if server_ctx.get_map_context() == MapContext::Region {
-                UNDOMANAGER.write().unwrap().add_region_undo(
-                    &server_ctx.curr_region,
-                    undo_atom,
-                    ctx,
-                );
+                if let Some(project_undo_atom) = undo_atom.to_project_atom(server_ctx.pc) {
+                    UNDOMANAGER
+                        .write()
+                        .unwrap()
+                        .add_undo(project_undo_atom, ctx);
+                }
                 if server_ctx.editor_view_mode == EditorViewMode::D2
                     && server_ctx.profile_view.is_some()
                 {
@@ -504,6 +504,8 @@ impl ToolList {
                             if let Some(map) = project.get_map_mut(server_ctx) {
                                 if server_ctx.paste_clipboard.is_some() {
                                     server_ctx.paste_clipboard = None;
+                                    server_ctx.paste_prefab = None;
+                                    server_ctx.paste_prop = None;
                                     return true;
                                 }
 
@@ -626,6 +628,34 @@ impl ToolList {
                 if id.name == "PolyView" {
                     self.char_click_selected = false;
                     self.item_click_selected = false;
+                    // A prefab being placed, cloned as the map borrows the project
+                    let prefab = server_ctx.paste_prefab.and_then(|(id, linked)| {
+                        project.prefabs.get(&id).cloned().map(|p| (p, linked))
+                    });
+                    // A prop being placed becomes an instance of the region
+                    if !server_ctx.game_mode && coord.y > 20 && server_ctx.paste_clipboard.is_some()
+                    {
+                        if let (Some(prop), Some(hover)) =
+                            (server_ctx.paste_prop.clone(), server_ctx.hover_cursor)
+                        {
+                            if let Some(region) = project.get_region_ctx(server_ctx) {
+                                let prev = region.props.clone();
+                                let mut props = prev.clone();
+                                let prop = PropInstance::new(prop.asset, hover, prop.transform);
+                                props.insert(prop.id, prop);
+                                let atom = ProjectUndoAtom::EditProps(
+                                    region.id,
+                                    Box::new(prev),
+                                    Box::new(props),
+                                );
+                                atom.redo(project, ui, ctx, server_ctx);
+                                UNDOMANAGER.write().unwrap().add_undo(atom, ctx);
+                            }
+                            server_ctx.paste_clipboard = None;
+                            server_ctx.paste_prop = None;
+                            return true;
+                        }
+                    }
                     if !server_ctx.game_mode {
                         if let Some(map) = project.get_map_mut(server_ctx) {
                             if coord.y > 20 {
@@ -633,7 +663,20 @@ impl ToolList {
                                 if let Some(paste) = &server_ctx.paste_clipboard {
                                     if let Some(hover) = server_ctx.hover_cursor {
                                         let prev = map.clone();
-                                        map.paste_at_position(paste, hover);
+                                        if let Some((prefab, linked)) = &prefab {
+                                            prefab.place(map, hover, *linked);
+                                        } else {
+                                            map.paste_at_position(paste, hover);
+                                            for (id, graph) in &paste.shapefx_graphs {
+                                                if !map.shapefx_graphs.contains_key(id) {
+                                                    map.shapefx_graphs.insert(*id, graph.clone());
+                                                }
+                                            }
+                                            // Solid parts of generated geometry
+                                            crate::actions::gen_geometry::apply_generated_extrusions(
+                                                map,
+                                            );
+                                        }
 
                                         if server_ctx.curr_map_tool_type == MapToolType::Vertex {
                                             map.selected_linedefs.clear();
@@ -652,6 +695,7 @@ impl ToolList {
 
                                         if server_ctx.curr_map_tool_helper
                                             != MapToolHelper::ShapePicker
+                                            && server_ctx.paste_prefab.is_none()
                                         {
                                             server_ctx.paste_clipboard = None;
                                         }
@@ -690,12 +734,13 @@ impl ToolList {
                                                     .unwrap()
                                                     .add_item_undo(item_undo_atom, ctx);
                                             }
-                                        } else {
-                                            UNDOMANAGER.write().unwrap().add_region_undo(
-                                                &server_ctx.curr_region,
-                                                undo_atom,
-                                                ctx,
-                                            );
+                                        } else if let Some(project_undo_atom) =
+                                            undo_atom.to_project_atom(server_ctx.pc)
+                                        {
+                                            UNDOMANAGER
+                                                .write()
+                                                .unwrap()
+                                                .add_undo(project_undo_atom, ctx);
                                         }
 
                                         return true;
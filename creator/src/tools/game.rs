@@ -1,6 +1,10 @@
-use crate::{editor::RUSTERIX, prelude::*};
+use crate::{
+    editor::{DIALOGUES, INPUTMAP, INVENTORY, RUSTERIX, SHOPS},
+    prelude::*,
+};
 use MapEvent::*;
-use rusterix::{EntityAction, Value};
+use rusterix::Value;
+use shared::rusterix_utils::apply_replay_input;
 use std::sync::Mutex;
 use theframework::prelude::*;
 
@@ -9,6 +13,9 @@ pub struct GameTool {
 
     right: Option<Mutex<Box<TheCanvas>>>,
     toolbar: Option<Mutex<Box<TheCanvas>>>,
+
+    /// The held keys of the game preview.
+    input_state: InputState,
 }
 
 impl Tool for GameTool {
@@ -21,6 +28,8 @@ impl Tool for GameTool {
 
             right: None,
             toolbar: None,
+
+            input_state: InputState::new(),
         }
     }
 
@@ -99,32 +108,45 @@ impl Tool for GameTool {
     fn map_event(
         &mut self,
         map_event: MapEvent,
-        _ui: &mut TheUI,
+        ui: &mut TheUI,
         _ctx: &mut TheContext,
         map: &mut Map,
-        _server_ctx: &mut ServerContext,
+        server_ctx: &mut ServerContext,
     ) -> Option<ProjectUndoAtom> {
-        match map_event {
-            MapClicked(coord) => {
-                let mut rusterix = RUSTERIX.write().unwrap();
-                let is_running = rusterix.server.state == rusterix::ServerState::Running;
+        if server_ctx.is_replaying() {
+            return None;
+        }
 
-                if is_running {
-                    if let Some(action) = rusterix.client.touch_down(coord, map) {
-                        rusterix.server.local_player_action(action);
-                    }
-                }
+        let (width, height) = ui
+            .get_render_view("PolyView")
+            .map(|render_view| {
+                let dim = *render_view.render_buffer_mut().dim();
+                (dim.width as usize, dim.height as usize)
+            })
+            .unwrap_or_default();
+
+        // The dialogue box and the trade screen take the clicks on their
+        // choices and rows, the inventory widgets for their drag and drop
+        let inputs = match map_event {
+            MapClicked(coord)
+                if !DIALOGUES.write().unwrap().click(coord, width, height)
+                    && !SHOPS.write().unwrap().click(coord, width, height)
+                    && !INVENTORY.write().unwrap().press_at(coord) =>
+            {
+                vec![ReplayInput::TouchDown(coord)]
             }
-            MapUp(coord) => {
-                let mut rusterix = RUSTERIX.write().unwrap();
-                let is_running = rusterix.server.state == rusterix::ServerState::Running;
-
-                if is_running {
-                    rusterix.client.touch_up(coord, map);
-                    rusterix.server.local_player_action(EntityAction::Off);
-                }
+            MapUp(coord) if !INVENTORY.write().unwrap().release_at(coord) => {
+                vec![ReplayInput::TouchUp(coord)]
+            }
+            _ => widget_inputs(),
+        };
+
+        let mut rusterix = RUSTERIX.write().unwrap();
+        if rusterix.server.state == rusterix::ServerState::Running {
+            for input in inputs {
+                apply_replay_input(&mut rusterix, &input, map);
+                server_ctx.record_replay_input(input);
             }
-            _ => {}
         }
 
         None
@@ -135,33 +157,71 @@ impl Tool for GameTool {
         event: &TheEvent,
         _ui: &mut TheUI,
         _ctx: &mut TheContext,
-        _project: &mut Project,
-        _server_ctx: &mut ServerContext,
+        project: &mut Project,
+        server_ctx: &mut ServerContext,
     ) -> bool {
-        #[allow(clippy::single_match)]
-        match event {
-            TheEvent::KeyDown(TheValue::Char(char)) => {
-                let mut rusterix = crate::editor::RUSTERIX.write().unwrap();
-                if rusterix.server.state == rusterix::ServerState::Running {
-                    let action = rusterix
-                        .client
-                        .user_event("key_down".into(), Value::Str(char.to_string()));
-
-                    rusterix.server.local_player_action(action);
-                }
+        if server_ctx.is_replaying() {
+            return false;
+        }
+
+        let source = match event {
+            TheEvent::KeyDown(TheValue::Char(char)) => Some((InputSource::from_char(*char), true)),
+            TheEvent::KeyUp(TheValue::Char(char)) => Some((InputSource::from_char(*char), false)),
+            TheEvent::KeyCodeDown(TheValue::KeyCode(code)) => {
+                InputSource::from_key_code(code).map(|source| (source, true))
             }
-            TheEvent::KeyUp(TheValue::Char(char)) => {
-                let mut rusterix = crate::editor::RUSTERIX.write().unwrap();
-                if rusterix.server.state == rusterix::ServerState::Running {
-                    let action = rusterix
-                        .client
-                        .user_event("key_up".into(), Value::Str(char.to_string()));
-                    rusterix.server.local_player_action(action);
+            TheEvent::KeyCodeUp(TheValue::KeyCode(code)) => {
+                InputSource::from_key_code(code).map(|source| (source, false))
+            }
+            _ => None,
+        };
+
+        // Keys are mapped to the input actions of the game config
+        if let Some((source, pressed)) = source {
+            let mut rusterix = crate::editor::RUSTERIX.write().unwrap();
+            if rusterix.server.state == rusterix::ServerState::Running {
+                let input_map = INPUTMAP.read().unwrap();
+
+                // A shown dialogue takes the keys to continue and pick choices,
+                // the trade screen the keys to pick and trade items and the
+                // hotbar actions use the items of the hotbar
+                let consumed = pressed
+                    && (DIALOGUES
+                        .write()
+                        .unwrap()
+                        .press(&project.dialogues, &input_map, &source)
+                        || SHOPS.write().unwrap().press(&input_map, &source)
+                        || INVENTORY.write().unwrap().press(&input_map, &source));
+
+                let inputs = if consumed {
+                    widget_inputs()
+                } else if pressed {
+                    self.input_state.press(&input_map, source)
+                } else {
+                    self.input_state.release(&input_map, source)
+                };
+                for input in inputs {
+                    if let ReplayInput::UserEvent(event, value) = &input {
+                        let action = rusterix
+                            .client
+                            .user_event(event.clone(), Value::Str(value.clone()));
+                        rusterix.server.local_player_action(action);
+                    }
+                    server_ctx.record_replay_input(input);
                 }
+                return consumed;
             }
-            _ => {}
         }
 
         false
     }
 }
+
+/// The user events of the dialogue, trade and inventory widgets, sent and
+/// recorded like the keys of the player.
+fn widget_inputs() -> Vec<ReplayInput> {
+    let mut inputs = DIALOGUES.write().unwrap().take_inputs();
+    inputs.extend(SHOPS.write().unwrap().take_inputs());
+    inputs.extend(INVENTORY.write().unwrap().take_inputs());
+    inputs
+}
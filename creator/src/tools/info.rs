@@ -1,6 +1,7 @@
 use crate::prelude::*;
 use ToolEvent::*;
 use rusterix::Value;
+use shared::rusterix_utils::send_user_event;
 
 use crate::editor::INFOVIEWER;
 
@@ -8,6 +9,9 @@ pub struct InfoTool {
     id: TheId,
 
     info_mode: i32,
+
+    /// The held keys of the game preview.
+    input_state: InputState,
 }
 
 impl Tool for InfoTool {
@@ -18,6 +22,7 @@ impl Tool for InfoTool {
         Self {
             id: TheId::named("Info Tool"),
             info_mode: 0,
+            input_state: InputState::new(),
         }
     }
     fn id(&self) -> TheId {
@@ -88,9 +93,8 @@ impl Tool for InfoTool {
         _ui: &mut TheUI,
         _ctx: &mut TheContext,
         _project: &mut Project,
-        _server_ctx: &mut ServerContext,
+        server_ctx: &mut ServerContext,
     ) -> bool {
-        #[allow(clippy::single_match)]
         match event {
             TheEvent::IndexChanged(id, index) => {
                 if id.name == "Info Switch" {
@@ -99,19 +103,19 @@ impl Tool for InfoTool {
                 }
             }
             TheEvent::KeyDown(TheValue::Char(char)) => {
-                let mut rusterix = crate::editor::RUSTERIX.write().unwrap();
-                if rusterix.server.state == rusterix::ServerState::Running {
-                    rusterix
-                        .server
-                        .local_player_event("key_down".into(), Value::Str(char.to_string()));
-                }
+                self.send_input(InputSource::from_char(*char), true, server_ctx);
             }
             TheEvent::KeyUp(TheValue::Char(char)) => {
-                let mut rusterix = crate::editor::RUSTERIX.write().unwrap();
-                if rusterix.server.state == rusterix::ServerState::Running {
-                    rusterix
-                        .server
-                        .local_player_event("key_up".into(), Value::Str(char.to_string()));
+                self.send_input(InputSource::from_char(*char), false, server_ctx);
+            }
+            TheEvent::KeyCodeDown(TheValue::KeyCode(code)) => {
+                if let Some(source) = InputSource::from_key_code(code) {
+                    self.send_input(source, true, server_ctx);
+                }
+            }
+            TheEvent::KeyCodeUp(TheValue::KeyCode(code)) => {
+                if let Some(source) = InputSource::from_key_code(code) {
+                    self.send_input(source, false, server_ctx);
                 }
             }
             _ => {}
@@ -120,3 +124,28 @@ impl Tool for InfoTool {
         false
     }
 }
+
+impl InfoTool {
+    /// Sends the input actions of a key to the local player and records them.
+    fn send_input(&mut self, source: InputSource, pressed: bool, server_ctx: &mut ServerContext) {
+        if server_ctx.is_replaying() {
+            return;
+        }
+
+        let mut rusterix = crate::editor::RUSTERIX.write().unwrap();
+        if rusterix.server.state == rusterix::ServerState::Running {
+            let input_map = crate::editor::INPUTMAP.read().unwrap();
+            let inputs = if pressed {
+                self.input_state.press(&input_map, source)
+            } else {
+                self.input_state.release(&input_map, source)
+            };
+            for input in inputs {
+                if let ReplayInput::UserEvent(event, value) = &input {
+                    send_user_event(&mut rusterix, event.clone(), Value::Str(value.clone()));
+                }
+                server_ctx.record_replay_input(input);
+            }
+        }
+    }
+}
@@ -1,3 +1,4 @@
+use crate::editor::{DIALOGUEEDITOR, LOCALIZATIONEDITOR};
 use crate::prelude::*;
 use theframework::prelude::*;
 
@@ -8,6 +9,7 @@ pub enum ProjectUndoAtom {
     AddRegion(Region),
     RemoveRegion(usize, Region),
     RenameRegion(Uuid, String, String),
+    GenerateRegion(Box<Region>, Box<Region>),
     AddRegionCharacterInstance(Uuid, Character),
     RemoveRegionCharacterInstance(usize, Uuid, Character),
     AddRegionItemInstance(Uuid, Item),
@@ -22,12 +24,33 @@ pub enum ProjectUndoAtom {
     RemoveTilemap(usize, Tilemap),
     RenameTilemap(Uuid, String, String),
     EditTilemapGridSize(Uuid, i32, i32),
+    ImportTiles(IndexMap<Uuid, rusterix::Tile>),
     AddScreen(Screen),
     RemoveScreen(usize, Screen),
     RenameScreen(Uuid, String, String),
     AddAsset(Asset),
     RemoveAsset(usize, Asset),
     RenameAsset(Uuid, String, String),
+    AddLibrary(ScriptLibrary),
+    RemoveLibrary(usize, ScriptLibrary),
+    RenameLibrary(Uuid, String, String),
+    AddScriptTest(ScriptTest),
+    RemoveScriptTest(usize, ScriptTest),
+    RenameScriptTest(Uuid, String, String),
+    AddDialogue(Dialogue),
+    RemoveDialogue(usize, Dialogue),
+    RenameDialogue(Uuid, String, String),
+    EditDialogue(Box<Dialogue>, Box<Dialogue>),
+    AddQuest(Quest),
+    RemoveQuest(usize, Quest),
+    RenameQuest(Uuid, String, String),
+    EditLocalization(Box<Localization>, Box<Localization>),
+    EditInstanceData(ProjectContext, String, String),
+    EditProps(
+        Uuid,
+        Box<IndexMap<Uuid, PropInstance>>,
+        Box<IndexMap<Uuid, PropInstance>>,
+    ),
 }
 
 use ProjectUndoAtom::*;
@@ -48,6 +71,7 @@ impl ProjectUndoAtom {
             AddRegion(region) => format!("Add Region: {}", region.name),
             RemoveRegion(_, region) => format!("Remove Region: {}", region.name),
             RenameRegion(_, old, new) => format!("Rename Region: {} -> {}", old, new),
+            GenerateRegion(_, new) => format!("Generate Region: {}", new.name),
             AddRegionCharacterInstance(_, character) => {
                 format!("Add Region Character Instance: {}", character.name)
             }
@@ -69,6 +93,7 @@ impl ProjectUndoAtom {
             AddTilemap(tilemap) => format!("Add Tilemap: {}", tilemap.name),
             RemoveTilemap(_, tilemap) => format!("Remove Tilemap: {}", tilemap.name),
             RenameTilemap(_, old, new) => format!("Rename Tilemap: {} -> {}", old, new),
+            ImportTiles(tiles) => format!("Import Tiles: {}", tiles.len()),
             EditTilemapGridSize(_, old, new) => {
                 format!("Edit Tilemap Grid Size: {} -> {}", old, new)
             }
@@ -78,6 +103,22 @@ impl ProjectUndoAtom {
             AddAsset(asset) => format!("Add Asset: {}", asset.name),
             RemoveAsset(_, asset) => format!("Remove Asset: {}", asset.name),
             RenameAsset(_, old, new) => format!("Rename Asset: {} -> {}", old, new),
+            AddLibrary(library) => format!("Add Library: {}", library.name),
+            RemoveLibrary(_, library) => format!("Remove Library: {}", library.name),
+            RenameLibrary(_, old, new) => format!("Rename Library: {} -> {}", old, new),
+            AddScriptTest(test) => format!("Add Test: {}", test.name),
+            RemoveScriptTest(_, test) => format!("Remove Test: {}", test.name),
+            RenameScriptTest(_, old, new) => format!("Rename Test: {} -> {}", old, new),
+            AddDialogue(dialogue) => format!("Add Dialogue: {}", dialogue.name),
+            RemoveDialogue(_, dialogue) => format!("Remove Dialogue: {}", dialogue.name),
+            RenameDialogue(_, old, new) => format!("Rename Dialogue: {} -> {}", old, new),
+            EditDialogue(_, dialogue) => format!("Edit Dialogue: {}", dialogue.name),
+            AddQuest(quest) => format!("Add Quest: {}", quest.name),
+            RemoveQuest(_, quest) => format!("Remove Quest: {}", quest.name),
+            RenameQuest(_, old, new) => format!("Rename Quest: {} -> {}", old, new),
+            EditLocalization(_, _) => "Edit Localization".to_string(),
+            EditInstanceData(_, _, _) => "Edit Instance Data".to_string(),
+            EditProps(_, _, _) => "Edit Props".to_string(),
         }
     }
 
@@ -135,6 +176,9 @@ impl ProjectUndoAtom {
                     update_region(ctx);
                 }
             }
+            GenerateRegion(old, _new) => {
+                Self::replace_region(old, project, ui, ctx, server_ctx);
+            }
             RenameRegion(id, old, _new) => {
                 if let Some(region) = project.get_region_mut(id) {
                     region.name = old.clone();
@@ -394,6 +438,15 @@ impl ProjectUndoAtom {
                     }
                 }
             }
+            ImportTiles(tiles) => {
+                for id in tiles.keys() {
+                    project.tiles.shift_remove(id);
+                }
+                ctx.ui.send(TheEvent::Custom(
+                    TheId::named("Update Tilepicker"),
+                    TheValue::Empty,
+                ));
+            }
             EditTilemapGridSize(id, old, _new) => {
                 if let Some(tilemap) = project.get_tilemap_mut(*id) {
                     tilemap.grid_size = *old;
@@ -513,6 +566,228 @@ impl ProjectUndoAtom {
                     }
                 }
             }
+            AddLibrary(library) => {
+                if let Some(tree_layout) = ui.get_tree_layout("Project Tree") {
+                    if let Some(library_node) =
+                        tree_layout.get_node_by_id_mut(&server_ctx.tree_libraries_id)
+                    {
+                        project.remove_library(&library.id);
+                        library_node.remove_child_by_uuid(&library.id);
+                    }
+                }
+            }
+            RemoveLibrary(index, library) => {
+                if let Some(tree_layout) = ui.get_tree_layout("Project Tree") {
+                    let library = library.clone();
+
+                    let mut node = gen_library_tree_node(&library);
+                    node.set_open(true);
+                    if let Some(library_node) =
+                        tree_layout.get_node_by_id_mut(&server_ctx.tree_libraries_id)
+                    {
+                        library_node.add_child_at(*index, node);
+                    }
+                    let library_id: Uuid = library.id;
+                    project.libraries.insert_before(*index, library_id, library);
+
+                    set_project_context(
+                        ctx,
+                        ui,
+                        project,
+                        server_ctx,
+                        ProjectContext::Library(library_id),
+                    );
+                }
+            }
+            RenameLibrary(id, old, _new) => {
+                if let Some(library) = project.libraries.get_mut(id) {
+                    library.name = old.clone();
+                    if let Some(tree_layout) = ui.get_tree_layout("Project Tree") {
+                        if let Some(library_node) = tree_layout.get_node_by_id_mut(&library.id) {
+                            library_node.widget.set_value(TheValue::Text(old.clone()));
+                            if let Some(widget) = library_node.widgets[0].as_tree_item() {
+                                if let Some(embedded) = widget.embedded_widget_mut() {
+                                    embedded.set_value(TheValue::Text(old.clone()));
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+            AddScriptTest(test) => {
+                if let Some(tree_layout) = ui.get_tree_layout("Project Tree") {
+                    if let Some(test_node) =
+                        tree_layout.get_node_by_id_mut(&server_ctx.tree_tests_id)
+                    {
+                        project.remove_test(&test.id);
+                        test_node.remove_child_by_uuid(&test.id);
+                    }
+                }
+            }
+            RemoveScriptTest(index, test) => {
+                if let Some(tree_layout) = ui.get_tree_layout("Project Tree") {
+                    let test = test.clone();
+
+                    let mut node = gen_test_tree_node(&test);
+                    node.set_open(true);
+                    if let Some(test_node) =
+                        tree_layout.get_node_by_id_mut(&server_ctx.tree_tests_id)
+                    {
+                        test_node.add_child_at(*index, node);
+                    }
+                    let test_id: Uuid = test.id;
+                    project.tests.insert_before(*index, test_id, test);
+
+                    set_project_context(
+                        ctx,
+                        ui,
+                        project,
+                        server_ctx,
+                        ProjectContext::ScriptTest(test_id),
+                    );
+                }
+            }
+            RenameScriptTest(id, old, _new) => {
+                if let Some(test) = project.tests.get_mut(id) {
+                    test.name = old.clone();
+                    if let Some(tree_layout) = ui.get_tree_layout("Project Tree") {
+                        if let Some(test_node) = tree_layout.get_node_by_id_mut(&test.id) {
+                            test_node.widget.set_value(TheValue::Text(old.clone()));
+                            if let Some(widget) = test_node.widgets[0].as_tree_item() {
+                                if let Some(embedded) = widget.embedded_widget_mut() {
+                                    embedded.set_value(TheValue::Text(old.clone()));
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+            AddDialogue(dialogue) => {
+                if let Some(tree_layout) = ui.get_tree_layout("Project Tree") {
+                    if let Some(dialogue_node) =
+                        tree_layout.get_node_by_id_mut(&server_ctx.tree_dialogues_id)
+                    {
+                        project.remove_dialogue(&dialogue.id);
+                        dialogue_node.remove_child_by_uuid(&dialogue.id);
+                    }
+                }
+            }
+            RemoveDialogue(index, dialogue) => {
+                if let Some(tree_layout) = ui.get_tree_layout("Project Tree") {
+                    let dialogue = dialogue.clone();
+
+                    let mut node = gen_dialogue_tree_node(&dialogue);
+                    node.set_open(true);
+                    if let Some(dialogue_node) =
+                        tree_layout.get_node_by_id_mut(&server_ctx.tree_dialogues_id)
+                    {
+                        dialogue_node.add_child_at(*index, node);
+                    }
+                    let dialogue_id: Uuid = dialogue.id;
+                    project
+                        .dialogues
+                        .insert_before(*index, dialogue_id, dialogue);
+
+                    set_project_context(
+                        ctx,
+                        ui,
+                        project,
+                        server_ctx,
+                        ProjectContext::Dialogue(dialogue_id),
+                    );
+                }
+            }
+            RenameDialogue(id, old, _new) => {
+                if let Some(dialogue) = project.dialogues.get_mut(id) {
+                    dialogue.name = old.clone();
+                    if let Some(tree_layout) = ui.get_tree_layout("Project Tree") {
+                        if let Some(dialogue_node) = tree_layout.get_node_by_id_mut(&dialogue.id) {
+                            dialogue_node.widget.set_value(TheValue::Text(old.clone()));
+                            if let Some(widget) = dialogue_node.widgets[0].as_tree_item() {
+                                if let Some(embedded) = widget.embedded_widget_mut() {
+                                    embedded.set_value(TheValue::Text(old.clone()));
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+            EditDialogue(old, _new) => {
+                project.add_dialogue(*old.clone());
+                set_project_context(
+                    ctx,
+                    ui,
+                    project,
+                    server_ctx,
+                    ProjectContext::Dialogue(old.id),
+                );
+                DIALOGUEEDITOR.write().unwrap().apply_dialogue(old, ui, ctx);
+            }
+            AddQuest(quest) => {
+                if let Some(tree_layout) = ui.get_tree_layout("Project Tree") {
+                    if let Some(quest_node) =
+                        tree_layout.get_node_by_id_mut(&server_ctx.tree_quests_id)
+                    {
+                        project.remove_quest(&quest.id);
+                        quest_node.remove_child_by_uuid(&quest.id);
+                    }
+                }
+            }
+            RemoveQuest(index, quest) => {
+                if let Some(tree_layout) = ui.get_tree_layout("Project Tree") {
+                    let quest = quest.clone();
+
+                    let mut node = gen_quest_tree_node(&quest);
+                    node.set_open(true);
+                    if let Some(quest_node) =
+                        tree_layout.get_node_by_id_mut(&server_ctx.tree_quests_id)
+                    {
+                        quest_node.add_child_at(*index, node);
+                    }
+                    let quest_id: Uuid = quest.id;
+                    project.quests.insert_before(*index, quest_id, quest);
+
+                    set_project_context(
+                        ctx,
+                        ui,
+                        project,
+                        server_ctx,
+                        ProjectContext::Quest(quest_id),
+                    );
+                }
+            }
+            RenameQuest(id, old, _new) => {
+                if let Some(quest) = project.quests.get_mut(id) {
+                    quest.name = old.clone();
+                    if let Some(tree_layout) = ui.get_tree_layout("Project Tree") {
+                        if let Some(quest_node) = tree_layout.get_node_by_id_mut(&quest.id) {
+                            quest_node.widget.set_value(TheValue::Text(old.clone()));
+                            if let Some(widget) = quest_node.widgets[0].as_tree_item() {
+                                if let Some(embedded) = widget.embedded_widget_mut() {
+                                    embedded.set_value(TheValue::Text(old.clone()));
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+            EditLocalization(old, _new) => {
+                project.localization = *old.clone();
+                set_project_context(ctx, ui, project, server_ctx, ProjectContext::Localization);
+                LOCALIZATIONEDITOR
+                    .write()
+                    .unwrap()
+                    .refresh(project, ui, ctx);
+            }
+            EditInstanceData(pc, old, _new) => {
+                Self::set_instance_data(*pc, old, project, ui, ctx, server_ctx);
+            }
+            EditProps(region_id, old, _new) => {
+                if let Some(region) = project.get_region_mut(region_id) {
+                    region.props = *old.clone();
+                    update_region(ctx);
+                }
+            }
         }
     }
 
@@ -586,6 +861,9 @@ impl ProjectUndoAtom {
                     }
                 }
             }
+            GenerateRegion(_old, new) => {
+                Self::replace_region(new, project, ui, ctx, server_ctx);
+            }
             RenameRegion(id, _old, new) => {
                 if let Some(region) = project.get_region_mut(id) {
                     region.name = new.clone();
@@ -912,6 +1190,13 @@ impl ProjectUndoAtom {
                     }
                 }
             }
+            ImportTiles(tiles) => {
+                project.tiles.extend(tiles.clone());
+                ctx.ui.send(TheEvent::Custom(
+                    TheId::named("Update Tilepicker"),
+                    TheValue::Empty,
+                ));
+            }
             EditTilemapGridSize(id, _old, new) => {
                 if let Some(tilemap) = project.get_tilemap_mut(*id) {
                     tilemap.grid_size = *new;
@@ -1056,6 +1341,360 @@ impl ProjectUndoAtom {
                     }
                 }
             }
+            AddLibrary(library) => {
+                if let Some(tree_layout) = ui.get_tree_layout("Project Tree") {
+                    if let Some(node) =
+                        tree_layout.get_node_by_id_mut(&server_ctx.tree_libraries_id)
+                    {
+                        let mut library = library.clone();
+
+                        if library.source.is_empty() {
+                            if let Some(bytes) = crate::Embedded::get("python/library.py") {
+                                if let Ok(source) = std::str::from_utf8(bytes.data.as_ref()) {
+                                    library.source = source.to_string();
+                                }
+                            }
+                        }
+
+                        let mut library_node = gen_library_tree_node(&library);
+                        library_node.set_open(true);
+                        node.add_child(library_node);
+
+                        let library_id = library.id;
+                        project.add_library(library);
+
+                        set_project_context(
+                            ctx,
+                            ui,
+                            project,
+                            server_ctx,
+                            ProjectContext::Library(library_id),
+                        );
+                    }
+                }
+            }
+            RemoveLibrary(_, library) => {
+                if let Some(tree_layout) = ui.get_tree_layout("Project Tree") {
+                    if let Some(library_node) =
+                        tree_layout.get_node_by_id_mut(&server_ctx.tree_libraries_id)
+                    {
+                        library_node.remove_child_by_uuid(&library.id);
+                    }
+                    project.remove_library(&library.id);
+
+                    if let Some(first_library) = project.libraries.first() {
+                        if let Some(library_node) = tree_layout.get_node_by_id_mut(first_library.0)
+                        {
+                            library_node.set_open(true);
+                        }
+                        set_project_context(
+                            ctx,
+                            ui,
+                            project,
+                            server_ctx,
+                            ProjectContext::Library(*first_library.0),
+                        );
+                    }
+                }
+            }
+            RenameLibrary(id, _old, new) => {
+                if let Some(library) = project.libraries.get_mut(id) {
+                    library.name = new.clone();
+                    if let Some(tree_layout) = ui.get_tree_layout("Project Tree") {
+                        if let Some(library_node) = tree_layout.get_node_by_id_mut(id) {
+                            library_node.widget.set_value(TheValue::Text(new.clone()));
+                            if let Some(widget) = library_node.widgets[0].as_tree_item() {
+                                if let Some(embedded) = widget.embedded_widget_mut() {
+                                    embedded.set_value(TheValue::Text(new.clone()));
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+            AddScriptTest(test) => {
+                if let Some(tree_layout) = ui.get_tree_layout("Project Tree") {
+                    if let Some(node) = tree_layout.get_node_by_id_mut(&server_ctx.tree_tests_id) {
+                        let mut test = test.clone();
+
+                        if test.source.is_empty() {
+                            if let Some(bytes) = crate::Embedded::get("toml/test.toml") {
+                                if let Ok(source) = std::str::from_utf8(bytes.data.as_ref()) {
+                                    test.source = source.to_string();
+                                }
+                            }
+                        }
+
+                        let mut test_node = gen_test_tree_node(&test);
+                        test_node.set_open(true);
+                        node.add_child(test_node);
+
+                        let test_id = test.id;
+                        project.add_test(test);
+
+                        set_project_context(
+                            ctx,
+                            ui,
+                            project,
+                            server_ctx,
+                            ProjectContext::ScriptTest(test_id),
+                        );
+                    }
+                }
+            }
+            RemoveScriptTest(_, test) => {
+                if let Some(tree_layout) = ui.get_tree_layout("Project Tree") {
+                    if let Some(test_node) =
+                        tree_layout.get_node_by_id_mut(&server_ctx.tree_tests_id)
+                    {
+                        test_node.remove_child_by_uuid(&test.id);
+                    }
+                    project.remove_test(&test.id);
+
+                    if let Some(first_test) = project.tests.first() {
+                        if let Some(test_node) = tree_layout.get_node_by_id_mut(first_test.0) {
+                            test_node.set_open(true);
+                        }
+                        set_project_context(
+                            ctx,
+                            ui,
+                            project,
+                            server_ctx,
+                            ProjectContext::ScriptTest(*first_test.0),
+                        );
+                    }
+                }
+            }
+            RenameScriptTest(id, _old, new) => {
+                if let Some(test) = project.tests.get_mut(id) {
+                    test.name = new.clone();
+                    if let Some(tree_layout) = ui.get_tree_layout("Project Tree") {
+                        if let Some(test_node) = tree_layout.get_node_by_id_mut(id) {
+                            test_node.widget.set_value(TheValue::Text(new.clone()));
+                            if let Some(widget) = test_node.widgets[0].as_tree_item() {
+                                if let Some(embedded) = widget.embedded_widget_mut() {
+                                    embedded.set_value(TheValue::Text(new.clone()));
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+            AddDialogue(dialogue) => {
+                if let Some(tree_layout) = ui.get_tree_layout("Project Tree") {
+                    if let Some(node) =
+                        tree_layout.get_node_by_id_mut(&server_ctx.tree_dialogues_id)
+                    {
+                        let dialogue = dialogue.clone();
+
+                        let mut dialogue_node = gen_dialogue_tree_node(&dialogue);
+                        dialogue_node.set_open(true);
+                        node.add_child(dialogue_node);
+
+                        let dialogue_id = dialogue.id;
+                        project.add_dialogue(dialogue);
+
+                        set_project_context(
+                            ctx,
+                            ui,
+                            project,
+                            server_ctx,
+                            ProjectContext::Dialogue(dialogue_id),
+                        );
+                    }
+                }
+            }
+            RemoveDialogue(_, dialogue) => {
+                if let Some(tree_layout) = ui.get_tree_layout("Project Tree") {
+                    if let Some(dialogue_node) =
+                        tree_layout.get_node_by_id_mut(&server_ctx.tree_dialogues_id)
+                    {
+                        dialogue_node.remove_child_by_uuid(&dialogue.id);
+                    }
+                    project.remove_dialogue(&dialogue.id);
+
+                    if let Some(first_dialogue) = project.dialogues.first() {
+                        if let Some(dialogue_node) =
+                            tree_layout.get_node_by_id_mut(first_dialogue.0)
+                        {
+                            dialogue_node.set_open(true);
+                        }
+                        set_project_context(
+                            ctx,
+                            ui,
+                            project,
+                            server_ctx,
+                            ProjectContext::Dialogue(*first_dialogue.0),
+                        );
+                    }
+                }
+            }
+            RenameDialogue(id, _old, new) => {
+                if let Some(dialogue) = project.dialogues.get_mut(id) {
+                    dialogue.name = new.clone();
+                    if let Some(tree_layout) = ui.get_tree_layout("Project Tree") {
+                        if let Some(dialogue_node) = tree_layout.get_node_by_id_mut(id) {
+                            dialogue_node.widget.set_value(TheValue::Text(new.clone()));
+                            if let Some(widget) = dialogue_node.widgets[0].as_tree_item() {
+                                if let Some(embedded) = widget.embedded_widget_mut() {
+                                    embedded.set_value(TheValue::Text(new.clone()));
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+            EditDialogue(_old, new) => {
+                project.add_dialogue(*new.clone());
+                set_project_context(
+                    ctx,
+                    ui,
+                    project,
+                    server_ctx,
+                    ProjectContext::Dialogue(new.id),
+                );
+                DIALOGUEEDITOR.write().unwrap().apply_dialogue(new, ui, ctx);
+            }
+            AddQuest(quest) => {
+                if let Some(tree_layout) = ui.get_tree_layout("Project Tree") {
+                    if let Some(node) = tree_layout.get_node_by_id_mut(&server_ctx.tree_quests_id) {
+                        let quest = quest.clone();
+
+                        let mut quest_node = gen_quest_tree_node(&quest);
+                        quest_node.set_open(true);
+                        node.add_child(quest_node);
+
+                        let quest_id = quest.id;
+                        project.add_quest(quest);
+
+                        set_project_context(
+                            ctx,
+                            ui,
+                            project,
+                            server_ctx,
+                            ProjectContext::Quest(quest_id),
+                        );
+                    }
+                }
+            }
+            RemoveQuest(_, quest) => {
+                if let Some(tree_layout) = ui.get_tree_layout("Project Tree") {
+                    if let Some(quest_node) =
+                        tree_layout.get_node_by_id_mut(&server_ctx.tree_quests_id)
+                    {
+                        quest_node.remove_child_by_uuid(&quest.id);
+                    }
+                    project.remove_quest(&quest.id);
+
+                    if let Some(first_quest) = project.quests.first() {
+                        if let Some(quest_node) = tree_layout.get_node_by_id_mut(first_quest.0) {
+                            quest_node.set_open(true);
+                        }
+                        set_project_context(
+                            ctx,
+                            ui,
+                            project,
+                            server_ctx,
+                            ProjectContext::Quest(*first_quest.0),
+                        );
+                    }
+                }
+            }
+            RenameQuest(id, _old, new) => {
+                if let Some(quest) = project.quests.get_mut(id) {
+                    quest.name = new.clone();
+                    if let Some(tree_layout) = ui.get_tree_layout("Project Tree") {
+                        if let Some(quest_node) = tree_layout.get_node_by_id_mut(id) {
+                            quest_node.widget.set_value(TheValue::Text(new.clone()));
+                            if let Some(widget) = quest_node.widgets[0].as_tree_item() {
+                                if let Some(embedded) = widget.embedded_widget_mut() {
+                                    embedded.set_value(TheValue::Text(new.clone()));
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+            EditLocalization(_old, new) => {
+                project.localization = *new.clone();
+                set_project_context(ctx, ui, project, server_ctx, ProjectContext::Localization);
+                LOCALIZATIONEDITOR
+                    .write()
+                    .unwrap()
+                    .refresh(project, ui, ctx);
+            }
+            EditInstanceData(pc, _old, new) => {
+                Self::set_instance_data(*pc, new, project, ui, ctx, server_ctx);
+            }
+            EditProps(region_id, _old, new) => {
+                if let Some(region) = project.get_region_mut(region_id) {
+                    region.props = *new.clone();
+                    update_region(ctx);
+                }
+            }
+        }
+    }
+
+    /// Sets the TOML data of the character or item instance of the context and
+    /// shows the instance.
+    fn set_instance_data(
+        pc: ProjectContext,
+        data: &str,
+        project: &mut Project,
+        ui: &mut TheUI,
+        ctx: &mut TheContext,
+        server_ctx: &mut ServerContext,
+    ) {
+        match pc {
+            ProjectContext::RegionCharacterInstance(region_id, id) => {
+                if let Some(character) = project
+                    .get_region_mut(&region_id)
+                    .and_then(|region| region.characters.get_mut(&id))
+                {
+                    character.data = data.to_string();
+                }
+            }
+            ProjectContext::RegionItemInstance(region_id, id) => {
+                if let Some(item) = project
+                    .get_region_mut(&region_id)
+                    .and_then(|region| region.items.get_mut(&id))
+                {
+                    item.data = data.to_string();
+                }
+            }
+            _ => return,
+        }
+        set_project_context(ctx, ui, project, server_ctx, pc);
+    }
+
+    /// Replaces the region with the same id by the given region and rebuilds
+    /// its tree items and map content.
+    fn replace_region(
+        region: &Region,
+        project: &mut Project,
+        ui: &mut TheUI,
+        ctx: &mut TheContext,
+        server_ctx: &mut ServerContext,
+    ) {
+        if let Some(existing) = project.get_region_mut(&region.id) {
+            *existing = region.clone();
+            existing.map.name = region.name.clone();
+        }
+        if let Some(tree_layout) = ui.get_tree_layout("Project Tree") {
+            if let Some(region_node) = tree_layout.get_node_by_id_mut(&region.id) {
+                gen_region_tree_items(region_node, region);
+            }
         }
+        shared::rusterix_utils::insert_content_into_maps(project);
+
+        server_ctx.curr_region = region.id;
+        set_project_context(
+            ctx,
+            ui,
+            project,
+            server_ctx,
+            ProjectContext::Region(region.id),
+        );
+        update_region(ctx);
     }
 }
@@ -1,4 +1,4 @@
-use crate::editor::{DOCKMANAGER, RUSTERIX};
+use crate::editor::{DOCKMANAGER, RUSTERIX, SIDEBARMODE};
 use crate::prelude::*;
 use theframework::prelude::*;
 
@@ -209,6 +209,104 @@ pub fn gen_asset_tree_node(asset: &Asset) -> TheTreeNode {
     node
 }
 
+/// Returns a TheTreeNode for the script library.
+pub fn gen_library_tree_node(library: &ScriptLibrary) -> TheTreeNode {
+    let mut node: TheTreeNode = TheTreeNode::new(TheId::named_with_id(&library.name, library.id));
+    node.set_root_mode(false);
+
+    let mut item = TheTreeItem::new(TheId::named_with_reference("Library Item", library.id));
+    item.set_text("Name".into());
+
+    let mut edit = TheTextLineEdit::new(TheId::named_with_id("Library Item Name Edit", library.id));
+    edit.set_text(library.name.clone());
+    item.add_widget_column(200, Box::new(edit));
+
+    node.add_widget(Box::new(item));
+
+    let mut item = TheTreeItem::new(TheId::named_with_reference(
+        "Library Item Code Edit",
+        library.id,
+    ));
+    item.set_background_color(TheColor::from(ActionRole::Dock.to_color()));
+    item.set_text("Python Code".into());
+    node.add_widget(Box::new(item));
+
+    node
+}
+
+/// Returns a TheTreeNode for the script test.
+pub fn gen_test_tree_node(test: &ScriptTest) -> TheTreeNode {
+    let mut node: TheTreeNode = TheTreeNode::new(TheId::named_with_id(&test.name, test.id));
+    node.set_root_mode(false);
+
+    let mut item = TheTreeItem::new(TheId::named_with_reference("Test Item", test.id));
+    item.set_text("Name".into());
+
+    let mut edit = TheTextLineEdit::new(TheId::named_with_id("Test Item Name Edit", test.id));
+    edit.set_text(test.name.clone());
+    item.add_widget_column(200, Box::new(edit));
+
+    node.add_widget(Box::new(item));
+
+    let mut item = TheTreeItem::new(TheId::named_with_reference("Test Item Spec Edit", test.id));
+    item.set_background_color(TheColor::from(ActionRole::Dock.to_color()));
+    item.set_text("Test".into());
+    node.add_widget(Box::new(item));
+
+    node
+}
+
+/// Returns a TheTreeNode for the dialogue.
+pub fn gen_dialogue_tree_node(dialogue: &Dialogue) -> TheTreeNode {
+    let mut node: TheTreeNode = TheTreeNode::new(TheId::named_with_id(&dialogue.name, dialogue.id));
+    node.set_root_mode(false);
+
+    let mut item = TheTreeItem::new(TheId::named_with_reference("Dialogue Item", dialogue.id));
+    item.set_text("Name".into());
+
+    let mut edit =
+        TheTextLineEdit::new(TheId::named_with_id("Dialogue Item Name Edit", dialogue.id));
+    edit.set_text(dialogue.name.clone());
+    item.add_widget_column(200, Box::new(edit));
+
+    node.add_widget(Box::new(item));
+
+    let mut item = TheTreeItem::new(TheId::named_with_reference(
+        "Dialogue Item Graph Edit",
+        dialogue.id,
+    ));
+    item.set_background_color(TheColor::from(ActionRole::Dock.to_color()));
+    item.set_text("Graph".into());
+    node.add_widget(Box::new(item));
+
+    node
+}
+
+/// Returns a TheTreeNode for the quest.
+pub fn gen_quest_tree_node(quest: &Quest) -> TheTreeNode {
+    let mut node: TheTreeNode = TheTreeNode::new(TheId::named_with_id(&quest.name, quest.id));
+    node.set_root_mode(false);
+
+    let mut item = TheTreeItem::new(TheId::named_with_reference("Quest Item", quest.id));
+    item.set_text("Name".into());
+
+    let mut edit = TheTextLineEdit::new(TheId::named_with_id("Quest Item Name Edit", quest.id));
+    edit.set_text(quest.name.clone());
+    item.add_widget_column(200, Box::new(edit));
+
+    node.add_widget(Box::new(item));
+
+    let mut item = TheTreeItem::new(TheId::named_with_reference(
+        "Quest Item Stages Edit",
+        quest.id,
+    ));
+    item.set_background_color(TheColor::from(ActionRole::Dock.to_color()));
+    item.set_text("Stages".into());
+    node.add_widget(Box::new(item));
+
+    node
+}
+
 /// Rerender the current region.
 pub fn update_region(ctx: &mut TheContext) {
     ctx.ui.send(TheEvent::Custom(
@@ -450,6 +548,58 @@ pub fn set_project_context(
                 .unwrap()
                 .set_dock("Tiles".into(), ui, ctx, project, server_ctx);
         }
+        ProjectContext::Library(id) => {
+            if let Some(library) = project.libraries.get(&id) {
+                ui.set_widget_value(
+                    "Project Context",
+                    ctx,
+                    TheValue::Text(format!("Library: {}", library.name)),
+                );
+            }
+            DOCKMANAGER
+                .write()
+                .unwrap()
+                .set_dock("Code".into(), ui, ctx, project, server_ctx);
+        }
+        ProjectContext::ScriptTest(id) => {
+            if let Some(test) = project.tests.get(&id) {
+                ui.set_widget_value(
+                    "Project Context",
+                    ctx,
+                    TheValue::Text(format!("Test: {}", test.name)),
+                );
+            }
+            DOCKMANAGER
+                .write()
+                .unwrap()
+                .set_dock("Tests".into(), ui, ctx, project, server_ctx);
+        }
+        ProjectContext::Dialogue(id) => {
+            if let Some(dialogue) = project.dialogues.get(&id) {
+                ui.set_widget_value(
+                    "Project Context",
+                    ctx,
+                    TheValue::Text(format!("Dialogue: {}", dialogue.name)),
+                );
+            }
+            DOCKMANAGER
+                .write()
+                .unwrap()
+                .set_dock("Dialogue".into(), ui, ctx, project, server_ctx);
+        }
+        ProjectContext::Quest(id) => {
+            if let Some(quest) = project.quests.get(&id) {
+                ui.set_widget_value(
+                    "Project Context",
+                    ctx,
+                    TheValue::Text(format!("Quest: {}", quest.name)),
+                );
+            }
+            DOCKMANAGER
+                .write()
+                .unwrap()
+                .set_dock("Quests".into(), ui, ctx, project, server_ctx);
+        }
         ProjectContext::ProjectSettings => {
             ui.set_widget_value(
                 "Project Context",
@@ -461,6 +611,36 @@ pub fn set_project_context(
                 .unwrap()
                 .set_dock("Data".into(), ui, ctx, project, server_ctx);
         }
+        ProjectContext::Profiler => {
+            ui.set_widget_value("Project Context", ctx, TheValue::Text("Profiler".into()));
+            *SIDEBARMODE.write().unwrap() = SidebarMode::Debug;
+            DOCKMANAGER
+                .write()
+                .unwrap()
+                .set_dock("Profiler".into(), ui, ctx, project, server_ctx);
+        }
+        ProjectContext::Inspector => {
+            ui.set_widget_value("Project Context", ctx, TheValue::Text("Inspector".into()));
+            *SIDEBARMODE.write().unwrap() = SidebarMode::Debug;
+            DOCKMANAGER
+                .write()
+                .unwrap()
+                .set_dock("Inspector".into(), ui, ctx, project, server_ctx);
+        }
+        ProjectContext::Localization => {
+            ui.set_widget_value(
+                "Project Context",
+                ctx,
+                TheValue::Text("Localization".into()),
+            );
+            DOCKMANAGER.write().unwrap().set_dock(
+                "Localization".into(),
+                ui,
+                ctx,
+                project,
+                server_ctx,
+            );
+        }
         _ => {}
     }
 
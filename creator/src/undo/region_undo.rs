@@ -43,6 +43,12 @@ impl RegionUndoAtom {
             _ => None, // Return None for unsupported variants
         }
     }
+    pub fn to_project_atom(self, pc: ProjectContext) -> Option<ProjectUndoAtom> {
+        match self {
+            RegionUndoAtom::MapEdit(map1, map2) => Some(ProjectUndoAtom::MapEdit(pc, map1, map2)),
+            _ => None,
+        }
+    }
 
     pub fn undo(&self, region: &mut Region, ui: &mut TheUI, ctx: &mut TheContext) {
         match self {
@@ -312,13 +312,150 @@ pub fn draw_shader_into(module: &Module, buffer: &mut TheRGBABuffer) {
 pub fn scenemanager_render_map(project: &Project, server_ctx: &ServerContext) {
     if server_ctx.editor_view_mode == EditorViewMode::D2 {
         // In 2D we render the current map, profile or base
-        if let Some(map) = project.get_map(server_ctx) {
+        if server_ctx.get_map_context() == MapContext::Region
+            && server_ctx.editing_surface.is_none()
+        {
+            if let Some(region) = project.get_region_ctx(server_ctx) {
+                let map = region_render_map(region, &project.assets);
+                SCENEMANAGER.write().unwrap().set_map(map);
+            }
+        } else if let Some(map) = project.get_map(server_ctx) {
             SCENEMANAGER.write().unwrap().set_map(map.clone());
         }
     } else {
-        // In 3D we always only render the base map
+        // In 3D we always only render the base map and its props
         if let Some(region) = project.get_region_ctx(server_ctx) {
-            SCENEMANAGER.write().unwrap().set_map(region.map.clone());
+            let map = region_render_map(region, &project.assets);
+            SCENEMANAGER.write().unwrap().set_map(map);
+        }
+    }
+}
+
+/// Decodes a PNG file into an RGBA buffer.
+pub fn decode_png(path: &std::path::Path) -> Option<TheRGBABuffer> {
+    decode_png_data(&std::fs::read(path).ok()?)
+}
+
+/// Decodes PNG data into an RGBA buffer.
+pub fn decode_png_data(data: &[u8]) -> Option<TheRGBABuffer> {
+    let mut decoder = png::Decoder::new(std::io::Cursor::new(data));
+    decoder.set_transformations(png::Transformations::normalize_to_color8());
+    let mut reader = decoder.read_info().ok()?;
+    let mut buf = vec![0; reader.output_buffer_size()?];
+    let info = reader.next_frame(&mut buf).ok()?;
+    let bytes = &buf[..info.buffer_size()];
+
+    let rgba: Vec<u8> = match info.color_type {
+        png::ColorType::Rgba => bytes.to_vec(),
+        png::ColorType::Rgb => bytes
+            .chunks_exact(3)
+            .flat_map(|c| [c[0], c[1], c[2], 255])
+            .collect(),
+        png::ColorType::GrayscaleAlpha => bytes
+            .chunks_exact(2)
+            .flat_map(|c| [c[0], c[0], c[0], c[1]])
+            .collect(),
+        png::ColorType::Grayscale => bytes.iter().flat_map(|g| [*g, *g, *g, 255]).collect(),
+        png::ColorType::Indexed => return None,
+    };
+
+    Some(TheRGBABuffer::from(rgba, info.width, info.height))
+}
+
+/// Writes an RGBA buffer as a PNG file.
+pub fn write_png(buffer: &TheRGBABuffer, path: &std::path::Path) -> bool {
+    encode_png(buffer).is_some_and(|data| std::fs::write(path, data).is_ok())
+}
+
+/// Encodes the buffer as an RGBA PNG image.
+pub fn encode_png(buffer: &TheRGBABuffer) -> Option<Vec<u8>> {
+    let mut data = vec![];
+    let dim = buffer.dim();
+    let mut encoder = png::Encoder::new(&mut data, dim.width as u32, dim.height as u32);
+    encoder.set_color(png::ColorType::Rgba);
+    encoder.set_depth(png::BitDepth::Eight);
+    encoder
+        .write_header()
+        .and_then(|mut writer| writer.write_image_data(buffer.pixels()))
+        .ok()?;
+    Some(data)
+}
+
+/// The embedded instance source of the given file, used for new character
+/// and item instances.
+pub fn embedded_instance_source(file: &str) -> String {
+    crate::Embedded::get(file)
+        .and_then(|bytes| {
+            std::str::from_utf8(bytes.data.as_ref())
+                .ok()
+                .map(String::from)
+        })
+        .unwrap_or(DEFAULT_INSTANCE_SOURCE.to_string())
+}
+
+/// Draws the walkable cells of the navigation grid and the walls between cells
+/// on top of the 2D map.
+pub fn draw_navgrid(buffer: &mut TheRGBABuffer, map: &Map, nav: &NavGrid) {
+    let width = buffer.dim().width;
+    let height = buffer.dim().height;
+    let screen_size = Vec2::new(width as f32, height as f32);
+    let cell_pixels = nav.cell_size * map.grid_size;
+    if cell_pixels < 3.0 || width <= 0 || height <= 0 {
+        return;
+    }
+
+    let to_grid = |local: Vec2<f32>| {
+        (local - screen_size / 2.0 - Vec2::new(map.offset.x, -map.offset.y)) / map.grid_size
+    };
+    let (x0, y0) = nav.cell_at(to_grid(Vec2::zero()));
+    let (x1, y1) = nav.cell_at(to_grid(screen_size));
+
+    let walkable = [40, 200, 110];
+    let wall = [230, 70, 60];
+    let pixels = buffer.pixels_mut();
+    let mut blend = |x: i32, y: i32, color: [u8; 3], alpha: u32| {
+        if x < 0 || y < 0 || x >= width || y >= height {
+            return;
+        }
+        let i = (y * width + x) as usize * 4;
+        for c in 0..3 {
+            let dst = pixels[i + c] as u32;
+            pixels[i + c] = ((dst * (255 - alpha) + color[c] as u32 * alpha) / 255) as u8;
+        }
+    };
+
+    for y in y0.min(y1).max(0)..=y0.max(y1).min(nav.height - 1) {
+        for x in x0.min(x1).max(0)..=x0.max(x1).min(nav.width - 1) {
+            let Some(cell) = nav.cell(x, y) else {
+                continue;
+            };
+            let corner = nav.origin + Vec2::new(x as f32, y as f32) * nav.cell_size;
+            let p = ServerContext::map_grid_to_local(screen_size, corner, map);
+            let (px, py, size) = (
+                p.x.round() as i32,
+                p.y.round() as i32,
+                cell_pixels.round() as i32,
+            );
+
+            if cell.walkable {
+                // Leave a one pixel gap so that the cells read as a grid
+                for sy in py + 1..py + size {
+                    for sx in px + 1..px + size {
+                        blend(sx, sy, walkable, 70);
+                    }
+                }
+            }
+            // Walls towards the right and bottom neighbour
+            if cell.walls & 1 != 0 {
+                for sy in py..py + size {
+                    blend(px + size, sy, wall, 220);
+                }
+            }
+            if cell.walls & (1 << 2) != 0 {
+                for sx in px..px + size {
+                    blend(sx, py + size, wall, 220);
+                }
+            }
         }
     }
 }
@@ -1,3 +1,5 @@
+use crate::audio::AudioData;
+use crate::prop::PropMesh;
 use theframework::prelude::*;
 
 #[derive(Serialize, Deserialize, PartialEq, Clone, Debug)]
@@ -35,6 +37,8 @@ pub enum AssetBuffer {
     Empty,
     Image(TheRGBABuffer),
     Font(Vec<u8>),
+    Mesh(PropMesh),
+    Audio(AudioData),
 }
 
 impl AssetBuffer {
@@ -43,6 +47,8 @@ impl AssetBuffer {
             AssetBuffer::Empty => "Empty",
             AssetBuffer::Image(_) => "Image",
             AssetBuffer::Font(_) => "Font",
+            AssetBuffer::Mesh(_) => "Mesh",
+            AssetBuffer::Audio(_) => "Audio",
         }
     }
 }
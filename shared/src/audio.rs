@@ -0,0 +1,1192 @@
+use rusterix::{Map, PlayerCamera, Value, ValueContainer};
+use std::sync::{Arc, Mutex};
+use theframework::prelude::*;
+
+/// The name of the built-in script library with the audio helpers.
+pub const AUDIO_LIBRARY: &str = "audio";
+
+/// The attribute holding the audio commands queued by a script. Every line is
+/// `<sequence>|<command>|<arguments>`, the host plays the commands it has not
+/// seen yet.
+pub const AUDIO_QUEUE: &str = "audio_queue";
+
+/// The attribute of an entity or item with its looping positional sound, as
+/// `<name>|<volume>|<radius>`.
+pub const AUDIO_EMITTER: &str = "audio_emitter";
+
+/// Vertex and sector property with the name of a looping positional sound.
+pub const SOUND_EMITTER: &str = "sound_emitter";
+/// The volume of a vertex or sector emitter.
+pub const SOUND_VOLUME: &str = "sound_volume";
+/// The audible radius of a vertex or sector emitter.
+pub const SOUND_RADIUS: &str = "sound_radius";
+
+/// Sector property with the name of the music played while the player is
+/// inside the sector.
+pub const SECTOR_MUSIC: &str = "music";
+/// Sector property with the name of the ambient loop played while the player
+/// is inside the sector.
+pub const SECTOR_AMBIENT: &str = "ambient_sound";
+
+/// The source of the built-in `audio` library.
+pub const AUDIO_SOURCE: &str = r##"# Built-in audio helpers. Sounds are audio assets referenced by name, the
+# channels are "master", "music", "ambient" and "sfx". Sounds played "here" and
+# emitters are positional and follow the character or item.
+
+def _audio(command):
+    seq = (get_attr("audio_seq") or 0) + 1
+    set_attr("audio_seq", seq)
+    queue = [c for c in (get_attr("audio_queue") or "").split("\n") if c][-15:]
+    queue.append(str(seq) + "|" + command)
+    set_attr("audio_queue", "\n".join(queue))
+
+def play_sound(name, volume=1.0):
+    _audio("sound|" + name + "|" + str(float(volume)))
+
+def play_sound_here(name, volume=1.0, radius=10.0):
+    _audio("sound_here|" + name + "|" + str(float(volume)) + "|" + str(float(radius)))
+
+def set_emitter(name, volume=1.0, radius=10.0):
+    set_attr("audio_emitter", name + "|" + str(float(volume)) + "|" + str(float(radius)))
+
+def clear_emitter():
+    set_attr("audio_emitter", "")
+
+def play_music(name, fade=1.0):
+    _audio("music|" + name + "|" + str(float(fade)))
+
+def stop_music(fade=1.0):
+    _audio("stop_music|" + str(float(fade)))
+
+def play_ambient(name, fade=1.0):
+    _audio("ambient|" + name + "|" + str(float(fade)))
+
+def stop_ambient(fade=1.0):
+    _audio("stop_ambient|" + str(float(fade)))
+
+def set_volume(channel, volume):
+    _audio("volume|" + channel + "|" + str(float(volume)))
+"##;
+
+/// The crossfade of sector music and ambient loops in seconds.
+const SECTOR_FADE: f32 = 1.5;
+
+/// The fade in and out of looping emitter sounds in seconds.
+const EMITTER_FADE: f32 = 0.5;
+
+/// The audible radius of emitters which do not set one.
+const DEFAULT_EMITTER_RADIUS: f32 = 10.0;
+
+/// Walls higher than this block and muffle sound.
+const OCCLUDING_WALL_HEIGHT: f32 = 1.0;
+
+/// The attenuation of every wall between the listener and an emitter.
+const WALL_DAMPING: f32 = 0.6;
+
+/// The sample rate of the null backend.
+const NULL_SAMPLE_RATE: u32 = 44100;
+
+#[derive(Serialize, Deserialize, PartialEq, Clone, Copy, Debug)]
+pub enum AudioFormat {
+    Wav,
+    Ogg,
+}
+
+/// An imported sound file, stored encoded in the project.
+#[derive(Serialize, Deserialize, PartialEq, Clone, Debug)]
+pub struct AudioData {
+    pub format: AudioFormat,
+    pub data: Vec<u8>,
+}
+
+impl AudioData {
+    /// Reads and validates a WAV or OGG Vorbis file.
+    pub fn load(path: &std::path::Path) -> Result<Self, String> {
+        let data = std::fs::read(path).map_err(|e| e.to_string())?;
+        let format = if data.starts_with(b"RIFF") {
+            AudioFormat::Wav
+        } else if data.starts_with(b"OggS") {
+            AudioFormat::Ogg
+        } else {
+            return Err("Unsupported audio format, use WAV or OGG Vorbis.".into());
+        };
+        let audio = AudioData { format, data };
+        audio.decode()?;
+        Ok(audio)
+    }
+
+    /// Decodes the file into interleaved samples.
+    pub fn decode(&self) -> Result<AudioClip, String> {
+        match self.format {
+            AudioFormat::Wav => decode_wav(&self.data),
+            AudioFormat::Ogg => decode_ogg(&self.data),
+        }
+    }
+}
+
+/// Decoded, interleaved samples in the range -1..1.
+#[derive(Clone, Debug, Default)]
+pub struct AudioClip {
+    pub sample_rate: u32,
+    pub channels: u16,
+    pub samples: Vec<f32>,
+}
+
+impl AudioClip {
+    /// The length in seconds.
+    pub fn duration(&self) -> f32 {
+        let frames = self.samples.len() / self.channels.max(1) as usize;
+        frames as f32 / self.sample_rate.max(1) as f32
+    }
+
+    /// Converts the clip into interleaved stereo at the given sample rate.
+    pub fn to_stereo(&self, sample_rate: u32) -> Vec<f32> {
+        let channels = self.channels.max(1) as usize;
+        let frames = self.samples.len() / channels;
+        if frames == 0 || self.sample_rate == 0 {
+            return vec![];
+        }
+        let frame = |i: usize| {
+            let base = i.min(frames - 1) * channels;
+            let left = self.samples[base];
+            let right = if channels > 1 {
+                self.samples[base + 1]
+            } else {
+                left
+            };
+            (left, right)
+        };
+
+        let ratio = self.sample_rate as f64 / sample_rate as f64;
+        let out_frames = (frames as f64 / ratio).floor() as usize;
+        let mut out = Vec::with_capacity(out_frames * 2);
+        for i in 0..out_frames {
+            let src = i as f64 * ratio;
+            let i0 = src.floor() as usize;
+            let t = (src - i0 as f64) as f32;
+            let (l0, r0) = frame(i0);
+            let (l1, r1) = frame(i0 + 1);
+            out.push(l0 + (l1 - l0) * t);
+            out.push(r0 + (r1 - r0) * t);
+        }
+        out
+    }
+}
+
+fn decode_wav(data: &[u8]) -> Result<AudioClip, String> {
+    if data.len() < 12 || &data[0..4] != b"RIFF" || &data[8..12] != b"WAVE" {
+        return Err("Not a WAV file.".into());
+    }
+    let u16_at = |o: usize| u16::from_le_bytes([data[o], data[o + 1]]);
+    let u32_at = |o: usize| u32::from_le_bytes([data[o], data[o + 1], data[o + 2], data[o + 3]]);
+
+    let mut format: Option<(u16, u16, u32, u16)> = None;
+    let mut offset = 12;
+    while offset + 8 <= data.len() {
+        let id = &data[offset..offset + 4];
+        let size = u32_at(offset + 4) as usize;
+        let body = offset + 8;
+        let end = (body + size).min(data.len());
+
+        if id == b"fmt " && size >= 16 && end >= body + 16 {
+            let mut tag = u16_at(body);
+            // WAVE_FORMAT_EXTENSIBLE keeps the real format in the sub format
+            if tag == 0xFFFE && size >= 26 {
+                tag = u16_at(body + 24);
+            }
+            format = Some((tag, u16_at(body + 2), u32_at(body + 4), u16_at(body + 14)));
+        } else if id == b"data" {
+            let (tag, channels, sample_rate, bits) = format.ok_or("WAV data before format.")?;
+            let bytes = &data[body..end];
+            let samples: Vec<f32> = match (tag, bits) {
+                (1, 8) => bytes.iter().map(|b| (*b as f32 - 128.0) / 128.0).collect(),
+                (1, 16) => bytes
+                    .chunks_exact(2)
+                    .map(|b| i16::from_le_bytes([b[0], b[1]]) as f32 / 32768.0)
+                    .collect(),
+                (1, 24) => bytes
+                    .chunks_exact(3)
+                    .map(|b| (i32::from_le_bytes([0, b[0], b[1], b[2]]) >> 8) as f32 / 8388608.0)
+                    .collect(),
+                (1, 32) => bytes
+                    .chunks_exact(4)
+                    .map(|b| i32::from_le_bytes([b[0], b[1], b[2], b[3]]) as f32 / 2147483648.0)
+                    .collect(),
+                (3, 32) => bytes
+                    .chunks_exact(4)
+                    .map(|b| f32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+                    .collect(),
+                (3, 64) => bytes
+                    .chunks_exact(8)
+                    .map(|b| {
+                        f64::from_le_bytes([b[0], b[1], b[2], b[3], b[4], b[5], b[6], b[7]]) as f32
+                    })
+                    .collect(),
+                _ => {
+                    return Err(format!(
+                        "Unsupported WAV encoding (format {}, {} bits).",
+                        tag, bits
+                    ));
+                }
+            };
+            if channels == 0 || sample_rate == 0 {
+                return Err("Invalid WAV format.".into());
+            }
+            return Ok(AudioClip {
+                sample_rate,
+                channels,
+                samples,
+            });
+        }
+
+        // Chunks are padded to an even size
+        offset = body + size + (size & 1);
+    }
+    Err("The WAV file has no data.".into())
+}
+
+fn decode_ogg(data: &[u8]) -> Result<AudioClip, String> {
+    let mut reader = lewton::inside_ogg::OggStreamReader::new(std::io::Cursor::new(data))
+        .map_err(|e| e.to_string())?;
+    let sample_rate = reader.ident_hdr.audio_sample_rate;
+    let channels = reader.ident_hdr.audio_channels as u16;
+    let mut samples = vec![];
+    while let Some(packet) = reader.read_dec_packet_itl().map_err(|e| e.to_string())? {
+        samples.extend(packet.iter().map(|s| *s as f32 / 32768.0));
+    }
+    Ok(AudioClip {
+        sample_rate,
+        channels,
+        samples,
+    })
+}
+
+/// The mixer channels, every channel has its own volume.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum AudioChannel {
+    Master,
+    Music,
+    Ambient,
+    Sfx,
+}
+
+impl AudioChannel {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.trim().to_lowercase().as_str() {
+            "master" => Some(AudioChannel::Master),
+            "music" => Some(AudioChannel::Music),
+            "ambient" => Some(AudioChannel::Ambient),
+            "sfx" | "sound" => Some(AudioChannel::Sfx),
+            _ => None,
+        }
+    }
+}
+
+/// A command for the mixer, queued by scripts or triggered by sectors.
+#[derive(Clone, Debug, PartialEq)]
+pub enum AudioCommand {
+    PlaySound(String, f32),
+    /// A sound at the position of the entity or item which queued it, with
+    /// volume and audible radius.
+    PlaySoundHere(String, f32, f32),
+    /// Crossfades to the given music over the given seconds.
+    PlayMusic(String, f32),
+    StopMusic(f32),
+    PlayAmbient(String, f32),
+    StopAmbient(f32),
+    SetVolume(AudioChannel, f32),
+    StopAll,
+}
+
+impl AudioCommand {
+    /// Parses a `<command>|<arguments>` line of the audio queue.
+    pub fn parse(line: &str) -> Option<Self> {
+        let parts: Vec<&str> = line.split('|').collect();
+        let float = |i: usize, default: f32| {
+            parts
+                .get(i)
+                .and_then(|v| v.trim().parse().ok())
+                .unwrap_or(default)
+        };
+        let name = || parts.get(1).map(|n| n.trim().to_string());
+        match *parts.first()? {
+            "sound" => Some(AudioCommand::PlaySound(name()?, float(2, 1.0))),
+            "sound_here" => Some(AudioCommand::PlaySoundHere(
+                name()?,
+                float(2, 1.0),
+                float(3, DEFAULT_EMITTER_RADIUS),
+            )),
+            "music" => Some(AudioCommand::PlayMusic(name()?, float(2, 1.0))),
+            "stop_music" => Some(AudioCommand::StopMusic(float(1, 1.0))),
+            "ambient" => Some(AudioCommand::PlayAmbient(name()?, float(2, 1.0))),
+            "stop_ambient" => Some(AudioCommand::StopAmbient(float(1, 1.0))),
+            "volume" => Some(AudioCommand::SetVolume(
+                AudioChannel::from_name(parts.get(1)?)?,
+                float(2, 1.0),
+            )),
+            "stop" => Some(AudioCommand::StopAll),
+            _ => None,
+        }
+    }
+}
+
+/// The source of a positional sound, identified by its map and the id of the
+/// instance or geometry it is attached to.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum AudioEmitter {
+    Entity(Uuid, u32),
+    Item(Uuid, u32),
+    Vertex(Uuid, u32),
+    Sector(Uuid, u32),
+}
+
+impl AudioEmitter {
+    pub fn map_id(&self) -> Uuid {
+        match self {
+            AudioEmitter::Entity(id, _)
+            | AudioEmitter::Item(id, _)
+            | AudioEmitter::Vertex(id, _)
+            | AudioEmitter::Sector(id, _) => *id,
+        }
+    }
+}
+
+/// How a positional sound is heard by the listener.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct AudioSpatial {
+    /// The distance attenuation.
+    pub gain: f32,
+    /// The stereo balance from -1 (left) to 1 (right).
+    pub pan: f32,
+    /// How muffled the sound is by walls, from 0 to 1.
+    pub occlusion: f32,
+}
+
+impl Default for AudioSpatial {
+    fn default() -> Self {
+        Self {
+            gain: 1.0,
+            pan: 0.0,
+            occlusion: 0.0,
+        }
+    }
+}
+
+/// A playing sound.
+struct Voice {
+    name: String,
+    channel: AudioChannel,
+    samples: Arc<Vec<f32>>,
+    /// The index of the next sample.
+    position: usize,
+    volume: f32,
+    looping: bool,
+    /// The fade gain, moved towards `target` by `fade_step` every frame.
+    gain: f32,
+    target: f32,
+    fade_step: f32,
+    finished: bool,
+
+    /// The source of a positional sound and its audible radius.
+    emitter: Option<(AudioEmitter, f32)>,
+    /// The spatial state the voice moves to during the next mix and the state
+    /// reached by the last one.
+    spatial: AudioSpatial,
+    mixed_spatial: Option<AudioSpatial>,
+    /// The state of the low pass filter muffling occluded sounds.
+    filter: [f32; 2],
+}
+
+/// Mixes the playing sounds into interleaved stereo.
+pub struct AudioMixer {
+    pub sample_rate: u32,
+    clips: FxHashMap<String, Arc<Vec<f32>>>,
+    voices: Vec<Voice>,
+    volumes: FxHashMap<AudioChannel, f32>,
+}
+
+impl AudioMixer {
+    pub fn new(sample_rate: u32) -> Self {
+        Self {
+            sample_rate,
+            clips: FxHashMap::default(),
+            voices: vec![],
+            volumes: FxHashMap::default(),
+        }
+    }
+
+    /// Decodes the audio assets of the project, the asset names are the sound
+    /// names used by scripts.
+    pub fn set_clips<'a>(&mut self, assets: impl Iterator<Item = (&'a str, &'a AudioData)>) {
+        self.voices.clear();
+        self.clips.clear();
+        for (name, data) in assets {
+            match data.decode() {
+                Ok(clip) => {
+                    self.clips
+                        .insert(name.to_string(), Arc::new(clip.to_stereo(self.sample_rate)));
+                }
+                Err(err) => eprintln!("Unable to decode audio asset {}: {}", name, err),
+            }
+        }
+    }
+
+    pub fn volume(&self, channel: AudioChannel) -> f32 {
+        self.volumes.get(&channel).copied().unwrap_or(1.0)
+    }
+
+    /// Returns true if a sound with the given name is playing.
+    pub fn is_playing(&self, name: &str) -> bool {
+        self.voices.iter().any(|v| v.name == name && v.target > 0.0)
+    }
+
+    pub fn apply(&mut self, command: AudioCommand) {
+        match command {
+            AudioCommand::PlaySound(name, volume)
+            | AudioCommand::PlaySoundHere(name, volume, _) => {
+                self.play(&name, AudioChannel::Sfx, volume, false, 0.0);
+            }
+            AudioCommand::PlayMusic(name, fade) => self.crossfade(AudioChannel::Music, &name, fade),
+            AudioCommand::StopMusic(fade) => self.fade_out(AudioChannel::Music, fade),
+            AudioCommand::PlayAmbient(name, fade) => {
+                self.crossfade(AudioChannel::Ambient, &name, fade)
+            }
+            AudioCommand::StopAmbient(fade) => self.fade_out(AudioChannel::Ambient, fade),
+            AudioCommand::SetVolume(channel, volume) => {
+                self.volumes.insert(channel, volume.clamp(0.0, 1.0));
+            }
+            AudioCommand::StopAll => self.voices.clear(),
+        }
+    }
+
+    /// Plays a sound attached to an emitter. Looping emitter sounds fade in.
+    pub fn play_emitter(
+        &mut self,
+        emitter: AudioEmitter,
+        radius: f32,
+        name: &str,
+        volume: f32,
+        looping: bool,
+    ) {
+        let channel = match emitter {
+            AudioEmitter::Vertex(..) | AudioEmitter::Sector(..) => AudioChannel::Ambient,
+            _ => AudioChannel::Sfx,
+        };
+        let fade = if looping { EMITTER_FADE } else { 0.0 };
+        if self.play(name, channel, volume, looping, fade) {
+            if let Some(voice) = self.voices.last_mut() {
+                voice.emitter = Some((emitter, radius.max(0.01)));
+            }
+        }
+    }
+
+    /// Fades out the looping sound of an emitter, one shot sounds play to
+    /// their end.
+    pub fn stop_emitter(&mut self, emitter: &AudioEmitter) {
+        let fade_step = self.fade_step(EMITTER_FADE);
+        for voice in &mut self.voices {
+            if voice.looping && voice.emitter.is_some_and(|(e, _)| e == *emitter) {
+                voice.target = 0.0;
+                voice.fade_step = fade_step;
+            }
+        }
+    }
+
+    /// The emitters with playing sounds.
+    pub fn active_emitters(&self) -> Vec<AudioEmitter> {
+        let mut emitters: Vec<AudioEmitter> = vec![];
+        for (emitter, _) in self.voices.iter().filter_map(|v| v.emitter) {
+            if !emitters.contains(&emitter) {
+                emitters.push(emitter);
+            }
+        }
+        emitters
+    }
+
+    /// Updates the positional sounds, `spatial` is called with the emitter
+    /// and the radius of every voice. Voices for which it returns None keep
+    /// their state.
+    pub fn update_emitters(
+        &mut self,
+        spatial: impl Fn(&AudioEmitter, f32) -> Option<AudioSpatial>,
+    ) {
+        for voice in &mut self.voices {
+            if let Some((emitter, radius)) = voice.emitter {
+                if let Some(s) = spatial(&emitter, radius) {
+                    voice.spatial = s;
+                }
+            }
+        }
+    }
+
+    /// Mixes the playing sounds into the interleaved stereo buffer `out`,
+    /// which is cleared first.
+    pub fn mix(&mut self, out: &mut [f32]) {
+        out.fill(0.0);
+        let master = self.volume(AudioChannel::Master);
+        let frames = (out.len() / 2).max(1) as f32;
+        for voice in &mut self.voices {
+            let volume =
+                voice.volume * master * self.volumes.get(&voice.channel).copied().unwrap_or(1.0);
+            // Interpolate the spatial state over the buffer to avoid clicks
+            let from = voice.mixed_spatial.unwrap_or(voice.spatial);
+            let to = voice.spatial;
+            voice.mixed_spatial = Some(to);
+            for (index, frame) in out.chunks_exact_mut(2).enumerate() {
+                if voice.position + 1 >= voice.samples.len() {
+                    if voice.looping && voice.samples.len() >= 2 {
+                        voice.position = 0;
+                    } else {
+                        voice.finished = true;
+                        break;
+                    }
+                }
+                if voice.gain != voice.target {
+                    voice.gain = if voice.gain < voice.target {
+                        (voice.gain + voice.fade_step).min(voice.target)
+                    } else {
+                        (voice.gain - voice.fade_step).max(voice.target)
+                    };
+                }
+                let t = (index + 1) as f32 / frames;
+                let gain = voice.gain * volume * (from.gain + (to.gain - from.gain) * t);
+                let pan = from.pan + (to.pan - from.pan) * t;
+                let occlusion = from.occlusion + (to.occlusion - from.occlusion) * t;
+
+                let mut left = voice.samples[voice.position];
+                let mut right = voice.samples[voice.position + 1];
+                if voice.emitter.is_some() {
+                    let alpha = 1.0 - occlusion.clamp(0.0, 1.0) * 0.9;
+                    voice.filter[0] += (left - voice.filter[0]) * alpha;
+                    voice.filter[1] += (right - voice.filter[1]) * alpha;
+                    left = voice.filter[0];
+                    right = voice.filter[1];
+                }
+                frame[0] += left * gain * (1.0 - pan).min(1.0);
+                frame[1] += right * gain * (1.0 + pan).min(1.0);
+                voice.position += 2;
+            }
+            if voice.target <= 0.0 && voice.gain <= 0.0 {
+                voice.finished = true;
+            }
+        }
+        self.voices.retain(|v| !v.finished);
+        for sample in out.iter_mut() {
+            *sample = sample.clamp(-1.0, 1.0);
+        }
+    }
+
+    /// Starts a voice, returns false if there is no sound with the name.
+    fn play(
+        &mut self,
+        name: &str,
+        channel: AudioChannel,
+        volume: f32,
+        looping: bool,
+        fade: f32,
+    ) -> bool {
+        let Some(samples) = self.clips.get(name) else {
+            return false;
+        };
+        let fade_step = self.fade_step(fade);
+        self.voices.push(Voice {
+            name: name.to_string(),
+            channel,
+            samples: samples.clone(),
+            position: 0,
+            volume: volume.max(0.0),
+            looping,
+            gain: if fade > 0.0 { 0.0 } else { 1.0 },
+            target: 1.0,
+            fade_step,
+            finished: false,
+            emitter: None,
+            spatial: AudioSpatial::default(),
+            mixed_spatial: None,
+            filter: [0.0; 2],
+        });
+        true
+    }
+
+    fn crossfade(&mut self, channel: AudioChannel, name: &str, fade: f32) {
+        if self.voices.iter().any(|v| {
+            v.channel == channel && v.emitter.is_none() && v.name == name && v.target > 0.0
+        }) {
+            return;
+        }
+        self.fade_out(channel, fade);
+        self.play(name, channel, 1.0, true, fade);
+    }
+
+    fn fade_out(&mut self, channel: AudioChannel, fade: f32) {
+        let fade_step = self.fade_step(fade);
+        for voice in &mut self.voices {
+            if voice.channel == channel && voice.emitter.is_none() {
+                voice.target = 0.0;
+                voice.fade_step = fade_step;
+            }
+        }
+    }
+
+    /// The gain change per frame of a fade over the given seconds.
+    fn fade_step(&self, fade: f32) -> f32 {
+        if fade > 0.0 {
+            1.0 / (fade * self.sample_rate as f32)
+        } else {
+            1.0
+        }
+    }
+}
+
+/// Plays the output of the mixer.
+pub trait AudioBackend {
+    /// Called once per frame. Device backends pull from the mixer on their own
+    /// thread and do nothing here.
+    fn advance(&mut self, mixer: &Mutex<AudioMixer>, dt: f32);
+}
+
+/// A backend without a device which mixes into a scratch buffer and discards
+/// the result, so that sounds progress and finish when running headless.
+#[derive(Default)]
+pub struct NullAudioBackend {
+    scratch: Vec<f32>,
+}
+
+impl AudioBackend for NullAudioBackend {
+    fn advance(&mut self, mixer: &Mutex<AudioMixer>, dt: f32) {
+        let mut mixer = mixer.lock().unwrap();
+        let frames = (dt.max(0.0) * mixer.sample_rate as f32) as usize;
+        self.scratch.resize(frames * 2, 0.0);
+        mixer.mix(&mut self.scratch);
+    }
+}
+
+#[cfg(all(feature = "audio", not(target_arch = "wasm32")))]
+mod device {
+    use super::*;
+    use cpal::traits::{DeviceTrait, HostTrait, StreamTrait};
+
+    /// Plays the mixer on the default output device of the system.
+    pub struct DeviceAudioBackend {
+        _stream: cpal::Stream,
+    }
+
+    impl AudioBackend for DeviceAudioBackend {
+        fn advance(&mut self, _mixer: &Mutex<AudioMixer>, _dt: f32) {}
+    }
+
+    /// Opens the default output device, returns None if there is none.
+    pub fn open() -> Option<(Arc<Mutex<AudioMixer>>, DeviceAudioBackend)> {
+        let device = cpal::default_host().default_output_device()?;
+        let supported = device.default_output_config().ok()?;
+        let sample_format = supported.sample_format();
+        let config: cpal::StreamConfig = supported.into();
+        let mixer = Arc::new(Mutex::new(AudioMixer::new(config.sample_rate.0)));
+
+        let stream = match sample_format {
+            cpal::SampleFormat::F32 => build::<f32>(&device, &config, mixer.clone()),
+            cpal::SampleFormat::I16 => build::<i16>(&device, &config, mixer.clone()),
+            cpal::SampleFormat::U16 => build::<u16>(&device, &config, mixer.clone()),
+            _ => None,
+        }?;
+        stream.play().ok()?;
+        Some((mixer, DeviceAudioBackend { _stream: stream }))
+    }
+
+    fn build<T>(
+        device: &cpal::Device,
+        config: &cpal::StreamConfig,
+        mixer: Arc<Mutex<AudioMixer>>,
+    ) -> Option<cpal::Stream>
+    where
+        T: cpal::SizedSample + cpal::FromSample<f32>,
+    {
+        let channels = config.channels.max(1) as usize;
+        let mut scratch = vec![];
+        device
+            .build_output_stream(
+                config,
+                move |data: &mut [T], _: &cpal::OutputCallbackInfo| {
+                    let frames = data.len() / channels;
+                    scratch.resize(frames * 2, 0.0);
+                    if let Ok(mut mixer) = mixer.lock() {
+                        mixer.mix(&mut scratch);
+                    }
+                    for (frame, out) in data.chunks_mut(channels).enumerate() {
+                        for (channel, sample) in out.iter_mut().enumerate() {
+                            *sample = T::from_sample(scratch[frame * 2 + channel.min(1)]);
+                        }
+                    }
+                },
+                |err| eprintln!("Audio stream error: {}", err),
+                None,
+            )
+            .ok()
+    }
+}
+
+/// Where positional sounds are heard from: the player entity.
+#[derive(Clone, Copy, Debug)]
+pub struct AudioListener {
+    pub position: Vec2<f32>,
+    /// The facing of the player in the 3D cameras. Without it sounds are
+    /// panned by their horizontal screen position.
+    pub forward: Option<Vec2<f32>>,
+}
+
+impl AudioListener {
+    /// The listener of the player entity of the map, if the player is in it.
+    pub fn from_map(map: &Map, camera: &PlayerCamera) -> Option<Self> {
+        let player = map
+            .entities
+            .iter()
+            .find(|e| matches!(e.attributes.get("player"), Some(Value::Bool(true))))?;
+        let forward = if matches!(camera, PlayerCamera::D2) || player.orientation == Vec2::zero() {
+            None
+        } else {
+            Some(player.orientation.normalized())
+        };
+        Some(Self {
+            position: player.get_pos_xz(),
+            forward,
+        })
+    }
+
+    /// How a sound at the given position with the given radius is heard,
+    /// `walls` is the number of walls in between.
+    pub fn spatial(&self, position: Vec2<f32>, radius: f32, walls: usize) -> AudioSpatial {
+        let delta = position - self.position;
+        let distance = delta.magnitude();
+        let t = (distance / radius.max(0.01)).clamp(0.0, 1.0);
+        let gain = (1.0 - t) * (1.0 - t) * WALL_DAMPING.powi(walls as i32);
+
+        let side = match self.forward {
+            Some(forward) => delta.dot(Vec2::new(-forward.y, forward.x)),
+            None => delta.x,
+        };
+        // Sounds at the listener are centered, sounds to the side are never
+        // completely one sided
+        let pan = if distance > 0.001 {
+            (side / distance) * distance.min(1.0) * 0.8
+        } else {
+            0.0
+        };
+
+        AudioSpatial {
+            gain,
+            pan: pan.clamp(-1.0, 1.0),
+            occlusion: (walls as f32 * 0.5).min(1.0),
+        }
+    }
+}
+
+/// The sound, volume and radius of a looping emitter.
+#[derive(Clone, Debug, PartialEq)]
+struct EmitterSound {
+    name: String,
+    volume: f32,
+    radius: f32,
+}
+
+impl EmitterSound {
+    /// Parses the `<name>|<volume>|<radius>` attribute of entities and items.
+    fn parse(value: &str) -> Option<Self> {
+        let mut parts = value.split('|');
+        let name = parts.next()?.trim();
+        if name.is_empty() {
+            return None;
+        }
+        let mut float = |default: f32| {
+            parts
+                .next()
+                .and_then(|v| v.trim().parse().ok())
+                .unwrap_or(default)
+        };
+        Some(Self {
+            name: name.to_string(),
+            volume: float(1.0),
+            radius: float(DEFAULT_EMITTER_RADIUS),
+        })
+    }
+
+    /// Reads the emitter properties of vertices and sectors.
+    fn from_properties(properties: &ValueContainer) -> Option<Self> {
+        let name = properties.get_str_default(SOUND_EMITTER, String::new());
+        if name.is_empty() {
+            return None;
+        }
+        Some(Self {
+            name,
+            volume: properties.get_float_default(SOUND_VOLUME, 1.0),
+            radius: properties.get_float_default(SOUND_RADIUS, DEFAULT_EMITTER_RADIUS),
+        })
+    }
+}
+
+/// The audio of a running game: the mixer, its backend and the state needed
+/// to turn script queues, emitters and sector properties into mixer commands.
+pub struct AudioOutput {
+    pub mixer: Arc<Mutex<AudioMixer>>,
+    backend: Box<dyn AudioBackend>,
+
+    /// The last command sequence played for every entity and item.
+    sequences: FxHashMap<AudioEmitter, i64>,
+    /// The looping emitters which are playing.
+    emitters: FxHashMap<AudioEmitter, EmitterSound>,
+    /// The music and ambient loop of the sector the player is in.
+    sector_music: String,
+    sector_ambient: String,
+}
+
+impl Default for AudioOutput {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl AudioOutput {
+    /// Opens the default output device, falls back to the null backend.
+    pub fn new() -> Self {
+        #[cfg(all(feature = "audio", not(target_arch = "wasm32")))]
+        if let Some((mixer, backend)) = device::open() {
+            return Self::with_backend(mixer, Box::new(backend));
+        }
+        Self::null()
+    }
+
+    /// An output without a device, for headless runs.
+    pub fn null() -> Self {
+        Self::with_backend(
+            Arc::new(Mutex::new(AudioMixer::new(NULL_SAMPLE_RATE))),
+            Box::new(NullAudioBackend::default()),
+        )
+    }
+
+    pub fn with_backend(mixer: Arc<Mutex<AudioMixer>>, backend: Box<dyn AudioBackend>) -> Self {
+        Self {
+            mixer,
+            backend,
+            sequences: FxHashMap::default(),
+            emitters: FxHashMap::default(),
+            sector_music: String::new(),
+            sector_ambient: String::new(),
+        }
+    }
+
+    /// Loads the audio assets of the project and resets the playback, called
+    /// when the game starts.
+    pub fn load(&mut self, project: &crate::project::Project) {
+        self.sequences.clear();
+        self.emitters.clear();
+        self.sector_music.clear();
+        self.sector_ambient.clear();
+        self.mixer
+            .lock()
+            .unwrap()
+            .set_clips(
+                project
+                    .assets
+                    .values()
+                    .filter_map(|asset| match &asset.buffer {
+                        crate::asset::AssetBuffer::Audio(data) => Some((asset.name.as_str(), data)),
+                        _ => None,
+                    }),
+            );
+    }
+
+    /// Stops all sounds.
+    pub fn stop(&mut self) {
+        self.emitters.clear();
+        self.mixer.lock().unwrap().apply(AudioCommand::StopAll);
+    }
+
+    /// Plays the new commands queued by the entities and items of the map and
+    /// places the positional sounds relative to the player. Called for every
+    /// map after its entities and items were updated. Emitters only sound in
+    /// the map the player is in, which also drives the sector music and
+    /// ambient loops.
+    pub fn update(&mut self, map: &Map, player_map: bool, camera: &PlayerCamera) {
+        let listener = if player_map {
+            AudioListener::from_map(map, camera)
+        } else {
+            None
+        };
+
+        let mut commands = vec![];
+        let queues = map
+            .entities
+            .iter()
+            .map(|e| (AudioEmitter::Entity(map.id, e.id), &e.attributes))
+            .chain(
+                map.items
+                    .iter()
+                    .map(|i| (AudioEmitter::Item(map.id, i.id), &i.attributes)),
+            );
+        for (emitter, attributes) in queues {
+            let queue = attributes.get_str_default(AUDIO_QUEUE, String::new());
+            if queue.is_empty() {
+                continue;
+            }
+            let last = self.sequences.entry(emitter).or_insert(0);
+            for line in queue.lines() {
+                let Some((seq, command)) = line.split_once('|') else {
+                    continue;
+                };
+                let Ok(seq) = seq.trim().parse::<i64>() else {
+                    continue;
+                };
+                if seq <= *last {
+                    continue;
+                }
+                *last = seq;
+                if let Some(command) = AudioCommand::parse(command) {
+                    commands.push((emitter, command));
+                }
+            }
+        }
+
+        let desired = if listener.is_some() {
+            emitter_sounds(map)
+        } else {
+            FxHashMap::default()
+        };
+
+        let mut mixer = self.mixer.lock().unwrap();
+        for (emitter, command) in commands {
+            match command {
+                AudioCommand::PlaySoundHere(name, volume, radius) => {
+                    mixer.play_emitter(emitter, radius, &name, volume, false)
+                }
+                command => mixer.apply(command),
+            }
+        }
+
+        // Start, change and stop the looping emitters of the map
+        self.emitters.retain(|emitter, sound| {
+            if emitter.map_id() != map.id || desired.get(emitter) == Some(sound) {
+                return true;
+            }
+            mixer.stop_emitter(emitter);
+            false
+        });
+        for (emitter, sound) in desired {
+            if !self.emitters.contains_key(&emitter) {
+                mixer.play_emitter(emitter, sound.radius, &sound.name, sound.volume, true);
+                self.emitters.insert(emitter, sound);
+            }
+        }
+
+        // Place the sounds of the map, they are silent if the player is elsewhere
+        let mut placed: FxHashMap<AudioEmitter, (Vec2<f32>, usize)> = FxHashMap::default();
+        if let Some(listener) = &listener {
+            for emitter in mixer.active_emitters() {
+                if emitter.map_id() != map.id {
+                    continue;
+                }
+                if let Some(position) = emitter_position(map, &emitter, listener.position) {
+                    let walls = occluding_walls(map, listener.position, position);
+                    placed.insert(emitter, (position, walls));
+                }
+            }
+        }
+        mixer.update_emitters(|emitter, radius| {
+            if emitter.map_id() != map.id {
+                return None;
+            }
+            match (&listener, placed.get(emitter)) {
+                (Some(listener), Some((position, walls))) => {
+                    Some(listener.spatial(*position, radius, *walls))
+                }
+                // The emitter was removed, keep its last placement
+                (Some(_), None) => None,
+                (None, _) => Some(AudioSpatial {
+                    gain: 0.0,
+                    ..Default::default()
+                }),
+            }
+        });
+        drop(mixer);
+
+        if let Some(listener) = &listener {
+            let commands = self.sector_audio(map, listener.position);
+            let mut mixer = self.mixer.lock().unwrap();
+            for command in commands {
+                mixer.apply(command);
+            }
+        }
+    }
+
+    /// Advances the backend, called once per frame.
+    pub fn advance(&mut self, dt: f32) {
+        self.backend.advance(&self.mixer, dt);
+    }
+
+    /// The commands for the music and ambient loop of the sector the player
+    /// entered. Music keeps playing in sectors without music, ambient loops
+    /// stop.
+    fn sector_audio(&mut self, map: &Map, position: Vec2<f32>) -> Vec<AudioCommand> {
+        let sector = map.sectors.iter().find(|sector| {
+            let has_audio = [SECTOR_MUSIC, SECTOR_AMBIENT].iter().any(|key| {
+                !sector
+                    .properties
+                    .get_str_default(key, String::new())
+                    .is_empty()
+            });
+            has_audio && sector_polygon(map, sector).is_some_and(|p| point_in_polygon(position, &p))
+        });
+        let property = |key: &str| {
+            sector
+                .map(|s| s.properties.get_str_default(key, String::new()))
+                .unwrap_or_default()
+        };
+
+        let mut commands = vec![];
+        let music = property(SECTOR_MUSIC);
+        if !music.is_empty() && music != self.sector_music {
+            commands.push(AudioCommand::PlayMusic(music.clone(), SECTOR_FADE));
+            self.sector_music = music;
+        }
+        let ambient = property(SECTOR_AMBIENT);
+        if ambient != self.sector_ambient {
+            if ambient.is_empty() {
+                commands.push(AudioCommand::StopAmbient(SECTOR_FADE));
+            } else {
+                commands.push(AudioCommand::PlayAmbient(ambient.clone(), SECTOR_FADE));
+            }
+            self.sector_ambient = ambient;
+        }
+        commands
+    }
+}
+
+/// The looping emitters of the entities, items, vertices and sectors of the map.
+fn emitter_sounds(map: &Map) -> FxHashMap<AudioEmitter, EmitterSound> {
+    let mut sounds = FxHashMap::default();
+    for entity in &map.entities {
+        let value = entity
+            .attributes
+            .get_str_default(AUDIO_EMITTER, String::new());
+        if let Some(sound) = EmitterSound::parse(&value) {
+            sounds.insert(AudioEmitter::Entity(map.id, entity.id), sound);
+        }
+    }
+    for item in &map.items {
+        let value = item
+            .attributes
+            .get_str_default(AUDIO_EMITTER, String::new());
+        if let Some(sound) = EmitterSound::parse(&value) {
+            sounds.insert(AudioEmitter::Item(map.id, item.id), sound);
+        }
+    }
+    for vertex in &map.vertices {
+        if let Some(sound) = EmitterSound::from_properties(&vertex.properties) {
+            sounds.insert(AudioEmitter::Vertex(map.id, vertex.id), sound);
+        }
+    }
+    for sector in &map.sectors {
+        if let Some(sound) = EmitterSound::from_properties(&sector.properties) {
+            sounds.insert(AudioEmitter::Sector(map.id, sector.id), sound);
+        }
+    }
+    sounds
+}
+
+/// The position a sound of the emitter is heard from. Sectors sound from their
+/// closest point, or from the listener when it is inside.
+fn emitter_position(map: &Map, emitter: &AudioEmitter, listener: Vec2<f32>) -> Option<Vec2<f32>> {
+    match emitter {
+        AudioEmitter::Entity(_, id) => map
+            .entities
+            .iter()
+            .find(|e| e.id == *id)
+            .map(|e| e.get_pos_xz()),
+        AudioEmitter::Item(_, id) => map
+            .items
+            .iter()
+            .find(|i| i.id == *id)
+            .map(|i| i.get_pos_xz()),
+        AudioEmitter::Vertex(_, id) => map.find_vertex(*id).map(|v| Vec2::new(v.x, v.y)),
+        AudioEmitter::Sector(_, id) => {
+            let sector = map.sectors.iter().find(|s| s.id == *id)?;
+            let polygon = sector_polygon(map, sector)?;
+            if point_in_polygon(listener, &polygon) {
+                return Some(listener);
+            }
+            let mut closest: Option<(f32, Vec2<f32>)> = None;
+            for (i, a) in polygon.iter().enumerate() {
+                let p = closest_on_segment(listener, *a, polygon[(i + 1) % polygon.len()]);
+                let distance = p.distance_squared(listener);
+                if closest.is_none_or(|(d, _)| distance < d) {
+                    closest = Some((distance, p));
+                }
+            }
+            closest.map(|(_, p)| p)
+        }
+    }
+}
+
+/// The number of walls between the listener and a sound.
+fn occluding_walls(map: &Map, from: Vec2<f32>, to: Vec2<f32>) -> usize {
+    if from.distance_squared(to) < 0.0001 {
+        return 0;
+    }
+    map.linedefs
+        .iter()
+        .filter(|linedef| {
+            linedef.properties.get_float_default("wall_height", 0.0) > OCCLUDING_WALL_HEIGHT
+        })
+        .filter(|linedef| {
+            let (Some(a), Some(b)) = (
+                map.find_vertex(linedef.start_vertex),
+                map.find_vertex(linedef.end_vertex),
+            ) else {
+                return false;
+            };
+            segments_intersect(from, to, Vec2::new(a.x, a.y), Vec2::new(b.x, b.y))
+        })
+        .count()
+}
+
+pub(crate) fn sector_polygon(map: &Map, sector: &rusterix::Sector) -> Option<Vec<Vec2<f32>>> {
+    sector
+        .vertices_world(map)
+        .map(|points| points.iter().map(|p| Vec2::new(p.x, p.z)).collect())
+}
+
+fn closest_on_segment(p: Vec2<f32>, a: Vec2<f32>, b: Vec2<f32>) -> Vec2<f32> {
+    let ab = b - a;
+    let length = ab.magnitude_squared();
+    if length <= f32::EPSILON {
+        return a;
+    }
+    a + ab * ((p - a).dot(ab) / length).clamp(0.0, 1.0)
+}
+
+fn segments_intersect(p1: Vec2<f32>, p2: Vec2<f32>, q1: Vec2<f32>, q2: Vec2<f32>) -> bool {
+    let cross = |a: Vec2<f32>, b: Vec2<f32>| a.x * b.y - a.y * b.x;
+    let r = p2 - p1;
+    let s = q2 - q1;
+    let denominator = cross(r, s);
+    if denominator.abs() <= f32::EPSILON {
+        return false;
+    }
+    let t = cross(q1 - p1, s) / denominator;
+    let u = cross(q1 - p1, r) / denominator;
+    (0.0..=1.0).contains(&t) && (0.0..=1.0).contains(&u)
+}
+
+pub(crate) fn point_in_polygon(p: Vec2<f32>, points: &[Vec2<f32>]) -> bool {
+    if points.len() < 3 {
+        return false;
+    }
+    let mut inside = false;
+    let mut j = points.len() - 1;
+    for (i, a) in points.iter().enumerate() {
+        let b = points[j];
+        if (a.y > p.y) != (b.y > p.y) && p.x < (b.x - a.x) * (p.y - a.y) / (b.y - a.y) + a.x {
+            inside = !inside;
+        }
+        j = i;
+    }
+    inside
+}
@@ -0,0 +1,1235 @@
+use crate::dialogue::value_text;
+use crate::prelude::*;
+use crate::quest::add_to_value;
+use crate::rusterix_utils::{InstanceEdit, apply_instance_edit};
+use rusterix::{Entity, Rusterix, Value};
+use theframework::prelude::*;
+
+/// The name of the built-in script library with the combat helpers.
+pub const COMBAT_LIBRARY: &str = "combat";
+/// The attribute with the combat commands of a character, one
+/// `<seq>|<command>|<args>` per line.
+pub const COMBAT_QUEUE: &str = "combat_queue";
+/// Set by the host to the latest resolved hits, one
+/// `<seq>|<target>|<source>|<amount>|<text>` per line. The character deals the
+/// damage and logs the text of the lines it has not seen yet on its next poll.
+pub const COMBAT_RESULTS: &str = "combat_results";
+/// The sequence number of the last result line handled by the library.
+pub const COMBAT_RESULTS_SEEN: &str = "combat_results_seen";
+/// The number of result lines the host keeps per character.
+const COMBAT_RESULTS_KEPT: usize = 16;
+/// The index of the combat rolls in the seeds derived by `DeterministicSettings`.
+const COMBAT_SEED_INDEX: u64 = u64::MAX / 2;
+/// Set by the host on the characters which are in a fight.
+pub const COMBAT_ACTIVE: &str = "combat_active";
+/// Set by the host on the character whose turn it is in turn based fights.
+pub const COMBAT_TURN: &str = "combat_turn";
+/// Set by the library once `on_combat_turn()` was called for the current turn.
+pub const COMBAT_TURN_SEEN: &str = "combat_turn_seen";
+/// The comma separated names of the active status effects of a character.
+pub const COMBAT_EFFECTS: &str = "status_effects";
+
+/// The source of the built-in `combat` library. Characters queue their attacks,
+/// the host resolves them with the formulas of the game config and the
+/// characters deal the resulting damage.
+pub const COMBAT_SOURCE: &str = r##"# Built-in combat helpers. The formulas, damage types and status effects are
+# defined in [combat] of the game config, the host resolves the attacks.
+# Call combat_event(event, value) at the top of event() of every fighter, it
+# deals the damage, writes the combat log and calls the hooks: abilities are
+# module functions ability_<name>(target_id), on_combat_turn() is called when
+# a turn based fight passes the turn to the character.
+
+def _combat(command):
+    seq = (get_attr("combat_seq") or 0) + 1
+    set_attr("combat_seq", seq)
+    queue = [c for c in str(get_attr("combat_queue") or "").split("\n") if c][-15:]
+    queue.append(str(seq) + "|" + command)
+    set_attr("combat_queue", "\n".join(queue))
+    _combat_poll()
+
+def _combat_poll():
+    if not get_attr("combat_polling"):
+        set_attr("combat_polling", True)
+        notify_in(1, "combat_poll")
+
+def attack(target_id, slot=""):
+    _combat("attack|" + str(target_id) + "|" + str(slot))
+
+def combat_hit(target_id, amount, damage_type="", effect=""):
+    _combat("hit|" + str(target_id) + "|" + str(float(amount)) + "|" + str(damage_type) + "|" + str(effect))
+
+def apply_effect(target_id, effect):
+    _combat("effect|" + str(target_id) + "|" + str(effect))
+
+def end_turn():
+    _combat("end_turn")
+
+def use_ability(name, target_id):
+    ability = globals().get("ability_" + str(name))
+    if ability is None:
+        return False
+    ability(target_id)
+    return True
+
+def in_combat():
+    return get_attr("combat_active") == True
+
+def my_turn():
+    return get_attr("combat_turn") == True
+
+def has_effect(name, entity_id=None):
+    effects = get_attr("status_effects") if entity_id is None else get_attr_of(entity_id, "status_effects")
+    return str(name) in str(effects or "").split(",")
+
+def combat_event(event, value):
+    seen = get_attr("combat_results_seen") or 0
+    last = seen
+    for line in str(get_attr("combat_results") or "").split("\n"):
+        parts = line.split("|", 4)
+        if len(parts) < 5 or int(parts[0]) <= seen:
+            continue
+        last = max(last, int(parts[0]))
+        target = int(parts[1])
+        amount = int(float(parts[3]))
+        if amount > 0:
+            deal_damage(target, {"from": int(parts[2]), "amount": amount})
+        if parts[4]:
+            message(id(), parts[4], "combat")
+            if target != id():
+                message(target, parts[4], "combat")
+    if last != seen:
+        set_attr("combat_results_seen", last)
+    if get_attr("combat_turn") == True and not get_attr("combat_turn_seen"):
+        set_attr("combat_turn_seen", True)
+        hook = globals().get("on_combat_turn")
+        if hook is not None:
+            hook()
+    if event == "combat_poll":
+        set_attr("combat_polling", False)
+    if get_attr("combat_active") == True:
+        _combat_poll()
+    return event == "combat_poll"
+"##;
+
+/// How fights are run.
+#[derive(Deserialize, Clone, Copy, Debug, PartialEq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum CombatMode {
+    /// The combat module is disabled.
+    #[default]
+    Off,
+    /// Characters attack whenever their cooldown allows.
+    Realtime,
+    /// The fighters act one after the other.
+    TurnBased,
+}
+
+/// A damage type of `[combat.damage_types]`.
+#[derive(Deserialize, Clone, Debug, Default)]
+#[serde(default)]
+struct DamageType {
+    /// The attribute with the resistance in percent, `<type>_resistance` if not
+    /// set. Negative values are weaknesses.
+    resistance: Option<String>,
+    /// The status effect a hit of this type applies.
+    effect: Option<String>,
+    /// The chance (0.0 - 1.0) of the effect.
+    chance: Option<f32>,
+}
+
+/// A status effect of `[combat.effects]`.
+#[derive(Deserialize, Clone, Debug)]
+#[serde(default)]
+struct StatusEffect {
+    /// The duration in game ticks.
+    duration: i64,
+    /// The game ticks between the periodic changes.
+    every: i64,
+    /// The periodic damage.
+    damage: f32,
+    /// The damage type of the periodic damage, the default type if empty.
+    damage_type: String,
+    /// The periodic changes of attributes.
+    attributes: IndexMap<String, f32>,
+    /// Added to attributes while the effect is active.
+    modifiers: IndexMap<String, f32>,
+    /// An attribute which is true while the effect is active.
+    flag: Option<String>,
+    /// Whether the character can't attack while the effect is active.
+    blocks_actions: bool,
+    /// The log texts when the effect starts, on every periodic damage and when
+    /// it ends.
+    text: String,
+    tick_text: String,
+    end_text: String,
+}
+
+impl Default for StatusEffect {
+    fn default() -> Self {
+        Self {
+            duration: 12,
+            every: 4,
+            damage: 0.0,
+            damage_type: String::new(),
+            attributes: IndexMap::default(),
+            modifiers: IndexMap::default(),
+            flag: None,
+            blocks_actions: false,
+            text: String::new(),
+            tick_text: "{target} suffers {amount} {type} damage.".into(),
+            end_text: String::new(),
+        }
+    }
+}
+
+/// The `[combat]` section of the game config.
+#[derive(Deserialize, Clone, Debug)]
+#[serde(default)]
+struct CombatConfig {
+    mode: CombatMode,
+    /// The formulas, see `Formula` for the syntax.
+    hit: String,
+    attack: String,
+    defense: String,
+    damage: String,
+    /// The game ticks between two real time attacks, weapons can set their own
+    /// `cooldown`.
+    cooldown: i64,
+    /// The game ticks of a turn before it passes to the next fighter.
+    turn_ticks: i64,
+    /// The game ticks without attacks after which a character leaves combat.
+    timeout: i64,
+    /// The damage type of weapons without a `damage_type`.
+    damage_type: String,
+    hit_text: String,
+    miss_text: String,
+    blocked_text: String,
+    turn_text: String,
+    damage_types: IndexMap<String, DamageType>,
+    effects: IndexMap<String, StatusEffect>,
+}
+
+impl Default for CombatConfig {
+    fn default() -> Self {
+        Self {
+            mode: CombatMode::Off,
+            hit: "1".into(),
+            attack: "A.STR + W.damage".into(),
+            defense: "D.armor".into(),
+            damage: "max(1, attack - defense)".into(),
+            cooldown: 4,
+            turn_ticks: 8,
+            timeout: 40,
+            damage_type: "physical".into(),
+            hit_text: "{attacker} hits {target} for {amount} {type} damage.".into(),
+            miss_text: "{attacker} misses {target}.".into(),
+            blocked_text: "{attacker} can't act.".into(),
+            turn_text: "It is not your turn.".into(),
+            damage_types: IndexMap::default(),
+            effects: IndexMap::default(),
+        }
+    }
+}
+
+/// The parsed formulas of the combat config.
+#[derive(Default)]
+struct CombatFormulas {
+    hit: Formula,
+    attack: Formula,
+    defense: Formula,
+    damage: Formula,
+}
+
+/// A formula of the combat config: numbers, `+ - * / %`, parentheses, the
+/// functions `min`, `max`, `floor`, `ceil`, `round`, `abs`, `clamp(x, lo, hi)`,
+/// `rand()` (0.0 - 1.0) and `roll(sides)` or `roll(count, sides)`, and
+/// variables. `A.<attr>` and `D.<attr>` are the attributes of the attacker and
+/// the defender plus the ones of their equipped items, `W.<attr>` the attributes
+/// of the weapon, `attack` and `defense` the results of the formulas.
+#[derive(Clone, Debug)]
+pub struct Formula(Expr);
+
+#[derive(Clone, Debug)]
+enum Expr {
+    Number(f32),
+    Var(String),
+    Neg(Box<Expr>),
+    Binary(char, Box<Expr>, Box<Expr>),
+    Call(String, Vec<Expr>),
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Token {
+    Number(f32),
+    Ident(String),
+    Op(char),
+}
+
+impl Default for Formula {
+    fn default() -> Self {
+        Self(Expr::Number(0.0))
+    }
+}
+
+impl Formula {
+    pub fn parse(source: &str) -> Result<Self, String> {
+        let tokens = tokenize(source)?;
+        let mut parser = FormulaParser { tokens, pos: 0 };
+        let expr = parser.expr()?;
+        match parser.tokens.get(parser.pos) {
+            Some(token) => Err(format!("Unexpected {:?} in \"{}\"", token, source)),
+            None => Ok(Self(expr)),
+        }
+    }
+
+    /// Evaluates the formula, unknown variables are 0.
+    pub fn eval(&self, vars: &dyn Fn(&str) -> f32, rng: &mut CombatRng) -> f32 {
+        let value = eval_expr(&self.0, vars, rng);
+        if value.is_finite() { value } else { 0.0 }
+    }
+}
+
+fn tokenize(source: &str) -> Result<Vec<Token>, String> {
+    let mut tokens = vec![];
+    let chars: Vec<char> = source.chars().collect();
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        if c.is_whitespace() {
+            i += 1;
+        } else if c.is_ascii_digit() || c == '.' {
+            let start = i;
+            while i < chars.len() && (chars[i].is_ascii_digit() || chars[i] == '.') {
+                i += 1;
+            }
+            let text: String = chars[start..i].iter().collect();
+            let number = text
+                .parse::<f32>()
+                .map_err(|_| format!("Invalid number \"{}\"", text))?;
+            tokens.push(Token::Number(number));
+        } else if c.is_alphabetic() || c == '_' {
+            let start = i;
+            while i < chars.len() && (chars[i].is_alphanumeric() || matches!(chars[i], '_' | '.')) {
+                i += 1;
+            }
+            tokens.push(Token::Ident(chars[start..i].iter().collect()));
+        } else if "+-*/%(),".contains(c) {
+            tokens.push(Token::Op(c));
+            i += 1;
+        } else {
+            return Err(format!("Unexpected '{}' in \"{}\"", c, source));
+        }
+    }
+    Ok(tokens)
+}
+
+struct FormulaParser {
+    tokens: Vec<Token>,
+    pos: usize,
+}
+
+impl FormulaParser {
+    fn peek_op(&self, ops: &str) -> Option<char> {
+        match self.tokens.get(self.pos) {
+            Some(Token::Op(op)) if ops.contains(*op) => Some(*op),
+            _ => None,
+        }
+    }
+
+    fn expect(&mut self, op: char) -> Result<(), String> {
+        if self.peek_op(&op.to_string()).is_some() {
+            self.pos += 1;
+            Ok(())
+        } else {
+            Err(format!("Expected '{}'", op))
+        }
+    }
+
+    fn expr(&mut self) -> Result<Expr, String> {
+        let mut left = self.term()?;
+        while let Some(op) = self.peek_op("+-") {
+            self.pos += 1;
+            left = Expr::Binary(op, Box::new(left), Box::new(self.term()?));
+        }
+        Ok(left)
+    }
+
+    fn term(&mut self) -> Result<Expr, String> {
+        let mut left = self.unary()?;
+        while let Some(op) = self.peek_op("*/%") {
+            self.pos += 1;
+            left = Expr::Binary(op, Box::new(left), Box::new(self.unary()?));
+        }
+        Ok(left)
+    }
+
+    fn unary(&mut self) -> Result<Expr, String> {
+        if self.peek_op("-").is_some() {
+            self.pos += 1;
+            return Ok(Expr::Neg(Box::new(self.unary()?)));
+        }
+        self.primary()
+    }
+
+    fn primary(&mut self) -> Result<Expr, String> {
+        let token = self.tokens.get(self.pos).cloned();
+        self.pos += 1;
+        match token {
+            Some(Token::Number(n)) => Ok(Expr::Number(n)),
+            Some(Token::Op('(')) => {
+                let expr = self.expr()?;
+                self.expect(')')?;
+                Ok(expr)
+            }
+            Some(Token::Ident(name)) => {
+                if self.peek_op("(").is_none() {
+                    return Ok(Expr::Var(name));
+                }
+                self.pos += 1;
+                let mut args = vec![];
+                if self.peek_op(")").is_none() {
+                    args.push(self.expr()?);
+                    while self.peek_op(",").is_some() {
+                        self.pos += 1;
+                        args.push(self.expr()?);
+                    }
+                }
+                self.expect(')')?;
+                let arity_ok = match name.as_str() {
+                    "min" | "max" => !args.is_empty(),
+                    "floor" | "ceil" | "round" | "abs" => args.len() == 1,
+                    "clamp" => args.len() == 3,
+                    "rand" => args.is_empty(),
+                    "roll" => matches!(args.len(), 1 | 2),
+                    _ => return Err(format!("Unknown function \"{}\"", name)),
+                };
+                if !arity_ok {
+                    return Err(format!("Wrong number of arguments for \"{}\"", name));
+                }
+                Ok(Expr::Call(name, args))
+            }
+            Some(token) => Err(format!("Unexpected {:?}", token)),
+            None => Err("Unexpected end of formula".into()),
+        }
+    }
+}
+
+fn eval_expr(expr: &Expr, vars: &dyn Fn(&str) -> f32, rng: &mut CombatRng) -> f32 {
+    match expr {
+        Expr::Number(n) => *n,
+        Expr::Var(name) => vars(name),
+        Expr::Neg(e) => -eval_expr(e, vars, rng),
+        Expr::Binary(op, a, b) => {
+            let a = eval_expr(a, vars, rng);
+            let b = eval_expr(b, vars, rng);
+            match op {
+                '+' => a + b,
+                '-' => a - b,
+                '*' => a * b,
+                '/' if b != 0.0 => a / b,
+                '%' if b != 0.0 => a % b,
+                _ => 0.0,
+            }
+        }
+        Expr::Call(name, args) => {
+            let args: Vec<f32> = args.iter().map(|a| eval_expr(a, vars, rng)).collect();
+            match name.as_str() {
+                "min" => args.iter().copied().fold(f32::INFINITY, f32::min),
+                "max" => args.iter().copied().fold(f32::NEG_INFINITY, f32::max),
+                "floor" => args[0].floor(),
+                "ceil" => args[0].ceil(),
+                "round" => args[0].round(),
+                "abs" => args[0].abs(),
+                "clamp" => args[0].max(args[1]).min(args[2]),
+                "rand" => rng.next_f32(),
+                "roll" => {
+                    let (count, sides) = match args.as_slice() {
+                        [sides] => (1, *sides),
+                        [count, sides, ..] => (count.max(0.0) as i32, *sides),
+                        _ => (0, 0.0),
+                    };
+                    (0..count)
+                        .map(|_| (rng.next_f32() * sides.max(1.0)).floor() + 1.0)
+                        .sum()
+                }
+                _ => 0.0,
+            }
+        }
+    }
+}
+
+/// The random numbers of the combat rolls, seeded from the settings of
+/// deterministic runs so that replays roll the same numbers.
+#[derive(Default, Clone, Debug)]
+pub struct CombatRng(u64);
+
+impl CombatRng {
+    pub fn new(seed: u64) -> Self {
+        Self(seed)
+    }
+
+    /// The next number in 0.0..1.0.
+    pub fn next_f32(&mut self) -> f32 {
+        if self.0 == 0 {
+            self.0 = 0x9e37_79b9_7f4a_7c15;
+        }
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        (self.0 >> 40) as f32 / (1u64 << 24) as f32
+    }
+}
+
+/// A status effect on a character.
+#[derive(Clone, Debug)]
+struct ActiveEffect {
+    region: Uuid,
+    entity: u32,
+    source: u32,
+    name: String,
+    /// The tick the effect ends.
+    until: i64,
+    /// The tick of the next periodic change.
+    next: i64,
+}
+
+/// A turn based fight.
+#[derive(Clone, Debug)]
+struct Fight {
+    region: Uuid,
+    order: Vec<u32>,
+    turn: usize,
+    turn_start: i64,
+    /// The turns in a row which passed without an action.
+    idle: usize,
+    changed: bool,
+}
+
+/// The attribute changes of an update, later changes see the earlier ones.
+#[derive(Default)]
+struct Changes {
+    values: IndexMap<(u32, String), Value>,
+    /// The new `<target>|<source>|<amount>|<text>` result lines per character.
+    results: IndexMap<u32, Vec<String>>,
+}
+
+impl Changes {
+    fn get<'a>(&'a self, region: &'a Region, id: u32, key: &str) -> Option<&'a Value> {
+        self.values
+            .get(&(id, key.to_string()))
+            .or_else(|| entity_of(region, id)?.attributes.get(key))
+    }
+
+    fn set(&mut self, id: u32, key: &str, value: Value) {
+        self.values.insert((id, key.to_string()), value);
+    }
+
+    fn add(&mut self, region: &Region, id: u32, key: &str, amount: f32) {
+        let value = add_to_value(self.get(region, id, key), amount);
+        self.set(id, key, value);
+    }
+
+    /// Adds a `<target>|<source>|<amount>|<text>` line to the results of the
+    /// character.
+    fn result(&mut self, id: u32, target: u32, source: u32, amount: f32, text: String) {
+        self.results
+            .entry(id)
+            .or_default()
+            .push(format!("{}|{}|{}|{}", target, source, amount, text));
+    }
+}
+
+/// Runs the fights. The host resolves the attacks queued by the `combat`
+/// library with the formulas of the game config, tracks the status effects in
+/// game ticks and hands the turns over in turn based fights. The damage itself
+/// is dealt by the scripts so that `took_damage` and the death handling of the
+/// `health` attribute apply.
+#[derive(Default)]
+pub struct CombatRunner {
+    config: CombatConfig,
+    formulas: CombatFormulas,
+    weapon_slots: Vec<String>,
+    health: String,
+    rng: CombatRng,
+    ticks: i64,
+    sequences: FxHashMap<(Uuid, u32), i64>,
+    /// The latest numbered result lines of the characters, the host is the
+    /// only writer of the results attribute.
+    results: FxHashMap<(Uuid, u32), (i64, Vec<String>)>,
+    /// The tick a character can attack again in real time fights.
+    ready: FxHashMap<(Uuid, u32), i64>,
+    /// The tick of the last combat action of the characters in a fight.
+    engaged: FxHashMap<(Uuid, u32), i64>,
+    effects: Vec<ActiveEffect>,
+    fights: Vec<Fight>,
+}
+
+impl CombatRunner {
+    /// Ends all fights and reads the combat config, called when the server
+    /// starts. Deterministic runs seed the combat rolls from their settings.
+    pub fn start(&mut self, config: &str, deterministic: Option<DeterministicSettings>) {
+        let table = config.parse::<toml::Table>().unwrap_or_default();
+        let game = table.get("game");
+        self.config = table
+            .get("combat")
+            .and_then(|c| match c.clone().try_into::<CombatConfig>() {
+                Ok(config) => Some(config),
+                Err(err) => {
+                    eprintln!("Invalid [combat]: {}", err);
+                    None
+                }
+            })
+            .unwrap_or_default();
+
+        let defaults = CombatConfig::default();
+        let parse = |source: &str, default: &str| {
+            Formula::parse(source).unwrap_or_else(|err| {
+                eprintln!("Invalid combat formula: {}", err);
+                Formula::parse(default).unwrap_or_default()
+            })
+        };
+        self.formulas = CombatFormulas {
+            hit: parse(&self.config.hit, &defaults.hit),
+            attack: parse(&self.config.attack, &defaults.attack),
+            defense: parse(&self.config.defense, &defaults.defense),
+            damage: parse(&self.config.damage, &defaults.damage),
+        };
+
+        self.weapon_slots = game
+            .and_then(|g| g.get("weapon_slots"))
+            .and_then(|s| s.as_array())
+            .map(|s| {
+                s.iter()
+                    .filter_map(|s| s.as_str())
+                    .map(str::to_string)
+                    .collect()
+            })
+            .unwrap_or_else(|| vec!["main_hand".into()]);
+        self.health = game
+            .and_then(|g| g.get("health"))
+            .and_then(|h| h.as_str())
+            .unwrap_or("HP")
+            .to_string();
+        self.rng = CombatRng::new(match deterministic {
+            Some(settings) => settings.seed_for(COMBAT_SEED_INDEX),
+            None => rand::random(),
+        });
+
+        self.ticks = 0;
+        self.sequences.clear();
+        self.results.clear();
+        self.ready.clear();
+        self.engaged.clear();
+        self.effects.clear();
+        self.fights.clear();
+    }
+
+    pub fn mode(&self) -> CombatMode {
+        self.config.mode
+    }
+
+    /// Advances the combat clock, called on every game tick.
+    pub fn tick(&mut self) {
+        self.ticks += 1;
+    }
+
+    /// Resolves the combat commands of the region and updates the status
+    /// effects and turns.
+    pub fn update(&mut self, rusterix: &mut Rusterix, region: &mut Region) {
+        if self.config.mode == CombatMode::Off {
+            return;
+        }
+
+        let mut changes = Changes::default();
+        let mut acted = vec![];
+        for (source, command, args) in self.commands(region) {
+            if self.command(region, source, &command, &args, &mut changes) {
+                acted.push(source);
+            }
+        }
+        self.update_effects(region, &mut changes);
+        self.update_fights(region, &acted, &mut changes);
+        self.update_engaged(region, &mut changes);
+
+        // The names of the active effects for scripts and widgets
+        for entity in &region.map.entities {
+            let names = self
+                .effects
+                .iter()
+                .filter(|e| e.region == region.id && e.entity == entity.id)
+                .map(|e| e.name.as_str())
+                .collect::<Vec<_>>()
+                .join(",");
+            let current = changes
+                .get(region, entity.id, COMBAT_EFFECTS)
+                .map(value_text)
+                .unwrap_or_default();
+            if current != names {
+                changes.set(entity.id, COMBAT_EFFECTS, Value::Str(names));
+            }
+        }
+
+        for (id, lines) in std::mem::take(&mut changes.results) {
+            let (seq, kept) = self.results.entry((region.id, id)).or_default();
+            for line in lines {
+                *seq += 1;
+                kept.push(format!("{}|{}", seq, line));
+            }
+            let skip = kept.len().saturating_sub(COMBAT_RESULTS_KEPT);
+            kept.drain(..skip);
+            changes.set(id, COMBAT_RESULTS, Value::Str(kept.join("\n")));
+        }
+
+        for ((id, key), value) in changes.values {
+            apply_instance_edit(
+                rusterix,
+                &mut region.map,
+                &InstanceEdit::EntityAttribute(id, key, value),
+            );
+        }
+    }
+
+    /// The new commands queued by the characters of the region, as source,
+    /// command and arguments.
+    fn commands(&mut self, region: &Region) -> Vec<(u32, String, Vec<String>)> {
+        let mut commands = vec![];
+        for entity in &region.map.entities {
+            let queue = entity
+                .attributes
+                .get_str_default(COMBAT_QUEUE, String::new());
+            if queue.is_empty() {
+                continue;
+            }
+            let last = self.sequences.entry((region.id, entity.id)).or_insert(0);
+            for line in queue.lines() {
+                let mut parts = line.splitn(3, '|');
+                let (Some(seq), Some(command)) = (parts.next(), parts.next()) else {
+                    continue;
+                };
+                let Ok(seq) = seq.trim().parse::<i64>() else {
+                    continue;
+                };
+                if seq <= *last {
+                    continue;
+                }
+                *last = seq;
+                let args = parts
+                    .next()
+                    .unwrap_or_default()
+                    .split('|')
+                    .map(|a| a.trim().to_string())
+                    .collect();
+                commands.push((entity.id, command.trim().to_string(), args));
+            }
+        }
+        commands
+    }
+
+    /// Runs a combat command, returns true if it used the turn of the source.
+    fn command(
+        &mut self,
+        region: &Region,
+        source: u32,
+        command: &str,
+        args: &[String],
+        changes: &mut Changes,
+    ) -> bool {
+        let Some(attacker) = entity_of(region, source) else {
+            return false;
+        };
+        if !self.alive(attacker) {
+            return false;
+        }
+        let turn_based = self.config.mode == CombatMode::TurnBased;
+        if command == "end_turn" {
+            return turn_based && self.turn_of(region.id, source) == Some(true);
+        }
+
+        let Some(target) = args
+            .first()
+            .and_then(|t| t.parse::<u32>().ok())
+            .and_then(|t| entity_of(region, t))
+        else {
+            return false;
+        };
+        let attacker_name = name_of(attacker);
+        let target_name = name_of(target);
+        let fill = |template: &str, amount: f32, damage_type: &str| {
+            fill_text(template, &attacker_name, &target_name, amount, damage_type)
+        };
+
+        if self.blocked(region.id, source) {
+            let text = fill(&self.config.blocked_text, 0.0, "");
+            changes.result(source, source, source, 0.0, text);
+            return false;
+        }
+        if turn_based && self.turn_of(region.id, source) == Some(false) {
+            let text = fill(&self.config.turn_text, 0.0, "");
+            changes.result(source, source, source, 0.0, text);
+            return false;
+        }
+
+        match command {
+            "attack" => {
+                let slot = args
+                    .get(1)
+                    .filter(|s| !s.is_empty())
+                    .or(self.weapon_slots.first())
+                    .cloned()
+                    .unwrap_or_else(|| "main_hand".into());
+                let weapon = attacker
+                    .equipped
+                    .iter()
+                    .find(|(s, _)| s.as_str() == slot)
+                    .map(|(_, item)| item);
+                let weapon_value =
+                    |key: &str| weapon.map(|w| number(w.attributes.get(key))).unwrap_or(0.0);
+
+                if self.config.mode == CombatMode::Realtime {
+                    let ready = self.ready.get(&(region.id, source)).copied().unwrap_or(0);
+                    if self.ticks < ready {
+                        return false;
+                    }
+                    let cooldown = weapon
+                        .and_then(|w| w.attributes.get("cooldown"))
+                        .map(|c| number(Some(c)) as i64)
+                        .unwrap_or(self.config.cooldown);
+                    self.ready
+                        .insert((region.id, source), self.ticks + cooldown.max(0));
+                }
+
+                let vars = |name: &str, attack: f32, defense: f32| match name {
+                    "attack" => attack,
+                    "defense" => defense,
+                    _ => match name.split_once('.') {
+                        Some(("A", key)) => stat(attacker, key),
+                        Some(("D", key)) => stat(target, key),
+                        Some(("W", key)) => weapon_value(key),
+                        _ => 0.0,
+                    },
+                };
+                let chance = self
+                    .formulas
+                    .hit
+                    .eval(&|n| vars(n, 0.0, 0.0), &mut self.rng);
+                if self.rng.next_f32() >= chance {
+                    let text = fill(&self.config.miss_text, 0.0, "");
+                    changes.result(source, target.id, source, 0.0, text);
+                } else {
+                    let attack = self
+                        .formulas
+                        .attack
+                        .eval(&|n| vars(n, 0.0, 0.0), &mut self.rng);
+                    let defense = self
+                        .formulas
+                        .defense
+                        .eval(&|n| vars(n, attack, 0.0), &mut self.rng);
+                    let damage = self
+                        .formulas
+                        .damage
+                        .eval(&|n| vars(n, attack, defense), &mut self.rng);
+                    let damage_type = weapon
+                        .and_then(|w| w.attributes.get_str("damage_type"))
+                        .map(str::to_string)
+                        .unwrap_or_else(|| self.config.damage_type.clone());
+                    let effect = weapon
+                        .and_then(|w| w.attributes.get_str("effect"))
+                        .map(|e| (e.to_string(), weapon_value("effect_chance")))
+                        .map(|(e, c)| (e, if c > 0.0 { c } else { 1.0 }));
+                    self.hit(
+                        region,
+                        attacker,
+                        target,
+                        damage,
+                        &damage_type,
+                        effect,
+                        changes,
+                    );
+                }
+            }
+            "hit" => {
+                let amount = args
+                    .get(1)
+                    .and_then(|a| a.parse::<f32>().ok())
+                    .unwrap_or(0.0);
+                let damage_type = args
+                    .get(2)
+                    .filter(|t| !t.is_empty())
+                    .cloned()
+                    .unwrap_or_else(|| self.config.damage_type.clone());
+                let effect = args
+                    .get(3)
+                    .filter(|e| !e.is_empty())
+                    .map(|e| (e.clone(), 1.0));
+                self.hit(
+                    region,
+                    attacker,
+                    target,
+                    amount,
+                    &damage_type,
+                    effect,
+                    changes,
+                );
+            }
+            "effect" => {
+                if let Some(name) = args.get(1) {
+                    self.apply_effect(region, source, target, name, changes);
+                }
+                self.engage(region.id, source, changes);
+                return false;
+            }
+            _ => return false,
+        }
+
+        self.engage(region.id, source, changes);
+        self.engage(region.id, target.id, changes);
+        if turn_based {
+            self.join(region.id, source, target.id);
+        }
+        true
+    }
+
+    /// Applies the resistance of the target to the damage and logs the hit.
+    #[allow(clippy::too_many_arguments)]
+    fn hit(
+        &mut self,
+        region: &Region,
+        attacker: &Entity,
+        target: &Entity,
+        damage: f32,
+        damage_type: &str,
+        effect: Option<(String, f32)>,
+        changes: &mut Changes,
+    ) {
+        let kind = self.config.damage_types.get(damage_type).cloned();
+        let amount = self.resisted(target, damage, damage_type);
+        let text = fill_text(
+            &self.config.hit_text,
+            &name_of(attacker),
+            &name_of(target),
+            amount,
+            damage_type,
+        );
+        changes.result(attacker.id, target.id, attacker.id, amount, text);
+
+        let effect = effect.or_else(|| {
+            let kind = kind?;
+            Some((kind.effect?, kind.chance.unwrap_or(1.0)))
+        });
+        if let Some((name, chance)) = effect
+            && self.rng.next_f32() < chance
+        {
+            self.apply_effect(region, attacker.id, target, &name, changes);
+        }
+    }
+
+    /// The damage after the resistance of the target, rounded.
+    fn resisted(&self, target: &Entity, damage: f32, damage_type: &str) -> f32 {
+        let attribute = self
+            .config
+            .damage_types
+            .get(damage_type)
+            .and_then(|t| t.resistance.clone())
+            .unwrap_or_else(|| format!("{}_resistance", damage_type));
+        let resistance = stat(target, &attribute).clamp(-100.0, 100.0);
+        (damage * (1.0 - resistance / 100.0)).round().max(0.0)
+    }
+
+    /// Starts or refreshes a status effect on the target.
+    fn apply_effect(
+        &mut self,
+        region: &Region,
+        source: u32,
+        target: &Entity,
+        name: &str,
+        changes: &mut Changes,
+    ) {
+        let Some(effect) = self.config.effects.get(name).cloned() else {
+            eprintln!("Unknown status effect \"{}\"", name);
+            return;
+        };
+        let until = self.ticks + effect.duration.max(1);
+        if let Some(active) = self
+            .effects
+            .iter_mut()
+            .find(|e| e.region == region.id && e.entity == target.id && e.name == name)
+        {
+            active.until = until;
+            active.source = source;
+            return;
+        }
+
+        for (attribute, amount) in &effect.modifiers {
+            changes.add(region, target.id, attribute, *amount);
+        }
+        if let Some(flag) = &effect.flag {
+            changes.set(target.id, flag, Value::Bool(true));
+        }
+        if !effect.text.is_empty() {
+            let text = fill_text(&effect.text, "", &name_of(target), 0.0, "");
+            changes.result(target.id, target.id, source, 0.0, text);
+        }
+        self.effects.push(ActiveEffect {
+            region: region.id,
+            entity: target.id,
+            source,
+            name: name.to_string(),
+            until,
+            next: self.ticks + effect.every.max(1),
+        });
+        self.engage(region.id, target.id, changes);
+    }
+
+    /// Applies the periodic changes of the status effects and ends the expired
+    /// ones.
+    fn update_effects(&mut self, region: &Region, changes: &mut Changes) {
+        let mut index = 0;
+        while index < self.effects.len() {
+            let active = self.effects[index].clone();
+            if active.region != region.id {
+                index += 1;
+                continue;
+            }
+            let entity = entity_of(region, active.entity);
+            let alive = entity.is_some_and(|e| self.alive(e));
+            let effect = self.config.effects.get(&active.name).cloned();
+
+            if let (Some(entity), Some(effect), true) = (entity, &effect, alive) {
+                let mut next = active.next;
+                while next <= self.ticks.min(active.until) {
+                    for (attribute, amount) in &effect.attributes {
+                        changes.add(region, entity.id, attribute, *amount);
+                    }
+                    if effect.damage > 0.0 {
+                        let damage_type = if effect.damage_type.is_empty() {
+                            &self.config.damage_type
+                        } else {
+                            &effect.damage_type
+                        };
+                        let amount = self.resisted(entity, effect.damage, damage_type);
+                        let text =
+                            fill_text(&effect.tick_text, "", &name_of(entity), amount, damage_type);
+                        changes.result(entity.id, entity.id, active.source, amount, text);
+                    }
+                    next += effect.every.max(1);
+                }
+                self.effects[index].next = next;
+                self.engage(region.id, entity.id, changes);
+            }
+
+            if alive && self.ticks < active.until && effect.is_some() {
+                index += 1;
+                continue;
+            }
+            self.effects.remove(index);
+            if let (Some(entity), Some(effect)) = (entity, effect) {
+                for (attribute, amount) in &effect.modifiers {
+                    changes.add(region, entity.id, attribute, -amount);
+                }
+                if let Some(flag) = &effect.flag {
+                    changes.set(entity.id, flag, Value::Bool(false));
+                }
+                if alive && !effect.end_text.is_empty() {
+                    let text = fill_text(&effect.end_text, "", &name_of(entity), 0.0, "");
+                    changes.result(entity.id, entity.id, entity.id, 0.0, text);
+                }
+            }
+        }
+    }
+
+    /// Passes the turns of the turn based fights and ends the finished ones.
+    fn update_fights(&mut self, region: &Region, acted: &[u32], changes: &mut Changes) {
+        let alive: FxHashSet<u32> = region
+            .map
+            .entities
+            .iter()
+            .filter(|e| self.alive(e))
+            .map(|e| e.id)
+            .collect();
+        let (ticks, turn_ticks) = (self.ticks, self.config.turn_ticks.max(1));
+
+        for fight in self.fights.iter_mut().filter(|f| f.region == region.id) {
+            let current = fight.order.get(fight.turn).copied();
+            let before = fight.order.clone();
+            fight.order.retain(|id| alive.contains(id));
+            for id in before.iter().filter(|id| !fight.order.contains(id)) {
+                changes.set(*id, COMBAT_TURN, Value::Bool(false));
+            }
+            if fight.order.is_empty() {
+                continue;
+            }
+            match current.and_then(|c| fight.order.iter().position(|id| *id == c)) {
+                Some(index) => {
+                    fight.turn = index;
+                    let timed_out = ticks - fight.turn_start >= turn_ticks;
+                    if acted.contains(&fight.order[index]) || timed_out {
+                        fight.idle = if timed_out && !acted.contains(&fight.order[index]) {
+                            fight.idle + 1
+                        } else {
+                            0
+                        };
+                        fight.turn = (index + 1) % fight.order.len();
+                        fight.turn_start = ticks;
+                        fight.changed = true;
+                    }
+                }
+                None => {
+                    fight.turn %= fight.order.len();
+                    fight.turn_start = ticks;
+                    fight.changed = true;
+                }
+            }
+            if fight.changed {
+                fight.changed = false;
+                for (index, id) in fight.order.iter().enumerate() {
+                    changes.set(*id, COMBAT_TURN, Value::Bool(index == fight.turn));
+                    if index == fight.turn {
+                        changes.set(*id, COMBAT_TURN_SEEN, Value::Bool(false));
+                    }
+                }
+            }
+        }
+
+        // A fight ends with the last opponent or after a round without actions
+        self.fights.retain(|fight| {
+            let over = fight.region == region.id
+                && (fight.order.len() < 2 || fight.idle >= fight.order.len());
+            if over {
+                for id in &fight.order {
+                    changes.set(*id, COMBAT_TURN, Value::Bool(false));
+                }
+            }
+            !over
+        });
+    }
+
+    /// Ends the combat state of the characters without recent actions.
+    fn update_engaged(&mut self, region: &Region, changes: &mut Changes) {
+        let fighting: Vec<u32> = self
+            .fights
+            .iter()
+            .filter(|f| f.region == region.id)
+            .flat_map(|f| f.order.iter().copied())
+            .collect();
+        for id in fighting {
+            self.engaged.insert((region.id, id), self.ticks);
+        }
+        let (ticks, timeout) = (self.ticks, self.config.timeout.max(1));
+        self.engaged.retain(|(r, id), last| {
+            let left = *r == region.id && ticks - *last > timeout;
+            if left {
+                changes.set(*id, COMBAT_ACTIVE, Value::Bool(false));
+            }
+            !left
+        });
+    }
+
+    /// Marks the character as in combat.
+    fn engage(&mut self, region: Uuid, id: u32, changes: &mut Changes) {
+        if self.engaged.insert((region, id), self.ticks).is_none() {
+            changes.set(id, COMBAT_ACTIVE, Value::Bool(true));
+        }
+    }
+
+    /// Adds the characters to their turn based fight, the first attack starts
+    /// a new one.
+    fn join(&mut self, region: Uuid, source: u32, target: u32) {
+        let index = self.fights.iter().position(|f| {
+            f.region == region && (f.order.contains(&source) || f.order.contains(&target))
+        });
+        match index {
+            Some(index) => {
+                let fight = &mut self.fights[index];
+                for id in [source, target] {
+                    if !fight.order.contains(&id) {
+                        fight.order.push(id);
+                    }
+                }
+            }
+            None if source != target => self.fights.push(Fight {
+                region,
+                order: vec![source, target],
+                turn: 0,
+                turn_start: self.ticks,
+                idle: 0,
+                changed: true,
+            }),
+            None => {}
+        }
+    }
+
+    /// Whether it is the turn of the character, None if it is not in a fight.
+    fn turn_of(&self, region: Uuid, id: u32) -> Option<bool> {
+        self.fights
+            .iter()
+            .find(|f| f.region == region && f.order.contains(&id))
+            .map(|f| f.order.get(f.turn) == Some(&id))
+    }
+
+    /// Whether an active status effect keeps the character from acting.
+    fn blocked(&self, region: Uuid, id: u32) -> bool {
+        self.effects.iter().any(|e| {
+            e.region == region
+                && e.entity == id
+                && self
+                    .config
+                    .effects
+                    .get(&e.name)
+                    .is_some_and(|effect| effect.blocks_actions)
+        })
+    }
+
+    fn alive(&self, entity: &Entity) -> bool {
+        entity
+            .attributes
+            .get(&self.health)
+            .is_none_or(|health| number(Some(health)) > 0.0)
+    }
+}
+
+fn entity_of(region: &Region, id: u32) -> Option<&Entity> {
+    region.map.entities.iter().find(|e| e.id == id)
+}
+
+fn name_of(entity: &Entity) -> String {
+    ["name", "class_name"]
+        .iter()
+        .map(|key| entity.attributes.get_str_default(key, String::new()))
+        .find(|name| !name.is_empty())
+        .unwrap_or_else(|| "Someone".into())
+}
+
+/// The attribute of the character plus the ones of its equipped items.
+fn stat(entity: &Entity, key: &str) -> f32 {
+    number(entity.attributes.get(key))
+        + entity
+            .equipped
+            .iter()
+            .map(|(_, item)| number(item.attributes.get(key)))
+            .sum::<f32>()
+}
+
+fn number(value: Option<&Value>) -> f32 {
+    match value {
+        Some(Value::Int(v)) => *v as f32,
+        Some(Value::Float(v)) => *v,
+        Some(Value::Bool(v)) => *v as i32 as f32,
+        Some(value) => value_text(value).trim().parse::<f32>().unwrap_or(0.0),
+        None => 0.0,
+    }
+}
+
+fn fill_text(
+    template: &str,
+    attacker: &str,
+    target: &str,
+    amount: f32,
+    damage_type: &str,
+) -> String {
+    template
+        .replace("{attacker}", attacker)
+        .replace("{target}", target)
+        .replace("{amount}", &amount.to_string())
+        .replace("{type}", damage_type)
+        .replace(['|', '\n'], " ")
+}
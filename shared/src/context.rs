@@ -67,13 +67,24 @@ pub enum ProjectContext {
     Screen(Uuid),
     ScreenWidget(Uuid, Uuid),
     Asset(Uuid),
+    Library(Uuid),
+    ScriptTest(Uuid),
+    Dialogue(Uuid),
+    Quest(Uuid),
     ProjectSettings,
+    Profiler,
+    Inspector,
+    Localization,
 }
 
 impl ProjectContext {
     pub fn id(self) -> Option<Uuid> {
         match self {
-            ProjectContext::Unknown | ProjectContext::ProjectSettings => None,
+            ProjectContext::Unknown
+            | ProjectContext::ProjectSettings
+            | ProjectContext::Profiler
+            | ProjectContext::Inspector
+            | ProjectContext::Localization => None,
             ProjectContext::Region(id)
             | ProjectContext::RegionCharacterInstance(id, _)
             | ProjectContext::RegionItemInstance(id, _)
@@ -88,7 +99,11 @@ impl ProjectContext {
             | ProjectContext::Tilemap(id)
             | ProjectContext::Screen(id)
             | ProjectContext::ScreenWidget(id, _)
-            | ProjectContext::Asset(id) => Some(id),
+            | ProjectContext::Asset(id)
+            | ProjectContext::Library(id)
+            | ProjectContext::ScriptTest(id)
+            | ProjectContext::Dialogue(id)
+            | ProjectContext::Quest(id) => Some(id),
         }
     }
 
@@ -163,6 +178,34 @@ impl ProjectContext {
         }
     }
 
+    pub fn is_library(&self) -> bool {
+        match self {
+            ProjectContext::Library(_) => true,
+            _ => false,
+        }
+    }
+
+    pub fn is_script_test(&self) -> bool {
+        match self {
+            ProjectContext::ScriptTest(_) => true,
+            _ => false,
+        }
+    }
+
+    pub fn is_dialogue(&self) -> bool {
+        match self {
+            ProjectContext::Dialogue(_) => true,
+            _ => false,
+        }
+    }
+
+    pub fn is_quest(&self) -> bool {
+        match self {
+            ProjectContext::Quest(_) => true,
+            _ => false,
+        }
+    }
+
     pub fn is_project_settings(&self) -> bool {
         match self {
             ProjectContext::ProjectSettings => true,
@@ -170,6 +213,27 @@ impl ProjectContext {
         }
     }
 
+    pub fn is_profiler(&self) -> bool {
+        match self {
+            ProjectContext::Profiler => true,
+            _ => false,
+        }
+    }
+
+    pub fn is_inspector(&self) -> bool {
+        match self {
+            ProjectContext::Inspector => true,
+            _ => false,
+        }
+    }
+
+    pub fn is_localization(&self) -> bool {
+        match self {
+            ProjectContext::Localization => true,
+            _ => false,
+        }
+    }
+
     pub fn has_custom_map(&self) -> bool {
         match self {
             ProjectContext::Screen(_) => true,
@@ -276,6 +340,10 @@ pub struct ServerContext {
     pub tree_screens_id: Uuid,
     pub tree_assets_id: Uuid,
     pub tree_assets_fonts_id: Uuid,
+    pub tree_libraries_id: Uuid,
+    pub tree_tests_id: Uuid,
+    pub tree_dialogues_id: Uuid,
+    pub tree_quests_id: Uuid,
     pub tree_palette_id: Uuid,
     pub tree_settings_id: Uuid,
 
@@ -389,6 +457,12 @@ pub struct ServerContext {
     /// Map clipboard which is currently being pasted
     pub paste_clipboard: Option<Map>,
 
+    /// The prefab which is currently being placed and if its instances are linked
+    pub paste_prefab: Option<(Uuid, bool)>,
+
+    /// The prop which is currently being placed
+    pub paste_prop: Option<PropInstance>,
+
     /// Background Progress Text
     pub background_progress: Option<String>,
 
@@ -412,6 +486,18 @@ pub struct ServerContext {
 
     ///Switch for showing 3D editing geometry
     pub show_editing_geometry: bool,
+
+    /// Switch for showing the navigation grid overlay in the 2D editor
+    pub show_navgrid: bool,
+
+    /// The fixed step clock of a deterministic game run
+    pub simulation_clock: Option<SimulationClock>,
+
+    /// The replay which is recorded during a deterministic game run
+    pub replay_recording: Option<Replay>,
+
+    /// The replay which is currently played back
+    pub replay_player: Option<ReplayPlayer>,
 }
 
 impl Default for ServerContext {
@@ -432,6 +518,10 @@ impl ServerContext {
             tree_screens_id: Uuid::new_v4(),
             tree_assets_id: Uuid::new_v4(),
             tree_assets_fonts_id: Uuid::new_v4(),
+            tree_libraries_id: Uuid::new_v4(),
+            tree_tests_id: Uuid::new_v4(),
+            tree_dialogues_id: Uuid::new_v4(),
+            tree_quests_id: Uuid::new_v4(),
             tree_palette_id: Uuid::new_v4(),
             tree_settings_id: Uuid::new_v4(),
 
@@ -488,6 +578,8 @@ impl ServerContext {
 
             clipboard: Map::default(),
             paste_clipboard: None,
+            paste_prefab: None,
+            paste_prop: None,
 
             background_progress: None,
 
@@ -502,9 +594,27 @@ impl ServerContext {
 
             selected_hud_icon_index: 0,
             show_editing_geometry: true,
+            show_navgrid: false,
+
+            simulation_clock: None,
+            replay_recording: None,
+            replay_player: None,
         }
     }
 
+    /// Records a player input into the current replay (if recording).
+    pub fn record_replay_input(&mut self, input: ReplayInput) {
+        if let Some(recording) = &mut self.replay_recording {
+            let frame = self.simulation_clock.as_ref().map(|c| c.frame).unwrap_or(0);
+            recording.record(frame, input);
+        }
+    }
+
+    /// Returns true if a replay is currently played back.
+    pub fn is_replaying(&self) -> bool {
+        self.replay_player.is_some()
+    }
+
     /// Checks if the PolyView has focus.
     pub fn polyview_has_focus(&self, ctx: &TheContext) -> bool {
         if let Some(focus) = &ctx.ui.focus {
@@ -0,0 +1,1050 @@
+use crate::prelude::*;
+use crate::rusterix_utils::{InstanceEdit, apply_instance_edit, apply_replay_input};
+use rusterix::{Rusterix, Value};
+use theframework::prelude::*;
+
+/// The name of the built-in script library with the dialogue helpers.
+pub const DIALOGUE_LIBRARY: &str = "dialogue";
+
+/// The attribute set by `start_dialogue()` on the speaker, as
+/// `<dialogue name>|<entity id>`. Cleared by the host when the dialogue starts.
+pub const DIALOGUE_START: &str = "dialogue_start";
+/// Set by the host on the speaker while its dialogue runs.
+pub const DIALOGUE_ACTIVE: &str = "dialogue_active";
+/// Set by the host on the speaker for an "Open Shop" effect, as
+/// `<entity id>|<filter>`. The speaker offers its inventory on the next poll.
+pub const DIALOGUE_SHOP: &str = "dialogue_shop";
+/// The user event sent to the player for a "Give Item" effect.
+pub const DIALOGUE_GIVE_ITEM: &str = "dialogue_give_item";
+/// The user event sent to the player for an "Event" effect.
+pub const DIALOGUE_EVENT: &str = "dialogue_event";
+/// The prefix of the player attributes holding the flags set with the
+/// "Global" scope.
+pub const DIALOGUE_GLOBAL_PREFIX: &str = "global_";
+/// The user event with the answer of the player to the shown node, as
+/// `<seq>|<output>`. The library stores it in the attribute of the same name
+/// and the host follows the output on its next update.
+pub const DIALOGUE_ANSWER: &str = "dialogue_answer";
+
+/// The source of the built-in `dialogue` library. The speaker starts the
+/// dialogue, the host shows its lines and choices and runs the effects.
+pub const DIALOGUE_SOURCE: &str = r##"# Built-in dialogue helpers. Dialogues are project assets edited as node graphs,
+# start_dialogue("Blacksmith", entity_id) talks to the given entity. Call
+# dialogue_event() from event() of the speaker and dialogue_user_event() from
+# user_event() of the player, they pass on the answers of the player and run
+# the "Open Shop" and "Give Item" effects. global_flag() reads the flags set
+# with the "Global" scope, they are kept by the player.
+
+def start_dialogue(name, entity_id):
+    set_attr("dialogue_start", str(name) + "|" + str(entity_id))
+    notify_in(1, "dialogue_poll")
+
+def in_dialogue():
+    return get_attr("dialogue_active") == True
+
+def global_flag(name, entity_id=None):
+    if entity_id is None:
+        return get_attr("global_" + str(name))
+    return get_attr_of(entity_id, "global_" + str(name))
+
+def dialogue_event(event, value):
+    if event != "dialogue_poll":
+        return False
+    shop = get_attr("dialogue_shop")
+    if shop:
+        set_attr("dialogue_shop", "")
+        parts = str(shop).split("|", 1)
+        offer_inventory(int(parts[0]), parts[1] if len(parts) > 1 else "")
+    if get_attr("dialogue_active") == True:
+        notify_in(1, "dialogue_poll")
+    return True
+
+def dialogue_user_event(event, value):
+    if event == "dialogue_answer":
+        set_attr("dialogue_answer", str(value))
+        return True
+    if event == "dialogue_give_item":
+        add_item(value)
+        return True
+    return False
+"##;
+
+/// The most choices of a choice node, every choice has its own output.
+pub const DIALOGUE_MAX_CHOICES: usize = 4;
+
+/// The most nodes followed without showing a line or choice, guards against
+/// loops of conditions and effects.
+const MAX_STEPS: usize = 64;
+
+/// Whose attribute a condition reads or an effect sets.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+pub enum DialogueScope {
+    /// The entity the speaker talks to.
+    Player,
+    /// The character which started the dialogue.
+    Speaker,
+    /// Flags shared by all dialogues, kept by the player with the `global_`
+    /// prefix so that scripts can read them and they are saved with the
+    /// player.
+    Global,
+}
+
+impl DialogueScope {
+    pub const ALL: [DialogueScope; 3] = [
+        DialogueScope::Player,
+        DialogueScope::Speaker,
+        DialogueScope::Global,
+    ];
+
+    pub fn to_index(self) -> i32 {
+        Self::ALL.iter().position(|s| *s == self).unwrap_or(0) as i32
+    }
+
+    pub fn from_index(index: i32) -> Self {
+        Self::ALL
+            .get(index.max(0) as usize)
+            .copied()
+            .unwrap_or(DialogueScope::Player)
+    }
+}
+
+/// The comparison of a condition.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+pub enum DialogueOp {
+    Equal,
+    NotEqual,
+    Less,
+    LessEqual,
+    Greater,
+    GreaterEqual,
+    /// The attribute exists and is not empty, false or zero.
+    IsSet,
+}
+
+impl DialogueOp {
+    pub const ALL: [DialogueOp; 7] = [
+        DialogueOp::Equal,
+        DialogueOp::NotEqual,
+        DialogueOp::Less,
+        DialogueOp::LessEqual,
+        DialogueOp::Greater,
+        DialogueOp::GreaterEqual,
+        DialogueOp::IsSet,
+    ];
+
+    pub fn to_index(self) -> i32 {
+        Self::ALL.iter().position(|o| *o == self).unwrap_or(0) as i32
+    }
+
+    pub fn from_index(index: i32) -> Self {
+        Self::ALL
+            .get(index.max(0) as usize)
+            .copied()
+            .unwrap_or(DialogueOp::Equal)
+    }
+
+    pub fn symbol(&self) -> &'static str {
+        match self {
+            DialogueOp::Equal => "==",
+            DialogueOp::NotEqual => "!=",
+            DialogueOp::Less => "<",
+            DialogueOp::LessEqual => "<=",
+            DialogueOp::Greater => ">",
+            DialogueOp::GreaterEqual => ">=",
+            DialogueOp::IsSet => "is set",
+        }
+    }
+
+    /// Compares the attribute value with the value of the condition. Numbers
+    /// are compared numerically, everything else as text.
+    pub fn compare(&self, attribute: Option<&Value>, value: &str) -> bool {
+        let text = attribute.map(value_text).unwrap_or_default();
+        if *self == DialogueOp::IsSet {
+            return !matches!(text.as_str(), "" | "false" | "0" | "0.0");
+        }
+        if attribute.is_none() && matches!(self, DialogueOp::Equal | DialogueOp::NotEqual) {
+            return (*self == DialogueOp::NotEqual) != value.trim().is_empty();
+        }
+
+        let ordering = match (text.trim().parse::<f32>(), value.trim().parse::<f32>()) {
+            (Ok(a), Ok(b)) => a.partial_cmp(&b),
+            _ => Some(text.trim().cmp(value.trim())),
+        };
+        let Some(ordering) = ordering else {
+            return false;
+        };
+        match self {
+            DialogueOp::Equal => ordering.is_eq(),
+            DialogueOp::NotEqual => ordering.is_ne(),
+            DialogueOp::Less => ordering.is_lt(),
+            DialogueOp::LessEqual => ordering.is_le(),
+            DialogueOp::Greater => ordering.is_gt(),
+            DialogueOp::GreaterEqual => ordering.is_ge(),
+            DialogueOp::IsSet => true,
+        }
+    }
+}
+
+/// What an effect node does.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+pub enum DialogueEffect {
+    /// Gives the item with the name of the argument to the player.
+    GiveItem,
+    /// Sets the attribute of the argument to the value.
+    SetFlag,
+    /// Starts the quest with the name of the argument.
+    StartQuest,
+    /// The speaker offers its inventory to the player, the argument filters it.
+    OpenShop,
+    /// Sends the argument to the player script as a `dialogue_event`.
+    Event,
+}
+
+impl DialogueEffect {
+    pub const ALL: [DialogueEffect; 5] = [
+        DialogueEffect::GiveItem,
+        DialogueEffect::SetFlag,
+        DialogueEffect::StartQuest,
+        DialogueEffect::OpenShop,
+        DialogueEffect::Event,
+    ];
+
+    pub fn to_index(self) -> i32 {
+        Self::ALL.iter().position(|e| *e == self).unwrap_or(0) as i32
+    }
+
+    pub fn from_index(index: i32) -> Self {
+        Self::ALL
+            .get(index.max(0) as usize)
+            .copied()
+            .unwrap_or(DialogueEffect::SetFlag)
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            DialogueEffect::GiveItem => "Give Item",
+            DialogueEffect::SetFlag => "Set Flag",
+            DialogueEffect::StartQuest => "Start Quest",
+            DialogueEffect::OpenShop => "Open Shop",
+            DialogueEffect::Event => "Event",
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub enum DialogueNodeKind {
+    /// Where the dialogue begins, every dialogue has exactly one.
+    Start,
+    /// A line spoken by the speaker, the player continues with return.
+    Line { speaker: String, text: String },
+    /// Up to `DIALOGUE_MAX_CHOICES` answers of the player, empty ones are hidden.
+    Choice { choices: Vec<String> },
+    /// Follows the "Yes" or "No" output depending on the attribute.
+    Condition {
+        scope: DialogueScope,
+        attribute: String,
+        op: DialogueOp,
+        value: String,
+    },
+    Effect {
+        effect: DialogueEffect,
+        scope: DialogueScope,
+        argument: String,
+        value: String,
+    },
+    /// Ends the dialogue. Outputs without a connection end it too.
+    End,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct DialogueNode {
+    pub kind: DialogueNodeKind,
+    pub position: Vec2<i32>,
+}
+
+impl DialogueNode {
+    pub fn new(kind: DialogueNodeKind) -> Self {
+        Self {
+            kind,
+            position: Vec2::new(20, 20),
+        }
+    }
+
+    /// Creates a node by its name: Line, Choice, Condition, Effect or End.
+    pub fn from_name(name: &str) -> Option<Self> {
+        let kind = match name {
+            "Line" => DialogueNodeKind::Line {
+                speaker: String::new(),
+                text: String::new(),
+            },
+            "Choice" => DialogueNodeKind::Choice {
+                choices: vec![String::new(); DIALOGUE_MAX_CHOICES],
+            },
+            "Condition" => DialogueNodeKind::Condition {
+                scope: DialogueScope::Player,
+                attribute: String::new(),
+                op: DialogueOp::Equal,
+                value: String::new(),
+            },
+            "Effect" => DialogueNodeKind::Effect {
+                effect: DialogueEffect::SetFlag,
+                scope: DialogueScope::Player,
+                argument: String::new(),
+                value: String::new(),
+            },
+            "End" => DialogueNodeKind::End,
+            _ => return None,
+        };
+        Some(Self::new(kind))
+    }
+
+    pub fn name(&self) -> String {
+        match &self.kind {
+            DialogueNodeKind::Start => "Start".into(),
+            DialogueNodeKind::Line { .. } => "Line".into(),
+            DialogueNodeKind::Choice { .. } => "Choice".into(),
+            DialogueNodeKind::Condition { .. } => "Condition".into(),
+            DialogueNodeKind::Effect { effect, .. } => effect.name().into(),
+            DialogueNodeKind::End => "End".into(),
+        }
+    }
+
+    /// The node category, used for the node colors.
+    pub fn category(&self) -> &'static str {
+        match &self.kind {
+            DialogueNodeKind::Start | DialogueNodeKind::End => "Flow",
+            DialogueNodeKind::Line { .. } | DialogueNodeKind::Choice { .. } => "Text",
+            DialogueNodeKind::Condition { .. } => "Condition",
+            DialogueNodeKind::Effect { .. } => "Effect",
+        }
+    }
+
+    pub fn inputs(&self) -> Vec<String> {
+        match &self.kind {
+            DialogueNodeKind::Start => vec![],
+            _ => vec!["In".into()],
+        }
+    }
+
+    pub fn outputs(&self) -> Vec<String> {
+        match &self.kind {
+            DialogueNodeKind::Start
+            | DialogueNodeKind::Line { .. }
+            | DialogueNodeKind::Effect { .. } => vec!["Next".into()],
+            DialogueNodeKind::Choice { .. } => (1..=DIALOGUE_MAX_CHOICES)
+                .map(|index| index.to_string())
+                .collect(),
+            DialogueNodeKind::Condition { .. } => vec!["Yes".into(), "No".into()],
+            DialogueNodeKind::End => vec![],
+        }
+    }
+}
+
+/// A dialogue asset: a graph of lines, choices, conditions and effects.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct Dialogue {
+    pub id: Uuid,
+    pub name: String,
+
+    pub nodes: Vec<DialogueNode>,
+    /// The connections as (source node, output, destination node, input).
+    #[serde(default)]
+    pub connections: Vec<(u16, u8, u16, u8)>,
+
+    #[serde(default)]
+    pub selected_node: Option<usize>,
+    #[serde(default)]
+    pub scroll_offset: Vec2<i32>,
+}
+
+impl Default for Dialogue {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Dialogue {
+    pub fn new() -> Self {
+        Self {
+            id: Uuid::new_v4(),
+            name: "New Dialogue".into(),
+            nodes: vec![DialogueNode::new(DialogueNodeKind::Start)],
+            connections: vec![],
+            selected_node: None,
+            scroll_offset: Vec2::zero(),
+        }
+    }
+
+    /// The node connected to the given output of a node.
+    pub fn next(&self, node: usize, output: usize) -> Option<usize> {
+        self.connections
+            .iter()
+            .find(|(src, out, _, _)| *src as usize == node && *out as usize == output)
+            .map(|(_, _, dest, _)| *dest as usize)
+            .filter(|dest| *dest < self.nodes.len())
+    }
+
+    /// The index of the start node.
+    pub fn start(&self) -> Option<usize> {
+        self.nodes
+            .iter()
+            .position(|n| n.kind == DialogueNodeKind::Start)
+    }
+}
+
+/// A running dialogue.
+#[derive(Clone, Debug)]
+struct DialogueSession {
+    dialogue: Uuid,
+    region: Uuid,
+    /// The character which started the dialogue.
+    speaker: u32,
+    speaker_name: String,
+    /// The entity the speaker talks to.
+    player: u32,
+    /// The line or choice node shown to the player.
+    node: usize,
+    /// The selected choice.
+    selected: usize,
+    /// Set when the player answered, the next update follows the output.
+    answered: Option<usize>,
+    /// Set while the answer of the player is on its way through the server.
+    asked: bool,
+}
+
+/// What the dialogue box shows.
+#[derive(Clone, Debug, PartialEq)]
+pub struct DialogueView {
+    pub speaker: String,
+    pub text: String,
+    /// The choices with their output index.
+    pub choices: Vec<(usize, String)>,
+    pub selected: usize,
+}
+
+/// Runs the dialogues started by scripts: follows the graph, evaluates the
+/// conditions against the entity attributes, applies the effects and shows the
+/// lines and choices in a dialogue box drawn by the host.
+///
+/// The answers of the player are sent as `dialogue_answer` user events, so that
+/// they are recorded with the player input, and read back from the player.
+///
+/// "Open Shop" hands over to the multiple choice list of the server, the speaker
+/// offers its inventory via `offer_inventory()` and the items are picked from
+/// the choices of `server.get_choices()` as with any other vendor.
+#[derive(Default)]
+pub struct DialogueRunner {
+    session: Option<DialogueSession>,
+    /// The shown node as of the last update, for the clicks.
+    shown: Option<DialogueView>,
+    /// The sequence number of the last answer.
+    answers: u64,
+    inputs: Vec<ReplayInput>,
+}
+
+impl DialogueRunner {
+    /// Forgets the running dialogue, called when the server starts.
+    pub fn clear(&mut self) {
+        *self = Self::default();
+    }
+
+    /// Returns true while a dialogue is shown.
+    pub fn is_active(&self) -> bool {
+        self.session.is_some()
+    }
+
+    /// Starts the dialogues requested by the characters of the region and
+    /// advances the running dialogue to the next line or choice.
+    pub fn update(
+        &mut self,
+        rusterix: &mut Rusterix,
+        region: &mut Region,
+        dialogues: &IndexMap<Uuid, Dialogue>,
+    ) {
+        let mut edits = vec![];
+
+        let requests: Vec<(u32, String, String)> = region
+            .map
+            .entities
+            .iter()
+            .filter_map(|e| {
+                let request = e.attributes.get_str_default(DIALOGUE_START, String::new());
+                (!request.is_empty()).then(|| {
+                    (
+                        e.id,
+                        request,
+                        e.attributes.get_str_default("name", String::new()),
+                    )
+                })
+            })
+            .collect();
+
+        for (speaker, request, speaker_name) in requests {
+            edits.push(InstanceEdit::EntityAttribute(
+                speaker,
+                DIALOGUE_START.into(),
+                Value::Str(String::new()),
+            ));
+            if self.session.is_some() {
+                continue;
+            }
+            let (name, player) = request.split_once('|').unwrap_or((request.as_str(), ""));
+            let Some(dialogue) = dialogues.values().find(|d| d.name == name.trim()) else {
+                eprintln!("Unknown dialogue \"{}\"", name.trim());
+                continue;
+            };
+            let (Ok(player), Some(start)) = (player.trim().parse::<u32>(), dialogue.start()) else {
+                continue;
+            };
+            edits.push(InstanceEdit::EntityAttribute(
+                speaker,
+                DIALOGUE_ACTIVE.into(),
+                Value::Bool(true),
+            ));
+            edits.push(InstanceEdit::EntityAttribute(
+                player,
+                QUEST_TALKED_TO.into(),
+                Value::Str(speaker_name.clone()),
+            ));
+            self.session = Some(DialogueSession {
+                dialogue: dialogue.id,
+                region: region.id,
+                speaker,
+                speaker_name,
+                player,
+                node: start,
+                selected: 0,
+                answered: Some(0),
+                asked: false,
+            });
+        }
+
+        let mut events = vec![];
+        if self.session.as_ref().is_some_and(|s| s.region == region.id)
+            && let Some(mut session) = self.session.take()
+        {
+            if session.asked
+                && let Some(output) = self.answer_of(region, session.player)
+            {
+                session.asked = false;
+                session.answered = Some(output);
+            }
+
+            let running = match (dialogues.get(&session.dialogue), session.answered) {
+                (Some(dialogue), Some(output)) => {
+                    session.answered = None;
+                    session.selected = 0;
+                    self.advance(
+                        dialogue,
+                        &mut session,
+                        output,
+                        region,
+                        &mut edits,
+                        &mut events,
+                    )
+                }
+                (Some(_), None) => true,
+                (None, _) => false,
+            };
+            if running {
+                self.session = Some(session);
+            } else {
+                edits.push(InstanceEdit::EntityAttribute(
+                    session.speaker,
+                    DIALOGUE_ACTIVE.into(),
+                    Value::Bool(false),
+                ));
+            }
+        }
+
+        for edit in &edits {
+            apply_instance_edit(rusterix, &mut region.map, edit);
+        }
+        for event in &events {
+            apply_replay_input(rusterix, event, &region.map);
+        }
+        self.shown = self.view(dialogues);
+    }
+
+    /// The output of the current answer once the player received it.
+    fn answer_of(&self, region: &Region, player: u32) -> Option<usize> {
+        let answer = region
+            .map
+            .entities
+            .iter()
+            .find(|e| e.id == player)?
+            .attributes
+            .get_str_default(DIALOGUE_ANSWER, String::new());
+        let (seq, output) = answer.split_once('|')?;
+        if seq.trim().parse::<u64>().ok()? != self.answers {
+            return None;
+        }
+        output.trim().parse::<usize>().ok()
+    }
+
+    /// Sends the answer of the player, the dialogue follows the output once the
+    /// player received it.
+    fn answer(&mut self, output: usize) {
+        let Some(session) = &mut self.session else {
+            return;
+        };
+        session.asked = true;
+        self.answers += 1;
+        self.inputs.push(ReplayInput::UserEvent(
+            DIALOGUE_ANSWER.into(),
+            format!("{}|{}", self.answers, output),
+        ));
+    }
+
+    /// The answers since the last call, to be sent and recorded as player
+    /// input.
+    pub fn take_inputs(&mut self) -> Vec<ReplayInput> {
+        std::mem::take(&mut self.inputs)
+    }
+
+    /// Follows the output of the current node until a line or choice is
+    /// reached. Returns false when the dialogue has ended.
+    fn advance(
+        &mut self,
+        dialogue: &Dialogue,
+        session: &mut DialogueSession,
+        output: usize,
+        region: &Region,
+        edits: &mut Vec<InstanceEdit>,
+        events: &mut Vec<ReplayInput>,
+    ) -> bool {
+        let mut next = dialogue.next(session.node, output);
+        for _ in 0..MAX_STEPS {
+            let Some(index) = next else {
+                return false;
+            };
+            session.node = index;
+            match &dialogue.nodes[index].kind {
+                DialogueNodeKind::Line { .. } => return true,
+                DialogueNodeKind::Choice { choices } => {
+                    if choices.iter().any(|c| !c.trim().is_empty()) {
+                        return true;
+                    }
+                    next = None;
+                }
+                DialogueNodeKind::Start => next = dialogue.next(index, 0),
+                DialogueNodeKind::End => return false,
+                DialogueNodeKind::Condition {
+                    scope,
+                    attribute,
+                    op,
+                    value,
+                } => {
+                    let attribute = self.attribute(region, session, *scope, attribute);
+                    let output = if op.compare(attribute.as_ref(), value) {
+                        0
+                    } else {
+                        1
+                    };
+                    next = dialogue.next(index, output);
+                }
+                DialogueNodeKind::Effect {
+                    effect,
+                    scope,
+                    argument,
+                    value,
+                } => {
+                    self.apply_effect(session, *effect, *scope, argument, value, edits, events);
+                    next = dialogue.next(index, 0);
+                }
+            }
+        }
+        eprintln!("Dialogue \"{}\" does not reach a line", dialogue.name);
+        false
+    }
+
+    /// The value of an attribute in the given scope.
+    fn attribute(
+        &self,
+        region: &Region,
+        session: &DialogueSession,
+        scope: DialogueScope,
+        attribute: &str,
+    ) -> Option<Value> {
+        let (id, key) = match scope {
+            DialogueScope::Global => (
+                session.player,
+                format!("{}{}", DIALOGUE_GLOBAL_PREFIX, attribute),
+            ),
+            DialogueScope::Player => (session.player, attribute.to_string()),
+            DialogueScope::Speaker => (session.speaker, attribute.to_string()),
+        };
+        region
+            .map
+            .entities
+            .iter()
+            .find(|e| e.id == id)
+            .and_then(|e| e.attributes.get(&key).cloned())
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn apply_effect(
+        &self,
+        session: &DialogueSession,
+        effect: DialogueEffect,
+        scope: DialogueScope,
+        argument: &str,
+        value: &str,
+        edits: &mut Vec<InstanceEdit>,
+        events: &mut Vec<ReplayInput>,
+    ) {
+        let argument = argument.trim();
+        match effect {
+            DialogueEffect::GiveItem => {
+                events.push(ReplayInput::UserEvent(
+                    DIALOGUE_GIVE_ITEM.into(),
+                    argument.into(),
+                ));
+            }
+            DialogueEffect::SetFlag => {
+                let value = parse_value(value);
+                match scope {
+                    DialogueScope::Global => edits.push(InstanceEdit::EntityAttribute(
+                        session.player,
+                        format!("{}{}", DIALOGUE_GLOBAL_PREFIX, argument),
+                        value,
+                    )),
+                    DialogueScope::Player => edits.push(InstanceEdit::EntityAttribute(
+                        session.player,
+                        argument.into(),
+                        value,
+                    )),
+                    DialogueScope::Speaker => edits.push(InstanceEdit::EntityAttribute(
+                        session.speaker,
+                        argument.into(),
+                        value,
+                    )),
+                }
+            }
+            DialogueEffect::StartQuest => {
+                // Quests started by the same step are queued line by line
+                let queued = edits.iter_mut().find_map(|edit| match edit {
+                    InstanceEdit::EntityAttribute(id, key, Value::Str(quests))
+                        if *id == session.player && key == QUEST_START =>
+                    {
+                        Some(quests)
+                    }
+                    _ => None,
+                });
+                if let Some(quests) = queued {
+                    quests.push('\n');
+                    quests.push_str(argument);
+                } else {
+                    edits.push(InstanceEdit::EntityAttribute(
+                        session.player,
+                        QUEST_START.into(),
+                        Value::Str(argument.into()),
+                    ));
+                }
+            }
+            DialogueEffect::OpenShop => {
+                edits.push(InstanceEdit::EntityAttribute(
+                    session.speaker,
+                    DIALOGUE_SHOP.into(),
+                    Value::Str(format!("{}|{}", session.player, argument)),
+                ));
+            }
+            DialogueEffect::Event => {
+                events.push(ReplayInput::UserEvent(
+                    DIALOGUE_EVENT.into(),
+                    argument.into(),
+                ));
+            }
+        }
+    }
+
+    /// The text and choices of the shown node.
+    pub fn view(&self, dialogues: &IndexMap<Uuid, Dialogue>) -> Option<DialogueView> {
+        let session = self.session.as_ref()?;
+        let node = dialogues.get(&session.dialogue)?.nodes.get(session.node)?;
+        let mut view = DialogueView {
+            speaker: session.speaker_name.clone(),
+            text: String::new(),
+            choices: vec![],
+            selected: session.selected,
+        };
+        match &node.kind {
+            DialogueNodeKind::Line { speaker, text } => {
+                if !speaker.trim().is_empty() {
+                    view.speaker = speaker.clone();
+                }
+                view.text = text.clone();
+            }
+            DialogueNodeKind::Choice { choices } => {
+                view.choices = choices
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, c)| !c.trim().is_empty())
+                    .map(|(index, c)| (index, c.clone()))
+                    .collect();
+            }
+            _ => {}
+        }
+        Some(view)
+    }
+
+    /// Handles a pressed key or button while a dialogue is shown. Return, space,
+    /// the south button or the interact action continue or pick the selected
+    /// choice, up / down select and the number keys pick a choice directly.
+    /// Escape or the east button ends the dialogue. Returns true if consumed.
+    pub fn press(
+        &mut self,
+        dialogues: &IndexMap<Uuid, Dialogue>,
+        map: &InputMap,
+        source: &InputSource,
+    ) -> bool {
+        let Some(view) = self.view(dialogues) else {
+            return false;
+        };
+        let Some(session) = &mut self.session else {
+            return false;
+        };
+        if session.answered.is_some() || session.asked {
+            return true;
+        }
+
+        let name = match source {
+            InputSource::Key(key) => key.as_str(),
+            InputSource::Gamepad(button) => button.as_str(),
+        };
+        let count = view.choices.len();
+        let answer = match name {
+            "up" | "dpad_up" | "left_stick_up" if count > 0 => {
+                session.selected = (session.selected + count - 1) % count;
+                None
+            }
+            "down" | "dpad_down" | "left_stick_down" if count > 0 => {
+                session.selected = (session.selected + 1) % count;
+                None
+            }
+            // No node has this output, the dialogue ends on the next update
+            "escape" | "east" => Some(usize::MAX),
+            _ => {
+                if let Ok(number) = name.parse::<usize>() {
+                    view.choices
+                        .get(number.wrapping_sub(1))
+                        .map(|(output, _)| *output)
+                } else if matches!(name, "return" | "space" | "south")
+                    || map.actions_for(source).iter().any(|a| a == "interact")
+                {
+                    Some(
+                        view.choices
+                            .get(session.selected)
+                            .map(|(output, _)| *output)
+                            .unwrap_or(0),
+                    )
+                } else {
+                    None
+                }
+            }
+        };
+        if let Some(output) = answer {
+            self.answer(output);
+        }
+        true
+    }
+
+    /// Picks the choice at the given screen coordinate or continues a line.
+    /// Returns true if the click was inside the dialogue box.
+    pub fn click(&mut self, coord: Vec2<i32>, width: usize, height: usize) -> bool {
+        let Some(view) = self.shown.clone() else {
+            return false;
+        };
+        let layout = DialogueLayout::new(&view, width, height);
+        let Some(session) = &mut self.session else {
+            return false;
+        };
+        if !layout.contains(coord) {
+            return false;
+        }
+        if session.answered.is_none() && !session.asked {
+            if view.choices.is_empty() {
+                self.answer(0);
+            } else if let Some(index) = layout.choice_at(coord)
+                && let Some((output, _)) = view.choices.get(index)
+            {
+                self.answer(*output);
+            }
+        }
+        true
+    }
+
+    /// Draws the dialogue box at the bottom of the game view.
+    pub fn draw(
+        &self,
+        dialogues: &IndexMap<Uuid, Dialogue>,
+        buffer: &mut TheRGBABuffer,
+        ctx: &mut TheContext,
+    ) {
+        let Some(view) = self.view(dialogues) else {
+            return;
+        };
+        let width = buffer.dim().width as usize;
+        let height = buffer.dim().height as usize;
+        let stride = buffer.stride();
+        let layout = DialogueLayout::new(&view, width, height);
+        let (x, y, w, h) = layout.rect;
+
+        let bg_color = [20, 20, 24, 235];
+        let selected_color = [60, 70, 100, 255];
+        let text_color = [220, 220, 220, 255];
+        let speaker_color = [240, 200, 120, 255];
+        let font = TheFontSettings {
+            size: 14.0,
+            ..Default::default()
+        };
+
+        ctx.draw
+            .rect(buffer.pixels_mut(), &(x, y, w, h), stride, &bg_color);
+
+        let mut row_y = y + DialogueLayout::MARGIN;
+        if !view.speaker.is_empty() {
+            ctx.draw.text_rect(
+                buffer.pixels_mut(),
+                &(x + 10, row_y, w - 20, DialogueLayout::ROW_HEIGHT),
+                stride,
+                &view.speaker,
+                font.clone(),
+                &speaker_color,
+                &bg_color,
+            );
+        }
+        row_y += DialogueLayout::ROW_HEIGHT;
+
+        for line in &layout.lines {
+            ctx.draw.text_rect(
+                buffer.pixels_mut(),
+                &(x + 10, row_y, w - 20, DialogueLayout::ROW_HEIGHT),
+                stride,
+                line,
+                font.clone(),
+                &text_color,
+                &bg_color,
+            );
+            row_y += DialogueLayout::ROW_HEIGHT;
+        }
+
+        for (index, (_, choice)) in view.choices.iter().enumerate() {
+            let (cx, cy, cw, ch) = layout.choice_rect(index);
+            let row_color = if index == view.selected {
+                ctx.draw.rect(
+                    buffer.pixels_mut(),
+                    &(cx, cy, cw, ch),
+                    stride,
+                    &selected_color,
+                );
+                selected_color
+            } else {
+                bg_color
+            };
+            ctx.draw.text_rect(
+                buffer.pixels_mut(),
+                &(cx + 6, cy, cw - 12, ch),
+                stride,
+                &format!("{}. {}", index + 1, choice),
+                font.clone(),
+                &text_color,
+                &row_color,
+            );
+        }
+    }
+}
+
+/// The placement of the dialogue box and its rows.
+struct DialogueLayout {
+    rect: (usize, usize, usize, usize),
+    lines: Vec<String>,
+    choices: usize,
+}
+
+impl DialogueLayout {
+    const ROW_HEIGHT: usize = 22;
+    const MARGIN: usize = 10;
+
+    fn new(view: &DialogueView, width: usize, height: usize) -> Self {
+        let w = if width < 300 { width } else { width * 3 / 4 };
+        // Roughly 7 pixels per character at the font size of the box
+        let lines = wrap_text(&view.text, (w.saturating_sub(20) / 7).max(10));
+        let rows = 1 + lines.len() + view.choices.len();
+        let h = (rows * Self::ROW_HEIGHT + 2 * Self::MARGIN).min(height);
+        Self {
+            rect: ((width - w) / 2, height - h - (height - h).min(20), w, h),
+            lines,
+            choices: view.choices.len(),
+        }
+    }
+
+    fn contains(&self, coord: Vec2<i32>) -> bool {
+        let (x, y, w, h) = self.rect;
+        coord.x >= x as i32
+            && coord.y >= y as i32
+            && coord.x < (x + w) as i32
+            && coord.y < (y + h) as i32
+    }
+
+    fn choice_rect(&self, index: usize) -> (usize, usize, usize, usize) {
+        let (x, y, w, _) = self.rect;
+        (
+            x + 4,
+            y + Self::MARGIN + (1 + self.lines.len() + index) * Self::ROW_HEIGHT,
+            w - 8,
+            Self::ROW_HEIGHT,
+        )
+    }
+
+    fn choice_at(&self, coord: Vec2<i32>) -> Option<usize> {
+        (0..self.choices).find(|index| {
+            let (_, y, _, h) = self.choice_rect(*index);
+            coord.y >= y as i32 && coord.y < (y + h) as i32
+        })
+    }
+}
+
+/// Breaks the text into lines of at most `columns` characters at word bounds.
+fn wrap_text(text: &str, columns: usize) -> Vec<String> {
+    let mut lines = vec![];
+    for paragraph in text.lines() {
+        let mut line = String::new();
+        for word in paragraph.split_whitespace() {
+            if !line.is_empty() && line.chars().count() + 1 + word.chars().count() > columns {
+                lines.push(std::mem::take(&mut line));
+            }
+            if !line.is_empty() {
+                line.push(' ');
+            }
+            line.push_str(word);
+        }
+        lines.push(line);
+    }
+    lines
+}
+
+/// The text of an attribute value as compared by conditions.
+pub(crate) fn value_text(value: &Value) -> String {
+    match value {
+        Value::Str(v) => v.clone(),
+        Value::Int(v) => v.to_string(),
+        Value::Float(v) => v.to_string(),
+        Value::Bool(v) => v.to_string(),
+        _ => String::new(),
+    }
+}
+
+/// The attribute value of an effect: a bool, an integer, a float or text.
+fn parse_value(value: &str) -> Value {
+    let value = value.trim();
+    if let Ok(v) = value.parse::<bool>() {
+        Value::Bool(v)
+    } else if let Ok(v) = value.parse::<i32>() {
+        Value::Int(v)
+    } else if let Ok(v) = value.parse::<f32>() {
+        Value::Float(v)
+    } else {
+        Value::Str(value.into())
+    }
+}
@@ -0,0 +1,1020 @@
+use crate::prelude::*;
+use rand::{Rng, SeedableRng, rngs::StdRng};
+use rusterix::{PixelSource, Value};
+use theframework::prelude::*;
+
+/// The instance source of spawned characters and items when generating at
+/// runtime.
+pub const DEFAULT_INSTANCE_SOURCE: &str = "def setup():\n    pass\n";
+
+/// The name of the built-in script library with the generator helpers.
+pub const GENERATOR_LIBRARY: &str = "generator";
+
+/// The attribute holding the generate requests queued by a script. Every line
+/// is `<sequence>|<region>|<seed>`, the host runs the requests it has not seen
+/// yet.
+pub const GENERATOR_QUEUE: &str = "generator_queue";
+
+/// The source of the built-in `generator` library.
+pub const GENERATOR_SOURCE: &str = r##"# Built-in generator helpers. Regions with a [generator] section in their
+# config can be generated again while the game runs. The region is replaced by
+# the new layout at the end of the frame, its characters and items start anew.
+
+def _generator(command):
+    seq = (get_attr("generator_seq") or 0) + 1
+    set_attr("generator_seq", seq)
+    queue = [c for c in (get_attr("generator_queue") or "").split("\n") if c][-15:]
+    queue.append(str(seq) + "|" + command)
+    set_attr("generator_queue", "\n".join(queue))
+
+def generate_region(region, seed=0):
+    # A seed of 0 uses the seed of the [generator] section
+    _generator(str(region) + "|" + str(int(seed)))
+"##;
+
+/// What the generator lays out.
+#[derive(Serialize, Deserialize, Default, Clone, Copy, Debug, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum GeneratorMode {
+    #[default]
+    Dungeon,
+    Town,
+}
+
+/// How dungeon rooms are placed.
+#[derive(Serialize, Deserialize, Default, Clone, Copy, Debug, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum DungeonLayout {
+    /// Binary space partitioning, one room per leaf.
+    #[default]
+    Bsp,
+    /// Randomly placed rooms connected in order.
+    Rooms,
+}
+
+/// A character or item template and its relative spawn weight.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct SpawnEntry {
+    pub template: String,
+    #[serde(default = "default_weight")]
+    pub weight: u32,
+}
+
+fn default_weight() -> u32 {
+    1
+}
+
+/// Fills the map of a region from a seed and parameters. The parameters can
+/// be set in the editor or in the `[generator]` section of the region config.
+/// With `on_start` the region is generated every time the server starts,
+/// scripts generate it again with `generate_region()` of the `generator`
+/// library.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(default)]
+pub struct RegionGenerator {
+    pub mode: GeneratorMode,
+    pub layout: DungeonLayout,
+
+    /// The seed, 0 picks a random seed.
+    pub seed: u64,
+    /// Generate the region when the server starts.
+    pub on_start: bool,
+
+    /// The size in cells.
+    pub width: i32,
+    pub height: i32,
+    /// The size of a cell in map units.
+    pub cell_size: f32,
+
+    /// Dungeon room sizes in cells.
+    pub room_min: i32,
+    pub room_max: i32,
+    /// The number of rooms of the rooms layout.
+    pub room_count: i32,
+    pub corridor_width: i32,
+
+    /// Town street width, block and minimum lot size in cells.
+    pub street_width: i32,
+    pub block_size: i32,
+    pub lot_min: i32,
+
+    pub floor_height: f32,
+    pub ceiling_height: f32,
+
+    /// Comma separated tile tags.
+    pub floor_tags: String,
+    pub wall_tags: String,
+    pub door_tags: String,
+    pub ceiling_tags: String,
+    pub street_tags: String,
+
+    pub characters: Vec<SpawnEntry>,
+    pub character_count: i32,
+    pub items: Vec<SpawnEntry>,
+    pub item_count: i32,
+}
+
+impl Default for RegionGenerator {
+    fn default() -> Self {
+        Self {
+            mode: GeneratorMode::Dungeon,
+            layout: DungeonLayout::Bsp,
+
+            seed: 0,
+            on_start: false,
+
+            width: 48,
+            height: 48,
+            cell_size: 1.0,
+
+            room_min: 4,
+            room_max: 9,
+            room_count: 10,
+            corridor_width: 1,
+
+            street_width: 2,
+            block_size: 10,
+            lot_min: 4,
+
+            floor_height: 0.0,
+            ceiling_height: 3.0,
+
+            floor_tags: "floor".into(),
+            wall_tags: "wall".into(),
+            door_tags: "door".into(),
+            ceiling_tags: String::new(),
+            street_tags: "street".into(),
+
+            characters: vec![],
+            character_count: 0,
+            items: vec![],
+            item_count: 0,
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+enum Cell {
+    Empty,
+    Room(usize),
+    Corridor,
+    Street,
+    House(usize),
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+enum Edge {
+    Open,
+    Wall,
+    Door,
+}
+
+#[derive(Clone, Copy, Debug)]
+struct Rect {
+    x: i32,
+    y: i32,
+    w: i32,
+    h: i32,
+}
+
+impl Rect {
+    fn new(x: i32, y: i32, w: i32, h: i32) -> Self {
+        Self { x, y, w, h }
+    }
+
+    fn center(&self) -> (i32, i32) {
+        (self.x + self.w / 2, self.y + self.h / 2)
+    }
+
+    /// True if the rects overlap or are closer than the gap.
+    fn touches(&self, other: &Rect, gap: i32) -> bool {
+        self.x - gap < other.x + other.w
+            && other.x - gap < self.x + self.w
+            && self.y - gap < other.y + other.h
+            && other.y - gap < self.y + self.h
+    }
+}
+
+struct Grid {
+    width: i32,
+    height: i32,
+    cells: Vec<Cell>,
+}
+
+impl Grid {
+    fn new(width: i32, height: i32) -> Self {
+        Self {
+            width,
+            height,
+            cells: vec![Cell::Empty; (width * height) as usize],
+        }
+    }
+
+    fn get(&self, x: i32, y: i32) -> Cell {
+        if x < 0 || y < 0 || x >= self.width || y >= self.height {
+            Cell::Empty
+        } else {
+            self.cells[(y * self.width + x) as usize]
+        }
+    }
+
+    fn set(&mut self, x: i32, y: i32, cell: Cell) {
+        if x >= 0 && y >= 0 && x < self.width && y < self.height {
+            self.cells[(y * self.width + x) as usize] = cell;
+        }
+    }
+
+    fn fill(&mut self, rect: &Rect, cell: Cell) {
+        for y in rect.y..rect.y + rect.h {
+            for x in rect.x..rect.x + rect.w {
+                self.set(x, y, cell);
+            }
+        }
+    }
+}
+
+/// Collects the `generate_region()` requests of the scripts of a running game.
+#[derive(Default)]
+pub struct GeneratorRunner {
+    /// The settings of a deterministic run, requests without a seed get a
+    /// seed derived from them.
+    deterministic: Option<DeterministicSettings>,
+    /// The number of requests so far, derives the seeds of deterministic runs.
+    count: u64,
+    /// The last request sequence seen for every entity and item.
+    sequences: FxHashMap<(Uuid, bool, u32), i64>,
+}
+
+impl GeneratorRunner {
+    /// Resets the runner, called when the game starts.
+    pub fn start(&mut self, deterministic: Option<DeterministicSettings>) {
+        self.deterministic = deterministic;
+        self.count = 0;
+        self.sequences.clear();
+    }
+
+    /// The new requests queued by the entities and items of the region, as the
+    /// name of the region to generate and the seed, 0 for the seed of its
+    /// config.
+    pub fn requests(&mut self, region: &Region) -> Vec<(String, u64)> {
+        let mut requests = vec![];
+        let queues = region
+            .map
+            .entities
+            .iter()
+            .map(|e| ((region.id, false, e.id), &e.attributes))
+            .chain(
+                region
+                    .map
+                    .items
+                    .iter()
+                    .map(|i| ((region.id, true, i.id), &i.attributes)),
+            );
+        for (source, attributes) in queues {
+            let queue = attributes.get_str_default(GENERATOR_QUEUE, String::new());
+            if queue.is_empty() {
+                continue;
+            }
+            let last = self.sequences.entry(source).or_insert(0);
+            for line in queue.lines() {
+                let mut parts = line.split('|');
+                let (Some(seq), Some(name), Some(seed)) =
+                    (parts.next(), parts.next(), parts.next())
+                else {
+                    continue;
+                };
+                let Ok(seq) = seq.trim().parse::<i64>() else {
+                    continue;
+                };
+                if seq <= *last {
+                    continue;
+                }
+                *last = seq;
+                let mut seed = seed.trim().parse::<i64>().unwrap_or(0).max(0) as u64;
+                self.count += 1;
+                if seed == 0 {
+                    if let Some(settings) = self.deterministic {
+                        seed = settings.seed_for(u64::MAX / 2 + self.count).max(1);
+                    }
+                }
+                requests.push((name.trim().to_string(), seed));
+            }
+        }
+        requests
+    }
+}
+
+impl RegionGenerator {
+    /// Reads the `[generator]` section of a region config.
+    pub fn from_config(config: &str) -> Option<Self> {
+        let table = config.parse::<toml::Table>().ok()?;
+        table.get("generator")?.clone().try_into().ok()
+    }
+
+    /// Parses a spawn list of the form `Orc:3, Rat:5, Chest`.
+    pub fn parse_spawn_list(text: &str) -> Vec<SpawnEntry> {
+        text.split(',')
+            .filter_map(|entry| {
+                let mut parts = entry.split(':');
+                let template = parts.next()?.trim();
+                if template.is_empty() {
+                    return None;
+                }
+                let weight = parts
+                    .next()
+                    .and_then(|w| w.trim().parse().ok())
+                    .unwrap_or(1);
+                Some(SpawnEntry {
+                    template: template.to_string(),
+                    weight,
+                })
+            })
+            .collect()
+    }
+
+    /// The inverse of `parse_spawn_list`.
+    pub fn spawn_list_to_string(list: &[SpawnEntry]) -> String {
+        list.iter()
+            .map(|e| format!("{}:{}", e.template, e.weight))
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+
+    /// Replaces the geometry of the region map. If the character or item
+    /// count is not zero, the character or item instances of the region are
+    /// replaced by instances spawned from the weighted template lists using
+    /// the given instance sources.
+    pub fn generate(
+        &self,
+        region: &mut Region,
+        project: &Project,
+        character_source: &str,
+        item_source: &str,
+    ) {
+        let seed = if self.seed == 0 {
+            rand::random()
+        } else {
+            self.seed
+        };
+        let mut rng = StdRng::seed_from_u64(seed);
+
+        let width = self.width.clamp(8, 512);
+        let height = self.height.clamp(8, 512);
+        let mut grid = Grid::new(width, height);
+
+        let (rects, doors) = match self.mode {
+            GeneratorMode::Dungeon => (self.layout_dungeon(&mut grid, &mut rng), vec![]),
+            GeneratorMode::Town => self.layout_town(&mut grid, &mut rng),
+        };
+
+        let map = &mut region.map;
+        map.vertices.clear();
+        map.linedefs.clear();
+        map.sectors.clear();
+        map.surfaces.clear();
+        map.selected_vertices.clear();
+        map.selected_linedefs.clear();
+        map.selected_sectors.clear();
+        map.possible_polygon.clear();
+
+        let tiles = TilePicker {
+            floor: tiles_by_tags(project, &self.floor_tags),
+            wall: tiles_by_tags(project, &self.wall_tags),
+            door: tiles_by_tags(project, &self.door_tags),
+            ceiling: tiles_by_tags(project, &self.ceiling_tags),
+            street: tiles_by_tags(project, &self.street_tags),
+        };
+
+        // Rooms and houses are one sector each, corridors and streets are
+        // merged into as few rectangles as possible.
+        let mut sectors: Vec<(Rect, Cell)> = rects
+            .iter()
+            .enumerate()
+            .map(|(i, r)| {
+                let cell = if self.mode == GeneratorMode::Town {
+                    Cell::House(i)
+                } else {
+                    Cell::Room(i)
+                };
+                (*r, cell)
+            })
+            .collect();
+        let shared = if self.mode == GeneratorMode::Town {
+            Cell::Street
+        } else {
+            Cell::Corridor
+        };
+        for rect in merge_cells(&grid, shared) {
+            sectors.push((rect, shared));
+        }
+
+        for (rect, cell) in sectors {
+            self.emit_sector(map, &grid, &rect, cell, &doors, &tiles, &mut rng);
+        }
+
+        // Spawn the instances on random free cells
+        let spawn_cells: Vec<(i32, i32)> = (0..height)
+            .flat_map(|y| (0..width).map(move |x| (x, y)))
+            .filter(|(x, y)| matches!(grid.get(*x, *y), Cell::Room(_) | Cell::Street))
+            .collect();
+        let mut free = spawn_cells;
+
+        if self.character_count > 0 {
+            region.characters.clear();
+            let templates = resolve_templates(&self.characters, |name| {
+                project
+                    .characters
+                    .iter()
+                    .find(|(_, c)| c.name.eq_ignore_ascii_case(name))
+                    .map(|(id, c)| (*id, c.name.clone()))
+            });
+            for _ in 0..self.character_count {
+                let (Some((id, name)), Some(pos)) = (
+                    pick_weighted(&templates, &mut rng),
+                    take_cell(&mut free, &mut rng),
+                ) else {
+                    break;
+                };
+                let instance = Character {
+                    name,
+                    character_id: id,
+                    source: character_source.to_string(),
+                    position: self.cell_position(&grid, pos),
+                    ..Default::default()
+                };
+                region.characters.insert(instance.id, instance);
+            }
+        }
+
+        if self.item_count > 0 {
+            region.items.clear();
+            let templates = resolve_templates(&self.items, |name| {
+                project
+                    .items
+                    .iter()
+                    .find(|(_, i)| i.name.eq_ignore_ascii_case(name))
+                    .map(|(id, i)| (*id, i.name.clone()))
+            });
+            for _ in 0..self.item_count {
+                let (Some((id, name)), Some(pos)) = (
+                    pick_weighted(&templates, &mut rng),
+                    take_cell(&mut free, &mut rng),
+                ) else {
+                    break;
+                };
+                let instance = Item {
+                    name,
+                    item_id: id,
+                    source: item_source.to_string(),
+                    position: self.cell_position(&grid, pos),
+                    ..Default::default()
+                };
+                region.items.insert(instance.id, instance);
+            }
+        }
+    }
+
+    /// Carves rooms and corridors, returns the rooms.
+    fn layout_dungeon(&self, grid: &mut Grid, rng: &mut StdRng) -> Vec<Rect> {
+        let room_min = self.room_min.max(2);
+        let room_max = self.room_max.max(room_min);
+        let corridor = self.corridor_width.clamp(1, room_min);
+
+        let mut rooms: Vec<Rect> = vec![];
+        let mut connections: Vec<(usize, usize)> = vec![];
+
+        match self.layout {
+            DungeonLayout::Rooms => {
+                let count = self.room_count.max(1) as usize;
+                for _ in 0..count * 16 {
+                    if rooms.len() >= count {
+                        break;
+                    }
+                    let w = rng.random_range(room_min..=room_max);
+                    let h = rng.random_range(room_min..=room_max);
+                    if w + 2 > grid.width || h + 2 > grid.height {
+                        continue;
+                    }
+                    let room = Rect::new(
+                        rng.random_range(1..=grid.width - w - 1),
+                        rng.random_range(1..=grid.height - h - 1),
+                        w,
+                        h,
+                    );
+                    if rooms.iter().all(|r| !r.touches(&room, corridor + 1)) {
+                        rooms.push(room);
+                    }
+                }
+                // Connect the rooms from left to right to avoid long detours
+                let mut order: Vec<usize> = (0..rooms.len()).collect();
+                order.sort_by_key(|i| rooms[*i].center());
+                for pair in order.windows(2) {
+                    connections.push((pair[0], pair[1]));
+                }
+            }
+            DungeonLayout::Bsp => {
+                let bounds = Rect::new(0, 0, grid.width, grid.height);
+                split_bsp(
+                    bounds,
+                    room_min,
+                    room_max,
+                    rng,
+                    &mut rooms,
+                    &mut connections,
+                );
+            }
+        }
+
+        for (i, room) in rooms.iter().enumerate() {
+            grid.fill(room, Cell::Room(i));
+        }
+        for (a, b) in connections {
+            let horizontal_first = rng.random_bool(0.5);
+            carve_corridor(
+                grid,
+                rooms[a].center(),
+                rooms[b].center(),
+                corridor,
+                horizontal_first,
+            );
+        }
+
+        rooms
+    }
+
+    /// Lays out streets and house footprints, returns the houses and their
+    /// door edges.
+    fn layout_town(
+        &self,
+        grid: &mut Grid,
+        rng: &mut StdRng,
+    ) -> (Vec<Rect>, Vec<(i32, i32, (i32, i32))>) {
+        let street = self.street_width.max(1);
+        let block = self.block_size.max(3);
+        let lot_min = self.lot_min.clamp(2, block);
+        let period = block + street;
+
+        for y in 0..grid.height {
+            for x in 0..grid.width {
+                if x % period < street || y % period < street {
+                    grid.set(x, y, Cell::Street);
+                }
+            }
+        }
+
+        let mut houses = vec![];
+        let mut by = street;
+        while by < grid.height {
+            let mut bx = street;
+            while bx < grid.width {
+                let block_rect = Rect::new(
+                    bx,
+                    by,
+                    block.min(grid.width - bx),
+                    block.min(grid.height - by),
+                );
+                if block_rect.w >= lot_min && block_rect.h >= lot_min {
+                    let mut lots = vec![];
+                    split_lots(block_rect, lot_min, rng, &mut lots);
+                    for lot in lots {
+                        // Keep a gap to the neighbours inside the block
+                        let mut house = lot;
+                        if lot.x + lot.w < block_rect.x + block_rect.w {
+                            house.w -= 1;
+                        }
+                        if lot.y + lot.h < block_rect.y + block_rect.h {
+                            house.h -= 1;
+                        }
+                        if house.w >= 2 && house.h >= 2 {
+                            houses.push(house);
+                        }
+                    }
+                }
+                bx += period;
+            }
+            by += period;
+        }
+
+        for (i, house) in houses.iter().enumerate() {
+            grid.fill(house, Cell::House(i));
+        }
+
+        // One door per house, preferably facing a street
+        let mut doors = vec![];
+        for house in &houses {
+            let mut street_edges = vec![];
+            let mut other_edges = vec![];
+            for (x, y, dir) in perimeter(house) {
+                match grid.get(x + dir.0, y + dir.1) {
+                    Cell::Street => street_edges.push((x, y, dir)),
+                    Cell::Empty => other_edges.push((x, y, dir)),
+                    _ => {}
+                }
+            }
+            let candidates = if street_edges.is_empty() {
+                other_edges
+            } else {
+                street_edges
+            };
+            if !candidates.is_empty() {
+                doors.push(candidates[rng.random_range(0..candidates.len())]);
+            }
+        }
+
+        (houses, doors)
+    }
+
+    /// Adds the rect as a sector. The boundary is split wherever the kind of
+    /// edge changes so walls and doors get their own linedefs.
+    #[allow(clippy::too_many_arguments)]
+    fn emit_sector(
+        &self,
+        map: &mut Map,
+        grid: &Grid,
+        rect: &Rect,
+        cell: Cell,
+        doors: &[(i32, i32, (i32, i32))],
+        tiles: &TilePicker,
+        rng: &mut StdRng,
+    ) {
+        let town = self.mode == GeneratorMode::Town;
+        let classify = |x: i32, y: i32, dir: (i32, i32)| -> Edge {
+            let outside = grid.get(x + dir.0, y + dir.1);
+            match cell {
+                Cell::House(_) => {
+                    if doors.contains(&(x, y, dir)) {
+                        Edge::Door
+                    } else if outside == cell {
+                        Edge::Open
+                    } else {
+                        Edge::Wall
+                    }
+                }
+                Cell::Street => Edge::Open,
+                _ => match (cell, outside) {
+                    (_, Cell::Empty) => Edge::Wall,
+                    (Cell::Room(_), Cell::Corridor) => {
+                        corridor_edge(grid, (x + dir.0, y + dir.1), dir)
+                    }
+                    (Cell::Corridor, Cell::Room(_)) => {
+                        corridor_edge(grid, (x, y), (-dir.0, -dir.1))
+                    }
+                    _ => Edge::Open,
+                },
+            }
+        };
+
+        // Corner points and edge kinds along the boundary
+        let mut points: Vec<(i32, i32)> = vec![];
+        let mut edges: Vec<Edge> = vec![];
+        let mut prev_dir = (0, 0);
+        for (x, y, dir) in perimeter(rect) {
+            let start = edge_start(x, y, dir);
+            let edge = classify(x, y, dir);
+            if dir != prev_dir || edges.last() != Some(&edge) {
+                points.push(start);
+                edges.push(edge);
+            }
+            prev_dir = dir;
+        }
+
+        let vertices: Vec<u32> = points
+            .iter()
+            .map(|(x, y)| {
+                let p = self.to_map(grid, *x as f32, *y as f32);
+                map.add_vertex_at_3d(p.x, p.y, self.floor_height, false)
+            })
+            .collect();
+
+        map.possible_polygon = vec![];
+        let mut sector_id = None;
+        let wall_height = (self.ceiling_height - self.floor_height).max(0.0);
+        for (i, edge) in edges.iter().enumerate() {
+            let (linedef_id, sector) =
+                map.create_linedef(vertices[i], vertices[(i + 1) % vertices.len()]);
+            if sector.is_some() {
+                sector_id = sector;
+            }
+            if let Some(linedef) = map.find_linedef_mut(linedef_id) {
+                match edge {
+                    Edge::Wall => {
+                        if let Some(tile) = pick(&tiles.wall, rng) {
+                            linedef.properties.set("row1_source", tile);
+                        }
+                        linedef
+                            .properties
+                            .set("wall_height", Value::Float(wall_height));
+                    }
+                    Edge::Door => {
+                        linedef.properties.set("door", Value::Bool(true));
+                        if let Some(tile) = pick(&tiles.door, rng) {
+                            linedef.properties.set("row1_source", tile);
+                            linedef
+                                .properties
+                                .set("wall_height", Value::Float(wall_height));
+                        }
+                    }
+                    Edge::Open => {}
+                }
+            }
+        }
+        map.possible_polygon = vec![];
+
+        if let Some(sector) = sector_id.and_then(|id| map.find_sector_mut(id)) {
+            sector
+                .properties
+                .set("floor_height", Value::Float(self.floor_height));
+            sector
+                .properties
+                .set("ceiling_height", Value::Float(self.ceiling_height));
+
+            let floor = if cell == Cell::Street {
+                &tiles.street
+            } else {
+                &tiles.floor
+            };
+            if let Some(tile) = pick(floor, rng) {
+                sector.properties.set("source", tile);
+            }
+            if !town || matches!(cell, Cell::House(_)) {
+                if let Some(tile) = pick(&tiles.ceiling, rng) {
+                    sector.properties.set("ceiling_source", tile);
+                }
+            }
+        }
+    }
+
+    /// Converts grid coordinates into map coordinates, the grid is centered.
+    fn to_map(&self, grid: &Grid, x: f32, y: f32) -> Vec2<f32> {
+        let cell = self.cell_size.max(0.1);
+        Vec2::new(
+            (x - (grid.width / 2) as f32) * cell,
+            (y - (grid.height / 2) as f32) * cell,
+        )
+    }
+
+    /// The instance position at the center of the cell.
+    fn cell_position(&self, grid: &Grid, (x, y): (i32, i32)) -> Vec3<f32> {
+        let p = self.to_map(grid, x as f32 + 0.5, y as f32 + 0.5);
+        Vec3::new(p.x, self.floor_height + 1.5, p.y)
+    }
+}
+
+/// Splits the area until it fits a room, connecting the rooms of both halves.
+/// Returns the index of a room of the area.
+fn split_bsp(
+    area: Rect,
+    room_min: i32,
+    room_max: i32,
+    rng: &mut StdRng,
+    rooms: &mut Vec<Rect>,
+    connections: &mut Vec<(usize, usize)>,
+) -> Option<usize> {
+    let min_part = room_min + 2;
+    let split_x = area.w > room_max + 2 && area.w >= min_part * 2;
+    let split_y = area.h > room_max + 2 && area.h >= min_part * 2;
+
+    if split_x || split_y {
+        let vertical = if split_x && split_y {
+            area.w >= area.h
+        } else {
+            split_x
+        };
+        let (a, b) = if vertical {
+            let at = rng.random_range(min_part..=area.w - min_part);
+            (
+                Rect::new(area.x, area.y, at, area.h),
+                Rect::new(area.x + at, area.y, area.w - at, area.h),
+            )
+        } else {
+            let at = rng.random_range(min_part..=area.h - min_part);
+            (
+                Rect::new(area.x, area.y, area.w, at),
+                Rect::new(area.x, area.y + at, area.w, area.h - at),
+            )
+        };
+        let room_a = split_bsp(a, room_min, room_max, rng, rooms, connections);
+        let room_b = split_bsp(b, room_min, room_max, rng, rooms, connections);
+        if let (Some(room_a), Some(room_b)) = (room_a, room_b) {
+            connections.push((room_a, room_b));
+        }
+        return room_a.or(room_b);
+    }
+
+    // A leaf, place a room with a margin of one cell
+    let max_w = (area.w - 2).min(room_max);
+    let max_h = (area.h - 2).min(room_max);
+    if max_w < 2 || max_h < 2 {
+        return None;
+    }
+    let w = rng.random_range(room_min.min(max_w)..=max_w);
+    let h = rng.random_range(room_min.min(max_h)..=max_h);
+    let x = rng.random_range(area.x + 1..=area.x + area.w - w - 1);
+    let y = rng.random_range(area.y + 1..=area.y + area.h - h - 1);
+    rooms.push(Rect::new(x, y, w, h));
+    Some(rooms.len() - 1)
+}
+
+/// Splits a town block into lots.
+fn split_lots(block: Rect, lot_min: i32, rng: &mut StdRng, lots: &mut Vec<Rect>) {
+    let split_x = block.w >= lot_min * 2 && (block.w > lot_min * 2 || rng.random_bool(0.5));
+    let split_y = block.h >= lot_min * 2 && (block.h > lot_min * 2 || rng.random_bool(0.5));
+
+    if split_x && (!split_y || block.w >= block.h) {
+        let at = rng.random_range(lot_min..=block.w - lot_min);
+        split_lots(Rect::new(block.x, block.y, at, block.h), lot_min, rng, lots);
+        split_lots(
+            Rect::new(block.x + at, block.y, block.w - at, block.h),
+            lot_min,
+            rng,
+            lots,
+        );
+    } else if split_y {
+        let at = rng.random_range(lot_min..=block.h - lot_min);
+        split_lots(Rect::new(block.x, block.y, block.w, at), lot_min, rng, lots);
+        split_lots(
+            Rect::new(block.x, block.y + at, block.w, block.h - at),
+            lot_min,
+            rng,
+            lots,
+        );
+    } else {
+        lots.push(block);
+    }
+}
+
+/// A door where the corridor leads away from the room, a wall where it only
+/// passes alongside.
+fn corridor_edge(grid: &Grid, (x, y): (i32, i32), away: (i32, i32)) -> Edge {
+    let beyond = grid.get(x + away.0, y + away.1) == Cell::Corridor;
+    let side_a = grid.get(x + away.1, y + away.0) == Cell::Corridor;
+    let side_b = grid.get(x - away.1, y - away.0) == Cell::Corridor;
+    if beyond || !(side_a && side_b) {
+        Edge::Door
+    } else {
+        Edge::Wall
+    }
+}
+
+/// Carves an L-shaped corridor between two cells. Room cells are kept.
+fn carve_corridor(
+    grid: &mut Grid,
+    from: (i32, i32),
+    to: (i32, i32),
+    width: i32,
+    horizontal_first: bool,
+) {
+    let corner = if horizontal_first {
+        (to.0, from.1)
+    } else {
+        (from.0, to.1)
+    };
+    for (a, b) in [(from, corner), (corner, to)] {
+        let (x0, x1) = (a.0.min(b.0), a.0.max(b.0));
+        let (y0, y1) = (a.1.min(b.1), a.1.max(b.1));
+        for y in y0..=y1 + width - 1 {
+            for x in x0..=x1 + width - 1 {
+                if grid.get(x, y) == Cell::Empty {
+                    grid.set(x, y, Cell::Corridor);
+                }
+            }
+        }
+    }
+}
+
+/// Greedily merges the cells of the given kind into rectangles.
+fn merge_cells(grid: &Grid, kind: Cell) -> Vec<Rect> {
+    let mut used = vec![false; grid.cells.len()];
+    let free = |used: &[bool], x: i32, y: i32| {
+        grid.get(x, y) == kind && !used[(y * grid.width + x) as usize]
+    };
+
+    let mut rects = vec![];
+    for y in 0..grid.height {
+        for x in 0..grid.width {
+            if !free(&used, x, y) {
+                continue;
+            }
+            let mut w = 1;
+            while x + w < grid.width && free(&used, x + w, y) {
+                w += 1;
+            }
+            let mut h = 1;
+            while y + h < grid.height && (x..x + w).all(|cx| free(&used, cx, y + h)) {
+                h += 1;
+            }
+            for cy in y..y + h {
+                for cx in x..x + w {
+                    used[(cy * grid.width + cx) as usize] = true;
+                }
+            }
+            rects.push(Rect::new(x, y, w, h));
+        }
+    }
+    rects
+}
+
+/// The boundary cells of the rect in order around it and the direction to
+/// the outside.
+fn perimeter(rect: &Rect) -> Vec<(i32, i32, (i32, i32))> {
+    let mut cells = vec![];
+    for x in rect.x..rect.x + rect.w {
+        cells.push((x, rect.y, (0, -1)));
+    }
+    for y in rect.y..rect.y + rect.h {
+        cells.push((rect.x + rect.w - 1, y, (1, 0)));
+    }
+    for x in (rect.x..rect.x + rect.w).rev() {
+        cells.push((x, rect.y + rect.h - 1, (0, 1)));
+    }
+    for y in (rect.y..rect.y + rect.h).rev() {
+        cells.push((rect.x, y, (-1, 0)));
+    }
+    cells
+}
+
+/// The grid point where the boundary edge of the cell starts when walking
+/// around the rect.
+fn edge_start(x: i32, y: i32, dir: (i32, i32)) -> (i32, i32) {
+    match dir {
+        (0, -1) => (x, y),
+        (1, 0) => (x + 1, y),
+        (0, 1) => (x + 1, y + 1),
+        _ => (x, y + 1),
+    }
+}
+
+struct TilePicker {
+    floor: Vec<Uuid>,
+    wall: Vec<Uuid>,
+    door: Vec<Uuid>,
+    ceiling: Vec<Uuid>,
+    street: Vec<Uuid>,
+}
+
+/// The tiles which have all of the comma separated tags.
+fn tiles_by_tags(project: &Project, tags: &str) -> Vec<Uuid> {
+    let tags: Vec<String> = tags
+        .split(',')
+        .map(|t| t.trim().to_lowercase())
+        .filter(|t| !t.is_empty())
+        .collect();
+    if tags.is_empty() {
+        return vec![];
+    }
+    project
+        .tiles
+        .values()
+        .filter(|tile| {
+            let tile_tags: Vec<String> = tile
+                .tags
+                .split(',')
+                .map(|t| t.trim().to_lowercase())
+                .collect();
+            tags.iter().all(|t| tile_tags.contains(t))
+        })
+        .map(|tile| tile.id)
+        .collect()
+}
+
+fn pick(tiles: &[Uuid], rng: &mut StdRng) -> Option<Value> {
+    if tiles.is_empty() {
+        None
+    } else {
+        let id = tiles[rng.random_range(0..tiles.len())];
+        Some(Value::Source(PixelSource::TileId(id)))
+    }
+}
+
+fn resolve_templates(
+    list: &[SpawnEntry],
+    find: impl Fn(&str) -> Option<(Uuid, String)>,
+) -> Vec<(Uuid, String, u32)> {
+    list.iter()
+        .filter(|e| e.weight > 0)
+        .filter_map(|e| find(&e.template).map(|(id, name)| (id, name, e.weight)))
+        .collect()
+}
+
+fn pick_weighted(templates: &[(Uuid, String, u32)], rng: &mut StdRng) -> Option<(Uuid, String)> {
+    let total: u32 = templates.iter().map(|t| t.2).sum();
+    if total == 0 {
+        return None;
+    }
+    let mut roll = rng.random_range(0..total);
+    for (id, name, weight) in templates {
+        if roll < *weight {
+            return Some((*id, name.clone()));
+        }
+        roll -= weight;
+    }
+    None
+}
+
+fn take_cell(cells: &mut Vec<(i32, i32)>, rng: &mut StdRng) -> Option<(i32, i32)> {
+    if cells.is_empty() {
+        None
+    } else {
+        Some(cells.swap_remove(rng.random_range(0..cells.len())))
+    }
+}
@@ -0,0 +1,438 @@
+use crate::replay::ReplayInput;
+use indexmap::IndexMap;
+use theframework::prelude::*;
+
+/// The user event sent when an action is pressed, the value is the action name.
+pub const ACTION_DOWN: &str = "action_down";
+/// The user event sent when an action is released, the value is the action name.
+pub const ACTION_UP: &str = "action_up";
+/// The action which opens the rebinding screen of the client. It is handled by
+/// the client and not sent to the scripts.
+pub const BINDINGS_MENU_ACTION: &str = "bindings_menu";
+
+/// The default gamepad stick deflection which counts as a press.
+pub const DEFAULT_STICK_THRESHOLD: f32 = 0.5;
+
+/// The names of the gamepad buttons. The sticks are split into virtual buttons
+/// per direction.
+pub const GAMEPAD_BUTTONS: [&str; 24] = [
+    "south",
+    "east",
+    "west",
+    "north",
+    "dpad_up",
+    "dpad_down",
+    "dpad_left",
+    "dpad_right",
+    "left_shoulder",
+    "right_shoulder",
+    "left_trigger",
+    "right_trigger",
+    "select",
+    "start",
+    "left_stick",
+    "right_stick",
+    "left_stick_up",
+    "left_stick_down",
+    "left_stick_left",
+    "left_stick_right",
+    "right_stick_up",
+    "right_stick_down",
+    "right_stick_left",
+    "right_stick_right",
+];
+
+/// A key or gamepad button.
+#[derive(Clone, PartialEq, Eq, Hash, Debug)]
+pub enum InputSource {
+    /// A key, either a lower case character or the name of a special key.
+    Key(String),
+    /// One of the `GAMEPAD_BUTTONS`.
+    Gamepad(String),
+}
+
+impl InputSource {
+    /// The source of a typed character.
+    pub fn from_char(c: char) -> Self {
+        if c == ' ' {
+            InputSource::Key("space".into())
+        } else {
+            InputSource::Key(c.to_lowercase().to_string())
+        }
+    }
+
+    /// The source of a special key.
+    pub fn from_key_code(code: &TheKeyCode) -> Option<Self> {
+        let name = match code {
+            TheKeyCode::Escape => "escape",
+            TheKeyCode::Return => "return",
+            TheKeyCode::Delete => "delete",
+            TheKeyCode::Up => "up",
+            TheKeyCode::Down => "down",
+            TheKeyCode::Left => "left",
+            TheKeyCode::Right => "right",
+            TheKeyCode::Space => "space",
+            TheKeyCode::Tab => "tab",
+            _ => return None,
+        };
+        Some(InputSource::Key(name.into()))
+    }
+
+    /// The name shown to the player.
+    pub fn display_name(&self) -> String {
+        match self {
+            InputSource::Key(key) => display_name(key),
+            InputSource::Gamepad(button) => format!("Pad {}", display_name(button)),
+        }
+    }
+}
+
+/// Upper cases a key name and turns `left_stick_up` into `Left Stick Up`.
+fn display_name(name: &str) -> String {
+    name.split('_')
+        .map(|word| {
+            let mut chars = word.chars();
+            match chars.next() {
+                Some(first) => first.to_uppercase().chain(chars).collect(),
+                None => String::new(),
+            }
+        })
+        .collect::<Vec<String>>()
+        .join(" ")
+}
+
+/// The keys and gamepad buttons of an action.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Default)]
+pub struct InputBinding {
+    #[serde(default)]
+    pub keys: Vec<String>,
+    #[serde(default)]
+    pub gamepad: Vec<String>,
+}
+
+impl InputBinding {
+    fn contains(&self, source: &InputSource) -> bool {
+        match source {
+            InputSource::Key(key) => self.keys.contains(key),
+            InputSource::Gamepad(button) => self.gamepad.contains(button),
+        }
+    }
+
+    fn remove(&mut self, source: &InputSource) {
+        match source {
+            InputSource::Key(key) => self.keys.retain(|k| k != key),
+            InputSource::Gamepad(button) => self.gamepad.retain(|b| b != button),
+        }
+    }
+
+    /// The bindings as shown to the player, i.e. "W, Up / Pad Dpad Up".
+    pub fn describe(&self) -> String {
+        let keys: Vec<String> = self
+            .keys
+            .iter()
+            .map(|k| InputSource::Key(k.clone()).display_name())
+            .collect();
+        let buttons: Vec<String> = self
+            .gamepad
+            .iter()
+            .map(|b| InputSource::Gamepad(b.clone()).display_name())
+            .collect();
+        match (keys.is_empty(), buttons.is_empty()) {
+            (true, true) => "-".into(),
+            (false, true) => keys.join(", "),
+            (true, false) => buttons.join(", "),
+            (false, false) => format!("{} / {}", keys.join(", "), buttons.join(", ")),
+        }
+    }
+}
+
+/// The named input actions of the game, read from `[input.actions]` of the game
+/// config, with the bindings changed by the player on top.
+#[derive(Clone, Debug, PartialEq)]
+pub struct InputMap {
+    pub actions: IndexMap<String, InputBinding>,
+    /// The stick deflection which counts as a press of a stick direction.
+    pub stick_threshold: f32,
+
+    /// The bindings of the game config.
+    defaults: IndexMap<String, InputBinding>,
+}
+
+impl Default for InputMap {
+    fn default() -> Self {
+        Self {
+            actions: IndexMap::default(),
+            stick_threshold: DEFAULT_STICK_THRESHOLD,
+            defaults: IndexMap::default(),
+        }
+    }
+}
+
+impl InputMap {
+    /// Reads the actions of the game config.
+    pub fn from_config(config: &str) -> Self {
+        let mut map = Self::default();
+        let Ok(table) = config.parse::<toml::Table>() else {
+            return map;
+        };
+        let Some(input) = table.get("input").and_then(|i| i.as_table()) else {
+            return map;
+        };
+        if let Some(threshold) = input.get("stick_threshold").and_then(|t| t.as_float()) {
+            map.stick_threshold = (threshold as f32).clamp(0.05, 0.95);
+        }
+        if let Some(actions) = input.get("actions").and_then(|a| a.as_table()) {
+            for (name, binding) in actions {
+                if let Ok(binding) = binding.clone().try_into::<InputBinding>() {
+                    map.actions.insert(name.clone(), binding);
+                }
+            }
+        }
+        map.defaults = map.actions.clone();
+        map
+    }
+
+    /// True if the game defines no actions, all keys are sent as raw key events.
+    pub fn is_empty(&self) -> bool {
+        self.actions.is_empty()
+    }
+
+    /// The actions bound to the source.
+    pub fn actions_for(&self, source: &InputSource) -> Vec<String> {
+        self.actions
+            .iter()
+            .filter(|(_, binding)| binding.contains(source))
+            .map(|(name, _)| name.clone())
+            .collect()
+    }
+
+    /// Binds the source as the primary key or button of the action. The source
+    /// is removed from all other actions.
+    pub fn bind(&mut self, action: &str, source: &InputSource) {
+        if !self.actions.contains_key(action) {
+            return;
+        }
+        for binding in self.actions.values_mut() {
+            binding.remove(source);
+        }
+        if let Some(binding) = self.actions.get_mut(action) {
+            match source {
+                InputSource::Key(key) => binding.keys.insert(0, key.clone()),
+                InputSource::Gamepad(button) => binding.gamepad.insert(0, button.clone()),
+            }
+        }
+    }
+
+    /// Restores the bindings of the game config for the action.
+    pub fn reset(&mut self, action: &str) {
+        if let (Some(binding), Some(default)) =
+            (self.actions.get_mut(action), self.defaults.get(action))
+        {
+            *binding = default.clone();
+        }
+    }
+
+    /// Applies the bindings changed by the player, as written by `overrides`.
+    pub fn apply_overrides(&mut self, text: &str) {
+        let Ok(table) = text.parse::<toml::Table>() else {
+            return;
+        };
+        for (name, binding) in table {
+            if let (Some(current), Ok(binding)) = (
+                self.actions.get_mut(&name),
+                binding.try_into::<InputBinding>(),
+            ) {
+                *current = binding;
+            }
+        }
+    }
+
+    /// The bindings which differ from the game config, as TOML.
+    pub fn overrides(&self) -> String {
+        let mut table = toml::Table::new();
+        for (name, binding) in &self.actions {
+            if self.defaults.get(name) != Some(binding) {
+                if let Ok(value) = toml::Value::try_from(binding) {
+                    table.insert(name.clone(), value);
+                }
+            }
+        }
+        toml::to_string(&table).unwrap_or_default()
+    }
+}
+
+/// Turns the presses and releases of keys and buttons into action events.
+/// An action is down while any of its sources is held.
+#[derive(Default, Clone, Debug)]
+pub struct InputState {
+    held: IndexMap<InputSource, Vec<String>>,
+}
+
+impl InputState {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The actions which are currently down.
+    fn active(&self) -> Vec<String> {
+        let mut active: Vec<String> = vec![];
+        for actions in self.held.values() {
+            for action in actions {
+                if !active.contains(action) {
+                    active.push(action.clone());
+                }
+            }
+        }
+        active
+    }
+
+    /// A source was pressed. Returns the user events to send. Keys which are
+    /// not bound to an action are sent as `key_down`.
+    pub fn press(&mut self, map: &InputMap, source: InputSource) -> Vec<ReplayInput> {
+        if self.held.contains_key(&source) {
+            return vec![];
+        }
+        let actions = map.actions_for(&source);
+        if actions.is_empty() {
+            return match source {
+                InputSource::Key(key) => vec![ReplayInput::UserEvent("key_down".into(), key)],
+                InputSource::Gamepad(_) => vec![],
+            };
+        }
+
+        let before = self.active();
+        self.held.insert(source, actions.clone());
+        actions
+            .into_iter()
+            .filter(|action| !before.contains(action) && action != BINDINGS_MENU_ACTION)
+            .map(|action| ReplayInput::UserEvent(ACTION_DOWN.into(), action))
+            .collect()
+    }
+
+    /// A source was released. Returns the user events to send.
+    pub fn release(&mut self, map: &InputMap, source: InputSource) -> Vec<ReplayInput> {
+        let Some(actions) = self.held.shift_remove(&source) else {
+            return match source {
+                InputSource::Key(key) if map.actions_for(&source).is_empty() => {
+                    vec![ReplayInput::UserEvent("key_up".into(), key)]
+                }
+                _ => vec![],
+            };
+        };
+
+        let after = self.active();
+        actions
+            .into_iter()
+            .filter(|action| !after.contains(action) && action != BINDINGS_MENU_ACTION)
+            .map(|action| ReplayInput::UserEvent(ACTION_UP.into(), action))
+            .collect()
+    }
+
+    /// Releases all held sources, i.e. when the rebinding screen opens.
+    pub fn release_all(&mut self) -> Vec<ReplayInput> {
+        let active = self.active();
+        self.held.clear();
+        active
+            .into_iter()
+            .filter(|action| action != BINDINGS_MENU_ACTION)
+            .map(|action| ReplayInput::UserEvent(ACTION_UP.into(), action))
+            .collect()
+    }
+
+    /// True if the source is bound to the rebinding screen action.
+    pub fn opens_bindings_menu(map: &InputMap, source: &InputSource) -> bool {
+        map.actions
+            .get(BINDINGS_MENU_ACTION)
+            .is_some_and(|binding| binding.contains(source))
+    }
+}
+
+/// The player facing rebinding screen: lists the actions with their bindings,
+/// the selected action is rebound to the next pressed key or button.
+#[derive(Default, Clone, Debug)]
+pub struct BindingsMenu {
+    pub open: bool,
+    pub selected: usize,
+    /// Waiting for the key or button to bind to the selected action.
+    pub capturing: bool,
+}
+
+impl BindingsMenu {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn open(&mut self) {
+        self.open = true;
+        self.selected = 0;
+        self.capturing = false;
+    }
+
+    /// Handles a pressed source while the menu is open. Up / down select, return
+    /// or the south button starts capturing, delete or the west button restores
+    /// the default and escape or the east button closes the menu. Returns true
+    /// if the bindings were changed.
+    pub fn press(&mut self, map: &mut InputMap, source: &InputSource) -> bool {
+        let count = map.actions.len();
+        if count == 0 {
+            self.open = false;
+            return false;
+        }
+
+        if self.capturing {
+            self.capturing = false;
+            if *source == InputSource::Key("escape".into()) {
+                return false;
+            }
+            if let Some(action) = map.actions.keys().nth(self.selected).cloned() {
+                map.bind(&action, source);
+                return true;
+            }
+            return false;
+        }
+
+        let name = match source {
+            InputSource::Key(key) => key.as_str(),
+            InputSource::Gamepad(button) => button.as_str(),
+        };
+        match name {
+            "up" | "dpad_up" | "left_stick_up" => {
+                self.selected = (self.selected + count - 1) % count;
+            }
+            "down" | "dpad_down" | "left_stick_down" => {
+                self.selected = (self.selected + 1) % count;
+            }
+            "return" | "south" => self.capturing = true,
+            "delete" | "west" => {
+                if let Some(action) = map.actions.keys().nth(self.selected).cloned() {
+                    map.reset(&action);
+                    return true;
+                }
+            }
+            "escape" | "east" => self.open = false,
+            _ => {
+                if InputState::opens_bindings_menu(map, source) {
+                    self.open = false;
+                }
+            }
+        }
+        false
+    }
+
+    /// The rows of the menu: the action name and its bindings.
+    pub fn rows(&self, map: &InputMap) -> Vec<(String, String)> {
+        map.actions
+            .iter()
+            .map(|(name, binding)| (display_name(name), binding.describe()))
+            .collect()
+    }
+
+    /// The help line at the bottom of the menu.
+    pub fn help(&self) -> &'static str {
+        if self.capturing {
+            "Press a key or button to bind, Escape to cancel."
+        } else {
+            "Up / Down: select   Return: rebind   Delete: reset   Escape: close"
+        }
+    }
+}
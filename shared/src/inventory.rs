@@ -0,0 +1,742 @@
+use crate::dialogue::value_text;
+use crate::prelude::*;
+use rusterix::{Entity, Value};
+use theframework::prelude::*;
+
+/// The name of the built-in script library with the inventory helpers.
+pub const INVENTORY_LIBRARY: &str = "inventory";
+/// The user event sent to the player to equip the item with the id.
+pub const INVENTORY_EQUIP: &str = "inventory_equip";
+/// The user event sent to the player to unequip the item of the slot.
+pub const INVENTORY_UNEQUIP: &str = "inventory_unequip";
+/// The user event sent to the player to drop the item with the id.
+pub const INVENTORY_DROP: &str = "inventory_drop";
+/// The user event sent to the player to set the hotbar, the value is the comma
+/// separated item names of the slots.
+pub const INVENTORY_HOTBAR: &str = "inventory_hotbar";
+/// The user event sent to the player when a hotbar slot is used, the value is
+/// the item id.
+pub const HOTBAR_USE: &str = "hotbar_use";
+/// The player attribute with the item names of the hotbar slots, comma
+/// separated.
+pub const HOTBAR: &str = "hotbar";
+
+/// The source of the built-in `inventory` library. The screen widgets move the
+/// items, the player script carries the moves out.
+pub const INVENTORY_SOURCE: &str = r##"# Built-in inventory helpers for the inventory, equipment, hotbar and tooltip
+# screen widgets. Call inventory_user_event(event, value) from user_event() of
+# the player, it equips, unequips and drops the items moved by drag and drop
+# and stores the hotbar. Hotbar slots send ("hotbar_use", item_id), handle it
+# to use the item.
+
+def inventory_user_event(event, value):
+    if event == "inventory_equip":
+        equip(int(value))
+        return True
+    if event == "inventory_unequip":
+        unequip(str(value))
+        return True
+    if event == "inventory_drop":
+        drop(int(value))
+        return True
+    if event == "inventory_hotbar":
+        set_attr("hotbar", str(value))
+        return True
+    return False
+"##;
+
+/// The widget roles handled by the host.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum InventoryRole {
+    /// A grid with the inventory slots of the player.
+    Inventory,
+    /// The gear and weapon slots arranged as a paper doll.
+    Equipment,
+    /// Quick slots which use items by click or the `hotbar_<n>` actions.
+    Hotbar,
+    /// The details of the selected item.
+    Tooltip,
+}
+
+impl InventoryRole {
+    pub fn from_role(role: &str) -> Option<Self> {
+        match role {
+            "inventory" => Some(Self::Inventory),
+            "equipment" => Some(Self::Equipment),
+            "hotbar" => Some(Self::Hotbar),
+            "tooltip" => Some(Self::Tooltip),
+            _ => None,
+        }
+    }
+}
+
+/// The `[ui]` options of the inventory widgets.
+#[derive(Deserialize, Clone, Debug)]
+#[serde(default)]
+struct InventoryStyle {
+    /// The columns of the inventory grid.
+    columns: usize,
+    /// The slots of the hotbar.
+    size: usize,
+    /// The equipment slots, the gear and weapon slots of the config if empty.
+    slots: Vec<String>,
+    /// The item attributes listed by the tooltip.
+    attributes: Vec<String>,
+    font_size: f32,
+    background: String,
+    slot: String,
+    selected: String,
+    text: String,
+    title: String,
+}
+
+impl Default for InventoryStyle {
+    fn default() -> Self {
+        Self {
+            columns: 4,
+            size: 8,
+            slots: vec![],
+            attributes: vec![
+                "slot".into(),
+                "damage".into(),
+                "armor".into(),
+                "worth".into(),
+            ],
+            font_size: 12.0,
+            background: "#141418".into(),
+            slot: "#2a2a32".into(),
+            selected: "#3c4664".into(),
+            text: "#dcdcdc".into(),
+            title: "#f0c878".into(),
+        }
+    }
+}
+
+/// An inventory widget of the start screen.
+#[derive(Clone, Debug)]
+struct InventoryWidget {
+    role: InventoryRole,
+    /// The rectangle in viewport pixels.
+    rect: (f32, f32, f32, f32),
+    anchor: WidgetAnchor,
+    style: InventoryStyle,
+}
+
+/// A slot of an inventory widget.
+#[derive(Clone, Debug, PartialEq)]
+enum Cell {
+    Inventory(usize),
+    Equipment(String),
+    Hotbar(usize),
+}
+
+/// An item of the player as shown by the widgets.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SlotItem {
+    pub id: u32,
+    pub name: String,
+    /// The slot the item is equipped in.
+    pub slot: String,
+    pub tile: Option<Uuid>,
+    /// The attributes listed by the tooltips.
+    pub details: Vec<(String, String)>,
+}
+
+/// The inventory and equipment of the player, read on every update.
+#[derive(Clone, Debug)]
+struct PlayerState {
+    region: Uuid,
+    slots: usize,
+    inventory: Vec<SlotItem>,
+    equipped: IndexMap<String, SlotItem>,
+    hotbar: Vec<String>,
+}
+
+/// A move of the player, carried out on the next update.
+/// Draws the inventory, equipment, hotbar and tooltip widgets of the start
+/// screen and handles their drag and drop. The widgets show the server state of
+/// the player, the moves are validated against the slots of the config and
+/// carried out by the player script through the `inventory` library. The moves
+/// are user events which the host sends and records as player input.
+#[derive(Default)]
+pub struct InventoryUi {
+    widgets: Vec<InventoryWidget>,
+    viewport: ScreenViewport,
+    equipment_slots: Vec<String>,
+    player: Option<PlayerState>,
+    /// The cell the pointer went down on.
+    drag: Option<Cell>,
+    /// The item shown by the tooltips.
+    selected: Option<SlotItem>,
+    /// The result of the last invalid move.
+    status: String,
+    inputs: Vec<ReplayInput>,
+    /// The size of the last drawn buffer.
+    size: (usize, usize),
+}
+
+impl InventoryUi {
+    /// Reads the inventory widgets of the start screen, called when the server
+    /// starts.
+    pub fn start(&mut self, project: &Project) {
+        *self = Self {
+            viewport: ScreenViewport::from_config(&project.config),
+            ..Default::default()
+        };
+        let table = project.config.parse::<toml::Table>().unwrap_or_default();
+        let game = table.get("game");
+        for key in ["gear_slots", "weapon_slots"] {
+            if let Some(slots) = game.and_then(|g| g.get(key)).and_then(|s| s.as_array()) {
+                self.equipment_slots
+                    .extend(slots.iter().filter_map(|s| s.as_str()).map(str::to_string));
+            }
+        }
+
+        let start_screen = game
+            .and_then(|g| g.get("start_screen"))
+            .and_then(|s| s.as_str())
+            .unwrap_or_default();
+        let Some(screen) = project
+            .screens
+            .values()
+            .find(|s| !start_screen.is_empty() && s.name == start_screen)
+        else {
+            return;
+        };
+        for sector in &screen.map.sectors {
+            let Some(ui) = widget_ui(sector) else {
+                continue;
+            };
+            let Some(role) = ui
+                .get("role")
+                .and_then(|r| r.as_str())
+                .and_then(InventoryRole::from_role)
+            else {
+                continue;
+            };
+            let style = toml::Value::Table(ui)
+                .try_into::<InventoryStyle>()
+                .unwrap_or_else(|err| {
+                    eprintln!("Invalid inventory widget \"{}\": {}", sector.name, err);
+                    InventoryStyle::default()
+                });
+            self.widgets.push(InventoryWidget {
+                role,
+                rect: self.viewport.widget_rect(&screen.map, sector),
+                anchor: WidgetAnchor::from_sector(sector),
+                style,
+            });
+        }
+    }
+
+    /// Reads the inventory of the player.
+    pub fn update(&mut self, region: &Region) {
+        if self.widgets.is_empty() {
+            return;
+        }
+        let Some(player) = region
+            .map
+            .entities
+            .iter()
+            .find(|e| matches!(e.attributes.get("player"), Some(Value::Bool(true))))
+        else {
+            if self.player.as_ref().is_some_and(|p| p.region == region.id) {
+                self.player = None;
+                self.drag = None;
+            }
+            return;
+        };
+        self.player = Some(self.read_player(region.id, player));
+    }
+
+    /// The user events of the moves since the last call, to be sent and
+    /// recorded as player input.
+    pub fn take_inputs(&mut self) -> Vec<ReplayInput> {
+        std::mem::take(&mut self.inputs)
+    }
+
+    fn read_player(&self, region: Uuid, player: &Entity) -> PlayerState {
+        let keys: Vec<&String> = self
+            .widgets
+            .iter()
+            .filter(|w| w.role == InventoryRole::Tooltip)
+            .flat_map(|w| w.style.attributes.iter())
+            .collect();
+        let slot_item = |item: &rusterix::Item| SlotItem {
+            id: item.id,
+            name: item.attributes.get_str_default("name", "Item".into()),
+            slot: item.attributes.get_str_default("slot", String::new()),
+            tile: item
+                .attributes
+                .get("tile_id")
+                .and_then(|t| Uuid::parse_str(value_text(t).trim()).ok()),
+            details: keys
+                .iter()
+                .filter_map(|key| {
+                    let value = value_text(item.attributes.get(key.as_str())?);
+                    Some((key.to_string(), value))
+                })
+                .collect(),
+        };
+
+        let slots = player
+            .attributes
+            .get("inventory_slots")
+            .map(value_text)
+            .and_then(|s| s.trim().parse::<f32>().ok())
+            .map(|s| s.max(0.0) as usize)
+            .unwrap_or(8);
+        let hotbar_size = self
+            .widgets
+            .iter()
+            .filter(|w| w.role == InventoryRole::Hotbar)
+            .map(|w| w.style.size)
+            .max()
+            .unwrap_or(0);
+        let mut hotbar: Vec<String> = player
+            .attributes
+            .get_str_default(HOTBAR, String::new())
+            .split(',')
+            .map(|name| name.trim().to_string())
+            .collect();
+        hotbar.resize(hotbar_size, String::new());
+
+        PlayerState {
+            region,
+            slots,
+            inventory: player
+                .iter_inventory()
+                .map(|(_, item)| slot_item(item))
+                .collect(),
+            equipped: player
+                .equipped
+                .iter()
+                .map(|(slot, item)| (slot.to_string(), slot_item(item)))
+                .collect(),
+            hotbar,
+        }
+    }
+
+    /// Starts a drag or selects the item at the buffer coordinate. Returns true
+    /// if the coordinate is inside an inventory widget.
+    pub fn press_at(&mut self, coord: Vec2<i32>) -> bool {
+        let Some(cell) = self.hit(coord) else {
+            return false;
+        };
+        self.status.clear();
+        if let Some(cell) = cell {
+            self.selected = self.item_of(&cell);
+            self.drag = Some(cell);
+        }
+        true
+    }
+
+    /// Drops the dragged item at the buffer coordinate. A release on the cell
+    /// the drag started on uses hotbar slots. Returns true if the pointer went
+    /// down on an inventory widget.
+    pub fn release_at(&mut self, coord: Vec2<i32>) -> bool {
+        let Some(from) = self.drag.take() else {
+            return self.hit(coord).is_some();
+        };
+        let Some(player) = self.player.clone() else {
+            return true;
+        };
+        let target = self.hit(coord);
+        if target.as_ref().is_some_and(|t| t.as_ref() == Some(&from)) {
+            if let Cell::Hotbar(index) = from {
+                self.use_hotbar(&player, index);
+            }
+            return true;
+        }
+
+        let item = self.item_of(&from);
+        match (&from, target) {
+            (Cell::Inventory(_), Some(Some(Cell::Equipment(slot)))) => {
+                if let Some(item) = item {
+                    self.equip(&player, &item, &slot);
+                }
+            }
+            (Cell::Inventory(_) | Cell::Equipment(_), Some(Some(Cell::Hotbar(index)))) => {
+                if let Some(item) = item {
+                    let mut hotbar = player.hotbar.clone();
+                    hotbar[index] = item.name;
+                    self.request(INVENTORY_HOTBAR, hotbar.join(","));
+                }
+            }
+            (Cell::Inventory(_), None) => {
+                if let Some(item) = item {
+                    self.request(INVENTORY_DROP, item.id.to_string());
+                }
+            }
+            (Cell::Equipment(slot), Some(Some(Cell::Inventory(_))) | None) => {
+                if player.inventory.len() >= player.slots {
+                    self.status = "Your inventory is full.".into();
+                } else {
+                    self.request(INVENTORY_UNEQUIP, slot.clone());
+                }
+            }
+            (Cell::Equipment(_), Some(Some(Cell::Equipment(slot)))) => {
+                if let Some(item) = item {
+                    self.status = format!("{} can't be equipped in {}.", item.name, slot);
+                }
+            }
+            (Cell::Hotbar(from), Some(Some(Cell::Hotbar(to)))) => {
+                let mut hotbar = player.hotbar.clone();
+                hotbar.swap(*from, to);
+                self.request(INVENTORY_HOTBAR, hotbar.join(","));
+            }
+            (Cell::Hotbar(index), None) => {
+                let mut hotbar = player.hotbar.clone();
+                hotbar[*index].clear();
+                self.request(INVENTORY_HOTBAR, hotbar.join(","));
+            }
+            _ => {}
+        }
+        true
+    }
+
+    /// Uses the hotbar slot bound to the `hotbar_<n>` action of the source.
+    pub fn press(&mut self, map: &InputMap, source: &InputSource) -> bool {
+        let Some(player) = self.player.clone() else {
+            return false;
+        };
+        let index = map.actions_for(source).iter().find_map(|action| {
+            action
+                .strip_prefix("hotbar_")
+                .and_then(|n| n.parse::<usize>().ok())
+                .filter(|n| *n >= 1 && *n <= player.hotbar.len())
+        });
+        match index {
+            Some(n) => {
+                self.use_hotbar(&player, n - 1);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Equips the item if the slot fits, the item in the slot goes back to the
+    /// inventory.
+    fn equip(&mut self, player: &PlayerState, item: &SlotItem, slot: &str) {
+        if item.slot.is_empty() {
+            self.status = format!("{} can't be equipped.", item.name);
+            return;
+        }
+        if item.slot != slot || !self.equipment_slots.iter().any(|s| s == slot) {
+            self.status = format!("{} can't be equipped in {}.", item.name, slot);
+            return;
+        }
+        if player.equipped.contains_key(slot) {
+            if player.inventory.len() >= player.slots {
+                self.status = "Your inventory is full.".into();
+                return;
+            }
+            self.request(INVENTORY_UNEQUIP, slot.to_string());
+        }
+        self.request(INVENTORY_EQUIP, item.id.to_string());
+    }
+
+    fn use_hotbar(&mut self, player: &PlayerState, index: usize) {
+        let Some(name) = player.hotbar.get(index).filter(|n| !n.is_empty()) else {
+            return;
+        };
+        match player.inventory.iter().find(|i| &i.name == name) {
+            Some(item) => {
+                self.selected = Some(item.clone());
+                self.request(HOTBAR_USE, item.id.to_string());
+            }
+            None => self.status = format!("No {} left.", name),
+        }
+    }
+
+    fn request(&mut self, event: &str, value: String) {
+        self.inputs
+            .push(ReplayInput::UserEvent(event.to_string(), value));
+    }
+
+    fn item_of(&self, cell: &Cell) -> Option<SlotItem> {
+        let player = self.player.as_ref()?;
+        match cell {
+            Cell::Inventory(index) => player.inventory.get(*index).cloned(),
+            Cell::Equipment(slot) => player.equipped.get(slot).cloned(),
+            Cell::Hotbar(index) => {
+                let name = player.hotbar.get(*index)?;
+                player.inventory.iter().find(|i| &i.name == name).cloned()
+            }
+        }
+    }
+
+    /// The cell at the buffer coordinate, Some(None) inside a widget but not on
+    /// a cell, None outside of all widgets.
+    fn hit(&self, coord: Vec2<i32>) -> Option<Option<Cell>> {
+        let (x, y) = (coord.x as f32, coord.y as f32);
+        let mut inside = false;
+        for widget in &self.widgets {
+            let (wx, wy, ww, wh) = self.buffer_rect(widget);
+            if x < wx || y < wy || x >= wx + ww || y >= wy + wh {
+                continue;
+            }
+            inside = true;
+            for (cell, (cx, cy, cw, ch)) in self.cells(widget) {
+                if x >= cx && y >= cy && x < cx + cw && y < cy + ch {
+                    return Some(Some(cell));
+                }
+            }
+        }
+        inside.then_some(None)
+    }
+
+    /// The rectangle of the widget in buffer pixels, placed by its anchor.
+    fn buffer_rect(&self, widget: &InventoryWidget) -> (f32, f32, f32, f32) {
+        let layout = self.viewport.layout(self.size);
+        layout.to_output(
+            self.viewport
+                .place(widget.rect, &widget.anchor, layout.size),
+        )
+    }
+
+    /// The cells of the widget with their rectangles in buffer pixels.
+    fn cells(&self, widget: &InventoryWidget) -> Vec<(Cell, (f32, f32, f32, f32))> {
+        let (x, y, w, h) = self.buffer_rect(widget);
+        let (cells, columns, rows): (Vec<(Cell, usize, usize)>, usize, usize) = match widget.role {
+            InventoryRole::Inventory => {
+                let slots = self.player.as_ref().map(|p| p.slots).unwrap_or(8);
+                let columns = widget.style.columns.max(1);
+                let cells = (0..slots)
+                    .map(|i| (Cell::Inventory(i), i % columns, i / columns))
+                    .collect();
+                (cells, columns, slots.div_ceil(columns))
+            }
+            InventoryRole::Equipment => {
+                let slots = if widget.style.slots.is_empty() {
+                    &self.equipment_slots
+                } else {
+                    &widget.style.slots
+                };
+                let cells = paper_doll(slots);
+                let rows = cells.iter().map(|(_, _, r)| r + 1).max().unwrap_or(0);
+                (cells, 3, rows)
+            }
+            InventoryRole::Hotbar => {
+                let size = widget.style.size;
+                let cells = (0..size).map(|i| (Cell::Hotbar(i), i, 0)).collect();
+                (cells, size, 1)
+            }
+            InventoryRole::Tooltip => (vec![], 1, 1),
+        };
+        if columns == 0 || rows == 0 {
+            return vec![];
+        }
+        let size = (w / columns as f32).min(h / rows as f32).floor();
+        let ox = x + (w - size * columns as f32) / 2.0;
+        let oy = y + (h - size * rows as f32) / 2.0;
+        cells
+            .into_iter()
+            .map(|(cell, column, row)| {
+                (
+                    cell,
+                    (
+                        ox + column as f32 * size + 2.0,
+                        oy + row as f32 * size + 2.0,
+                        size - 4.0,
+                        size - 4.0,
+                    ),
+                )
+            })
+            .collect()
+    }
+
+    /// Draws the widgets on top of the game.
+    pub fn draw(
+        &mut self,
+        buffer: &mut TheRGBABuffer,
+        ctx: &mut TheContext,
+        tiles: &IndexMap<Uuid, rusterix::Tile>,
+    ) {
+        let dim = buffer.dim();
+        self.size = (dim.width as usize, dim.height as usize);
+        let Some(player) = &self.player else {
+            return;
+        };
+        let stride = buffer.stride();
+        let bounds = (self.size.0 as f32, self.size.1 as f32);
+        let clip = |(x, y, w, h): (f32, f32, f32, f32)| {
+            let x0 = x.max(0.0).min(bounds.0);
+            let y0 = y.max(0.0).min(bounds.1);
+            let x1 = (x + w).max(0.0).min(bounds.0);
+            let y1 = (y + h).max(0.0).min(bounds.1);
+            (
+                x0 as usize,
+                y0 as usize,
+                (x1 - x0) as usize,
+                (y1 - y0) as usize,
+            )
+        };
+
+        for widget in &self.widgets {
+            let style = &widget.style;
+            let background = TheColor::from_hex(&style.background).to_u8_array();
+            let slot_color = TheColor::from_hex(&style.slot).to_u8_array();
+            let selected_color = TheColor::from_hex(&style.selected).to_u8_array();
+            let text_color = TheColor::from_hex(&style.text).to_u8_array();
+            let title_color = TheColor::from_hex(&style.title).to_u8_array();
+            let font = TheFontSettings {
+                size: style.font_size,
+                ..Default::default()
+            };
+            let rect = clip(self.buffer_rect(widget));
+            if rect.2 == 0 || rect.3 == 0 {
+                continue;
+            }
+            ctx.draw
+                .rect(buffer.pixels_mut(), &rect, stride, &background);
+
+            if widget.role == InventoryRole::Tooltip {
+                let line_h = (style.font_size * 1.5).ceil() as usize;
+                let mut lines: Vec<(String, [u8; 4])> = vec![];
+                if let Some(item) = &self.selected {
+                    lines.push((item.name.clone(), title_color));
+                    for (key, value) in &item.details {
+                        lines.push((format!("{}: {}", key, value), text_color));
+                    }
+                }
+                if !self.status.is_empty() {
+                    lines.push((self.status.clone(), title_color));
+                }
+                for (index, (text, color)) in lines.iter().enumerate() {
+                    let y = rect.1 + 4 + index * line_h;
+                    if y + line_h > rect.1 + rect.3 {
+                        break;
+                    }
+                    ctx.draw.text_rect(
+                        buffer.pixels_mut(),
+                        &(rect.0 + 6, y, rect.2.saturating_sub(12), line_h),
+                        stride,
+                        text,
+                        font.clone(),
+                        color,
+                        &background,
+                    );
+                }
+                continue;
+            }
+
+            for (cell, cell_rect) in self.cells(widget) {
+                let cell_rect = clip(cell_rect);
+                if cell_rect.2 == 0 || cell_rect.3 == 0 {
+                    continue;
+                }
+                let color = if self.drag.as_ref() == Some(&cell) {
+                    &selected_color
+                } else {
+                    &slot_color
+                };
+                ctx.draw
+                    .rect(buffer.pixels_mut(), &cell_rect, stride, color);
+
+                let (item, label) = match &cell {
+                    Cell::Inventory(index) => (player.inventory.get(*index), String::new()),
+                    Cell::Equipment(slot) => (player.equipped.get(slot), slot.replace('_', " ")),
+                    Cell::Hotbar(index) => {
+                        let name = &player.hotbar[*index];
+                        let count = player.inventory.iter().filter(|i| &i.name == name).count();
+                        let item = player.inventory.iter().find(|i| &i.name == name);
+                        let label = if name.is_empty() {
+                            format!("{}", index + 1)
+                        } else {
+                            format!("{} {}", index + 1, count)
+                        };
+                        (item, label)
+                    }
+                };
+                let icon = item
+                    .and_then(|item| item.tile)
+                    .and_then(|tile| tiles.get(&tile))
+                    .and_then(|tile| tile.textures.first());
+                if let Some(texture) = icon {
+                    ctx.draw.blend_scale_chunk(
+                        buffer.pixels_mut(),
+                        &cell_rect,
+                        stride,
+                        &texture.data,
+                        &(texture.width as usize, texture.height as usize),
+                    );
+                } else if let Some(item) = item {
+                    ctx.draw.text_rect(
+                        buffer.pixels_mut(),
+                        &cell_rect,
+                        stride,
+                        &item.name,
+                        font.clone(),
+                        &text_color,
+                        color,
+                    );
+                }
+                if !label.is_empty() && (item.is_none() || matches!(cell, Cell::Hotbar(_))) {
+                    let label_h = (style.font_size * 1.5).ceil() as usize;
+                    ctx.draw.text_rect(
+                        buffer.pixels_mut(),
+                        &(
+                            cell_rect.0,
+                            cell_rect.1 + cell_rect.3.saturating_sub(label_h),
+                            cell_rect.2,
+                            label_h.min(cell_rect.3),
+                        ),
+                        stride,
+                        &label,
+                        font.clone(),
+                        &text_color,
+                        color,
+                    );
+                }
+            }
+        }
+    }
+}
+
+/// The column and row of the equipment slots in a paper doll of three columns.
+/// Known slots have their place on the body, the others fill the free cells.
+fn paper_doll(slots: &[String]) -> Vec<(Cell, usize, usize)> {
+    const PLACES: [(&str, usize, usize); 10] = [
+        ("head", 1, 0),
+        ("neck", 2, 0),
+        ("main_hand", 0, 1),
+        ("torso", 1, 1),
+        ("off_hand", 2, 1),
+        ("hands", 0, 2),
+        ("legs", 1, 2),
+        ("ring", 2, 2),
+        ("feet", 1, 3),
+        ("back", 0, 0),
+    ];
+    let mut cells: Vec<(Cell, usize, usize)> = vec![];
+    let mut others = vec![];
+    for slot in slots {
+        match PLACES.iter().find(|(name, _, _)| name == slot) {
+            Some((_, column, row)) => cells.push((Cell::Equipment(slot.clone()), *column, *row)),
+            None => others.push(slot.clone()),
+        }
+    }
+    let mut index = 0;
+    for slot in others {
+        while cells
+            .iter()
+            .any(|(_, c, r)| *c == index % 3 && *r == index / 3)
+        {
+            index += 1;
+        }
+        cells.push((Cell::Equipment(slot), index % 3, index / 3));
+        index += 1;
+    }
+    cells
+}
+
+/// Turns the data of an inventory widget into a widget without a role, the
+/// host draws it. Returns None for other widgets.
+pub fn inventory_widget_data(data: &str) -> Option<String> {
+    let mut table = data.parse::<toml::Table>().ok()?;
+    let ui = table.get_mut("ui")?.as_table_mut()?;
+    InventoryRole::from_role(ui.get("role")?.as_str()?)?;
+    ui.insert("role".into(), toml::Value::String("none".into()));
+    toml::to_string(&table).ok()
+}
@@ -0,0 +1,218 @@
+use crate::levelimport::*;
+use crate::prelude::*;
+use serde_json::Value as Json;
+use std::path::Path;
+use theframework::prelude::*;
+
+/// Imports an LDtk project (`.ldtk`). Every level becomes a region, tile,
+/// auto and int grid layers with tiles become tile layers and entities become
+/// objects. Levels saved in separate files are loaded as well.
+pub fn import_ldtk(path: &Path) -> Result<ImportedProject, String> {
+    let text = std::fs::read_to_string(path).map_err(|e| e.to_string())?;
+    let root: Json = serde_json::from_str(&text).map_err(|e| e.to_string())?;
+
+    let mut import = ImportedProject::default();
+
+    // Tilesets by uid
+    let mut tileset_index: FxHashMap<i64, usize> = FxHashMap::default();
+    for tileset in array(&root["defs"]["tilesets"]) {
+        let name = string(&tileset["identifier"]);
+        let Some(rel_path) = tileset["relPath"].as_str() else {
+            import
+                .warnings
+                .push(format!("Tileset '{}' has no image and was skipped.", name));
+            continue;
+        };
+
+        let mut imported = ImportedTileset {
+            name,
+            image: resolve_relative_path(path, rel_path),
+            tile_width: int(&tileset["tileGridSize"], 16),
+            tile_height: int(&tileset["tileGridSize"], 16),
+            margin: int(&tileset["padding"], 0),
+            spacing: int(&tileset["spacing"], 0),
+            columns: int(&tileset["__cWid"], 0),
+            tiles: IndexMap::default(),
+        };
+
+        // Enum tags become tile tags, custom data TOML may set tags and blocking
+        for tag in array(&tileset["enumTags"]) {
+            let value = string(&tag["enumValueId"]);
+            for id in array(&tag["tileIds"]).iter().filter_map(|id| id.as_u64()) {
+                let info = imported.tiles.entry(id as u32).or_default();
+                if !info.tags.is_empty() {
+                    info.tags.push(',');
+                }
+                info.tags += &value;
+            }
+        }
+        for data in array(&tileset["customData"]) {
+            let Some(id) = data["tileId"].as_u64() else {
+                continue;
+            };
+            let text = string(&data["data"]);
+            let info = imported.tiles.entry(id as u32).or_default();
+            match text.parse::<toml::Table>() {
+                Ok(table) => {
+                    if let Some(tags) = table.get("tags").and_then(|t| t.as_str()) {
+                        if !info.tags.is_empty() {
+                            info.tags.push(',');
+                        }
+                        info.tags += tags;
+                    }
+                    info.blocking = table.get("blocking").and_then(|b| b.as_bool()) == Some(true);
+                }
+                Err(_) => {
+                    if !info.tags.is_empty() {
+                        info.tags.push(',');
+                    }
+                    info.tags += text.trim();
+                }
+            }
+        }
+
+        if let Some(uid) = tileset["uid"].as_i64() {
+            tileset_index.insert(uid, import.tilesets.len());
+        }
+        import.tilesets.push(imported);
+    }
+
+    let default_grid = int(&root["defaultGridSize"], 16).max(1) as f32;
+
+    for level in array(&root["levels"]) {
+        // Levels of projects with separate level files only hold a reference
+        let external;
+        let level = if level["layerInstances"].is_null() {
+            let Some(rel_path) = level["externalRelPath"].as_str() else {
+                continue;
+            };
+            let level_path = resolve_relative_path(path, rel_path);
+            external = std::fs::read_to_string(&level_path)
+                .map_err(|e| e.to_string())
+                .and_then(|text| serde_json::from_str::<Json>(&text).map_err(|e| e.to_string()))
+                .map_err(|err| format!("Unable to read level '{}': {}", rel_path, err))?;
+            &external
+        } else {
+            level
+        };
+
+        let mut imported = ImportedLevel {
+            name: string(&level["identifier"]),
+            properties: read_fields(&level["fieldInstances"]),
+            ..Default::default()
+        };
+
+        // LDtk lists the top most layer first
+        for layer in array(&level["layerInstances"]).iter().rev() {
+            let name = string(&layer["__identifier"]);
+            let grid = int(&layer["__gridSize"], 16).max(1);
+            let offset_x = int(&layer["__pxTotalOffsetX"], 0);
+            let offset_y = int(&layer["__pxTotalOffsetY"], 0);
+
+            if layer["__type"].as_str() == Some("Entities") {
+                for entity in array(&layer["entityInstances"]) {
+                    let px = &entity["px"];
+                    let pivot = &entity["__pivot"];
+                    let (w, h) = (float(&entity["width"], 0.0), float(&entity["height"], 0.0));
+                    // The position is at the pivot, use the center of the entity
+                    let x = float(&px[0], 0.0) - float(&pivot[0], 0.5) * w + w * 0.5;
+                    let y = float(&px[1], 0.0) - float(&pivot[1], 0.5) * h + h * 0.5;
+
+                    let mut properties = read_fields(&entity["fieldInstances"]);
+                    let name = properties
+                        .remove("name")
+                        .or_else(|| properties.remove("Name"))
+                        .and_then(|n| n.as_str().map(String::from))
+                        .unwrap_or_default();
+
+                    imported.objects.push(ImportedObject {
+                        name,
+                        class: string(&entity["__identifier"]),
+                        x: (x + offset_x as f32) / default_grid,
+                        y: (y + offset_y as f32) / default_grid,
+                        properties,
+                    });
+                }
+                continue;
+            }
+
+            let Some(tileset) = layer["__tilesetDefUid"]
+                .as_i64()
+                .and_then(|uid| tileset_index.get(&uid))
+            else {
+                continue;
+            };
+
+            let mut imported_layer = ImportedLayer {
+                name,
+                cells: vec![],
+            };
+            let tiles = array(&layer["gridTiles"])
+                .iter()
+                .chain(array(&layer["autoLayerTiles"]).iter());
+            for tile in tiles {
+                let Some(id) = tile["t"].as_u64() else {
+                    continue;
+                };
+                imported_layer.cells.push(ImportedCell {
+                    x: (int(&tile["px"][0], 0) + offset_x).div_euclid(grid),
+                    y: (int(&tile["px"][1], 0) + offset_y).div_euclid(grid),
+                    tileset: *tileset,
+                    tile: id as u32,
+                });
+            }
+            imported.layers.push(imported_layer);
+        }
+
+        import.levels.push(imported);
+    }
+
+    Ok(import)
+}
+
+/// Converts LDtk field instances into a TOML table, empty fields are skipped.
+fn read_fields(fields: &Json) -> toml::Table {
+    let mut table = toml::Table::new();
+    for field in array(fields) {
+        if let Some(value) = json_to_toml(&field["__value"]) {
+            table.insert(string(&field["__identifier"]), value);
+        }
+    }
+    table
+}
+
+fn json_to_toml(value: &Json) -> Option<toml::Value> {
+    match value {
+        Json::Null => None,
+        Json::Bool(b) => Some(toml::Value::Boolean(*b)),
+        Json::Number(n) => n
+            .as_i64()
+            .map(toml::Value::Integer)
+            .or_else(|| n.as_f64().map(toml::Value::Float)),
+        Json::String(s) => Some(toml::Value::String(s.clone())),
+        Json::Array(a) => Some(toml::Value::Array(
+            a.iter().filter_map(json_to_toml).collect(),
+        )),
+        Json::Object(o) => Some(toml::Value::Table(
+            o.iter()
+                .filter_map(|(k, v)| json_to_toml(v).map(|v| (k.clone(), v)))
+                .collect(),
+        )),
+    }
+}
+
+fn array(value: &Json) -> &[Json] {
+    value.as_array().map(|a| a.as_slice()).unwrap_or(&[])
+}
+
+fn string(value: &Json) -> String {
+    value.as_str().unwrap_or_default().to_string()
+}
+
+fn int(value: &Json, default: i32) -> i32 {
+    value.as_i64().map(|v| v as i32).unwrap_or(default)
+}
+
+fn float(value: &Json, default: f32) -> f32 {
+    value.as_f64().map(|v| v as f32).unwrap_or(default)
+}
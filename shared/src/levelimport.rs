@@ -0,0 +1,321 @@
+use crate::prelude::*;
+use crate::rusterix_utils::toml_to_value;
+use rusterix::{PixelSource, Value};
+use std::path::{Path, PathBuf};
+use theframework::prelude::*;
+
+/// Custom tile information of an imported tileset.
+#[derive(Clone, Debug, Default)]
+pub struct ImportedTileInfo {
+    /// Comma separated tags, stored as the name of the tile.
+    pub tags: String,
+    pub blocking: bool,
+    /// The local ids of the animation frames.
+    pub frames: Vec<u32>,
+}
+
+/// A tileset of an imported level, cut on a grid from a single image.
+#[derive(Clone, Debug, Default)]
+pub struct ImportedTileset {
+    pub name: String,
+    /// The resolved path of the tileset image.
+    pub image: PathBuf,
+
+    pub tile_width: i32,
+    pub tile_height: i32,
+    pub margin: i32,
+    pub spacing: i32,
+    /// The number of tile columns, 0 derives the columns from the image width.
+    pub columns: i32,
+
+    /// Custom tile information by local tile id.
+    pub tiles: IndexMap<u32, ImportedTileInfo>,
+}
+
+/// A tile of a tile layer. The position is in cells.
+#[derive(Clone, Copy, Debug)]
+pub struct ImportedCell {
+    pub x: i32,
+    pub y: i32,
+    /// The index into the tilesets of the import.
+    pub tileset: usize,
+    /// The local tile id in the tileset.
+    pub tile: u32,
+}
+
+/// A tile layer, the first layer is the bottom most one.
+#[derive(Clone, Debug, Default)]
+pub struct ImportedLayer {
+    pub name: String,
+    pub cells: Vec<ImportedCell>,
+}
+
+/// An object of an object or entity layer. The position is in cells.
+#[derive(Clone, Debug, Default)]
+pub struct ImportedObject {
+    pub name: String,
+    /// The class or entity identifier, used to look up character and item
+    /// templates.
+    pub class: String,
+    pub x: f32,
+    pub y: f32,
+    pub properties: toml::Table,
+}
+
+/// A single level of an import.
+#[derive(Clone, Debug, Default)]
+pub struct ImportedLevel {
+    pub name: String,
+    pub layers: Vec<ImportedLayer>,
+    pub objects: Vec<ImportedObject>,
+    pub properties: toml::Table,
+}
+
+/// The result of parsing a Tiled or LDtk file, independent of the format.
+#[derive(Clone, Debug, Default)]
+pub struct ImportedProject {
+    pub tilesets: Vec<ImportedTileset>,
+    pub levels: Vec<ImportedLevel>,
+    /// Content which was skipped during parsing.
+    pub warnings: Vec<String>,
+}
+
+impl ImportedProject {
+    /// Converts the import into tilemaps and regions. Tile layers become
+    /// rect sectors, objects become character or item instances if their
+    /// class or name matches a template and named vertices otherwise. Custom
+    /// level properties are written to the `[properties]` section of the
+    /// region config, object properties to the `[attributes]` of the
+    /// instance data. `load_image` decodes the tileset images.
+    pub fn build(
+        &mut self,
+        project: &Project,
+        character_source: &str,
+        item_source: &str,
+        load_image: impl Fn(&Path) -> Option<TheRGBABuffer>,
+    ) -> (Vec<Tilemap>, Vec<Region>) {
+        // Without levels (a standalone tileset) all tiles are created,
+        // otherwise the used and the tagged ones.
+        let mut used: Vec<Vec<u32>> = self
+            .tilesets
+            .iter()
+            .map(|t| t.tiles.keys().copied().collect())
+            .collect();
+        for cell in self
+            .levels
+            .iter()
+            .flat_map(|l| &l.layers)
+            .flat_map(|l| &l.cells)
+        {
+            if let Some(tiles) = used.get_mut(cell.tileset) {
+                tiles.push(cell.tile);
+            }
+        }
+
+        let mut tilemaps = vec![];
+        let mut tile_ids: Vec<FxHashMap<u32, Uuid>> =
+            vec![FxHashMap::default(); self.tilesets.len()];
+
+        for (index, tileset) in self.tilesets.iter().enumerate() {
+            let Some(buffer) = load_image(&tileset.image) else {
+                self.warnings.push(format!(
+                    "Unable to load the image of tileset '{}'.",
+                    tileset.name
+                ));
+                continue;
+            };
+            let dim = *buffer.dim();
+
+            let tw = tileset.tile_width.max(1);
+            let th = tileset.tile_height.max(1);
+            let columns = if tileset.columns > 0 {
+                tileset.columns
+            } else {
+                ((dim.width - 2 * tileset.margin + tileset.spacing) / (tw + tileset.spacing)).max(1)
+            };
+            let rows = ((dim.height - 2 * tileset.margin + tileset.spacing)
+                / (th + tileset.spacing))
+                .max(1);
+
+            let region_of = |id: u32| -> Option<TheRGBARegion> {
+                let (col, row) = (id as i32 % columns, id as i32 / columns);
+                if row >= rows {
+                    return None;
+                }
+                Some(TheRGBARegion::new(
+                    (tileset.margin + col * (tw + tileset.spacing)) as usize,
+                    (tileset.margin + row * (th + tileset.spacing)) as usize,
+                    tw as usize,
+                    th as usize,
+                ))
+            };
+
+            let mut ids = if self.levels.is_empty() {
+                (0..(columns * rows) as u32).collect()
+            } else {
+                used[index].clone()
+            };
+            ids.sort_unstable();
+            ids.dedup();
+
+            let mut tilemap = Tilemap::new();
+            tilemap.name = tileset.name.clone();
+            tilemap.grid_size = tw;
+
+            for id in ids {
+                let Some(region) = region_of(id) else {
+                    continue;
+                };
+                let info = tileset.tiles.get(&id).cloned().unwrap_or_default();
+
+                let mut tile = Tile::new();
+                tile.name = info.tags;
+                tile.blocking = info.blocking;
+                tile.sequence.regions.push(region);
+                for frame in info.frames.iter().skip(1) {
+                    if let Some(region) = region_of(*frame) {
+                        tile.sequence.regions.push(region);
+                    }
+                }
+                tile_ids[index].insert(id, tile.id);
+                tilemap.tiles.push(tile);
+            }
+
+            tilemap.buffer = buffer;
+            tilemaps.push(tilemap);
+        }
+
+        let mut regions = vec![];
+        for level in &self.levels {
+            let mut region = Region::new();
+            region.name = level.name.clone();
+            region.map.name = level.name.clone();
+
+            let map = &mut region.map;
+            for (layer_index, layer) in level.layers.iter().enumerate() {
+                let layer_id = (layer_index + 1).min(u8::MAX as usize) as u8;
+                for cell in &layer.cells {
+                    let Some(tile_id) = tile_ids
+                        .get(cell.tileset)
+                        .and_then(|ids| ids.get(&cell.tile))
+                    else {
+                        continue;
+                    };
+                    add_tile_sector(map, cell.x, cell.y, *tile_id, layer_id);
+                }
+            }
+
+            for object in &level.objects {
+                let character = find_template(&project.characters, object, |c| &c.name);
+                let item = find_template(&project.items, object, |i| &i.name);
+                let name = if object.name.is_empty() {
+                    object.class.clone()
+                } else {
+                    object.name.clone()
+                };
+                let position = Vec3::new(object.x, 1.5, object.y);
+
+                if let Some(character_id) = character {
+                    let instance = Character {
+                        character_id,
+                        name,
+                        position,
+                        source: character_source.to_string(),
+                        data: attributes_data(&object.properties),
+                        ..Default::default()
+                    };
+                    region.characters.insert(instance.id, instance);
+                } else if let Some(item_id) = item {
+                    let instance = Item {
+                        item_id,
+                        name,
+                        position,
+                        source: item_source.to_string(),
+                        data: attributes_data(&object.properties),
+                        ..Default::default()
+                    };
+                    region.items.insert(instance.id, instance);
+                } else {
+                    let vertex_id = region.map.add_vertex_at(object.x, object.y);
+                    if let Some(vertex) = region.map.find_vertex_mut(vertex_id) {
+                        vertex.name = name;
+                        for (key, value) in &object.properties {
+                            vertex.properties.set(key, toml_to_value(value));
+                        }
+                    }
+                }
+            }
+
+            if !level.properties.is_empty() {
+                let mut config = toml::Table::new();
+                config.insert(
+                    "properties".into(),
+                    toml::Value::Table(level.properties.clone()),
+                );
+                region.config = toml::to_string(&config).unwrap_or_default();
+            }
+
+            regions.push(region);
+        }
+
+        (tilemaps, regions)
+    }
+}
+
+/// Adds a one cell rect sector with the given tile, like the rect tool does.
+fn add_tile_sector(map: &mut Map, x: i32, y: i32, tile_id: Uuid, layer: u8) {
+    let (x, y) = (x as f32, y as f32);
+    let v0 = map.add_vertex_at(x, y);
+    let v1 = map.add_vertex_at(x, y + 1.0);
+    let v2 = map.add_vertex_at(x + 1.0, y + 1.0);
+    let v3 = map.add_vertex_at(x + 1.0, y);
+
+    map.possible_polygon = vec![];
+    let _ = map.create_linedef(v0, v1);
+    let _ = map.create_linedef(v1, v2);
+    let _ = map.create_linedef(v2, v3);
+    let (_, sector_id) = map.create_linedef(v3, v0);
+
+    if let Some(sector) = sector_id.and_then(|id| map.find_sector_mut(id)) {
+        sector.properties.set("rect", Value::Bool(true));
+        sector
+            .properties
+            .set("source", Value::Source(PixelSource::TileId(tile_id)));
+        sector.layer = Some(layer);
+    }
+}
+
+/// Looks up the template matching the class or, if the class does not
+/// match, the name of the object.
+fn find_template<T>(
+    templates: &IndexMap<Uuid, T>,
+    object: &ImportedObject,
+    name: impl Fn(&T) -> &String,
+) -> Option<Uuid> {
+    [&object.class, &object.name]
+        .into_iter()
+        .filter(|n| !n.is_empty())
+        .find_map(|n| {
+            templates
+                .iter()
+                .find(|(_, t)| name(t).eq_ignore_ascii_case(n))
+                .map(|(id, _)| *id)
+        })
+}
+
+/// The instance data for the given custom properties.
+fn attributes_data(properties: &toml::Table) -> String {
+    if properties.is_empty() {
+        return String::new();
+    }
+    let mut data = toml::Table::new();
+    data.insert("attributes".into(), toml::Value::Table(properties.clone()));
+    toml::to_string(&data).unwrap_or_default()
+}
+
+/// Resolves a path relative to the directory of the given file.
+pub fn resolve_relative_path(file: &Path, relative: &str) -> PathBuf {
+    file.parent()
+        .map(|dir| dir.join(relative))
+        .unwrap_or_else(|| PathBuf::from(relative))
+}
@@ -1,16 +1,35 @@
 pub mod asset;
+pub mod audio;
 pub mod character;
+pub mod combat;
 pub mod context;
+pub mod dialogue;
 pub mod effectwrapper;
 pub mod fx;
+pub mod generator;
+pub mod input;
 pub mod interaction;
+pub mod inventory;
 pub mod item;
+pub mod ldtk;
+pub mod levelimport;
+pub mod library;
+pub mod localization;
+pub mod meshexport;
+pub mod navgrid;
+pub mod prefab;
 pub mod project;
+pub mod prop;
+pub mod quest;
 pub mod region;
 pub mod renderer_utils;
+pub mod replay;
 pub mod rusterix_utils;
 pub mod screen;
+pub mod scripttest;
 pub mod settingscontainer;
+pub mod shop;
+pub mod tiled;
 pub mod tilemap;
 pub mod tileselection;
 
@@ -18,16 +37,32 @@ pub mod prelude {
     pub use ::serde::{Deserialize, Serialize};
 
     pub use crate::asset::*;
+    pub use crate::audio::*;
     pub use crate::character::Character;
+    pub use crate::combat::*;
     pub use crate::context::*;
+    pub use crate::dialogue::*;
     pub use crate::effectwrapper::*;
     pub use crate::fx::*;
+    pub use crate::generator::*;
+    pub use crate::input::*;
     pub use crate::interaction::*;
+    pub use crate::inventory::*;
     pub use crate::item::Item;
+    pub use crate::levelimport::*;
+    pub use crate::library::ScriptLibrary;
+    pub use crate::localization::*;
+    pub use crate::navgrid::*;
+    pub use crate::prefab::*;
     pub use crate::project::{MapMode, Project};
+    pub use crate::prop::*;
+    pub use crate::quest::*;
     pub use crate::region::Region;
     pub use crate::renderer_utils::ray_sphere;
+    pub use crate::replay::*;
     pub use crate::screen::*;
+    pub use crate::scripttest::*;
+    pub use crate::shop::*;
     pub use crate::tilemap::{Tile, Tilemap};
     pub use indexmap::IndexMap;
 }
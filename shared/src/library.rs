@@ -0,0 +1,352 @@
+use crate::audio::{AUDIO_LIBRARY, AUDIO_SOURCE};
+use crate::combat::{COMBAT_LIBRARY, COMBAT_SOURCE};
+use crate::dialogue::{DIALOGUE_LIBRARY, DIALOGUE_SOURCE};
+use crate::generator::{GENERATOR_LIBRARY, GENERATOR_SOURCE};
+use crate::inventory::{INVENTORY_LIBRARY, INVENTORY_SOURCE};
+use crate::navgrid::{NAVIGATION_LIBRARY, NAVIGATION_SOURCE};
+use crate::quest::{QUEST_LIBRARY, QUEST_SOURCE};
+use crate::shop::{SHOP_LIBRARY, SHOP_SOURCE};
+use indexmap::IndexMap;
+use theframework::prelude::*;
+
+/// A project level script library. Libraries hold shared helper functions and
+/// base classes which can be imported by character, item and widget classes via
+/// `from <library> import ...` or `import <library> [as <alias>]`.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct ScriptLibrary {
+    pub id: Uuid,
+    pub name: String,
+
+    /// The library source.
+    #[serde(default)]
+    pub source: String,
+}
+
+impl Default for ScriptLibrary {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ScriptLibrary {
+    pub fn new() -> Self {
+        Self {
+            id: Uuid::new_v4(),
+            name: "new_library".to_string(),
+            source: String::new(),
+        }
+    }
+
+    /// The libraries which ship with the editor. A project library with the same
+    /// name replaces the built-in one.
+    pub fn builtin() -> Vec<ScriptLibrary> {
+        vec![
+            ScriptLibrary {
+                id: Uuid::from_u128(0x6e61_7669_6761_7469_6f6e_0000_0000_0001),
+                name: NAVIGATION_LIBRARY.to_string(),
+                source: NAVIGATION_SOURCE.to_string(),
+            },
+            ScriptLibrary {
+                id: Uuid::from_u128(0x6175_6469_6f00_0000_0000_0000_0000_0001),
+                name: AUDIO_LIBRARY.to_string(),
+                source: AUDIO_SOURCE.to_string(),
+            },
+            ScriptLibrary {
+                id: Uuid::from_u128(0x6469_616c_6f67_7565_0000_0000_0000_0001),
+                name: DIALOGUE_LIBRARY.to_string(),
+                source: DIALOGUE_SOURCE.to_string(),
+            },
+            ScriptLibrary {
+                id: Uuid::from_u128(0x7175_6573_7473_0000_0000_0000_0000_0001),
+                name: QUEST_LIBRARY.to_string(),
+                source: QUEST_SOURCE.to_string(),
+            },
+            ScriptLibrary {
+                id: Uuid::from_u128(0x7368_6f70_0000_0000_0000_0000_0000_0001),
+                name: SHOP_LIBRARY.to_string(),
+                source: SHOP_SOURCE.to_string(),
+            },
+            ScriptLibrary {
+                id: Uuid::from_u128(0x636f_6d62_6174_0000_0000_0000_0000_0001),
+                name: COMBAT_LIBRARY.to_string(),
+                source: COMBAT_SOURCE.to_string(),
+            },
+            ScriptLibrary {
+                id: Uuid::from_u128(0x696e_7665_6e74_6f72_7900_0000_0000_0001),
+                name: INVENTORY_LIBRARY.to_string(),
+                source: INVENTORY_SOURCE.to_string(),
+            },
+            ScriptLibrary {
+                id: Uuid::from_u128(0x6765_6e65_7261_746f_7200_0000_0000_0001),
+                name: GENERATOR_LIBRARY.to_string(),
+                source: GENERATOR_SOURCE.to_string(),
+            },
+        ]
+    }
+
+    /// Returns the names of the libraries imported by the given source.
+    pub fn imports_of(source: &str) -> Vec<String> {
+        let mut imports = vec![];
+        for (line, _) in logical_lines(source) {
+            for import in parse_imports(&line) {
+                if !imports.contains(&import.module) {
+                    imports.push(import.module);
+                }
+            }
+        }
+        imports
+    }
+
+    /// Resolves the libraries imported by the given source. Every import line
+    /// of a project library is replaced, on the same line, by a statement which
+    /// runs the library once and binds the imported names. `import lib as
+    /// alias` runs the library in its own namespace and binds the alias to its
+    /// top level names. The lines of the source keep their numbers, also for
+    /// parenthesized imports over several lines, and errors in a library report
+    /// the library name and the line in the library. Imports of unknown
+    /// modules are kept.
+    pub fn resolve_imports(source: &str, libraries: &IndexMap<Uuid, ScriptLibrary>) -> String {
+        Self::resolve_lines(source, libraries, &mut vec![])
+    }
+
+    /// Replaces the import lines of project libraries, `stack` holds the
+    /// libraries being resolved and breaks import cycles.
+    fn resolve_lines(
+        source: &str,
+        libraries: &IndexMap<Uuid, ScriptLibrary>,
+        stack: &mut Vec<String>,
+    ) -> String {
+        let mut output = String::new();
+        for (line, lines) in logical_lines(source) {
+            let imports = parse_imports(&line);
+            if !imports
+                .iter()
+                .any(|i| libraries.values().any(|l| l.name == i.module))
+            {
+                for line in lines {
+                    output.push_str(line);
+                    output.push('\n');
+                }
+                continue;
+            }
+
+            let indent = &lines[0][..lines[0].len() - lines[0].trim_start().len()];
+            let mut statements = vec![];
+            for import in imports {
+                let Some(library) = libraries.values().find(|l| l.name == import.module) else {
+                    statements.push(import.statement());
+                    continue;
+                };
+                let code = if stack.contains(&library.name) {
+                    None
+                } else {
+                    stack.push(library.name.clone());
+                    let code = Self::resolve_lines(&library.source, libraries, stack);
+                    stack.pop();
+                    Some(code)
+                };
+
+                // `import lib [as alias]` runs the library in a copy of the
+                // globals, the server functions stay visible to the library
+                if import.names.iter().all(|(name, _)| name.is_empty()) {
+                    let module = format!("__library_{}_module__", library.name);
+                    if let Some(code) = code {
+                        statements.push(format!(
+                            "exec(compile(\"{}\", \"{}\", \"exec\"), globals().setdefault(\"{}\", dict(globals()))) if \"{}\" not in globals() else None",
+                            escape_python(&code),
+                            escape_python(&library.name),
+                            module,
+                            module
+                        ));
+                    }
+                    let members = top_level_names(&library.source)
+                        .iter()
+                        .map(|n| format!("\"{}\"", n))
+                        .collect::<Vec<_>>()
+                        .join(", ");
+                    for (_, local) in &import.names {
+                        statements.push(format!(
+                            "{} = type(\"{}\", (), {{n: v for n, v in globals().get(\"{}\", globals()).items() if n in [{}]}})",
+                            local, local, module, members
+                        ));
+                    }
+                    continue;
+                }
+
+                if let Some(code) = code {
+                    let flag = format!("__library_{}__", library.name);
+                    statements.push(format!(
+                        "exec(compile(\"{}\", \"{}\", \"exec\"), globals()) if \"{}\" not in globals() else None",
+                        escape_python(&format!("{}{} = True\n", code, flag)),
+                        escape_python(&library.name),
+                        flag
+                    ));
+                }
+                for (name, local) in &import.names {
+                    if name != "*" && name != local {
+                        statements.push(format!("{} = {}", local, name));
+                    }
+                }
+            }
+            output.push_str(indent);
+            output.push_str(&statements.join("; "));
+            output.push('\n');
+
+            // The continuation lines of the import stay as empty lines
+            for _ in 1..lines.len() {
+                output.push('\n');
+            }
+        }
+        output
+    }
+}
+
+/// A module imported by an import line with the imported names and the names
+/// they are bound to. An empty name imports the module itself.
+struct Import {
+    module: String,
+    names: Vec<(String, String)>,
+}
+
+impl Import {
+    /// The import statement for a module which is not a project library.
+    fn statement(&self) -> String {
+        let mut modules = vec![];
+        let mut names = vec![];
+        for (name, local) in &self.names {
+            if name.is_empty() {
+                modules.push(if *local == self.module {
+                    format!("import {}", self.module)
+                } else {
+                    format!("import {} as {}", self.module, local)
+                });
+            } else if name == local {
+                names.push(name.clone());
+            } else {
+                names.push(format!("{} as {}", name, local));
+            }
+        }
+        if !names.is_empty() {
+            modules.push(format!("from {} import {}", self.module, names.join(", ")));
+        }
+        modules.join("; ")
+    }
+}
+
+/// The lines of a source with the source lines they span. A parenthesized
+/// `from a import (b,` continued on the next lines is joined into one line,
+/// without the comments.
+fn logical_lines(source: &str) -> Vec<(String, Vec<&str>)> {
+    /// The line without a trailing comment.
+    fn code(line: &str) -> &str {
+        line.split_once('#').map(|(code, _)| code).unwrap_or(line)
+    }
+
+    let mut result = vec![];
+    let mut lines = source.lines();
+    while let Some(line) = lines.next() {
+        let open = line.trim_start().starts_with("from ")
+            && code(line).split_once(" import ").is_some_and(|(_, names)| {
+                names.trim_start().starts_with('(') && !names.contains(')')
+            });
+        if !open {
+            result.push((line.to_string(), vec![line]));
+            continue;
+        }
+
+        let mut joined = code(line).to_string();
+        let mut spanned = vec![line];
+        for line in lines.by_ref() {
+            joined.push(' ');
+            joined.push_str(code(line));
+            spanned.push(line);
+            if code(line).contains(')') {
+                break;
+            }
+        }
+        result.push((joined, spanned));
+    }
+    result
+}
+
+/// The imports of a line: `import a, b as c` or `from a import b, c as d`.
+fn parse_imports(line: &str) -> Vec<Import> {
+    /// `name` or `name as local`.
+    fn alias(text: &str) -> Option<(String, String)> {
+        let mut words = text.split_whitespace();
+        let name = words.next()?.to_string();
+        match (words.next(), words.next()) {
+            (Some("as"), Some(local)) => Some((name, local.to_string())),
+            _ => Some((name.clone(), name)),
+        }
+    }
+
+    let line = line.trim();
+    if let Some(rest) = line.strip_prefix("from ") {
+        let Some((module, names)) = rest.split_once(" import ") else {
+            return vec![];
+        };
+        let names = names
+            .trim()
+            .trim_start_matches('(')
+            .trim_end_matches(')')
+            .split(',')
+            .filter_map(alias)
+            .collect();
+        vec![Import {
+            module: module.trim().to_string(),
+            names,
+        }]
+    } else if let Some(rest) = line.strip_prefix("import ") {
+        rest.split(',')
+            .filter_map(alias)
+            .map(|(module, local)| Import {
+                module,
+                names: vec![(String::new(), local)],
+            })
+            .collect()
+    } else {
+        vec![]
+    }
+}
+
+/// The functions, classes and variables defined at the top level of a source.
+fn top_level_names(source: &str) -> Vec<String> {
+    let mut names: Vec<String> = vec![];
+    for line in source.lines() {
+        if line.starts_with(char::is_whitespace) {
+            continue;
+        }
+        let name = if let Some(rest) = line
+            .strip_prefix("def ")
+            .or_else(|| line.strip_prefix("class "))
+        {
+            rest.split(|c: char| !c.is_alphanumeric() && c != '_')
+                .next()
+                .unwrap_or_default()
+        } else {
+            match line.split_once('=') {
+                Some((name, value)) if !value.starts_with('=') => name.trim(),
+                _ => "",
+            }
+        };
+        let valid = name
+            .chars()
+            .next()
+            .is_some_and(|c| c.is_alphabetic() || c == '_')
+            && name.chars().all(|c| c.is_alphanumeric() || c == '_');
+        if valid && !names.iter().any(|n| n == name) {
+            names.push(name.to_string());
+        }
+    }
+    names
+}
+
+/// A Python string literal body with the text on a single line.
+pub(crate) fn escape_python(text: &str) -> String {
+    text.replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\r', "")
+        .replace('\n', "\\n")
+        .replace('\t', "\\t")
+}
@@ -0,0 +1,879 @@
+use crate::library::escape_python;
+use crate::project::Project;
+use crate::tiled::{XmlNode, xml_escape};
+use indexmap::IndexMap;
+use regex::Regex;
+use std::sync::LazyLock;
+use theframework::prelude::*;
+
+/// The prefix of the string tables in the game config, e.g. `[locale_en]`.
+pub const LOCALE_TABLE_PREFIX: &str = "locale_";
+
+/// `{key}` references to string table entries. Placeholders with upper case
+/// letters or dots like `{PLAYER.HP}` are not translatable strings.
+static KEY_REFERENCE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"\{([a-z_][a-z0-9_]*)\}").unwrap());
+
+/// The text literal of `message(receiver, "text", ...)` script calls.
+static MESSAGE_CALL: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r#"message\s*\(\s*[^,()]+,\s*"((?:[^"\\]|\\.)*)""#).unwrap());
+
+/// A translatable string with its text in every locale.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Default)]
+pub struct LocalizedString {
+    /// Where the string is used, shown to translators.
+    #[serde(default)]
+    pub context: String,
+    /// The text by locale code.
+    #[serde(default)]
+    pub text: IndexMap<String, String>,
+    /// The hash of the source text each translation was made from, by locale
+    /// code. A translation is stale when the source text changed since.
+    #[serde(default)]
+    pub translated_from: IndexMap<String, u32>,
+}
+
+/// The string tables of the project.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct Localization {
+    /// The locale codes. The first one is the source locale the project is
+    /// written in.
+    pub locales: Vec<String>,
+    /// The locale the game runs in.
+    pub active: String,
+    /// The strings by key.
+    #[serde(default)]
+    pub strings: IndexMap<String, LocalizedString>,
+}
+
+impl Default for Localization {
+    fn default() -> Self {
+        Self {
+            locales: vec!["en".into()],
+            active: "en".into(),
+            strings: IndexMap::default(),
+        }
+    }
+}
+
+impl Localization {
+    /// The locale the project is written in.
+    pub fn source_locale(&self) -> &str {
+        self.locales.first().map(|l| l.as_str()).unwrap_or("en")
+    }
+
+    /// Adds a locale, returns false if the code is invalid or exists.
+    pub fn add_locale(&mut self, code: &str) -> bool {
+        let code = code.trim();
+        let valid = !code.is_empty()
+            && code
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-');
+        if !valid || self.locales.iter().any(|l| l == code) {
+            return false;
+        }
+        self.locales.push(code.to_string());
+        true
+    }
+
+    /// Removes a locale and its texts. The source locale cannot be removed.
+    pub fn remove_locale(&mut self, code: &str) -> bool {
+        let Some(index) = self.locales.iter().position(|l| l == code) else {
+            return false;
+        };
+        if index == 0 {
+            return false;
+        }
+        self.locales.remove(index);
+        for string in self.strings.values_mut() {
+            string.text.shift_remove(code);
+        }
+        if self.active == code {
+            self.active = self.source_locale().to_string();
+        }
+        true
+    }
+
+    /// The text of the key in the locale, falling back to the source locale.
+    pub fn translate(&self, key: &str, locale: &str) -> Option<&str> {
+        let string = self.strings.get(key)?;
+        string
+            .text
+            .get(locale)
+            .filter(|t| !t.is_empty())
+            .or_else(|| string.text.get(self.source_locale()))
+            .filter(|t| !t.is_empty())
+            .map(|t| t.as_str())
+    }
+
+    /// The keys without text in the locale.
+    pub fn missing(&self, locale: &str) -> Vec<&str> {
+        self.strings
+            .iter()
+            .filter(|(_, s)| s.text.get(locale).is_none_or(|t| t.is_empty()))
+            .map(|(k, _)| k.as_str())
+            .collect()
+    }
+
+    /// The keys with a translation in the locale made from an older source
+    /// text.
+    pub fn stale(&self, locale: &str) -> Vec<&str> {
+        let source = self.source_locale();
+        self.strings
+            .iter()
+            .filter(|(_, s)| {
+                s.text.get(locale).is_some_and(|t| !t.is_empty())
+                    && s.translated_from.get(locale).is_some_and(|hash| {
+                        *hash != text_hash(s.text.get(source).map_or("", |t| t.as_str()))
+                    })
+            })
+            .map(|(k, _)| k.as_str())
+            .collect()
+    }
+
+    /// Sets the text of the key in the locale. Translations remember the
+    /// source text they were made from.
+    fn set_text(&mut self, key: String, locale: &str, text: String) {
+        let source = self.source_locale().to_string();
+        let string = self.strings.entry(key).or_default();
+        if locale != source {
+            let hash = text_hash(string.text.get(&source).map_or("", |t| t.as_str()));
+            string.translated_from.insert(locale.to_string(), hash);
+        }
+        string.text.insert(locale.to_string(), text);
+    }
+
+    /// Collects the translatable strings of the project: the texts of screen
+    /// widgets, the messages of scripts, `{key}` references, character and item
+    /// names and the `[locale_*]` tables of the game config. New keys are added
+    /// with their source text, existing translations are kept. Returns the
+    /// number of new keys.
+    pub fn extract(&mut self, project: &Project) -> usize {
+        let before = self.strings.len();
+        let source = self.source_locale().to_string();
+
+        // The tables of the game config
+        if let Ok(config) = project.config.parse::<toml::Table>() {
+            for (name, value) in &config {
+                let (Some(code), Some(table)) =
+                    (name.strip_prefix(LOCALE_TABLE_PREFIX), value.as_table())
+                else {
+                    continue;
+                };
+                self.add_locale(code);
+                for (key, text) in table {
+                    if let Some(text) = text.as_str() {
+                        let string = self.strings.entry(key.clone()).or_default();
+                        if string.context.is_empty() {
+                            string.context = "Game config".into();
+                        }
+                        string
+                            .text
+                            .entry(code.to_string())
+                            .or_insert_with(|| text.to_string());
+                    }
+                }
+            }
+        }
+
+        // Scripts
+        let mut scripts: Vec<(String, &str)> = vec![];
+        for character in project.characters.values() {
+            scripts.push((
+                format!("Character {}", character.name),
+                character.source.as_str(),
+            ));
+        }
+        for item in project.items.values() {
+            scripts.push((format!("Item {}", item.name), item.source.as_str()));
+        }
+        for library in project.libraries.values() {
+            scripts.push((format!("Library {}", library.name), library.source.as_str()));
+        }
+        for screen in project.screens.values() {
+            for sector in &screen.map.sectors {
+                if let Some(rusterix::Value::Str(source)) = sector.properties.get("source") {
+                    scripts.push((
+                        format!("Screen {}, widget {}", screen.name, sector.name),
+                        source.as_str(),
+                    ));
+                }
+            }
+        }
+        for (context, script) in scripts {
+            for captures in MESSAGE_CALL.captures_iter(script) {
+                let literal = &captures[1];
+                if literal.starts_with('{') || literal.trim().is_empty() {
+                    continue;
+                }
+                let text = unescape_python(literal);
+                self.add_text_string("message", &context, &source, &text);
+            }
+            self.add_references(script, &context);
+        }
+
+        // Screen widget texts
+        for screen in project.screens.values() {
+            for sector in &screen.map.sectors {
+                let Some(rusterix::Value::Str(data)) = sector.properties.get("data") else {
+                    continue;
+                };
+                let Some(text) = widget_text(data) else {
+                    continue;
+                };
+                let context = format!("Screen {}, widget {}", screen.name, sector.name);
+                self.add_references(&text, &context);
+                if !KEY_REFERENCE.replace_all(&text, "").trim().is_empty() {
+                    self.add_string(
+                        widget_key(&screen.name, &sector.name),
+                        &context,
+                        &source,
+                        &text,
+                    );
+                }
+            }
+        }
+
+        // Names
+        for character in project.characters.values() {
+            self.add_text_string("name", "Character name", &source, &character.name);
+        }
+        for item in project.items.values() {
+            self.add_text_string("name", "Item name", &source, &item.name);
+        }
+
+        self.strings.len() - before
+    }
+
+    /// Adds a string with its source text, or updates the source text and
+    /// context of an existing one.
+    fn add_string(&mut self, key: String, context: &str, locale: &str, text: &str) {
+        let string = self.strings.entry(key).or_default();
+        string.context = context.to_string();
+        string.text.insert(locale.to_string(), text.to_string());
+    }
+
+    /// Adds a message or name under its text key. The translations of keys
+    /// made of the words alone, as written by older versions, move to it.
+    fn add_text_string(&mut self, prefix: &str, context: &str, locale: &str, text: &str) {
+        let key = text_key(prefix, text);
+        let legacy = format!("{}.{}", prefix, slug(text));
+        if !self.strings.contains_key(&key)
+            && self
+                .strings
+                .get(&legacy)
+                .is_some_and(|s| s.text.get(locale).map(|t| t.as_str()) == Some(text))
+        {
+            if let Some(string) = self.strings.shift_remove(&legacy) {
+                self.strings.insert(key.clone(), string);
+            }
+        }
+        self.add_string(key, context, locale, text);
+    }
+
+    /// Adds the keys referenced as `{key}` in the text.
+    fn add_references(&mut self, text: &str, context: &str) {
+        for captures in KEY_REFERENCE.captures_iter(text) {
+            let string = self.strings.entry(captures[1].to_string()).or_default();
+            if string.context.is_empty() {
+                string.context = context.to_string();
+            }
+        }
+    }
+
+    // Editing
+
+    /// The strings of the locale as TOML for editing. Every entry is preceded
+    /// by its context and, for translations, the source text.
+    pub fn to_toml(&self, locale: &str) -> String {
+        let source = self.source_locale();
+        let missing = self.missing(locale).len();
+        let stale = self.stale(locale);
+        let mut output = vec![format!(
+            "# Strings of \"{}\", {} of {} missing, {} stale.",
+            locale,
+            missing,
+            self.strings.len(),
+            stale.len()
+        )];
+        for (key, string) in &self.strings {
+            output.push(String::new());
+            if !string.context.is_empty() {
+                output.push(format!("# {}", string.context));
+            }
+            let text = string.text.get(locale).cloned().unwrap_or_default();
+            if locale != source {
+                if let Some(source_text) = string.text.get(source).filter(|t| !t.is_empty()) {
+                    for line in source_text.lines() {
+                        output.push(format!("# {}: {}", source, line));
+                    }
+                }
+            }
+            if text.is_empty() {
+                output.push("# MISSING".into());
+            } else if stale.contains(&key.as_str()) {
+                output.push("# STALE, the source text changed".into());
+            }
+            output.push(format!("{} = {}", toml_string(key), toml_string(&text)));
+        }
+        output.join("\n") + "\n"
+    }
+
+    /// Applies the edited TOML of a locale, returns the number of changed texts.
+    pub fn apply_toml(&mut self, locale: &str, text: &str) -> Result<usize, String> {
+        let table: toml::Table = text.parse().map_err(|e: toml::de::Error| e.to_string())?;
+        let mut changed = 0;
+        for (key, value) in table {
+            let Some(value) = value.as_str() else {
+                return Err(format!("The value of \"{}\" is not a string.", key));
+            };
+            let unchanged = self
+                .strings
+                .get(&key)
+                .is_some_and(|s| s.text.get(locale).map(|t| t.as_str()) == Some(value));
+            if !unchanged {
+                self.set_text(key, locale, value.to_string());
+                changed += 1;
+            }
+        }
+        Ok(changed)
+    }
+
+    // Exchange formats
+
+    /// All strings as CSV with a column per locale.
+    pub fn to_csv(&self) -> String {
+        let mut header = vec!["key".to_string(), "context".to_string()];
+        header.extend(self.locales.iter().cloned());
+        let mut rows = vec![csv_row(&header)];
+        for (key, string) in &self.strings {
+            let mut row = vec![key.clone(), string.context.clone()];
+            for locale in &self.locales {
+                row.push(string.text.get(locale).cloned().unwrap_or_default());
+            }
+            rows.push(csv_row(&row));
+        }
+        rows.join("\r\n") + "\r\n"
+    }
+
+    /// Imports CSV with a `key` column and a column per locale. Unknown locales
+    /// are added. Returns the number of imported texts.
+    pub fn import_csv(&mut self, csv: &str) -> Result<usize, String> {
+        let mut rows = parse_csv(csv).into_iter();
+        let header = rows.next().ok_or("The CSV file is empty.")?;
+        let key_column = header
+            .iter()
+            .position(|h| h.trim().eq_ignore_ascii_case("key"))
+            .ok_or("The CSV file has no \"key\" column.")?;
+        let context_column = header
+            .iter()
+            .position(|h| h.trim().eq_ignore_ascii_case("context"));
+
+        let mut count = 0;
+        for row in rows {
+            let Some(key) = row
+                .get(key_column)
+                .map(|k| k.trim())
+                .filter(|k| !k.is_empty())
+            else {
+                continue;
+            };
+            let string = self.strings.entry(key.to_string()).or_default();
+            if let Some(context) = context_column.and_then(|c| row.get(c)) {
+                if !context.is_empty() {
+                    string.context = context.clone();
+                }
+            }
+            // The source text first, the translations are made from it
+            let mut columns: Vec<usize> = (0..header.len())
+                .filter(|c| *c != key_column && Some(*c) != context_column)
+                .collect();
+            columns.sort_by_key(|c| header[*c].trim() != self.source_locale());
+            for column in columns {
+                if let Some(text) = row.get(column).filter(|t| !t.is_empty()) {
+                    self.set_text(key.to_string(), header[column].trim(), text.clone());
+                    count += 1;
+                }
+            }
+        }
+        for locale in header
+            .iter()
+            .enumerate()
+            .filter(|(c, _)| *c != key_column && Some(*c) != context_column)
+            .map(|(_, l)| l.trim().to_string())
+            .collect::<Vec<_>>()
+        {
+            self.add_locale(&locale);
+        }
+        Ok(count)
+    }
+
+    /// The strings of a locale as XLIFF 1.2, with the source locale as source.
+    pub fn to_xliff(&self, locale: &str, original: &str) -> String {
+        let source = self.source_locale();
+        let stale = self.stale(locale);
+        let mut xml = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+        xml += "<xliff version=\"1.2\" xmlns=\"urn:oasis:names:tc:xliff:document:1.2\">\n";
+        xml += &format!(
+            "  <file original=\"{}\" source-language=\"{}\" target-language=\"{}\" datatype=\"plaintext\">\n    <body>\n",
+            xml_escape(original),
+            xml_escape(source),
+            xml_escape(locale)
+        );
+        for (key, string) in &self.strings {
+            xml += &format!("      <trans-unit id=\"{}\">\n", xml_escape(key));
+            xml += &format!(
+                "        <source>{}</source>\n",
+                xml_escape(string.text.get(source).map(|t| t.as_str()).unwrap_or(""))
+            );
+            if let Some(text) = string.text.get(locale).filter(|t| !t.is_empty()) {
+                let state = if stale.contains(&key.as_str()) {
+                    " state=\"needs-review-translation\""
+                } else {
+                    ""
+                };
+                xml += &format!("        <target{}>{}</target>\n", state, xml_escape(text));
+            }
+            if !string.context.is_empty() {
+                xml += &format!("        <note>{}</note>\n", xml_escape(&string.context));
+            }
+            xml += "      </trans-unit>\n";
+        }
+        xml += "    </body>\n  </file>\n</xliff>\n";
+        xml
+    }
+
+    /// Imports the targets of an XLIFF 1.2 or 2.0 file into its target
+    /// language. Returns the locale and the number of imported texts.
+    pub fn import_xliff(&mut self, xml: &str) -> Result<(String, usize), String> {
+        let root = XmlNode::parse(xml)?;
+        if root.name != "xliff" {
+            return Err("Not an XLIFF file.".into());
+        }
+        let locale = root
+            .attr("trgLang")
+            .or_else(|| {
+                root.child("file")
+                    .and_then(|file| file.attr("target-language"))
+            })
+            .ok_or("The XLIFF file has no target language.")?
+            .to_string();
+
+        let mut units = vec![];
+        collect_units(&root, &mut units);
+        let mut count = 0;
+        for (key, target) in units {
+            if target.is_empty() {
+                continue;
+            }
+            self.set_text(key, &locale, target);
+            count += 1;
+        }
+        self.add_locale(&locale);
+        Ok((locale, count))
+    }
+
+    /// The strings of a locale as a gettext `.po` file. The keys are stored as
+    /// message contexts.
+    pub fn to_po(&self, locale: &str) -> String {
+        let source = self.source_locale();
+        let stale = self.stale(locale);
+        let mut po = String::from("msgid \"\"\nmsgstr \"\"\n");
+        po += &format!("\"Language: {}\\n\"\n", po_escape(locale));
+        po += "\"Content-Type: text/plain; charset=UTF-8\\n\"\n";
+        for (key, string) in &self.strings {
+            po += "\n";
+            if !string.context.is_empty() {
+                po += &format!("#. {}\n", string.context);
+            }
+            if stale.contains(&key.as_str()) {
+                po += "#, fuzzy\n";
+            }
+            po += &format!("msgctxt \"{}\"\n", po_escape(key));
+            po += &format!(
+                "msgid \"{}\"\n",
+                po_escape(string.text.get(source).map(|t| t.as_str()).unwrap_or(""))
+            );
+            po += &format!(
+                "msgstr \"{}\"\n",
+                po_escape(string.text.get(locale).map(|t| t.as_str()).unwrap_or(""))
+            );
+        }
+        po
+    }
+
+    /// Imports a gettext `.po` file. The locale is read from the header and
+    /// falls back to `locale`. Entries without context are matched by their
+    /// source text. Returns the locale and the number of imported texts.
+    pub fn import_po(&mut self, po: &str, locale: &str) -> Result<(String, usize), String> {
+        let entries = parse_po(po);
+        let header = entries
+            .iter()
+            .find(|e| e.msgid.is_empty() && e.msgctxt.is_none())
+            .map(|e| e.msgstr.clone())
+            .unwrap_or_default();
+        let locale = header
+            .lines()
+            .find_map(|line| line.strip_prefix("Language:"))
+            .map(|l| l.trim().to_string())
+            .filter(|l| !l.is_empty())
+            .unwrap_or_else(|| locale.to_string());
+
+        let source = self.source_locale().to_string();
+        let mut count = 0;
+        for entry in entries {
+            if entry.msgstr.is_empty() || (entry.msgid.is_empty() && entry.msgctxt.is_none()) {
+                continue;
+            }
+            let key = match entry.msgctxt {
+                Some(key) => key,
+                None => match self
+                    .strings
+                    .iter()
+                    .find(|(_, s)| s.text.get(&source) == Some(&entry.msgid))
+                {
+                    Some((key, _)) => key.clone(),
+                    None => continue,
+                },
+            };
+            self.set_text(key, &locale, entry.msgstr);
+            count += 1;
+        }
+        self.add_locale(&locale);
+        Ok((locale, count))
+    }
+
+    // Runtime
+
+    /// The game config with a `[locale_*]` table per locale holding the string
+    /// tables, and the active locale in `[game] locale`. Runtimes which do not
+    /// read the setting use the table of the source locale, so it holds the
+    /// strings of the active locale.
+    pub fn runtime_config(&self, config: &str) -> String {
+        let Ok(mut table) = config.parse::<toml::Table>() else {
+            return config.to_string();
+        };
+        if self.strings.is_empty() && self.locales.len() < 2 {
+            return config.to_string();
+        }
+
+        let source = self.source_locale();
+        for locale in &self.locales {
+            let strings_locale = if locale == source {
+                self.active.as_str()
+            } else {
+                locale.as_str()
+            };
+            let entry = table
+                .entry(format!("{}{}", LOCALE_TABLE_PREFIX, locale))
+                .or_insert_with(|| toml::Value::Table(toml::Table::new()));
+            if let toml::Value::Table(locale_table) = entry {
+                for key in self.strings.keys() {
+                    if let Some(text) = self.translate(key, strings_locale) {
+                        locale_table.insert(key.clone(), toml::Value::String(text.to_string()));
+                    }
+                }
+            }
+        }
+        if let Some(toml::Value::Table(game)) = table.get_mut("game") {
+            game.insert("locale".into(), toml::Value::String(self.active.clone()));
+        }
+
+        toml::to_string(&table).unwrap_or_else(|_| config.to_string())
+    }
+
+    /// Replaces the literals of script messages with their text in the active
+    /// locale.
+    pub fn localize_source(&self, source: &str) -> String {
+        if self.active == self.source_locale() {
+            return source.to_string();
+        }
+        MESSAGE_CALL
+            .replace_all(source, |captures: &regex::Captures| {
+                let call = &captures[0];
+                let literal = &captures[1];
+                let key = text_key("message", &unescape_python(literal));
+                match self.translate(&key, &self.active) {
+                    Some(text) if !literal.starts_with('{') => {
+                        let start = call.len() - literal.len() - 1;
+                        format!("{}{}\"", &call[..start], escape_python(text))
+                    }
+                    _ => call.to_string(),
+                }
+            })
+            .to_string()
+    }
+
+    /// The widget TOML with the text in the active locale.
+    pub fn localize_widget_data(&self, screen: &str, widget: &str, data: &str) -> String {
+        if self.active == self.source_locale() {
+            return data.to_string();
+        }
+        let Some(text) = self.translate(&widget_key(screen, widget), &self.active) else {
+            return data.to_string();
+        };
+        let Ok(mut table) = data.parse::<toml::Table>() else {
+            return data.to_string();
+        };
+        if let Some(toml::Value::Table(ui)) = table.get_mut("ui") {
+            ui.insert("text".into(), toml::Value::String(text.to_string()));
+        }
+        toml::to_string(&table).unwrap_or_else(|_| data.to_string())
+    }
+
+    /// The name of a character or item in the active locale.
+    pub fn localize_name(&self, name: &str) -> Option<String> {
+        if self.active == self.source_locale() {
+            return None;
+        }
+        self.translate(&text_key("name", name), &self.active)
+            .map(|t| t.to_string())
+    }
+}
+
+/// The key of the text of a screen widget.
+fn widget_key(screen: &str, widget: &str) -> String {
+    format!("screen.{}.{}", slug(screen), slug(widget))
+}
+
+/// The `[ui] text` of widget TOML.
+fn widget_text(data: &str) -> Option<String> {
+    let table = data.parse::<toml::Table>().ok()?;
+    let text = table.get("ui")?.get("text")?.as_str()?;
+    if text.trim().is_empty() {
+        None
+    } else {
+        Some(text.to_string())
+    }
+}
+
+/// The key of a message or name: the words of the text followed by the hash
+/// of the whole text, which keeps texts with the same words apart.
+fn text_key(prefix: &str, text: &str) -> String {
+    format!("{}.{}_{:08x}", prefix, slug(text), text_hash(text))
+}
+
+/// The FNV-1a hash of the text, stable across builds and platforms.
+fn text_hash(text: &str) -> u32 {
+    text.bytes().fold(0x811c9dc5u32, |hash, byte| {
+        (hash ^ byte as u32).wrapping_mul(0x01000193)
+    })
+}
+
+/// A key fragment made of the lower case words of the text.
+fn slug(text: &str) -> String {
+    let mut slug = String::new();
+    for word in text
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|w| !w.is_empty())
+    {
+        if slug.len() + word.len() > 40 {
+            break;
+        }
+        if !slug.is_empty() {
+            slug.push('_');
+        }
+        slug.push_str(&word.to_lowercase());
+    }
+    if slug.is_empty() {
+        slug.push('_');
+    }
+    slug
+}
+
+fn toml_string(text: &str) -> String {
+    toml::Value::String(text.to_string()).to_string()
+}
+
+fn unescape_python(text: &str) -> String {
+    let mut output = String::new();
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        if c == '\\' {
+            match chars.next() {
+                Some('n') => output.push('\n'),
+                Some('t') => output.push('\t'),
+                Some(other) => output.push(other),
+                None => {}
+            }
+        } else {
+            output.push(c);
+        }
+    }
+    output
+}
+
+fn csv_row(fields: &[String]) -> String {
+    fields
+        .iter()
+        .map(|field| {
+            if field.contains([',', '"', '\n', '\r']) {
+                format!("\"{}\"", field.replace('"', "\"\""))
+            } else {
+                field.clone()
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+fn parse_csv(csv: &str) -> Vec<Vec<String>> {
+    let mut rows = vec![];
+    let mut row = vec![];
+    let mut field = String::new();
+    let mut quoted = false;
+    let mut chars = csv.trim_start_matches('\u{feff}').chars().peekable();
+    while let Some(c) = chars.next() {
+        if quoted {
+            match c {
+                '"' if chars.peek() == Some(&'"') => {
+                    field.push('"');
+                    chars.next();
+                }
+                '"' => quoted = false,
+                _ => field.push(c),
+            }
+        } else {
+            match c {
+                '"' => quoted = true,
+                ',' => row.push(std::mem::take(&mut field)),
+                '\r' => {}
+                '\n' => {
+                    row.push(std::mem::take(&mut field));
+                    rows.push(std::mem::take(&mut row));
+                }
+                _ => field.push(c),
+            }
+        }
+    }
+    if !field.is_empty() || !row.is_empty() {
+        row.push(field);
+        rows.push(row);
+    }
+    rows
+}
+
+/// Collects the ids and targets of XLIFF 1.2 `trans-unit` and 2.0 `unit`
+/// elements.
+fn collect_units(node: &XmlNode, units: &mut Vec<(String, String)>) {
+    for child in &node.children {
+        match child.name.as_str() {
+            "trans-unit" => {
+                if let (Some(id), Some(target)) = (child.attr("id"), child.child("target")) {
+                    units.push((id.to_string(), target.text.clone()));
+                }
+            }
+            "unit" => {
+                if let (Some(id), Some(target)) = (
+                    child.attr("id"),
+                    child
+                        .child("segment")
+                        .and_then(|segment| segment.child("target")),
+                ) {
+                    units.push((id.to_string(), target.text.clone()));
+                }
+            }
+            _ => collect_units(child, units),
+        }
+    }
+}
+
+fn po_escape(text: &str) -> String {
+    text.replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+        .replace('\t', "\\t")
+}
+
+#[derive(Default)]
+struct PoEntry {
+    msgctxt: Option<String>,
+    msgid: String,
+    msgstr: String,
+}
+
+/// Parses the context, id and first translation of the entries of a `.po`
+/// file.
+fn parse_po(po: &str) -> Vec<PoEntry> {
+    #[derive(PartialEq)]
+    enum Field {
+        None,
+        Context,
+        Id,
+        Str,
+    }
+
+    let mut entries = vec![];
+    let mut entry = PoEntry::default();
+    let mut field = Field::None;
+    let mut has_entry = false;
+
+    let mut finish = |entry: &mut PoEntry, has_entry: &mut bool| {
+        if *has_entry {
+            entries.push(std::mem::take(entry));
+            *has_entry = false;
+        }
+    };
+
+    for line in po.lines() {
+        let line = line.trim();
+        if line.is_empty() {
+            finish(&mut entry, &mut has_entry);
+            field = Field::None;
+            continue;
+        }
+        if line.starts_with('#') {
+            continue;
+        }
+        let (keyword, rest) = match line.split_once(char::is_whitespace) {
+            Some((keyword, rest)) if !line.starts_with('"') => (keyword, rest.trim()),
+            _ => ("", line),
+        };
+        let value = po_unquote(rest);
+        match keyword {
+            "msgctxt" => {
+                if field == Field::Str {
+                    finish(&mut entry, &mut has_entry);
+                }
+                entry.msgctxt = Some(value);
+                field = Field::Context;
+                has_entry = true;
+            }
+            "msgid" => {
+                if field == Field::Str {
+                    finish(&mut entry, &mut has_entry);
+                }
+                entry.msgid = value;
+                field = Field::Id;
+                has_entry = true;
+            }
+            "msgstr" | "msgstr[0]" => {
+                entry.msgstr = value;
+                field = Field::Str;
+            }
+            "" => match field {
+                Field::Context => {
+                    if let Some(context) = &mut entry.msgctxt {
+                        context.push_str(&value);
+                    }
+                }
+                Field::Id => entry.msgid.push_str(&value),
+                Field::Str => entry.msgstr.push_str(&value),
+                Field::None => {}
+            },
+            // Plural ids and forms other than the first are not supported
+            _ => field = Field::None,
+        }
+    }
+    finish(&mut entry, &mut has_entry);
+    entries
+}
+
+fn po_unquote(text: &str) -> String {
+    let text = text.trim();
+    let text = text
+        .strip_prefix('"')
+        .and_then(|t| t.strip_suffix('"'))
+        .unwrap_or(text);
+    unescape_python(text)
+}
@@ -0,0 +1,581 @@
+use crate::prelude::*;
+use rusterix::{PixelSource, Terrain, Value};
+use serde_json::json;
+use theframework::prelude::*;
+
+/// The material of a mesh part, derived from the pixel source of the geometry.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+enum MaterialKey {
+    Tile(Uuid),
+    Color([u8; 4]),
+    Terrain,
+    Default,
+}
+
+/// The triangles of a single material.
+#[derive(Default, Debug)]
+struct MeshPart {
+    positions: Vec<Vec3<f32>>,
+    normals: Vec<Vec3<f32>>,
+    uvs: Vec<Vec2<f32>>,
+    indices: Vec<u32>,
+}
+
+impl MeshPart {
+    /// Adds a triangle, the winding is corrected to face along the normal.
+    fn add_triangle(&mut self, points: [Vec3<f32>; 3], uvs: [Vec2<f32>; 3], normal: Vec3<f32>) {
+        let face = (points[1] - points[0]).cross(points[2] - points[0]);
+        let order = if face.dot(normal) < 0.0 {
+            [0, 2, 1]
+        } else {
+            [0, 1, 2]
+        };
+        for i in order {
+            self.indices.push(self.positions.len() as u32);
+            self.positions.push(points[i]);
+            self.normals.push(normal);
+            self.uvs.push(uvs[i]);
+        }
+    }
+}
+
+/// The triangulated geometry of a map, grouped by material. Sector floors,
+/// ceilings and extruded surfaces as well as linedef walls are included,
+/// regions add their terrain and props. Texture coordinates repeat the tiles
+/// once per world unit.
+#[derive(Default, Debug)]
+pub struct MapMesh {
+    pub name: String,
+    parts: IndexMap<MaterialKey, MeshPart>,
+}
+
+/// A Wavefront OBJ export with its material library and the textures
+/// referenced by the materials.
+pub struct ObjExport {
+    pub obj: String,
+    pub mtl: String,
+    /// The texture file names and images.
+    pub textures: Vec<(String, TheRGBABuffer)>,
+}
+
+impl MapMesh {
+    /// Triangulates the geometry of a region: the map, the terrain and the
+    /// meshes of the props.
+    pub fn build_region(region: &Region, project: &Project) -> Self {
+        let mut mesh = Self::build(&region.map);
+        mesh.add_terrain(&region.map.terrain);
+        for prop in region.props.values() {
+            if let Some(AssetBuffer::Mesh(prop_mesh)) =
+                project.assets.get(&prop.asset).map(|a| &a.buffer)
+            {
+                mesh.add_prop(prop_mesh, prop);
+            }
+        }
+        mesh.parts.retain(|_, part| !part.indices.is_empty());
+        mesh
+    }
+
+    /// Adds the terrain as a height field, two triangles per terrain cell
+    /// with heights at all four corners.
+    fn add_terrain(&mut self, terrain: &Terrain) {
+        let Some(bounds) = terrain.compute_bounds() else {
+            return;
+        };
+        let scale = terrain.scale;
+        let (x0, x1) = (
+            (bounds.min.x / scale.x).floor() as i32,
+            (bounds.max.x / scale.x).ceil() as i32,
+        );
+        let (y0, y1) = (
+            (bounds.min.y / scale.y).floor() as i32,
+            (bounds.max.y / scale.y).ceil() as i32,
+        );
+        let point = |x: i32, y: i32| {
+            Vec3::new(
+                x as f32 * scale.x,
+                terrain.get_height(x, y),
+                y as f32 * scale.y,
+            )
+        };
+
+        let part = self.parts.entry(MaterialKey::Terrain).or_default();
+        for y in y0..y1 {
+            for x in x0..x1 {
+                if !(terrain.exists(x, y)
+                    && terrain.exists(x + 1, y)
+                    && terrain.exists(x, y + 1)
+                    && terrain.exists(x + 1, y + 1))
+                {
+                    continue;
+                }
+                let corners = [
+                    point(x, y),
+                    point(x + 1, y),
+                    point(x + 1, y + 1),
+                    point(x, y + 1),
+                ];
+                for points in [
+                    [corners[0], corners[1], corners[2]],
+                    [corners[0], corners[2], corners[3]],
+                ] {
+                    let mut normal = polygon_normal(&points);
+                    if normal.y < 0.0 {
+                        normal = -normal;
+                    }
+                    part.add_triangle(points, points.map(|p| Vec2::new(p.x, p.z)), normal);
+                }
+            }
+        }
+    }
+
+    /// Adds the triangles of a placed prop with their baked colors.
+    fn add_prop(&mut self, mesh: &PropMesh, prop: &PropInstance) {
+        for triangle in &mesh.triangles {
+            // Map coordinates have the height in z
+            let points = triangle.points.map(|p| {
+                let p = prop.place(p);
+                Vec3::new(p.x, p.z, p.y)
+            });
+            let normal = polygon_normal(&points);
+            if normal.magnitude_squared() == 0.0 {
+                continue;
+            }
+            let part = self
+                .parts
+                .entry(MaterialKey::Color(triangle.color))
+                .or_default();
+            part.add_triangle(points, [Vec2::zero(); 3], normal);
+        }
+    }
+
+    /// Triangulates the geometry of the given map.
+    pub fn build(map: &Map) -> Self {
+        let mut mesh = MapMesh {
+            name: map.name.clone(),
+            ..Default::default()
+        };
+
+        for sector in &map.sectors {
+            let Some(points) = sector.vertices_world(map) else {
+                continue;
+            };
+            if points.len() < 3 {
+                continue;
+            }
+
+            // Layered 2D tiles are lifted slightly to avoid z-fighting
+            let lift = Vec3::new(0.0, sector.layer.unwrap_or(0) as f32 * 0.001, 0.0);
+            let points: Vec<Vec3<f32>> = points.iter().map(|p| *p + lift).collect();
+            let mut normal = polygon_normal(&points);
+            if normal.y < 0.0 {
+                normal = -normal;
+            }
+            if normal.magnitude_squared() == 0.0 {
+                continue;
+            }
+
+            let floor = sector
+                .properties
+                .get("source")
+                .or_else(|| sector.properties.get("floor_source"));
+
+            let surface = map
+                .surfaces
+                .values()
+                .find(|s| s.sector_id == sector.id && s.extrusion.enabled);
+
+            if let Some(surface) = surface {
+                let extrusion = &surface.extrusion;
+                let offset = normal * extrusion.depth;
+                let back: Vec<Vec3<f32>> = points.iter().map(|p| *p + offset).collect();
+                let Some(part) = material_key(floor).map(|k| mesh.parts.entry(k).or_default())
+                else {
+                    continue;
+                };
+                let depth_sign = if extrusion.depth < 0.0 { -1.0 } else { 1.0 };
+                if extrusion.cap_front {
+                    add_polygon(part, &points, -normal * depth_sign);
+                }
+                if extrusion.cap_back {
+                    add_polygon(part, &back, normal * depth_sign);
+                }
+                for i in 0..points.len() {
+                    let (a, b) = (points[i], points[(i + 1) % points.len()]);
+                    let side = (b - a).cross(offset);
+                    if side.magnitude_squared() > 0.0 {
+                        add_quad(part, a, b, offset, side.normalized());
+                    }
+                }
+                continue;
+            }
+
+            if let Some(part) = material_key(floor).map(|k| mesh.parts.entry(k).or_default()) {
+                add_polygon(part, &points, normal);
+            }
+
+            // Ceilings are only created for sectors with a ceiling source
+            let ceiling_height = sector.properties.get_float_default("ceiling_height", 0.0);
+            let floor_height = points.iter().map(|p| p.y).fold(f32::MIN, f32::max);
+            if let Some(ceiling) = sector.properties.get("ceiling_source")
+                && ceiling_height > floor_height
+                && let Some(part) =
+                    material_key(Some(ceiling)).map(|k| mesh.parts.entry(k).or_default())
+            {
+                let points: Vec<Vec3<f32>> = points
+                    .iter()
+                    .map(|p| Vec3::new(p.x, ceiling_height, p.z))
+                    .collect();
+                add_polygon(part, &points, Vec3::new(0.0, -1.0, 0.0));
+            }
+        }
+
+        for linedef in &map.linedefs {
+            let height = linedef.properties.get_float_default("wall_height", 0.0);
+            if height <= 0.0 {
+                continue;
+            }
+            let (Some(start), Some(end)) = (
+                map.find_vertex(linedef.start_vertex),
+                map.find_vertex(linedef.end_vertex),
+            ) else {
+                continue;
+            };
+            let a = Vec3::new(start.x, start.z, start.y);
+            let b = Vec3::new(end.x, end.z, end.y);
+            let up = Vec3::new(0.0, height, 0.0);
+            let side = (b - a).cross(up);
+            if side.magnitude_squared() == 0.0 {
+                continue;
+            }
+            let source = linedef.properties.get("row1_source");
+            if let Some(part) = material_key(source).map(|k| mesh.parts.entry(k).or_default()) {
+                add_quad(part, a, b, up, side.normalized());
+            }
+        }
+
+        mesh.parts.retain(|_, part| !part.indices.is_empty());
+        mesh
+    }
+
+    /// Returns true if the map has no exportable geometry.
+    pub fn is_empty(&self) -> bool {
+        self.parts.is_empty()
+    }
+
+    /// The base color and the baked texture of a material.
+    fn material_data(key: &MaterialKey, project: &Project) -> ([f32; 4], Option<TheRGBABuffer>) {
+        match key {
+            MaterialKey::Tile(id) => match project.tiles.get(id).and_then(|t| t.textures.first()) {
+                Some(texture) => (
+                    [1.0, 1.0, 1.0, 1.0],
+                    Some(TheRGBABuffer::from(
+                        texture.data.clone(),
+                        texture.width as u32,
+                        texture.height as u32,
+                    )),
+                ),
+                None => ([0.5, 0.5, 0.5, 1.0], None),
+            },
+            MaterialKey::Color(c) => (c.map(|v| v as f32 / 255.0), None),
+            MaterialKey::Terrain | MaterialKey::Default => ([0.5, 0.5, 0.5, 1.0], None),
+        }
+    }
+
+    /// The name of a material, tile materials use the tile tags if available.
+    fn material_name(index: usize, key: &MaterialKey, project: &Project) -> String {
+        let base = match key {
+            MaterialKey::Tile(id) => project
+                .tiles
+                .get(id)
+                .map(|t| t.tags.clone())
+                .filter(|tags| !tags.is_empty())
+                .unwrap_or("tile".into()),
+            MaterialKey::Color(_) => "color".into(),
+            MaterialKey::Terrain => "terrain".into(),
+            MaterialKey::Default => "default".into(),
+        };
+        let base: String = base
+            .chars()
+            .map(|c| if c.is_alphanumeric() { c } else { '_' })
+            .collect();
+        format!("{}_{}", base, index)
+    }
+
+    /// Exports the mesh as a binary glTF 2.0 file (`.glb`) with one
+    /// primitive per material. Tile textures are embedded as PNG images,
+    /// `encode_png` encodes the images.
+    pub fn to_glb(
+        &self,
+        project: &Project,
+        encode_png: impl Fn(&TheRGBABuffer) -> Option<Vec<u8>>,
+    ) -> Vec<u8> {
+        let mut bin: Vec<u8> = vec![];
+        let mut buffer_views = vec![];
+        let mut accessors = vec![];
+        let mut materials = vec![];
+        let mut textures = vec![];
+        let mut images = vec![];
+        let mut primitives = vec![];
+
+        // Appends data to the binary chunk and returns the buffer view index
+        let mut add_view = |bin: &mut Vec<u8>, data: &[u8], target: Option<u32>| -> usize {
+            while bin.len() % 4 != 0 {
+                bin.push(0);
+            }
+            let mut view = json!({
+                "buffer": 0,
+                "byteOffset": bin.len(),
+                "byteLength": data.len(),
+            });
+            if let Some(target) = target {
+                view["target"] = json!(target);
+            }
+            bin.extend_from_slice(data);
+            buffer_views.push(view);
+            buffer_views.len() - 1
+        };
+
+        for (index, (key, part)) in self.parts.iter().enumerate() {
+            let (color, texture) = Self::material_data(key, project);
+            let mut material = json!({
+                "name": Self::material_name(index, key, project),
+                "doubleSided": true,
+                "pbrMetallicRoughness": {
+                    "baseColorFactor": color,
+                    "metallicFactor": 0.0,
+                    "roughnessFactor": 1.0,
+                },
+            });
+            if let Some(texture) = texture
+                && let Some(png) = encode_png(&texture)
+            {
+                let view = add_view(&mut bin, &png, None);
+                images.push(json!({ "bufferView": view, "mimeType": "image/png" }));
+                textures.push(json!({ "sampler": 0, "source": images.len() - 1 }));
+                material["pbrMetallicRoughness"]["baseColorTexture"] =
+                    json!({ "index": textures.len() - 1 });
+                if texture.pixels().chunks_exact(4).any(|p| p[3] < 255) {
+                    material["alphaMode"] = json!("MASK");
+                }
+            }
+            materials.push(material);
+
+            let positions: Vec<f32> = part
+                .positions
+                .iter()
+                .flat_map(|p| [p.x, p.y, p.z])
+                .collect();
+            let normals: Vec<f32> = part.normals.iter().flat_map(|n| [n.x, n.y, n.z]).collect();
+            let uvs: Vec<f32> = part.uvs.iter().flat_map(|uv| [uv.x, uv.y]).collect();
+            let min = part
+                .positions
+                .iter()
+                .fold(Vec3::broadcast(f32::MAX), |a, p| a.map2(*p, f32::min));
+            let max = part
+                .positions
+                .iter()
+                .fold(Vec3::broadcast(f32::MIN), |a, p| a.map2(*p, f32::max));
+
+            let count = part.positions.len();
+            let view = add_view(&mut bin, &f32_bytes(&positions), Some(34962));
+            accessors.push(json!({
+                "bufferView": view, "componentType": 5126, "count": count, "type": "VEC3",
+                "min": [min.x, min.y, min.z], "max": [max.x, max.y, max.z],
+            }));
+            let view = add_view(&mut bin, &f32_bytes(&normals), Some(34962));
+            accessors.push(json!({
+                "bufferView": view, "componentType": 5126, "count": count, "type": "VEC3",
+            }));
+            let view = add_view(&mut bin, &f32_bytes(&uvs), Some(34962));
+            accessors.push(json!({
+                "bufferView": view, "componentType": 5126, "count": count, "type": "VEC2",
+            }));
+            let indices: Vec<u8> = part.indices.iter().flat_map(|i| i.to_le_bytes()).collect();
+            let view = add_view(&mut bin, &indices, Some(34963));
+            accessors.push(json!({
+                "bufferView": view, "componentType": 5125, "count": part.indices.len(),
+                "type": "SCALAR",
+            }));
+
+            let first = accessors.len() - 4;
+            primitives.push(json!({
+                "attributes": {
+                    "POSITION": first,
+                    "NORMAL": first + 1,
+                    "TEXCOORD_0": first + 2,
+                },
+                "indices": first + 3,
+                "material": index,
+            }));
+        }
+        while bin.len() % 4 != 0 {
+            bin.push(0);
+        }
+
+        let mut root = json!({
+            "asset": { "version": "2.0", "generator": "Eldiron" },
+            "scene": 0,
+            "scenes": [{ "nodes": [0] }],
+            "nodes": [{ "name": self.name, "mesh": 0 }],
+            "meshes": [{ "name": self.name, "primitives": primitives }],
+            "materials": materials,
+            "accessors": accessors,
+            "bufferViews": buffer_views,
+            "buffers": [{ "byteLength": bin.len() }],
+        });
+        if !images.is_empty() {
+            // Nearest filtering and repeat wrapping keeps the tiles crisp
+            root["samplers"] = json!([{
+                "magFilter": 9728, "minFilter": 9728, "wrapS": 10497, "wrapT": 10497,
+            }]);
+            root["textures"] = json!(textures);
+            root["images"] = json!(images);
+        }
+
+        let mut json_chunk = serde_json::to_vec(&root).unwrap_or_default();
+        while json_chunk.len() % 4 != 0 {
+            json_chunk.push(b' ');
+        }
+
+        let length = 12 + 8 + json_chunk.len() + 8 + bin.len();
+        let mut glb = Vec::with_capacity(length);
+        glb.extend_from_slice(b"glTF");
+        glb.extend_from_slice(&2_u32.to_le_bytes());
+        glb.extend_from_slice(&(length as u32).to_le_bytes());
+        glb.extend_from_slice(&(json_chunk.len() as u32).to_le_bytes());
+        glb.extend_from_slice(b"JSON");
+        glb.extend_from_slice(&json_chunk);
+        glb.extend_from_slice(&(bin.len() as u32).to_le_bytes());
+        glb.extend_from_slice(b"BIN\0");
+        glb.extend_from_slice(&bin);
+        glb
+    }
+
+    /// Exports the mesh as a Wavefront OBJ with a material library. `name`
+    /// is the file name without extension, the material library and the
+    /// textures are named after it.
+    pub fn to_obj(&self, project: &Project, name: &str) -> ObjExport {
+        let mut obj = format!("# Eldiron\nmtllib {}.mtl\no {}\n", name, self.name);
+        let mut mtl = String::from("# Eldiron\n");
+        let mut textures = vec![];
+
+        let mut offset = 1;
+        for (index, (key, part)) in self.parts.iter().enumerate() {
+            let material_name = Self::material_name(index, key, project);
+            let (color, texture) = Self::material_data(key, project);
+
+            mtl += &format!(
+                "\nnewmtl {}\nKa 0 0 0\nKd {} {} {}\nKs 0 0 0\nd {}\nillum 1\n",
+                material_name, color[0], color[1], color[2], color[3]
+            );
+            if let Some(texture) = texture {
+                let file = format!("{}_{}.png", name, index);
+                mtl += &format!("map_Kd {}\n", file);
+                textures.push((file, texture));
+            }
+
+            for p in &part.positions {
+                obj += &format!("v {} {} {}\n", p.x, p.y, p.z);
+            }
+            // OBJ texture coordinates start at the bottom of the image
+            for uv in &part.uvs {
+                obj += &format!("vt {} {}\n", uv.x, 1.0 - uv.y);
+            }
+            for n in &part.normals {
+                obj += &format!("vn {} {} {}\n", n.x, n.y, n.z);
+            }
+            obj += &format!("usemtl {}\n", material_name);
+            for triangle in part.indices.chunks_exact(3) {
+                let [a, b, c] = [0, 1, 2].map(|i| triangle[i] as usize + offset);
+                obj += &format!("f {a}/{a}/{a} {b}/{b}/{b} {c}/{c}/{c}\n");
+            }
+            offset += part.positions.len();
+        }
+
+        ObjExport { obj, mtl, textures }
+    }
+}
+
+/// The material of a pixel source, None if the geometry is switched off.
+fn material_key(source: Option<&Value>) -> Option<MaterialKey> {
+    match source {
+        Some(Value::Source(PixelSource::Off)) => None,
+        Some(Value::Source(PixelSource::TileId(id))) => Some(MaterialKey::Tile(*id)),
+        Some(Value::Source(PixelSource::Color(color))) => Some(MaterialKey::Color([
+            (color.r * 255.0) as u8,
+            (color.g * 255.0) as u8,
+            (color.b * 255.0) as u8,
+            (color.a * 255.0) as u8,
+        ])),
+        _ => Some(MaterialKey::Default),
+    }
+}
+
+/// The normal of a polygon using Newell's method.
+fn polygon_normal(points: &[Vec3<f32>]) -> Vec3<f32> {
+    let mut normal = Vec3::zero();
+    for i in 0..points.len() {
+        let (a, b) = (points[i], points[(i + 1) % points.len()]);
+        normal.x += (a.y - b.y) * (a.z + b.z);
+        normal.y += (a.z - b.z) * (a.x + b.x);
+        normal.z += (a.x - b.x) * (a.y + b.y);
+    }
+    if normal.magnitude_squared() > 0.0 {
+        normal.normalized()
+    } else {
+        normal
+    }
+}
+
+/// Triangulates a planar polygon. The texture coordinates are the world
+/// coordinates on the dominant plane of the polygon.
+fn add_polygon(part: &mut MeshPart, points: &[Vec3<f32>], normal: Vec3<f32>) {
+    let n = normal.map(f32::abs);
+    let project = |p: &Vec3<f32>| -> Vec2<f32> {
+        if n.y >= n.x && n.y >= n.z {
+            Vec2::new(p.x, p.z)
+        } else if n.x >= n.z {
+            Vec2::new(p.z, -p.y)
+        } else {
+            Vec2::new(p.x, -p.y)
+        }
+    };
+
+    let flat: Vec<f64> = points
+        .iter()
+        .flat_map(|p| {
+            let p = project(p);
+            [p.x as f64, p.y as f64]
+        })
+        .collect();
+    let Ok(triangles) = earcutr::earcut(&flat, &[], 2) else {
+        return;
+    };
+    for triangle in triangles.chunks_exact(3) {
+        let points = [0, 1, 2].map(|i| points[triangle[i]]);
+        part.add_triangle(points, points.map(|p| project(&p)), normal);
+    }
+}
+
+/// Adds a quad spanning from the edge `a` - `b` along `up`. The texture
+/// repeats along the edge and starts at the top.
+fn add_quad(part: &mut MeshPart, a: Vec3<f32>, b: Vec3<f32>, up: Vec3<f32>, normal: Vec3<f32>) {
+    let length = (b - a).magnitude();
+    let height = up.magnitude();
+    let (a_top, b_top) = (a + up, b + up);
+    let uv = |u: f32, v: f32| Vec2::new(u, v);
+    part.add_triangle(
+        [a, b, b_top],
+        [uv(0.0, height), uv(length, height), uv(length, 0.0)],
+        normal,
+    );
+    part.add_triangle(
+        [a, b_top, a_top],
+        [uv(0.0, height), uv(length, 0.0), uv(0.0, 0.0)],
+        normal,
+    );
+}
+
+fn f32_bytes(values: &[f32]) -> Vec<u8> {
+    values.iter().flat_map(|v| v.to_le_bytes()).collect()
+}
@@ -0,0 +1,1254 @@
+use crate::prelude::*;
+use crate::rusterix_utils::{InstanceEdit, apply_instance_edit};
+use rusterix::{Map, PixelSource, Rusterix, Value};
+use std::cmp::Ordering;
+use std::collections::BinaryHeap;
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use theframework::prelude::*;
+
+/// The name of the built-in script library with the navigation helpers.
+pub const NAVIGATION_LIBRARY: &str = "navigation";
+
+/// The attribute holding the destination requested by a script: a sector name,
+/// `#<entity id>` or `x,y`. Cleared by the host on arrival.
+pub const NAV_GOAL: &str = "nav_goal";
+/// The movement speed in map units per second.
+pub const NAV_SPEED: &str = "nav_speed";
+/// Set by the host to `moving`, `arrived` or `unreachable`.
+pub const NAV_STATE: &str = "nav_state";
+
+/// The source of the built-in `navigation` library. The functions only set
+/// attributes, the host moves the character along the navigation mesh of its
+/// region.
+pub const NAVIGATION_SOURCE: &str = r##"# Built-in navigation helpers. The character walks along the navigation mesh
+# of its region one step per game tick, get_attr("nav_state") is "moving",
+# "arrived" or "unreachable".
+
+def move_to(x, y, speed=2.0):
+    set_attr("nav_speed", float(speed))
+    set_attr("nav_goal", str(float(x)) + "," + str(float(y)))
+
+def path_to(target, speed=2.0):
+    if isinstance(target, int):
+        target = "#" + str(target)
+    set_attr("nav_speed", float(speed))
+    set_attr("nav_goal", str(target))
+
+def stop_moving():
+    set_attr("nav_goal", "")
+
+def has_arrived():
+    return get_attr("nav_state") == "arrived"
+"##;
+
+/// The neighbour offsets of a cell, the index is the bit in `NavCell::walls`.
+const DIRS: [(i32, i32); 8] = [
+    (1, 0),
+    (1, 1),
+    (0, 1),
+    (-1, 1),
+    (-1, 0),
+    (-1, -1),
+    (0, -1),
+    (1, -1),
+];
+
+/// The largest grid which gets baked, the cell size grows for bigger maps.
+const MAX_CELLS: f32 = 2_000_000.0;
+
+/// The number of rows of terrain which are compared on every update.
+const TERRAIN_ROWS_PER_UPDATE: i32 = 16;
+
+/// Baking parameters, read from the `[navigation]` section of a region config.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(default)]
+pub struct NavSettings {
+    /// The size of a navigation cell in map units.
+    pub cell_size: f32,
+    /// The distance agents keep from walls.
+    pub agent_radius: f32,
+    /// The steepest walkable terrain or floor in degrees.
+    pub max_slope: f32,
+    /// The largest height difference an agent can step up or down. Walls
+    /// lower than this do not block.
+    pub max_step: f32,
+}
+
+impl Default for NavSettings {
+    fn default() -> Self {
+        Self {
+            cell_size: 0.5,
+            agent_radius: 0.25,
+            max_slope: 40.0,
+            max_step: 0.5,
+        }
+    }
+}
+
+impl NavSettings {
+    /// Reads the `[navigation]` section of a region config.
+    pub fn from_config(config: &str) -> Self {
+        config
+            .parse::<toml::Table>()
+            .ok()
+            .and_then(|table| table.get("navigation").cloned())
+            .and_then(|section| section.try_into().ok())
+            .unwrap_or_default()
+    }
+}
+
+/// An axis aligned rectangle on the map plane.
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct NavBounds {
+    min: Vec2<f32>,
+    max: Vec2<f32>,
+}
+
+impl NavBounds {
+    fn from_points(points: impl IntoIterator<Item = Vec2<f32>>) -> Option<Self> {
+        let mut bounds: Option<NavBounds> = None;
+        for p in points {
+            bounds = Some(match bounds {
+                Some(b) => NavBounds {
+                    min: b.min.map2(p, f32::min),
+                    max: b.max.map2(p, f32::max),
+                },
+                None => NavBounds { min: p, max: p },
+            });
+        }
+        bounds
+    }
+
+    fn union(&self, other: &NavBounds) -> NavBounds {
+        NavBounds {
+            min: self.min.map2(other.min, f32::min),
+            max: self.max.map2(other.max, f32::max),
+        }
+    }
+
+    fn expanded(&self, amount: f32) -> NavBounds {
+        NavBounds {
+            min: self.min - amount,
+            max: self.max + amount,
+        }
+    }
+
+    fn contains(&self, other: &NavBounds) -> bool {
+        self.min.x <= other.min.x
+            && self.min.y <= other.min.y
+            && self.max.x >= other.max.x
+            && self.max.y >= other.max.y
+    }
+}
+
+/// A walkable surface: the polygon of a sector with its height.
+#[derive(Clone, Debug)]
+struct NavSector {
+    layer: i32,
+    /// Map plane position and height of the polygon.
+    points: Vec<Vec3<f32>>,
+    bounds: NavBounds,
+    blocking: bool,
+}
+
+/// A wall which blocks movement.
+#[derive(Clone, Debug)]
+struct NavWall {
+    a: Vec2<f32>,
+    b: Vec2<f32>,
+    bounds: NavBounds,
+}
+
+/// The navigation relevant geometry of a map and the fingerprints used to
+/// find the areas which changed since the last bake.
+#[derive(Clone, Debug, Default)]
+struct NavGeometry {
+    sectors: Vec<NavSector>,
+    walls: Vec<NavWall>,
+    fingerprints: FxHashMap<(bool, u32), (u64, NavBounds)>,
+    terrain: Option<NavBounds>,
+}
+
+impl NavGeometry {
+    fn new(map: &Map, tiles: &IndexMap<Uuid, rusterix::Tile>, settings: &NavSettings) -> Self {
+        let vertices: FxHashMap<u32, Vec3<f32>> = map
+            .vertices
+            .iter()
+            .map(|v| (v.id, Vec3::new(v.x, v.y, v.z)))
+            .collect();
+        let ends: FxHashMap<u32, (u32, u32)> = map
+            .linedefs
+            .iter()
+            .map(|l| (l.id, (l.start_vertex, l.end_vertex)))
+            .collect();
+
+        let mut geometry = NavGeometry {
+            sectors: vec![],
+            walls: vec![],
+            fingerprints: FxHashMap::default(),
+            terrain: map.terrain.compute_bounds().map(|b| NavBounds {
+                min: b.min,
+                max: b.max,
+            }),
+        };
+
+        for sector in &map.sectors {
+            // Chain the linedefs into the outline of the sector
+            let mut ids: Vec<u32> = vec![];
+            for linedef_id in &sector.linedefs {
+                let Some((start, end)) = ends.get(linedef_id) else {
+                    continue;
+                };
+                match ids.last() {
+                    None => ids.extend([*start, *end]),
+                    Some(last) if last == start => ids.push(*end),
+                    Some(last) if last == end => ids.push(*start),
+                    Some(_) => ids.push(*start),
+                }
+            }
+            if ids.len() > 1 && ids.first() == ids.last() {
+                ids.pop();
+            }
+            let points: Vec<Vec3<f32>> = ids
+                .iter()
+                .filter_map(|id| vertices.get(id))
+                .copied()
+                .collect();
+            let Some(bounds) = NavBounds::from_points(points.iter().map(|p| p.xy())) else {
+                continue;
+            };
+            if points.len() < 3 {
+                continue;
+            }
+
+            let blocking = ["source", "floor_source"].iter().any(|key| {
+                matches!(
+                    sector.properties.get(key),
+                    Some(Value::Source(PixelSource::TileId(id))) if tiles.get(id).is_some_and(|t| t.blocking)
+                )
+            });
+            let layer = sector.layer.unwrap_or(0) as i32;
+
+            let mut hasher = DefaultHasher::new();
+            for p in &points {
+                hash_vec3(&mut hasher, *p);
+            }
+            blocking.hash(&mut hasher);
+            layer.hash(&mut hasher);
+            geometry
+                .fingerprints
+                .insert((false, sector.id), (hasher.finish(), bounds));
+
+            geometry.sectors.push(NavSector {
+                layer,
+                points,
+                bounds,
+                blocking,
+            });
+        }
+
+        for linedef in &map.linedefs {
+            let (Some(a), Some(b)) = (
+                vertices.get(&linedef.start_vertex),
+                vertices.get(&linedef.end_vertex),
+            ) else {
+                continue;
+            };
+            let wall_height = linedef.properties.get_float_default("wall_height", 0.0);
+            let bounds = NavBounds::from_points([a.xy(), b.xy()]).unwrap();
+
+            let mut hasher = DefaultHasher::new();
+            hash_vec3(&mut hasher, *a);
+            hash_vec3(&mut hasher, *b);
+            wall_height.to_bits().hash(&mut hasher);
+            geometry
+                .fingerprints
+                .insert((true, linedef.id), (hasher.finish(), bounds));
+
+            if wall_height > settings.max_step {
+                geometry.walls.push(NavWall {
+                    a: a.xy(),
+                    b: b.xy(),
+                    bounds,
+                });
+            }
+        }
+
+        geometry
+    }
+
+    /// The area covered by walkable surfaces and walls.
+    fn bounds(&self) -> Option<NavBounds> {
+        self.fingerprints
+            .values()
+            .map(|(_, b)| *b)
+            .chain(self.terrain)
+            .reduce(|a, b| a.union(&b))
+    }
+}
+
+fn hash_vec3(hasher: &mut DefaultHasher, p: Vec3<f32>) {
+    p.x.to_bits().hash(hasher);
+    p.y.to_bits().hash(hasher);
+    p.z.to_bits().hash(hasher);
+}
+
+/// A cell of the navigation grid.
+#[derive(Clone, Copy, Default, Debug, PartialEq)]
+pub struct NavCell {
+    pub walkable: bool,
+    /// The height of the ground at the center of the cell.
+    pub height: f32,
+    /// One bit per neighbour (see `DIRS`) which is separated by a wall.
+    pub walls: u8,
+    /// The height is sampled from the terrain.
+    pub terrain: bool,
+}
+
+/// A search node of the A* open list.
+#[derive(PartialEq)]
+struct OpenNode {
+    cost: f32,
+    index: usize,
+}
+
+impl Eq for OpenNode {}
+
+impl Ord for OpenNode {
+    fn cmp(&self, other: &Self) -> Ordering {
+        other.cost.total_cmp(&self.cost)
+    }
+}
+
+impl PartialOrd for OpenNode {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+/// The shared edge of two neighbouring polygons of the navigation mesh.
+#[derive(Clone, Debug, PartialEq)]
+pub struct NavPortal {
+    /// The index of the polygon on the other side.
+    pub poly: usize,
+    pub a: Vec2<f32>,
+    pub b: Vec2<f32>,
+}
+
+/// A convex walkable polygon of the navigation mesh, a rectangle of cells
+/// in `min..max` which an agent can cross in a straight line.
+#[derive(Clone, Debug, PartialEq)]
+pub struct NavPoly {
+    pub min: (i32, i32),
+    pub max: (i32, i32),
+    pub portals: Vec<NavPortal>,
+}
+
+/// The navigation mesh of a grid. Paths are searched over the polygons and
+/// straightened through the portals with the funnel algorithm.
+#[derive(Clone, Debug, Default)]
+pub struct NavMesh {
+    pub polys: Vec<NavPoly>,
+    /// The polygon of every grid cell, `u32::MAX` for blocked cells.
+    cell_polys: Vec<u32>,
+}
+
+/// The largest side of a polygon in cells, which keeps the cost estimates of
+/// the search close to the walked distance.
+const MAX_POLY_CELLS: i32 = 32;
+
+/// A navigation mesh baked from the geometry of a region map. The geometry
+/// is first rasterized into a grid of cells: walkable sectors and terrain
+/// which is not too steep, minus tiles marked as blocking and the
+/// surroundings of walls. The walkable cells are merged into the convex
+/// polygons of the mesh. When the map changes only the area around the
+/// changed geometry is rebaked.
+#[derive(Clone, Debug, Default)]
+pub struct NavGrid {
+    pub settings: NavSettings,
+    /// The map position of the lower corner of the grid.
+    pub origin: Vec2<f32>,
+    /// The size of a cell, larger than the setting for very large maps.
+    pub cell_size: f32,
+    pub width: i32,
+    pub height: i32,
+    pub cells: Vec<NavCell>,
+    pub mesh: NavMesh,
+
+    /// The geometry of the last bake and the key of the map it was read from.
+    geometry: NavGeometry,
+    geometry_key: u64,
+    terrain_row: i32,
+}
+
+impl NavGrid {
+    /// Bakes the navigation mesh of the given map.
+    pub fn bake(map: &Map, tiles: &IndexMap<Uuid, rusterix::Tile>, settings: NavSettings) -> Self {
+        let mut grid = NavGrid {
+            geometry: NavGeometry::new(map, tiles, &settings),
+            geometry_key: geometry_key(map),
+            settings,
+            ..Default::default()
+        };
+        grid.rebuild(map);
+        grid
+    }
+
+    /// Rebakes the areas of the map which changed since the last bake. The
+    /// geometry is only read again when the map changed, terrain is compared
+    /// a few rows at a time. Returns true if anything changed.
+    pub fn update(&mut self, map: &Map, tiles: &IndexMap<Uuid, rusterix::Tile>) -> bool {
+        let mut dirty: Option<NavBounds> = None;
+        let mut extend = |b: &NavBounds| {
+            dirty = Some(dirty.map_or(*b, |d| d.union(b)));
+        };
+
+        let map_key = geometry_key(map);
+        if map_key != self.geometry_key {
+            let geometry = NavGeometry::new(map, tiles, &self.settings);
+            self.geometry_key = map_key;
+
+            // A grid which does not cover the map anymore is baked from scratch
+            if let Some(bounds) = geometry.bounds()
+                && !self.grid_bounds().contains(&bounds)
+            {
+                self.geometry = geometry;
+                self.rebuild(map);
+                return true;
+            }
+
+            for (key, (hash, bounds)) in &geometry.fingerprints {
+                match self.geometry.fingerprints.get(key) {
+                    Some((old_hash, _)) if old_hash == hash => {}
+                    Some((_, old_bounds)) => {
+                        extend(bounds);
+                        extend(old_bounds);
+                    }
+                    None => extend(bounds),
+                }
+            }
+            for (key, (_, bounds)) in &self.geometry.fingerprints {
+                if !geometry.fingerprints.contains_key(key) {
+                    extend(bounds);
+                }
+            }
+            self.geometry = geometry;
+        }
+        if self.geometry.terrain.is_some() {
+            for b in self.changed_terrain(map) {
+                extend(&b);
+            }
+        }
+
+        let Some(dirty) = dirty else {
+            return false;
+        };
+
+        let margin = self.settings.agent_radius + self.cell_size * 2.0;
+        let dirty = dirty.expanded(margin);
+        let (x0, y0) = self.cell_at(dirty.min);
+        let (x1, y1) = self.cell_at(dirty.max);
+        self.bake_area(
+            map,
+            x0.max(0),
+            y0.max(0),
+            (x1 + 1).min(self.width),
+            (y1 + 1).min(self.height),
+        );
+        self.build_mesh();
+        true
+    }
+
+    /// The grid cell containing the given map position.
+    pub fn cell_at(&self, p: Vec2<f32>) -> (i32, i32) {
+        let local = (p - self.origin) / self.cell_size;
+        (local.x.floor() as i32, local.y.floor() as i32)
+    }
+
+    /// The map position of the center of the given cell.
+    pub fn cell_center(&self, x: i32, y: i32) -> Vec2<f32> {
+        self.origin + (Vec2::new(x as f32, y as f32) + 0.5) * self.cell_size
+    }
+
+    /// The map position of the lower corner of the given cell.
+    fn cell_corner(&self, x: i32, y: i32) -> Vec2<f32> {
+        self.origin + Vec2::new(x as f32, y as f32) * self.cell_size
+    }
+
+    pub fn cell(&self, x: i32, y: i32) -> Option<&NavCell> {
+        if x < 0 || y < 0 || x >= self.width || y >= self.height {
+            return None;
+        }
+        self.cells.get((y * self.width + x) as usize)
+    }
+
+    /// Returns true if the given map position can be walked on.
+    pub fn is_walkable(&self, p: Vec2<f32>) -> bool {
+        let (x, y) = self.cell_at(p);
+        self.cell(x, y).is_some_and(|c| c.walkable)
+    }
+
+    /// The polygon of the navigation mesh containing the given cell.
+    fn poly_at(&self, (x, y): (i32, i32)) -> Option<usize> {
+        self.cell(x, y)?;
+        let poly = self.mesh.cell_polys[(y * self.width + x) as usize];
+        (poly != u32::MAX).then_some(poly as usize)
+    }
+
+    /// Finds a path between two map positions. The path does not contain the
+    /// start position and ends at the destination, or at the closest walkable
+    /// cell if the destination itself is blocked.
+    pub fn find_path(&self, from: Vec2<f32>, to: Vec2<f32>) -> Option<Vec<Vec2<f32>>> {
+        let start_cell = self.nearest_walkable(self.cell_at(from), 2)?;
+        let goal_cell = self.cell_at(to);
+        let search_rings = (1.0 / self.cell_size).ceil() as i32 + 2;
+        let goal = self.nearest_walkable(goal_cell, search_rings)?;
+
+        let start_poly = self.poly_at(start_cell)?;
+        let goal_poly = self.poly_at(goal)?;
+        let from = if start_cell == self.cell_at(from) {
+            from
+        } else {
+            self.cell_center(start_cell.0, start_cell.1)
+        };
+        let to = if goal == goal_cell {
+            to
+        } else {
+            self.cell_center(goal.0, goal.1)
+        };
+
+        // A* over the polygons, a polygon is entered at the middle of a portal
+        let polys = &self.mesh.polys;
+        let mut g = vec![f32::MAX; polys.len()];
+        let mut entry = vec![from; polys.len()];
+        let mut came_from = vec![(usize::MAX, usize::MAX); polys.len()];
+        let mut open = BinaryHeap::new();
+
+        g[start_poly] = 0.0;
+        open.push(OpenNode {
+            cost: (to - from).magnitude(),
+            index: start_poly,
+        });
+
+        let mut found = false;
+        while let Some(OpenNode {
+            cost,
+            index: current,
+        }) = open.pop()
+        {
+            if current == goal_poly {
+                found = true;
+                break;
+            }
+            if cost - (to - entry[current]).magnitude() > g[current] + 1e-4 {
+                continue;
+            }
+
+            for (p, portal) in polys[current].portals.iter().enumerate() {
+                let middle = (portal.a + portal.b) * 0.5;
+                let cost = g[current] + (middle - entry[current]).magnitude();
+                if cost < g[portal.poly] {
+                    g[portal.poly] = cost;
+                    entry[portal.poly] = middle;
+                    came_from[portal.poly] = (current, p);
+                    open.push(OpenNode {
+                        cost: cost + (to - middle).magnitude(),
+                        index: portal.poly,
+                    });
+                }
+            }
+        }
+
+        if !found {
+            return None;
+        }
+
+        // The portals of the corridor, as seen when walking through them
+        let mut portals = vec![];
+        let mut current = goal_poly;
+        while current != start_poly {
+            let (previous, p) = came_from[current];
+            let poly = &polys[previous];
+            let portal = &poly.portals[p];
+            let center = self.cell_corner(poly.min.0, poly.min.1)
+                + (self.cell_corner(poly.max.0, poly.max.1)
+                    - self.cell_corner(poly.min.0, poly.min.1))
+                    * 0.5;
+            if cross(portal.a - center, portal.b - center) > 0.0 {
+                portals.push((portal.b, portal.a));
+            } else {
+                portals.push((portal.a, portal.b));
+            }
+            current = previous;
+        }
+        portals.reverse();
+
+        Some(funnel(from, to, &portals))
+    }
+
+    /// The map area covered by the grid.
+    fn grid_bounds(&self) -> NavBounds {
+        NavBounds {
+            min: self.origin,
+            max: self.origin + Vec2::new(self.width as f32, self.height as f32) * self.cell_size,
+        }
+    }
+
+    /// Resizes the grid to the geometry and bakes all cells.
+    fn rebuild(&mut self, map: &Map) {
+        self.terrain_row = 0;
+
+        let Some(bounds) = self.geometry.bounds() else {
+            self.width = 0;
+            self.height = 0;
+            self.cells.clear();
+            self.build_mesh();
+            return;
+        };
+
+        let mut cell_size = self.settings.cell_size.max(0.05);
+        let size = bounds.max - bounds.min;
+        let area = (size.x / cell_size + 4.0) * (size.y / cell_size + 4.0);
+        if area > MAX_CELLS {
+            cell_size *= (area / MAX_CELLS).sqrt();
+        }
+
+        let bounds = bounds.expanded(cell_size * 2.0);
+        self.cell_size = cell_size;
+        self.origin = (bounds.min / cell_size).map(|v| v.floor()) * cell_size;
+        self.width = ((bounds.max.x - self.origin.x) / cell_size).ceil() as i32;
+        self.height = ((bounds.max.y - self.origin.y) / cell_size).ceil() as i32;
+        self.cells = vec![NavCell::default(); (self.width * self.height) as usize];
+
+        self.bake_area(map, 0, 0, self.width, self.height);
+        self.build_mesh();
+    }
+
+    /// Bakes the cells in `x0..x1`, `y0..y1`.
+    fn bake_area(&mut self, map: &Map, x0: i32, y0: i32, x1: i32, y1: i32) {
+        if x0 >= x1 || y0 >= y1 {
+            return;
+        }
+        let geometry = std::mem::take(&mut self.geometry);
+        let area = NavBounds {
+            min: self.cell_corner(x0, y0),
+            max: self.cell_corner(x1, y1),
+        };
+        let max_slope = self.settings.max_slope.to_radians().tan();
+        let mut layers = vec![None; ((x1 - x0) * (y1 - y0)) as usize];
+
+        // Terrain
+        for y in y0..y1 {
+            for x in x0..x1 {
+                let p = self.cell_center(x, y);
+                let cell = match geometry.terrain {
+                    Some(t) if t.contains(&NavBounds { min: p, max: p }) => {
+                        let height = map.terrain.sample_height_bilinear(p.x, p.y);
+                        NavCell {
+                            walkable: self.terrain_slope(map, p) <= max_slope,
+                            height,
+                            walls: 0,
+                            terrain: true,
+                        }
+                    }
+                    _ => NavCell::default(),
+                };
+                self.cells[(y * self.width + x) as usize] = cell;
+            }
+        }
+
+        // Sectors, the highest layer wins
+        for sector in &geometry.sectors {
+            if !overlaps(&sector.bounds, &area) {
+                continue;
+            }
+            let normal = polygon_normal(&sector.points);
+            let steep = normal.z.abs() < 1e-4
+                || Vec2::new(normal.x, normal.y).magnitude() / normal.z.abs() > max_slope;
+            let (sx0, sy0) = self.cell_at(sector.bounds.min);
+            let (sx1, sy1) = self.cell_at(sector.bounds.max);
+            for y in sy0.max(y0)..(sy1 + 1).min(y1) {
+                for x in sx0.max(x0)..(sx1 + 1).min(x1) {
+                    let p = self.cell_center(x, y);
+                    if !point_in_polygon(p, &sector.points) {
+                        continue;
+                    }
+                    let layer = &mut layers[((y - y0) * (x1 - x0) + (x - x0)) as usize];
+                    if layer.is_some_and(|l| l > sector.layer) {
+                        continue;
+                    }
+                    *layer = Some(sector.layer);
+
+                    let p0 = sector.points[0];
+                    let height = if normal.z.abs() > 1e-4 {
+                        p0.z - (normal.x * (p.x - p0.x) + normal.y * (p.y - p0.y)) / normal.z
+                    } else {
+                        p0.z
+                    };
+                    self.cells[(y * self.width + x) as usize] = NavCell {
+                        walkable: !steep && !sector.blocking,
+                        height,
+                        walls: 0,
+                        terrain: false,
+                    };
+                }
+            }
+        }
+
+        // Walls block the cells within the agent radius and the steps across them
+        let reach = self.settings.agent_radius.max(self.cell_size * 1.5);
+        for wall in &geometry.walls {
+            let wall_area = wall.bounds.expanded(reach);
+            if !overlaps(&wall_area, &area) {
+                continue;
+            }
+            let (wx0, wy0) = self.cell_at(wall_area.min);
+            let (wx1, wy1) = self.cell_at(wall_area.max);
+            for y in wy0.max(y0)..(wy1 + 1).min(y1) {
+                for x in wx0.max(x0)..(wx1 + 1).min(x1) {
+                    let p = self.cell_center(x, y);
+                    let index = (y * self.width + x) as usize;
+                    if distance_to_segment(p, wall.a, wall.b) < self.settings.agent_radius {
+                        self.cells[index].walkable = false;
+                    }
+                    for (d, (dx, dy)) in DIRS.iter().enumerate() {
+                        let q = p + Vec2::new(*dx as f32, *dy as f32) * self.cell_size;
+                        if segments_intersect(p, q, wall.a, wall.b) {
+                            self.cells[index].walls |= 1 << d;
+                        }
+                    }
+                }
+            }
+        }
+
+        self.geometry = geometry;
+    }
+
+    /// Merges the walkable cells into the rectangles of the navigation mesh
+    /// and links neighbouring rectangles through the cells an agent can step
+    /// across.
+    fn build_mesh(&mut self) {
+        let mut mesh = NavMesh {
+            polys: vec![],
+            cell_polys: vec![u32::MAX; self.cells.len()],
+        };
+        let index = |x: i32, y: i32| (y * self.width + x) as usize;
+
+        for y in 0..self.height {
+            for x in 0..self.width {
+                let free = |mesh: &NavMesh, x: i32, y: i32| {
+                    self.cells[index(x, y)].walkable && mesh.cell_polys[index(x, y)] == u32::MAX
+                };
+                if !free(&mesh, x, y) {
+                    continue;
+                }
+
+                // Grow along the row, then add the rows above which connect
+                let mut x1 = x + 1;
+                while x1 < self.width.min(x + MAX_POLY_CELLS)
+                    && free(&mesh, x1, y)
+                    && self.can_step(x1 - 1, y, 0)
+                {
+                    x1 += 1;
+                }
+                let mut y1 = y + 1;
+                'grow: while y1 < self.height.min(y + MAX_POLY_CELLS) {
+                    for cx in x..x1 {
+                        if !free(&mesh, cx, y1)
+                            || !self.can_step(cx, y1 - 1, 2)
+                            || (cx > x && !self.can_step(cx - 1, y1, 0))
+                        {
+                            break 'grow;
+                        }
+                    }
+                    y1 += 1;
+                }
+
+                let poly = mesh.polys.len() as u32;
+                for cy in y..y1 {
+                    for cx in x..x1 {
+                        mesh.cell_polys[index(cx, cy)] = poly;
+                    }
+                }
+                mesh.polys.push(NavPoly {
+                    min: (x, y),
+                    max: (x1, y1),
+                    portals: vec![],
+                });
+            }
+        }
+
+        // The right and the upper edge of every polygon, the other edges are
+        // those of the neighbours
+        for p in 0..mesh.polys.len() {
+            let NavPoly { min, max, .. } = mesh.polys[p];
+            let mut edges = vec![];
+            for y in min.1..max.1 {
+                edges.push(((max.0 - 1, y), 0, (max.0, y), (max.0, y + 1)));
+            }
+            for x in min.0..max.0 {
+                edges.push(((x, max.1 - 1), 2, (x, max.1), (x + 1, max.1)));
+            }
+
+            let mut run: Option<(usize, (i32, i32), (i32, i32))> = None;
+            let mut runs = vec![];
+            for (cell, d, a, b) in edges {
+                let (dx, dy) = DIRS[d];
+                let neighbour = (cell.0 + dx, cell.1 + dy);
+                let q = self
+                    .cell(neighbour.0, neighbour.1)
+                    .map(|_| mesh.cell_polys[index(neighbour.0, neighbour.1)])
+                    .filter(|q| *q != u32::MAX && self.can_step(cell.0, cell.1, d))
+                    .map(|q| q as usize);
+                match (&mut run, q) {
+                    (Some((poly, _, end)), Some(q)) if *poly == q && *end == a => *end = b,
+                    (_, q) => {
+                        runs.extend(run.take());
+                        run = q.map(|q| (q, a, b));
+                    }
+                }
+            }
+            runs.extend(run);
+
+            for (q, a, b) in runs {
+                let (a, b) = (self.cell_corner(a.0, a.1), self.cell_corner(b.0, b.1));
+                mesh.polys[p].portals.push(NavPortal { poly: q, a, b });
+                mesh.polys[q].portals.push(NavPortal { poly: p, a, b });
+            }
+        }
+
+        self.mesh = mesh;
+    }
+
+    /// Compares a few rows of terrain cells with the terrain and returns the
+    /// positions which changed.
+    fn changed_terrain(&mut self, map: &Map) -> Vec<NavBounds> {
+        let mut changed = vec![];
+        for _ in 0..TERRAIN_ROWS_PER_UPDATE.min(self.height) {
+            let y = self.terrain_row;
+            self.terrain_row = (self.terrain_row + 1) % self.height;
+            for x in 0..self.width {
+                let cell = self.cells[(y * self.width + x) as usize];
+                let p = self.cell_center(x, y);
+                if cell.terrain
+                    && (cell.height - map.terrain.sample_height_bilinear(p.x, p.y)).abs() > 1e-3
+                {
+                    changed.push(NavBounds { min: p, max: p });
+                }
+            }
+        }
+        changed
+    }
+
+    /// The gradient of the terrain at the given position.
+    fn terrain_slope(&self, map: &Map, p: Vec2<f32>) -> f32 {
+        let e = self.cell_size * 0.5;
+        let dx = map.terrain.sample_height_bilinear(p.x + e, p.y)
+            - map.terrain.sample_height_bilinear(p.x - e, p.y);
+        let dy = map.terrain.sample_height_bilinear(p.x, p.y + e)
+            - map.terrain.sample_height_bilinear(p.x, p.y - e);
+        Vec2::new(dx, dy).magnitude() / (2.0 * e)
+    }
+
+    /// The closest walkable cell within the given number of rings.
+    fn nearest_walkable(&self, (x, y): (i32, i32), rings: i32) -> Option<(i32, i32)> {
+        for ring in 0..=rings {
+            let mut best: Option<((i32, i32), i32)> = None;
+            for cy in y - ring..=y + ring {
+                for cx in x - ring..=x + ring {
+                    if (cx - x).abs() != ring && (cy - y).abs() != ring {
+                        continue;
+                    }
+                    if self.cell(cx, cy).is_some_and(|c| c.walkable) {
+                        let d = (cx - x).pow(2) + (cy - y).pow(2);
+                        if best.is_none_or(|(_, bd)| d < bd) {
+                            best = Some(((cx, cy), d));
+                        }
+                    }
+                }
+            }
+            if let Some((cell, _)) = best {
+                return Some(cell);
+            }
+        }
+        None
+    }
+
+    /// Returns true if an agent can step from the cell into the neighbour in
+    /// the given direction. Diagonal steps must not cut corners.
+    fn can_step(&self, x: i32, y: i32, d: usize) -> bool {
+        let (dx, dy) = DIRS[d];
+        let (Some(a), Some(b)) = (self.cell(x, y), self.cell(x + dx, y + dy)) else {
+            return false;
+        };
+        if !a.walkable || !b.walkable || a.walls & (1 << d) != 0 {
+            return false;
+        }
+        if (a.height - b.height).abs() > self.settings.max_step {
+            return false;
+        }
+        if dx != 0 && dy != 0 {
+            let horizontal = if dx > 0 { 0 } else { 4 };
+            let vertical = if dy > 0 { 2 } else { 6 };
+            return self.can_step(x, y, horizontal) && self.can_step(x, y, vertical);
+        }
+        true
+    }
+}
+
+/// A key of the geometry of a map, the navigation geometry is only read
+/// again when it changes. Property edits are picked up with the change
+/// counter of the map.
+fn geometry_key(map: &Map) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    map.id.hash(&mut hasher);
+    map.changed.hash(&mut hasher);
+    for v in &map.vertices {
+        v.id.hash(&mut hasher);
+        hash_vec3(&mut hasher, Vec3::new(v.x, v.y, v.z));
+    }
+    for l in &map.linedefs {
+        (l.id, l.start_vertex, l.end_vertex).hash(&mut hasher);
+        l.properties
+            .get_float_default("wall_height", 0.0)
+            .to_bits()
+            .hash(&mut hasher);
+    }
+    for s in &map.sectors {
+        (s.id, s.layer).hash(&mut hasher);
+        s.linedefs.hash(&mut hasher);
+    }
+    map.terrain.compute_bounds().is_some().hash(&mut hasher);
+    hasher.finish()
+}
+
+fn cross(a: Vec2<f32>, b: Vec2<f32>) -> f32 {
+    a.x * b.y - a.y * b.x
+}
+
+/// Straightens the path through the portals of a corridor, given as (left,
+/// right) pairs in walking direction (the simple stupid funnel algorithm).
+/// The path does not contain the start position.
+fn funnel(from: Vec2<f32>, to: Vec2<f32>, portals: &[(Vec2<f32>, Vec2<f32>)]) -> Vec<Vec2<f32>> {
+    let mut portals = portals.to_vec();
+    portals.push((to, to));
+
+    let mut path = vec![];
+    let mut apex = from;
+    let (mut left, mut right) = (from, from);
+    let (mut left_index, mut right_index) = (0, 0);
+    let mut i = 0;
+    while i < portals.len() {
+        let (portal_left, portal_right) = portals[i];
+
+        // Narrow the funnel from the right
+        if cross(right - apex, portal_right - apex) >= 0.0 {
+            if apex == right || cross(left - apex, portal_right - apex) < 0.0 {
+                right = portal_right;
+                right_index = i;
+            } else {
+                // The right side crosses the left one, its corner is a turn
+                apex = left;
+                path.push(apex);
+                right = apex;
+                right_index = left_index;
+                i = left_index + 1;
+                continue;
+            }
+        }
+
+        // Narrow the funnel from the left
+        if cross(left - apex, portal_left - apex) <= 0.0 {
+            if apex == left || cross(right - apex, portal_left - apex) > 0.0 {
+                left = portal_left;
+                left_index = i;
+            } else {
+                apex = right;
+                path.push(apex);
+                left = apex;
+                left_index = right_index;
+                i = right_index + 1;
+                continue;
+            }
+        }
+        i += 1;
+    }
+
+    if path.last() != Some(&to) {
+        path.push(to);
+    }
+    path
+}
+
+fn overlaps(a: &NavBounds, b: &NavBounds) -> bool {
+    a.min.x <= b.max.x && a.max.x >= b.min.x && a.min.y <= b.max.y && a.max.y >= b.min.y
+}
+
+/// The normal of a polygon with map plane coordinates and height in z.
+fn polygon_normal(points: &[Vec3<f32>]) -> Vec3<f32> {
+    let mut normal = Vec3::zero();
+    for (i, a) in points.iter().enumerate() {
+        let b = points[(i + 1) % points.len()];
+        normal.x += (a.y - b.y) * (a.z + b.z);
+        normal.y += (a.z - b.z) * (a.x + b.x);
+        normal.z += (a.x - b.x) * (a.y + b.y);
+    }
+    normal.try_normalized().unwrap_or(Vec3::unit_z())
+}
+
+fn point_in_polygon(p: Vec2<f32>, points: &[Vec3<f32>]) -> bool {
+    let mut inside = false;
+    let mut j = points.len() - 1;
+    for (i, a) in points.iter().enumerate() {
+        let b = points[j];
+        if (a.y > p.y) != (b.y > p.y) && p.x < (b.x - a.x) * (p.y - a.y) / (b.y - a.y) + a.x {
+            inside = !inside;
+        }
+        j = i;
+    }
+    inside
+}
+
+fn distance_to_segment(p: Vec2<f32>, a: Vec2<f32>, b: Vec2<f32>) -> f32 {
+    let ab = b - a;
+    let t = if ab.magnitude_squared() > 0.0 {
+        ((p - a).dot(ab) / ab.magnitude_squared()).clamp(0.0, 1.0)
+    } else {
+        0.0
+    };
+    (p - (a + ab * t)).magnitude()
+}
+
+fn segments_intersect(p1: Vec2<f32>, p2: Vec2<f32>, q1: Vec2<f32>, q2: Vec2<f32>) -> bool {
+    let r = p2 - p1;
+    let s = q2 - q1;
+    let denom = cross(r, s);
+    if denom.abs() < 1e-8 {
+        return false;
+    }
+    let t = cross(q1 - p1, s) / denom;
+    let u = cross(q1 - p1, r) / denom;
+    (0.0..=1.0).contains(&t) && (0.0..=1.0).contains(&u)
+}
+
+/// The path of a character which is on its way.
+struct NavAgent {
+    goal: String,
+    target: Vec2<f32>,
+    path: Vec<Vec2<f32>>,
+}
+
+/// Moves characters which requested a destination via the `navigation` script
+/// library along the navigation mesh of their region. The meshes are baked the
+/// first time a character of a region starts walking and rebaked
+/// incrementally. The characters move on the game ticks, which keeps their
+/// speed independent of the frame rate.
+#[derive(Default)]
+pub struct Navigation {
+    grids: FxHashMap<Uuid, NavGrid>,
+    agents: FxHashMap<(Uuid, u32), NavAgent>,
+    /// The length of a game tick in seconds.
+    tick_seconds: f32,
+    /// The game ticks since the server started.
+    ticks: u64,
+    /// The tick each region was last advanced to.
+    advanced: FxHashMap<Uuid, u64>,
+}
+
+impl Navigation {
+    /// Forgets all grids and paths and sets the length of a game tick, called
+    /// when the server starts.
+    pub fn start(&mut self, tick_ms: u32) {
+        *self = Self {
+            tick_seconds: tick_ms.max(1) as f32 / 1000.0,
+            ..Default::default()
+        };
+    }
+
+    /// Advances the navigation clock, called on every game tick.
+    pub fn tick(&mut self) {
+        self.ticks += 1;
+    }
+
+    /// Returns the navigation mesh of the region, baking it or updating the
+    /// areas which changed. `geometry` is the map the region is running with.
+    pub fn grid(
+        &mut self,
+        region: &Region,
+        geometry: &Map,
+        tiles: &IndexMap<Uuid, rusterix::Tile>,
+    ) -> &NavGrid {
+        let settings = NavSettings::from_config(&region.config);
+        let mut changed = true;
+        match self.grids.get_mut(&region.id) {
+            Some(grid) if grid.settings == settings => changed = grid.update(geometry, tiles),
+            _ => {
+                self.grids
+                    .insert(region.id, NavGrid::bake(geometry, tiles, settings));
+            }
+        }
+        if changed {
+            // Paths are searched again on the changed grid
+            for ((region_id, _), agent) in self.agents.iter_mut() {
+                if *region_id == region.id {
+                    agent.path.clear();
+                }
+            }
+        }
+        &self.grids[&region.id]
+    }
+
+    /// Advances the characters of the region by the game ticks since its last
+    /// update. `geometry` is the map the region is running with if it differs
+    /// from the region map.
+    pub fn update(
+        &mut self,
+        rusterix: &mut Rusterix,
+        region: &mut Region,
+        geometry: Option<&Map>,
+        tiles: &IndexMap<Uuid, rusterix::Tile>,
+    ) {
+        let advanced = self.advanced.entry(region.id).or_insert(self.ticks);
+        let ticks = self.ticks - *advanced;
+        if ticks == 0 {
+            return;
+        }
+        *advanced = self.ticks;
+
+        let walkers: Vec<(u32, String, f32, Vec2<f32>)> = region
+            .map
+            .entities
+            .iter()
+            .filter_map(|e| {
+                let goal = e.attributes.get_str_default(NAV_GOAL, String::new());
+                (!goal.is_empty()).then(|| {
+                    (
+                        e.id,
+                        goal,
+                        e.attributes.get_float_default(NAV_SPEED, 2.0),
+                        e.get_pos_xz(),
+                    )
+                })
+            })
+            .collect();
+
+        let region_id = region.id;
+        self.agents
+            .retain(|(r, id), _| *r != region_id || walkers.iter().any(|w| w.0 == *id));
+        if walkers.is_empty() {
+            return;
+        }
+
+        self.grid(region, geometry.unwrap_or(&region.map), tiles);
+        let grid = &self.grids[&region_id];
+
+        let mut edits = vec![];
+        for (id, goal, speed, mut pos) in walkers {
+            let Some(target) = resolve_goal(geometry.unwrap_or(&region.map), &region.map, &goal)
+            else {
+                edits.extend(finish(id, "unreachable"));
+                self.agents.remove(&(region_id, id));
+                continue;
+            };
+
+            let agent = self
+                .agents
+                .entry((region_id, id))
+                .or_insert_with(|| NavAgent {
+                    goal: String::new(),
+                    target,
+                    path: vec![],
+                });
+
+            // Search a new path for new goals and moving targets
+            let arrival = if goal.starts_with('#') { 1.0 } else { 0.05 };
+            if agent.goal != goal
+                || agent.path.is_empty()
+                || (agent.target - target).magnitude() > arrival
+            {
+                if agent.goal != goal {
+                    edits.push(InstanceEdit::EntityAttribute(
+                        id,
+                        NAV_STATE.into(),
+                        Value::Str("moving".into()),
+                    ));
+                }
+                agent.goal = goal;
+                agent.target = target;
+                match grid.find_path(pos, target) {
+                    Some(path) => agent.path = path,
+                    None => {
+                        edits.extend(finish(id, "unreachable"));
+                        self.agents.remove(&(region_id, id));
+                        continue;
+                    }
+                }
+            }
+
+            let mut step = speed * self.tick_seconds * ticks as f32;
+            while step > 0.0 && !agent.path.is_empty() {
+                let delta = agent.path[0] - pos;
+                let length = delta.magnitude();
+                if length <= step {
+                    pos = agent.path.remove(0);
+                    step -= length;
+                } else {
+                    pos += delta / length * step;
+                    step = 0.0;
+                }
+            }
+
+            if let Some(entity) = region.map.entities.iter().find(|e| e.id == id) {
+                edits.push(InstanceEdit::EntityPosition(
+                    id,
+                    Vec3::new(pos.x, entity.position.y, pos.y),
+                ));
+            }
+
+            if agent.path.is_empty() || (pos - target).magnitude() <= arrival {
+                edits.extend(finish(id, "arrived"));
+                self.agents.remove(&(region_id, id));
+            }
+        }
+
+        for edit in &edits {
+            apply_instance_edit(rusterix, &mut region.map, edit);
+        }
+    }
+}
+
+/// The edits which end the walk of a character with the given state.
+fn finish(id: u32, state: &str) -> [InstanceEdit; 2] {
+    [
+        InstanceEdit::EntityAttribute(id, NAV_GOAL.into(), Value::Str(String::new())),
+        InstanceEdit::EntityAttribute(id, NAV_STATE.into(), Value::Str(state.into())),
+    ]
+}
+
+/// The map position of a goal: `#<entity id>`, `x,y` or the name of a sector.
+fn resolve_goal(geometry: &Map, map: &Map, goal: &str) -> Option<Vec2<f32>> {
+    if let Some(id) = goal.strip_prefix('#') {
+        let id: u32 = id.trim().parse().ok()?;
+        return map
+            .entities
+            .iter()
+            .find(|e| e.id == id)
+            .map(|e| e.get_pos_xz());
+    }
+    if let Some((x, y)) = goal.split_once(',')
+        && let (Ok(x), Ok(y)) = (x.trim().parse(), y.trim().parse())
+    {
+        return Some(Vec2::new(x, y));
+    }
+
+    let sector = geometry.sectors.iter().find(|s| s.name == goal)?;
+    let points = sector.vertices_world(geometry)?;
+    if points.is_empty() {
+        return None;
+    }
+    let sum = points
+        .iter()
+        .fold(Vec2::zero(), |acc, p| acc + Vec2::new(p.x, p.z));
+    Some(sum / points.len() as f32)
+}
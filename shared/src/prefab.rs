@@ -0,0 +1,349 @@
+use crate::prelude::*;
+use rusterix::{Map, PixelSource, Value, ValueContainer};
+use std::collections::HashSet;
+use theframework::prelude::*;
+
+/// The width and height of prefab thumbnails.
+pub const PREFAB_THUMBNAIL_SIZE: usize = 64;
+
+/// The first line of map geometry in the system clipboard.
+pub const MAP_CLIPBOARD_HEADER: &str = "eldiron-map-geometry/1";
+
+/// A reusable piece of map geometry. Prefabs keep the properties of their
+/// vertices, linedefs and sectors, the referenced shapefx graphs and the
+/// referenced tiles so they can be moved between projects.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Prefab {
+    pub id: Uuid,
+    pub name: String,
+
+    /// The geometry as created by `Map::copy_selected`.
+    pub map: Map,
+
+    /// The tiles referenced by the geometry.
+    #[serde(default)]
+    pub tiles: IndexMap<Uuid, rusterix::Tile>,
+
+    /// RGBA pixels of the thumbnail, `PREFAB_THUMBNAIL_SIZE` squared.
+    #[serde(default)]
+    pub thumbnail: Vec<u8>,
+}
+
+impl Prefab {
+    /// Creates a prefab from the selected geometry of the map.
+    pub fn from_selection(name: String, map: &Map, project: &Project) -> Option<Self> {
+        if !map.has_selection() {
+            return None;
+        }
+
+        let mut prefab = Self {
+            id: Uuid::new_v4(),
+            name,
+            map: Map::default(),
+            tiles: IndexMap::default(),
+            thumbnail: vec![],
+        };
+        prefab.set_geometry(map, project);
+        Some(prefab)
+    }
+
+    /// Replaces the geometry of the prefab with the selected geometry of the map.
+    pub fn set_geometry(&mut self, map: &Map, project: &Project) {
+        let mut geometry = map.copy_selected(false);
+
+        let (tiles, graphs) = referenced_sources(&geometry);
+        for id in graphs {
+            if let Some(graph) = map.shapefx_graphs.get(&id) {
+                geometry.shapefx_graphs.insert(id, graph.clone());
+            }
+        }
+        self.tiles.clear();
+        for id in tiles {
+            if let Some(tile) = project.tiles.get(&id) {
+                self.tiles.insert(id, tile.clone());
+            }
+        }
+
+        self.map = geometry;
+        self.render_thumbnail();
+    }
+
+    /// The tiles of the prefab which are missing in the project.
+    pub fn missing_tiles(&self, project: &Project) -> IndexMap<Uuid, rusterix::Tile> {
+        self.tiles
+            .iter()
+            .filter(|(id, _)| !project.tiles.contains_key(*id))
+            .map(|(id, tile)| (*id, tile.clone()))
+            .collect()
+    }
+
+    /// Adds the tiles of the prefab which are missing in the project.
+    pub fn import_tiles(&self, project: &mut Project) -> usize {
+        let tiles = self.missing_tiles(project);
+        let count = tiles.len();
+        project.tiles.extend(tiles);
+        count
+    }
+
+    /// Places the prefab into the map at the given position. Linked instances
+    /// are tagged so that they can be updated when the prefab changes.
+    pub fn place(&self, map: &mut Map, position: Vec2<f32>, linked: bool) {
+        let instance = if linked {
+            Some(Uuid::new_v4().to_string())
+        } else {
+            None
+        };
+        self.paste(map, position, instance);
+    }
+
+    /// Replaces all linked instances of the prefab in the map with the current
+    /// geometry. Returns the number of updated instances.
+    pub fn update_instances(&self, map: &mut Map) -> usize {
+        let prefab_id = self.id.to_string();
+
+        let mut instances: IndexMap<String, Vec2<f32>> = IndexMap::default();
+        let mut collect = |properties: &ValueContainer| {
+            if let Some((prefab, instance, anchor)) = instance_tag(properties) {
+                if prefab == prefab_id {
+                    instances.entry(instance).or_insert(anchor);
+                }
+            }
+        };
+        map.vertices.iter().for_each(|v| collect(&v.properties));
+        map.linedefs.iter().for_each(|l| collect(&l.properties));
+        map.sectors.iter().for_each(|s| collect(&s.properties));
+
+        for (instance, anchor) in &instances {
+            let tagged = |properties: &ValueContainer| {
+                instance_tag(properties)
+                    .map(|(prefab, i, _)| prefab == prefab_id && i == *instance)
+                    .unwrap_or(false)
+            };
+
+            let sectors: Vec<u32> = map
+                .sectors
+                .iter()
+                .filter(|s| tagged(&s.properties))
+                .map(|s| s.id)
+                .collect();
+            let linedefs: Vec<u32> = map
+                .linedefs
+                .iter()
+                .filter(|l| tagged(&l.properties))
+                .map(|l| l.id)
+                .collect();
+
+            // Vertices shared with other geometry stay in place
+            let used: HashSet<u32> = map
+                .linedefs
+                .iter()
+                .filter(|l| !linedefs.contains(&l.id))
+                .flat_map(|l| [l.start_vertex, l.end_vertex])
+                .collect();
+            let vertices: Vec<u32> = map
+                .vertices
+                .iter()
+                .filter(|v| tagged(&v.properties) && !used.contains(&v.id))
+                .map(|v| v.id)
+                .collect();
+
+            map.delete_elements(&vertices, &linedefs, &sectors);
+            map.surfaces.retain(|_, s| !sectors.contains(&s.sector_id));
+
+            self.paste(map, *anchor, Some(instance.clone()));
+        }
+
+        instances.len()
+    }
+
+    fn paste(&self, map: &mut Map, position: Vec2<f32>, instance: Option<String>) {
+        let vertices: HashSet<u32> = map.vertices.iter().map(|v| v.id).collect();
+        let linedefs: HashSet<u32> = map.linedefs.iter().map(|l| l.id).collect();
+        let sectors: HashSet<u32> = map.sectors.iter().map(|s| s.id).collect();
+
+        map.paste_at_position(&self.map, position);
+        for (id, graph) in &self.map.shapefx_graphs {
+            if !map.shapefx_graphs.contains_key(id) {
+                map.shapefx_graphs.insert(*id, graph.clone());
+            }
+        }
+
+        if let Some(instance) = instance {
+            let tag = |properties: &mut ValueContainer| {
+                properties.set("prefab", Value::Str(self.id.to_string()));
+                properties.set("prefab_instance", Value::Str(instance.clone()));
+                properties.set("prefab_x", Value::Float(position.x));
+                properties.set("prefab_y", Value::Float(position.y));
+            };
+            for vertex in map.vertices.iter_mut() {
+                if !vertices.contains(&vertex.id) {
+                    tag(&mut vertex.properties);
+                }
+            }
+            for linedef in map.linedefs.iter_mut() {
+                if !linedefs.contains(&linedef.id) {
+                    tag(&mut linedef.properties);
+                }
+            }
+            for sector in map.sectors.iter_mut() {
+                if !sectors.contains(&sector.id) {
+                    tag(&mut sector.properties);
+                }
+            }
+        }
+
+        map.update_surfaces();
+    }
+
+    /// Draws the sectors and linedefs of the prefab into the thumbnail.
+    pub fn render_thumbnail(&mut self) {
+        let size = PREFAB_THUMBNAIL_SIZE;
+        let mut pixels = vec![0_u8; size * size * 4];
+
+        let mut min = Vec2::broadcast(f32::MAX);
+        let mut max = Vec2::broadcast(f32::MIN);
+        for vertex in &self.map.vertices {
+            min = Vec2::partial_min(min, Vec2::new(vertex.x, vertex.y));
+            max = Vec2::partial_max(max, Vec2::new(vertex.x, vertex.y));
+        }
+        if min.x > max.x {
+            self.thumbnail = pixels;
+            return;
+        }
+
+        let margin = 4.0;
+        let extent = (max - min).reduce_partial_max().max(0.001);
+        let scale = (size as f32 - margin * 2.0) / extent;
+        let offset = (Vec2::broadcast(size as f32) - (max - min) * scale) * 0.5;
+        // Map y points up, the thumbnail y points down
+        let to_pixel = |x: f32, y: f32| -> Vec2<f32> {
+            Vec2::new(
+                offset.x + (x - min.x) * scale,
+                size as f32 - (offset.y + (y - min.y) * scale),
+            )
+        };
+
+        let edge = |linedef_id: u32| -> Option<(Vec2<f32>, Vec2<f32>)> {
+            let linedef = self.map.find_linedef(linedef_id)?;
+            let a = self.map.find_vertex(linedef.start_vertex)?;
+            let b = self.map.find_vertex(linedef.end_vertex)?;
+            Some((to_pixel(a.x, a.y), to_pixel(b.x, b.y)))
+        };
+
+        // Fill the sectors (even-odd, independent of the edge order)
+        for sector in &self.map.sectors {
+            let edges: Vec<(Vec2<f32>, Vec2<f32>)> =
+                sector.linedefs.iter().filter_map(|id| edge(*id)).collect();
+            for y in 0..size {
+                for x in 0..size {
+                    let p = Vec2::new(x as f32 + 0.5, y as f32 + 0.5);
+                    let mut inside = false;
+                    for (a, b) in &edges {
+                        if (a.y > p.y) != (b.y > p.y)
+                            && p.x < (b.x - a.x) * (p.y - a.y) / (b.y - a.y) + a.x
+                        {
+                            inside = !inside;
+                        }
+                    }
+                    if inside {
+                        let i = (y * size + x) * 4;
+                        pixels[i..i + 4].copy_from_slice(&[90, 110, 130, 255]);
+                    }
+                }
+            }
+        }
+
+        // Outline
+        for linedef in &self.map.linedefs {
+            if let Some((a, b)) = edge(linedef.id) {
+                let steps = (b - a)
+                    .map(|v| v.abs())
+                    .reduce_partial_max()
+                    .ceil()
+                    .max(1.0) as usize;
+                for s in 0..=steps {
+                    let p = a + (b - a) * (s as f32 / steps as f32);
+                    let (x, y) = (p.x as isize, p.y as isize);
+                    if x >= 0 && y >= 0 && (x as usize) < size && (y as usize) < size {
+                        let i = (y as usize * size + x as usize) * 4;
+                        pixels[i..i + 4].copy_from_slice(&[220, 220, 220, 255]);
+                    }
+                }
+            }
+        }
+
+        self.thumbnail = pixels;
+    }
+
+    /// Serializes the geometry as tagged text for the system clipboard.
+    pub fn to_clipboard_text(&self) -> String {
+        let mut fragment = self.clone();
+        fragment.thumbnail.clear();
+        format!("{}\n{}", MAP_CLIPBOARD_HEADER, fragment.to_json())
+    }
+
+    /// Parses geometry copied to the system clipboard by `to_clipboard_text`.
+    pub fn from_clipboard_text(text: &str) -> Option<Self> {
+        let (header, json) = text.split_once('\n')?;
+        if header.trim() != MAP_CLIPBOARD_HEADER {
+            return None;
+        }
+        Self::from_json(json)
+    }
+
+    pub fn from_json(json: &str) -> Option<Self> {
+        serde_json::from_str(json).ok()
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(&self).unwrap_or_default()
+    }
+}
+
+/// Returns the prefab id, the instance id and the anchor of a linked element.
+fn instance_tag(properties: &ValueContainer) -> Option<(String, String, Vec2<f32>)> {
+    let Some(Value::Str(prefab)) = properties.get("prefab") else {
+        return None;
+    };
+    let Some(Value::Str(instance)) = properties.get("prefab_instance") else {
+        return None;
+    };
+    let x = match properties.get("prefab_x") {
+        Some(Value::Float(x)) => *x,
+        _ => 0.0,
+    };
+    let y = match properties.get("prefab_y") {
+        Some(Value::Float(y)) => *y,
+        _ => 0.0,
+    };
+    Some((prefab.clone(), instance.clone(), Vec2::new(x, y)))
+}
+
+/// The tile and shapefx graph ids referenced by the properties of the map.
+fn referenced_sources(map: &Map) -> (Vec<Uuid>, Vec<Uuid>) {
+    let mut tiles = vec![];
+    let mut graphs = vec![];
+
+    let mut scan = |properties: &ValueContainer| {
+        for key in properties.keys_sorted() {
+            match properties.get(key) {
+                Some(Value::Source(PixelSource::TileId(id))) => {
+                    if !tiles.contains(id) {
+                        tiles.push(*id);
+                    }
+                }
+                Some(Value::Source(PixelSource::ShapeFXGraphId(id))) => {
+                    if !graphs.contains(id) {
+                        graphs.push(*id);
+                    }
+                }
+                _ => {}
+            }
+        }
+    };
+    map.vertices.iter().for_each(|v| scan(&v.properties));
+    map.linedefs.iter().for_each(|l| scan(&l.properties));
+    map.sectors.iter().for_each(|s| scan(&s.properties));
+
+    (tiles, graphs)
+}
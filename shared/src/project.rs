@@ -47,9 +47,29 @@ pub struct Project {
     #[serde(default)]
     pub screens: IndexMap<Uuid, Screen>,
 
+    /// Shared script libraries
+    #[serde(default)]
+    pub libraries: IndexMap<Uuid, ScriptLibrary>,
+
+    /// Unit tests for the character and item classes
+    #[serde(default)]
+    pub tests: IndexMap<Uuid, ScriptTest>,
+
+    /// Branching dialogues, started by scripts
+    #[serde(default)]
+    pub dialogues: IndexMap<Uuid, Dialogue>,
+
+    /// Quests with stages, objectives and rewards
+    #[serde(default)]
+    pub quests: IndexMap<Uuid, Quest>,
+
     #[serde(default)]
     pub assets: IndexMap<Uuid, Asset>,
 
+    /// Reusable map geometry
+    #[serde(default)]
+    pub prefabs: IndexMap<Uuid, Prefab>,
+
     #[serde(default)]
     pub palette: ThePalette,
 
@@ -70,6 +90,14 @@ pub struct Project {
 
     #[serde(default)]
     pub render_graph: ShapeFXGraph,
+
+    /// The string tables of the supported languages.
+    #[serde(default)]
+    pub localization: Localization,
+
+    /// The maps of regions generated when the server started.
+    #[serde(skip)]
+    pub generated_maps: IndexMap<Uuid, Map>,
 }
 
 impl Default for Project {
@@ -112,7 +140,12 @@ impl Project {
             items: IndexMap::default(),
 
             screens: IndexMap::default(),
+            libraries: IndexMap::default(),
+            tests: IndexMap::default(),
+            dialogues: IndexMap::default(),
+            quests: IndexMap::default(),
             assets: IndexMap::default(),
+            prefabs: IndexMap::default(),
 
             palette: ThePalette::default(),
             models,
@@ -123,6 +156,9 @@ impl Project {
 
             config: String::new(),
             render_graph,
+
+            localization: Localization::default(),
+            generated_maps: IndexMap::default(),
         }
     }
 
@@ -175,6 +211,40 @@ impl Project {
         self.tilemaps.push(tilemap)
     }
 
+    /// Creates the render tiles for all tiles of the given tilemap.
+    pub fn add_tilemap_tiles(&mut self, uuid: Uuid) {
+        let Some(tilemap) = self.tilemaps.iter().find(|t| t.id == uuid) else {
+            return;
+        };
+        for tile in &tilemap.tiles {
+            let textures = tilemap
+                .buffer
+                .extract_sequence(&tile.sequence)
+                .iter()
+                .map(|b| {
+                    let mut texture = rusterix::Texture::new(
+                        b.pixels().to_vec(),
+                        b.dim().width as usize,
+                        b.dim().height as usize,
+                    );
+                    texture.generate_normals(true);
+                    texture
+                })
+                .collect();
+            let mut render_tile = rusterix::Tile {
+                id: tile.id,
+                role: tile.role,
+                textures,
+                module: None,
+                blocking: tile.blocking,
+                scale: tile.scale,
+                tags: tile.name.clone(),
+            };
+            render_tile.set_default_materials();
+            self.tiles.insert(tile.id, render_tile);
+        }
+    }
+
     /// Get the tilemap of the given uuid.
     pub fn get_tilemap(&self, uuid: Uuid) -> Option<&Tilemap> {
         self.tilemaps.iter().find(|t| t.id == uuid)
@@ -340,6 +410,87 @@ impl Project {
         entries
     }
 
+    /// Add a script library
+    pub fn add_library(&mut self, library: ScriptLibrary) {
+        self.libraries.insert(library.id, library);
+    }
+
+    /// Removes the given script library from the project.
+    pub fn remove_library(&mut self, id: &Uuid) {
+        self.libraries.shift_remove(id);
+    }
+
+    /// Returns a list of all script libraries sorted by name.
+    pub fn sorted_library_list(&self) -> Vec<(Uuid, String)> {
+        let mut entries: Vec<(Uuid, String)> = self
+            .libraries
+            .iter()
+            .map(|(uuid, data)| (*uuid, data.name.clone()))
+            .collect();
+
+        entries.sort_by(|a, b| a.1.cmp(&b.1));
+        entries
+    }
+
+    /// The project libraries and the built-in libraries which are not
+    /// overridden by the project.
+    fn script_libraries(&self) -> IndexMap<Uuid, ScriptLibrary> {
+        let mut libraries = self.libraries.clone();
+        for library in ScriptLibrary::builtin() {
+            if !libraries.values().any(|l| l.name == library.name) {
+                libraries.insert(library.id, library);
+            }
+        }
+        libraries
+    }
+
+    /// Resolves the script libraries imported by the given source, including
+    /// the built-in libraries which are not overridden by the project.
+    pub fn resolve_script_imports(&self, source: &str) -> String {
+        ScriptLibrary::resolve_imports(source, &self.script_libraries())
+    }
+
+    /// The source as run by the game: with the imported libraries resolved and
+    /// the messages in the active locale. The messages are localized before
+    /// the libraries are embedded.
+    pub fn runtime_source(&self, source: &str) -> String {
+        let mut libraries = self.script_libraries();
+        for library in libraries.values_mut() {
+            library.source = self.localization.localize_source(&library.source);
+        }
+        ScriptLibrary::resolve_imports(&self.localization.localize_source(source), &libraries)
+    }
+
+    /// Add a script test
+    pub fn add_test(&mut self, test: ScriptTest) {
+        self.tests.insert(test.id, test);
+    }
+
+    /// Removes the given script test from the project.
+    pub fn remove_test(&mut self, id: &Uuid) {
+        self.tests.shift_remove(id);
+    }
+
+    /// Add a dialogue
+    pub fn add_dialogue(&mut self, dialogue: Dialogue) {
+        self.dialogues.insert(dialogue.id, dialogue);
+    }
+
+    /// Removes the given dialogue from the project.
+    pub fn remove_dialogue(&mut self, id: &Uuid) {
+        self.dialogues.shift_remove(id);
+    }
+
+    /// Add a quest
+    pub fn add_quest(&mut self, quest: Quest) {
+        self.quests.insert(quest.id, quest);
+    }
+
+    /// Removes the given quest from the project.
+    pub fn remove_quest(&mut self, id: &Uuid) {
+        self.quests.shift_remove(id);
+    }
+
     /// Add an asset
     pub fn add_asset(&mut self, asset: Asset) {
         self.assets.insert(asset.id, asset);
@@ -0,0 +1,832 @@
+use crate::levelimport::resolve_relative_path;
+use crate::prelude::*;
+use base64::Engine;
+use rusterix::{Map, PixelSource, Value, ValueContainer};
+use serde_json::Value as Json;
+use std::path::Path;
+use theframework::prelude::*;
+
+/// Property of the generated prop geometry holding the id of the instance.
+pub const PROP_INSTANCE: &str = "prop_instance";
+
+/// A triangle of a prop in model space, y points up. Textures are baked into
+/// a flat color per triangle.
+#[derive(Serialize, Deserialize, PartialEq, Clone, Debug)]
+pub struct PropTriangle {
+    pub points: [[f32; 3]; 3],
+    pub color: [u8; 4],
+}
+
+/// An imported static mesh. The mesh is centered on its base, the size is
+/// the extent of its bounding box.
+#[derive(Serialize, Deserialize, PartialEq, Clone, Debug, Default)]
+pub struct PropMesh {
+    pub triangles: Vec<PropTriangle>,
+    pub size: [f32; 3],
+}
+
+/// The collision shape of a prop instance, independent of its triangles.
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Debug, Default)]
+pub enum PropCollision {
+    /// The prop does not block movement.
+    #[default]
+    None,
+    /// The footprint of the bounding box.
+    Box,
+    /// A cylinder around the bounding box, for round props.
+    Cylinder,
+}
+
+/// The placement of a prop instance. The rotation is in degrees around the
+/// vertical axis, the elevation is the height of the base.
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Debug)]
+pub struct PropTransform {
+    pub rotation: f32,
+    pub scale: f32,
+    pub elevation: f32,
+    #[serde(default)]
+    pub collision: PropCollision,
+}
+
+impl Default for PropTransform {
+    fn default() -> Self {
+        Self {
+            rotation: 0.0,
+            scale: 1.0,
+            elevation: 0.0,
+            collision: PropCollision::None,
+        }
+    }
+}
+
+/// A mesh asset placed in a region. Like characters and items, props are
+/// instances of the region and not part of its map, the geometry for
+/// rendering and the collision shape are derived from the instance.
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+pub struct PropInstance {
+    pub id: Uuid,
+    /// The id of the mesh asset.
+    pub asset: Uuid,
+    pub position: Vec2<f32>,
+    pub transform: PropTransform,
+}
+
+impl PropInstance {
+    pub fn new(asset: Uuid, position: Vec2<f32>, transform: PropTransform) -> Self {
+        Self {
+            id: Uuid::new_v4(),
+            asset,
+            position,
+            transform,
+        }
+    }
+
+    /// Model space to map coordinates, the map z is the height.
+    pub(crate) fn place(&self, p: [f32; 3]) -> Vec3<f32> {
+        let scale = self.transform.scale.max(0.001);
+        let (sin, cos) = self.transform.rotation.to_radians().sin_cos();
+        let (x, z) = (p[0] * scale, p[2] * scale);
+        Vec3::new(
+            self.position.x + x * cos - z * sin,
+            self.position.y + x * sin + z * cos,
+            self.transform.elevation + p[1] * scale,
+        )
+    }
+
+    /// The footprint of the collision shape in map coordinates and its
+    /// height. Returns None for props without collision.
+    pub fn collision_shape(&self, mesh: &PropMesh) -> Option<(Vec<Vec2<f32>>, f32)> {
+        let (hx, hz) = (mesh.size[0] * 0.5, mesh.size[2] * 0.5);
+        let corners: Vec<[f32; 3]> = match self.transform.collision {
+            PropCollision::None => return None,
+            PropCollision::Box => vec![
+                [-hx, 0.0, -hz],
+                [hx, 0.0, -hz],
+                [hx, 0.0, hz],
+                [-hx, 0.0, hz],
+            ],
+            PropCollision::Cylinder => {
+                let radius = hx.max(hz);
+                (0..8)
+                    .map(|i| {
+                        let angle = i as f32 * std::f32::consts::TAU / 8.0;
+                        [angle.cos() * radius, 0.0, angle.sin() * radius]
+                    })
+                    .collect()
+            }
+        };
+        let footprint = corners
+            .into_iter()
+            .map(|p| {
+                let p = self.place(p);
+                Vec2::new(p.x, p.y)
+            })
+            .collect();
+        Some((footprint, mesh.size[1] * self.transform.scale.max(0.001)))
+    }
+}
+
+impl PropMesh {
+    /// Centers the triangles on their base.
+    fn new(mut triangles: Vec<PropTriangle>) -> Self {
+        let mut min = [f32::MAX; 3];
+        let mut max = [f32::MIN; 3];
+        for p in triangles.iter().flat_map(|t| t.points.iter()) {
+            min = std::array::from_fn(|i| min[i].min(p[i]));
+            max = std::array::from_fn(|i| max[i].max(p[i]));
+        }
+        if triangles.is_empty() {
+            return Self::default();
+        }
+
+        let offset = [(min[0] + max[0]) * 0.5, min[1], (min[2] + max[2]) * 0.5];
+        for p in triangles.iter_mut().flat_map(|t| t.points.iter_mut()) {
+            for (value, offset) in p.iter_mut().zip(offset) {
+                *value -= offset;
+            }
+        }
+        Self {
+            triangles,
+            size: [max[0] - min[0], max[1] - min[1], max[2] - min[2]],
+        }
+    }
+
+    /// Adds the triangles of the mesh at the placement of the instance as
+    /// colored sectors, which is how the scene builder draws geometry. Only
+    /// used on copies of the region map for rendering.
+    pub fn add_render_geometry(&self, map: &mut Map, instance: &PropInstance) {
+        let id = instance.id.to_string();
+        let tag = |properties: &mut ValueContainer| {
+            properties.set(PROP_INSTANCE, Value::Str(id.clone()));
+        };
+
+        for triangle in &self.triangles {
+            let vertices: Vec<u32> = triangle
+                .points
+                .iter()
+                .map(|p| {
+                    let p = instance.place(*p);
+                    map.add_vertex_at_3d(p.x, p.y, p.z, false)
+                })
+                .collect();
+            if vertices[0] == vertices[1]
+                || vertices[1] == vertices[2]
+                || vertices[0] == vertices[2]
+            {
+                continue;
+            }
+
+            map.possible_polygon = vec![];
+            let mut sector_id = None;
+            for i in 0..3 {
+                let (linedef_id, sector) = map.create_linedef(vertices[i], vertices[(i + 1) % 3]);
+                if sector.is_some() {
+                    sector_id = sector;
+                }
+                if let Some(linedef) = map.find_linedef_mut(linedef_id) {
+                    tag(&mut linedef.properties);
+                }
+            }
+            map.possible_polygon = vec![];
+
+            for id in &vertices {
+                if let Some(vertex) = map.find_vertex_mut(*id) {
+                    tag(&mut vertex.properties);
+                }
+            }
+            if let Some(sector) = sector_id.and_then(|id| map.find_sector_mut(id)) {
+                tag(&mut sector.properties);
+                sector.properties.set(
+                    "source",
+                    Value::Source(PixelSource::Color(TheColor::from_u8_array(triangle.color))),
+                );
+            }
+        }
+    }
+}
+
+/// Adds the collision shape of a prop as blocking walls without texture,
+/// the way the server collides with geometry.
+fn add_collision_geometry(map: &mut Map, instance: &PropInstance, mesh: &PropMesh) {
+    let Some((footprint, height)) = instance.collision_shape(mesh) else {
+        return;
+    };
+    let id = instance.id.to_string();
+    let corners: Vec<u32> = footprint
+        .iter()
+        .map(|p| map.add_vertex_at_3d(p.x, p.y, instance.transform.elevation, false))
+        .collect();
+    for i in 0..corners.len() {
+        // Without a possible polygon the walls do not close into a sector
+        map.possible_polygon = vec![];
+        let (linedef_id, _) = map.create_linedef(corners[i], corners[(i + 1) % corners.len()]);
+        if let Some(linedef) = map.find_linedef_mut(linedef_id) {
+            linedef
+                .properties
+                .set(PROP_INSTANCE, Value::Str(id.clone()));
+            linedef.properties.set("wall_height", Value::Float(height));
+            linedef
+                .properties
+                .set("row1_source", Value::Source(PixelSource::Off));
+        }
+    }
+    map.possible_polygon = vec![];
+}
+
+/// Adds the props of a region to a copy of its map. The triangles are added
+/// for rendering, the collision shapes for the map of the server instance.
+pub fn add_props_to_map(
+    map: &mut Map,
+    props: &IndexMap<Uuid, PropInstance>,
+    assets: &IndexMap<Uuid, Asset>,
+    collision: bool,
+) {
+    if props.is_empty() {
+        return;
+    }
+    for instance in props.values() {
+        let Some(AssetBuffer::Mesh(mesh)) = assets.get(&instance.asset).map(|a| &a.buffer) else {
+            continue;
+        };
+        mesh.add_render_geometry(map, instance);
+        if collision {
+            add_collision_geometry(map, instance, mesh);
+        }
+    }
+    map.update_surfaces();
+}
+
+/// The map of a region with the meshes of its props, for rendering.
+pub fn region_render_map(region: &Region, assets: &IndexMap<Uuid, Asset>) -> Map {
+    let mut map = region.map.clone();
+    add_props_to_map(&mut map, &region.props, assets, false);
+    map
+}
+
+/// A material of an imported mesh.
+#[derive(Clone, Default)]
+struct MeshMaterial {
+    color: [f32; 4],
+    texture: Option<TheRGBABuffer>,
+}
+
+impl MeshMaterial {
+    fn plain() -> Self {
+        Self {
+            color: [0.8, 0.8, 0.8, 1.0],
+            texture: None,
+        }
+    }
+
+    /// The baked color of a triangle, the texture is averaged over a few
+    /// points of the triangle. Returns None for transparent triangles.
+    fn shade(&self, uvs: Option<[[f32; 2]; 3]>, colors: Option<[[f32; 4]; 3]>) -> Option<[u8; 4]> {
+        let mut color = self.color;
+
+        if let (Some(texture), Some(uvs)) = (&self.texture, uvs) {
+            const WEIGHTS: [[f32; 3]; 7] = [
+                [1.0 / 3.0, 1.0 / 3.0, 1.0 / 3.0],
+                [4.0 / 6.0, 1.0 / 6.0, 1.0 / 6.0],
+                [1.0 / 6.0, 4.0 / 6.0, 1.0 / 6.0],
+                [1.0 / 6.0, 1.0 / 6.0, 4.0 / 6.0],
+                [1.0 / 6.0, 5.0 / 12.0, 5.0 / 12.0],
+                [5.0 / 12.0, 1.0 / 6.0, 5.0 / 12.0],
+                [5.0 / 12.0, 5.0 / 12.0, 1.0 / 6.0],
+            ];
+            let dim = *texture.dim();
+            let (width, height) = (dim.width.max(1) as f32, dim.height.max(1) as f32);
+            let pixels = texture.pixels();
+            let mut sum = [0.0; 4];
+            for w in WEIGHTS {
+                let u = w[0] * uvs[0][0] + w[1] * uvs[1][0] + w[2] * uvs[2][0];
+                let v = w[0] * uvs[0][1] + w[1] * uvs[1][1] + w[2] * uvs[2][1];
+                let x = ((u.rem_euclid(1.0) * width) as usize).min(width as usize - 1);
+                let y = ((v.rem_euclid(1.0) * height) as usize).min(height as usize - 1);
+                let i = (y * width as usize + x) * 4;
+                if let Some(p) = pixels.get(i..i + 4) {
+                    for (sum, value) in sum.iter_mut().zip(p) {
+                        *sum += *value as f32 / 255.0;
+                    }
+                }
+            }
+            for (color, sum) in color.iter_mut().zip(sum) {
+                *color *= sum / WEIGHTS.len() as f32;
+            }
+        }
+
+        if let Some(colors) = colors {
+            for (c, color) in color.iter_mut().enumerate() {
+                *color *= (colors[0][c] + colors[1][c] + colors[2][c]) / 3.0;
+            }
+        }
+
+        if color[3] < 0.5 {
+            return None;
+        }
+        Some([
+            (color[0].clamp(0.0, 1.0) * 255.0) as u8,
+            (color[1].clamp(0.0, 1.0) * 255.0) as u8,
+            (color[2].clamp(0.0, 1.0) * 255.0) as u8,
+            255,
+        ])
+    }
+}
+
+/// Imports a Wavefront OBJ (`.obj`) or glTF 2.0 (`.gltf`, `.glb`) file as a
+/// prop mesh. `decode_image` decodes the texture images. Returns the mesh and
+/// warnings about skipped content.
+pub fn import_prop(
+    path: &Path,
+    decode_image: impl Fn(&[u8]) -> Option<TheRGBABuffer>,
+) -> Result<(PropMesh, Vec<String>), String> {
+    let extension = path
+        .extension()
+        .map(|e| e.to_string_lossy().to_lowercase())
+        .unwrap_or_default();
+    let mut warnings = vec![];
+    let triangles = if extension == "obj" {
+        import_obj(path, &decode_image, &mut warnings)?
+    } else {
+        import_gltf(path, &decode_image, &mut warnings)?
+    };
+    if triangles.is_empty() {
+        return Err("The file contains no triangles.".into());
+    }
+    Ok((PropMesh::new(triangles), warnings))
+}
+
+fn import_obj(
+    path: &Path,
+    decode_image: &impl Fn(&[u8]) -> Option<TheRGBABuffer>,
+    warnings: &mut Vec<String>,
+) -> Result<Vec<PropTriangle>, String> {
+    let text = std::fs::read_to_string(path).map_err(|e| e.to_string())?;
+
+    let mut positions: Vec<[f32; 3]> = vec![];
+    let mut colors: Vec<[f32; 4]> = vec![];
+    let mut uvs: Vec<[f32; 2]> = vec![];
+    let mut materials: FxHashMap<String, MeshMaterial> = FxHashMap::default();
+    let mut material = MeshMaterial::plain();
+    let mut triangles = vec![];
+
+    let number = |s: Option<&str>| s.and_then(|s| s.parse::<f32>().ok()).unwrap_or(0.0);
+
+    for line in text.lines() {
+        let mut tokens = line.split_whitespace();
+        match tokens.next() {
+            Some("v") => {
+                let values: Vec<f32> = tokens.filter_map(|t| t.parse().ok()).collect();
+                positions.push([
+                    values.first().copied().unwrap_or(0.0),
+                    values.get(1).copied().unwrap_or(0.0),
+                    values.get(2).copied().unwrap_or(0.0),
+                ]);
+                // Optional vertex colors after the position
+                colors.push(if values.len() >= 6 {
+                    [values[3], values[4], values[5], 1.0]
+                } else {
+                    [1.0; 4]
+                });
+            }
+            Some("vt") => {
+                // OBJ texture coordinates start at the bottom of the image
+                uvs.push([number(tokens.next()), 1.0 - number(tokens.next())]);
+            }
+            Some("mtllib") => {
+                let file = tokens.collect::<Vec<_>>().join(" ");
+                let mtl_path = resolve_relative_path(path, &file);
+                match std::fs::read_to_string(&mtl_path) {
+                    Ok(mtl) => read_mtl(&mtl, &mtl_path, decode_image, &mut materials, warnings),
+                    Err(_) => warnings.push(format!("Unable to read material library '{}'.", file)),
+                }
+            }
+            Some("usemtl") => {
+                let name = tokens.collect::<Vec<_>>().join(" ");
+                material = materials
+                    .get(&name)
+                    .cloned()
+                    .unwrap_or_else(MeshMaterial::plain);
+            }
+            Some("f") => {
+                // Indices are one based, negative indices are relative to the end
+                let resolve = |index: &str, count: usize| -> Option<usize> {
+                    let index: i64 = index.parse().ok()?;
+                    let index = if index < 0 {
+                        count as i64 + index
+                    } else {
+                        index - 1
+                    };
+                    (index >= 0 && (index as usize) < count).then_some(index as usize)
+                };
+                let corners: Vec<(usize, Option<usize>)> = tokens
+                    .filter_map(|t| {
+                        let mut parts = t.split('/');
+                        let v = resolve(parts.next()?, positions.len())?;
+                        let uv = parts
+                            .next()
+                            .filter(|p| !p.is_empty())
+                            .and_then(|p| resolve(p, uvs.len()));
+                        Some((v, uv))
+                    })
+                    .collect();
+                for i in 1..corners.len().saturating_sub(1) {
+                    let corner = [corners[0], corners[i], corners[i + 1]];
+                    let uv = match corner.map(|c| c.1) {
+                        [Some(a), Some(b), Some(c)] => Some([uvs[a], uvs[b], uvs[c]]),
+                        _ => None,
+                    };
+                    let color = corner.map(|c| colors[c.0]);
+                    if let Some(color) = material.shade(uv, Some(color)) {
+                        triangles.push(PropTriangle {
+                            points: corner.map(|c| positions[c.0]),
+                            color,
+                        });
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    Ok(triangles)
+}
+
+fn read_mtl(
+    text: &str,
+    path: &Path,
+    decode_image: &impl Fn(&[u8]) -> Option<TheRGBABuffer>,
+    materials: &mut FxHashMap<String, MeshMaterial>,
+    warnings: &mut Vec<String>,
+) {
+    let mut current: Option<String> = None;
+    for line in text.lines() {
+        let mut tokens = line.split_whitespace();
+        let keyword = tokens.next();
+        let rest: Vec<&str> = tokens.collect();
+        match keyword {
+            Some("newmtl") => {
+                let name = rest.join(" ");
+                materials.insert(name.clone(), MeshMaterial::plain());
+                current = Some(name);
+            }
+            Some("Kd") => {
+                if let Some(material) = current.as_ref().and_then(|n| materials.get_mut(n)) {
+                    for (i, value) in rest.iter().take(3).enumerate() {
+                        material.color[i] = value.parse().unwrap_or(1.0);
+                    }
+                }
+            }
+            Some("d") => {
+                if let Some(material) = current.as_ref().and_then(|n| materials.get_mut(n)) {
+                    material.color[3] = rest.first().and_then(|v| v.parse().ok()).unwrap_or(1.0);
+                }
+            }
+            Some("map_Kd") => {
+                // The file name follows the options
+                let Some(file) = rest.last() else {
+                    continue;
+                };
+                let image = std::fs::read(resolve_relative_path(path, file))
+                    .ok()
+                    .and_then(|data| decode_image(&data));
+                if image.is_none() {
+                    warnings.push(format!("Unable to load texture '{}'.", file));
+                }
+                if let Some(material) = current.as_ref().and_then(|n| materials.get_mut(n)) {
+                    material.texture = image;
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+fn import_gltf(
+    path: &Path,
+    decode_image: &impl Fn(&[u8]) -> Option<TheRGBABuffer>,
+    warnings: &mut Vec<String>,
+) -> Result<Vec<PropTriangle>, String> {
+    let data = std::fs::read(path).map_err(|e| e.to_string())?;
+
+    // Binary glTF holds the JSON and the first buffer in chunks
+    let (root, bin): (Json, Option<Vec<u8>>) = if data.starts_with(b"glTF") {
+        let mut root = None;
+        let mut bin = None;
+        let mut offset = 12;
+        while offset + 8 <= data.len() {
+            let length = u32::from_le_bytes(data[offset..offset + 4].try_into().unwrap()) as usize;
+            let kind = &data[offset + 4..offset + 8];
+            let chunk = data
+                .get(offset + 8..offset + 8 + length)
+                .ok_or("Invalid binary glTF chunk.")?;
+            if kind == b"JSON" {
+                root = Some(serde_json::from_slice(chunk).map_err(|e| e.to_string())?);
+            } else if kind == b"BIN\0" {
+                bin = Some(chunk.to_vec());
+            }
+            offset += 8 + length;
+        }
+        (root.ok_or("Binary glTF without JSON chunk.")?, bin)
+    } else {
+        (
+            serde_json::from_slice(&data).map_err(|e| e.to_string())?,
+            None,
+        )
+    };
+
+    let mut buffers: Vec<Vec<u8>> = vec![];
+    for (index, buffer) in array(&root["buffers"]).iter().enumerate() {
+        match buffer["uri"].as_str() {
+            Some(uri) => buffers.push(read_uri(path, uri)?),
+            None if index == 0 => buffers.push(bin.clone().unwrap_or_default()),
+            None => buffers.push(vec![]),
+        }
+    }
+
+    let gltf = Gltf {
+        root: &root,
+        buffers,
+    };
+
+    // Materials with decoded textures
+    let mut materials = vec![];
+    for material in array(&root["materials"]) {
+        let pbr = &material["pbrMetallicRoughness"];
+        let mut color = [1.0; 4];
+        for (i, value) in array(&pbr["baseColorFactor"]).iter().take(4).enumerate() {
+            color[i] = value.as_f64().unwrap_or(1.0) as f32;
+        }
+        let mut texture = None;
+        if let Some(index) = pbr["baseColorTexture"]["index"].as_u64() {
+            let image = root["textures"][index as usize]["source"]
+                .as_u64()
+                .map(|i| &root["images"][i as usize]);
+            texture = image.and_then(|image| gltf.image(path, image, decode_image));
+            if texture.is_none() {
+                warnings.push(format!(
+                    "Unable to load the texture of material '{}', only PNG images are supported.",
+                    material["name"].as_str().unwrap_or_default()
+                ));
+            }
+        }
+        materials.push(MeshMaterial { color, texture });
+    }
+
+    let mut triangles = vec![];
+    let scene = root["scene"].as_u64().unwrap_or(0) as usize;
+    let nodes: Vec<u64> = match root["scenes"][scene]["nodes"].as_array() {
+        Some(nodes) => nodes.iter().filter_map(|n| n.as_u64()).collect(),
+        None => (0..array(&root["nodes"]).len() as u64).collect(),
+    };
+    let mut stack: Vec<(u64, [f32; 16])> = nodes.into_iter().map(|n| (n, IDENTITY)).collect();
+    while let Some((index, parent)) = stack.pop() {
+        let node = &root["nodes"][index as usize];
+        let matrix = mat_mul(&parent, &node_matrix(node));
+        for child in array(&node["children"]).iter().filter_map(|c| c.as_u64()) {
+            stack.push((child, matrix));
+        }
+        let Some(mesh) = node["mesh"].as_u64() else {
+            continue;
+        };
+        for primitive in array(&root["meshes"][mesh as usize]["primitives"]) {
+            if primitive["mode"].as_u64().unwrap_or(4) != 4 {
+                warnings.push("Skipped a primitive which does not consist of triangles.".into());
+                continue;
+            }
+            let attributes = &primitive["attributes"];
+            let Some((positions, 3)) = attributes["POSITION"]
+                .as_u64()
+                .and_then(|a| gltf.accessor(a as usize))
+            else {
+                continue;
+            };
+            let uvs = attributes["TEXCOORD_0"]
+                .as_u64()
+                .and_then(|a| gltf.accessor(a as usize))
+                .filter(|(_, c)| *c == 2);
+            let colors = attributes["COLOR_0"]
+                .as_u64()
+                .and_then(|a| gltf.accessor(a as usize))
+                .filter(|(_, c)| *c == 3 || *c == 4);
+            let count = positions.len() / 3;
+            let indices: Vec<usize> = match primitive["indices"]
+                .as_u64()
+                .and_then(|a| gltf.accessor(a as usize))
+            {
+                Some((indices, _)) => indices.iter().map(|i| *i as usize).collect(),
+                None => (0..count).collect(),
+            };
+            let material = primitive["material"]
+                .as_u64()
+                .and_then(|m| materials.get(m as usize))
+                .cloned()
+                .unwrap_or_else(MeshMaterial::plain);
+
+            for triangle in indices.chunks_exact(3) {
+                if triangle.iter().any(|i| *i >= count) {
+                    continue;
+                }
+                let points = [0, 1, 2].map(|c| {
+                    let i = triangle[c] * 3;
+                    transform_point(
+                        &matrix,
+                        [
+                            positions[i] as f32,
+                            positions[i + 1] as f32,
+                            positions[i + 2] as f32,
+                        ],
+                    )
+                });
+                let uv = uvs.as_ref().map(|(uvs, _)| {
+                    [0, 1, 2].map(|c| {
+                        let i = triangle[c] * 2;
+                        [
+                            uvs.get(i).copied().unwrap_or(0.0) as f32,
+                            uvs.get(i + 1).copied().unwrap_or(0.0) as f32,
+                        ]
+                    })
+                });
+                let color = colors.as_ref().map(|(colors, components)| {
+                    [0, 1, 2].map(|c| {
+                        let i = triangle[c] * components;
+                        let value = |o: usize| colors.get(i + o).copied().unwrap_or(1.0) as f32;
+                        [
+                            value(0),
+                            value(1),
+                            value(2),
+                            if *components == 4 { value(3) } else { 1.0 },
+                        ]
+                    })
+                });
+                if let Some(color) = material.shade(uv, color) {
+                    triangles.push(PropTriangle { points, color });
+                }
+            }
+        }
+    }
+
+    Ok(triangles)
+}
+
+/// The parsed glTF document and its loaded buffers.
+struct Gltf<'a> {
+    root: &'a Json,
+    buffers: Vec<Vec<u8>>,
+}
+
+impl Gltf<'_> {
+    /// The data of a buffer view.
+    fn view(&self, index: usize) -> Option<(&[u8], usize)> {
+        let view = &self.root["bufferViews"][index];
+        let buffer = self.buffers.get(view["buffer"].as_u64()? as usize)?;
+        let offset = view["byteOffset"].as_u64().unwrap_or(0) as usize;
+        let length = view["byteLength"].as_u64()? as usize;
+        let stride = view["byteStride"].as_u64().unwrap_or(0) as usize;
+        Some((buffer.get(offset..offset + length)?, stride))
+    }
+
+    /// Reads an accessor as a flat list of values and the number of components
+    /// per element. Normalized integers are converted to the 0..1 range.
+    fn accessor(&self, index: usize) -> Option<(Vec<f64>, usize)> {
+        let accessor = &self.root["accessors"][index];
+        let count = accessor["count"].as_u64()? as usize;
+        let components = match accessor["type"].as_str()? {
+            "SCALAR" => 1,
+            "VEC2" => 2,
+            "VEC3" => 3,
+            "VEC4" => 4,
+            _ => return None,
+        };
+        let (size, max): (usize, f64) = match accessor["componentType"].as_u64()? {
+            5120 => (1, 127.0),
+            5121 => (1, 255.0),
+            5122 => (2, 32767.0),
+            5123 => (2, 65535.0),
+            5125 => (4, u32::MAX as f64),
+            5126 => (4, 1.0),
+            _ => return None,
+        };
+        let kind = accessor["componentType"].as_u64()?;
+        let normalized = accessor["normalized"].as_bool().unwrap_or(false);
+
+        let Some(view) = accessor["bufferView"].as_u64() else {
+            return Some((vec![0.0; count * components], components));
+        };
+        let (data, stride) = self.view(view as usize)?;
+        let offset = accessor["byteOffset"].as_u64().unwrap_or(0) as usize;
+        let stride = if stride == 0 {
+            size * components
+        } else {
+            stride
+        };
+
+        let mut values = Vec::with_capacity(count * components);
+        for element in 0..count {
+            for component in 0..components {
+                let at = offset + element * stride + component * size;
+                let bytes = data.get(at..at + size)?;
+                let value = match kind {
+                    5120 => bytes[0] as i8 as f64,
+                    5121 => bytes[0] as f64,
+                    5122 => i16::from_le_bytes([bytes[0], bytes[1]]) as f64,
+                    5123 => u16::from_le_bytes([bytes[0], bytes[1]]) as f64,
+                    5125 => u32::from_le_bytes(bytes.try_into().ok()?) as f64,
+                    _ => f32::from_le_bytes(bytes.try_into().ok()?) as f64,
+                };
+                values.push(if normalized && kind != 5126 {
+                    (value / max).max(-1.0)
+                } else {
+                    value
+                });
+            }
+        }
+        Some((values, components))
+    }
+
+    /// Decodes an image, either embedded in a buffer view or referenced by an uri.
+    fn image(
+        &self,
+        path: &Path,
+        image: &Json,
+        decode_image: &impl Fn(&[u8]) -> Option<TheRGBABuffer>,
+    ) -> Option<TheRGBABuffer> {
+        if let Some(view) = image["bufferView"].as_u64() {
+            let (data, _) = self.view(view as usize)?;
+            return decode_image(data);
+        }
+        let data = read_uri(path, image["uri"].as_str()?).ok()?;
+        decode_image(&data)
+    }
+}
+
+/// Reads a data uri or a file relative to the glTF file.
+fn read_uri(path: &Path, uri: &str) -> Result<Vec<u8>, String> {
+    if let Some(data) = uri.strip_prefix("data:") {
+        let (_, encoded) = data.split_once(";base64,").ok_or("Unsupported data uri.")?;
+        return base64::engine::general_purpose::STANDARD
+            .decode(encoded)
+            .map_err(|e| e.to_string());
+    }
+    let file = uri.replace("%20", " ");
+    std::fs::read(resolve_relative_path(path, &file))
+        .map_err(|err| format!("Unable to read '{}': {}", file, err))
+}
+
+const IDENTITY: [f32; 16] = [
+    1.0, 0.0, 0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 0.0, 1.0,
+];
+
+/// The local column major matrix of a node, either given or composed of the
+/// translation, rotation and scale.
+fn node_matrix(node: &Json) -> [f32; 16] {
+    let values = |key: &str, default: &[f32]| -> Vec<f32> {
+        match node[key].as_array() {
+            Some(a) if a.len() == default.len() => {
+                a.iter().map(|v| v.as_f64().unwrap_or(0.0) as f32).collect()
+            }
+            _ => default.to_vec(),
+        }
+    };
+    if node["matrix"].is_array() {
+        let m = values("matrix", &IDENTITY);
+        return std::array::from_fn(|i| m[i]);
+    }
+
+    let t = values("translation", &[0.0, 0.0, 0.0]);
+    let r = values("rotation", &[0.0, 0.0, 0.0, 1.0]);
+    let s = values("scale", &[1.0, 1.0, 1.0]);
+    let (x, y, z, w) = (r[0], r[1], r[2], r[3]);
+    [
+        (1.0 - 2.0 * (y * y + z * z)) * s[0],
+        (2.0 * (x * y + z * w)) * s[0],
+        (2.0 * (x * z - y * w)) * s[0],
+        0.0,
+        (2.0 * (x * y - z * w)) * s[1],
+        (1.0 - 2.0 * (x * x + z * z)) * s[1],
+        (2.0 * (y * z + x * w)) * s[1],
+        0.0,
+        (2.0 * (x * z + y * w)) * s[2],
+        (2.0 * (y * z - x * w)) * s[2],
+        (1.0 - 2.0 * (x * x + y * y)) * s[2],
+        0.0,
+        t[0],
+        t[1],
+        t[2],
+        1.0,
+    ]
+}
+
+fn mat_mul(a: &[f32; 16], b: &[f32; 16]) -> [f32; 16] {
+    std::array::from_fn(|i| {
+        let (column, row) = (i / 4, i % 4);
+        (0..4).map(|k| a[k * 4 + row] * b[column * 4 + k]).sum()
+    })
+}
+
+fn transform_point(m: &[f32; 16], p: [f32; 3]) -> [f32; 3] {
+    std::array::from_fn(|row| m[row] * p[0] + m[4 + row] * p[1] + m[8 + row] * p[2] + m[12 + row])
+}
+
+fn array(value: &Json) -> &[Json] {
+    value.as_array().map(|a| a.as_slice()).unwrap_or(&[])
+}
@@ -0,0 +1,833 @@
+use crate::audio::{point_in_polygon, sector_polygon};
+use crate::dialogue::value_text;
+use crate::prelude::*;
+use crate::rusterix_utils::{InstanceEdit, apply_instance_edit, apply_replay_input};
+use rusterix::{Entity, Rusterix, Value};
+use theframework::prelude::*;
+
+/// The name of the built-in script library with the quest helpers.
+pub const QUEST_LIBRARY: &str = "quests";
+
+/// The attribute holding the quest commands queued by a script. Every line is
+/// `<sequence>|<command>|<quest>|<argument>`, the host runs the commands it
+/// has not seen yet.
+pub const QUEST_QUEUE: &str = "quest_queue";
+/// The prefix of the player attributes with the quest state. `quest_<key>` is
+/// "active", "done" or "failed", `quest_<key>_stage` the index of the current
+/// stage and `quest_<key>_progress` the counts of its objectives.
+pub const QUEST_PREFIX: &str = "quest_";
+/// Set on the player to the name of the character it talked to, consumed by
+/// the "talk" objectives. Dialogues set it when they start.
+pub const QUEST_TALKED_TO: &str = "talked_to";
+/// Set on the player to the names of the quests to start, one per line. Used
+/// by the "Start Quest" effect of dialogues.
+pub const QUEST_START: &str = "quest_start";
+/// The player attribute with the journal text, shown by `journal` widgets.
+pub const QUEST_JOURNAL: &str = "journal";
+/// The user event sent to the player for every reward item.
+pub const QUEST_REWARD_ITEM: &str = "quest_reward_item";
+/// The user event sent to the player when a quest starts or reaches a new
+/// stage, the value is the quest name.
+pub const QUEST_UPDATED: &str = "quest_updated";
+/// The user event sent to the player when a quest is completed.
+pub const QUEST_COMPLETED: &str = "quest_completed";
+/// The user event sent to the player when a quest failed.
+pub const QUEST_FAILED: &str = "quest_failed";
+
+/// The source of the built-in `quests` library. The commands are queued and
+/// run by the host for the player, the queries read the player attributes.
+pub const QUEST_SOURCE: &str = r##"# Built-in quest helpers. Quests are project assets with stages, objectives and
+# rewards, the host tracks the objectives of the active stage for the player.
+# The queries take the id of the player when called by another character.
+# Call quest_user_event() from user_event() of the player to receive the reward
+# items, the events "quest_updated", "quest_completed" and "quest_failed" carry
+# the quest name. Call quest_event() from event() of the player, only the kills
+# it reports count for the "kill" objectives.
+
+def _quest_key(name):
+    return "".join([c if c.isalnum() else "_" for c in str(name).lower()])
+
+def _quest(command):
+    seq = (get_attr("quest_seq") or 0) + 1
+    set_attr("quest_seq", seq)
+    queue = [c for c in (get_attr("quest_queue") or "").split("\n") if c][-15:]
+    queue.append(str(seq) + "|" + command)
+    set_attr("quest_queue", "\n".join(queue))
+
+def _quest_attr(key, entity_id):
+    if entity_id is None:
+        return get_attr(key)
+    return get_attr_of(entity_id, key)
+
+def start_quest(name):
+    _quest("start|" + str(name))
+
+def advance_quest(name):
+    _quest("advance|" + str(name))
+
+def set_quest_stage(name, stage):
+    _quest("stage|" + str(name) + "|" + str(int(stage)))
+
+def complete_quest(name):
+    _quest("complete|" + str(name))
+
+def fail_quest(name):
+    _quest("fail|" + str(name))
+
+def set_flag(name):
+    _quest("flag|" + str(name))
+
+def has_flag(name, entity_id=None):
+    value = _quest_attr(str(name), entity_id)
+    return value not in (None, "", False, 0)
+
+def quest_state(name, entity_id=None):
+    return str(_quest_attr("quest_" + _quest_key(name), entity_id) or "")
+
+def quest_stage(name, entity_id=None):
+    return int(_quest_attr("quest_" + _quest_key(name) + "_stage", entity_id) or 0)
+
+def quest_active(name, entity_id=None):
+    return quest_state(name, entity_id) == "active"
+
+def quest_done(name, entity_id=None):
+    return quest_state(name, entity_id) == "done"
+
+def talked_to(name):
+    # Called by the player, dialogues report their speaker on their own
+    set_attr("talked_to", str(name))
+
+def quest_event(event, value):
+    # The player may handle the kill event as well
+    if event == "kill":
+        name = get_attr_of(value, "name") or ""
+        class_name = get_attr_of(value, "class_name") or ""
+        _quest("kill|" + str(name) + "|" + str(class_name))
+    return False
+
+def quest_user_event(event, value):
+    if event == "quest_reward_item":
+        add_item(value)
+        return True
+    return False
+"##;
+
+/// The most stages a quest advances in one update, guards against stages
+/// which complete each other.
+const MAX_STAGE_STEPS: usize = 16;
+
+/// The key of a quest in the player attributes: the lower case name with
+/// everything but letters and digits replaced by `_`.
+pub fn quest_key(name: &str) -> String {
+    name.trim()
+        .to_lowercase()
+        .chars()
+        .map(|c| if c.is_alphanumeric() { c } else { '_' })
+        .collect()
+}
+
+/// The player attribute with the state of the named quest.
+pub fn quest_state_key(name: &str) -> String {
+    format!("{}{}", QUEST_PREFIX, quest_key(name))
+}
+
+/// What an objective asks the player to do.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum ObjectiveKind {
+    /// Kill characters with the name or class of the target.
+    #[default]
+    Kill,
+    /// Carry items with the name of the target.
+    Collect,
+    /// Enter the region or the sector with the name of the target.
+    Reach,
+    /// Talk to the character with the name of the target.
+    Talk,
+    /// Have the player attribute of the target set.
+    Flag,
+}
+
+impl ObjectiveKind {
+    /// The default journal text of an objective.
+    fn describe(&self, target: &str) -> String {
+        match self {
+            ObjectiveKind::Kill => format!("Kill {}", target),
+            ObjectiveKind::Collect => format!("Collect {}", target),
+            ObjectiveKind::Reach => format!("Reach {}", target),
+            ObjectiveKind::Talk => format!("Talk to {}", target),
+            ObjectiveKind::Flag => target.to_string(),
+        }
+    }
+}
+
+/// An objective of a quest stage.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Default)]
+pub struct QuestObjective {
+    pub kind: ObjectiveKind,
+    pub target: String,
+    /// How often the objective has to be met.
+    #[serde(default = "default_count")]
+    pub count: i32,
+    /// The journal text, describes the objective if empty.
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub text: String,
+}
+
+fn default_count() -> i32 {
+    1
+}
+
+/// A stage of a quest. The stage completes when all objectives are met, a
+/// stage without objectives is advanced by scripts.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Default)]
+pub struct QuestStage {
+    /// The journal text while the stage is active.
+    #[serde(default)]
+    pub text: String,
+    #[serde(default)]
+    pub objectives: Vec<QuestObjective>,
+    /// The flags set on the player when the stage completes.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub flags: Vec<String>,
+}
+
+/// What the player receives when the quest is completed.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Default)]
+pub struct QuestRewards {
+    /// The names of the items given to the player.
+    #[serde(default)]
+    pub items: Vec<String>,
+    /// Amounts added to the player attributes, like experience or gold.
+    #[serde(default)]
+    pub attributes: IndexMap<String, f32>,
+    /// The flags set on the player.
+    #[serde(default)]
+    pub flags: Vec<String>,
+}
+
+/// The part of a quest edited as TOML.
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+struct QuestSpec {
+    #[serde(default)]
+    description: String,
+    #[serde(default)]
+    stages: Vec<QuestStage>,
+    #[serde(default)]
+    rewards: QuestRewards,
+}
+
+/// A quest of the project.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct Quest {
+    pub id: Uuid,
+    pub name: String,
+    #[serde(default)]
+    pub description: String,
+    #[serde(default)]
+    pub stages: Vec<QuestStage>,
+    #[serde(default)]
+    pub rewards: QuestRewards,
+}
+
+impl Default for Quest {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Quest {
+    pub fn new() -> Self {
+        Self {
+            id: Uuid::new_v4(),
+            name: "New Quest".to_string(),
+            description: String::new(),
+            stages: vec![QuestStage {
+                text: "Talk to the village elder.".to_string(),
+                objectives: vec![QuestObjective {
+                    kind: ObjectiveKind::Talk,
+                    target: "Elder".to_string(),
+                    count: 1,
+                    text: String::new(),
+                }],
+                flags: vec![],
+            }],
+            rewards: QuestRewards::default(),
+        }
+    }
+
+    /// The description, stages and rewards as TOML.
+    pub fn to_toml(&self) -> String {
+        let spec = QuestSpec {
+            description: self.description.clone(),
+            stages: self.stages.clone(),
+            rewards: self.rewards.clone(),
+        };
+        toml::to_string(&spec).unwrap_or_default()
+    }
+
+    /// The stages with their objectives and the rewards, as shown in the
+    /// editor.
+    pub fn outline(&self) -> String {
+        let mut lines = vec![self.name.clone()];
+        if !self.description.trim().is_empty() {
+            lines.push(self.description.trim().to_string());
+        }
+        for (index, stage) in self.stages.iter().enumerate() {
+            lines.push(String::new());
+            lines.push(format!("Stage {}: {}", index, stage.text.trim()));
+            if stage.objectives.is_empty() {
+                lines.push("- Advanced by scripts".to_string());
+            }
+            for objective in &stage.objectives {
+                let text = objective.kind.describe(objective.target.trim());
+                if objective.count > 1 {
+                    lines.push(format!("- {} x{}", text, objective.count));
+                } else {
+                    lines.push(format!("- {}", text));
+                }
+            }
+            for flag in &stage.flags {
+                lines.push(format!("- Sets {}", flag));
+            }
+        }
+        let rewards = &self.rewards;
+        if !rewards.items.is_empty() || !rewards.attributes.is_empty() || !rewards.flags.is_empty()
+        {
+            lines.push(String::new());
+            lines.push("Rewards".to_string());
+            for item in &rewards.items {
+                lines.push(format!("- {}", item));
+            }
+            for (attribute, amount) in &rewards.attributes {
+                lines.push(format!("- {} {:+}", attribute, amount));
+            }
+            for flag in &rewards.flags {
+                lines.push(format!("- Sets {}", flag));
+            }
+        }
+        lines.join("\n")
+    }
+
+    /// Sets the description, stages and rewards from TOML.
+    pub fn apply_toml(&mut self, source: &str) -> Result<(), String> {
+        let spec: QuestSpec = toml::from_str(source).map_err(|e| e.message().to_string())?;
+        self.description = spec.description;
+        self.stages = spec.stages;
+        self.rewards = spec.rewards;
+        Ok(())
+    }
+}
+
+/// The state of a quest for the player.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum QuestState {
+    Inactive,
+    Active,
+    Done,
+    Failed,
+}
+
+impl QuestState {
+    fn from_value(value: Option<&Value>) -> Self {
+        match value.map(value_text).unwrap_or_default().as_str() {
+            "active" => QuestState::Active,
+            "done" => QuestState::Done,
+            "failed" => QuestState::Failed,
+            _ => QuestState::Inactive,
+        }
+    }
+
+    fn as_str(&self) -> &'static str {
+        match self {
+            QuestState::Inactive => "",
+            QuestState::Active => "active",
+            QuestState::Done => "done",
+            QuestState::Failed => "failed",
+        }
+    }
+}
+
+/// The progress of the player in a quest, read from and written to the
+/// player attributes.
+#[derive(Clone, Debug, PartialEq)]
+struct QuestProgress {
+    state: QuestState,
+    stage: usize,
+    counts: Vec<i32>,
+}
+
+impl QuestProgress {
+    fn read(player: &Entity, quest: &Quest) -> Self {
+        let key = quest_state_key(&quest.name);
+        let stage = player
+            .attributes
+            .get(&format!("{}_stage", key))
+            .map(value_text)
+            .and_then(|s| s.trim().parse::<f32>().ok())
+            .unwrap_or(0.0)
+            .max(0.0) as usize;
+        let counts = player
+            .attributes
+            .get_str_default(&format!("{}_progress", key), String::new())
+            .split(',')
+            .filter_map(|c| c.trim().parse::<i32>().ok())
+            .collect();
+        Self {
+            state: QuestState::from_value(player.attributes.get(&key)),
+            stage,
+            counts,
+        }
+    }
+
+    fn restart(&mut self, stage: usize) {
+        self.state = QuestState::Active;
+        self.stage = stage;
+        self.counts.clear();
+    }
+}
+
+/// What happened in the region since the last update.
+#[derive(Default)]
+struct QuestEvents {
+    /// The names and classes of the characters the player killed.
+    kills: Vec<String>,
+    /// The character the player talked to.
+    talked_to: Option<String>,
+}
+
+/// Tracks the quests of the player: runs the commands queued by scripts,
+/// counts the objectives of the active stages, hands out the rewards and
+/// writes the state and the journal to the player attributes, which makes
+/// them part of the game state.
+#[derive(Default)]
+pub struct QuestTracker {
+    /// The last command sequence seen per region, entity or item.
+    sequences: FxHashMap<(Uuid, bool, u32), i64>,
+}
+
+impl QuestTracker {
+    /// Forgets the seen commands, called when the server starts.
+    pub fn start(&mut self) {
+        self.sequences.clear();
+    }
+
+    /// Updates the quests of the player if it is in the region.
+    pub fn update(
+        &mut self,
+        rusterix: &mut Rusterix,
+        region: &mut Region,
+        quests: &IndexMap<Uuid, Quest>,
+    ) {
+        let commands = self.commands(region);
+
+        let Some(player) = region
+            .map
+            .entities
+            .iter()
+            .find(|e| matches!(e.attributes.get("player"), Some(Value::Bool(true))))
+        else {
+            return;
+        };
+
+        // Only the kills reported by the player count, see quest_event()
+        let mut kills = vec![];
+        let mut commands: Vec<(String, String, String)> = commands
+            .into_iter()
+            .filter_map(|(source, command, name, argument)| {
+                if command != "kill" {
+                    return Some((command, name, argument));
+                }
+                if source == Some(player.id) {
+                    kills.extend([name, argument.trim().to_string()]);
+                }
+                None
+            })
+            .collect();
+        kills.retain(|k| !k.is_empty());
+        if quests.is_empty() && commands.is_empty() && kills.is_empty() {
+            return;
+        }
+
+        let mut edits = vec![];
+        let mut replay = vec![];
+
+        let talked_to = player
+            .attributes
+            .get_str_default(QUEST_TALKED_TO, String::new());
+        if !talked_to.is_empty() {
+            edits.push(InstanceEdit::EntityAttribute(
+                player.id,
+                QUEST_TALKED_TO.into(),
+                Value::Str(String::new()),
+            ));
+        }
+        let start = player
+            .attributes
+            .get_str_default(QUEST_START, String::new());
+        if !start.is_empty() {
+            edits.push(InstanceEdit::EntityAttribute(
+                player.id,
+                QUEST_START.into(),
+                Value::Str(String::new()),
+            ));
+            for name in start.lines().filter(|n| !n.trim().is_empty()) {
+                commands.push(("start".into(), name.trim().into(), String::new()));
+            }
+        }
+        let events = QuestEvents {
+            kills,
+            talked_to: (!talked_to.is_empty()).then_some(talked_to),
+        };
+
+        let before: Vec<QuestProgress> = quests
+            .values()
+            .map(|q| QuestProgress::read(player, q))
+            .collect();
+        let mut after = before.clone();
+        let mut flags: Vec<String> = vec![];
+
+        for (command, name, argument) in &commands {
+            if command == "flag" {
+                flags.push(name.clone());
+                continue;
+            }
+            let Some(index) = quests
+                .values()
+                .position(|q| quest_key(&q.name) == quest_key(name))
+            else {
+                eprintln!("Unknown quest \"{}\"", name);
+                continue;
+            };
+            let quest = &quests[index];
+            let progress = &mut after[index];
+            match command.as_str() {
+                "start" => {
+                    if matches!(progress.state, QuestState::Inactive | QuestState::Failed) {
+                        progress.restart(0);
+                    }
+                }
+                "advance" => {
+                    if progress.state == QuestState::Active {
+                        flags.extend(stage_flags(quest, progress.stage));
+                        progress.restart(progress.stage + 1);
+                    }
+                }
+                "stage" => {
+                    if let Ok(stage) = argument.trim().parse::<usize>() {
+                        progress.restart(stage);
+                    }
+                }
+                "complete" => {
+                    if progress.state != QuestState::Done {
+                        progress.restart(quest.stages.len());
+                    }
+                }
+                "fail" => progress.state = QuestState::Failed,
+                _ => eprintln!("Unknown quest command \"{}\"", command),
+            }
+        }
+
+        for (index, quest) in quests.values().enumerate() {
+            let progress = &mut after[index];
+            if progress.state != QuestState::Active {
+                continue;
+            }
+            let mut events = Some(&events);
+            for _ in 0..MAX_STAGE_STEPS {
+                let Some(stage) = quest.stages.get(progress.stage) else {
+                    progress.state = QuestState::Done;
+                    break;
+                };
+                progress.counts.resize(stage.objectives.len(), 0);
+                for (objective, count) in stage.objectives.iter().zip(progress.counts.iter_mut()) {
+                    *count = objective_count(objective, *count, player, region, events)
+                        .min(objective.count.max(1));
+                }
+                // The kills and talks only count for the stage they happened in
+                events = None;
+
+                let met = stage
+                    .objectives
+                    .iter()
+                    .zip(progress.counts.iter())
+                    .all(|(o, c)| *c >= o.count.max(1));
+                if stage.objectives.is_empty() || !met {
+                    break;
+                }
+                flags.extend(stage.flags.iter().cloned());
+                progress.restart(progress.stage + 1);
+            }
+        }
+
+        // Write the changes and hand out the rewards
+        for (index, quest) in quests.values().enumerate() {
+            let (old, new) = (&before[index], &after[index]);
+            if old == new {
+                continue;
+            }
+            let key = quest_state_key(&quest.name);
+            edits.push(InstanceEdit::EntityAttribute(
+                player.id,
+                key.clone(),
+                Value::Str(new.state.as_str().into()),
+            ));
+            edits.push(InstanceEdit::EntityAttribute(
+                player.id,
+                format!("{}_stage", key),
+                Value::Int(new.stage.min(quest.stages.len()) as i32),
+            ));
+            let counts: Vec<String> = new.counts.iter().map(|c| c.to_string()).collect();
+            edits.push(InstanceEdit::EntityAttribute(
+                player.id,
+                format!("{}_progress", key),
+                Value::Str(counts.join(",")),
+            ));
+
+            if new.state == QuestState::Done && old.state != QuestState::Done {
+                for item in &quest.rewards.items {
+                    replay.push(ReplayInput::UserEvent(
+                        QUEST_REWARD_ITEM.into(),
+                        item.clone(),
+                    ));
+                }
+                for (attribute, amount) in &quest.rewards.attributes {
+                    edits.push(InstanceEdit::EntityAttribute(
+                        player.id,
+                        attribute.clone(),
+                        add_to_value(player.attributes.get(attribute), *amount),
+                    ));
+                }
+                flags.extend(quest.rewards.flags.iter().cloned());
+                replay.push(ReplayInput::UserEvent(
+                    QUEST_COMPLETED.into(),
+                    quest.name.clone(),
+                ));
+            } else if new.state == QuestState::Failed && old.state != QuestState::Failed {
+                replay.push(ReplayInput::UserEvent(
+                    QUEST_FAILED.into(),
+                    quest.name.clone(),
+                ));
+            } else if new.state == QuestState::Active
+                && (old.state != QuestState::Active || old.stage != new.stage)
+            {
+                replay.push(ReplayInput::UserEvent(
+                    QUEST_UPDATED.into(),
+                    quest.name.clone(),
+                ));
+            }
+        }
+        for flag in flags {
+            edits.push(InstanceEdit::EntityAttribute(
+                player.id,
+                flag,
+                Value::Bool(true),
+            ));
+        }
+
+        let journal = journal_text(quests, &after);
+        if player
+            .attributes
+            .get_str_default(QUEST_JOURNAL, String::new())
+            != journal
+        {
+            edits.push(InstanceEdit::EntityAttribute(
+                player.id,
+                QUEST_JOURNAL.into(),
+                Value::Str(journal),
+            ));
+        }
+
+        for edit in &edits {
+            apply_instance_edit(rusterix, &mut region.map, edit);
+        }
+        for input in &replay {
+            apply_replay_input(rusterix, input, &region.map);
+        }
+    }
+
+    /// The new commands queued by the characters and items of the region, as
+    /// the queuing character, command, quest name and argument.
+    fn commands(&mut self, region: &Region) -> Vec<(Option<u32>, String, String, String)> {
+        let mut commands = vec![];
+        let queues = region
+            .map
+            .entities
+            .iter()
+            .map(|e| ((region.id, false, e.id), &e.attributes))
+            .chain(
+                region
+                    .map
+                    .items
+                    .iter()
+                    .map(|i| ((region.id, true, i.id), &i.attributes)),
+            );
+        for (source, attributes) in queues {
+            let queue = attributes.get_str_default(QUEST_QUEUE, String::new());
+            if queue.is_empty() {
+                continue;
+            }
+            let last = self.sequences.entry(source).or_insert(0);
+            for line in queue.lines() {
+                let mut parts = line.splitn(4, '|');
+                let (Some(seq), Some(command), Some(name)) =
+                    (parts.next(), parts.next(), parts.next())
+                else {
+                    continue;
+                };
+                let Ok(seq) = seq.trim().parse::<i64>() else {
+                    continue;
+                };
+                if seq <= *last {
+                    continue;
+                }
+                *last = seq;
+                commands.push((
+                    (!source.1).then_some(source.2),
+                    command.trim().to_string(),
+                    name.trim().to_string(),
+                    parts.next().unwrap_or_default().to_string(),
+                ));
+            }
+        }
+        commands
+    }
+}
+
+/// The flags set when the given stage of the quest completes.
+fn stage_flags(quest: &Quest, stage: usize) -> Vec<String> {
+    quest
+        .stages
+        .get(stage)
+        .map(|s| s.flags.clone())
+        .unwrap_or_default()
+}
+
+/// The new count of the objective. Kills and talks are counted from the
+/// events, the other objectives check the current state of the player.
+fn objective_count(
+    objective: &QuestObjective,
+    count: i32,
+    player: &Entity,
+    region: &Region,
+    events: Option<&QuestEvents>,
+) -> i32 {
+    let target = objective.target.trim();
+    let done = objective.count.max(1);
+    match objective.kind {
+        ObjectiveKind::Kill => {
+            let kills = events.map_or(0, |e| {
+                e.kills
+                    .iter()
+                    .filter(|k| k.eq_ignore_ascii_case(target))
+                    .count()
+            });
+            count + kills as i32
+        }
+        ObjectiveKind::Collect => player
+            .iter_inventory()
+            .filter(|(_, item)| {
+                item.attributes
+                    .get_str("name")
+                    .is_some_and(|n| n.eq_ignore_ascii_case(target))
+            })
+            .count() as i32,
+        ObjectiveKind::Reach => {
+            let position = player.get_pos_xz();
+            let inside = region.name.eq_ignore_ascii_case(target)
+                || region.map.name.eq_ignore_ascii_case(target)
+                || region.map.sectors.iter().any(|s| {
+                    s.name.eq_ignore_ascii_case(target)
+                        && sector_polygon(&region.map, s)
+                            .is_some_and(|p| point_in_polygon(position, &p))
+                });
+            if inside { done } else { count }
+        }
+        ObjectiveKind::Talk => {
+            let talked = events
+                .and_then(|e| e.talked_to.as_ref())
+                .is_some_and(|t| t.eq_ignore_ascii_case(target));
+            count + talked as i32
+        }
+        ObjectiveKind::Flag => {
+            let set = player
+                .attributes
+                .get(target)
+                .map(value_text)
+                .is_some_and(|v| !matches!(v.as_str(), "" | "false" | "0" | "0.0"));
+            if set { done } else { count }
+        }
+    }
+}
+
+/// Adds the reward amount to an attribute, integers stay integers if the
+/// amount is whole.
+pub(crate) fn add_to_value(value: Option<&Value>, amount: f32) -> Value {
+    match value {
+        Some(Value::Int(v)) if amount.fract() == 0.0 => Value::Int(*v + amount as i32),
+        Some(Value::Int(v)) => Value::Float(*v as f32 + amount),
+        Some(Value::Float(v)) => Value::Float(*v + amount),
+        _ if amount.fract() == 0.0 => Value::Int(amount as i32),
+        _ => Value::Float(amount),
+    }
+}
+
+/// The journal: the active quests with the text and objectives of their
+/// current stage, followed by the finished quests.
+fn journal_text(quests: &IndexMap<Uuid, Quest>, progress: &[QuestProgress]) -> String {
+    let mut lines = vec![];
+    for (quest, progress) in quests.values().zip(progress) {
+        if progress.state != QuestState::Active {
+            continue;
+        }
+        if !lines.is_empty() {
+            lines.push(String::new());
+        }
+        lines.push(quest.name.clone());
+        let Some(stage) = quest.stages.get(progress.stage) else {
+            continue;
+        };
+        if !stage.text.trim().is_empty() {
+            lines.push(stage.text.trim().to_string());
+        }
+        for (index, objective) in stage.objectives.iter().enumerate() {
+            let text = if objective.text.trim().is_empty() {
+                objective.kind.describe(objective.target.trim())
+            } else {
+                objective.text.trim().to_string()
+            };
+            let count = progress.counts.get(index).copied().unwrap_or(0);
+            if objective.count > 1 {
+                lines.push(format!("- {} ({}/{})", text, count, objective.count));
+            } else if count >= 1 {
+                lines.push(format!("- {} (done)", text));
+            } else {
+                lines.push(format!("- {}", text));
+            }
+        }
+    }
+    for (quest, progress) in quests.values().zip(progress) {
+        match progress.state {
+            QuestState::Done => lines.push(format!("{} (completed)", quest.name)),
+            QuestState::Failed => lines.push(format!("{} (failed)", quest.name)),
+            _ => {}
+        }
+    }
+    lines.join("\n")
+}
+
+/// Turns the data of a `journal` widget into a text widget which shows the
+/// journal of the player. Returns None for other widgets.
+pub fn journal_widget_data(data: &str) -> Option<String> {
+    let mut table = data.parse::<toml::Table>().ok()?;
+    let ui = table.get_mut("ui")?.as_table_mut()?;
+    if ui.get("role")?.as_str()? != "journal" {
+        return None;
+    }
+    ui.insert("role".into(), toml::Value::String("text".into()));
+    ui.insert(
+        "text".into(),
+        toml::Value::String(format!("{{PLAYER.{}}}", QUEST_JOURNAL)),
+    );
+    toml::to_string(&table).ok()
+}
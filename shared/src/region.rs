@@ -18,6 +18,9 @@ pub struct Region {
 
     pub characters: IndexMap<Uuid, Character>,
     pub items: IndexMap<Uuid, Item>,
+    /// The placed mesh assets.
+    #[serde(default)]
+    pub props: IndexMap<Uuid, PropInstance>,
 
     pub editing_position_3d: Vec3<f32>,
     #[serde(default = "default_editing_look_at_3d")]
@@ -47,6 +50,7 @@ impl Region {
 
             characters: IndexMap::default(),
             items: IndexMap::default(),
+            props: IndexMap::default(),
 
             editing_position_3d: Vec3::zero(),
             editing_look_at_3d: Vec3::zero(),
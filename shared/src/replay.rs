@@ -0,0 +1,173 @@
+use theframework::prelude::*;
+
+/// Appended to the scripts of deterministic runs: replaces `random(a, b)` of the
+/// server by numbers drawn from the `rng_seed` attribute of the entity or item.
+/// The internal random numbers of the server itself are not seeded.
+pub const DETERMINISTIC_RANDOM_SOURCE: &str = r##"
+
+def random(a, b):
+    if b < a:
+        a, b = b, a
+    state = (int(get_attr("rng_seed") or 0) * 1103515245 + 12345) % 2147483648
+    set_attr("rng_seed", state)
+    if isinstance(a, int) and isinstance(b, int):
+        return a + (state >> 8) % (b - a + 1)
+    return a + (b - a) * (state / 2147483648.0)
+"##;
+
+/// The settings of a deterministic simulation run.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+pub struct DeterministicSettings {
+    /// The seed of the script `random()` and the combat rolls.
+    pub seed: u64,
+    /// The simulated milliseconds per redraw frame.
+    pub frame_ms: u32,
+    /// The simulated milliseconds per game tick.
+    pub tick_ms: u32,
+}
+
+impl DeterministicSettings {
+    pub fn new(seed: u64, target_fps: u32, tick_ms: u32) -> Self {
+        Self {
+            seed,
+            frame_ms: 1000 / target_fps.clamp(1, 60),
+            tick_ms: tick_ms.max(1),
+        }
+    }
+
+    /// The simulated frames per second.
+    pub fn frames_per_second(&self) -> u64 {
+        (1000 / self.frame_ms.max(1)).max(1) as u64
+    }
+
+    /// Derives a seed for the given index (entity, item, region) from the global seed.
+    pub fn seed_for(&self, index: u64) -> u64 {
+        // SplitMix64
+        let mut z = self
+            .seed
+            .wrapping_add(index.wrapping_add(1).wrapping_mul(0x9E37_79B9_7F4A_7C15));
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+}
+
+/// A fixed step clock for deterministic runs. Game ticks are derived from the
+/// number of simulated frames and not from the wall clock.
+#[derive(Clone, Debug)]
+pub struct SimulationClock {
+    pub settings: DeterministicSettings,
+
+    /// The current frame.
+    pub frame: u64,
+    /// The current game tick.
+    pub tick: u64,
+
+    accumulated_ms: u32,
+}
+
+impl SimulationClock {
+    pub fn new(settings: DeterministicSettings) -> Self {
+        Self {
+            settings,
+            frame: 0,
+            tick: 0,
+            accumulated_ms: 0,
+        }
+    }
+
+    /// Advance the clock by one frame. Returns true if a game tick is due.
+    pub fn advance(&mut self) -> bool {
+        self.frame += 1;
+        self.accumulated_ms += self.settings.frame_ms;
+        if self.accumulated_ms >= self.settings.tick_ms {
+            self.accumulated_ms -= self.settings.tick_ms;
+            self.tick += 1;
+            true
+        } else {
+            false
+        }
+    }
+}
+
+/// A recorded player input.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub enum ReplayInput {
+    /// A user event and its value, i.e. ("key_down", "w").
+    UserEvent(String, String),
+    /// A touch / click at the given screen coordinate.
+    TouchDown(Vec2<i32>),
+    /// A touch / click release at the given screen coordinate.
+    TouchUp(Vec2<i32>),
+}
+
+/// A recorded input at the given frame.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct ReplayEvent {
+    pub frame: u64,
+    pub input: ReplayInput,
+}
+
+/// A replay of a deterministic run.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct Replay {
+    pub project_name: String,
+    pub settings: DeterministicSettings,
+    pub events: Vec<ReplayEvent>,
+}
+
+impl Replay {
+    pub fn new(project_name: String, settings: DeterministicSettings) -> Self {
+        Self {
+            project_name,
+            settings,
+            events: vec![],
+        }
+    }
+
+    /// Record an input at the given frame.
+    pub fn record(&mut self, frame: u64, input: ReplayInput) {
+        self.events.push(ReplayEvent { frame, input });
+    }
+
+    /// Create a replay from json.
+    pub fn from_json(json: &str) -> Option<Self> {
+        serde_json::from_str(json).ok()
+    }
+
+    /// Convert the replay to json.
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(&self).unwrap_or_default()
+    }
+}
+
+/// Feeds the inputs of a replay back frame by frame.
+#[derive(Clone, Debug)]
+pub struct ReplayPlayer {
+    pub replay: Replay,
+    index: usize,
+}
+
+impl ReplayPlayer {
+    pub fn new(replay: Replay) -> Self {
+        Self { replay, index: 0 }
+    }
+
+    /// Returns the inputs recorded up to and including the given frame.
+    pub fn inputs_for_frame(&mut self, frame: u64) -> Vec<ReplayInput> {
+        let mut inputs = vec![];
+        while let Some(event) = self.replay.events.get(self.index) {
+            if event.frame > frame {
+                break;
+            }
+            inputs.push(event.input.clone());
+            self.index += 1;
+        }
+        inputs
+    }
+
+    /// Returns true if all inputs have been played back.
+    pub fn is_finished(&self) -> bool {
+        self.index >= self.replay.events.len()
+    }
+}
@@ -1,14 +1,93 @@
 use crate::prelude::*;
-use rusterix::{Command, Entity, Rusterix, Value};
+use rusterix::{Command, Entity, EntityAction, Rusterix, Value};
+use theframework::prelude::*;
 
-/// Start the server
-pub fn start_server(rusterix: &mut Rusterix, project: &mut Project, debug: bool) {
+/// The attribute in which profiled scripts sum up the microseconds spent in
+/// their callbacks, one `<callback> <event>=<microseconds>` line per event.
+pub const SCRIPT_PROFILE: &str = "script_profile";
+
+/// Appended to the class sources of profiled runs: wraps the `event` and
+/// `user_event` callbacks of the class and sums up their run time per event in
+/// the `script_profile` attribute.
+fn script_profile_source(class: &str) -> String {
+    format!(
+        r##"
+
+import time as _profile_time
+
+def _profile_wrap(method, callback):
+    def call(self, event, value):
+        start = _profile_time.perf_counter()
+        try:
+            return method(self, event, value)
+        finally:
+            us = int((_profile_time.perf_counter() - start) * 1000000)
+            totals = {{}}
+            for line in str(get_attr("script_profile") or "").split("\n"):
+                key, _, total = line.rpartition("=")
+                if key:
+                    totals[key] = int(total)
+            key = callback + " " + str(event)
+            totals[key] = totals.get(key, 0) + us
+            set_attr("script_profile", "\n".join([k + "=" + str(v) for k, v in totals.items()]))
+    return call
+
+_profile_class = globals().get("{}")
+for _profile_callback in ("event", "user_event"):
+    if _profile_class is not None and hasattr(_profile_class, _profile_callback):
+        setattr(_profile_class, _profile_callback, _profile_wrap(getattr(_profile_class, _profile_callback), _profile_callback))
+"##,
+        class
+    )
+}
+
+/// Start the server. If deterministic settings are given, every entity and item
+/// receives a derived `rng_seed` attribute and the scripts draw their `random()`
+/// numbers from it. The random behaviour built into the server is not seeded.
+/// In profiled runs the scripts report the run time of their callbacks in the
+/// `script_profile` attribute.
+pub fn start_server(
+    rusterix: &mut Rusterix,
+    project: &mut Project,
+    debug: bool,
+    profile: bool,
+    deterministic: Option<DeterministicSettings>,
+) {
     rusterix.server.clear();
     rusterix.server.debug_mode = debug;
     rusterix.server.log_changed = true;
     rusterix.assets.global = project.render_graph.clone();
 
     insert_content_into_maps(project);
+    insert_shops(project);
+
+    let config = project.localization.runtime_config(&project.config);
+    if let Some(settings) = deterministic {
+        let mut index = 0;
+        for region in &mut project.regions {
+            for entity in &mut region.map.entities {
+                let seed = settings.seed_for(index) as i32;
+                entity.set_attribute("rng_seed", Value::Int(seed));
+                index += 1;
+            }
+            for item in &mut region.map.items {
+                let seed = settings.seed_for(index) as i32;
+                item.set_attribute("rng_seed", Value::Int(seed));
+                index += 1;
+            }
+        }
+    }
+
+    let runtime_source = |source: &str, class: &str| {
+        let mut source = project.runtime_source(source);
+        if deterministic.is_some() {
+            source.push_str(DETERMINISTIC_RANDOM_SOURCE);
+        }
+        if profile {
+            source.push_str(&script_profile_source(class));
+        }
+        source
+    };
 
     // Characters
     rusterix.assets.entities.clear();
@@ -18,12 +97,18 @@ pub fn start_server(rusterix: &mut Rusterix, project: &mut Project, debug: bool)
         if debug && !character.source_debug.is_empty() {
             rusterix.assets.entities.insert(
                 character.name.clone(),
-                (character.source_debug.clone(), character.data.clone()),
+                (
+                    runtime_source(&character.source_debug, &character.name),
+                    character.data.clone(),
+                ),
             );
         } else {
             rusterix.assets.entities.insert(
                 character.name.clone(),
-                (character.source.clone(), character.data.clone()),
+                (
+                    runtime_source(&character.source, &character.name),
+                    character.data.clone(),
+                ),
             );
         }
         if !character.map.vertices.is_empty() {
@@ -42,13 +127,16 @@ pub fn start_server(rusterix: &mut Rusterix, project: &mut Project, debug: bool)
         if debug && !item.source_debug.is_empty() {
             rusterix.assets.items.insert(
                 item.name.clone(),
-                (item.source_debug.clone(), item.data.clone()),
+                (
+                    runtime_source(&item.source_debug, &item.name),
+                    item.data.clone(),
+                ),
             );
         } else {
-            rusterix
-                .assets
-                .items
-                .insert(item.name.clone(), (item.source.clone(), item.data.clone()));
+            rusterix.assets.items.insert(
+                item.name.clone(),
+                (runtime_source(&item.source, &item.name), item.data.clone()),
+            );
         }
         if !item.map.vertices.is_empty() {
             rusterix
@@ -58,15 +146,29 @@ pub fn start_server(rusterix: &mut Rusterix, project: &mut Project, debug: bool)
         }
     }
 
-    // Create the regions
-    for region in &mut project.regions {
+    // Create the regions, regions with a generator running on start get a new layout
+    let mut generated_maps = IndexMap::default();
+    for (index, region) in project.regions.iter().enumerate() {
+        let mut map = region.map.clone();
+        if let Some(mut generator) =
+            RegionGenerator::from_config(&region.config).filter(|g| g.on_start)
+        {
+            if let Some(settings) = deterministic {
+                generator.seed = settings.seed_for(u64::MAX - index as u64).max(1);
+            }
+            map = generate_region_map(project, region, &generator, deterministic);
+            generated_maps.insert(region.id, map.clone());
+        }
+        localize_map_names(&mut map, &project.localization);
+        add_props_to_map(&mut map, &region.props, &project.assets, true);
         rusterix.server.create_region_instance(
             region.name.clone(),
-            region.map.clone(),
+            map,
             &rusterix.assets,
-            project.config.clone(),
+            config.clone(),
         );
     }
+    project.generated_maps = generated_maps;
 
     // Wait for the region to be created
     #[cfg(not(target_arch = "wasm32"))]
@@ -79,23 +181,112 @@ pub fn start_server(rusterix: &mut Rusterix, project: &mut Project, debug: bool)
     rusterix.server.set_state(rusterix::ServerState::Running);
 }
 
+/// Generates the map of a region and its instances. In deterministic runs the
+/// spawned entities and items get seeds derived from the generator seed.
+fn generate_region_map(
+    project: &Project,
+    region: &Region,
+    generator: &RegionGenerator,
+    deterministic: Option<DeterministicSettings>,
+) -> Map {
+    let mut generated = region.clone();
+    generator.generate(
+        &mut generated,
+        project,
+        DEFAULT_INSTANCE_SOURCE,
+        DEFAULT_INSTANCE_SOURCE,
+    );
+    insert_region_content(&mut generated, &project.characters, &project.items);
+    if let Some(settings) = deterministic {
+        let mut index = generator.seed;
+        for entity in &mut generated.map.entities {
+            let seed = settings.seed_for(index) as i32;
+            entity.set_attribute("rng_seed", Value::Int(seed));
+            index = index.wrapping_add(1);
+        }
+        for item in &mut generated.map.items {
+            let seed = settings.seed_for(index) as i32;
+            item.set_attribute("rng_seed", Value::Int(seed));
+            index = index.wrapping_add(1);
+        }
+    }
+    generated.map
+}
+
+/// Generates the region with the given name again while the game runs, as
+/// requested by `generate_region()` of the `generator` library. A seed other
+/// than 0 replaces the seed of the `[generator]` section. The region instance
+/// is created again with the new map, so its characters and items start anew.
+/// Returns false if there is no such region or it has no `[generator]` section.
+pub fn regenerate_region(
+    rusterix: &mut Rusterix,
+    project: &mut Project,
+    name: &str,
+    seed: u64,
+    deterministic: Option<DeterministicSettings>,
+) -> bool {
+    let Some(region) = project.regions.iter().find(|r| r.name == name) else {
+        return false;
+    };
+    let Some(mut generator) = RegionGenerator::from_config(&region.config) else {
+        return false;
+    };
+    if seed != 0 {
+        generator.seed = seed;
+    }
+    let region_id = region.id;
+    let map = generate_region_map(project, region, &generator, deterministic);
+    let time = rusterix.server.get_time(&map.id);
+
+    let mut instance_map = map.clone();
+    localize_map_names(&mut instance_map, &project.localization);
+    add_props_to_map(&mut instance_map, &region.props, &project.assets, true);
+    rusterix.server.create_region_instance(
+        name.to_string(),
+        instance_map,
+        &rusterix.assets,
+        project.localization.runtime_config(&project.config),
+    );
+
+    // Wait for the region to be created
+    #[cfg(not(target_arch = "wasm32"))]
+    std::thread::sleep(std::time::Duration::from_millis(10));
+    if let Some(time) = time {
+        rusterix.server.set_time(&map.id, time);
+    }
+
+    let mut client_map = map.clone();
+    add_props_to_map(&mut client_map, &region.props, &project.assets, false);
+    rusterix.assets.maps.insert(map.name.clone(), client_map);
+    if let Some(region) = project.get_region_mut(&region_id) {
+        region.map.entities = map.entities.clone();
+        region.map.items = map.items.clone();
+    }
+    project.generated_maps.insert(region_id, map);
+    true
+}
+
 /// Setup the client
 pub fn setup_client(rusterix: &mut Rusterix, project: &mut Project) -> Vec<Command> {
-    rusterix.assets.config = project.config.clone();
+    rusterix.assets.config = project.localization.runtime_config(&project.config);
     rusterix.assets.read_locales();
     rusterix.assets.palette = project.palette.clone();
     rusterix.assets.maps.clear();
     for region in &project.regions {
-        rusterix
-            .assets
-            .maps
-            .insert(region.map.name.clone(), region.map.clone());
-    }
-    rusterix.assets.screens.clear();
-    for (_, screen) in &project.screens {
-        let scr = screen.map.clone();
-        rusterix.assets.screens.insert(screen.map.name.clone(), scr);
+        let mut map = project
+            .generated_maps
+            .get(&region.id)
+            .unwrap_or(&region.map)
+            .clone();
+        add_props_to_map(&mut map, &region.props, &project.assets, false);
+        rusterix.assets.maps.insert(region.map.name.clone(), map);
     }
+    let viewport = ScreenViewport::from_config(&project.config);
+    insert_screens(
+        rusterix,
+        project,
+        Vec2::new(viewport.width, viewport.height),
+    );
     rusterix.assets.fonts.clear();
     for (_, asset) in project.assets.iter() {
         if let AssetBuffer::Font(bytes) = &asset.buffer {
@@ -109,43 +300,210 @@ pub fn setup_client(rusterix: &mut Rusterix, project: &mut Project) -> Vec<Comma
     rusterix.setup_client()
 }
 
+/// Insert the screens into the client assets with their widgets placed by
+/// their anchors on a screen of the given size.
+pub fn insert_screens(rusterix: &mut Rusterix, project: &Project, size: Vec2<f32>) {
+    let viewport = ScreenViewport::from_config(&project.config);
+    rusterix.assets.screens.clear();
+    for (_, screen) in &project.screens {
+        let mut scr = screen.map.clone();
+        viewport.anchor_map(&mut scr, size);
+        // Inline the script libraries imported by the widget classes and
+        // translate the widget texts
+        for sector in &mut scr.sectors {
+            if let Some(Value::Str(source)) = sector.properties.get("source") {
+                let source = project.runtime_source(source);
+                sector.properties.set("source", Value::Str(source));
+            }
+            if let Some(Value::Str(data)) = sector.properties.get("data") {
+                let data = journal_widget_data(data)
+                    .or_else(|| inventory_widget_data(data))
+                    .unwrap_or_else(|| data.clone());
+                let data =
+                    project
+                        .localization
+                        .localize_widget_data(&screen.name, &sector.name, &data);
+                sector.properties.set("data", Value::Str(data));
+            }
+        }
+        rusterix.assets.screens.insert(screen.map.name.clone(), scr);
+    }
+}
+
+/// Set the names of the entities and items of the map to the active locale.
+pub fn localize_map_names(map: &mut Map, localization: &Localization) {
+    for entity in &mut map.entities {
+        if let Some(Value::Str(name)) = entity.attributes.get("name") {
+            if let Some(name) = localization.localize_name(name) {
+                entity.set_attribute("name", Value::Str(name));
+            }
+        }
+    }
+    for item in &mut map.items {
+        if let Some(Value::Str(name)) = item.attributes.get("name") {
+            if let Some(name) = localization.localize_name(name) {
+                item.set_attribute("name", Value::Str(name));
+            }
+        }
+    }
+}
+
 /// Convert the characters and items into Entities / Items for the rusterix server
 pub fn insert_content_into_maps(project: &mut Project) {
     for region in &mut project.regions {
-        region.map.entities.clear();
-        for instance in region.characters.values() {
-            let mut entity = Entity {
-                creator_id: instance.id,
-                position: instance.position,
-                ..Default::default()
-            };
-            entity.set_attribute("name", Value::Str(instance.name.clone()));
-            if let Some(character_template) = project.characters.get(&instance.character_id) {
-                entity.set_attribute("name", Value::Str(character_template.name.clone()));
+        insert_region_content(region, &project.characters, &project.items);
+    }
+}
+
+/// Convert the characters and items of the region into Entities / Items.
+pub fn insert_region_content(
+    region: &mut Region,
+    characters: &IndexMap<Uuid, Character>,
+    items: &IndexMap<Uuid, Item>,
+) {
+    region.map.entities.clear();
+    for instance in region.characters.values() {
+        let mut entity = Entity {
+            creator_id: instance.id,
+            position: instance.position,
+            ..Default::default()
+        };
+        entity.set_attribute("name", Value::Str(instance.name.clone()));
+        if let Some(character_template) = characters.get(&instance.character_id) {
+            entity.set_attribute("name", Value::Str(character_template.name.clone()));
+        }
+        entity.set_attribute("setup", Value::Str(instance.source.clone()));
+        if let Some(character) = characters.get(&instance.character_id) {
+            entity.set_attribute("class_name", Value::Str(character.name.clone()));
+        }
+        for (key, value) in data_attributes(&instance.data) {
+            entity.set_attribute(&key, value);
+        }
+        region.map.entities.push(entity);
+    }
+
+    region.map.items.clear();
+    for instance in region.items.values() {
+        let mut item = rusterix::Item {
+            creator_id: instance.id,
+            position: instance.position,
+            ..Default::default()
+        };
+        item.set_attribute("name", Value::Str(instance.name.clone()));
+        if let Some(item_template) = items.get(&instance.item_id) {
+            item.set_attribute("name", Value::Str(item_template.name.clone()));
+        }
+        item.set_attribute("setup", Value::Str(instance.source.clone()));
+        if let Some(character) = items.get(&instance.item_id) {
+            item.set_attribute("class_name", Value::Str(character.name.clone()));
+        }
+        for (key, value) in data_attributes(&instance.data) {
+            item.set_attribute(&key, value);
+        }
+        region.map.items.push(item);
+    }
+}
+
+/// Send a user event to the local player through the client and send the
+/// resulting action to the server.
+pub fn send_user_event(rusterix: &mut Rusterix, event: String, value: Value) {
+    let action = rusterix.client.user_event(event, value);
+    rusterix.server.local_player_action(action);
+}
+
+/// Feed a recorded input into the client and send the resulting action to the server.
+pub fn apply_replay_input(rusterix: &mut Rusterix, input: &ReplayInput, map: &Map) {
+    match input {
+        ReplayInput::UserEvent(event, value) => {
+            send_user_event(rusterix, event.clone(), Value::Str(value.clone()));
+        }
+        ReplayInput::TouchDown(coord) => {
+            if let Some(action) = rusterix.client.touch_down(*coord, map) {
+                rusterix.server.local_player_action(action);
+            }
+        }
+        ReplayInput::TouchUp(coord) => {
+            rusterix.client.touch_up(*coord, map);
+            rusterix.server.local_player_action(EntityAction::Off);
+        }
+    }
+}
+
+/// Returns the `[attributes]` table of an instance's TOML data as attribute values.
+pub fn data_attributes(data: &str) -> Vec<(String, Value)> {
+    let mut attributes = vec![];
+    if let Ok(table) = data.parse::<toml::Table>() {
+        if let Some(toml::Value::Table(attrs)) = table.get("attributes") {
+            for (key, value) in attrs {
+                attributes.push((key.clone(), toml_to_value(value)));
             }
-            entity.set_attribute("setup", Value::Str(instance.source.clone()));
-            if let Some(character) = project.characters.get(&instance.character_id) {
-                entity.set_attribute("class_name", Value::Str(character.name.clone()));
+        }
+    }
+    attributes
+}
+
+/// Converts a TOML value into an attribute value.
+pub fn toml_to_value(value: &toml::Value) -> Value {
+    match value {
+        toml::Value::Integer(v) => Value::Int(*v as i32),
+        toml::Value::Float(v) => Value::Float(*v as f32),
+        toml::Value::Boolean(v) => Value::Bool(*v),
+        toml::Value::String(v) => Value::Str(v.clone()),
+        _ => Value::Str(value.to_string()),
+    }
+}
+
+/// Converts an attribute value into a TOML value. Returns None for values which
+/// have no TOML representation.
+pub fn value_to_toml(value: &Value) -> Option<toml::Value> {
+    match value {
+        Value::Int(v) => Some(toml::Value::Integer(*v as i64)),
+        Value::Float(v) => Some(toml::Value::Float(*v as f64)),
+        Value::Bool(v) => Some(toml::Value::Boolean(*v)),
+        Value::Str(v) => Some(toml::Value::String(v.clone())),
+        _ => None,
+    }
+}
+
+/// A live edit of an entity or item in a running region, see `apply_instance_edit`.
+#[derive(Clone, Debug)]
+pub enum InstanceEdit {
+    EntityAttribute(u32, String, Value),
+    EntityPosition(u32, Vec3<f32>),
+    ItemAttribute(u32, String, Value),
+}
+
+/// Send a live edit to the region instance running the given map and apply it to
+/// the local copy of the map so that the change is visible before the next update.
+/// Item edits also apply to items in inventories and equipment slots.
+///
+/// This needs `set_entity_attribute`, `set_entity_position` and
+/// `set_item_attribute` on the rusterix `Server`, which take the region id,
+/// the entity or item id and the new value. They are not part of a published
+/// rusterix revision yet.
+pub fn apply_instance_edit(rusterix: &mut Rusterix, map: &mut Map, edit: &InstanceEdit) {
+    match edit {
+        InstanceEdit::EntityAttribute(id, key, value) => {
+            rusterix
+                .server
+                .set_entity_attribute(&map.id, *id, key, value.clone());
+            if let Some(entity) = map.entities.iter_mut().find(|e| e.id == *id) {
+                entity.set_attribute(key, value.clone());
             }
-            region.map.entities.push(entity);
-        }
-
-        region.map.items.clear();
-        for instance in region.items.values() {
-            let mut item = rusterix::Item {
-                creator_id: instance.id,
-                position: instance.position,
-                ..Default::default()
-            };
-            item.set_attribute("name", Value::Str(instance.name.clone()));
-            if let Some(item_template) = project.items.get(&instance.item_id) {
-                item.set_attribute("name", Value::Str(item_template.name.clone()));
+        }
+        InstanceEdit::EntityPosition(id, position) => {
+            rusterix.server.set_entity_position(&map.id, *id, *position);
+            if let Some(entity) = map.entities.iter_mut().find(|e| e.id == *id) {
+                entity.position = *position;
             }
-            item.set_attribute("setup", Value::Str(instance.source.clone()));
-            if let Some(character) = project.items.get(&instance.item_id) {
-                item.set_attribute("class_name", Value::Str(character.name.clone()));
+        }
+        InstanceEdit::ItemAttribute(id, key, value) => {
+            rusterix
+                .server
+                .set_item_attribute(&map.id, *id, key, value.clone());
+            if let Some(item) = map.items.iter_mut().find(|i| i.id == *id) {
+                item.set_attribute(key, value.clone());
             }
-            region.map.items.push(item);
         }
     }
 }
@@ -1,4 +1,4 @@
-use rusterix::Map;
+use rusterix::{Map, Sector, Value};
 use theframework::prelude::*;
 
 #[derive(Serialize, Deserialize, Clone, Debug)]
@@ -41,6 +41,12 @@ impl Screen {
 pub enum ScreenAspectRatio {
     _16_9,
     _4_3,
+    _16_10,
+    _21_9,
+    _32_9,
+    _19_5_9,
+    _9_16,
+    _9_19_5,
 }
 
 impl ScreenAspectRatio {
@@ -48,16 +54,39 @@ impl ScreenAspectRatio {
         match self {
             Self::_16_9 => "16:9",
             Self::_4_3 => "4:3",
+            Self::_16_10 => "16:10",
+            Self::_21_9 => "21:9",
+            Self::_32_9 => "32:9",
+            Self::_19_5_9 => "19.5:9",
+            Self::_9_16 => "9:16",
+            Self::_9_19_5 => "9:19.5",
         }
     }
     pub fn ratio(self) -> f32 {
         match self {
             Self::_16_9 => 16.0 / 9.0,
             Self::_4_3 => 4.0 / 3.0,
+            Self::_16_10 => 16.0 / 10.0,
+            Self::_21_9 => 21.0 / 9.0,
+            Self::_32_9 => 32.0 / 9.0,
+            Self::_19_5_9 => 19.5 / 9.0,
+            Self::_9_16 => 9.0 / 16.0,
+            Self::_9_19_5 => 9.0 / 19.5,
         }
     }
     pub fn iterator() -> impl Iterator<Item = ScreenAspectRatio> {
-        [Self::_16_9, Self::_4_3].iter().copied()
+        [
+            Self::_16_9,
+            Self::_4_3,
+            Self::_16_10,
+            Self::_21_9,
+            Self::_32_9,
+            Self::_19_5_9,
+            Self::_9_16,
+            Self::_9_19_5,
+        ]
+        .iter()
+        .copied()
     }
     pub fn width(self, height: i32) -> i32 {
         (height as f32 * self.ratio()) as i32
@@ -66,10 +95,342 @@ impl ScreenAspectRatio {
         (width as f32 / self.ratio()) as i32
     }
     pub fn from_index(index: u8) -> Option<ScreenAspectRatio> {
-        match index {
-            0 => Some(Self::_16_9),
-            1 => Some(Self::_4_3),
-            _ => None,
+        Self::iterator().nth(index as usize)
+    }
+}
+
+/// How the screen is scaled to the window.
+#[derive(Serialize, Deserialize, PartialEq, Clone, Copy, Debug, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum ScreenScaling {
+    /// Fills the window, distorting the screen if the aspect ratios differ.
+    #[default]
+    Stretch,
+    /// Keeps the aspect ratio and letterboxes the rest of the window.
+    Fit,
+    /// Like fit but only scales by whole numbers, for crisp pixel art.
+    Integer,
+    /// Keeps the aspect ratio and grows the screen to the aspect ratio of the
+    /// window, the anchors of the widgets place them on the larger screen.
+    Expand,
+}
+
+impl ScreenScaling {
+    pub fn to_string(self) -> &'static str {
+        match self {
+            Self::Stretch => "Stretch",
+            Self::Fit => "Fit",
+            Self::Integer => "Integer",
+            Self::Expand => "Expand",
+        }
+    }
+    pub fn iterator() -> impl Iterator<Item = ScreenScaling> {
+        [Self::Stretch, Self::Fit, Self::Integer, Self::Expand]
+            .iter()
+            .copied()
+    }
+    pub fn from_index(index: u8) -> Option<ScreenScaling> {
+        Self::iterator().nth(index as usize)
+    }
+}
+
+/// The corner, edge or center of the screen a widget keeps its distance to.
+#[derive(Serialize, Deserialize, PartialEq, Clone, Copy, Debug, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum ScreenAnchor {
+    #[default]
+    TopLeft,
+    Top,
+    TopRight,
+    Left,
+    Center,
+    Right,
+    BottomLeft,
+    Bottom,
+    BottomRight,
+}
+
+impl ScreenAnchor {
+    /// The horizontal and vertical position of the anchor, 0.0 at the top left
+    /// and 1.0 at the bottom right.
+    pub fn factors(self) -> (f32, f32) {
+        match self {
+            Self::TopLeft => (0.0, 0.0),
+            Self::Top => (0.5, 0.0),
+            Self::TopRight => (1.0, 0.0),
+            Self::Left => (0.0, 0.5),
+            Self::Center => (0.5, 0.5),
+            Self::Right => (1.0, 0.5),
+            Self::BottomLeft => (0.0, 1.0),
+            Self::Bottom => (0.5, 1.0),
+            Self::BottomRight => (1.0, 1.0),
+        }
+    }
+}
+
+/// The directions a widget grows in with the screen.
+#[derive(Serialize, Deserialize, PartialEq, Clone, Copy, Debug, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum ScreenStretch {
+    #[default]
+    None,
+    Horizontal,
+    Vertical,
+    Both,
+}
+
+/// The layout options of a screen widget, the `anchor`, `stretch` and
+/// `safe_area` keys of its `[ui]` table.
+#[derive(Deserialize, Clone, Copy, Debug, PartialEq)]
+#[serde(default)]
+pub struct WidgetAnchor {
+    pub anchor: ScreenAnchor,
+    pub stretch: ScreenStretch,
+    /// Keep the widget inside the safe area of the viewport.
+    pub safe_area: bool,
+}
+
+impl Default for WidgetAnchor {
+    fn default() -> Self {
+        Self {
+            anchor: ScreenAnchor::TopLeft,
+            stretch: ScreenStretch::None,
+            safe_area: true,
+        }
+    }
+}
+
+impl WidgetAnchor {
+    /// The layout options of the widget, the defaults if it has no `[ui]` table.
+    pub fn from_sector(sector: &Sector) -> Self {
+        let Some(ui) = widget_ui(sector) else {
+            return Self::default();
+        };
+        toml::Value::Table(ui)
+            .try_into::<Self>()
+            .unwrap_or_else(|err| {
+                eprintln!("Invalid layout of widget \"{}\": {}", sector.name, err);
+                Self::default()
+            })
+    }
+}
+
+/// The `[viewport]` of the game config, the size the screens are designed for
+/// and how they are laid out in windows of other sizes.
+#[derive(Deserialize, Clone, Debug)]
+#[serde(default)]
+pub struct ScreenViewport {
+    pub width: f32,
+    pub height: f32,
+    pub grid_size: f32,
+    pub scaling: ScreenScaling,
+    /// The color of the bars around a letterboxed screen.
+    pub letterbox: String,
+    /// The insets of the safe area in viewport pixels as [top, right, bottom,
+    /// left], kept clear of widgets for notches and rounded corners.
+    pub safe_area: [f32; 4],
+}
+
+impl Default for ScreenViewport {
+    fn default() -> Self {
+        Self {
+            width: 960.0,
+            height: 540.0,
+            grid_size: 32.0,
+            scaling: ScreenScaling::Stretch,
+            letterbox: "#000000".into(),
+            safe_area: [0.0; 4],
+        }
+    }
+}
+
+/// The placement of a screen in a window.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ScreenLayout {
+    /// The size of the screen in viewport pixels, only differs from the
+    /// viewport for the `expand` scaling.
+    pub size: Vec2<f32>,
+    /// The scale from screen to window pixels.
+    pub scale: Vec2<f32>,
+    /// The top left corner of the screen in the window.
+    pub offset: Vec2<f32>,
+}
+
+impl ScreenLayout {
+    /// Maps a rectangle in screen pixels to the window.
+    pub fn to_output(&self, rect: (f32, f32, f32, f32)) -> (f32, f32, f32, f32) {
+        (
+            self.offset.x + rect.0 * self.scale.x,
+            self.offset.y + rect.1 * self.scale.y,
+            rect.2 * self.scale.x,
+            rect.3 * self.scale.y,
+        )
+    }
+
+    /// The rectangle of the screen in the window, the rest are letterbox bars.
+    pub fn screen_rect(&self) -> (f32, f32, f32, f32) {
+        self.to_output((0.0, 0.0, self.size.x, self.size.y))
+    }
+
+    /// Maps a window coordinate to screen pixels, None on the letterbox bars.
+    pub fn to_screen(&self, coord: Vec2<i32>) -> Option<Vec2<i32>> {
+        let x = (coord.x as f32 - self.offset.x) / self.scale.x;
+        let y = (coord.y as f32 - self.offset.y) / self.scale.y;
+        if x < 0.0 || y < 0.0 || x >= self.size.x || y >= self.size.y {
+            return None;
+        }
+        Some(Vec2::new(x as i32, y as i32))
+    }
+
+    /// The size of the screen in whole pixels.
+    pub fn screen_size(&self) -> (usize, usize) {
+        (
+            self.size.x.round().max(1.0) as usize,
+            self.size.y.round().max(1.0) as usize,
+        )
+    }
+}
+
+impl ScreenViewport {
+    pub fn from_config(config: &str) -> Self {
+        config
+            .parse::<toml::Table>()
+            .ok()
+            .and_then(|t| t.get("viewport")?.clone().try_into::<Self>().ok())
+            .unwrap_or_default()
+    }
+
+    /// The rectangle of a widget in viewport pixels as (x, y, width, height).
+    /// Screen coordinates are grid cells from the top left corner of the
+    /// viewport.
+    pub fn widget_rect(&self, map: &Map, sector: &Sector) -> (f32, f32, f32, f32) {
+        let (min, max) = sector.bounding_box(map);
+        (
+            min.x * self.grid_size,
+            min.y * self.grid_size,
+            (max.x - min.x) * self.grid_size,
+            (max.y - min.y) * self.grid_size,
+        )
+    }
+
+    /// Places the screen in a window of the given size.
+    pub fn layout(&self, window: (usize, usize)) -> ScreenLayout {
+        let (ww, wh) = (window.0.max(1) as f32, window.1.max(1) as f32);
+        let (w, h) = (self.width.max(1.0), self.height.max(1.0));
+        let fit = (ww / w).min(wh / h);
+        let (size, scale) = match self.scaling {
+            ScreenScaling::Stretch => (Vec2::new(w, h), Vec2::new(ww / w, wh / h)),
+            ScreenScaling::Fit => (Vec2::new(w, h), Vec2::broadcast(fit)),
+            // Windows smaller than the viewport can only be fitted
+            ScreenScaling::Integer => {
+                let scale = if fit >= 1.0 { fit.floor() } else { fit };
+                (Vec2::new(w, h), Vec2::broadcast(scale))
+            }
+            ScreenScaling::Expand => (Vec2::new(ww / fit, wh / fit), Vec2::broadcast(fit)),
+        };
+        ScreenLayout {
+            size,
+            scale,
+            offset: Vec2::new(
+                ((ww - size.x * scale.x) / 2.0).floor(),
+                ((wh - size.y * scale.y) / 2.0).floor(),
+            ),
         }
     }
+
+    /// The area of a screen of the given size inside the safe area insets.
+    pub fn safe_rect(&self, size: Vec2<f32>) -> (f32, f32, f32, f32) {
+        let [top, right, bottom, left] = self.safe_area;
+        (
+            left,
+            top,
+            (size.x - left - right).max(0.0),
+            (size.y - top - bottom).max(0.0),
+        )
+    }
+
+    /// Places a widget rectangle designed for the viewport on a screen of the
+    /// given size. The widget keeps its distance to the edges of its anchor and
+    /// grows by the size difference in its stretch directions.
+    pub fn place(
+        &self,
+        rect: (f32, f32, f32, f32),
+        anchor: &WidgetAnchor,
+        size: Vec2<f32>,
+    ) -> (f32, f32, f32, f32) {
+        let (fx, fy, fw, fh) = if anchor.safe_area {
+            self.safe_rect(size)
+        } else {
+            (0.0, 0.0, size.x, size.y)
+        };
+        let (dw, dh) = (fw - self.width, fh - self.height);
+        let (ax, ay) = anchor.anchor.factors();
+        let horizontal = matches!(
+            anchor.stretch,
+            ScreenStretch::Horizontal | ScreenStretch::Both
+        );
+        let vertical = matches!(
+            anchor.stretch,
+            ScreenStretch::Vertical | ScreenStretch::Both
+        );
+        let (x, w) = if horizontal {
+            (fx + rect.0, (rect.2 + dw).max(0.0))
+        } else {
+            (fx + rect.0 + ax * dw, rect.2)
+        };
+        let (y, h) = if vertical {
+            (fy + rect.1, (rect.3 + dh).max(0.0))
+        } else {
+            (fy + rect.1 + ay * dh, rect.3)
+        };
+        (x, y, w, h)
+    }
+
+    /// Moves the widget sectors of a screen map to their places on a screen of
+    /// the given size. Vertices shared by widgets follow the first widget.
+    pub fn anchor_map(&self, map: &mut Map, size: Vec2<f32>) {
+        let grid_size = self.grid_size.max(1.0);
+        let mut moved = FxHashMap::default();
+        for sector in &map.sectors {
+            let rect = self.widget_rect(map, sector);
+            let placed = self.place(rect, &WidgetAnchor::from_sector(sector), size);
+            if placed == rect {
+                continue;
+            }
+            let sx = if rect.2 > 0.0 { placed.2 / rect.2 } else { 1.0 };
+            let sy = if rect.3 > 0.0 { placed.3 / rect.3 } else { 1.0 };
+            for linedef_id in &sector.linedefs {
+                let Some(linedef) = map.find_linedef(*linedef_id) else {
+                    continue;
+                };
+                for vertex_id in [linedef.start_vertex, linedef.end_vertex] {
+                    let Some(vertex) = map.find_vertex(vertex_id) else {
+                        continue;
+                    };
+                    let x = placed.0 + (vertex.x * grid_size - rect.0) * sx;
+                    let y = placed.1 + (vertex.y * grid_size - rect.1) * sy;
+                    moved
+                        .entry(vertex_id)
+                        .or_insert(Vec2::new(x / grid_size, y / grid_size));
+                }
+            }
+        }
+        for (vertex_id, position) in moved {
+            if let Some(vertex) = map.find_vertex_mut(vertex_id) {
+                vertex.x = position.x;
+                vertex.y = position.y;
+            }
+        }
+    }
+}
+
+/// The `[ui]` table of the data of a screen widget.
+pub fn widget_ui(sector: &Sector) -> Option<toml::Table> {
+    let Some(Value::Str(data)) = sector.properties.get("data") else {
+        return None;
+    };
+    match data.parse::<toml::Table>().ok()?.remove("ui")? {
+        toml::Value::Table(ui) => Some(ui),
+        _ => None,
+    }
 }
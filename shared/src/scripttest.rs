@@ -0,0 +1,578 @@
+use crate::prelude::*;
+use crate::rusterix_utils::{send_user_event, toml_to_value};
+use rusterix::{Entity, Rusterix, Value};
+use theframework::prelude::*;
+
+/// The name of the isolated test region.
+const TEST_REGION: &str = "__script_test";
+/// The class of the character the instance under test interacts with.
+const PARTNER_CLASS: &str = "__TestPartner";
+/// The user event which numbers the steps, the local player stores the step
+/// in the `test_step` attribute once the server ran it.
+const STEP_EVENT: &str = "__test_step";
+/// How often the server is polled for a step before the test fails. The
+/// region thread runs a step in well under a millisecond.
+const MAX_STEP_POLLS: u32 = 5000;
+/// Separates the receiver, category and text of a recorded message.
+const FIELD_SEPARATOR: char = '\u{1f}';
+/// Separates the recorded messages.
+const RECORD_SEPARATOR: char = '\u{1e}';
+
+/// A unit test for a character or item class. The test is defined in TOML, it
+/// names the class under test, the events to send and the expected results.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct ScriptTest {
+    pub id: Uuid,
+    pub name: String,
+
+    /// The TOML test specification.
+    #[serde(default)]
+    pub source: String,
+}
+
+impl Default for ScriptTest {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// The parsed TOML specification of a test.
+#[derive(Deserialize, Clone, Debug, Default)]
+struct ScriptTestSpec {
+    /// The name of the character or item class under test.
+    class: String,
+
+    /// The number of game ticks to run after the events have been sent.
+    #[serde(default = "default_ticks")]
+    ticks: u32,
+
+    /// Attribute overrides for the instance.
+    #[serde(default)]
+    attributes: toml::Table,
+
+    #[serde(default)]
+    events: Vec<ScriptTestEvent>,
+
+    #[serde(default)]
+    expect: Vec<ScriptTestExpect>,
+}
+
+fn default_ticks() -> u32 {
+    1
+}
+
+/// An event sent to the instance. Exactly one of the kinds should be set.
+#[derive(Deserialize, Clone, Debug, Default)]
+struct ScriptTestEvent {
+    /// A user event, i.e. "key_down". Sent by the server to `user_event` of a
+    /// character, which is the local player of the test region.
+    user_event: Option<String>,
+    /// A custom event. Dispatched to `event`.
+    event: Option<String>,
+    /// An interaction of the partner character with the instance, i.e.
+    /// "talk". Dispatched to `event` as the "intent" event the server sends.
+    intent: Option<String>,
+
+    /// The value of the user or custom event.
+    #[serde(default)]
+    value: String,
+}
+
+/// An expected result after all ticks have run.
+#[derive(Deserialize, Clone, Debug, Default)]
+struct ScriptTestExpect {
+    /// The attribute to check, compared against `value`.
+    attribute: Option<String>,
+    /// The text of a message which must have been sent by the instance.
+    message: Option<String>,
+    /// The category the message must have been sent with.
+    category: Option<String>,
+    /// The receiver of the message, "self" or "partner".
+    to: Option<String>,
+    /// An action which must have been taken by the instance.
+    action: Option<String>,
+
+    #[serde(default)]
+    value: String,
+}
+
+/// A message sent by the instance under test.
+#[derive(Clone, Debug, PartialEq)]
+struct SentMessage {
+    receiver: String,
+    category: String,
+    text: String,
+}
+
+impl SentMessage {
+    /// The messages recorded by the driver in the `test_messages` attribute.
+    fn parse_all(recorded: &str) -> Vec<Self> {
+        recorded
+            .split(RECORD_SEPARATOR)
+            .filter(|r| !r.is_empty())
+            .filter_map(|record| {
+                let mut fields = record.splitn(3, FIELD_SEPARATOR);
+                Some(Self {
+                    receiver: fields.next()?.to_string(),
+                    category: fields.next()?.to_string(),
+                    text: fields.next()?.to_string(),
+                })
+            })
+            .collect()
+    }
+}
+
+/// The result of a test run.
+#[derive(Clone, Debug)]
+pub struct ScriptTestResult {
+    pub name: String,
+    pub passed: bool,
+    pub failures: Vec<String>,
+    pub log: String,
+}
+
+impl ScriptTestResult {
+    fn failed(name: &str, failure: String) -> Self {
+        Self {
+            name: name.to_string(),
+            passed: false,
+            failures: vec![failure],
+            log: String::new(),
+        }
+    }
+}
+
+impl ScriptTest {
+    pub fn new() -> Self {
+        Self {
+            id: Uuid::new_v4(),
+            name: "New Test".to_string(),
+            source: String::new(),
+        }
+    }
+
+    /// Run the test against the classes of the given project. The class is
+    /// instantiated in an isolated region on an empty map, together with a
+    /// partner character it can interact with. A character under test is the
+    /// local player of the region, for an item the partner is. The server is
+    /// stepped one game tick at a time: every step ends with a numbered user
+    /// event to the local player and the test waits until the player stored
+    /// the number, so the step ran completely before the next one is sent.
+    pub fn run(&self, project: &Project) -> ScriptTestResult {
+        let spec: ScriptTestSpec = match toml::from_str(&self.source) {
+            Ok(spec) => spec,
+            Err(err) => {
+                return ScriptTestResult::failed(&self.name, format!("Invalid test: {}", err));
+            }
+        };
+
+        let character = project.characters.values().find(|c| c.name == spec.class);
+        let item = project.items.values().find(|i| i.name == spec.class);
+
+        let (source, data) = if let Some(character) = character {
+            (&character.source, &character.data)
+        } else if let Some(item) = item {
+            (&item.source, &item.data)
+        } else {
+            return ScriptTestResult::failed(
+                &self.name,
+                format!("Unknown class '{}'.", spec.class),
+            );
+        };
+        let is_character = character.is_some();
+        if !is_character && spec.events.iter().any(|e| e.user_event.is_some()) {
+            return ScriptTestResult::failed(
+                &self.name,
+                "User events can only be sent to characters.".into(),
+            );
+        }
+
+        let mut rusterix = Rusterix::default();
+        rusterix.assets.global = project.render_graph.clone();
+
+        // All classes are available so that the class under test can reference them
+        for character in project.characters.values() {
+            rusterix.assets.entities.insert(
+                character.name.clone(),
+                (
+                    project.resolve_script_imports(&character.source),
+                    character.data.clone(),
+                ),
+            );
+        }
+        for item in project.items.values() {
+            rusterix.assets.items.insert(
+                item.name.clone(),
+                (
+                    project.resolve_script_imports(&item.source),
+                    item.data.clone(),
+                ),
+            );
+        }
+        rusterix.assets.entities.insert(
+            PARTNER_CLASS.into(),
+            (Self::partner_source(), String::new()),
+        );
+
+        // The class under test is wrapped in a driver class which records its
+        // actions and messages and dispatches the test events
+        let driver_name = format!("{}__Test", spec.class);
+        let driver = format!(
+            "{}\n{}",
+            project.resolve_script_imports(source),
+            Self::driver_source(&driver_name, &spec, is_character)
+        );
+
+        let mut map = Map {
+            name: TEST_REGION.into(),
+            ..Default::default()
+        };
+        map.id = Uuid::new_v4();
+
+        let partner_id = Uuid::new_v4();
+        let mut partner = Entity {
+            creator_id: partner_id,
+            ..Default::default()
+        };
+        partner.set_attribute("name", Value::Str("Partner".into()));
+        partner.set_attribute("class_name", Value::Str(PARTNER_CLASS.into()));
+        partner.set_attribute("player", Value::Bool(!is_character));
+        map.entities.push(partner);
+
+        if is_character {
+            rusterix
+                .assets
+                .entities
+                .insert(driver_name.clone(), (driver, data.clone()));
+
+            let mut entity = Entity {
+                creator_id: self.id,
+                ..Default::default()
+            };
+            entity.set_attribute("name", Value::Str(spec.class.clone()));
+            entity.set_attribute("class_name", Value::Str(driver_name));
+            for (key, value) in spec.attributes.iter() {
+                entity.set_attribute(key, toml_to_value(value));
+            }
+            entity.set_attribute("player", Value::Bool(true));
+            map.entities.push(entity);
+        } else {
+            rusterix
+                .assets
+                .items
+                .insert(driver_name.clone(), (driver, data.clone()));
+
+            let mut item = rusterix::Item {
+                creator_id: self.id,
+                ..Default::default()
+            };
+            item.set_attribute("name", Value::Str(spec.class.clone()));
+            item.set_attribute("class_name", Value::Str(driver_name));
+            for (key, value) in spec.attributes.iter() {
+                item.set_attribute(key, toml_to_value(value));
+            }
+            map.items.push(item);
+        }
+
+        rusterix.server.debug_mode = true;
+        rusterix.server.create_region_instance(
+            TEST_REGION.into(),
+            map.clone(),
+            &rusterix.assets,
+            project.config.clone(),
+        );
+        rusterix.server.set_state(rusterix::ServerState::Running);
+
+        // The local player reports step 0 on startup
+        let player_id = if is_character { self.id } else { partner_id };
+        let mut failures = vec![];
+        if !Self::wait_for_step(&mut rusterix, &mut map, player_id, 0) {
+            failures.push("The test region did not start.".into());
+        }
+
+        // Characters receive the events as the local player, one per step
+        let partner = Self::entity_id(&map, partner_id);
+        let mut steps: Vec<Option<(String, String)>> = vec![];
+        if is_character {
+            for event in &spec.events {
+                if let Some(name) = &event.user_event {
+                    steps.push(Some((name.clone(), event.value.clone())));
+                } else if let Some(name) = &event.event {
+                    steps.push(Some((
+                        "__test_event".into(),
+                        format!("{}|{}", name, event.value),
+                    )));
+                } else if let Some(intent) = &event.intent {
+                    steps.push(Some((
+                        "__test_intent".into(),
+                        format!("{}|{}", intent, partner.unwrap_or_default()),
+                    )));
+                }
+            }
+        }
+        steps.extend((0..spec.ticks.max(1)).map(|_| None));
+
+        for (index, step) in steps.into_iter().enumerate() {
+            if !failures.is_empty() {
+                break;
+            }
+            rusterix.server.system_tick();
+            rusterix.server.redraw_tick();
+            if let Some((event, value)) = step {
+                send_user_event(&mut rusterix, event, Value::Str(value));
+            }
+            let step = index as i32 + 1;
+            send_user_event(
+                &mut rusterix,
+                STEP_EVENT.into(),
+                Value::Str(step.to_string()),
+            );
+            if !Self::wait_for_step(&mut rusterix, &mut map, player_id, step) {
+                failures.push(format!("Step {} did not finish.", step));
+            }
+        }
+
+        let log = rusterix.server.get_log();
+        rusterix.server.stop();
+
+        // Check the expectations
+        let instance = if is_character {
+            map.entities
+                .iter()
+                .find(|e| e.creator_id == self.id)
+                .map(|e| (e.id, e.attributes.clone()))
+        } else {
+            map.items
+                .iter()
+                .find(|i| i.creator_id == self.id)
+                .map(|i| (i.id, i.attributes.clone()))
+        };
+
+        let Some((instance_id, attributes)) = instance else {
+            failures.push("The instance was not created.".into());
+            return ScriptTestResult {
+                name: self.name.clone(),
+                passed: false,
+                failures,
+                log,
+            };
+        };
+
+        let actions: Vec<String> = attributes
+            .get_str_default("test_actions", "".into())
+            .split(',')
+            .filter(|a| !a.is_empty())
+            .map(|a| a.to_string())
+            .collect();
+        let messages =
+            SentMessage::parse_all(&attributes.get_str_default("test_messages", "".into()));
+
+        for expect in &spec.expect {
+            if let Some(key) = &expect.attribute {
+                let value = attributes
+                    .get(key)
+                    .map(Self::value_to_string)
+                    .unwrap_or_else(|| "None".into());
+                if value != expect.value {
+                    failures.push(format!(
+                        "Attribute '{}': expected '{}', got '{}'.",
+                        key, expect.value, value
+                    ));
+                }
+            }
+            if let Some(text) = &expect.message {
+                let receiver = match expect.to.as_deref() {
+                    Some("self") => Some(instance_id.to_string()),
+                    Some("partner") => partner.map(|id| id.to_string()),
+                    _ => None,
+                };
+                let sent = messages.iter().any(|m| {
+                    m.text == *text
+                        && expect.category.as_ref().is_none_or(|c| m.category == *c)
+                        && receiver.as_ref().is_none_or(|r| m.receiver == *r)
+                });
+                if !sent {
+                    failures.push(format!(
+                        "Message '{}' was not sent (sent: {}).",
+                        text,
+                        messages
+                            .iter()
+                            .map(|m| format!("'{}' to {} [{}]", m.text, m.receiver, m.category))
+                            .collect::<Vec<_>>()
+                            .join(", ")
+                    ));
+                }
+            }
+            if let Some(action) = &expect.action {
+                if !actions.contains(action) {
+                    failures.push(format!(
+                        "Action '{}' was not taken (taken: {}).",
+                        action,
+                        actions.join(", ")
+                    ));
+                }
+            }
+        }
+
+        ScriptTestResult {
+            name: self.name.clone(),
+            passed: failures.is_empty(),
+            failures,
+            log,
+        }
+    }
+
+    /// The id of the entity created for the given instance, once the server
+    /// reported it.
+    fn entity_id(map: &Map, creator_id: Uuid) -> Option<u32> {
+        map.entities
+            .iter()
+            .find(|e| e.creator_id == creator_id)
+            .map(|e| e.id)
+    }
+
+    /// Polls the server until the local player stored the step in its
+    /// `test_step` attribute. Returns false if it did not in time.
+    fn wait_for_step(rusterix: &mut Rusterix, map: &mut Map, player: Uuid, step: i32) -> bool {
+        for _ in 0..MAX_STEP_POLLS {
+            rusterix.update_server();
+            rusterix.server.apply_entities_items(map);
+            let done = map
+                .entities
+                .iter()
+                .find(|e| e.creator_id == player)
+                .and_then(|e| e.attributes.get("test_step"))
+                .is_some_and(|s| Self::value_to_string(s) == step.to_string());
+            if done {
+                return true;
+            }
+            #[cfg(not(target_arch = "wasm32"))]
+            std::thread::sleep(std::time::Duration::from_millis(1));
+        }
+        false
+    }
+
+    /// The partner character. As the local player of item tests it numbers the
+    /// steps.
+    fn partner_source() -> String {
+        let mut source = String::new();
+        source += &format!("class {}:\n", PARTNER_CLASS);
+        source += "    def event(self, event, value):\n";
+        source += "        if event == \"startup\" and get_attr(\"player\") == True:\n";
+        source += "            set_attr(\"test_step\", 0)\n\n";
+        source += "    def user_event(self, event, value):\n";
+        source += &format!("        if event == \"{}\":\n", STEP_EVENT);
+        source += "            set_attr(\"test_step\", int(value))\n";
+        source
+    }
+
+    /// Generates the driver class. It records the actions and messages of the
+    /// instance. Characters are the local player and receive the test events
+    /// as user events, items dispatch them on startup. Intents are dispatched
+    /// with the value of the "intent" event the server sends, the partner is
+    /// the entity which interacts.
+    fn driver_source(driver_name: &str, spec: &ScriptTestSpec, is_character: bool) -> String {
+        let class = &spec.class;
+        let mut source = String::new();
+        source += "_test_action = action\n";
+        source += "def action(name):\n";
+        source += "    actions = get_attr(\"test_actions\") or \"\"\n";
+        source += "    set_attr(\"test_actions\", actions + name + \",\")\n";
+        source += "    _test_action(name)\n\n";
+        source += "_test_message = message\n";
+        source += "def message(receiver, text, *args):\n";
+        source += "    category = str(args[0]) if args else \"\"\n";
+        source += "    record = str(receiver) + \"\\x1f\" + category + \"\\x1f\" + str(text) + \"\\x1e\"\n";
+        source +=
+            "    set_attr(\"test_messages\", (get_attr(\"test_messages\") or \"\") + record)\n";
+        source += "    _test_message(receiver, text, *args)\n\n";
+        source += "def _test_intent(intent, partner):\n";
+        source += "    return {\"intent\": intent, \"distance\": 1.0, \"item_id\": None, \"entity_id\": partner, \"target_id\": id()}\n\n";
+
+        source += &format!("class {}({}):\n", driver_name, class);
+        source += "    def event(self, event, value):\n";
+        source += &format!("        {}.event(self, event, value)\n", class);
+        source += "        if event == \"startup\":\n";
+        if is_character {
+            source += "            set_attr(\"test_step\", 0)\n";
+        } else {
+            let mut calls = vec![];
+            for event in &spec.events {
+                if let Some(name) = &event.event {
+                    calls.push(format!("self.event({:?}, {:?})", name, event.value));
+                } else if let Some(intent) = &event.intent {
+                    calls.push(format!(
+                        "self.event(\"intent\", _test_intent({:?}, None))",
+                        intent
+                    ));
+                }
+            }
+            if calls.is_empty() {
+                calls.push("pass".into());
+            }
+            for call in calls {
+                source += &format!("            {}\n", call);
+            }
+        }
+
+        if is_character {
+            source += "\n    def user_event(self, event, value):\n";
+            source += &format!("        if event == \"{}\":\n", STEP_EVENT);
+            source += "            set_attr(\"test_step\", int(value))\n";
+            source += "        elif event == \"__test_event\":\n";
+            source += "            name, _, value = str(value).partition(\"|\")\n";
+            source += "            self.event(name, value)\n";
+            source += "        elif event == \"__test_intent\":\n";
+            source += "            intent, _, partner = str(value).partition(\"|\")\n";
+            source += "            self.event(\"intent\", _test_intent(intent, int(partner)))\n";
+            source += &format!("        elif hasattr({}, \"user_event\"):\n", class);
+            source += &format!("            {}.user_event(self, event, value)\n", class);
+        }
+        source
+    }
+
+    /// Converts an attribute value into the string used for comparisons.
+    fn value_to_string(value: &Value) -> String {
+        match value {
+            Value::Int(v) => v.to_string(),
+            Value::Float(v) => v.to_string(),
+            Value::Bool(v) => v.to_string(),
+            Value::Str(v) => v.clone(),
+            _ => format!("{:?}", value),
+        }
+    }
+}
+
+/// Runs all tests of the project.
+pub fn run_script_tests(project: &Project) -> Vec<ScriptTestResult> {
+    project
+        .tests
+        .values()
+        .map(|test| test.run(project))
+        .collect()
+}
+
+/// Formats test results as a readable report.
+pub fn script_test_report(results: &[ScriptTestResult]) -> String {
+    let mut output = String::new();
+    let passed = results.iter().filter(|r| r.passed).count();
+
+    for result in results {
+        if result.passed {
+            output += &format!("PASS  {}\n", result.name);
+        } else {
+            output += &format!("FAIL  {}\n", result.name);
+            for failure in &result.failures {
+                output += &format!("      {}\n", failure);
+            }
+            if !result.log.is_empty() {
+                for line in result.log.lines() {
+                    output += &format!("      | {}\n", line);
+                }
+            }
+        }
+    }
+
+    output += &format!("\n{} of {} tests passed.\n", passed, results.len());
+    output
+}
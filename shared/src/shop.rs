@@ -0,0 +1,973 @@
+use crate::dialogue::value_text;
+use crate::prelude::*;
+use rusterix::{Entity, Value};
+use theframework::prelude::*;
+
+/// The name of the built-in script library with the shop helpers.
+pub const SHOP_LIBRARY: &str = "shop";
+
+/// Set by `open_shop()` on the vendor while it trades, cleared by the vendor
+/// when the customer closes the trade screen.
+pub const SHOP_ACTIVE: &str = "shop_active";
+/// The vendor attribute with the id of the customer, the entity passed to
+/// `open_shop()`.
+pub const SHOP_CUSTOMER: &str = "shop_customer";
+/// The vendor attribute with the stock, one `<item>|<price>|<count>` per line.
+/// A count of -1 is unlimited. Set by the host from the `[shop]` of the vendor
+/// class when the server starts, the vendor counts the stock down.
+pub const SHOP_STOCK: &str = "shop_stock";
+/// The vendor attribute with the factor of the item worth it pays.
+pub const SHOP_SELL_FACTOR: &str = "shop_sell_factor";
+/// The vendor attribute with the comma separated names of the items it buys,
+/// `*` for all items.
+pub const SHOP_BUYS: &str = "shop_buys";
+/// The user event sent to the player for the trades picked on the trade
+/// screen, as `<seq>|buy|<vendor>|<item name>`, `<seq>|sell|<vendor>|<item id>`
+/// or `<seq>|close|<vendor>`. The library passes it on to the vendor.
+pub const SHOP_TRADE: &str = "shop_trade";
+/// The vendor attribute with the result of the last trade, as
+/// `<seq>|<customer>|<outcome>|<item>|<price>|<money>|<wealth change>|<item ids>`.
+/// The customer books the change of its wealth and hands over the items.
+pub const SHOP_RESULT: &str = "shop_result";
+/// The attribute with the money of the player and the vendors in the base
+/// currency, besides the monetary items in their inventory.
+pub const SHOP_WEALTH: &str = "wealth";
+
+/// The source of the built-in `shop` library. The vendor opens the trade
+/// screen, checks the trades of the customer and books them.
+pub const SHOP_SOURCE: &str = r##"# Built-in shop helpers. Vendors define their stock and prices in the [shop]
+# section of their data, open_shop(entity_id) shows the trade screen to the
+# customer, usually the entity_id of an "intent" event. The vendor checks and
+# books the trades the customer picks. Call shop_event() from event() of the
+# vendors and the player and shop_user_event() from user_event() of the
+# player. Money is the wealth attribute plus the worth of the monetary items.
+
+def open_shop(entity_id):
+    if get_attr("shop_active") == True and get_attr("shop_customer") != int(entity_id):
+        return False
+    set_attr("shop_customer", int(entity_id))
+    set_attr("shop_active", True)
+    _shop_poll()
+    return True
+
+def in_shop():
+    return get_attr("shop_active") == True
+
+def shop_money(entity_id):
+    return _shop_number(get_attr_of(entity_id, "wealth")) + sum([c[1] for c in _shop_coins(entity_id)])
+
+def _shop_poll():
+    if not get_attr("shop_polling"):
+        set_attr("shop_polling", True)
+        notify_in(1, "shop_poll")
+
+def _shop_number(value):
+    try:
+        return float(value)
+    except Exception:
+        return 0.0
+
+def _shop_amount(value):
+    return int(value) if value == int(value) else value
+
+def _shop_items(entity_id):
+    return [int(item) for item in inventory_items_of(entity_id, "")]
+
+def _shop_coins(entity_id):
+    coins = []
+    for item in _shop_items(entity_id):
+        if get_attr_of(item, "monetary") == True:
+            coins.append((item, _shop_number(get_attr_of(item, "worth"))))
+    return coins
+
+def _shop_buy(customer, name):
+    stock = [line.split("|") for line in str(get_attr("shop_stock") or "").split("\n") if line]
+    rows = [row for row in stock if len(row) >= 3 and row[0] == name]
+    if not rows or int(rows[0][2]) == 0:
+        return ["sold_out", name, "0", "0", "0", ""]
+    price = _shop_number(rows[0][1])
+    money = shop_money(customer)
+    if money < price:
+        return ["money", name, str(price), str(money), "0", ""]
+    slots = get_attr_of(customer, "inventory_slots")
+    if slots is not None and len(_shop_items(customer)) >= _shop_number(slots):
+        return ["full", name, str(price), str(money), "0", ""]
+    # The wealth pays first, then the coins, the change goes to the wealth
+    paid = min(max(_shop_number(get_attr_of(customer, "wealth")), 0.0), price)
+    coins = []
+    value = 0.0
+    for coin in _shop_coins(customer):
+        if paid + value >= price:
+            break
+        coins.append(str(coin[0]))
+        value += coin[1]
+    if int(rows[0][2]) > 0:
+        rows[0][2] = str(int(rows[0][2]) - 1)
+        set_attr("shop_stock", "\n".join(["|".join(row) for row in stock]))
+    wealth = get_attr("wealth")
+    if wealth is not None:
+        set_attr("wealth", _shop_amount(_shop_number(wealth) + price - value))
+    return ["bought", name, str(price), str(money - price), str(value - price), ",".join(coins)]
+
+def _shop_sell(customer, item):
+    name = str(get_attr_of(item, "name") or "")
+    money = shop_money(customer)
+    if item not in _shop_items(customer):
+        return ["missing", name, "0", str(money), "0", ""]
+    buys = str(get_attr("shop_buys") or "")
+    if get_attr_of(item, "monetary") == True or (buys != "*" and name.lower() not in [b.strip().lower() for b in buys.split(",")]):
+        return ["not_bought", name, "0", str(money), "0", ""]
+    price = float(int(_shop_number(get_attr_of(item, "worth")) * _shop_number(get_attr("shop_sell_factor"))))
+    wealth = get_attr("wealth")
+    if wealth is not None and _shop_number(wealth) < price:
+        return ["vendor_money", name, str(price), str(money), "0", ""]
+    if wealth is not None:
+        set_attr("wealth", _shop_amount(_shop_number(wealth) - price))
+    return ["sold", name, str(price), str(money + price), str(price), str(item)]
+
+def _shop_vendor_poll():
+    customer = get_attr("shop_customer")
+    if get_attr("shop_active") == True and customer is not None:
+        request = str(get_attr_of(customer, "shop_request") or "").split("|")
+        if len(request) >= 3 and int(request[2]) == id() and int(request[0]) > (get_attr("shop_request_seen") or 0):
+            set_attr("shop_request_seen", int(request[0]))
+            if request[1] == "buy" and len(request) >= 4:
+                result = _shop_buy(customer, request[3])
+            elif request[1] == "sell" and len(request) >= 4:
+                result = _shop_sell(customer, int(request[3]))
+            else:
+                set_attr("shop_active", False)
+                result = ["closed", "", "0", "0", "0", ""]
+            set_attr("shop_result", "|".join([request[0], str(customer)] + result))
+            if result[5]:
+                pending = str(get_attr("shop_take") or "")
+                set_attr("shop_take", pending + request[0] + "|" + str(customer) + "|" + result[5] + "\n")
+    # The items handed over are taken once the customer dropped them
+    taken = False
+    pending = []
+    for line in str(get_attr("shop_take") or "").split("\n"):
+        parts = line.split("|")
+        if len(parts) < 3:
+            continue
+        if (get_attr_of(int(parts[1]), "shop_done") or 0) >= int(parts[0]):
+            taken = True
+            for item in parts[2].split(","):
+                take(int(item))
+        else:
+            pending.append(line + "\n")
+    if taken:
+        set_attr("shop_take", "".join(pending))
+    return get_attr("shop_active") == True or len(pending) > 0
+
+def _shop_customer_poll():
+    vendor = get_attr("shop_vendor")
+    if vendor is None:
+        return False
+    seen = get_attr("shop_seen") or 0
+    result = str(get_attr_of(vendor, "shop_result") or "").split("|")
+    if len(result) >= 8 and int(result[1]) == id() and int(result[0]) > seen:
+        seen = int(result[0])
+        set_attr("shop_seen", seen)
+        if result[2] == "bought" or result[2] == "sold":
+            set_attr("wealth", _shop_amount(_shop_number(get_attr("wealth")) + _shop_number(result[6])))
+            for item in result[7].split(","):
+                if item:
+                    drop(int(item))
+            if result[2] == "bought":
+                add_item(result[3])
+        set_attr("shop_done", seen)
+    return seen < (get_attr("shop_sent") or 0)
+
+def shop_event(event, value):
+    if event != "shop_poll":
+        return False
+    set_attr("shop_polling", False)
+    vendor = _shop_vendor_poll()
+    customer = _shop_customer_poll()
+    if vendor or customer:
+        _shop_poll()
+    return True
+
+def shop_user_event(event, value):
+    if event != "shop_trade":
+        return False
+    request = str(value).split("|")
+    if len(request) >= 3:
+        set_attr("shop_vendor", int(request[2]))
+        set_attr("shop_sent", int(request[0]))
+        set_attr("shop_request", str(value))
+        _shop_poll()
+    return True
+"##;
+
+/// The most rows of a trade column, longer lists scroll with the selection.
+const MAX_ROWS: usize = 10;
+
+/// An item the vendor sells.
+#[derive(Deserialize, Clone, Debug)]
+pub struct ShopStock {
+    /// The name of the item class.
+    pub item: String,
+    /// How many the vendor has, unlimited if not set.
+    #[serde(default)]
+    pub count: Option<i32>,
+    /// The price before the buy factor, the worth of the item if not set.
+    #[serde(default)]
+    pub price: Option<f32>,
+}
+
+/// The `[shop]` section of the data of a vendor class.
+#[derive(Deserialize, Clone, Debug, Default)]
+pub struct ShopDefinition {
+    #[serde(default)]
+    pub stock: Vec<ShopStock>,
+    /// The factor of the price the player pays.
+    #[serde(default)]
+    pub buy_factor: Option<f32>,
+    /// The factor of the item worth the player receives.
+    #[serde(default)]
+    pub sell_factor: Option<f32>,
+    /// The names of the items the vendor buys, all items if not set. An empty
+    /// list buys nothing.
+    #[serde(default)]
+    pub buys: Option<Vec<String>>,
+}
+
+impl ShopDefinition {
+    /// The shop of the character data, None if the data has no `[shop]`.
+    pub fn from_data(data: &str) -> Option<Self> {
+        let table = data.parse::<toml::Table>().ok()?;
+        let shop = table.get("shop")?.clone();
+        match shop.try_into::<ShopDefinition>() {
+            Ok(shop) => Some(shop),
+            Err(err) => {
+                eprintln!("Invalid [shop]: {}", err);
+                None
+            }
+        }
+    }
+}
+
+/// The `[shop]` section of the game config: the default price factors and the
+/// look of the trade screen.
+#[derive(Deserialize, Clone, Debug)]
+#[serde(default)]
+struct ShopConfig {
+    buy_factor: f32,
+    sell_factor: f32,
+    font_size: f32,
+    background: String,
+    selected: String,
+    text: String,
+    title: String,
+}
+
+impl Default for ShopConfig {
+    fn default() -> Self {
+        Self {
+            buy_factor: 1.0,
+            sell_factor: 0.5,
+            font_size: 14.0,
+            background: "#141418".into(),
+            selected: "#3c4664".into(),
+            text: "#dcdcdc".into(),
+            title: "#f0c878".into(),
+        }
+    }
+}
+
+impl ShopConfig {
+    fn from_table(table: &toml::Table) -> Self {
+        table
+            .get("shop")
+            .and_then(|s| s.clone().try_into::<ShopConfig>().ok())
+            .unwrap_or_default()
+    }
+}
+
+/// Sets the stock, the sell factor and the bought items of the `[shop]` of
+/// their class on the vendors, called when the server starts. The prices of
+/// the stock include the buy factor.
+pub fn insert_shops(project: &mut Project) {
+    let table = project.config.parse::<toml::Table>().unwrap_or_default();
+    let config = ShopConfig::from_table(&table);
+
+    let mut shops: FxHashMap<String, Vec<(String, Value)>> = FxHashMap::default();
+    for character in project.characters.values() {
+        let Some(shop) = ShopDefinition::from_data(&character.data) else {
+            continue;
+        };
+        let factor = shop.buy_factor.unwrap_or(config.buy_factor).max(0.0);
+        let stock: Vec<String> = shop
+            .stock
+            .iter()
+            .map(|stock| {
+                let worth = stock.price.unwrap_or_else(|| {
+                    project
+                        .items
+                        .values()
+                        .find(|i| i.name == stock.item)
+                        .map(|i| item_worth(&i.data))
+                        .unwrap_or(0.0)
+                });
+                format!(
+                    "{}|{}|{}",
+                    stock.item,
+                    (worth * factor).ceil(),
+                    stock.count.map(|c| c.max(0)).unwrap_or(-1)
+                )
+            })
+            .collect();
+        let buys = match &shop.buys {
+            Some(buys) => buys.join(","),
+            None => "*".into(),
+        };
+        shops.insert(
+            character.name.clone(),
+            vec![
+                (SHOP_STOCK.into(), Value::Str(stock.join("\n"))),
+                (
+                    SHOP_SELL_FACTOR.into(),
+                    Value::Float(shop.sell_factor.unwrap_or(config.sell_factor).max(0.0)),
+                ),
+                (SHOP_BUYS.into(), Value::Str(buys)),
+            ],
+        );
+    }
+
+    for region in &mut project.regions {
+        for entity in &mut region.map.entities {
+            let class = entity
+                .attributes
+                .get_str_default("class_name", String::new());
+            for (key, value) in shops.get(&class).into_iter().flatten() {
+                entity.set_attribute(key, value.clone());
+            }
+        }
+    }
+}
+
+/// The side of the trade screen.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum TradeColumn {
+    /// The stock of the vendor.
+    Buy,
+    /// The inventory of the player.
+    Sell,
+}
+
+/// A row of the trade screen.
+#[derive(Clone, Debug, PartialEq)]
+pub struct TradeRow {
+    pub name: String,
+    pub price: f32,
+    /// The remaining stock, None if unlimited.
+    pub count: Option<i32>,
+    /// The item id for the items of the player.
+    pub item: Option<u32>,
+}
+
+/// The result of a trade as written by the vendor, see `SHOP_RESULT`.
+#[derive(Clone, Debug, PartialEq)]
+struct TradeResult {
+    seq: u64,
+    outcome: String,
+    item: String,
+    price: f32,
+    money: f32,
+}
+
+impl TradeResult {
+    /// The last result of the vendor for the customer.
+    fn of(vendor: &Entity, customer: u32) -> Option<Self> {
+        let result = vendor.attributes.get(SHOP_RESULT).map(value_text)?;
+        let parts: Vec<&str> = result.split('|').collect();
+        if parts.len() < 8 || parts[1].trim().parse::<u32>().ok()? != customer {
+            return None;
+        }
+        Some(Self {
+            seq: parts[0].trim().parse().ok()?,
+            outcome: parts[2].to_string(),
+            item: parts[3].to_string(),
+            price: parts[4].trim().parse().unwrap_or(0.0),
+            money: parts[5].trim().parse().unwrap_or(0.0),
+        })
+    }
+}
+
+/// A running trade.
+#[derive(Clone, Debug)]
+struct TradeSession {
+    region: Uuid,
+    vendor: u32,
+    vendor_name: String,
+    player: u32,
+    column: TradeColumn,
+    selected: usize,
+    /// The request on its way to the vendor, no other trade is sent until the
+    /// vendor answered it.
+    pending: Option<u64>,
+    /// The first request of the session, older results are not shown.
+    first: u64,
+    /// Set when the player closed the screen, the vendor ends the trade.
+    closed: bool,
+    stock: Vec<TradeRow>,
+    items: Vec<TradeRow>,
+    money: f32,
+    /// The result of the last transaction.
+    status: String,
+}
+
+/// Shows the trade screens of the vendors. The vendor script is the authority
+/// for the transactions: the screen shows the stock and prices published by
+/// the vendor and sends the picked trades as `shop_trade` user events, which
+/// are recorded with the player input. The vendor checks the stock, the money
+/// and the free inventory slots of the customer and both scripts book the
+/// wealth and hand over the items.
+#[derive(Default)]
+pub struct ShopRunner {
+    session: Option<TradeSession>,
+    config: ShopConfig,
+    currency: String,
+    /// The sequence number of the last trade request.
+    requests: u64,
+    inputs: Vec<ReplayInput>,
+}
+
+impl ShopRunner {
+    /// Closes the trade and reads the shop config, called when the server
+    /// starts.
+    pub fn start(&mut self, config: &str) {
+        let table = config.parse::<toml::Table>().unwrap_or_default();
+        let game = table.get("game");
+        *self = Self {
+            config: ShopConfig::from_table(&table),
+            currency: game
+                .and_then(|g| g.get("base_currency_symbol"))
+                .or_else(|| game.and_then(|g| g.get("base_currency_name")))
+                .and_then(|s| s.as_str())
+                .unwrap_or("G")
+                .to_string(),
+            ..Default::default()
+        };
+    }
+
+    pub fn is_active(&self) -> bool {
+        self.session.as_ref().is_some_and(|s| !s.closed)
+    }
+
+    /// Shows the trade screen of the vendor trading with the player of the
+    /// region and reads the stock, the offers and the result of the last
+    /// trade.
+    pub fn update(&mut self, region: &Region) {
+        let entities = &region.map.entities;
+        let Some(player) = entities
+            .iter()
+            .find(|e| matches!(e.attributes.get("player"), Some(Value::Bool(true))))
+        else {
+            if self.session.as_ref().is_some_and(|s| s.region == region.id) {
+                self.session = None;
+            }
+            return;
+        };
+        let trading = |vendor: &Entity| {
+            matches!(vendor.attributes.get(SHOP_ACTIVE), Some(Value::Bool(true)))
+                && vendor
+                    .attributes
+                    .get(SHOP_CUSTOMER)
+                    .map(value_text)
+                    .and_then(|c| c.trim().parse::<u32>().ok())
+                    == Some(player.id)
+        };
+
+        if self.session.is_none()
+            && let Some(vendor) = entities.iter().find(|e| trading(e))
+        {
+            self.session = Some(TradeSession {
+                region: region.id,
+                vendor: vendor.id,
+                vendor_name: vendor.attributes.get_str_default("name", String::new()),
+                player: player.id,
+                column: TradeColumn::Buy,
+                selected: 0,
+                pending: None,
+                first: self.requests + 1,
+                closed: false,
+                stock: vec![],
+                items: vec![],
+                money: 0.0,
+                status: String::new(),
+            });
+        }
+
+        if !self.session.as_ref().is_some_and(|s| s.region == region.id) {
+            return;
+        }
+        let Some(mut session) = self.session.take() else {
+            return;
+        };
+        let Some(vendor) = entities
+            .iter()
+            .find(|e| e.id == session.vendor)
+            .filter(|v| session.player == player.id && trading(v))
+        else {
+            return;
+        };
+
+        if let Some(result) = TradeResult::of(vendor, player.id)
+            && result.seq >= session.first
+        {
+            if session.pending.is_some_and(|p| result.seq >= p) {
+                session.pending = None;
+            }
+            session.status = self.status_text(&session, &result);
+        }
+        session.stock = stock_rows(vendor);
+        session.items = offer_rows(vendor, player);
+        session.money = money(player);
+        let rows = match session.column {
+            TradeColumn::Buy => session.stock.len(),
+            TradeColumn::Sell => session.items.len(),
+        };
+        session.selected = session.selected.min(rows.saturating_sub(1));
+        self.session = Some(session);
+    }
+
+    /// The trade requests since the last call, to be sent and recorded as
+    /// player input.
+    pub fn take_inputs(&mut self) -> Vec<ReplayInput> {
+        std::mem::take(&mut self.inputs)
+    }
+
+    /// Sends the trade of the row to the vendor.
+    fn trade(&mut self, column: TradeColumn, index: usize) {
+        let Some(session) = &mut self.session else {
+            return;
+        };
+        if session.pending.is_some() {
+            return;
+        }
+        let request = match column {
+            TradeColumn::Buy => session
+                .stock
+                .get(index)
+                .map(|row| format!("buy|{}|{}", session.vendor, row.name)),
+            TradeColumn::Sell => session
+                .items
+                .get(index)
+                .and_then(|row| row.item)
+                .map(|item| format!("sell|{}|{}", session.vendor, item)),
+        };
+        let Some(request) = request else {
+            return;
+        };
+        self.requests += 1;
+        session.pending = Some(self.requests);
+        self.inputs.push(ReplayInput::UserEvent(
+            SHOP_TRADE.into(),
+            format!("{}|{}", self.requests, request),
+        ));
+    }
+
+    /// Hides the trade screen and asks the vendor to end the trade.
+    fn close(&mut self) {
+        let Some(session) = &mut self.session else {
+            return;
+        };
+        session.closed = true;
+        self.requests += 1;
+        self.inputs.push(ReplayInput::UserEvent(
+            SHOP_TRADE.into(),
+            format!("{}|close|{}", self.requests, session.vendor),
+        ));
+    }
+
+    /// The status line for the result of a trade.
+    fn status_text(&self, session: &TradeSession, result: &TradeResult) -> String {
+        let item = &result.item;
+        match result.outcome.as_str() {
+            "bought" => format!("Bought {} for {}.", item, self.price_text(result.price)),
+            "sold" => format!("Sold {} for {}.", item, self.price_text(result.price)),
+            "sold_out" => format!("{} is sold out.", item),
+            "money" => format!(
+                "{} costs {}, you have {}.",
+                item,
+                self.price_text(result.price),
+                self.price_text(result.money)
+            ),
+            "full" => "Your inventory is full.".into(),
+            "missing" | "not_bought" => format!("{} can't be sold here.", item),
+            "vendor_money" => format!("{} can't afford {}.", session.vendor_name, item),
+            _ => String::new(),
+        }
+    }
+
+    /// Handles a pressed key or button while the trade screen is shown. Up /
+    /// down select, left / right or tab switch between buying and selling,
+    /// return, space, the south button or the interact action trade the
+    /// selected item. Escape or the east button close the screen. Returns
+    /// true if consumed.
+    pub fn press(&mut self, map: &InputMap, source: &InputSource) -> bool {
+        let Some(session) = self.session.as_mut().filter(|s| !s.closed) else {
+            return false;
+        };
+        let name = match source {
+            InputSource::Key(key) => key.as_str(),
+            InputSource::Gamepad(button) => button.as_str(),
+        };
+        let count = match session.column {
+            TradeColumn::Buy => session.stock.len(),
+            TradeColumn::Sell => session.items.len(),
+        };
+        match name {
+            "up" | "dpad_up" | "left_stick_up" if count > 0 => {
+                session.selected = (session.selected + count - 1) % count;
+            }
+            "down" | "dpad_down" | "left_stick_down" if count > 0 => {
+                session.selected = (session.selected + 1) % count;
+            }
+            "left" | "right" | "tab" | "dpad_left" | "dpad_right" | "left_stick_left"
+            | "left_stick_right" | "left_shoulder" | "right_shoulder" => {
+                session.column = match session.column {
+                    TradeColumn::Buy => TradeColumn::Sell,
+                    TradeColumn::Sell => TradeColumn::Buy,
+                };
+                session.selected = 0;
+            }
+            "escape" | "east" => self.close(),
+            _ => {
+                if (matches!(name, "return" | "space" | "south")
+                    || map.actions_for(source).iter().any(|a| a == "interact"))
+                    && count > 0
+                {
+                    let (column, selected) = (session.column, session.selected);
+                    self.trade(column, selected);
+                }
+            }
+        }
+        true
+    }
+
+    /// Selects the row at the given screen coordinate, a click on the selected
+    /// row trades it. Returns true if the click was inside the trade screen.
+    pub fn click(&mut self, coord: Vec2<i32>, width: usize, height: usize) -> bool {
+        let Some(session) = self.session.as_mut().filter(|s| !s.closed) else {
+            return false;
+        };
+        let layout = TradeLayout::new(width, height, self.config.font_size);
+        if !layout.contains(coord) {
+            return false;
+        }
+        if let Some((column, index)) = layout.row_at(coord, session) {
+            if column == session.column && index == session.selected {
+                self.trade(column, index);
+            } else {
+                session.column = column;
+                session.selected = index;
+            }
+        }
+        true
+    }
+
+    /// Draws the trade screen in the center of the game view.
+    pub fn draw(&self, buffer: &mut TheRGBABuffer, ctx: &mut TheContext) {
+        let Some(session) = self.session.as_ref().filter(|s| !s.closed) else {
+            return;
+        };
+        let width = buffer.dim().width as usize;
+        let height = buffer.dim().height as usize;
+        let stride = buffer.stride();
+        let layout = TradeLayout::new(width, height, self.config.font_size);
+        let (x, y, w, h) = layout.rect;
+
+        let bg_color = TheColor::from_hex(&self.config.background).to_u8_array();
+        let selected_color = TheColor::from_hex(&self.config.selected).to_u8_array();
+        let text_color = TheColor::from_hex(&self.config.text).to_u8_array();
+        let title_color = TheColor::from_hex(&self.config.title).to_u8_array();
+        let font = TheFontSettings {
+            size: self.config.font_size,
+            ..Default::default()
+        };
+
+        ctx.draw
+            .rect(buffer.pixels_mut(), &(x, y, w, h), stride, &bg_color);
+
+        let row_h = layout.row_height;
+        let inner = w - 2 * TradeLayout::MARGIN;
+        ctx.draw.text_rect(
+            buffer.pixels_mut(),
+            &(
+                x + TradeLayout::MARGIN,
+                y + TradeLayout::MARGIN,
+                inner,
+                row_h,
+            ),
+            stride,
+            &format!(
+                "{} - Your money: {}",
+                session.vendor_name,
+                self.price_text(session.money)
+            ),
+            font.clone(),
+            &title_color,
+            &bg_color,
+        );
+
+        for (column, rows, header) in [
+            (TradeColumn::Buy, &session.stock, "Buy"),
+            (TradeColumn::Sell, &session.items, "Sell"),
+        ] {
+            let (cx, cy, cw) = layout.column_rect(column);
+            let header_color = if session.column == column {
+                title_color
+            } else {
+                text_color
+            };
+            ctx.draw.text_rect(
+                buffer.pixels_mut(),
+                &(cx, cy, cw, row_h),
+                stride,
+                header,
+                font.clone(),
+                &header_color,
+                &bg_color,
+            );
+            if rows.is_empty() {
+                ctx.draw.text_rect(
+                    buffer.pixels_mut(),
+                    &(cx + 4, cy + row_h, cw.saturating_sub(8), row_h),
+                    stride,
+                    "-",
+                    font.clone(),
+                    &text_color,
+                    &bg_color,
+                );
+            }
+
+            let first = layout.first_row(session, column);
+            for (slot, (index, row)) in rows
+                .iter()
+                .enumerate()
+                .skip(first)
+                .take(MAX_ROWS)
+                .enumerate()
+            {
+                let ry = cy + (slot + 1) * row_h;
+                let row_color = if session.column == column && session.selected == index {
+                    ctx.draw.rect(
+                        buffer.pixels_mut(),
+                        &(cx, ry, cw, row_h),
+                        stride,
+                        &selected_color,
+                    );
+                    selected_color
+                } else {
+                    bg_color
+                };
+                let label = match row.count {
+                    Some(count) => format!("{} ({})", row.name, count.max(0)),
+                    None => row.name.clone(),
+                };
+                // Roughly half the font size per character
+                let price = self.price_text(row.price);
+                let price_w =
+                    (price.chars().count() + 1) * (self.config.font_size as usize / 2 + 1);
+                ctx.draw.text_rect(
+                    buffer.pixels_mut(),
+                    &(cx + 4, ry, cw.saturating_sub(price_w + 8), row_h),
+                    stride,
+                    &label,
+                    font.clone(),
+                    &text_color,
+                    &row_color,
+                );
+                ctx.draw.text_rect(
+                    buffer.pixels_mut(),
+                    &(cx + cw.saturating_sub(price_w + 4), ry, price_w, row_h),
+                    stride,
+                    &price,
+                    font.clone(),
+                    &text_color,
+                    &row_color,
+                );
+            }
+        }
+
+        if !session.status.is_empty() {
+            ctx.draw.text_rect(
+                buffer.pixels_mut(),
+                &(
+                    x + TradeLayout::MARGIN,
+                    y + h - TradeLayout::MARGIN - row_h,
+                    inner,
+                    row_h,
+                ),
+                stride,
+                &session.status,
+                font.clone(),
+                &title_color,
+                &bg_color,
+            );
+        }
+    }
+
+    /// The price with the symbol of the base currency.
+    fn price_text(&self, price: f32) -> String {
+        if price.fract() == 0.0 {
+            format!("{} {}", price, self.currency)
+        } else {
+            format!("{:.2} {}", price, self.currency)
+        }
+    }
+}
+
+/// The placement of the trade screen, its two columns and their rows.
+struct TradeLayout {
+    rect: (usize, usize, usize, usize),
+    row_height: usize,
+}
+
+impl TradeLayout {
+    const MARGIN: usize = 10;
+
+    fn new(width: usize, height: usize, font_size: f32) -> Self {
+        let row_height = (font_size * 1.6).max(12.0) as usize;
+        // Title, column headers, the rows and the status line
+        let h = ((MAX_ROWS + 3) * row_height + 2 * Self::MARGIN).min(height);
+        let w = if width < 400 {
+            width
+        } else {
+            (width * 3 / 4).min(720)
+        };
+        Self {
+            rect: ((width - w) / 2, (height - h) / 2, w, h),
+            row_height,
+        }
+    }
+
+    fn contains(&self, coord: Vec2<i32>) -> bool {
+        let (x, y, w, h) = self.rect;
+        coord.x >= x as i32
+            && coord.y >= y as i32
+            && coord.x < (x + w) as i32
+            && coord.y < (y + h) as i32
+    }
+
+    /// The left edge, top and width of a column.
+    fn column_rect(&self, column: TradeColumn) -> (usize, usize, usize) {
+        let (x, y, w, _) = self.rect;
+        let cw = (w - 3 * Self::MARGIN) / 2;
+        let cx = match column {
+            TradeColumn::Buy => x + Self::MARGIN,
+            TradeColumn::Sell => x + 2 * Self::MARGIN + cw,
+        };
+        (cx, y + Self::MARGIN + self.row_height, cw)
+    }
+
+    /// The first shown row of a column, keeps the selection visible.
+    fn first_row(&self, session: &TradeSession, column: TradeColumn) -> usize {
+        if session.column == column {
+            session.selected.saturating_sub(MAX_ROWS - 1)
+        } else {
+            0
+        }
+    }
+
+    fn row_at(&self, coord: Vec2<i32>, session: &TradeSession) -> Option<(TradeColumn, usize)> {
+        for (column, rows) in [
+            (TradeColumn::Buy, session.stock.len()),
+            (TradeColumn::Sell, session.items.len()),
+        ] {
+            let (cx, cy, cw) = self.column_rect(column);
+            if coord.x < cx as i32 || coord.x >= (cx + cw) as i32 || coord.y < cy as i32 {
+                continue;
+            }
+            let slot = (coord.y as usize - cy) / self.row_height;
+            if slot == 0 || slot > MAX_ROWS {
+                return None;
+            }
+            let index = self.first_row(session, column) + slot - 1;
+            return (index < rows).then_some((column, index));
+        }
+        None
+    }
+}
+
+/// The stock of the vendor with the prices and the remaining counts.
+fn stock_rows(vendor: &Entity) -> Vec<TradeRow> {
+    vendor
+        .attributes
+        .get_str_default(SHOP_STOCK, String::new())
+        .lines()
+        .filter_map(|line| {
+            let mut parts = line.split('|');
+            let name = parts.next()?.to_string();
+            let price = parts.next()?.trim().parse::<f32>().ok()?;
+            let count = parts.next()?.trim().parse::<i32>().ok()?;
+            Some(TradeRow {
+                name,
+                price,
+                count: (count >= 0).then_some(count),
+                item: None,
+            })
+        })
+        .collect()
+}
+
+/// The items of the player the vendor buys, with the price it pays.
+fn offer_rows(vendor: &Entity, player: &Entity) -> Vec<TradeRow> {
+    let buys = vendor.attributes.get_str_default(SHOP_BUYS, String::new());
+    let factor = number(vendor.attributes.get(SHOP_SELL_FACTOR)).max(0.0);
+    player
+        .iter_inventory()
+        .filter_map(|(_, item)| {
+            let name = item.attributes.get_str("name")?.to_string();
+            let bought = buys == "*"
+                || buys
+                    .split(',')
+                    .any(|b| b.trim().eq_ignore_ascii_case(&name));
+            if monetary(item) || !bought {
+                return None;
+            }
+            Some(TradeRow {
+                name,
+                price: (number(item.attributes.get("worth")) * factor).floor(),
+                count: None,
+                item: Some(item.id),
+            })
+        })
+        .collect()
+}
+
+/// The money of the entity: its wealth and the worth of its monetary items.
+fn money(entity: &Entity) -> f32 {
+    number(entity.attributes.get(SHOP_WEALTH))
+        + entity
+            .iter_inventory()
+            .filter(|(_, item)| monetary(item))
+            .map(|(_, item)| number(item.attributes.get("worth")))
+            .sum::<f32>()
+}
+
+fn monetary(item: &rusterix::Item) -> bool {
+    matches!(item.attributes.get("monetary"), Some(Value::Bool(true)))
+}
+
+fn number(value: Option<&Value>) -> f32 {
+    value
+        .map(value_text)
+        .and_then(|v| v.trim().parse::<f32>().ok())
+        .unwrap_or(0.0)
+}
+
+/// The `worth` attribute of the item class data.
+fn item_worth(data: &str) -> f32 {
+    data.parse::<toml::Table>()
+        .ok()
+        .and_then(|t| {
+            t.get("attributes")
+                .and_then(|a| a.get("worth"))
+                .and_then(|w| w.as_float().or_else(|| w.as_integer().map(|i| i as f64)))
+        })
+        .unwrap_or(0.0) as f32
+}
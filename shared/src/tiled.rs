@@ -0,0 +1,789 @@
+use crate::levelimport::*;
+use crate::prelude::*;
+use crate::rusterix_utils::value_to_toml;
+use base64::{Engine, engine::general_purpose::STANDARD};
+use quick_xml::Reader;
+use quick_xml::events::{BytesStart, Event};
+use rusterix::{PixelSource, Value};
+use std::io::Read;
+use std::path::Path;
+use theframework::prelude::*;
+
+/// Tiled stores the flip and rotation flags in the upper bits of a gid.
+const GID_MASK: u32 = 0x0FFF_FFFF;
+
+/// A minimal XML element tree.
+#[derive(Default, Debug)]
+pub(crate) struct XmlNode {
+    pub(crate) name: String,
+    attributes: Vec<(String, String)>,
+    pub(crate) children: Vec<XmlNode>,
+    pub(crate) text: String,
+}
+
+impl XmlNode {
+    pub(crate) fn parse(xml: &str) -> Result<Self, String> {
+        fn open(e: &BytesStart) -> XmlNode {
+            XmlNode {
+                name: String::from_utf8_lossy(e.name().as_ref()).to_string(),
+                attributes: e
+                    .attributes()
+                    .flatten()
+                    .map(|a| {
+                        (
+                            String::from_utf8_lossy(a.key.as_ref()).to_string(),
+                            a.unescape_value()
+                                .map(|v| v.to_string())
+                                .unwrap_or_default(),
+                        )
+                    })
+                    .collect(),
+                ..Default::default()
+            }
+        }
+
+        let mut reader = Reader::from_str(xml);
+        reader.config_mut().trim_text(true);
+
+        let mut stack: Vec<XmlNode> = vec![XmlNode::default()];
+        loop {
+            match reader.read_event() {
+                Ok(Event::Start(e)) => stack.push(open(&e)),
+                Ok(Event::Empty(e)) => {
+                    let node = open(&e);
+                    if let Some(parent) = stack.last_mut() {
+                        parent.children.push(node);
+                    }
+                }
+                Ok(Event::Text(e)) => {
+                    if let (Some(node), Ok(text)) = (stack.last_mut(), e.unescape()) {
+                        node.text.push_str(&text);
+                    }
+                }
+                Ok(Event::CData(e)) => {
+                    if let Some(node) = stack.last_mut() {
+                        node.text
+                            .push_str(&String::from_utf8_lossy(&e.into_inner()));
+                    }
+                }
+                Ok(Event::End(_)) => {
+                    if stack.len() > 1
+                        && let Some(node) = stack.pop()
+                        && let Some(parent) = stack.last_mut()
+                    {
+                        parent.children.push(node);
+                    }
+                }
+                Ok(Event::Eof) => break,
+                Err(err) => {
+                    return Err(format!(
+                        "XML error at position {}: {}",
+                        reader.error_position(),
+                        err
+                    ));
+                }
+                _ => {}
+            }
+        }
+
+        stack
+            .into_iter()
+            .next()
+            .and_then(|root| root.children.into_iter().next())
+            .ok_or("The file contains no XML element.".to_string())
+    }
+
+    pub(crate) fn attr(&self, name: &str) -> Option<&str> {
+        self.attributes
+            .iter()
+            .find(|(k, _)| k == name)
+            .map(|(_, v)| v.as_str())
+    }
+
+    fn attr_i32(&self, name: &str, default: i32) -> i32 {
+        self.attr(name)
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(default)
+    }
+
+    fn attr_f32(&self, name: &str, default: f32) -> f32 {
+        self.attr(name)
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(default)
+    }
+
+    pub(crate) fn child(&self, name: &str) -> Option<&XmlNode> {
+        self.children.iter().find(|c| c.name == name)
+    }
+
+    fn children_named<'a>(&'a self, name: &'a str) -> impl Iterator<Item = &'a XmlNode> {
+        self.children.iter().filter(move |c| c.name == name)
+    }
+}
+
+/// Imports a Tiled map (`.tmx`) or a standalone Tiled tileset (`.tsx`).
+/// Only orthogonal maps with tilesets based on a single image are supported.
+pub fn import_tiled(path: &Path) -> Result<ImportedProject, String> {
+    let xml = std::fs::read_to_string(path).map_err(|e| e.to_string())?;
+    let root = XmlNode::parse(&xml)?;
+
+    let mut import = ImportedProject::default();
+    match root.name.as_str() {
+        "tileset" => {
+            if let Some(tileset) = read_tileset(&root, path, &mut import.warnings) {
+                import.tilesets.push(tileset);
+            }
+        }
+        "map" => {
+            let orientation = root.attr("orientation").unwrap_or("orthogonal");
+            if orientation != "orthogonal" {
+                return Err(format!(
+                    "Only orthogonal maps are supported, this map is {}.",
+                    orientation
+                ));
+            }
+
+            // The first gid of every imported tileset
+            let mut first_gids: Vec<(u32, usize)> = vec![];
+            for node in root.children_named("tileset") {
+                let first_gid = node.attr_i32("firstgid", 1) as u32;
+                let tileset = match node.attr("source") {
+                    Some(source) => {
+                        let tsx_path = resolve_relative_path(path, source);
+                        std::fs::read_to_string(&tsx_path)
+                            .map_err(|e| e.to_string())
+                            .and_then(|xml| XmlNode::parse(&xml))
+                            .map(|tsx| read_tileset(&tsx, &tsx_path, &mut import.warnings))
+                            .unwrap_or_else(|err| {
+                                import
+                                    .warnings
+                                    .push(format!("Unable to read tileset '{}': {}", source, err));
+                                None
+                            })
+                    }
+                    None => read_tileset(node, path, &mut import.warnings),
+                };
+                if let Some(tileset) = tileset {
+                    first_gids.push((first_gid, import.tilesets.len()));
+                    import.tilesets.push(tileset);
+                }
+            }
+            first_gids.sort_by_key(|(gid, _)| std::cmp::Reverse(*gid));
+
+            let mut level = ImportedLevel {
+                name: path
+                    .file_stem()
+                    .map(|s| s.to_string_lossy().to_string())
+                    .unwrap_or("Tiled Map".into()),
+                properties: read_properties(&root),
+                ..Default::default()
+            };
+            let tile_size = Vec2::new(
+                root.attr_f32("tilewidth", 16.0).max(1.0),
+                root.attr_f32("tileheight", 16.0).max(1.0),
+            );
+            read_layers(
+                &root,
+                Vec2::zero(),
+                tile_size,
+                &first_gids,
+                &mut level,
+                &mut import.warnings,
+            )?;
+            import.levels.push(level);
+        }
+        other => return Err(format!("Unknown Tiled file type '{}'.", other)),
+    }
+
+    Ok(import)
+}
+
+/// Reads a tileset, embedded in a map or the root of a tsx file.
+fn read_tileset(
+    node: &XmlNode,
+    path: &Path,
+    warnings: &mut Vec<String>,
+) -> Option<ImportedTileset> {
+    let name = node.attr("name").unwrap_or("Tileset").to_string();
+    let Some(image) = node.child("image").and_then(|i| i.attr("source")) else {
+        warnings.push(format!(
+            "Tileset '{}' is an image collection, only tilesets based on a single image are supported.",
+            name
+        ));
+        return None;
+    };
+
+    let mut tileset = ImportedTileset {
+        name,
+        image: resolve_relative_path(path, image),
+        tile_width: node.attr_i32("tilewidth", 16),
+        tile_height: node.attr_i32("tileheight", 16),
+        margin: node.attr_i32("margin", 0),
+        spacing: node.attr_i32("spacing", 0),
+        columns: node.attr_i32("columns", 0),
+        tiles: IndexMap::default(),
+    };
+
+    for tile in node.children_named("tile") {
+        let id = tile.attr_i32("id", 0) as u32;
+        let properties = read_properties(tile);
+
+        let mut tags = vec![];
+        if let Some(class) = tile.attr("class").or(tile.attr("type"))
+            && !class.is_empty()
+        {
+            tags.push(class.to_string());
+        }
+        if let Some(toml::Value::String(t)) = properties.get("tags") {
+            tags.push(t.clone());
+        }
+
+        let frames: Vec<u32> = tile
+            .child("animation")
+            .map(|a| {
+                a.children_named("frame")
+                    .map(|f| f.attr_i32("tileid", 0) as u32)
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        tileset.tiles.insert(
+            id,
+            ImportedTileInfo {
+                tags: tags.join(","),
+                blocking: properties.get("blocking").and_then(|v| v.as_bool()) == Some(true),
+                frames,
+            },
+        );
+    }
+
+    Some(tileset)
+}
+
+/// Reads the tile and object layers, recursing into layer groups.
+fn read_layers(
+    node: &XmlNode,
+    offset: Vec2<f32>,
+    tile_size: Vec2<f32>,
+    first_gids: &[(u32, usize)],
+    level: &mut ImportedLevel,
+    warnings: &mut Vec<String>,
+) -> Result<(), String> {
+    for child in &node.children {
+        let offset = offset
+            + Vec2::new(
+                child.attr_f32("offsetx", 0.0),
+                child.attr_f32("offsety", 0.0),
+            );
+        match child.name.as_str() {
+            "group" => read_layers(child, offset, tile_size, first_gids, level, warnings)?,
+            "layer" => {
+                let Some(data) = child.child("data") else {
+                    continue;
+                };
+                let cell_offset = Vec2::new(
+                    (offset.x / tile_size.x).round() as i32,
+                    (offset.y / tile_size.y).round() as i32,
+                );
+                let mut layer = ImportedLayer {
+                    name: child.attr("name").unwrap_or_default().to_string(),
+                    cells: vec![],
+                };
+
+                // Infinite maps store the tiles in chunks
+                let chunks: Vec<(&XmlNode, i32, i32, i32)> = if data.child("chunk").is_some() {
+                    data.children_named("chunk")
+                        .map(|c| {
+                            (
+                                c,
+                                c.attr_i32("x", 0),
+                                c.attr_i32("y", 0),
+                                c.attr_i32("width", 16),
+                            )
+                        })
+                        .collect()
+                } else {
+                    vec![(data, 0, 0, child.attr_i32("width", 0))]
+                };
+
+                for (chunk, x0, y0, width) in chunks {
+                    let gids = decode_gids(chunk, data.attr("encoding"), data.attr("compression"))?;
+                    for (index, gid) in gids.into_iter().enumerate() {
+                        let gid = gid & GID_MASK;
+                        if gid == 0 || width <= 0 {
+                            continue;
+                        }
+                        let Some((first_gid, tileset)) =
+                            first_gids.iter().find(|(first, _)| *first <= gid)
+                        else {
+                            continue;
+                        };
+                        layer.cells.push(ImportedCell {
+                            x: x0 + index as i32 % width + cell_offset.x,
+                            y: y0 + index as i32 / width + cell_offset.y,
+                            tileset: *tileset,
+                            tile: gid - first_gid,
+                        });
+                    }
+                }
+                level.layers.push(layer);
+            }
+            "objectgroup" => {
+                for object in child.children_named("object") {
+                    let x = object.attr_f32("x", 0.0);
+                    let y = object.attr_f32("y", 0.0);
+                    let w = object.attr_f32("width", 0.0);
+                    let h = object.attr_f32("height", 0.0);
+                    // Tile objects are anchored at the bottom left
+                    let center = if object.attr("gid").is_some() {
+                        Vec2::new(x + w * 0.5, y - h * 0.5)
+                    } else {
+                        Vec2::new(x + w * 0.5, y + h * 0.5)
+                    } + offset;
+
+                    level.objects.push(ImportedObject {
+                        name: object.attr("name").unwrap_or_default().to_string(),
+                        class: object
+                            .attr("class")
+                            .or(object.attr("type"))
+                            .unwrap_or_default()
+                            .to_string(),
+                        x: center.x / tile_size.x,
+                        y: center.y / tile_size.y,
+                        properties: read_properties(object),
+                    });
+                }
+            }
+            "imagelayer" => warnings.push(format!(
+                "Image layer '{}' was skipped.",
+                child.attr("name").unwrap_or_default()
+            )),
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+/// Decodes the gids of a layer or chunk.
+fn decode_gids(
+    data: &XmlNode,
+    encoding: Option<&str>,
+    compression: Option<&str>,
+) -> Result<Vec<u32>, String> {
+    match encoding {
+        Some("csv") => Ok(data
+            .text
+            .split(',')
+            .filter_map(|v| v.trim().parse::<u32>().ok())
+            .collect()),
+        Some("base64") => {
+            let raw = STANDARD
+                .decode(data.text.trim())
+                .map_err(|e| format!("Invalid base64 layer data: {}", e))?;
+            let bytes = match compression {
+                None | Some("") => raw,
+                Some("zlib") => {
+                    let mut bytes = vec![];
+                    flate2::read::ZlibDecoder::new(&raw[..])
+                        .read_to_end(&mut bytes)
+                        .map_err(|e| e.to_string())?;
+                    bytes
+                }
+                Some("gzip") => {
+                    let mut bytes = vec![];
+                    flate2::read::GzDecoder::new(&raw[..])
+                        .read_to_end(&mut bytes)
+                        .map_err(|e| e.to_string())?;
+                    bytes
+                }
+                Some(other) => {
+                    return Err(format!(
+                        "The layer compression '{}' is not supported, use CSV, zlib or gzip.",
+                        other
+                    ));
+                }
+            };
+            Ok(bytes
+                .chunks_exact(4)
+                .map(|b| u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+                .collect())
+        }
+        _ => Ok(data
+            .children_named("tile")
+            .map(|t| t.attr_i32("gid", 0) as u32)
+            .collect()),
+    }
+}
+
+/// Reads the custom properties of an element into a TOML table.
+fn read_properties(node: &XmlNode) -> toml::Table {
+    let mut table = toml::Table::new();
+    let Some(properties) = node.child("properties") else {
+        return table;
+    };
+    for property in properties.children_named("property") {
+        let Some(name) = property.attr("name") else {
+            continue;
+        };
+        let text = property.attr("value").unwrap_or(property.text.as_str());
+        let value = match property.attr("type").unwrap_or("string") {
+            "int" | "object" => text
+                .parse::<i64>()
+                .map(toml::Value::Integer)
+                .unwrap_or(toml::Value::Integer(0)),
+            "float" => text
+                .parse::<f64>()
+                .map(toml::Value::Float)
+                .unwrap_or(toml::Value::Float(0.0)),
+            "bool" => toml::Value::Boolean(text == "true"),
+            "class" => toml::Value::Table(read_properties(property)),
+            _ => toml::Value::String(text.to_string()),
+        };
+        table.insert(name.to_string(), value);
+    }
+    table
+}
+
+/// A region exported as a Tiled map with its tileset.
+pub struct TiledExport {
+    pub tmx: String,
+    pub tsx: String,
+    /// The tileset image, referenced as `<name>.png` by the tileset.
+    pub image: TheRGBABuffer,
+    /// The number of sectors which are not grid aligned rectangles.
+    pub skipped: usize,
+}
+
+/// Exports the tiled rectangles of a 2D grid region as an orthogonal Tiled
+/// map. Every sector layer becomes a tile layer, character and item
+/// instances and named vertices become point objects. `name` is the file
+/// name of the map without extension, the tileset files use the same name.
+pub fn export_tiled(region: &Region, project: &Project, name: &str) -> TiledExport {
+    let map = &region.map;
+
+    // The cells of all sectors with a tile source which are grid aligned rectangles
+    let mut cells: Vec<(u8, i32, i32, Uuid)> = vec![];
+    let mut skipped = 0;
+    for sector in &map.sectors {
+        let Some(Value::Source(PixelSource::TileId(tile_id))) = sector.properties.get("source")
+        else {
+            continue;
+        };
+        let points: Vec<Vec2<f32>> = sector
+            .linedefs
+            .iter()
+            .filter_map(|id| map.find_linedef(*id))
+            .filter_map(|l| map.find_vertex(l.start_vertex))
+            .map(|v| v.as_vec2())
+            .collect();
+        if points.is_empty() {
+            continue;
+        }
+        let min = points
+            .iter()
+            .fold(Vec2::broadcast(f32::MAX), |a, p| a.map2(*p, f32::min));
+        let max = points
+            .iter()
+            .fold(Vec2::broadcast(f32::MIN), |a, p| a.map2(*p, f32::max));
+        let aligned = |v: f32| (v - v.round()).abs() < 0.001;
+        let rectangle = points.iter().all(|p| {
+            ((p.x - min.x).abs() < 0.001 || (p.x - max.x).abs() < 0.001)
+                && ((p.y - min.y).abs() < 0.001 || (p.y - max.y).abs() < 0.001)
+        });
+        if !rectangle || !aligned(min.x) || !aligned(min.y) || !aligned(max.x) || !aligned(max.y) {
+            skipped += 1;
+            continue;
+        }
+        let layer = sector.layer.unwrap_or(0);
+        for y in min.y.round() as i32..max.y.round() as i32 {
+            for x in min.x.round() as i32..max.x.round() as i32 {
+                cells.push((layer, x, y, *tile_id));
+            }
+        }
+    }
+
+    // The named vertices and instances, all positions in map units
+    let mut objects: Vec<(String, String, Vec2<f32>, String)> = vec![];
+    for character in region.characters.values() {
+        let class = project
+            .characters
+            .get(&character.character_id)
+            .map(|c| c.name.clone())
+            .unwrap_or_default();
+        objects.push((
+            character.name.clone(),
+            class,
+            Vec2::new(character.position.x, character.position.z),
+            character.data.clone(),
+        ));
+    }
+    for item in region.items.values() {
+        let class = project
+            .items
+            .get(&item.item_id)
+            .map(|i| i.name.clone())
+            .unwrap_or_default();
+        objects.push((
+            item.name.clone(),
+            class,
+            Vec2::new(item.position.x, item.position.z),
+            item.data.clone(),
+        ));
+    }
+    let mut vertex_objects = vec![];
+    for vertex in map.vertices.iter().filter(|v| !v.name.is_empty()) {
+        let mut properties = toml::Table::new();
+        for key in vertex.properties.keys_sorted() {
+            if let Some(value) = vertex.properties.get(key).and_then(value_to_toml) {
+                properties.insert(key.to_string(), value);
+            }
+        }
+        vertex_objects.push((
+            vertex.name.clone(),
+            Vec2::new(vertex.x, vertex.y),
+            properties,
+        ));
+    }
+
+    // The map bounds, the top left cell becomes the origin
+    let mut min = Vec2::new(i32::MAX, i32::MAX);
+    let mut max = Vec2::new(i32::MIN, i32::MIN);
+    for (_, x, y, _) in &cells {
+        min = min.map2(Vec2::new(*x, *y), i32::min);
+        max = max.map2(Vec2::new(*x + 1, *y + 1), i32::max);
+    }
+    let positions = objects
+        .iter()
+        .map(|o| o.2)
+        .chain(vertex_objects.iter().map(|v| v.1));
+    for p in positions {
+        min = min.map2(Vec2::new(p.x.floor() as i32, p.y.floor() as i32), i32::min);
+        max = max.map2(
+            Vec2::new(p.x.floor() as i32 + 1, p.y.floor() as i32 + 1),
+            i32::max,
+        );
+    }
+    if min.x > max.x {
+        min = Vec2::zero();
+        max = Vec2::one();
+    }
+    let width = max.x - min.x;
+    let height = max.y - min.y;
+
+    // The tileset, one tile per animation frame
+    let mut tile_order: Vec<Uuid> = vec![];
+    for (_, _, _, id) in &cells {
+        if !tile_order.contains(id) {
+            tile_order.push(*id);
+        }
+    }
+    let mut tile_size = 0;
+    for id in &tile_order {
+        if let Some(texture) = project.tiles.get(id).and_then(|t| t.textures.first()) {
+            tile_size = tile_size.max(texture.width.max(texture.height));
+        }
+    }
+    let tile_size = if tile_size == 0 { 16 } else { tile_size };
+
+    let mut first_frame: FxHashMap<Uuid, usize> = FxHashMap::default();
+    let mut frames: Vec<&rusterix::Texture> = vec![];
+    let mut tile_xml = String::new();
+    for id in &tile_order {
+        let Some(tile) = project.tiles.get(id) else {
+            continue;
+        };
+        let first = frames.len();
+        first_frame.insert(*id, first);
+        frames.extend(tile.textures.iter());
+
+        let mut properties = String::new();
+        if !tile.tags.is_empty() {
+            properties += &format!(
+                "   <property name=\"tags\" value=\"{}\"/>\n",
+                xml_escape(&tile.tags)
+            );
+        }
+        if tile.blocking {
+            properties += "   <property name=\"blocking\" type=\"bool\" value=\"true\"/>\n";
+        }
+        let mut animation = String::new();
+        if tile.textures.len() > 1 {
+            animation += "  <animation>\n";
+            for i in 0..tile.textures.len() {
+                animation += &format!("   <frame tileid=\"{}\" duration=\"250\"/>\n", first + i);
+            }
+            animation += "  </animation>\n";
+        }
+        if !properties.is_empty() || !animation.is_empty() {
+            tile_xml += &format!(" <tile id=\"{}\">\n", first);
+            if !properties.is_empty() {
+                tile_xml += &format!("  <properties>\n{}  </properties>\n", properties);
+            }
+            tile_xml += &animation;
+            tile_xml += " </tile>\n";
+        }
+    }
+
+    let count = frames.len().max(1);
+    let columns = (count as f32).sqrt().ceil() as usize;
+    let rows = count.div_ceil(columns);
+    let image_width = columns * tile_size;
+    let image_height = rows * tile_size;
+    let mut pixels = vec![0_u8; image_width * image_height * 4];
+    for (index, texture) in frames.iter().enumerate() {
+        let ox = (index % columns) * tile_size;
+        let oy = (index / columns) * tile_size;
+        for y in 0..tile_size {
+            for x in 0..tile_size {
+                // Nearest neighbor scaling of smaller tiles
+                let sx = x * texture.width / tile_size;
+                let sy = y * texture.height / tile_size;
+                let s = (sy * texture.width + sx) * 4;
+                let d = ((oy + y) * image_width + ox + x) * 4;
+                if s + 4 <= texture.data.len() {
+                    pixels[d..d + 4].copy_from_slice(&texture.data[s..s + 4]);
+                }
+            }
+        }
+    }
+    let image = TheRGBABuffer::from(pixels, image_width as u32, image_height as u32);
+
+    let tsx = format!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<tileset version=\"1.10\" name=\"{name}\" tilewidth=\"{tile_size}\" tileheight=\"{tile_size}\" tilecount=\"{}\" columns=\"{columns}\">\n <image source=\"{name}.png\" width=\"{image_width}\" height=\"{image_height}\"/>\n{tile_xml}</tileset>\n",
+        frames.len(),
+        name = xml_escape(name),
+    );
+
+    // The tile layers, sorted by the sector layer
+    let mut layers: Vec<u8> = cells.iter().map(|c| c.0).collect();
+    layers.sort_unstable();
+    layers.dedup();
+
+    let mut next_id = 1;
+    let mut layer_xml = String::new();
+    for layer in &layers {
+        let mut gids = vec![0_usize; (width * height) as usize];
+        for (_, x, y, id) in cells.iter().filter(|c| c.0 == *layer) {
+            if let Some(first) = first_frame.get(id) {
+                gids[((y - min.y) * width + x - min.x) as usize] = first + 1;
+            }
+        }
+        let rows: Vec<String> = gids
+            .chunks(width as usize)
+            .map(|row| {
+                row.iter()
+                    .map(|g| g.to_string())
+                    .collect::<Vec<_>>()
+                    .join(",")
+            })
+            .collect();
+        layer_xml += &format!(
+            " <layer id=\"{next_id}\" name=\"Layer {layer}\" width=\"{width}\" height=\"{height}\">\n  <data encoding=\"csv\">\n{}\n  </data>\n </layer>\n",
+            rows.join(",\n")
+        );
+        next_id += 1;
+    }
+
+    let mut object_xml = String::new();
+    let mut object_id = 1;
+    let to_pixels = |p: Vec2<f32>| {
+        (
+            (p.x - min.x as f32) * tile_size as f32,
+            (p.y - min.y as f32) * tile_size as f32,
+        )
+    };
+    for (object_name, class, position, data) in &objects {
+        let (x, y) = to_pixels(*position);
+        let properties = data
+            .parse::<toml::Table>()
+            .ok()
+            .and_then(|t| t.get("attributes").and_then(|a| a.as_table()).cloned())
+            .unwrap_or_default();
+        object_xml += &format!(
+            "  <object id=\"{object_id}\" name=\"{}\" type=\"{}\" x=\"{x}\" y=\"{y}\">\n{}   <point/>\n  </object>\n",
+            xml_escape(object_name),
+            xml_escape(class),
+            write_properties(&properties, "   "),
+        );
+        object_id += 1;
+    }
+    for (vertex_name, position, properties) in &vertex_objects {
+        let (x, y) = to_pixels(*position);
+        object_xml += &format!(
+            "  <object id=\"{object_id}\" name=\"{}\" x=\"{x}\" y=\"{y}\">\n{}   <point/>\n  </object>\n",
+            xml_escape(vertex_name),
+            write_properties(properties, "   "),
+        );
+        object_id += 1;
+    }
+    if !object_xml.is_empty() {
+        layer_xml += &format!(
+            " <objectgroup id=\"{next_id}\" name=\"Objects\">\n{object_xml} </objectgroup>\n"
+        );
+        next_id += 1;
+    }
+
+    let map_properties = region
+        .config
+        .parse::<toml::Table>()
+        .ok()
+        .and_then(|t| t.get("properties").and_then(|p| p.as_table()).cloned())
+        .unwrap_or_default();
+
+    let tmx = format!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<map version=\"1.10\" orientation=\"orthogonal\" renderorder=\"right-down\" width=\"{width}\" height=\"{height}\" tilewidth=\"{tile_size}\" tileheight=\"{tile_size}\" infinite=\"0\" nextlayerid=\"{next_id}\" nextobjectid=\"{object_id}\">\n{} <tileset firstgid=\"1\" source=\"{}.tsx\"/>\n{layer_xml}</map>\n",
+        write_properties(&map_properties, " "),
+        xml_escape(name),
+    );
+
+    TiledExport {
+        tmx,
+        tsx,
+        image,
+        skipped,
+    }
+}
+
+/// Writes a TOML table as Tiled custom properties.
+fn write_properties(properties: &toml::Table, indent: &str) -> String {
+    if properties.is_empty() {
+        return String::new();
+    }
+    let mut xml = format!("{indent}<properties>\n");
+    for (key, value) in properties {
+        let key = xml_escape(key);
+        xml += &match value {
+            toml::Value::Integer(v) => {
+                format!("{indent} <property name=\"{key}\" type=\"int\" value=\"{v}\"/>\n")
+            }
+            toml::Value::Float(v) => {
+                format!("{indent} <property name=\"{key}\" type=\"float\" value=\"{v}\"/>\n")
+            }
+            toml::Value::Boolean(v) => {
+                format!("{indent} <property name=\"{key}\" type=\"bool\" value=\"{v}\"/>\n")
+            }
+            toml::Value::String(v) => {
+                format!(
+                    "{indent} <property name=\"{key}\" value=\"{}\"/>\n",
+                    xml_escape(v)
+                )
+            }
+            toml::Value::Table(t) => format!(
+                "{indent} <property name=\"{key}\" type=\"class\">\n{}{indent} </property>\n",
+                write_properties(t, &format!("{indent}  "))
+            ),
+            other => format!(
+                "{indent} <property name=\"{key}\" value=\"{}\"/>\n",
+                xml_escape(&other.to_string())
+            ),
+        };
+    }
+    xml + &format!("{indent}</properties>\n")
+}
+
+pub(crate) fn xml_escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}